@@ -0,0 +1,650 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
+use rocketmq_remoting::acl::acl_signer;
+use rocketmq_remoting::acl::acl_signer::ACCESS_KEY;
+use rocketmq_remoting::acl::acl_signer::SIGNATURE;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::body::batch_ack_message_request_body::BatchAckMessageRequestBody;
+use rocketmq_remoting::protocol::body::request::lock_batch_request_body::LockBatchRequestBody;
+use rocketmq_remoting::protocol::body::unlock_batch_request_body::UnlockBatchRequestBody;
+use rocketmq_remoting::protocol::heartbeat::heartbeat_data::HeartbeatData;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::remoting_error::RemotingError;
+use rocketmq_remoting::runtime::RPCHook;
+
+use crate::acl::plain_access_validator::AccessResource;
+use crate::acl::plain_access_validator::Permission;
+use crate::acl::plain_access_validator::PlainAccessValidator;
+
+/// Rejects the requests the ACL doesn't permit with `NoPermission` before they are dispatched
+/// to the broker processors.
+pub(crate) struct AclRpcHook {
+    validator: Arc<PlainAccessValidator>,
+}
+
+impl AclRpcHook {
+    pub fn new(validator: Arc<PlainAccessValidator>) -> Self {
+        Self { validator }
+    }
+}
+
+impl RPCHook for AclRpcHook {
+    fn do_before_request(
+        &self,
+        remote_addr: SocketAddr,
+        request: &mut RemotingCommand,
+    ) -> rocketmq_remoting::Result<()> {
+        let resource = parse_access_resource(remote_addr, request);
+        self.validator.validate(&resource).map_err(|message| {
+            RemotingError::AbortProcessError(ResponseCode::NoPermission as i32, message)
+        })
+    }
+
+    fn do_after_response(
+        &self,
+        _remote_addr: SocketAddr,
+        _response: &mut RemotingCommand,
+    ) -> rocketmq_remoting::Result<()> {
+        Ok(())
+    }
+}
+
+fn ext_field(request: &RemotingCommand, name: &str) -> Option<CheetahString> {
+    request
+        .ext_fields()
+        .and_then(|fields| fields.get(name))
+        .cloned()
+}
+
+/// Collects the topics and groups a request touches, along with the permission it needs on
+/// each of them. Every request code is listed, the ones not granted to plain accounts need an
+/// admin account, so that a code added later is denied until it is listed here.
+fn parse_access_resource(remote_addr: SocketAddr, request: &RemotingCommand) -> AccessResource {
+    let signature = ext_field(request, SIGNATURE);
+    let content = match signature {
+        Some(_) => acl_signer::combine_request_content(
+            request.ext_fields(),
+            request.body().as_ref().map(|body| body.as_ref()),
+        ),
+        None => Vec::new(),
+    };
+    let mut resource = AccessResource {
+        access_key: ext_field(request, ACCESS_KEY),
+        signature,
+        content,
+        remote_addr: Some(remote_addr),
+        ..AccessResource::default()
+    };
+    let add_topic = |resource: &mut AccessResource, name: &str, perm: u8| {
+        if let Some(topic) = ext_field(request, name) {
+            resource.topics.push((topic, perm));
+        }
+    };
+    let add_group = |resource: &mut AccessResource, name: &str, perm: u8| {
+        if let Some(group) = ext_field(request, name) {
+            resource.groups.push((group, perm));
+        }
+    };
+    match RequestCode::from(request.code()) {
+        RequestCode::SendMessage | RequestCode::SendReplyMessage | RequestCode::EndTransaction => {
+            add_topic(&mut resource, "topic", Permission::PUB)
+        }
+        // the compact header of V2 names the topic `b`
+        RequestCode::SendMessageV2
+        | RequestCode::SendBatchMessage
+        | RequestCode::SendReplyMessageV2 => {
+            add_topic(&mut resource, "b", Permission::PUB);
+            add_topic(&mut resource, "topic", Permission::PUB);
+        }
+        RequestCode::ConsumerSendMsgBack | RequestCode::CheckClientConfig => {
+            add_group(&mut resource, "group", Permission::SUB)
+        }
+        RequestCode::PullMessage
+        | RequestCode::LitePullMessage
+        | RequestCode::PopMessage
+        | RequestCode::PeekMessage
+        | RequestCode::AckMessage
+        | RequestCode::ChangeMessageInvisibleTime
+        | RequestCode::EndTwoPhaseAck
+        | RequestCode::Notification
+        | RequestCode::PollingInfo
+        | RequestCode::QueryPopInflightMessageNum
+        | RequestCode::QueryConsumerOffset
+        | RequestCode::UpdateConsumerOffset => {
+            add_topic(&mut resource, "topic", Permission::SUB);
+            add_group(&mut resource, "consumerGroup", Permission::SUB);
+        }
+        RequestCode::QueryMessage
+        | RequestCode::ViewMessageById
+        | RequestCode::GetMaxOffset
+        | RequestCode::GetMinOffset
+        | RequestCode::GetEarliestMsgStoreTime
+        | RequestCode::SearchOffsetByTimestamp => {
+            add_topic(&mut resource, "topic", Permission::SUB)
+        }
+        RequestCode::UnregisterClient | RequestCode::GetConsumerListByGroup => {
+            add_group(&mut resource, "consumerGroup", Permission::SUB)
+        }
+        RequestCode::BatchAckMessage => match decode_body::<BatchAckMessageRequestBody>(request) {
+            Some(body) => {
+                for ack in body.acks {
+                    resource.topics.push((ack.topic, Permission::SUB));
+                    resource.groups.push((ack.consumer_group, Permission::SUB));
+                }
+            }
+            None => resource.admin_required = true,
+        },
+        RequestCode::LockBatchMq => match decode_body::<LockBatchRequestBody>(request) {
+            Some(body) => add_queues(&mut resource, body.consumer_group, &body.mq_set),
+            None => resource.admin_required = true,
+        },
+        RequestCode::UnlockBatchMq => match decode_body::<UnlockBatchRequestBody>(request) {
+            Some(body) => add_queues(&mut resource, body.consumer_group, &body.mq_set),
+            None => resource.admin_required = true,
+        },
+        RequestCode::HeartBeat => {
+            let heartbeat_data = decode_body::<HeartbeatData>(request);
+            for consumer_data in heartbeat_data
+                .iter()
+                .flat_map(|data| &data.consumer_data_set)
+            {
+                resource
+                    .groups
+                    .push((consumer_data.group_name.clone(), Permission::SUB));
+                for subscription_data in &consumer_data.subscription_data_set {
+                    resource
+                        .topics
+                        .push((subscription_data.topic.clone(), Permission::SUB));
+                }
+            }
+        }
+        RequestCode::QueryBrokerOffset
+        | RequestCode::UpdateAndCreateTopic
+        | RequestCode::UpdateAndCreateTopicList
+        | RequestCode::GetAllTopicConfig
+        | RequestCode::GetTopicConfigList
+        | RequestCode::GetTopicNameList
+        | RequestCode::UpdateBrokerConfig
+        | RequestCode::GetBrokerConfig
+        | RequestCode::TriggerDeleteFiles
+        | RequestCode::GetBrokerRuntimeInfo
+        | RequestCode::CheckTransactionState
+        | RequestCode::NotifyConsumerIdsChanged
+        | RequestCode::GetAllConsumerOffset
+        | RequestCode::GetAllDelayOffset
+        | RequestCode::GetClientConfig
+        | RequestCode::UpdateAndCreateAclConfig
+        | RequestCode::DeleteAclConfig
+        | RequestCode::GetBrokerClusterAclInfo
+        | RequestCode::UpdateGlobalWhiteAddrsConfig
+        | RequestCode::GetBrokerClusterAclConfig
+        | RequestCode::GetTimerCheckPoint
+        | RequestCode::GetTimerMetrics
+        | RequestCode::PutKvConfig
+        | RequestCode::GetKvConfig
+        | RequestCode::DeleteKvConfig
+        | RequestCode::RegisterBroker
+        | RequestCode::UnregisterBroker
+        | RequestCode::GetRouteinfoByTopic
+        | RequestCode::GetBrokerClusterInfo
+        | RequestCode::UpdateAndCreateSubscriptionGroup
+        | RequestCode::GetAllSubscriptionGroupConfig
+        | RequestCode::GetTopicStatsInfo
+        | RequestCode::GetConsumerConnectionList
+        | RequestCode::GetProducerConnectionList
+        | RequestCode::WipeWritePermOfBroker
+        | RequestCode::GetAllTopicListFromNameserver
+        | RequestCode::DeleteSubscriptionGroup
+        | RequestCode::GetConsumeStats
+        | RequestCode::SuspendConsumer
+        | RequestCode::ResumeConsumer
+        | RequestCode::ResetConsumerOffsetInConsumer
+        | RequestCode::ResetConsumerOffsetInBroker
+        | RequestCode::AdjustConsumerThreadPool
+        | RequestCode::WhoConsumeTheMessage
+        | RequestCode::DeleteTopicInBroker
+        | RequestCode::DeleteTopicInNamesrv
+        | RequestCode::RegisterTopicInNamesrv
+        | RequestCode::GetKvlistByNamespace
+        | RequestCode::ResetConsumerClientOffset
+        | RequestCode::GetConsumerStatusFromClient
+        | RequestCode::InvokeBrokerToResetOffset
+        | RequestCode::InvokeBrokerToGetConsumerStatus
+        | RequestCode::QueryTopicConsumeByWho
+        | RequestCode::GetTopicsByCluster
+        | RequestCode::QueryTopicsByConsumer
+        | RequestCode::QuerySubscriptionByConsumer
+        | RequestCode::RegisterFilterServer
+        | RequestCode::RegisterMessageFilterClass
+        | RequestCode::QueryConsumeTimeSpan
+        | RequestCode::GetSystemTopicListFromNs
+        | RequestCode::GetSystemTopicListFromBroker
+        | RequestCode::CleanExpiredConsumequeue
+        | RequestCode::GetConsumerRunningInfo
+        | RequestCode::QueryCorrectionOffset
+        | RequestCode::ConsumeMessageDirectly
+        | RequestCode::GetUnitTopicList
+        | RequestCode::GetHasUnitSubTopicList
+        | RequestCode::GetHasUnitSubUnunitTopicList
+        | RequestCode::CloneGroupOffset
+        | RequestCode::ViewBrokerStatsData
+        | RequestCode::CleanUnusedTopic
+        | RequestCode::GetBrokerConsumeStats
+        | RequestCode::UpdateNamesrvConfig
+        | RequestCode::GetNamesrvConfig
+        | RequestCode::QueryConsumeQueue
+        | RequestCode::QueryDataVersion
+        | RequestCode::ResumeCheckHalfMessage
+        | RequestCode::PushReplyMessageToClient
+        | RequestCode::AddWritePermOfBroker
+        | RequestCode::GetTopicConfig
+        | RequestCode::GetSubscriptionGroupConfig
+        | RequestCode::UpdateAndGetGroupForbidden
+        | RequestCode::UpdateAckInvisibleTimeCap
+        | RequestCode::ResetGroupOffsetAllQueues
+        | RequestCode::UpdateAckProcessingSwitch
+        | RequestCode::QueryReviveQueueAcks
+        | RequestCode::QueryReviveLag
+        | RequestCode::QueryConsumerLag
+        | RequestCode::TrimTopicBeforeOffset
+        | RequestCode::QueryAssignment
+        | RequestCode::SetMessageRequestMode
+        | RequestCode::GetAllMessageRequestMode
+        | RequestCode::UpdateAndCreateStaticTopic
+        | RequestCode::GetBrokerMemberGroup
+        | RequestCode::AddBroker
+        | RequestCode::RemoveBroker
+        | RequestCode::BrokerHeartbeat
+        | RequestCode::NotifyMinBrokerIdChange
+        | RequestCode::ExchangeBrokerHaInfo
+        | RequestCode::GetBrokerHaStatus
+        | RequestCode::ResetMasterFlushOffset
+        | RequestCode::GetAllProducerInfo
+        | RequestCode::DeleteExpiredCommitlog
+        | RequestCode::UpdateColdDataFlowCtrConfig
+        | RequestCode::RemoveColdDataFlowCtrConfig
+        | RequestCode::GetColdDataFlowCtrInfo
+        | RequestCode::SetCommitlogReadMode
+        | RequestCode::Unknown => resource.admin_required = true,
+    }
+    resource
+}
+
+fn decode_body<T: serde::de::DeserializeOwned>(request: &RemotingCommand) -> Option<T> {
+    request
+        .body()
+        .as_ref()
+        .and_then(|body| SerdeJsonUtils::decode::<T>(body.as_ref()).ok())
+}
+
+/// Adds the queues a lock or unlock request takes for `consumer_group`, a body without a group
+/// needs an admin account.
+fn add_queues(
+    resource: &mut AccessResource,
+    consumer_group: Option<CheetahString>,
+    mq_set: &HashSet<MessageQueue>,
+) {
+    let Some(consumer_group) = consumer_group else {
+        resource.admin_required = true;
+        return;
+    };
+    resource.groups.push((consumer_group, Permission::SUB));
+    for mq in mq_set {
+        resource
+            .topics
+            .push((mq.get_topic_cs().clone(), Permission::SUB));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use bitvec::prelude::BitVec;
+    use bitvec::prelude::Lsb0;
+    use bytes::Bytes;
+    use rocketmq_remoting::acl::acl_client_rpc_hook::AclClientRpcHook;
+    use rocketmq_remoting::protocol::body::batch_ack::BatchAck;
+    use rocketmq_remoting::protocol::body::batch_ack::SerializableBitVec;
+    use rocketmq_remoting::protocol::RemotingSerializable;
+
+    use super::*;
+
+    const ACL: &str = r#"{"accounts":[
+        {"accessKey":"producer","secretKey":"producer-secret","topicPerms":["orders=PUB"]},
+        {"accessKey":"consumer","secretKey":"consumer-secret","defaultTopicPerm":"SUB",
+         "topicPerms":["secret_*=DENY"],"groupPerms":["billing_*=SUB"]},
+        {"accessKey":"admin","secretKey":"admin-secret","admin":true}]}"#;
+
+    fn acl_hook() -> (AclRpcHook, PathBuf) {
+        let path = std::env::temp_dir().join(format!(
+            "rocketmq-acl-hook-{}-{}.json",
+            std::process::id(),
+            rand::random::<u32>()
+        ));
+        std::fs::write(&path, ACL).unwrap();
+        let validator = PlainAccessValidator::new(&path);
+        assert!(validator.load());
+        (AclRpcHook::new(Arc::new(validator)), path)
+    }
+
+    fn request(
+        code: RequestCode,
+        credentials: Option<(&str, &str)>,
+        fields: &[(&str, &str)],
+    ) -> RemotingCommand {
+        request_with_body(code, credentials, fields, None)
+    }
+
+    /// Builds a request signed the way a client does when it has `credentials`.
+    fn request_with_body(
+        code: RequestCode,
+        credentials: Option<(&str, &str)>,
+        fields: &[(&str, &str)],
+        body: Option<Bytes>,
+    ) -> RemotingCommand {
+        let mut request = RemotingCommand::create_remoting_command(code);
+        for (key, value) in fields {
+            request.add_ext_field(*key, *value);
+        }
+        if let Some(body) = body {
+            request = request.set_body(body);
+        }
+        if let Some((access_key, secret_key)) = credentials {
+            AclClientRpcHook::new(access_key, secret_key)
+                .do_before_request("127.0.0.1:10000".parse().unwrap(), &mut request)
+                .unwrap();
+        }
+        request
+    }
+
+    fn batch_ack_body(consumer_group: &str, topic: &str) -> Bytes {
+        BatchAckMessageRequestBody {
+            broker_name: CheetahString::from_static_str("broker-a"),
+            acks: vec![BatchAck {
+                consumer_group: CheetahString::from_string(consumer_group.to_string()),
+                topic: CheetahString::from_string(topic.to_string()),
+                retry: CheetahString::from_static_str("0"),
+                start_offset: 10,
+                queue_id: 0,
+                revive_queue_id: 0,
+                pop_time: 0,
+                invisible_time: 30_000,
+                bit_set: SerializableBitVec(BitVec::<u64, Lsb0>::repeat(true, 64)),
+            }],
+        }
+        .encode()
+        .unwrap()
+        .into()
+    }
+
+    fn is_permitted(hook: &AclRpcHook, mut request: RemotingCommand) -> bool {
+        let remote_addr = "127.0.0.1:10911".parse().unwrap();
+        match hook.do_before_request(remote_addr, &mut request) {
+            Ok(()) => true,
+            Err(RemotingError::AbortProcessError(code, _)) => {
+                assert_eq!(code, ResponseCode::NoPermission as i32);
+                false
+            }
+            Err(err) => panic!("unexpected error {}", err),
+        }
+    }
+
+    #[test]
+    fn publish_is_checked_against_topic_permissions() {
+        let (hook, path) = acl_hook();
+        let producer = Some(("producer", "producer-secret"));
+
+        assert!(is_permitted(
+            &hook,
+            request(RequestCode::SendMessage, producer, &[("topic", "orders")])
+        ));
+        assert!(is_permitted(
+            &hook,
+            request(RequestCode::SendMessageV2, producer, &[("b", "orders")])
+        ));
+        assert!(!is_permitted(
+            &hook,
+            request(RequestCode::SendMessage, producer, &[("topic", "payments")])
+        ));
+        assert!(!is_permitted(
+            &hook,
+            request(
+                RequestCode::SendMessage,
+                Some(("consumer", "consumer-secret")),
+                &[("topic", "orders")]
+            )
+        ));
+        assert!(!is_permitted(
+            &hook,
+            request(RequestCode::SendMessage, None, &[("topic", "orders")])
+        ));
+        assert!(!is_permitted(
+            &hook,
+            request(
+                RequestCode::SendMessage,
+                Some(("producer", "guess")),
+                &[("topic", "orders")]
+            )
+        ));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn subscribe_is_checked_against_topic_and_group_permissions() {
+        let (hook, path) = acl_hook();
+        let consumer = Some(("consumer", "consumer-secret"));
+
+        assert!(is_permitted(
+            &hook,
+            request(
+                RequestCode::PullMessage,
+                consumer,
+                &[("topic", "orders"), ("consumerGroup", "billing_orders")]
+            )
+        ));
+        assert!(is_permitted(
+            &hook,
+            request(
+                RequestCode::PullMessage,
+                consumer,
+                &[
+                    ("topic", "%RETRY%billing_orders"),
+                    ("consumerGroup", "billing_orders")
+                ]
+            )
+        ));
+        assert!(!is_permitted(
+            &hook,
+            request(
+                RequestCode::PullMessage,
+                consumer,
+                &[
+                    ("topic", "secret_orders"),
+                    ("consumerGroup", "billing_orders")
+                ]
+            )
+        ));
+        assert!(!is_permitted(
+            &hook,
+            request(
+                RequestCode::PopMessage,
+                consumer,
+                &[("topic", "orders"), ("consumerGroup", "audit")]
+            )
+        ));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn admin_requests_need_an_admin_account() {
+        let (hook, path) = acl_hook();
+
+        assert!(!is_permitted(
+            &hook,
+            request(
+                RequestCode::UpdateAndCreateTopic,
+                Some(("producer", "producer-secret")),
+                &[("topic", "orders")]
+            )
+        ));
+        assert!(is_permitted(
+            &hook,
+            request(
+                RequestCode::UpdateAndCreateTopic,
+                Some(("admin", "admin-secret")),
+                &[("topic", "orders")]
+            )
+        ));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn unsigned_and_tampered_requests_are_rejected() {
+        let (hook, path) = acl_hook();
+
+        // the secret key sent as is, as the requests did before they were signed
+        let mut plaintext = request(RequestCode::SendMessage, None, &[("topic", "orders")]);
+        plaintext.add_ext_field(ACCESS_KEY, "producer");
+        plaintext.add_ext_field("SecretKey", "producer-secret");
+        plaintext.add_ext_field(SIGNATURE, "producer-secret");
+        assert!(!is_permitted(&hook, plaintext));
+
+        let mut tampered = request(
+            RequestCode::SendMessage,
+            Some(("producer", "producer-secret")),
+            &[("topic", "orders")],
+        );
+        tampered.add_ext_field("queueId", "3");
+        assert!(!is_permitted(&hook, tampered));
+
+        let tampered_body = request_with_body(
+            RequestCode::SendMessage,
+            Some(("producer", "producer-secret")),
+            &[("topic", "orders")],
+            Some(Bytes::from_static(b"body")),
+        )
+        .set_body(Bytes::from_static(b"forged"));
+        assert!(!is_permitted(&hook, tampered_body));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn unlisted_and_pop_admin_codes_need_an_admin_account() {
+        let (hook, path) = acl_hook();
+        let consumer = Some(("consumer", "consumer-secret"));
+        let admin = Some(("admin", "admin-secret"));
+        let fields = [("topic", "orders"), ("consumerGroup", "billing_orders")];
+
+        for code in [
+            RequestCode::UpdateAckInvisibleTimeCap,
+            RequestCode::ResetGroupOffsetAllQueues,
+            RequestCode::UpdateAckProcessingSwitch,
+            RequestCode::QueryReviveQueueAcks,
+            RequestCode::QueryReviveLag,
+            RequestCode::QueryConsumerLag,
+            RequestCode::Unknown,
+        ] {
+            assert!(
+                !is_permitted(&hook, request(code, consumer, &fields)),
+                "{:?} must need an admin",
+                code
+            );
+            assert!(is_permitted(&hook, request(code, admin, &fields)));
+        }
+        let mut unknown = RemotingCommand::create_remoting_command(123_456);
+        AclClientRpcHook::new("consumer", "consumer-secret")
+            .do_before_request("127.0.0.1:10000".parse().unwrap(), &mut unknown)
+            .unwrap();
+        assert!(!is_permitted(&hook, unknown));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn acks_and_transactions_are_checked_against_their_resources() {
+        let (hook, path) = acl_hook();
+        let consumer = Some(("consumer", "consumer-secret"));
+        let producer = Some(("producer", "producer-secret"));
+
+        let batch_ack = |group: &str, topic: &str| {
+            request_with_body(
+                RequestCode::BatchAckMessage,
+                consumer,
+                &[],
+                Some(batch_ack_body(group, topic)),
+            )
+        };
+        assert!(is_permitted(&hook, batch_ack("billing_orders", "orders")));
+        assert!(!is_permitted(&hook, batch_ack("audit", "orders")));
+        assert!(!is_permitted(
+            &hook,
+            batch_ack("billing_orders", "secret_orders")
+        ));
+        assert!(!is_permitted(
+            &hook,
+            request_with_body(
+                RequestCode::BatchAckMessage,
+                consumer,
+                &[],
+                Some(Bytes::from_static(b"not json")),
+            )
+        ));
+
+        assert!(is_permitted(
+            &hook,
+            request(
+                RequestCode::EndTwoPhaseAck,
+                consumer,
+                &[("consumerGroup", "billing_orders")]
+            )
+        ));
+        assert!(!is_permitted(
+            &hook,
+            request(
+                RequestCode::EndTwoPhaseAck,
+                consumer,
+                &[("consumerGroup", "audit")]
+            )
+        ));
+
+        assert!(is_permitted(
+            &hook,
+            request(
+                RequestCode::EndTransaction,
+                producer,
+                &[("topic", "orders")]
+            )
+        ));
+        assert!(!is_permitted(
+            &hook,
+            request(
+                RequestCode::EndTransaction,
+                producer,
+                &[("topic", "payments")]
+            )
+        ));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,396 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+use rocketmq_common::common::base::plain_access_config::PlainAccessConfig;
+use rocketmq_common::common::mix_all::RETRY_GROUP_TOPIC_PREFIX;
+use rocketmq_remoting::acl::acl_signer;
+use serde::Deserialize;
+use tracing::info;
+use tracing::warn;
+
+/// Permission bits an account is granted on a topic or group, same as the plain ACL of the
+/// Java broker.
+pub(crate) struct Permission;
+
+impl Permission {
+    pub const DENY: u8 = 1;
+    pub const ANY: u8 = 1 << 1;
+    pub const PUB: u8 = 1 << 2;
+    pub const SUB: u8 = 1 << 3;
+
+    /// Parses `DENY`, `PUB`, `SUB` or `PUB|SUB`, anything else denies.
+    pub fn parse(perm: &str) -> u8 {
+        match perm.trim().to_uppercase().as_str() {
+            "PUB" => Self::PUB,
+            "SUB" => Self::SUB,
+            "PUB|SUB" | "SUB|PUB" => Self::PUB | Self::SUB,
+            _ => Self::DENY,
+        }
+    }
+
+    pub fn check(needed: u8, owned: u8) -> bool {
+        if owned & Self::DENY != 0 {
+            return false;
+        }
+        if needed & Self::ANY != 0 {
+            return owned & (Self::PUB | Self::SUB) != 0;
+        }
+        needed & owned != 0
+    }
+}
+
+/// What a request touches, parsed from its code, header and body.
+#[derive(Debug, Default)]
+pub(crate) struct AccessResource {
+    pub access_key: Option<CheetahString>,
+    pub signature: Option<CheetahString>,
+    /// What the signature is computed over, see `acl_signer::combine_request_content`.
+    pub content: Vec<u8>,
+    pub remote_addr: Option<SocketAddr>,
+    pub admin_required: bool,
+    pub topics: Vec<(CheetahString, u8)>,
+    pub groups: Vec<(CheetahString, u8)>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlainAclConfig {
+    #[serde(default)]
+    accounts: Vec<PlainAccessConfig>,
+}
+
+/// Resource permissions of an account, wildcard entries end with `*` and match any resource
+/// starting with what precedes it.
+#[derive(Debug, Default)]
+struct ResourcePerms {
+    exact: HashMap<CheetahString, u8>,
+    wildcards: Vec<(CheetahString, u8)>,
+    default_perm: u8,
+}
+
+impl ResourcePerms {
+    fn new(entries: &[CheetahString], default_perm: Option<&CheetahString>) -> Self {
+        let mut perms = ResourcePerms {
+            default_perm: default_perm.map_or(Permission::DENY, |perm| Permission::parse(perm)),
+            ..ResourcePerms::default()
+        };
+        for entry in entries {
+            let Some((resource, perm)) = entry.split_once('=') else {
+                warn!("ignore malformed acl entry {}", entry);
+                continue;
+            };
+            let resource = resource.trim();
+            let perm = Permission::parse(perm);
+            match resource.strip_suffix('*') {
+                Some(prefix) => perms.wildcards.push((prefix.into(), perm)),
+                None => {
+                    perms.exact.insert(resource.into(), perm);
+                }
+            }
+        }
+        // the most specific wildcard wins
+        perms
+            .wildcards
+            .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        perms
+    }
+
+    fn perm_of(&self, resource: &str) -> u8 {
+        if let Some(perm) = self.exact.get(resource) {
+            return *perm;
+        }
+        self.wildcards
+            .iter()
+            .find(|(prefix, _)| resource.starts_with(prefix.as_str()))
+            .map_or(self.default_perm, |(_, perm)| *perm)
+    }
+}
+
+#[derive(Debug)]
+struct AccessAccount {
+    secret_key: Option<CheetahString>,
+    white_remote_address: Option<CheetahString>,
+    admin: bool,
+    topic_perms: ResourcePerms,
+    group_perms: ResourcePerms,
+}
+
+impl From<&PlainAccessConfig> for AccessAccount {
+    fn from(config: &PlainAccessConfig) -> Self {
+        AccessAccount {
+            secret_key: config.secret_key.clone(),
+            white_remote_address: config.white_remote_address.clone(),
+            admin: config.admin,
+            topic_perms: ResourcePerms::new(
+                &config.topic_perms,
+                config.default_topic_perm.as_ref(),
+            ),
+            group_perms: ResourcePerms::new(
+                &config.group_perms,
+                config.default_group_perm.as_ref(),
+            ),
+        }
+    }
+}
+
+impl AccessAccount {
+    fn is_white_remote_address(&self, remote_addr: Option<SocketAddr>) -> bool {
+        let (Some(white), Some(remote_addr)) = (&self.white_remote_address, remote_addr) else {
+            return false;
+        };
+        let ip = remote_addr.ip().to_string();
+        match white.strip_suffix('*') {
+            Some(prefix) => ip.starts_with(prefix),
+            None => ip == white.as_str(),
+        }
+    }
+}
+
+/// Validates requests against the accounts of the plain ACL file, requests of unknown access
+/// keys and resources without a permission are denied.
+pub(crate) struct PlainAccessValidator {
+    acl_file_path: PathBuf,
+    accounts: RwLock<HashMap<CheetahString, AccessAccount>>,
+    last_modified: Mutex<Option<SystemTime>>,
+}
+
+impl PlainAccessValidator {
+    pub fn new(acl_file_path: impl Into<PathBuf>) -> Self {
+        PlainAccessValidator {
+            acl_file_path: acl_file_path.into(),
+            accounts: RwLock::new(HashMap::new()),
+            last_modified: Mutex::new(None),
+        }
+    }
+
+    /// Loads the accounts of the ACL file, the rules in place are kept if it can't be read.
+    pub fn load(&self) -> bool {
+        let modified = std::fs::metadata(&self.acl_file_path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let content = match std::fs::read_to_string(&self.acl_file_path) {
+            Ok(content) => content,
+            Err(err) => {
+                warn!(
+                    "load acl file {} failed: {}",
+                    self.acl_file_path.display(),
+                    err
+                );
+                return false;
+            }
+        };
+        let config = match serde_json::from_str::<PlainAclConfig>(&content) {
+            Ok(config) => config,
+            Err(err) => {
+                warn!(
+                    "parse acl file {} failed: {}",
+                    self.acl_file_path.display(),
+                    err
+                );
+                return false;
+            }
+        };
+        let mut accounts = HashMap::with_capacity(config.accounts.len());
+        for account in &config.accounts {
+            match &account.access_key {
+                Some(access_key) => {
+                    accounts.insert(access_key.clone(), AccessAccount::from(account));
+                }
+                None => warn!("ignore acl account without access key"),
+            }
+        }
+        info!(
+            "load {} acl accounts from {}",
+            accounts.len(),
+            self.acl_file_path.display()
+        );
+        *self.accounts.write() = accounts;
+        *self.last_modified.lock() = modified;
+        true
+    }
+
+    /// Reloads the ACL file if it was modified since it was last loaded.
+    pub fn reload_if_changed(&self) -> bool {
+        let Ok(modified) =
+            std::fs::metadata(&self.acl_file_path).and_then(|metadata| metadata.modified())
+        else {
+            return false;
+        };
+        if *self.last_modified.lock() == Some(modified) {
+            return false;
+        }
+        self.load()
+    }
+
+    pub fn validate(&self, resource: &AccessResource) -> Result<(), String> {
+        let Some(access_key) = &resource.access_key else {
+            return Err("no access key in the request".to_string());
+        };
+        let accounts = self.accounts.read();
+        let Some(account) = accounts.get(access_key) else {
+            return Err(format!("no acl config for access key {}", access_key));
+        };
+        if !account.is_white_remote_address(resource.remote_addr) {
+            let signed = match (&account.secret_key, &resource.signature) {
+                (Some(secret_key), Some(signature)) => {
+                    acl_signer::verify_signature(&resource.content, secret_key, signature)
+                }
+                _ => false,
+            };
+            if !signed {
+                return Err(format!("wrong signature for access key {}", access_key));
+            }
+        }
+        if account.admin {
+            return Ok(());
+        }
+        if resource.admin_required {
+            return Err(format!("access key {} is not an admin", access_key));
+        }
+        for (topic, needed) in &resource.topics {
+            // retry topics are consumed on behalf of their group
+            let allowed = match topic.strip_prefix(RETRY_GROUP_TOPIC_PREFIX) {
+                Some(group) => {
+                    Permission::check(Permission::SUB, account.group_perms.perm_of(group))
+                }
+                None => Permission::check(*needed, account.topic_perms.perm_of(topic)),
+            };
+            if !allowed {
+                return Err(format!(
+                    "access key {} has no permission on topic {}",
+                    access_key, topic
+                ));
+            }
+        }
+        for (group, needed) in &resource.groups {
+            if !Permission::check(*needed, account.group_perms.perm_of(group)) {
+                return Err(format!(
+                    "access key {} has no permission on group {}",
+                    access_key, group
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn write_acl_file(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "rocketmq-acl-{}-{}-{}.json",
+            name,
+            std::process::id(),
+            rand::random::<u32>()
+        ));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn publish(access_key: &str, secret_key: &str, topic: &str) -> AccessResource {
+        let content = format!("{}{}", access_key, topic).into_bytes();
+        AccessResource {
+            access_key: Some(access_key.into()),
+            signature: Some(acl_signer::calculate_signature(&content, secret_key).into()),
+            content,
+            topics: vec![(topic.into(), Permission::PUB)],
+            ..AccessResource::default()
+        }
+    }
+
+    #[test]
+    fn parse_and_check_permission() {
+        assert_eq!(
+            Permission::parse("PUB|SUB"),
+            Permission::PUB | Permission::SUB
+        );
+        assert_eq!(Permission::parse("whatever"), Permission::DENY);
+        assert!(Permission::check(
+            Permission::PUB,
+            Permission::PUB | Permission::SUB
+        ));
+        assert!(!Permission::check(Permission::PUB, Permission::SUB));
+        assert!(Permission::check(Permission::ANY, Permission::SUB));
+        assert!(!Permission::check(Permission::ANY, Permission::DENY));
+    }
+
+    #[test]
+    fn wildcard_and_default_deny() {
+        let path = write_acl_file(
+            "wildcard",
+            r#"{"accounts":[{"accessKey":"ak","secretKey":"sk",
+                "topicPerms":["order_*=PUB","order_audit_*=DENY","*_public=SUB"]}]}"#,
+        );
+        let validator = PlainAccessValidator::new(&path);
+        assert!(validator.load());
+
+        assert!(validator
+            .validate(&publish("ak", "sk", "order_created"))
+            .is_ok());
+        assert!(validator
+            .validate(&publish("ak", "sk", "order_audit_log"))
+            .is_err());
+        assert!(validator.validate(&publish("ak", "sk", "payment")).is_err());
+        assert!(validator
+            .validate(&publish("ak", "wrong", "order_created"))
+            .is_err());
+        assert!(validator
+            .validate(&publish("unknown", "sk", "order_created"))
+            .is_err());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn reload_when_file_changes() {
+        let path = write_acl_file(
+            "reload",
+            r#"{"accounts":[{"accessKey":"ak","secretKey":"sk","topicPerms":["t=SUB"]}]}"#,
+        );
+        let validator = PlainAccessValidator::new(&path);
+        assert!(validator.load());
+        assert!(validator.validate(&publish("ak", "sk", "t")).is_err());
+        assert!(!validator.reload_if_changed());
+
+        std::fs::write(
+            &path,
+            r#"{"accounts":[{"accessKey":"ak","secretKey":"sk","topicPerms":["t=PUB"]}]}"#,
+        )
+        .unwrap();
+        let modified = SystemTime::now() + Duration::from_secs(1);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert!(validator.reload_if_changed());
+        assert!(validator.validate(&publish("ak", "sk", "t")).is_ok());
+        let _ = std::fs::remove_file(path);
+    }
+}
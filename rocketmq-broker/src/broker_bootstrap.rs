@@ -15,12 +15,15 @@
  * limitations under the License.
  */
 
+use std::sync::Arc;
+
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::server::config::ServerConfig;
 use rocketmq_store::config::message_store_config::MessageStoreConfig;
 use tracing::error;
 
 use crate::broker_runtime::BrokerRuntime;
+use crate::offset::manager::consumer_offset_backend::ConsumerOffsetBackend;
 
 pub struct BrokerBootstrap {
     broker_runtime: BrokerRuntime,
@@ -48,6 +51,7 @@ pub struct Builder {
     broker_config: BrokerConfig,
     message_store_config: MessageStoreConfig,
     server_config: ServerConfig,
+    consumer_offset_backend: Option<Arc<dyn ConsumerOffsetBackend>>,
 }
 
 impl Builder {
@@ -56,6 +60,7 @@ impl Builder {
             broker_config: Default::default(),
             message_store_config: MessageStoreConfig::default(),
             server_config: Default::default(),
+            consumer_offset_backend: None,
         }
     }
 
@@ -74,14 +79,26 @@ impl Builder {
         self
     }
 
+    /// Stores the consumer offsets in `consumer_offset_backend` instead of the local consumer
+    /// offset file.
+    pub fn set_consumer_offset_backend(
+        mut self,
+        consumer_offset_backend: Arc<dyn ConsumerOffsetBackend>,
+    ) -> Self {
+        self.consumer_offset_backend = Some(consumer_offset_backend);
+        self
+    }
+
     pub fn build(self) -> BrokerBootstrap {
-        BrokerBootstrap {
-            broker_runtime: BrokerRuntime::new(
-                self.broker_config,
-                self.message_store_config,
-                self.server_config,
-            ),
+        let mut broker_runtime = BrokerRuntime::new(
+            self.broker_config,
+            self.message_store_config,
+            self.server_config,
+        );
+        if let Some(consumer_offset_backend) = self.consumer_offset_backend {
+            broker_runtime.set_consumer_offset_backend(consumer_offset_backend);
         }
+        BrokerBootstrap { broker_runtime }
     }
 }
 
@@ -71,6 +71,15 @@ pub fn get_consumer_order_info_path(root_dir: &str) -> String {
         .into_owned()
 }
 
+// Plain ACL path
+pub fn get_acl_config_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("config")
+        .join("plainAcl.json")
+        .to_string_lossy()
+        .into_owned()
+}
+
 // Subscription group path
 pub fn get_subscription_group_path(root_dir: &str) -> String {
     PathBuf::from(root_dir)
@@ -125,6 +134,15 @@ pub fn get_message_request_mode_path(root_dir: &str) -> String {
         .into_owned()
 }
 
+// Pop buffer write-ahead log path
+pub fn get_pop_buffer_wal_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("pop")
+        .join("popBufferMerge.wal")
+        .to_string_lossy()
+        .into_owned()
+}
+
 #[cfg(test)]
 mod test {
     use std::path::PathBuf;
@@ -273,4 +291,16 @@ mod test {
             .join("messageRequestMode.json");
         assert_eq!(path, expected_path.to_string_lossy().into_owned());
     }
+
+    #[test]
+    fn test_get_pop_buffer_wal_path() {
+        let root_dir = PathBuf::from("/path/to/root")
+            .to_string_lossy()
+            .into_owned();
+        let path = get_pop_buffer_wal_path(root_dir.as_str());
+        let expected_path = PathBuf::from(root_dir.clone())
+            .join("pop")
+            .join("popBufferMerge.wal");
+        assert_eq!(path, expected_path.to_string_lossy().into_owned());
+    }
 }
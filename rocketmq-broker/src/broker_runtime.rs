@@ -20,6 +20,7 @@ use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::thread;
 use std::time::Duration;
 
 use cheetah_string::CheetahString;
@@ -39,6 +40,7 @@ use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_detail::Topic
 use rocketmq_remoting::protocol::DataVersion;
 use rocketmq_remoting::remoting_server::server::RocketMQServer;
 use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
+use rocketmq_remoting::runtime::RPCHook;
 use rocketmq_runtime::RocketMQRuntime;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::base::store_enum::StoreType;
@@ -48,10 +50,15 @@ use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
 use rocketmq_store::stats::broker_stats::BrokerStats;
 use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
 use rocketmq_store::timer::timer_message_store::TimerMessageStore;
+use tokio::runtime::Handle;
+use tracing::error;
 use tracing::info;
 use tracing::warn;
 
+use crate::acl::acl_rpc_hook::AclRpcHook;
+use crate::acl::plain_access_validator::PlainAccessValidator;
 use crate::broker::broker_hook::BrokerShutdownHook;
+use crate::broker_path_config_helper::get_acl_config_path;
 use crate::client::default_consumer_ids_change_listener::DefaultConsumerIdsChangeListener;
 use crate::client::manager::consumer_manager::ConsumerManager;
 use crate::client::manager::producer_manager::ProducerManager;
@@ -61,19 +68,28 @@ use crate::failover::escape_bridge::EscapeBridge;
 use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
 use crate::hook::batch_check_before_put_message::BatchCheckBeforePutMessageHook;
 use crate::hook::check_before_put_message::CheckBeforePutMessageHook;
+use crate::load_balance::pop_sticky_assignment_manager::PopStickyAssignmentManager;
 use crate::long_polling::long_polling_service::pull_request_hold_service::PullRequestHoldService;
 use crate::long_polling::notify_message_arriving_listener::NotifyMessageArrivingListener;
+use crate::mqtrace::broker_trace_hook::BrokerTraceDispatcher;
+use crate::mqtrace::consume_message_hook::ConsumeMessageHook;
 use crate::offset::manager::broadcast_offset_manager::BroadcastOffsetManager;
+use crate::offset::manager::consumer_offset_backend::ConsumerOffsetBackend;
 use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
 use crate::offset::manager::consumer_order_info_manager::ConsumerOrderInfoManager;
 use crate::out_api::broker_outer_api::BrokerOuterAPI;
+use crate::processor::ack_invisible_time_cap_table::AckInvisibleTimeCapTable;
+use crate::processor::ack_message_processor::resolve_store_host;
 use crate::processor::ack_message_processor::AckMessageProcessor;
+use crate::processor::ack_message_processor::AckMessageProcessorBuildError;
+use crate::processor::ack_processing_switch::AckProcessingSwitch;
 use crate::processor::admin_broker_processor::AdminBrokerProcessor;
 use crate::processor::change_invisible_time_processor::ChangeInvisibleTimeProcessor;
 use crate::processor::client_manage_processor::ClientManageProcessor;
 use crate::processor::consumer_manage_processor::ConsumerManageProcessor;
 use crate::processor::default_pull_message_result_handler::DefaultPullMessageResultHandler;
 use crate::processor::end_transaction_processor::EndTransactionProcessor;
+use crate::processor::pop_consumer_flow_controller::PopConsumerFlowController;
 use crate::processor::pop_inflight_message_counter::PopInflightMessageCounter;
 use crate::processor::pop_message_processor::PopMessageProcessor;
 use crate::processor::processor_service::pop_buffer_merge_service::PopBufferMergeService;
@@ -96,6 +112,9 @@ use crate::transaction::queue::transactional_message_bridge::TransactionalMessag
 use crate::transaction::transaction_metrics_flush_service::TransactionMetricsFlushService;
 use crate::transaction::transactional_message_check_service::TransactionalMessageCheckService;
 
+/// How often the acl file is checked for changes.
+const ACL_FILE_CHECK_INTERVAL_MILLIS: u64 = 5000;
+
 pub(crate) struct BrokerRuntime {
     store_host: SocketAddr,
     broker_config: Arc<BrokerConfig>,
@@ -139,13 +158,34 @@ pub(crate) struct BrokerRuntime {
         Option<ArcMut<DefaultTransactionalMessageService<DefaultMessageStore>>>,
     #[cfg(feature = "local_file_store")]
     transactional_message_check_listener:
-        Option<Arc<DefaultTransactionalMessageCheckListener<DefaultMessageStore>>>,
-    transactional_message_check_service: Option<Arc<TransactionalMessageCheckService>>,
+        Option<ArcMut<DefaultTransactionalMessageCheckListener<DefaultMessageStore>>>,
+    #[cfg(feature = "local_file_store")]
+    transactional_message_check_service: Option<
+        Arc<
+            TransactionalMessageCheckService<
+                DefaultTransactionalMessageService<DefaultMessageStore>,
+                DefaultTransactionalMessageCheckListener<DefaultMessageStore>,
+            >,
+        >,
+    >,
     transaction_metrics_flush_service: Option<Arc<TransactionMetricsFlushService>>,
     topic_route_info_manager: Arc<TopicRouteInfoManager>,
     #[cfg(feature = "local_file_store")]
     escape_bridge: ArcMut<EscapeBridge<DefaultMessageStore>>,
     pop_inflight_message_counter: Arc<PopInflightMessageCounter>,
+    pop_sticky_assignment_manager: Arc<PopStickyAssignmentManager>,
+    ack_invisible_time_cap_table: Arc<AckInvisibleTimeCapTable>,
+    ack_processing_switch: Arc<AckProcessingSwitch>,
+    pop_buffer_merge_service: ArcMut<PopBufferMergeService>,
+    pop_consumer_flow_controller: Arc<PopConsumerFlowController>,
+    #[cfg(feature = "local_file_store")]
+    request_processor: Option<
+        BrokerRequestProcessor<
+            DefaultMessageStore,
+            DefaultTransactionalMessageService<DefaultMessageStore>,
+        >,
+    >,
+    rpc_hooks: Vec<Arc<dyn RPCHook>>,
 }
 
 impl Clone for BrokerRuntime {
@@ -188,6 +228,13 @@ impl Clone for BrokerRuntime {
             topic_route_info_manager: self.topic_route_info_manager.clone(),
             escape_bridge: self.escape_bridge.clone(),
             pop_inflight_message_counter: self.pop_inflight_message_counter.clone(),
+            pop_sticky_assignment_manager: self.pop_sticky_assignment_manager.clone(),
+            ack_invisible_time_cap_table: self.ack_invisible_time_cap_table.clone(),
+            ack_processing_switch: self.ack_processing_switch.clone(),
+            pop_buffer_merge_service: self.pop_buffer_merge_service.clone(),
+            pop_consumer_flow_controller: self.pop_consumer_flow_controller.clone(),
+            request_processor: self.request_processor.clone(),
+            rpc_hooks: self.rpc_hooks.clone(),
         }
     }
 }
@@ -221,7 +268,7 @@ impl BrokerRuntime {
         let mut stats_manager = BrokerStatsManager::new(broker_config.clone());
         let producer_manager = Arc::new(ProducerManager::new());
         let consumer_manager = Arc::new(ConsumerManager::new_with_broker_stats(
-            Box::new(DefaultConsumerIdsChangeListener {}),
+            Box::new(DefaultConsumerIdsChangeListener::default()),
             broker_config.clone(),
         ));
         stats_manager.set_producer_state_getter(Arc::new(ProducerStateGetter {
@@ -261,6 +308,9 @@ impl BrokerRuntime {
         let should_start_time = Arc::new(AtomicU64::new(0));
         let pop_inflight_message_counter =
             Arc::new(PopInflightMessageCounter::new(should_start_time.clone()));
+        let pop_sticky_assignment_manager = Arc::new(PopStickyAssignmentManager::new(
+            broker_config.pop_sticky_assignment_lease_millis,
+        ));
         Self {
             store_host,
             broker_config: broker_config.clone(),
@@ -299,6 +349,18 @@ impl BrokerRuntime {
             topic_route_info_manager,
             escape_bridge,
             pop_inflight_message_counter,
+            pop_sticky_assignment_manager,
+            ack_invisible_time_cap_table: Arc::new(AckInvisibleTimeCapTable::default()),
+            ack_processing_switch: Arc::new(AckProcessingSwitch::new(
+                broker_config.disable_ack_processing,
+            )),
+            pop_buffer_merge_service: ArcMut::new(PopBufferMergeService::new(
+                broker_config.clone(),
+                store_host,
+            )),
+            pop_consumer_flow_controller: Arc::new(PopConsumerFlowController::default()),
+            request_processor: None,
+            rpc_hooks: Vec::new(),
         }
     }
 
@@ -310,8 +372,25 @@ impl BrokerRuntime {
         &self.message_store_config
     }
 
+    /// Stores the consumer offsets in `offset_backend` instead of the local consumer offset
+    /// file. Must be called before `initialize`, which hands the offset manager to the
+    /// processors and the flush task.
+    pub(crate) fn set_consumer_offset_backend(
+        &mut self,
+        offset_backend: Arc<dyn ConsumerOffsetBackend>,
+    ) {
+        self.consumer_offset_manager
+            .set_offset_backend(offset_backend);
+    }
+
+    /// Shuts the broker down. Acks still held in the pop buffer are written to the revive topic
+    /// before the message store is closed, new acks are rejected meanwhile.
     pub fn shutdown(&mut self) {
         self.broker_outer_api.shutdown();
+        self.drain_pop_buffer();
+        if let Some(timer_message_store) = &mut self.timer_message_store {
+            timer_message_store.shutdown();
+        }
         if let Some(message_store) = &mut self.message_store {
             message_store.shutdown()
         }
@@ -323,12 +402,36 @@ impl BrokerRuntime {
         if let Some(pull_request_hold_service) = self.pull_request_hold_service.as_mut() {
             pull_request_hold_service.shutdown();
         }
+        self.pop_buffer_merge_service.shutdown();
+        if let Some(transactional_message_check_service) =
+            self.transactional_message_check_service.as_ref()
+        {
+            transactional_message_check_service.shutdown();
+        }
 
         if let Some(runtime) = self.broker_runtime.take() {
             runtime.shutdown();
         }
     }
 
+    fn drain_pop_buffer(&mut self) {
+        let Ok(handle) = Handle::try_current() else {
+            warn!("No runtime to drain the pop buffer, buffered acks are lost");
+            return;
+        };
+        let pop_buffer_merge_service = self.pop_buffer_merge_service.clone();
+        let mut escape_bridge = self.escape_bridge.clone();
+        let timeout = Duration::from_millis(self.broker_config.pop_buffer_drain_timeout_millis);
+        let _ = thread::spawn(move || {
+            handle.block_on(async move {
+                pop_buffer_merge_service
+                    .drain(escape_bridge.as_mut(), timeout)
+                    .await
+            })
+        })
+        .join();
+    }
+
     pub(crate) fn shutdown_basic_service(&mut self) {
         self.shutdown.store(true, Ordering::SeqCst);
 
@@ -352,6 +455,10 @@ impl Drop for BrokerRuntime {
 
 impl BrokerRuntime {
     pub(crate) async fn initialize(&mut self) -> bool {
+        if let Err(e) = resolve_store_host(self.store_host, &self.broker_config) {
+            error!("Invalid store host, {}", e);
+            return false;
+        }
         let mut result = self.initialize_metadata();
         if !result {
             warn!("Initialize metadata failed");
@@ -362,7 +469,20 @@ impl BrokerRuntime {
         if !result {
             return false;
         }
-        self.recover_initialize_service().await
+        result = self.recover_initialize_service().await;
+        if !result {
+            return false;
+        }
+        match self.init_processor() {
+            Ok(request_processor) => {
+                self.request_processor = Some(request_processor);
+                true
+            }
+            Err(e) => {
+                error!("Initialize request processor failed: {}", e);
+                false
+            }
+        }
     }
 
     ///Load the original configuration data from the corresponding configuration files located
@@ -391,7 +511,8 @@ impl BrokerRuntime {
             message_store.set_message_store_arc(Some(message_store_clone));
             if self.message_store_config.is_timer_wheel_enable() {
                 let time_message_store = TimerMessageStore::new(Some(message_store.clone()));
-                message_store.set_timer_message_store(Arc::new(time_message_store));
+                message_store.set_timer_message_store(Arc::new(time_message_store.clone()));
+                self.timer_message_store = Some(time_message_store);
             }
             self.consumer_offset_manager
                 .set_message_store(Some(message_store.clone()));
@@ -419,9 +540,10 @@ impl BrokerRuntime {
             self.register_message_store_hook();
             self.message_store.as_mut().unwrap().load().await;
         }
+        self.pop_buffer_merge_service.recover();
 
-        if self.broker_config.timer_wheel_config.timer_wheel_enable {
-            result &= self.timer_message_store.as_mut().unwrap().load();
+        if let Some(timer_message_store) = self.timer_message_store.as_mut() {
+            result &= timer_message_store.load();
         }
         result &= self.schedule_message_service.load();
 
@@ -460,11 +582,14 @@ impl BrokerRuntime {
 
     fn init_processor(
         &mut self,
-    ) -> BrokerRequestProcessor<
-        DefaultMessageStore,
-        DefaultTransactionalMessageService<DefaultMessageStore>,
+    ) -> Result<
+        BrokerRequestProcessor<
+            DefaultMessageStore,
+            DefaultTransactionalMessageService<DefaultMessageStore>,
+        >,
+        AckMessageProcessorBuildError,
     > {
-        let send_message_processor = SendMessageProcessor::new(
+        let mut send_message_processor = SendMessageProcessor::new(
             self.topic_queue_mapping_manager.clone(),
             self.subscription_group_manager.clone(),
             self.topic_config_manager.clone(),
@@ -487,6 +612,17 @@ impl BrokerRuntime {
             self.transactional_message_service.as_ref().unwrap().clone(),
             self.store_host,
         );
+        let mut consume_message_hook_list: Vec<Box<dyn ConsumeMessageHook>> = Vec::new();
+        if self.broker_config.trace_on && self.broker_config.trace_topic_enable {
+            let (trace_hook, trace_dispatcher) = BrokerTraceDispatcher::new(
+                self.broker_config.clone(),
+                self.escape_bridge.clone(),
+                self.store_host,
+            );
+            send_message_processor.register_send_message_hook(Box::new(trace_hook.clone()));
+            consume_message_hook_list.push(Box::new(trace_hook));
+            trace_dispatcher.start();
+        }
         let mut pull_message_result_handler =
             ArcMut::new(Box::new(DefaultPullMessageResultHandler::new(
                 self.message_store_config.clone(),
@@ -496,7 +632,7 @@ impl BrokerRuntime {
                 self.broadcast_offset_manager.clone(),
                 self.broker_stats_manager.clone(),
                 self.broker_config.clone(),
-                Arc::new(Default::default()),
+                Arc::new(consume_message_hook_list),
             )) as Box<dyn PullMessageResultHandler>);
         let message_store = self.message_store.clone().unwrap();
         let pull_message_processor = ArcMut::new(PullMessageProcessor::new(
@@ -535,12 +671,6 @@ impl BrokerRuntime {
             .expect("downcast DefaultPullMessageResultHandler failed")
             .set_pull_request_hold_service(self.pull_request_hold_service.clone());
 
-        self.message_store
-            .as_mut()
-            .unwrap()
-            .set_message_arriving_listener(Some(Arc::new(Box::new(
-                NotifyMessageArrivingListener::new(self.pull_request_hold_service.clone().unwrap()),
-            ))));
         let query_message_processor =
             QueryMessageProcessor::new(self.message_store_config.clone(), message_store.clone());
 
@@ -560,17 +690,54 @@ impl BrokerRuntime {
             self.rebalance_lock_manager.clone(),
             self.broker_member_group.clone(),
             self.pop_inflight_message_counter.clone(),
+            self.ack_invisible_time_cap_table.clone(),
+            self.ack_processing_switch.clone(),
         );
-        let pop_message_processor = ArcMut::new(PopMessageProcessor::default());
-        let ack_message_processor = ArcMut::new(AckMessageProcessor::new(
+        let pop_message_processor = ArcMut::new(PopMessageProcessor::new(
+            self.broker_config.clone(),
             self.topic_config_manager.clone(),
+            self.subscription_group_manager.clone(),
+            Arc::new(self.consumer_offset_manager.clone()),
+            self.consumer_order_info_manager.clone(),
+            self.consumer_filter_manager.clone(),
             self.message_store.as_ref().unwrap().clone(),
             self.escape_bridge.clone(),
-            self.broker_config.clone(),
+            self.pop_buffer_merge_service.clone(),
             self.pop_inflight_message_counter.clone(),
+            self.pop_consumer_flow_controller.clone(),
             self.store_host,
         ));
-        BrokerRequestProcessor {
+        self.message_store
+            .as_mut()
+            .unwrap()
+            .set_message_arriving_listener(Some(Arc::new(Box::new(
+                NotifyMessageArrivingListener::new(
+                    self.pull_request_hold_service.clone().unwrap(),
+                    pop_message_processor.clone(),
+                ),
+            ))));
+        let ack_message_processor = ArcMut::new(
+            AckMessageProcessor::builder()
+                .topic_config_manager(self.topic_config_manager.clone())
+                .subscription_group_manager(self.subscription_group_manager.clone())
+                .consumer_offset_manager(Arc::new(self.consumer_offset_manager.clone()))
+                .consumer_order_info_manager(self.consumer_order_info_manager.clone())
+                .message_store(self.message_store.as_ref().unwrap().clone())
+                .escape_bridge(self.escape_bridge.clone())
+                .pop_message_processor(pop_message_processor.clone())
+                .broker_config(self.broker_config.clone())
+                .pop_inflight_message_counter(self.pop_inflight_message_counter.clone())
+                .pop_sticky_assignment_manager(self.pop_sticky_assignment_manager.clone())
+                .consumer_manager(self.consumer_manager.clone())
+                .ack_invisible_time_cap_table(self.ack_invisible_time_cap_table.clone())
+                .pop_consumer_flow_controller(self.pop_consumer_flow_controller.clone())
+                .ack_processing_switch(self.ack_processing_switch.clone())
+                .pop_buffer_merge_service(self.pop_buffer_merge_service.clone())
+                .broker_stats_manager(self.broker_stats_manager.clone())
+                .store_host(self.store_host)
+                .build()?,
+        );
+        Ok(BrokerRequestProcessor {
             send_message_processor: ArcMut::new(send_message_processor),
             pull_message_processor,
             peek_message_processor: Default::default(),
@@ -583,7 +750,7 @@ impl BrokerRuntime {
                 Arc::new(self.consumer_offset_manager.clone()),
                 self.consumer_order_info_manager.clone(),
                 self.broker_stats_manager.clone(),
-                ArcMut::new(PopBufferMergeService),
+                self.pop_buffer_merge_service.clone(),
                 self.escape_bridge.clone(),
                 pop_message_processor,
             )),
@@ -597,6 +764,7 @@ impl BrokerRuntime {
                 self.consumer_manager.clone(),
                 self.topic_config_manager.clone(),
                 self.subscription_group_manager.clone(),
+                self.pop_sticky_assignment_manager.clone(),
             )),
             consumer_manage_processor: ArcMut::new(consumer_manage_processor),
             query_assignment_processor: ArcMut::new(QueryAssignmentProcessor::new(
@@ -604,6 +772,7 @@ impl BrokerRuntime {
                 self.broker_config.clone(),
                 self.topic_route_info_manager.clone(),
                 self.consumer_manager.clone(),
+                self.pop_sticky_assignment_manager.clone(),
             )),
             query_message_processor: ArcMut::new(query_message_processor),
             end_transaction_processor: ArcMut::new(EndTransactionProcessor::new(
@@ -612,7 +781,7 @@ impl BrokerRuntime {
                 self.transactional_message_service.as_ref().unwrap().clone(),
                 self.message_store.as_ref().unwrap().clone(),
             )),
-        }
+        })
     }
 
     async fn initialize_scheduled_tasks(&mut self) {
@@ -648,7 +817,7 @@ impl BrokerRuntime {
                 tokio::time::sleep(Duration::from_millis(1000 * 10)).await;
                 loop {
                     let current_execution_time = tokio::time::Instant::now();
-                    consumer_offset_manager.persist();
+                    consumer_offset_manager.flush();
                     let next_execution_time = current_execution_time
                         + Duration::from_millis(flush_consumer_offset_interval);
                     let delay =
@@ -679,6 +848,24 @@ impl BrokerRuntime {
                 }
             });
 
+        let consumer_manager = self.consumer_manager.clone();
+        self.broker_runtime
+            .as_ref()
+            .unwrap()
+            .get_handle()
+            .spawn(async move {
+                info!("Client housekeeping Start scheduled task");
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                loop {
+                    let current_execution_time = tokio::time::Instant::now();
+                    consumer_manager.scan_not_active_channel();
+                    let next_execution_time = current_execution_time + Duration::from_secs(10);
+                    let delay =
+                        next_execution_time.saturating_duration_since(tokio::time::Instant::now());
+                    tokio::time::sleep(delay).await;
+                }
+            });
+
         let mut runtime = self.clone();
         self.broker_runtime
             .as_ref()
@@ -759,19 +946,51 @@ impl BrokerRuntime {
                 self.transactional_message_service = Some(ArcMut::new(service));
             }
         }
-        self.transactional_message_check_listener =
-            Some(Arc::new(DefaultTransactionalMessageCheckListener::new(
-                self.broker_config.clone(),
-                self.producer_manager.clone(),
-                Broker2Client,
-                self.topic_config_manager.clone(),
-                self.message_store.as_ref().cloned().unwrap(),
-            )));
-        self.transactional_message_check_service = Some(Arc::new(TransactionalMessageCheckService));
+        let listener = ArcMut::new(DefaultTransactionalMessageCheckListener::new(
+            self.broker_config.clone(),
+            self.producer_manager.clone(),
+            Broker2Client,
+            self.topic_config_manager.clone(),
+            self.message_store.as_ref().cloned().unwrap(),
+        ));
+        self.transactional_message_check_listener = Some(listener.clone());
+        if let Some(transactional_message_service) = self.transactional_message_service.clone() {
+            self.transactional_message_check_service =
+                Some(Arc::new(TransactionalMessageCheckService::new(
+                    self.broker_config.clone(),
+                    transactional_message_service,
+                    listener,
+                )));
+        }
         self.transaction_metrics_flush_service = Some(Arc::new(TransactionMetricsFlushService));
     }
 
-    fn initial_acl(&mut self) {}
+    fn initial_acl(&mut self) {
+        if !self.broker_config.acl_enable {
+            info!("The broker does not enable acl");
+            return;
+        }
+        let validator = Arc::new(PlainAccessValidator::new(get_acl_config_path(
+            self.broker_config.store_path_root_dir.as_str(),
+        )));
+        if !validator.load() {
+            warn!("No acl account is loaded, all requests are denied until the acl file is fixed");
+        }
+        self.rpc_hooks
+            .push(Arc::new(AclRpcHook::new(validator.clone())));
+
+        self.broker_runtime
+            .as_ref()
+            .unwrap()
+            .get_handle()
+            .spawn(async move {
+                info!("Acl file watcher Start scheduled task");
+                loop {
+                    tokio::time::sleep(Duration::from_millis(ACL_FILE_CHECK_INTERVAL_MILLIS)).await;
+                    validator.reload_if_changed();
+                }
+            });
+    }
 
     fn initial_rpc_hooks(&mut self) {}
 
@@ -780,21 +999,34 @@ impl BrokerRuntime {
     fn protect_broker(&mut self) {}
 
     fn start_basic_service(&mut self) {
-        let request_processor = self.init_processor();
+        let request_processor = self
+            .request_processor
+            .clone()
+            .expect("request processor is built in initialize");
         let fast_request_processor = request_processor.clone();
+        request_processor.pop_message_processor.start();
         self.message_store
             .as_mut()
             .unwrap()
             .start()
             .expect("Message store start error");
+        if let Some(timer_message_store) = self.timer_message_store.as_mut() {
+            timer_message_store.start();
+        }
 
-        let server = RocketMQServer::new(self.server_config.clone());
+        let mut server = RocketMQServer::new(self.server_config.clone());
+        for hook in &self.rpc_hooks {
+            server.register_rpc_hook(hook.clone());
+        }
         //start nomarl broker remoting_server
         tokio::spawn(async move { server.run(request_processor).await });
         //start fast broker remoting_server
         let mut fast_server_config = (*self.server_config).clone();
         fast_server_config.listen_port = self.server_config.listen_port - 2;
-        let fast_server = RocketMQServer::new(Arc::new(fast_server_config));
+        let mut fast_server = RocketMQServer::new(Arc::new(fast_server_config));
+        for hook in &self.rpc_hooks {
+            fast_server.register_rpc_hook(hook.clone());
+        }
         tokio::spawn(async move { fast_server.run(fast_request_processor).await });
 
         if let Some(pull_request_hold_service) = self.pull_request_hold_service.as_mut() {
@@ -805,6 +1037,16 @@ impl BrokerRuntime {
         self.topic_route_info_manager.start();
 
         self.escape_bridge.start(self.message_store.clone());
+
+        let this = self.pop_buffer_merge_service.clone();
+        self.pop_buffer_merge_service
+            .start(this, self.escape_bridge.clone());
+
+        if let Some(transactional_message_check_service) =
+            self.transactional_message_check_service.as_ref()
+        {
+            transactional_message_check_service.start();
+        }
     }
 
     async fn update_namesrv_addr(&mut self) {
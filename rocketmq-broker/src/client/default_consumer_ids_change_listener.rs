@@ -16,14 +16,50 @@
  */
 use std::any::Any;
 
+use cheetah_string::CheetahString;
+use rocketmq_remoting::net::channel::Channel;
+use tracing::warn;
+
 use crate::client::consumer_group_event::ConsumerGroupEvent;
 use crate::client::consumer_ids_change_listener::ConsumerIdsChangeListener;
+use crate::client::net::broker_to_client::Broker2Client;
 
 #[derive(Default)]
-pub struct DefaultConsumerIdsChangeListener {}
+pub struct DefaultConsumerIdsChangeListener {
+    broker_to_client: Broker2Client,
+}
 
 impl ConsumerIdsChangeListener for DefaultConsumerIdsChangeListener {
-    fn handle(&self, _event: ConsumerGroupEvent, _group: &str, _args: &[&dyn Any]) {}
+    fn handle(&self, event: ConsumerGroupEvent, group: &str, args: &[&dyn Any]) {
+        if !matches!(event, ConsumerGroupEvent::Change) {
+            return;
+        }
+        // the consumers of the group, told to rebalance
+        let Some(channels) = args
+            .first()
+            .and_then(|arg| arg.downcast_ref::<Vec<Channel>>())
+        else {
+            return;
+        };
+        for channel in channels {
+            let broker_to_client = self.broker_to_client.clone();
+            let mut channel = channel.clone();
+            let group = CheetahString::from_string(group.to_string());
+            tokio::spawn(async move {
+                if let Err(e) = broker_to_client
+                    .notify_consumer_ids_changed(&mut channel, &group)
+                    .await
+                {
+                    warn!(
+                        "notify consumer {} of group {} ids changed failed: {}",
+                        channel.remote_address(),
+                        group,
+                        e
+                    );
+                }
+            });
+        }
+    }
 
     fn shutdown(&self) {
         todo!()
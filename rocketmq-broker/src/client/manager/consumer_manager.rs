@@ -24,10 +24,12 @@ use cheetah_string::CheetahString;
 use parking_lot::RwLock;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::consumer::consume_from_where::ConsumeFromWhere;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::protocol::heartbeat::consume_type::ConsumeType;
 use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
 use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
 use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
+use tracing::warn;
 
 use crate::client::client_channel_info::ClientChannelInfo;
 use crate::client::consumer_group_event::ConsumerGroupEvent;
@@ -244,6 +246,127 @@ impl ConsumerManager {
         r1 || r2
     }
 
+    pub fn unregister_consumer(
+        &self,
+        group: &CheetahString,
+        client_channel_info: &ClientChannelInfo,
+        is_notify_consumer_ids_changed_enable: bool,
+    ) {
+        let consumer_group_info = match self.consumer_table.read().get(group) {
+            Some(consumer_group_info) => consumer_group_info.clone(),
+            None => return,
+        };
+        if consumer_group_info.unregister_channel(client_channel_info) {
+            self.call_consumer_ids_change_listener(
+                ConsumerGroupEvent::ClientUnregister,
+                group,
+                &[
+                    client_channel_info as &dyn Any,
+                    &consumer_group_info.get_subscribe_topics() as &dyn Any,
+                ],
+            );
+        }
+        if consumer_group_info
+            .get_channel_info_table()
+            .read()
+            .is_empty()
+            && self.consumer_table.write().remove(group).is_some()
+        {
+            self.call_consumer_ids_change_listener(ConsumerGroupEvent::Unregister, group, &[]);
+        }
+        if is_notify_consumer_ids_changed_enable {
+            let all_channel = consumer_group_info.get_all_channels();
+            self.call_consumer_ids_change_listener(
+                ConsumerGroupEvent::Change,
+                group,
+                &[&all_channel as &dyn Any],
+            );
+        }
+    }
+
+    /// Removes the consumers whose last heartbeat is older than the channel expired timeout.
+    ///
+    /// The consumers left in a group losing some are notified to rebalance, a group losing all of
+    /// them is removed.
+    pub fn scan_not_active_channel(&self) {
+        let now = get_current_millis();
+        let consumer_groups = self
+            .consumer_table
+            .read()
+            .iter()
+            .map(|(group, consumer_group_info)| (group.clone(), consumer_group_info.clone()))
+            .collect::<Vec<_>>();
+        for (group, consumer_group_info) in consumer_groups {
+            let expired = {
+                let channel_info_table = consumer_group_info.get_channel_info_table();
+                let mut channel_info_table = channel_info_table.write();
+                let expired = channel_info_table
+                    .values()
+                    .filter(|client_channel_info| {
+                        now.saturating_sub(client_channel_info.last_update_timestamp())
+                            > self.channel_expired_timeout
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>();
+                for client_channel_info in &expired {
+                    channel_info_table.remove(client_channel_info.channel());
+                }
+                expired
+            };
+            if expired.is_empty() {
+                continue;
+            }
+            let subscribe_topics = consumer_group_info.get_subscribe_topics();
+            for client_channel_info in &expired {
+                warn!(
+                    "SCAN: remove expired channel from ConsumerManager consumerTable. channel={}, \
+                     clientId={}, consumerGroup={}",
+                    client_channel_info.channel().remote_address(),
+                    client_channel_info.client_id(),
+                    group
+                );
+                self.call_consumer_ids_change_listener(
+                    ConsumerGroupEvent::ClientUnregister,
+                    &group,
+                    &[
+                        client_channel_info as &dyn Any,
+                        &subscribe_topics as &dyn Any,
+                    ],
+                );
+            }
+            let all_channel = consumer_group_info.get_all_channels();
+            if !all_channel.is_empty() {
+                self.call_consumer_ids_change_listener(
+                    ConsumerGroupEvent::Change,
+                    &group,
+                    &[&all_channel as &dyn Any],
+                );
+                continue;
+            }
+            let removed = {
+                let mut consumer_table = self.consumer_table.write();
+                // a consumer may have registered again since the scan started
+                let still_empty = consumer_table
+                    .get(&group)
+                    .is_some_and(|consumer_group_info| {
+                        consumer_group_info
+                            .get_channel_info_table()
+                            .read()
+                            .is_empty()
+                    });
+                still_empty && consumer_table.remove(&group).is_some()
+            };
+            if removed {
+                warn!(
+                    "SCAN: remove expired consumer group from ConsumerManager consumerTable, \
+                     consumerGroup={}",
+                    group
+                );
+                self.call_consumer_ids_change_listener(ConsumerGroupEvent::Unregister, &group, &[]);
+            }
+        }
+    }
+
     pub fn call_consumer_ids_change_listener(
         &self,
         event: ConsumerGroupEvent,
@@ -265,3 +388,134 @@ impl ConsumerManager {
         groups
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_remoting::net::channel::Channel;
+    use rocketmq_remoting::protocol::LanguageCode;
+
+    use super::*;
+    use crate::util::test_channel::test_channel;
+
+    /// An event seen by [`RecordingListener`], with the client of a `ClientUnregister` and the
+    /// channels of a `Change`.
+    struct RecordedEvent {
+        event: ConsumerGroupEvent,
+        group: String,
+        client_id: Option<CheetahString>,
+        channels: Vec<Channel>,
+    }
+
+    #[derive(Default, Clone)]
+    struct RecordingListener {
+        events: Arc<parking_lot::Mutex<Vec<RecordedEvent>>>,
+    }
+
+    impl ConsumerIdsChangeListener for RecordingListener {
+        fn handle(&self, event: ConsumerGroupEvent, group: &str, args: &[&dyn Any]) {
+            let first = args.first();
+            self.events.lock().push(RecordedEvent {
+                event,
+                group: group.to_string(),
+                client_id: first
+                    .and_then(|arg| arg.downcast_ref::<ClientChannelInfo>())
+                    .map(|client_channel_info| client_channel_info.client_id().clone()),
+                channels: first
+                    .and_then(|arg| arg.downcast_ref::<Vec<Channel>>())
+                    .cloned()
+                    .unwrap_or_default(),
+            });
+        }
+
+        fn shutdown(&self) {}
+    }
+
+    async fn register(consumer_manager: &ConsumerManager, client_id: &str) -> Channel {
+        let channel = test_channel().await;
+        consumer_manager.register_consumer(
+            &CheetahString::from_static_str("group"),
+            ClientChannelInfo::new(channel.clone(), client_id.into(), LanguageCode::RUST, 1),
+            ConsumeType::ConsumePassively,
+            MessageModel::Clustering,
+            ConsumeFromWhere::ConsumeFromLastOffset,
+            HashSet::new(),
+            false,
+        );
+        channel
+    }
+
+    /// Makes the last heartbeat of `channel` older than the expired timeout of the tests.
+    fn miss_heartbeat(consumer_manager: &ConsumerManager, channel: &Channel) {
+        consumer_manager
+            .get_consumer_group_info(&CheetahString::from_static_str("group"))
+            .unwrap()
+            .get_channel_info_table()
+            .write()
+            .get_mut(channel)
+            .unwrap()
+            .set_last_update_timestamp(get_current_millis() - 5_000);
+    }
+
+    #[tokio::test]
+    async fn consumer_missing_heartbeat_is_evicted_and_group_rebalanced() {
+        let listener = RecordingListener::default();
+        let consumer_manager = ConsumerManager::new(Box::new(listener.clone()), 1_000);
+        let stale = register(&consumer_manager, "stale").await;
+        let live = register(&consumer_manager, "live").await;
+        miss_heartbeat(&consumer_manager, &stale);
+        listener.events.lock().clear();
+
+        consumer_manager.scan_not_active_channel();
+
+        let group = CheetahString::from_static_str("group");
+        let consumer_group_info = consumer_manager.get_consumer_group_info(&group).unwrap();
+        assert_eq!(consumer_group_info.get_all_channels(), vec![live.clone()]);
+        let events = listener.events.lock();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            events[0].event,
+            ConsumerGroupEvent::ClientUnregister
+        ));
+        assert_eq!(events[0].client_id.as_deref(), Some("stale"));
+        // the consumer left is told to rebalance
+        assert!(matches!(events[1].event, ConsumerGroupEvent::Change));
+        assert_eq!(events[1].group, "group");
+        assert_eq!(events[1].channels, vec![live]);
+    }
+
+    #[tokio::test]
+    async fn group_without_live_consumer_is_removed() {
+        let listener = RecordingListener::default();
+        let consumer_manager = ConsumerManager::new(Box::new(listener.clone()), 1_000);
+        let channel = register(&consumer_manager, "stale").await;
+        miss_heartbeat(&consumer_manager, &channel);
+        listener.events.lock().clear();
+
+        consumer_manager.scan_not_active_channel();
+
+        let group = CheetahString::from_static_str("group");
+        assert!(consumer_manager.get_consumer_group_info(&group).is_none());
+        let events = listener.events.lock();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            events[0].event,
+            ConsumerGroupEvent::ClientUnregister
+        ));
+        assert!(matches!(events[1].event, ConsumerGroupEvent::Unregister));
+    }
+
+    #[tokio::test]
+    async fn consumers_heartbeating_in_time_are_kept() {
+        let listener = RecordingListener::default();
+        let consumer_manager = ConsumerManager::new(Box::new(listener.clone()), 1_000);
+        let channel = register(&consumer_manager, "live").await;
+        listener.events.lock().clear();
+
+        consumer_manager.scan_not_active_channel();
+
+        let group = CheetahString::from_static_str("group");
+        let consumer_group_info = consumer_manager.get_consumer_group_info(&group).unwrap();
+        assert_eq!(consumer_group_info.get_all_channels(), vec![channel]);
+        assert!(listener.events.lock().is_empty());
+    }
+}
@@ -20,6 +20,7 @@ use rocketmq_common::MessageDecoder;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::header::check_transaction_state_request_header::CheckTransactionStateRequestHeader;
+use rocketmq_remoting::protocol::header::notify_consumer_ids_changed_request_header::NotifyConsumerIdsChangedRequestHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 
 use crate::broker_error::BrokerError::BrokerCommonError;
@@ -66,4 +67,23 @@ impl Broker2Client {
             Err(e) => Err(BrokerRemotingError(e)),
         }
     }
+
+    /// Tells a consumer the consumers of its group changed, so that it rebalances.
+    pub async fn notify_consumer_ids_changed(
+        &self,
+        channel: &mut Channel,
+        consumer_group: &CheetahString,
+    ) -> Result<()> {
+        let request = RemotingCommand::create_request_command(
+            RequestCode::NotifyConsumerIdsChanged,
+            NotifyConsumerIdsChangedRequestHeader {
+                consumer_group: consumer_group.clone(),
+                rpc_request_header: None,
+            },
+        );
+        match channel.send_one_way(request, 100).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(BrokerRemotingError(e)),
+        }
+    }
 }
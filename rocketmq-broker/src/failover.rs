@@ -15,3 +15,4 @@
  * limitations under the License.
  */
 pub mod escape_bridge;
+pub(crate) mod origin_offset_reconciler;
@@ -26,6 +26,8 @@ use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::mix_all;
+use rocketmq_remoting::protocol::header::query_consumer_offset_request_header::QueryConsumerOffsetRequestHeader;
+use rocketmq_remoting::protocol::header::update_consumer_offset_header::UpdateConsumerOffsetRequestHeader;
 use rocketmq_runtime::RocketMQRuntime;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::base::message_result::PutMessageResult;
@@ -34,12 +36,15 @@ use rocketmq_store::log_file::MessageStore;
 use tracing::error;
 use tracing::warn;
 
+use crate::broker_error::BrokerError;
+use crate::failover::origin_offset_reconciler::OriginOffsetStore;
 use crate::out_api::broker_outer_api::BrokerOuterAPI;
 use crate::topic::manager::topic_route_info_manager::TopicRouteInfoManager;
 use crate::transaction::queue::transactional_message_util::TransactionalMessageUtil;
 
 const SEND_TIMEOUT: u64 = 3_000;
 const DEFAULT_PULL_TIMEOUT_MILLIS: u64 = 10_000;
+const OFFSET_RPC_TIMEOUT: u64 = 3_000;
 ///### RocketMQ's EscapeBridge for Dead Letter Queue (DLQ) Mechanism
 ///
 /// In the context of message passing within RocketMQ, the `EscapeBridge` primarily handles the Dead
@@ -116,17 +121,70 @@ impl<MS> EscapeBridge<MS> {
     }
 
     pub fn start(&mut self, message_store: Option<ArcMut<MS>>) {
+        // a master writes escaped messages to its own store whatever the escape settings
+        self.message_store = message_store;
         if self.broker_config.enable_slave_acting_master && self.broker_config.enable_remote_escape
         {
             self.escape_bridge_runtime = Some(RocketMQRuntime::new_multi(
                 num_cpus::get(),
                 "AsyncEscapeBridgeExecutor",
             ));
-            self.message_store = message_store;
         }
     }
 }
 
+impl<MS> OriginOffsetStore for EscapeBridge<MS>
+where
+    MS: Send + Sync,
+{
+    async fn query_offset(
+        &self,
+        broker_name: &CheetahString,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+    ) -> crate::Result<Option<i64>> {
+        let broker_addr = self
+            .topic_route_info_manager
+            .find_broker_address_in_publish(Some(broker_name))
+            .ok_or_else(|| origin_broker_not_found(broker_name))?;
+        let request_header = QueryConsumerOffsetRequestHeader {
+            consumer_group: group.clone(),
+            topic: topic.clone(),
+            queue_id,
+            set_zero_if_not_found: Some(false),
+            topic_request_header: None,
+        };
+        self.broker_outer_api
+            .query_consumer_offset(&broker_addr, request_header, OFFSET_RPC_TIMEOUT)
+            .await
+    }
+
+    async fn commit_offset(
+        &self,
+        broker_name: &CheetahString,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+    ) -> crate::Result<()> {
+        let broker_addr = self
+            .topic_route_info_manager
+            .find_broker_address_in_publish(Some(broker_name))
+            .ok_or_else(|| origin_broker_not_found(broker_name))?;
+        let request_header = UpdateConsumerOffsetRequestHeader {
+            consumer_group: group.clone(),
+            topic: topic.clone(),
+            queue_id,
+            commit_offset: offset,
+            topic_request_header: None,
+        };
+        self.broker_outer_api
+            .update_consumer_offset(&broker_addr, request_header, OFFSET_RPC_TIMEOUT)
+            .await
+    }
+}
+
 impl<MS> EscapeBridge<MS>
 where
     MS: MessageStore,
@@ -136,11 +194,7 @@ where
         mut message_ext: MessageExtBrokerInner,
     ) -> PutMessageResult {
         if self.broker_config.broker_identity.broker_id == mix_all::MASTER_ID {
-            self.message_store
-                .as_mut()
-                .unwrap()
-                .put_message(message_ext)
-                .await
+            self.put_message_to_local_store(message_ext).await
         } else if self.broker_config.enable_slave_acting_master
             && self.broker_config.enable_remote_escape
         {
@@ -162,6 +216,29 @@ where
         }
     }
 
+    async fn put_message_to_local_store(
+        &mut self,
+        message_ext: MessageExtBrokerInner,
+    ) -> PutMessageResult {
+        // the message still waits for its flush, only the replication to slaves is not waited for
+        let standalone = is_standalone_master(
+            &self.broker_config,
+            self.topic_route_info_manager
+                .has_slave(&self.broker_config.broker_identity.broker_name),
+        );
+        let result = self
+            .message_store
+            .as_mut()
+            .unwrap()
+            .put_message(message_ext)
+            .await;
+        if standalone {
+            standalone_put_result(result)
+        } else {
+            result
+        }
+    }
+
     pub async fn put_message_to_remote_broker(
         &mut self,
         message_ext: MessageExtBrokerInner,
@@ -274,11 +351,7 @@ where
         mut message_ext: MessageExtBrokerInner,
     ) -> PutMessageResult {
         if self.broker_config.broker_identity.broker_id == mix_all::MASTER_ID {
-            self.message_store
-                .as_mut()
-                .unwrap()
-                .put_message(message_ext)
-                .await
+            self.put_message_to_local_store(message_ext).await
         } else if self.broker_config.enable_slave_acting_master
             && self.broker_config.enable_remote_escape
         {
@@ -323,11 +396,7 @@ where
         mut message_ext: MessageExtBrokerInner,
     ) -> PutMessageResult {
         if self.broker_config.broker_identity.broker_id == mix_all::MASTER_ID {
-            self.message_store
-                .as_mut()
-                .unwrap()
-                .put_message(message_ext)
-                .await
+            self.put_message_to_local_store(message_ext).await
         } else if self.broker_config.enable_slave_acting_master
             && self.broker_config.enable_remote_escape
         {
@@ -388,7 +457,9 @@ where
 }
 
 #[inline]
-fn transform_send_result2put_result(send_result: Option<SendResult>) -> PutMessageResult {
+pub(crate) fn transform_send_result2put_result(
+    send_result: Option<SendResult>,
+) -> PutMessageResult {
     match send_result {
         None => PutMessageResult::new(PutMessageStatus::PutToRemoteBrokerFail, None, true),
         Some(result) => match result.send_status {
@@ -406,12 +477,43 @@ fn transform_send_result2put_result(send_result: Option<SendResult>) -> PutMessa
     }
 }
 
+/// Whether the broker is a master allowed to run without slaves and no slave is registered for
+/// it, in which case local writes do not wait for a slave to acknowledge them.
+#[inline]
+fn is_standalone_master(broker_config: &BrokerConfig, has_slave: bool) -> bool {
+    broker_config.enable_standalone_master_fast_path
+        && broker_config.broker_identity.broker_id == mix_all::MASTER_ID
+        && !has_slave
+}
+
+/// Slave-side statuses cannot happen without slaves; a message that reached the local store is
+/// reported as stored.
+#[inline]
+fn standalone_put_result(mut result: PutMessageResult) -> PutMessageResult {
+    if matches!(
+        result.put_message_status(),
+        PutMessageStatus::FlushSlaveTimeout | PutMessageStatus::SlaveNotAvailable
+    ) {
+        result.set_put_message_status(PutMessageStatus::PutOk);
+    }
+    result
+}
+
+fn origin_broker_not_found(broker_name: &CheetahString) -> BrokerError {
+    BrokerError::IllegalArgumentError(format!(
+        "address of origin broker {} not found",
+        broker_name
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use rocketmq_client_rust::producer::send_result::SendResult;
     use rocketmq_client_rust::producer::send_status::SendStatus;
+    use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
 
     use super::*;
+    use crate::util::test_message_store::TestMessageStore;
 
     #[test]
     fn transform_send_result2put_result_handles_none() {
@@ -470,4 +572,70 @@ mod tests {
             PutMessageStatus::SlaveNotAvailable
         );
     }
+
+    #[test]
+    fn standalone_master_requires_flag_master_id_and_no_slave() {
+        let mut broker_config = BrokerConfig::default();
+        assert!(!is_standalone_master(&broker_config, false));
+
+        broker_config.enable_standalone_master_fast_path = true;
+        assert!(is_standalone_master(&broker_config, false));
+        assert!(!is_standalone_master(&broker_config, true));
+
+        broker_config.broker_identity.broker_id = 1;
+        assert!(!is_standalone_master(&broker_config, false));
+    }
+
+    #[test]
+    fn standalone_put_result_maps_statuses() {
+        let cases = [
+            (PutMessageStatus::PutOk, PutMessageStatus::PutOk),
+            (PutMessageStatus::FlushSlaveTimeout, PutMessageStatus::PutOk),
+            (PutMessageStatus::SlaveNotAvailable, PutMessageStatus::PutOk),
+            (
+                PutMessageStatus::FlushDiskTimeout,
+                PutMessageStatus::FlushDiskTimeout,
+            ),
+            (
+                PutMessageStatus::ServiceNotAvailable,
+                PutMessageStatus::ServiceNotAvailable,
+            ),
+            (
+                PutMessageStatus::MessageIllegal,
+                PutMessageStatus::MessageIllegal,
+            ),
+        ];
+        for (stored, expected) in cases {
+            let result = standalone_put_result(PutMessageResult::new_default(stored));
+            assert_eq!(result.put_message_status(), expected, "{:?}", stored);
+        }
+    }
+
+    #[test]
+    fn standalone_master_still_waits_for_the_flush() {
+        let broker_config = Arc::new(BrokerConfig {
+            enable_standalone_master_fast_path: true,
+            ..BrokerConfig::default()
+        });
+        let broker_outer_api =
+            Arc::new(BrokerOuterAPI::new(Arc::new(TokioClientConfig::default())));
+        let topic_route_info_manager = Arc::new(TopicRouteInfoManager::new(
+            broker_outer_api.clone(),
+            broker_config.clone(),
+        ));
+        let message_store = ArcMut::new(TestMessageStore::default());
+        message_store.push_put_statuses([PutMessageStatus::FlushSlaveTimeout]);
+        let mut escape_bridge =
+            EscapeBridge::new(broker_config, topic_route_info_manager, broker_outer_api);
+        escape_bridge.start(Some(message_store.clone()));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let result = runtime.block_on(escape_bridge.put_message(MessageExtBrokerInner::default()));
+
+        assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        assert!(message_store.put_messages()[0].is_wait_store_msg_ok());
+    }
 }
@@ -0,0 +1,205 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+
+/// Consumer offsets kept by the broker owning a queue.
+///
+/// A popped message may be escaped to another broker and acked there, while the queue and its
+/// offsets still belong to the broker named in the receipt handle.
+#[trait_variant::make(OriginOffsetStore: Send)]
+pub(crate) trait LocalOriginOffsetStore: Sync {
+    /// Returns the committed offset of the queue on `broker_name`, `None` if it has none yet.
+    async fn query_offset(
+        &self,
+        broker_name: &CheetahString,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+    ) -> crate::Result<Option<i64>>;
+
+    /// Commits `offset` for the queue on `broker_name`.
+    async fn commit_offset(
+        &self,
+        broker_name: &CheetahString,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+    ) -> crate::Result<()>;
+}
+
+/// Advances the offset on the origin broker of acked messages when it is not the local broker.
+///
+/// The origin offset is moved to the offset after the highest acked one and never moved back, as
+/// the origin may already have advanced past it. Returns the committed offset, `None` when there
+/// was nothing to reconcile.
+pub(crate) async fn reconcile_origin_offset<S>(
+    store: &S,
+    local_broker_name: &str,
+    origin_broker_name: &CheetahString,
+    group: &CheetahString,
+    topic: &CheetahString,
+    queue_id: i32,
+    acked_offsets: &[i64],
+) -> crate::Result<Option<i64>>
+where
+    S: OriginOffsetStore,
+{
+    if origin_broker_name.is_empty() || origin_broker_name.as_str() == local_broker_name {
+        return Ok(None);
+    }
+    let Some(commit_offset) = acked_offsets.iter().max().map(|offset| offset + 1) else {
+        return Ok(None);
+    };
+    let current_offset = store
+        .query_offset(origin_broker_name, group, topic, queue_id)
+        .await?;
+    if current_offset.is_some_and(|offset| offset >= commit_offset) {
+        return Ok(None);
+    }
+    store
+        .commit_offset(origin_broker_name, group, topic, queue_id, commit_offset)
+        .await?;
+    Ok(Some(commit_offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Offsets of a remote origin broker, keyed by broker name and queue.
+    #[derive(Default)]
+    struct FakeOriginBrokers {
+        offsets: Mutex<HashMap<(CheetahString, CheetahString, CheetahString, i32), i64>>,
+    }
+
+    impl FakeOriginBrokers {
+        fn set_offset(
+            &self,
+            broker_name: &str,
+            group: &str,
+            topic: &str,
+            queue_id: i32,
+            offset: i64,
+        ) {
+            self.offsets.lock().unwrap().insert(
+                (broker_name.into(), group.into(), topic.into(), queue_id),
+                offset,
+            );
+        }
+
+        fn offset(
+            &self,
+            broker_name: &str,
+            group: &str,
+            topic: &str,
+            queue_id: i32,
+        ) -> Option<i64> {
+            self.offsets
+                .lock()
+                .unwrap()
+                .get(&(broker_name.into(), group.into(), topic.into(), queue_id))
+                .copied()
+        }
+    }
+
+    impl OriginOffsetStore for FakeOriginBrokers {
+        async fn query_offset(
+            &self,
+            broker_name: &CheetahString,
+            group: &CheetahString,
+            topic: &CheetahString,
+            queue_id: i32,
+        ) -> crate::Result<Option<i64>> {
+            Ok(self.offset(broker_name, group, topic, queue_id))
+        }
+
+        async fn commit_offset(
+            &self,
+            broker_name: &CheetahString,
+            group: &CheetahString,
+            topic: &CheetahString,
+            queue_id: i32,
+            offset: i64,
+        ) -> crate::Result<()> {
+            self.offsets.lock().unwrap().insert(
+                (broker_name.clone(), group.clone(), topic.clone(), queue_id),
+                offset,
+            );
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn escaped_then_acked_message_advances_origin_offset() {
+        let origin = FakeOriginBrokers::default();
+        let origin_name = CheetahString::from_static_str("broker-a");
+        let group = CheetahString::from_static_str("group");
+        let topic = CheetahString::from_static_str("topic");
+        origin.set_offset("broker-a", "group", "topic", 1, 10);
+
+        // popped from broker-a, escaped to broker-b and acked there
+        let committed =
+            reconcile_origin_offset(&origin, "broker-b", &origin_name, &group, &topic, 1, &[12])
+                .await
+                .unwrap();
+        assert_eq!(committed, Some(13));
+        assert_eq!(origin.offset("broker-a", "group", "topic", 1), Some(13));
+    }
+
+    #[tokio::test]
+    async fn origin_offset_is_never_moved_back() {
+        let origin = FakeOriginBrokers::default();
+        let origin_name = CheetahString::from_static_str("broker-a");
+        let group = CheetahString::from_static_str("group");
+        let topic = CheetahString::from_static_str("topic");
+        origin.set_offset("broker-a", "group", "topic", 1, 20);
+
+        let committed =
+            reconcile_origin_offset(&origin, "broker-b", &origin_name, &group, &topic, 1, &[12])
+                .await
+                .unwrap();
+        assert_eq!(committed, None);
+        assert_eq!(origin.offset("broker-a", "group", "topic", 1), Some(20));
+    }
+
+    #[tokio::test]
+    async fn local_acks_are_not_reconciled() {
+        let origin = FakeOriginBrokers::default();
+        let group = CheetahString::from_static_str("group");
+        let topic = CheetahString::from_static_str("topic");
+
+        for broker_name in ["broker-a", ""] {
+            let committed = reconcile_origin_offset(
+                &origin,
+                "broker-a",
+                &CheetahString::from(broker_name),
+                &group,
+                &topic,
+                1,
+                &[12],
+            )
+            .await
+            .unwrap();
+            assert_eq!(committed, None);
+        }
+        assert_eq!(origin.offset("broker-a", "group", "topic", 1), None);
+    }
+}
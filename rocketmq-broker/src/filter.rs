@@ -19,3 +19,4 @@ pub(crate) mod consumer_filter_data;
 pub(crate) mod expression_for_retry_message_filter;
 pub(crate) mod expression_message_filter;
 pub(crate) mod manager;
+pub(crate) mod message_evaluation_context;
@@ -62,6 +62,12 @@ impl ConsumerFilterData {
         self.dead_time
     }
 
+    pub fn compiled_expression(&self) -> Option<&(dyn Expression + Send + Sync + 'static)> {
+        self.compiled_expression
+            .as_ref()
+            .map(|expression| expression.as_ref().as_ref())
+    }
+
     pub fn bloom_filter_data(&self) -> Option<&BloomFilterData> {
         self.bloom_filter_data.as_ref()
     }
@@ -94,6 +100,13 @@ impl ConsumerFilterData {
         self.dead_time = dead_time;
     }
 
+    pub fn set_compiled_expression(
+        &mut self,
+        compiled_expression: Option<Arc<Box<dyn Expression + Send + Sync + 'static>>>,
+    ) {
+        self.compiled_expression = compiled_expression;
+    }
+
     pub fn set_bloom_filter_data(&mut self, bloom_filter_data: Option<BloomFilterData>) {
         self.bloom_filter_data = bloom_filter_data;
     }
@@ -17,14 +17,19 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use bytes::Bytes;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::filter::expression_type::ExpressionType;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::MessageTrait;
 use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
 use rocketmq_store::consume_queue::consume_queue_ext::CqExtUnit;
 use rocketmq_store::filter::MessageFilter;
+use tracing::error;
 
 use crate::filter::consumer_filter_data::ConsumerFilterData;
 use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
+use crate::filter::message_evaluation_context::MessageEvaluationContext;
 
 pub struct ExpressionMessageFilter {
     subscription_data: Option<SubscriptionData>,
@@ -81,7 +86,9 @@ impl MessageFilter for ExpressionMessageFilter {
                 .code_set
                 .contains(&(tags_code.unwrap() as i32))
         } else {
-            unimplemented!("SQL92 expression type is not supported yet.")
+            // without a bloom filter hit test the consume queue can not tell, the expression
+            // is evaluated against the properties in the commit log
+            true
         }
     }
 
@@ -107,6 +114,42 @@ impl MessageFilter for ExpressionMessageFilter {
         if real_filter_data.expression().is_none() || real_filter_data.expression_type().is_none() {
             return true;
         }
-        unimplemented!("SQL92 expression type is not supported yet.")
+        let Some(compiled_expression) = real_filter_data.compiled_expression() else {
+            return true;
+        };
+        let decoded_properties;
+        let properties = match properties {
+            Some(properties) => properties,
+            None => {
+                decoded_properties = msg_buffer
+                    .and_then(|buffer| {
+                        message_decoder::decode(
+                            &mut Bytes::copy_from_slice(buffer),
+                            false,
+                            false,
+                            false,
+                            false,
+                            false,
+                        )
+                    })
+                    .map(|msg| msg.get_properties().clone())
+                    .unwrap_or_default();
+                &decoded_properties
+            }
+        };
+        // a property referenced by the expression but missing from the message evaluates to
+        // null, which is not a match
+        match compiled_expression.evaluate(&MessageEvaluationContext::new(properties)) {
+            Ok(result) => result.downcast_ref::<bool>().copied().unwrap_or(false),
+            Err(e) => {
+                error!(
+                    "evaluate expression of group {} failed, expression: {:?}, error: {}",
+                    real_filter_data.consumer_group(),
+                    real_filter_data.expression(),
+                    e
+                );
+                false
+            }
+        }
     }
 }
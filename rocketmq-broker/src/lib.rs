@@ -21,11 +21,13 @@
 
 pub use broker_bootstrap::BrokerBootstrap;
 pub use broker_bootstrap::Builder;
+pub use offset::manager::consumer_offset_backend::ConsumerOffsetBackend;
 
 use crate::broker_error::BrokerError;
 
 pub mod command;
 
+pub(crate) mod acl;
 pub(crate) mod broker;
 pub(crate) mod broker_bootstrap;
 pub(crate) mod broker_error;
@@ -43,6 +45,7 @@ pub(crate) mod mqtrace;
 pub(crate) mod offset;
 pub(crate) mod out_api;
 pub(crate) mod processor;
+pub(crate) mod proxy;
 pub(crate) mod schedule;
 pub(crate) mod subscription;
 pub(crate) mod topic;
@@ -15,3 +15,4 @@
  * limitations under the License.
  */
 pub(crate) mod message_request_mode_manager;
+pub(crate) mod pop_sticky_assignment_manager;
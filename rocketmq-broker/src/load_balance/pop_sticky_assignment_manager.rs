@@ -0,0 +1,416 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+
+struct QueueLease {
+    client_id: CheetahString,
+    expire_at: u64,
+}
+
+/// Keeps pop queue assignments sticky across successive assignment queries.
+///
+/// Every queue handed to a client is leased to it for `lease_millis`. While the lease is alive
+/// the queue is not handed to another client of the same group, and the owner gets it back on its
+/// next query (up to its fair share). A client that disconnects stops renewing, so its leases
+/// expire and the queues become assignable again. Acks of the owner on a leased queue renew the
+/// lease, so a queue is never moved away while its owner still acks inflight handles.
+pub(crate) struct PopStickyAssignmentManager {
+    lease_millis: u64,
+    lease_table: parking_lot::Mutex<
+        HashMap<CheetahString /* topic@group */, HashMap<MessageQueue, QueueLease>>,
+    >,
+}
+
+impl PopStickyAssignmentManager {
+    pub fn new(lease_millis: u64) -> Self {
+        Self {
+            lease_millis,
+            lease_table: parking_lot::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Turns the strategy allocation of `client_id` at `now` into a sticky one.
+    ///
+    /// The client keeps the queues it already leases, bounded by the size of `allocated`, then
+    /// takes queues from `allocated` and finally from `mq_all` that no other client leases.
+    /// Queues with a negative id (shared pop queues) are passed through untouched.
+    pub fn assign(
+        &self,
+        topic: &CheetahString,
+        consumer_group: &CheetahString,
+        client_id: &CheetahString,
+        allocated: HashSet<MessageQueue>,
+        mq_all: &[MessageQueue],
+        now: u64,
+    ) -> HashSet<MessageQueue> {
+        let key = Self::build_key(topic, consumer_group);
+        let mut lease_table = self.lease_table.lock();
+        let leases = lease_table.entry(key).or_default();
+        leases.retain(|_, lease| lease.expire_at > now);
+
+        let mut result = HashSet::with_capacity(allocated.len());
+        let mut allocated_queues = Vec::with_capacity(allocated.len());
+        for mq in allocated {
+            if mq.get_queue_id() < 0 {
+                result.insert(mq);
+            } else {
+                allocated_queues.push(mq);
+            }
+        }
+        allocated_queues.sort();
+        let quota = allocated_queues.len();
+
+        let mut owned = leases
+            .iter()
+            .filter(|(_, lease)| &lease.client_id == client_id)
+            .map(|(mq, _)| mq.clone())
+            .collect::<Vec<MessageQueue>>();
+        owned.sort();
+        let mut assigned = 0;
+        for mq in owned {
+            if assigned < quota && mq_all.contains(&mq) {
+                result.insert(mq);
+                assigned += 1;
+            } else {
+                leases.remove(&mq);
+            }
+        }
+
+        for mq in allocated_queues.iter().chain(mq_all.iter()) {
+            if assigned >= quota {
+                break;
+            }
+            if mq.get_queue_id() < 0 || leases.contains_key(mq) {
+                continue;
+            }
+            leases.insert(
+                mq.clone(),
+                QueueLease {
+                    client_id: client_id.clone(),
+                    expire_at: 0,
+                },
+            );
+            result.insert(mq.clone());
+            assigned += 1;
+        }
+
+        let expire_at = now + self.lease_millis;
+        for lease in leases.values_mut() {
+            if &lease.client_id == client_id {
+                lease.expire_at = expire_at;
+            }
+        }
+        result
+    }
+
+    /// Extends at `now` the lease of a queue acked by `client_id`, if it is still the owner.
+    pub fn renew(
+        &self,
+        consumer_group: &CheetahString,
+        mq: &MessageQueue,
+        client_id: &CheetahString,
+        now: u64,
+    ) {
+        let key = Self::build_key(mq.get_topic_cs(), consumer_group);
+        let mut lease_table = self.lease_table.lock();
+        if let Some(lease) = lease_table
+            .get_mut(&key)
+            .and_then(|leases| leases.get_mut(mq))
+        {
+            if &lease.client_id == client_id && lease.expire_at > now {
+                lease.expire_at = now + self.lease_millis;
+            }
+        }
+    }
+
+    /// Drops every lease held by `client_id`, making its queues assignable right away.
+    pub fn release_client(&self, client_id: &CheetahString) {
+        let mut lease_table = self.lease_table.lock();
+        for leases in lease_table.values_mut() {
+            leases.retain(|_, lease| &lease.client_id != client_id);
+        }
+        lease_table.retain(|_, leases| !leases.is_empty());
+    }
+
+    pub fn get_lease_owner(
+        &self,
+        consumer_group: &CheetahString,
+        mq: &MessageQueue,
+        now: u64,
+    ) -> Option<CheetahString> {
+        let key = Self::build_key(mq.get_topic_cs(), consumer_group);
+        let lease_table = self.lease_table.lock();
+        lease_table
+            .get(&key)
+            .and_then(|leases| leases.get(mq))
+            .filter(|lease| lease.expire_at > now)
+            .map(|lease| lease.client_id.clone())
+    }
+
+    fn build_key(topic: &CheetahString, consumer_group: &CheetahString) -> CheetahString {
+        CheetahString::from_string(format!("{}@{}", topic, consumer_group))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queues(ids: &[i32]) -> Vec<MessageQueue> {
+        ids.iter()
+            .map(|id| MessageQueue::from_parts("test_topic", "test_broker", *id))
+            .collect()
+    }
+
+    fn queue(id: i32) -> MessageQueue {
+        MessageQueue::from_parts("test_topic", "test_broker", id)
+    }
+
+    fn queue_ids(assigned: &HashSet<MessageQueue>) -> Vec<i32> {
+        let mut ids = assigned
+            .iter()
+            .map(|mq| mq.get_queue_id())
+            .collect::<Vec<i32>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn assign_keeps_previously_leased_queues() {
+        let manager = PopStickyAssignmentManager::new(60_000);
+        let topic = CheetahString::from_static_str("test_topic");
+        let group = CheetahString::from_static_str("test_group");
+        let client = CheetahString::from_static_str("client_a");
+        let mq_all = queues(&[0, 1, 2, 3]);
+
+        let first = manager.assign(
+            &topic,
+            &group,
+            &client,
+            queues(&[0, 1]).into_iter().collect(),
+            &mq_all,
+            0,
+        );
+        assert_eq!(queue_ids(&first), vec![0, 1]);
+
+        // the strategy now proposes other queues, the client keeps its leased ones
+        let second = manager.assign(
+            &topic,
+            &group,
+            &client,
+            queues(&[2, 3]).into_iter().collect(),
+            &mq_all,
+            0,
+        );
+        assert_eq!(queue_ids(&second), vec![0, 1]);
+    }
+
+    #[test]
+    fn assign_skips_queues_leased_by_other_clients() {
+        let manager = PopStickyAssignmentManager::new(60_000);
+        let topic = CheetahString::from_static_str("test_topic");
+        let group = CheetahString::from_static_str("test_group");
+        let client_a = CheetahString::from_static_str("client_a");
+        let client_b = CheetahString::from_static_str("client_b");
+        let mq_all = queues(&[0, 1, 2, 3]);
+
+        manager.assign(
+            &topic,
+            &group,
+            &client_a,
+            queues(&[0, 1]).into_iter().collect(),
+            &mq_all,
+            0,
+        );
+        let assigned = manager.assign(
+            &topic,
+            &group,
+            &client_b,
+            queues(&[1, 2]).into_iter().collect(),
+            &mq_all,
+            0,
+        );
+        assert_eq!(queue_ids(&assigned), vec![2, 3]);
+        assert_eq!(
+            manager.get_lease_owner(&group, &queue(1), 0),
+            Some(client_a.clone())
+        );
+    }
+
+    #[test]
+    fn expired_lease_makes_queue_reassignable() {
+        let manager = PopStickyAssignmentManager::new(10);
+        let topic = CheetahString::from_static_str("test_topic");
+        let group = CheetahString::from_static_str("test_group");
+        let client_a = CheetahString::from_static_str("client_a");
+        let client_b = CheetahString::from_static_str("client_b");
+        let mq_all = queues(&[0]);
+
+        manager.assign(
+            &topic,
+            &group,
+            &client_a,
+            queues(&[0]).into_iter().collect(),
+            &mq_all,
+            0,
+        );
+        let assigned = manager.assign(
+            &topic,
+            &group,
+            &client_b,
+            queues(&[0]).into_iter().collect(),
+            &mq_all,
+            0,
+        );
+        assert!(assigned.is_empty());
+
+        assert_eq!(manager.get_lease_owner(&group, &queue(0), 10), None);
+        let assigned = manager.assign(
+            &topic,
+            &group,
+            &client_b,
+            queues(&[0]).into_iter().collect(),
+            &mq_all,
+            10,
+        );
+        assert_eq!(queue_ids(&assigned), vec![0]);
+    }
+
+    #[test]
+    fn release_client_frees_its_queues() {
+        let manager = PopStickyAssignmentManager::new(60_000);
+        let topic = CheetahString::from_static_str("test_topic");
+        let group = CheetahString::from_static_str("test_group");
+        let client = CheetahString::from_static_str("client_a");
+        let mq_all = queues(&[0, 1]);
+
+        manager.assign(
+            &topic,
+            &group,
+            &client,
+            queues(&[0, 1]).into_iter().collect(),
+            &mq_all,
+            0,
+        );
+        manager.release_client(&client);
+        assert_eq!(manager.get_lease_owner(&group, &queue(0), 0), None);
+        assert_eq!(manager.get_lease_owner(&group, &queue(1), 0), None);
+    }
+
+    #[test]
+    fn renew_extends_live_lease() {
+        let manager = PopStickyAssignmentManager::new(500);
+        let topic = CheetahString::from_static_str("test_topic");
+        let group = CheetahString::from_static_str("test_group");
+        let client = CheetahString::from_static_str("client_a");
+        let mq_all = queues(&[0]);
+
+        manager.assign(
+            &topic,
+            &group,
+            &client,
+            queues(&[0]).into_iter().collect(),
+            &mq_all,
+            0,
+        );
+        manager.renew(&group, &queue(0), &client, 300);
+        assert_eq!(
+            manager.get_lease_owner(&group, &queue(0), 600),
+            Some(client)
+        );
+    }
+
+    #[test]
+    fn renew_ignores_acks_of_other_clients() {
+        let manager = PopStickyAssignmentManager::new(500);
+        let topic = CheetahString::from_static_str("test_topic");
+        let group = CheetahString::from_static_str("test_group");
+        let client_a = CheetahString::from_static_str("client_a");
+        let client_b = CheetahString::from_static_str("client_b");
+        let mq_all = queues(&[0]);
+
+        manager.assign(
+            &topic,
+            &group,
+            &client_a,
+            queues(&[0]).into_iter().collect(),
+            &mq_all,
+            0,
+        );
+        manager.renew(&group, &queue(0), &client_b, 300);
+        assert_eq!(manager.get_lease_owner(&group, &queue(0), 600), None);
+    }
+
+    #[test]
+    fn shared_queues_are_passed_through() {
+        let manager = PopStickyAssignmentManager::new(60_000);
+        let topic = CheetahString::from_static_str("test_topic");
+        let group = CheetahString::from_static_str("test_group");
+        let client = CheetahString::from_static_str("client_a");
+
+        let assigned = manager.assign(
+            &topic,
+            &group,
+            &client,
+            queues(&[-1]).into_iter().collect(),
+            &queues(&[0, 1]),
+            0,
+        );
+        assert_eq!(queue_ids(&assigned), vec![-1]);
+    }
+
+    #[test]
+    fn same_queue_id_on_other_broker_is_leased_independently() {
+        let manager = PopStickyAssignmentManager::new(60_000);
+        let topic = CheetahString::from_static_str("test_topic");
+        let group = CheetahString::from_static_str("test_group");
+        let client_a = CheetahString::from_static_str("client_a");
+        let client_b = CheetahString::from_static_str("client_b");
+        let other_broker_queue = MessageQueue::from_parts("test_topic", "other_broker", 0);
+        let mq_all = vec![queue(0), other_broker_queue.clone()];
+
+        manager.assign(
+            &topic,
+            &group,
+            &client_a,
+            [queue(0)].into_iter().collect(),
+            &mq_all,
+            0,
+        );
+        let assigned = manager.assign(
+            &topic,
+            &group,
+            &client_b,
+            [other_broker_queue.clone()].into_iter().collect(),
+            &mq_all,
+            0,
+        );
+        assert_eq!(assigned, [other_broker_queue.clone()].into_iter().collect());
+        assert_eq!(
+            manager.get_lease_owner(&group, &queue(0), 0),
+            Some(client_a)
+        );
+        assert_eq!(
+            manager.get_lease_owner(&group, &other_broker_queue, 0),
+            Some(client_b)
+        );
+    }
+}
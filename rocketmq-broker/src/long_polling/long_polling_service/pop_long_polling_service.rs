@@ -0,0 +1,306 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Long polling of pops: a pop finding no message is held until messages arrive in the queues
+//! it pops or its poll time is over, sparing consumers from popping empty queues again and
+//! again.
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
+use tokio::sync::Mutex;
+use tracing::info;
+
+const TOPIC_QUEUE_ID_SEPARATOR: &str = "@";
+
+/// Interval in milliseconds between two scans for the pops whose poll time is over.
+const EXPIRED_SCAN_INTERVAL_MILLIS: u64 = 20;
+
+/// A pop request held until messages arrive or until `expired`.
+pub(crate) struct PopRequest {
+    request: RemotingCommand,
+    channel: Channel,
+    ctx: ConnectionHandlerContext,
+    expired: u64,
+}
+
+impl PopRequest {
+    pub(crate) fn new(
+        request: RemotingCommand,
+        channel: Channel,
+        ctx: ConnectionHandlerContext,
+        expired: u64,
+    ) -> Self {
+        PopRequest {
+            request,
+            channel,
+            ctx,
+            expired,
+        }
+    }
+
+    pub(crate) fn request(&self) -> &RemotingCommand {
+        &self.request
+    }
+
+    pub(crate) fn channel(&self) -> &Channel {
+        &self.channel
+    }
+
+    pub(crate) fn ctx(&self) -> &ConnectionHandlerContext {
+        &self.ctx
+    }
+
+    pub(crate) fn is_expired(&self, now: u64) -> bool {
+        now >= self.expired
+    }
+}
+
+/// Holds the pops that found no message, by the topic and the queue they pop. A pop of every
+/// queue of a topic is held under the queue id -1 and woken by an arrival in any of them.
+pub(crate) struct PopLongPollingService {
+    polling_map: parking_lot::Mutex<HashMap<String, VecDeque<PopRequest>>>,
+    /// The most pops held for a queue.
+    polling_size: usize,
+    /// Serializes the answers written to a connection, by channel id.
+    write_locks: parking_lot::Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+impl PopLongPollingService {
+    pub(crate) fn new(polling_size: usize) -> Self {
+        PopLongPollingService {
+            polling_map: parking_lot::Mutex::new(HashMap::new()),
+            polling_size,
+            write_locks: parking_lot::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Holds `pop_request` on `queue_id` of `topic`, returns false when the queue holds too
+    /// many pops already.
+    pub(crate) fn polling(&self, topic: &str, queue_id: i32, pop_request: PopRequest) -> bool {
+        let mut polling_map = self.polling_map.lock();
+        let requests = polling_map.entry(build_key(topic, queue_id)).or_default();
+        if requests.len() >= self.polling_size {
+            return false;
+        }
+        requests.push_back(pop_request);
+        true
+    }
+
+    /// Takes the pops to wake as messages arrived in `queue_id` of `topic`, the pops of the
+    /// queue and the pops of every queue of the topic.
+    pub(crate) fn notify_message_arriving(&self, topic: &str, queue_id: i32) -> Vec<PopRequest> {
+        let mut polling_map = self.polling_map.lock();
+        [build_key(topic, queue_id), build_key(topic, -1)]
+            .iter()
+            .filter_map(|key| polling_map.remove(key))
+            .flatten()
+            .collect()
+    }
+
+    /// Takes the pops whose poll time is over at `now`.
+    pub(crate) fn take_expired(&self, now: u64) -> Vec<PopRequest> {
+        let mut polling_map = self.polling_map.lock();
+        let mut expired = Vec::new();
+        for requests in polling_map.values_mut() {
+            let (timed_out, waiting) = requests
+                .drain(..)
+                .partition::<VecDeque<_>, _>(|request| request.is_expired(now));
+            *requests = waiting;
+            expired.extend(timed_out);
+        }
+        polling_map.retain(|_, requests| !requests.is_empty());
+        expired
+    }
+
+    /// Number of pops held.
+    pub(crate) fn polling_num(&self) -> usize {
+        self.polling_map.lock().values().map(VecDeque::len).sum()
+    }
+
+    /// Answers the held pop with `response`, if its connection is still open.
+    pub(crate) async fn write_response(&self, pop_request: &PopRequest, response: RemotingCommand) {
+        let Some(mut ctx) = pop_request.ctx.upgrade() else {
+            return;
+        };
+        let response = response
+            .set_opaque(pop_request.request.opaque())
+            .mark_response_type();
+        let channel_id = pop_request.channel.channel_id();
+        let write_lock = self
+            .write_locks
+            .lock()
+            .entry(channel_id.to_string())
+            .or_default()
+            .clone();
+        let guard = write_lock.lock().await;
+        ctx.write(response).await;
+        drop(guard);
+        let mut write_locks = self.write_locks.lock();
+        // only the table and this answer hold the lock, no other answer waits for it
+        if Arc::strong_count(&write_lock) == 2 {
+            write_locks.remove(channel_id);
+        }
+    }
+
+    /// Answers the pops whose poll time is over that no message arrived for.
+    pub(crate) fn start(self: Arc<Self>) {
+        tokio::spawn(async move {
+            info!("PopLongPollingService started");
+            loop {
+                tokio::time::sleep(tokio::time::Duration::from_millis(
+                    EXPIRED_SCAN_INTERVAL_MILLIS,
+                ))
+                .await;
+                for pop_request in self.take_expired(get_current_millis()) {
+                    self.write_response(
+                        &pop_request,
+                        RemotingCommand::create_response_command_with_code_remark(
+                            ResponseCode::PollingTimeout,
+                            "no new message, pop again later",
+                        ),
+                    )
+                    .await;
+                }
+            }
+        });
+    }
+}
+
+fn build_key(topic: &str, queue_id: i32) -> String {
+    format!("{}{}{}", topic, TOPIC_QUEUE_ID_SEPARATOR, queue_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use cheetah_string::CheetahString;
+    use rocketmq_remoting::code::request_code::RequestCode;
+    use rocketmq_remoting::protocol::header::pop_message_request_header::PopMessageRequestHeader;
+    use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContextWrapper;
+    use rocketmq_rust::ArcMut;
+
+    use super::*;
+    use crate::util::test_channel::test_channel;
+
+    fn pop_request_command(queue_id: i32) -> RemotingCommand {
+        let mut request = RemotingCommand::create_request_command(
+            RequestCode::PopMessage,
+            PopMessageRequestHeader {
+                topic: CheetahString::from_static_str("PollingTopic"),
+                queue_id,
+                ..Default::default()
+            },
+        );
+        request.make_custom_header_to_net();
+        request
+    }
+
+    async fn pop_request(queue_id: i32, expired: u64) -> PopRequest {
+        let channel = test_channel().await;
+        // the connection is gone, nothing is written to it
+        let ctx = ArcMut::downgrade(&ArcMut::new(ConnectionHandlerContextWrapper::new(
+            channel.clone(),
+        )));
+        PopRequest::new(pop_request_command(queue_id), channel, ctx, expired)
+    }
+
+    fn queue_ids(requests: &[PopRequest]) -> Vec<i32> {
+        let mut queue_ids: Vec<i32> = requests
+            .iter()
+            .map(|request| {
+                request
+                    .request()
+                    .decode_command_custom_header::<PopMessageRequestHeader>()
+                    .unwrap()
+                    .queue_id
+            })
+            .collect();
+        queue_ids.sort();
+        queue_ids
+    }
+
+    #[tokio::test]
+    async fn arriving_message_wakes_the_pops_of_its_queue() {
+        let service = PopLongPollingService::new(16);
+        let expired = get_current_millis() + 60_000;
+        for queue_id in [-1, 0, 1] {
+            assert!(service.polling(
+                "PollingTopic",
+                queue_id,
+                pop_request(queue_id, expired).await
+            ));
+        }
+
+        let woken = service.notify_message_arriving("PollingTopic", 1);
+        assert_eq!(queue_ids(&woken), vec![-1, 1]);
+        assert_eq!(service.polling_num(), 1);
+        assert!(service.notify_message_arriving("OtherTopic", 0).is_empty());
+        assert_eq!(
+            queue_ids(&service.notify_message_arriving("PollingTopic", 0)),
+            vec![0]
+        );
+        assert_eq!(service.polling_num(), 0);
+    }
+
+    #[tokio::test]
+    async fn pops_whose_poll_time_is_over_are_taken() {
+        let service = PopLongPollingService::new(16);
+        let now = get_current_millis();
+        assert!(service.polling("PollingTopic", 0, pop_request(0, now + 100).await));
+        assert!(service.polling("PollingTopic", 1, pop_request(1, now + 60_000).await));
+
+        assert!(service.take_expired(now).is_empty());
+        assert_eq!(queue_ids(&service.take_expired(now + 100)), vec![0]);
+        assert_eq!(service.polling_num(), 1);
+    }
+
+    #[tokio::test]
+    async fn queue_holds_no_more_pops_than_its_polling_size() {
+        let service = PopLongPollingService::new(2);
+        let expired = get_current_millis() + 60_000;
+        assert!(service.polling("PollingTopic", 0, pop_request(0, expired).await));
+        assert!(service.polling("PollingTopic", 0, pop_request(0, expired).await));
+        assert!(!service.polling("PollingTopic", 0, pop_request(0, expired).await));
+        assert!(service.polling("PollingTopic", 1, pop_request(1, expired).await));
+    }
+
+    #[tokio::test]
+    async fn answered_pops_leave_no_write_lock_behind() {
+        let service = PopLongPollingService::new(16);
+        let channel = test_channel().await;
+        let ctx = ArcMut::new(ConnectionHandlerContextWrapper::new(channel.clone()));
+        let first = PopRequest::new(
+            pop_request_command(0),
+            channel.clone(),
+            ArcMut::downgrade(&ctx),
+            0,
+        );
+        let second = PopRequest::new(pop_request_command(1), channel, ArcMut::downgrade(&ctx), 0);
+
+        tokio::join!(
+            service.write_response(&first, RemotingCommand::create_response_command()),
+            service.write_response(&second, RemotingCommand::create_response_command()),
+        );
+
+        assert!(service.write_locks.lock().is_empty());
+    }
+}
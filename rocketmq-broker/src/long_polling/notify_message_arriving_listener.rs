@@ -22,18 +22,24 @@ use rocketmq_store::base::message_arriving_listener::MessageArrivingListener;
 use rocketmq_store::log_file::MessageStore;
 
 use crate::long_polling::long_polling_service::pull_request_hold_service::PullRequestHoldService;
+use crate::processor::pop_message_processor::PopMessageProcessor;
 
 pub struct NotifyMessageArrivingListener<MS> {
     pull_request_hold_service: ArcMut<PullRequestHoldService<MS>>,
+    pop_message_processor: ArcMut<PopMessageProcessor<MS>>,
 }
 
 impl<MS> NotifyMessageArrivingListener<MS>
 where
     MS: MessageStore + Send + Sync,
 {
-    pub fn new(pull_request_hold_service: ArcMut<PullRequestHoldService<MS>>) -> Self {
+    pub fn new(
+        pull_request_hold_service: ArcMut<PullRequestHoldService<MS>>,
+        pop_message_processor: ArcMut<PopMessageProcessor<MS>>,
+    ) -> Self {
         Self {
             pull_request_hold_service,
+            pop_message_processor,
         }
     }
 }
@@ -62,5 +68,6 @@ where
             filter_bit_map,
             properties,
         );
+        PopMessageProcessor::notify_message_arriving(&self.pop_message_processor, topic, queue_id);
     }
 }
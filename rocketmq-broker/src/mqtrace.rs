@@ -15,6 +15,9 @@
  * limitations under the License.
  */
 
+pub(crate) mod ack_message_context;
+pub(crate) mod ack_message_hook;
+pub(crate) mod broker_trace_hook;
 pub(crate) mod consume_message_context;
 pub(crate) mod consume_message_hook;
 pub(crate) mod send_message_context;
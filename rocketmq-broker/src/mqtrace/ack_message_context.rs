@@ -0,0 +1,33 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+
+/// An ack persisted by the ack processor, see
+/// [`AckMessageHook`](crate::mqtrace::ack_message_hook::AckMessageHook).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AckMessageContext {
+    pub consumer_group: CheetahString,
+    pub topic: CheetahString,
+    pub queue_id: i32,
+    /// First queue offset acked, the only one of a single ack.
+    pub ack_offset: i64,
+    /// Number of queue offsets acked, more than one for a batch ack.
+    pub ack_count: usize,
+    /// Offset the ack was stored at in its revive queue, `None` when it was merged into its
+    /// buffered checkpoint or stored by another broker.
+    pub revive_queue_offset: Option<i64>,
+}
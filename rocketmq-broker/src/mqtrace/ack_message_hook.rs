@@ -0,0 +1,35 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::mqtrace::ack_message_context::AckMessageContext;
+
+/// Observes the acks persisted by the ack processor, to audit them or derive metrics of them.
+///
+/// # Requirements
+///
+/// Implementors must be thread-safe (`Sync + Send`) and support static lifetimes (`'static`).
+pub trait AckMessageHook: Sync + Send + 'static {
+    /// Returns the name of the hook.
+    fn hook_name(&self) -> &str;
+
+    /// Called once an ack is persisted: stored to the revive topic, or merged into its buffered
+    /// checkpoint and written to the write-ahead log of the buffer. It runs on the ack path,
+    /// and must not block.
+    ///
+    /// # Arguments
+    /// * `context` - The ack persisted.
+    fn ack_persisted(&self, context: &AckMessageContext);
+}
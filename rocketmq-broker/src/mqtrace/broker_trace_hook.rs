@@ -0,0 +1,408 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Broker side message trace: the receive, the store and the delivery of the messages whose
+//! trace switch is not turned off are written to the trace topic, letting the flow of a
+//! message be rebuilt along with the traces of its clients.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use cheetah_string::CheetahString;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::base::message_status_enum::PutMessageStatus;
+use rocketmq_store::log_file::MessageStore;
+use tokio::sync::mpsc;
+use tracing::info;
+use tracing::warn;
+
+use crate::failover::escape_bridge::EscapeBridge;
+use crate::mqtrace::consume_message_context::ConsumeMessageContext;
+use crate::mqtrace::consume_message_hook::ConsumeMessageHook;
+use crate::mqtrace::send_message_context::SendMessageContext;
+use crate::mqtrace::send_message_hook::SendMessageHook;
+
+const CONTENT_SPLITOR: char = '\u{0001}';
+const FIELD_SPLITOR: char = '\u{0002}';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BrokerTraceType {
+    /// A producer sent the message to the broker.
+    Receive,
+    /// The broker stored the message, or failed to.
+    Store,
+    /// The broker delivered the message to a consumer.
+    Deliver,
+}
+
+impl Display for BrokerTraceType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BrokerTraceType::Receive => write!(f, "Receive"),
+            BrokerTraceType::Store => write!(f, "Store"),
+            BrokerTraceType::Deliver => write!(f, "Deliver"),
+        }
+    }
+}
+
+/// One trace event of a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BrokerTraceEntry {
+    pub(crate) trace_type: BrokerTraceType,
+    pub(crate) timestamp: u64,
+    /// Producer group of a receive or a store, consumer group of a delivery.
+    pub(crate) group: CheetahString,
+    pub(crate) topic: CheetahString,
+    pub(crate) msg_id: CheetahString,
+    pub(crate) tags: CheetahString,
+    pub(crate) keys: CheetahString,
+    /// Address of the producer or of the consumer.
+    pub(crate) client_host: CheetahString,
+    pub(crate) queue_id: i32,
+    /// -1 until the message is stored.
+    pub(crate) queue_offset: i64,
+    pub(crate) body_length: i32,
+    pub(crate) success: bool,
+}
+
+impl BrokerTraceEntry {
+    /// Encodes the entry as the clients encode their traces: content separated fields ended
+    /// by a field separator.
+    pub(crate) fn encode(&self) -> String {
+        let fields = [
+            self.trace_type.to_string(),
+            self.timestamp.to_string(),
+            self.group.to_string(),
+            self.topic.to_string(),
+            self.msg_id.to_string(),
+            self.tags.to_string(),
+            self.keys.to_string(),
+            self.client_host.to_string(),
+            self.queue_id.to_string(),
+            self.queue_offset.to_string(),
+            self.body_length.to_string(),
+            self.success.to_string(),
+        ];
+        let mut encoded = fields.join(&CONTENT_SPLITOR.to_string());
+        encoded.push(FIELD_SPLITOR);
+        encoded
+    }
+}
+
+/// Whether a message with `properties` is traced, every message is but the ones whose trace
+/// switch is turned off.
+pub(crate) fn is_traced(properties: &HashMap<CheetahString, CheetahString>) -> bool {
+    !matches!(
+        properties
+            .get(MessageConst::PROPERTY_TRACE_SWITCH)
+            .map(CheetahString::as_str),
+        Some("false")
+    )
+}
+
+/// Send and consume hook queueing the trace entries of traced messages to a
+/// [`BrokerTraceDispatcher`].
+#[derive(Clone)]
+pub(crate) struct BrokerTraceHook {
+    sender: mpsc::UnboundedSender<BrokerTraceEntry>,
+}
+
+impl BrokerTraceHook {
+    fn send_entry(&self, context: &SendMessageContext, trace_type: BrokerTraceType) {
+        let properties = message_decoder::string_to_message_properties(Some(&context.msg_props));
+        if !is_traced(&properties) {
+            return;
+        }
+        let property = |name: &str| properties.get(name).cloned().unwrap_or_default();
+        let (msg_id, queue_offset, success) = match trace_type {
+            BrokerTraceType::Receive => (
+                property(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX),
+                -1,
+                true,
+            ),
+            _ => (
+                context.msg_id.clone(),
+                context.queue_offset.unwrap_or(-1),
+                context.is_success,
+            ),
+        };
+        self.trace(BrokerTraceEntry {
+            trace_type,
+            timestamp: get_current_millis(),
+            group: context.producer_group.clone(),
+            topic: context.topic.clone(),
+            msg_id,
+            tags: property(MessageConst::PROPERTY_TAGS),
+            keys: property(MessageConst::PROPERTY_KEYS),
+            client_host: context.born_host.clone(),
+            queue_id: context.queue_id.unwrap_or(-1),
+            queue_offset,
+            body_length: context.body_length,
+            success,
+        });
+    }
+
+    fn trace(&self, entry: BrokerTraceEntry) {
+        if self.sender.send(entry).is_err() {
+            warn!("Broker trace dispatcher stopped, trace entry dropped");
+        }
+    }
+}
+
+impl SendMessageHook for BrokerTraceHook {
+    fn hook_name(&self) -> &str {
+        "BrokerTraceHook"
+    }
+
+    fn send_message_before(&self, context: &SendMessageContext) {
+        self.send_entry(context, BrokerTraceType::Receive);
+    }
+
+    fn send_message_after(&self, context: &SendMessageContext) {
+        self.send_entry(context, BrokerTraceType::Store);
+    }
+}
+
+impl ConsumeMessageHook for BrokerTraceHook {
+    fn hook_name(&self) -> &str {
+        "BrokerTraceHook"
+    }
+
+    fn consume_message_before(&self, context: &mut ConsumeMessageContext) {
+        // the ids of the traced messages found for the consumer, with their queue offsets
+        let timestamp = get_current_millis();
+        for (msg_id, queue_offset) in &context.message_ids {
+            self.trace(BrokerTraceEntry {
+                trace_type: BrokerTraceType::Deliver,
+                timestamp,
+                group: context.consumer_group.clone(),
+                topic: context.topic.clone(),
+                msg_id: CheetahString::from_string(msg_id.clone()),
+                tags: CheetahString::empty(),
+                keys: CheetahString::empty(),
+                client_host: context.client_host.clone(),
+                queue_id: context.queue_id.unwrap_or(-1),
+                queue_offset: *queue_offset,
+                body_length: 0,
+                success: true,
+            });
+        }
+    }
+
+    fn consume_message_after(&self, _context: &mut ConsumeMessageContext) {}
+}
+
+/// Writes the trace entries queued by its [`BrokerTraceHook`] to the trace topic.
+pub(crate) struct BrokerTraceDispatcher<MS> {
+    broker_config: Arc<BrokerConfig>,
+    escape_bridge: ArcMut<EscapeBridge<MS>>,
+    store_host: SocketAddr,
+    receiver: mpsc::UnboundedReceiver<BrokerTraceEntry>,
+}
+
+impl<MS> BrokerTraceDispatcher<MS>
+where
+    MS: MessageStore,
+{
+    pub(crate) fn new(
+        broker_config: Arc<BrokerConfig>,
+        escape_bridge: ArcMut<EscapeBridge<MS>>,
+        store_host: SocketAddr,
+    ) -> (BrokerTraceHook, Self) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (
+            BrokerTraceHook { sender },
+            BrokerTraceDispatcher {
+                broker_config,
+                escape_bridge,
+                store_host,
+                receiver,
+            },
+        )
+    }
+
+    /// Writes the queued entries until every hook is dropped.
+    pub(crate) fn start(mut self) {
+        tokio::spawn(async move {
+            info!("BrokerTraceDispatcher started");
+            while let Some(entry) = self.receiver.recv().await {
+                self.dispatch(entry).await;
+            }
+        });
+    }
+
+    async fn dispatch(&mut self, entry: BrokerTraceEntry) {
+        let inner = self.build_trace_msg(&entry);
+        let put_message_result = self.escape_bridge.put_message(inner).await;
+        if put_message_result.put_message_status() != PutMessageStatus::PutOk {
+            warn!(
+                "Write trace of message {} failed, status={:?}",
+                entry.msg_id,
+                put_message_result.put_message_status()
+            );
+        }
+    }
+
+    fn build_trace_msg(&self, entry: &BrokerTraceEntry) -> MessageExtBrokerInner {
+        let mut inner = MessageExtBrokerInner::default();
+        inner.set_topic(self.broker_config.msg_trace_topic_name.clone());
+        inner.set_body(Bytes::from(entry.encode()));
+        inner.message_ext_inner.queue_id = 0;
+        // traces are looked up by the id and the topic of their message
+        inner.set_keys(CheetahString::from_string(format!(
+            "{} {}",
+            entry.msg_id, entry.topic
+        )));
+        inner.message_ext_inner.born_timestamp = get_current_millis() as i64;
+        inner.message_ext_inner.born_host = self.store_host;
+        inner.message_ext_inner.store_host = self.store_host;
+        inner.properties_string =
+            message_decoder::message_properties_to_string(inner.get_properties());
+        inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::topic::TopicValidator;
+    use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
+
+    use super::*;
+    use crate::out_api::broker_outer_api::BrokerOuterAPI;
+    use crate::topic::manager::topic_route_info_manager::TopicRouteInfoManager;
+    use crate::util::test_message_store::TestMessageStore;
+
+    const TEST_TOPIC: &str = "TraceTestTopic";
+
+    fn send_context(uniq_key: &str, trace_on: bool) -> SendMessageContext {
+        let mut properties = HashMap::new();
+        properties.insert(
+            CheetahString::from_static_str(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX),
+            CheetahString::from(uniq_key),
+        );
+        properties.insert(
+            CheetahString::from_static_str(MessageConst::PROPERTY_TAGS),
+            CheetahString::from_static_str("TagA"),
+        );
+        properties.insert(
+            CheetahString::from_static_str(MessageConst::PROPERTY_TRACE_SWITCH),
+            CheetahString::from(trace_on.to_string()),
+        );
+        let mut context = SendMessageContext::new();
+        context.producer_group = CheetahString::from_static_str("TraceProducerGroup");
+        context.topic = CheetahString::from_static_str(TEST_TOPIC);
+        context.msg_id = CheetahString::from_static_str("7F00000100002A9F0000000000000000");
+        context.queue_id = Some(1);
+        context.queue_offset = Some(8);
+        context.born_host = CheetahString::from_static_str("127.0.0.1:50000");
+        context.body_length = 16;
+        context.is_success = true;
+        context.msg_props = message_decoder::message_properties_to_string(&properties);
+        context
+    }
+
+    fn trace_fields(
+        msg: &rocketmq_common::common::message::message_ext::MessageExt,
+    ) -> Vec<String> {
+        let body = String::from_utf8(msg.get_body().unwrap().to_vec()).unwrap();
+        body.trim_end_matches(FIELD_SPLITOR)
+            .split(CONTENT_SPLITOR)
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn traced_message_writes_its_receive_store_and_deliver_traces() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let _guard = runtime.enter();
+        let broker_config = Arc::new(BrokerConfig::default());
+        let broker_outer_api =
+            Arc::new(BrokerOuterAPI::new(Arc::new(TokioClientConfig::default())));
+        let topic_route_info_manager = Arc::new(TopicRouteInfoManager::new(
+            broker_outer_api.clone(),
+            broker_config.clone(),
+        ));
+        let message_store = ArcMut::new(TestMessageStore::default());
+        let mut escape_bridge = ArcMut::new(EscapeBridge::new(
+            broker_config.clone(),
+            topic_route_info_manager,
+            broker_outer_api,
+        ));
+        escape_bridge.start(Some(message_store.clone()));
+        let (hook, mut dispatcher) = BrokerTraceDispatcher::new(
+            broker_config,
+            escape_bridge,
+            "127.0.0.1:10911".parse().unwrap(),
+        );
+
+        let traced = send_context("TRACED_UNIQ_KEY", true);
+        hook.send_message_before(&traced);
+        hook.send_message_after(&traced);
+        let untraced = send_context("UNTRACED_UNIQ_KEY", false);
+        hook.send_message_before(&untraced);
+        hook.send_message_after(&untraced);
+        let mut consume_context = ConsumeMessageContext {
+            consumer_group: CheetahString::from_static_str("TraceConsumerGroup"),
+            topic: CheetahString::from_static_str(TEST_TOPIC),
+            queue_id: Some(1),
+            client_host: CheetahString::from_static_str("127.0.0.1:50001"),
+            message_ids: HashMap::from([("TRACED_UNIQ_KEY".to_string(), 8)]),
+            ..Default::default()
+        };
+        ConsumeMessageHook::consume_message_before(&hook, &mut consume_context);
+        runtime.block_on(async {
+            while let Ok(entry) = dispatcher.receiver.try_recv() {
+                dispatcher.dispatch(entry).await;
+            }
+        });
+
+        let traces = message_store.put_messages_of(TopicValidator::RMQ_SYS_TRACE_TOPIC);
+        assert_eq!(traces.len(), 3);
+        let receive = trace_fields(&traces[0]);
+        assert_eq!(receive[0], "Receive");
+        assert_eq!(receive[2], "TraceProducerGroup");
+        assert_eq!(receive[3], TEST_TOPIC);
+        assert_eq!(receive[4], "TRACED_UNIQ_KEY");
+        assert_eq!(receive[5], "TagA");
+        assert_eq!(receive[7], "127.0.0.1:50000");
+        let store = trace_fields(&traces[1]);
+        assert_eq!(store[0], "Store");
+        assert_eq!(store[4], "7F00000100002A9F0000000000000000");
+        assert_eq!(store[8..], ["1", "8", "16", "true"]);
+        let deliver = trace_fields(&traces[2]);
+        assert_eq!(deliver[0], "Deliver");
+        assert_eq!(deliver[2], "TraceConsumerGroup");
+        assert_eq!(deliver[4], "TRACED_UNIQ_KEY");
+        assert_eq!(deliver[7], "127.0.0.1:50001");
+        assert_eq!(deliver[9], "8");
+        assert_eq!(
+            traces[0].get_keys().unwrap().as_str(),
+            format!("TRACED_UNIQ_KEY {TEST_TOPIC}")
+        );
+    }
+}
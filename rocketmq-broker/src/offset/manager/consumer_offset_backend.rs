@@ -0,0 +1,51 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use cheetah_string::CheetahString;
+
+/// Storage of the offsets committed by consumer groups, by `(group, topic, queue_id)`.
+///
+/// The broker keeps the offsets in the local consumer offset file by default. Another backend,
+/// such as an external store shared by a cluster of proxies, is set on the broker with
+/// `Builder::set_consumer_offset_backend`. The `ConsumerOffsetManager` still mirrors the
+/// committed offsets in its own offset table, which answers the listing queries, but reads the
+/// offset of a queue from the backend, so offsets committed through another node are seen.
+pub trait ConsumerOffsetBackend: Send + Sync {
+    /// Returns the offset `group` committed on the queue, `None` if it committed none.
+    fn read_offset(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+    ) -> Option<i64>;
+
+    /// Stores the offset `group` committed on the queue, replacing the previous one.
+    fn commit_offset(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+    );
+
+    /// Removes the offset `group` committed on the queue.
+    fn remove_offset(&self, group: &CheetahString, topic: &CheetahString, queue_id: i32);
+
+    /// Makes the committed offsets durable, called periodically by the broker every
+    /// `flush_consumer_offset_interval` milliseconds.
+    fn flush(&self);
+}
@@ -27,6 +27,7 @@ use cheetah_string::CheetahString;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::config_manager::ConfigManager;
 use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
+use rocketmq_common::FileUtils;
 use rocketmq_remoting::protocol::DataVersion;
 use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_rust::ArcMut;
@@ -40,39 +41,65 @@ use serde::Deserialize;
 use serde::Deserializer;
 use serde::Serialize;
 use serde::Serializer;
+use tracing::error;
 use tracing::warn;
 
 use crate::broker_path_config_helper::get_consumer_offset_path;
+use crate::offset::manager::consumer_offset_backend::ConsumerOffsetBackend;
 
 pub const TOPIC_GROUP_SEPARATOR: &str = "@";
 
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub(crate) struct ConsumerOffsetManager {
     pub(crate) broker_config: Arc<BrokerConfig>,
     consumer_offset_wrapper: ConsumerOffsetWrapper,
+    offset_backend: Arc<dyn ConsumerOffsetBackend>,
     message_store: Option<ArcMut<DefaultMessageStore>>,
 }
 
+impl Default for ConsumerOffsetManager {
+    fn default() -> Self {
+        Self::new(Arc::new(BrokerConfig::default()), None)
+    }
+}
+
 impl ConsumerOffsetManager {
     pub fn new(
         broker_config: Arc<BrokerConfig>,
         message_store: Option<ArcMut<DefaultMessageStore>>,
     ) -> Self {
+        let consumer_offset_wrapper = ConsumerOffsetWrapper {
+            data_version: ArcMut::new(DataVersion::default()),
+            offset_table: Arc::new(parking_lot::RwLock::new(HashMap::new())),
+            reset_offset_table: Arc::new(parking_lot::RwLock::new(HashMap::new())),
+            pull_offset_table: Arc::new(parking_lot::RwLock::new(HashMap::new())),
+            version_change_counter: Arc::new(AtomicI64::new(0)),
+        };
+        let offset_backend = Arc::new(LocalFileOffsetBackend {
+            consumer_offset_wrapper: consumer_offset_wrapper.clone(),
+            file_path: get_consumer_offset_path(broker_config.store_path_root_dir.as_str()),
+        });
         ConsumerOffsetManager {
             broker_config,
-            consumer_offset_wrapper: ConsumerOffsetWrapper {
-                data_version: ArcMut::new(DataVersion::default()),
-                offset_table: Arc::new(parking_lot::RwLock::new(HashMap::new())),
-                reset_offset_table: Arc::new(parking_lot::RwLock::new(HashMap::new())),
-                pull_offset_table: Arc::new(parking_lot::RwLock::new(HashMap::new())),
-                version_change_counter: Arc::new(AtomicI64::new(0)),
-            },
+            consumer_offset_wrapper,
+            offset_backend,
             message_store,
         }
     }
     pub fn set_message_store(&mut self, message_store: Option<ArcMut<DefaultMessageStore>>) {
         self.message_store = message_store;
     }
+
+    /// Stores the committed offsets in `offset_backend` instead of the local consumer offset
+    /// file.
+    pub fn set_offset_backend(&mut self, offset_backend: Arc<dyn ConsumerOffsetBackend>) {
+        self.offset_backend = offset_backend;
+    }
+
+    /// Flushes the committed offsets to the offset backend.
+    pub fn flush(&self) {
+        self.offset_backend.flush();
+    }
 }
 
 impl ConsumerOffsetManager {
@@ -84,12 +111,22 @@ impl ConsumerOffsetManager {
             if topic_at_group.contains(topic.as_str()) {
                 let arrays: Vec<&str> = topic_at_group.split(TOPIC_GROUP_SEPARATOR).collect();
                 if arrays.len() == 2 && arrays[0] == topic {
-                    keys_to_remove.push(topic_at_group.clone());
+                    keys_to_remove.push((
+                        topic_at_group.clone(),
+                        CheetahString::from_string(arrays[1].to_string()),
+                    ));
                 }
             }
         }
-        for key in keys_to_remove {
-            offset_table.remove(&key);
+        let removed: Vec<_> = keys_to_remove
+            .into_iter()
+            .filter_map(|(key, group)| offset_table.remove(&key).map(|offsets| (group, offsets)))
+            .collect();
+        drop(offset_table);
+        for (group, offsets) in removed {
+            for queue_id in offsets.keys() {
+                self.offset_backend.remove_offset(&group, topic, *queue_id);
+            }
         }
     }
 
@@ -117,9 +154,15 @@ impl ConsumerOffsetManager {
         let key =
             CheetahString::from_string(format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, group));
 
-        let mut write_guard = self.consumer_offset_wrapper.offset_table.write();
-        let map = write_guard.entry(key.clone()).or_default();
-        let store_offset = map.insert(queue_id, offset);
+        let store_offset = self
+            .consumer_offset_wrapper
+            .offset_table
+            .write()
+            .entry(key.clone())
+            .or_default()
+            .insert(queue_id, offset);
+        self.offset_backend
+            .commit_offset(group, topic, queue_id, offset);
         if let Some(store_offset) = store_offset {
             if offset < store_offset {
                 warn!(
@@ -152,6 +195,46 @@ impl ConsumerOffsetManager {
         }
     }
 
+    /// Overwrites the committed offsets of `group` on the given queues of `topic` at once.
+    ///
+    /// The offset table stays write-locked for the whole batch, so an ack or pull committing
+    /// an offset concurrently lands either before or after the reset, never in the middle.
+    /// Returns `(queue_id, offset_before, offset_after)` for every queue, with `-1` as the
+    /// offset before for a queue which had none.
+    pub fn reset_offsets(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        offsets: &[(i32, i64)],
+    ) -> Vec<(i32, i64, i64)> {
+        let key =
+            CheetahString::from_string(format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, group));
+        let mut write_guard = self.consumer_offset_wrapper.offset_table.write();
+        let map = write_guard.entry(key).or_default();
+        let changes = offsets
+            .iter()
+            .map(|&(queue_id, offset)| {
+                let before = map.insert(queue_id, offset).unwrap_or(-1);
+                (queue_id, before, offset)
+            })
+            .collect();
+        drop(write_guard);
+        for &(queue_id, offset) in offsets {
+            self.offset_backend
+                .commit_offset(group, topic, queue_id, offset);
+        }
+        let state_machine_version = if let Some(ref message_store) = self.message_store {
+            message_store.get_state_machine_version()
+        } else {
+            0
+        };
+        self.consumer_offset_wrapper
+            .data_version
+            .mut_from_ref()
+            .next_version_with(state_machine_version);
+        changes
+    }
+
     pub fn has_offset_reset(&self, group: &str, topic: &str, queue_id: i32) -> bool {
         let key = format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, group);
         match self
@@ -179,17 +262,24 @@ impl ConsumerOffsetManager {
                 }
             }
         }
-        if let Some(value) = self
-            .consumer_offset_wrapper
+        self.offset_backend
+            .read_offset(group, topic, queue_id)
+            .unwrap_or(-1)
+    }
+
+    /// Returns the offsets `group` committed on the queues of `topic`, by queue id.
+    pub fn query_offsets(&self, group: &CheetahString, topic: &CheetahString) -> HashMap<i32, i64> {
+        let key = format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, group);
+        self.consumer_offset_wrapper
             .offset_table
             .read()
             .get(key.as_str())
-        {
-            if value.contains_key(&queue_id) {
-                return value.get(&queue_id).cloned().unwrap_or(-1);
-            }
-        }
-        -1
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn get_group_topic_map(&self) -> HashMap<CheetahString, HashSet<CheetahString>> {
+        self.consumer_offset_wrapper.get_group_topic_map()
     }
 
     pub fn which_topic_by_consumer(&self, group: &CheetahString) -> HashSet<CheetahString> {
@@ -275,6 +365,53 @@ impl ConsumerOffsetManager {
     }
 }
 
+/// Keeps the offsets in the offset table of the manager, which `flush` writes as a whole to
+/// the local consumer offset file, so there is nothing to do for a single offset.
+struct LocalFileOffsetBackend {
+    consumer_offset_wrapper: ConsumerOffsetWrapper,
+    file_path: String,
+}
+
+impl ConsumerOffsetBackend for LocalFileOffsetBackend {
+    fn read_offset(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+    ) -> Option<i64> {
+        let key = format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, group);
+        self.consumer_offset_wrapper
+            .offset_table
+            .read()
+            .get(key.as_str())
+            .and_then(|offsets| offsets.get(&queue_id).copied())
+    }
+
+    fn commit_offset(
+        &self,
+        _group: &CheetahString,
+        _topic: &CheetahString,
+        _queue_id: i32,
+        _offset: i64,
+    ) {
+    }
+
+    fn remove_offset(&self, _group: &CheetahString, _topic: &CheetahString, _queue_id: i32) {}
+
+    fn flush(&self) {
+        let json = match self.consumer_offset_wrapper.to_json_pretty() {
+            Ok(json) => json,
+            Err(e) => {
+                error!("encode consumer offsets failed: {}", e);
+                return;
+            }
+        };
+        if FileUtils::string_to_file(json.as_str(), self.file_path.as_str()).is_err() {
+            error!("persist file {} exception", self.file_path);
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 struct ConsumerOffsetWrapper {
     data_version: ArcMut<DataVersion>,
@@ -435,3 +572,111 @@ impl<'de> Deserialize<'de> for ConsumerOffsetWrapper {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct InMemoryOffsetBackend {
+        offsets: parking_lot::Mutex<HashMap<(CheetahString, CheetahString, i32), i64>>,
+        flush_times: AtomicUsize,
+    }
+
+    impl ConsumerOffsetBackend for InMemoryOffsetBackend {
+        fn read_offset(
+            &self,
+            group: &CheetahString,
+            topic: &CheetahString,
+            queue_id: i32,
+        ) -> Option<i64> {
+            self.offsets
+                .lock()
+                .get(&(group.clone(), topic.clone(), queue_id))
+                .copied()
+        }
+
+        fn commit_offset(
+            &self,
+            group: &CheetahString,
+            topic: &CheetahString,
+            queue_id: i32,
+            offset: i64,
+        ) {
+            self.offsets
+                .lock()
+                .insert((group.clone(), topic.clone(), queue_id), offset);
+        }
+
+        fn remove_offset(&self, group: &CheetahString, topic: &CheetahString, queue_id: i32) {
+            self.offsets
+                .lock()
+                .remove(&(group.clone(), topic.clone(), queue_id));
+        }
+
+        fn flush(&self) {
+            self.flush_times.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn client_host() -> SocketAddr {
+        "127.0.0.1:10911".parse().unwrap()
+    }
+
+    #[test]
+    fn offsets_are_stored_in_offset_backend() {
+        let backend = Arc::new(InMemoryOffsetBackend::default());
+        let mut manager = ConsumerOffsetManager::default();
+        manager.set_offset_backend(backend.clone());
+        let group = CheetahString::from_static_str("group");
+        let topic = CheetahString::from_static_str("topic");
+
+        manager.commit_offset(client_host(), &group, &topic, 0, 10);
+        manager.reset_offsets(&group, &topic, &[(1, 20)]);
+        assert_eq!(backend.read_offset(&group, &topic, 0), Some(10));
+        assert_eq!(backend.read_offset(&group, &topic, 1), Some(20));
+
+        // committed through another node sharing the backend
+        backend.commit_offset(&group, &topic, 0, 15);
+        assert_eq!(manager.query_offset(&group, &topic, 0), 15);
+        assert_eq!(manager.query_offset(&group, &topic, 2), -1);
+        assert!(manager.which_group_by_topic("topic").contains("group"));
+
+        manager.flush();
+        assert_eq!(backend.flush_times.load(Ordering::Relaxed), 1);
+
+        manager.clean_offset_by_topic(&topic);
+        assert_eq!(backend.read_offset(&group, &topic, 0), None);
+        assert_eq!(backend.read_offset(&group, &topic, 1), None);
+        assert_eq!(manager.query_offset(&group, &topic, 0), -1);
+    }
+
+    #[test]
+    fn local_file_backend_flushes_offset_table() {
+        let store_dir = std::env::temp_dir().join(format!(
+            "rocketmq-consumer-offset-{}-{}",
+            std::process::id(),
+            rocketmq_common::TimeUtils::get_current_millis()
+        ));
+        let broker_config = Arc::new(BrokerConfig {
+            store_path_root_dir: CheetahString::from_string(
+                store_dir.to_string_lossy().into_owned(),
+            ),
+            ..BrokerConfig::default()
+        });
+        let group = CheetahString::from_static_str("group");
+        let topic = CheetahString::from_static_str("topic");
+
+        let manager = ConsumerOffsetManager::new(broker_config.clone(), None);
+        manager.commit_offset(client_host(), &group, &topic, 3, 42);
+        assert_eq!(manager.query_offset(&group, &topic, 3), 42);
+        manager.flush();
+
+        let reloaded = ConsumerOffsetManager::new(broker_config, None);
+        assert!(reloaded.load());
+        assert_eq!(reloaded.query_offset(&group, &topic, 3), 42);
+        let _ = std::fs::remove_dir_all(store_dir);
+    }
+}
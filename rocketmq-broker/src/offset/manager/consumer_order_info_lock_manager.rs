@@ -15,10 +15,22 @@
  * limitations under the License.
  */
 
+use cheetah_string::CheetahString;
+
 use crate::offset::manager::consumer_order_info_manager::ConsumerOrderInfoWrapper;
+use crate::offset::manager::consumer_order_info_manager::OrderInfo;
 
 pub struct ConsumerOrderInfoLockManager {}
 
 impl ConsumerOrderInfoLockManager {
     pub fn recover(&self, _consumer_order_info_wrapper: &ConsumerOrderInfoWrapper) {}
+
+    pub fn update_lock_free_timestamp(
+        &self,
+        _topic: &CheetahString,
+        _group: &CheetahString,
+        _queue_id: i32,
+        _order_info: &OrderInfo,
+    ) {
+    }
 }
@@ -26,6 +26,7 @@ use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::config_manager::ConfigManager;
 use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
 use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::protocol::header::extra_info_util::ExtraInfoUtil;
 use serde::Deserialize;
 use serde::Serialize;
 use tracing::info;
@@ -76,9 +77,9 @@ impl<MS> ConfigManager for ConsumerOrderInfoManager<MS> {
         self.auto_clean();
         let wrapper = self.consumer_order_info_wrapper.lock();
         match pretty_format {
-            true => SerdeJsonUtils::to_json_pretty(&wrapper.table)
+            true => SerdeJsonUtils::to_json_pretty(wrapper.deref())
                 .expect("Failed to serialize consumer order info wrapper"),
-            false => serde_json::to_string(&wrapper.table)
+            false => serde_json::to_string(wrapper.deref())
                 .expect("Failed to serialize consumer order info wrapper"),
         }
     }
@@ -191,6 +192,100 @@ impl<MS> ConsumerOrderInfoManager<MS> {
         }
     }
 
+    /// Tells whether an orderly pop of `attempt_id` has to skip the queue, as messages handed
+    /// out by another attempt are neither acked nor visible again yet.
+    pub fn check_block(
+        &self,
+        attempt_id: &str,
+        topic: &CheetahString,
+        group: &CheetahString,
+        queue_id: i32,
+        invisible_time: u64,
+    ) -> bool {
+        let key = CheetahString::from_string(build_key(topic, group));
+        let mut table = self.consumer_order_info_wrapper.lock();
+        match table
+            .table
+            .get_mut(&key)
+            .and_then(|qs| qs.get_mut(&queue_id))
+        {
+            Some(order_info) => order_info.need_block(attempt_id, invisible_time),
+            None => false,
+        }
+    }
+
+    /// Records the offsets handed out by an orderly pop of the queue, which holds it until they
+    /// are acked or visible again. The number of times each offset was consumed is carried over
+    /// from the previous pop and appended to `order_count_info`, followed by the least count of
+    /// the queue for clients reading it by queue id.
+    pub fn update(
+        &self,
+        attempt_id: &str,
+        topic: &CheetahString,
+        group: &CheetahString,
+        queue_id: i32,
+        pop_time: u64,
+        invisible_time: u64,
+        msg_queue_offset_list: Vec<u64>,
+        order_count_info: &mut String,
+    ) {
+        let key = CheetahString::from_string(build_key(topic, group));
+        let mut table = self.consumer_order_info_wrapper.lock();
+        let qs = table.table.entry(key).or_default();
+        let mut order_info = OrderInfo::new(
+            attempt_id,
+            pop_time,
+            invisible_time,
+            msg_queue_offset_list,
+            get_current_millis(),
+        );
+        if let Some(pre_order_info) = qs.remove(&queue_id) {
+            order_info.merge_offset_consumed_count(
+                &pre_order_info.attempt_id,
+                pre_order_info.offset_list,
+                pre_order_info.offset_consumed_count,
+            );
+        }
+        for (offset, consumed_count) in &order_info.offset_consumed_count {
+            ExtraInfoUtil::build_queue_offset_order_count_info(
+                order_count_info,
+                topic,
+                queue_id as i64,
+                *offset as i64,
+                *consumed_count,
+            );
+        }
+        // offsets consumed for the first time have no count
+        let min_consumed_count =
+            if order_info.offset_consumed_count.len() != order_info.offset_list.len() {
+                0
+            } else {
+                order_info
+                    .offset_consumed_count
+                    .values()
+                    .copied()
+                    .min()
+                    .unwrap_or(0)
+            };
+        ExtraInfoUtil::build_queue_id_order_count_info(
+            order_count_info,
+            topic,
+            queue_id,
+            min_consumed_count,
+        );
+        self.update_lock_free_timestamp(topic, group, queue_id, &order_info);
+        qs.insert(queue_id, order_info);
+    }
+
+    /// Releases the queue held by an orderly pop, the next orderly pop reads it whatever the
+    /// offsets handed out before.
+    pub fn clear_block(&self, topic: &CheetahString, group: &CheetahString, queue_id: i32) {
+        let key = CheetahString::from_string(build_key(topic, group));
+        if let Some(qs) = self.consumer_order_info_wrapper.lock().table.get_mut(&key) {
+            qs.remove(&queue_id);
+        }
+    }
+
     pub fn update_next_visible_time(
         &self,
         topic: &CheetahString,
@@ -232,14 +327,53 @@ impl<MS> ConsumerOrderInfoManager<MS> {
         self.update_lock_free_timestamp(topic, group, queue_id, order_info);
     }
 
+    /// Commits the ack of `queue_offset` delivered by the orderly pop of `pop_time`, see
+    /// [`OrderInfo::commit`].
+    pub fn commit_and_next(
+        &self,
+        topic: &CheetahString,
+        group: &CheetahString,
+        queue_id: i32,
+        queue_offset: u64,
+        pop_time: u64,
+    ) -> OrderedAckCommit {
+        let key = CheetahString::from_string(build_key(topic, group));
+        let mut table = self.consumer_order_info_wrapper.lock();
+        let Some(order_info) = table
+            .table
+            .get_mut(&key)
+            .and_then(|qs| qs.get_mut(&queue_id))
+        else {
+            warn!(
+                "orderInfo of queueId is null. key: {}, queueOffset: {}, queueId: {}",
+                key, queue_offset, queue_id
+            );
+            return OrderedAckCommit::NotLocked;
+        };
+        let commit = order_info.commit(queue_offset, pop_time);
+        match commit {
+            OrderedAckCommit::Next(_) => {
+                self.update_lock_free_timestamp(topic, group, queue_id, order_info)
+            }
+            _ => warn!(
+                "commit orderly ack failed: {:?}. key: {}, queueOffset: {}, orderInfo: {}, \
+                 popTime: {}",
+                commit, key, queue_offset, order_info, pop_time
+            ),
+        }
+        commit
+    }
+
     fn update_lock_free_timestamp(
         &self,
-        _topic: &CheetahString,
-        _group: &CheetahString,
-        _queue_id: i32,
-        _order_info: &OrderInfo,
+        topic: &CheetahString,
+        group: &CheetahString,
+        queue_id: i32,
+        order_info: &OrderInfo,
     ) {
-        unimplemented!("")
+        if let Some(lock_manager) = self.consumer_order_info_lock_manager.as_ref() {
+            lock_manager.update_lock_free_timestamp(topic, group, queue_id, order_info);
+        }
     }
 }
 
@@ -249,6 +383,19 @@ fn build_key(topic: &CheetahString, group: &CheetahString) -> String {
     format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, group)
 }
 
+/// Outcome of committing the ack of a message popped in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OrderedAckCommit {
+    /// The ack was committed, holds the next offset of the queue not acked yet.
+    Next(i64),
+    /// No orderly pop holds the queue.
+    NotLocked,
+    /// The ack belongs to another pop of the queue than the one holding it.
+    PopTimeMismatch,
+    /// The acked offset was not delivered by the pop holding the queue.
+    OffsetNotFound,
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub(crate) struct ConsumerOrderInfoWrapper {
     table: HashMap<CheetahString /* topic@group */, HashMap<i32, OrderInfo>>,
@@ -294,6 +441,23 @@ impl Display for OrderInfo {
 }
 
 impl OrderInfo {
+    pub fn new(
+        attempt_id: &str,
+        pop_time: u64,
+        invisible_time: u64,
+        queue_offset_list: Vec<u64>,
+        last_consume_timestamp: u64,
+    ) -> Self {
+        OrderInfo {
+            pop_time,
+            invisible_time: Some(invisible_time),
+            offset_list: Self::build_offset_list(queue_offset_list),
+            last_consume_timestamp,
+            attempt_id: attempt_id.to_string(),
+            ..Default::default()
+        }
+    }
+
     /// Builds a list of offsets from a given list of queue offsets.
     /// If the list contains only one element, it returns the same list.
     /// Otherwise, it returns a list where each element is the difference
@@ -419,6 +583,27 @@ impl OrderInfo {
         }
     }
 
+    /// Marks `queue_offset` delivered by the pop of `pop_time` as acked.
+    ///
+    /// # Returns
+    ///
+    /// The next offset not acked yet, or why the ack could not be committed.
+    pub fn commit(&mut self, queue_offset: u64, pop_time: u64) -> OrderedAckCommit {
+        if self.offset_list.is_empty() {
+            return OrderedAckCommit::NotLocked;
+        }
+        if pop_time != self.pop_time {
+            return OrderedAckCommit::PopTimeMismatch;
+        }
+        let Some(index) = (0..self.offset_list.len().min(64))
+            .find(|index| self.get_queue_offset(*index) == queue_offset)
+        else {
+            return OrderedAckCommit::OffsetNotFound;
+        };
+        self.commit_offset_bit |= 1 << index;
+        OrderedAckCommit::Next(self.get_next_offset())
+    }
+
     /// Gets the queue offset for a given offset index.
     ///
     /// # Arguments
@@ -580,4 +765,37 @@ mod tests {
         assert_eq!(order_info.offset_consumed_count.get(&1), Some(&1));
         assert_eq!(order_info.offset_consumed_count.get(&2), Some(&1));
     }
+
+    #[test]
+    fn commit_returns_next_offset_not_acked() {
+        let mut order_info = OrderInfo {
+            pop_time: 1000,
+            offset_list: OrderInfo::build_offset_list(vec![10, 11, 12]),
+            ..Default::default()
+        };
+        assert_eq!(order_info.commit(11, 1000), OrderedAckCommit::Next(10));
+        assert_eq!(order_info.commit(10, 1000), OrderedAckCommit::Next(12));
+        assert_eq!(order_info.commit(12, 1000), OrderedAckCommit::Next(13));
+    }
+
+    #[test]
+    fn commit_rejects_ack_not_matching_pop() {
+        let mut order_info = OrderInfo {
+            pop_time: 1000,
+            offset_list: OrderInfo::build_offset_list(vec![10, 11]),
+            ..Default::default()
+        };
+        assert_eq!(
+            order_info.commit(10, 999),
+            OrderedAckCommit::PopTimeMismatch
+        );
+        assert_eq!(
+            order_info.commit(12, 1000),
+            OrderedAckCommit::OffsetNotFound
+        );
+        assert_eq!(
+            OrderInfo::default().commit(10, 1000),
+            OrderedAckCommit::NotLocked
+        );
+    }
 }
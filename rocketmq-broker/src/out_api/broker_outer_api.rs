@@ -50,7 +50,10 @@ use rocketmq_remoting::protocol::header::message_operation_header::send_message_
 use rocketmq_remoting::protocol::header::namesrv::register_broker_header::RegisterBrokerRequestHeader;
 use rocketmq_remoting::protocol::header::namesrv::register_broker_header::RegisterBrokerResponseHeader;
 use rocketmq_remoting::protocol::header::namesrv::topic_operation_header::RegisterTopicRequestHeader;
+use rocketmq_remoting::protocol::header::query_consumer_offset_request_header::QueryConsumerOffsetRequestHeader;
+use rocketmq_remoting::protocol::header::query_consumer_offset_response_header::QueryConsumerOffsetResponseHeader;
 use rocketmq_remoting::protocol::header::unlock_batch_mq_request_header::UnlockBatchMqRequestHeader;
+use rocketmq_remoting::protocol::header::update_consumer_offset_header::UpdateConsumerOffsetRequestHeader;
 use rocketmq_remoting::protocol::namesrv::RegisterBrokerResult;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::protocol::route::route_data_view::QueueData;
@@ -421,6 +424,73 @@ impl BrokerOuterAPI {
         }
     }
 
+    /// Queries the committed offset of a queue on another broker, `None` if it has none yet.
+    pub async fn query_consumer_offset(
+        &self,
+        addr: &CheetahString,
+        request_header: QueryConsumerOffsetRequestHeader,
+        timeout_millis: u64,
+    ) -> Result<Option<i64>> {
+        let request = RemotingCommand::create_request_command(
+            RequestCode::QueryConsumerOffset,
+            request_header,
+        );
+        let response = self
+            .remoting_client
+            .invoke_async(Some(addr), request, timeout_millis)
+            .await
+            .map_err(BrokerRemotingError)?;
+        match ResponseCode::from(response.code()) {
+            ResponseCode::Success => {
+                let response_header = response
+                    .decode_command_custom_header::<QueryConsumerOffsetResponseHeader>()
+                    .map_err(BrokerRemotingError)?;
+                Ok(response_header.offset)
+            }
+            ResponseCode::QueryNotFound => Ok(None),
+            _ => Err(BrokerError::MQBrokerError(
+                response.code(),
+                response
+                    .remark()
+                    .cloned()
+                    .unwrap_or(CheetahString::empty())
+                    .to_string(),
+                addr.to_string(),
+            )),
+        }
+    }
+
+    /// Commits a consumer offset on another broker.
+    pub async fn update_consumer_offset(
+        &self,
+        addr: &CheetahString,
+        request_header: UpdateConsumerOffsetRequestHeader,
+        timeout_millis: u64,
+    ) -> Result<()> {
+        let request = RemotingCommand::create_request_command(
+            RequestCode::UpdateConsumerOffset,
+            request_header,
+        );
+        let response = self
+            .remoting_client
+            .invoke_async(Some(addr), request, timeout_millis)
+            .await
+            .map_err(BrokerRemotingError)?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            Ok(())
+        } else {
+            Err(BrokerError::MQBrokerError(
+                response.code(),
+                response
+                    .remark()
+                    .cloned()
+                    .unwrap_or(CheetahString::empty())
+                    .to_string(),
+                addr.to_string(),
+            ))
+        }
+    }
+
     pub async fn get_topic_route_info_from_name_server(
         &self,
         topic: &CheetahString,
@@ -41,7 +41,16 @@ use crate::processor::reply_message_processor::ReplyMessageProcessor;
 use crate::processor::send_message_processor::SendMessageProcessor;
 use crate::transaction::transactional_message_service::TransactionalMessageService;
 
+pub(crate) mod ack_failure_log;
+pub(crate) mod ack_handle;
+pub(crate) mod ack_invisible_time_cap_table;
 pub(crate) mod ack_message_processor;
+pub(crate) mod ack_parity_telemetry;
+pub(crate) mod ack_priority_gate;
+pub(crate) mod ack_processing_switch;
+pub(crate) mod ack_put_limiter;
+pub(crate) mod ack_request_handler;
+pub(crate) mod ack_store_latency;
 pub(crate) mod admin_broker_processor;
 pub(crate) mod change_invisible_time_processor;
 pub(crate) mod client_manage_processor;
@@ -51,8 +60,13 @@ pub(crate) mod end_transaction_processor;
 pub(crate) mod notification_processor;
 pub(crate) mod peek_message_processor;
 pub(crate) mod polling_info_processor;
+#[cfg(all(test, feature = "pop_integration_test"))]
+mod pop_ack_revive_test;
+pub(crate) mod pop_ack_unique_id_cache;
+pub(crate) mod pop_consumer_flow_controller;
 pub(crate) mod pop_inflight_message_counter;
 pub(crate) mod pop_message_processor;
+pub(crate) mod pop_revive_queue_selector;
 pub(crate) mod processor_service;
 pub(crate) mod pull_message_processor;
 pub(crate) mod pull_message_result_handler;
@@ -60,12 +74,13 @@ pub(crate) mod query_assignment_processor;
 pub(crate) mod query_message_processor;
 pub(crate) mod reply_message_processor;
 pub(crate) mod send_message_processor;
+pub(crate) mod two_phase_ack_table;
 
 pub struct BrokerRequestProcessor<MS, TS> {
     pub(crate) send_message_processor: ArcMut<SendMessageProcessor<MS, TS>>,
     pub(crate) pull_message_processor: ArcMut<PullMessageProcessor<MS>>,
     pub(crate) peek_message_processor: ArcMut<PeekMessageProcessor>,
-    pub(crate) pop_message_processor: ArcMut<PopMessageProcessor>,
+    pub(crate) pop_message_processor: ArcMut<PopMessageProcessor<MS>>,
     pub(crate) ack_message_processor: ArcMut<AckMessageProcessor<MS>>,
     pub(crate) change_invisible_time_processor: ArcMut<ChangeInvisibleTimeProcessor<MS>>,
     pub(crate) notification_processor: ArcMut<NotificationProcessor>,
@@ -177,7 +192,9 @@ where
                     .map_err(Into::into);
             }
 
-            RequestCode::AckMessage | RequestCode::BatchAckMessage => {
+            RequestCode::AckMessage
+            | RequestCode::BatchAckMessage
+            | RequestCode::EndTwoPhaseAck => {
                 return self
                     .ack_message_processor
                     .process_request(channel, ctx, request_code, request)
@@ -0,0 +1,80 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+/// Samples the log lines of acks failing to be stored, so that a store outage failing every
+/// ack does not flood the log.
+///
+/// The first failure is always logged, then one of every `sample_rate` failures.
+pub(crate) struct AckFailureLogSampler {
+    sample_rate: u64,
+    failures: AtomicU64,
+}
+
+impl AckFailureLogSampler {
+    /// A `sample_rate` of 0 or 1 logs every failure.
+    pub fn new(sample_rate: u64) -> Self {
+        AckFailureLogSampler {
+            sample_rate: sample_rate.max(1),
+            failures: AtomicU64::new(0),
+        }
+    }
+
+    /// Records a failure, returns the number of failures so far if this one is to be logged.
+    pub fn sample(&self) -> Option<u64> {
+        let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+        ((failures - 1) % self.sample_rate == 0).then_some(failures)
+    }
+
+    /// Number of failures recorded, logged or not.
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_failure_is_logged() {
+        let sampler = AckFailureLogSampler::new(100);
+
+        assert_eq!(sampler.sample(), Some(1));
+    }
+
+    #[test]
+    fn rapid_failures_are_logged_at_the_sample_rate() {
+        let sampler = AckFailureLogSampler::new(100);
+
+        let logged = (0..1_000)
+            .filter_map(|_| sampler.sample())
+            .collect::<Vec<_>>();
+
+        assert_eq!(logged.len(), 10);
+        assert_eq!(logged[..3], [1, 101, 201]);
+        assert_eq!(sampler.failures(), 1_000);
+    }
+
+    #[test]
+    fn sample_rate_of_zero_logs_every_failure() {
+        let sampler = AckFailureLogSampler::new(0);
+
+        assert_eq!((0..5).filter_map(|_| sampler.sample()).count(), 5);
+    }
+}
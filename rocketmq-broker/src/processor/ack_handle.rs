@@ -0,0 +1,272 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::str::FromStr;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::mix_all;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::header::extra_info_util::ExtraInfoUtil;
+use rocketmq_remoting::protocol::heartbeat::pop_ack_protocol_version::PopAckProtocolVersion;
+use thiserror::Error;
+
+/// Positions of the receipt handle fields read by a single ack, see `ExtraInfoUtil`.
+const CK_QUEUE_OFFSET_INDEX: usize = 0;
+const POP_TIME_INDEX: usize = 1;
+const INVISIBLE_TIME_INDEX: usize = 2;
+const REVIVE_QID_INDEX: usize = 3;
+const RETRY_INDEX: usize = 4;
+const BROKER_NAME_INDEX: usize = 5;
+
+/// Number of receipt handle fields read by a single ack, the last one being the broker name.
+pub(crate) const ACK_HANDLE_MIN_FIELDS: usize = BROKER_NAME_INDEX + 1;
+
+/// The receipt handle fields a single ack is written with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ParsedAckHandle {
+    pub ck_queue_offset: i64,
+    pub pop_time: i64,
+    pub invisible_time: i64,
+    pub revive_qid: i32,
+    pub retry: CheetahString,
+    pub broker_name: CheetahString,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub(crate) enum AckParseError {
+    #[error("extraInfo is empty")]
+    Empty,
+
+    #[error("extraInfo is malformed, fields: {fields}, expected at least: {expected}")]
+    Truncated { fields: usize, expected: usize },
+
+    #[error("extraInfo misses {0}")]
+    MissingField(&'static str),
+
+    #[error("extraInfo has an invalid {field}: {value}")]
+    InvalidField { field: &'static str, value: String },
+}
+
+impl AckParseError {
+    /// Code answered to the client of an ack whose handle could not be parsed. Every failure is
+    /// a handle the client mangled, retrying the ack as is can not succeed.
+    pub(crate) fn response_code(&self) -> ResponseCode {
+        match self {
+            AckParseError::Empty
+            | AckParseError::Truncated { .. }
+            | AckParseError::MissingField(_)
+            | AckParseError::InvalidField { .. } => ResponseCode::MessageIllegal,
+        }
+    }
+}
+
+/// Parses every receipt handle field a single ack needs at once. A handle missing any of them
+/// is rejected instead of acking with defaults, which would write the ack to a wrong revive
+/// queue or lose it.
+pub(crate) fn parse_ack_handle(
+    version: PopAckProtocolVersion,
+    extra_info: &str,
+) -> Result<ParsedAckHandle, AckParseError> {
+    let fields = version
+        .split_extra_info(extra_info)
+        .map_err(|_| AckParseError::Empty)?;
+    if fields.len() < ACK_HANDLE_MIN_FIELDS {
+        return Err(AckParseError::Truncated {
+            fields: fields.len(),
+            expected: ACK_HANDLE_MIN_FIELDS,
+        });
+    }
+    let broker_name = required_field(&fields, BROKER_NAME_INDEX, "brokerName")?;
+    Ok(ParsedAckHandle {
+        ck_queue_offset: parse_field(&fields, CK_QUEUE_OFFSET_INDEX, "ckQueueOffset")?,
+        pop_time: parse_field(&fields, POP_TIME_INDEX, "popTime")?,
+        invisible_time: parse_field(&fields, INVISIBLE_TIME_INDEX, "invisibleTime")?,
+        revive_qid: parse_field(&fields, REVIVE_QID_INDEX, "reviveQueueId")?,
+        retry: CheetahString::from(required_field(&fields, RETRY_INDEX, "retry")?),
+        broker_name: CheetahString::from(broker_name),
+    })
+}
+
+/// Returns the topic a single ack of `topic` applies to: the pop retry topic of `group` when
+/// the handle says it was popped from it, as batch acks translate theirs. A topic the client
+/// already translated is kept.
+pub(crate) fn real_ack_topic(
+    topic: &CheetahString,
+    group: &str,
+    retry: &str,
+) -> Result<CheetahString, AckParseError> {
+    if topic.starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX) {
+        return Ok(topic.clone());
+    }
+    ExtraInfoUtil::get_real_topic_with_retry(topic, group, retry)
+        .map(CheetahString::from_string)
+        .map_err(|_| AckParseError::InvalidField {
+            field: "retry",
+            value: retry.to_string(),
+        })
+}
+
+fn required_field<'a>(
+    fields: &'a [String],
+    index: usize,
+    name: &'static str,
+) -> Result<&'a str, AckParseError> {
+    match fields[index].trim() {
+        "" => Err(AckParseError::MissingField(name)),
+        value => Ok(value),
+    }
+}
+
+fn parse_field<T: FromStr>(
+    fields: &[String],
+    index: usize,
+    name: &'static str,
+) -> Result<T, AckParseError> {
+    let value = required_field(fields, index, name)?;
+    value.parse().map_err(|_| AckParseError::InvalidField {
+        field: name,
+        value: value.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(extra_info: &str) -> Result<ParsedAckHandle, AckParseError> {
+        parse_ack_handle(PopAckProtocolVersion::V1, extra_info)
+    }
+
+    #[test]
+    fn complete_handle_is_parsed() {
+        assert_eq!(
+            parse("5|1000|30000|1|0|broker-a|0|3"),
+            Ok(ParsedAckHandle {
+                ck_queue_offset: 5,
+                pop_time: 1000,
+                invisible_time: 30000,
+                revive_qid: 1,
+                retry: CheetahString::from_static_str("0"),
+                broker_name: CheetahString::from_static_str("broker-a"),
+            })
+        );
+    }
+
+    #[test]
+    fn ack_topic_is_translated_to_the_pop_retry_topic() {
+        let topic = CheetahString::from_static_str("topic");
+        assert_eq!(
+            real_ack_topic(&topic, "group", "0").unwrap().as_str(),
+            "topic"
+        );
+        assert_eq!(
+            real_ack_topic(&topic, "group", "1").unwrap().as_str(),
+            "%RETRY%group_topic"
+        );
+        assert_eq!(
+            real_ack_topic(&topic, "group", "2").unwrap().as_str(),
+            "%RETRY%group+topic"
+        );
+        let retry_topic = CheetahString::from_static_str("%RETRY%group_topic");
+        assert_eq!(real_ack_topic(&retry_topic, "group", "1"), Ok(retry_topic));
+        assert_eq!(
+            real_ack_topic(&topic, "group", "x"),
+            Err(AckParseError::InvalidField {
+                field: "retry",
+                value: "x".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn empty_handle_is_rejected() {
+        assert_eq!(parse(""), Err(AckParseError::Empty));
+        assert_eq!(
+            parse_ack_handle(PopAckProtocolVersion::V2, ""),
+            Err(AckParseError::Empty)
+        );
+    }
+
+    #[test]
+    fn truncated_handle_is_rejected() {
+        let error = parse("0|1000|30000").unwrap_err();
+        assert_eq!(
+            error,
+            AckParseError::Truncated {
+                fields: 3,
+                expected: ACK_HANDLE_MIN_FIELDS,
+            }
+        );
+        assert!(error.to_string().starts_with("extraInfo is malformed"));
+    }
+
+    #[test]
+    fn missing_broker_name_is_rejected() {
+        assert_eq!(
+            parse("0|1000|30000|1|0||0|3"),
+            Err(AckParseError::MissingField("brokerName"))
+        );
+    }
+
+    #[test]
+    fn missing_pop_time_is_rejected() {
+        assert_eq!(
+            parse("0||30000|1|0|broker-a|0|3"),
+            Err(AckParseError::MissingField("popTime"))
+        );
+    }
+
+    #[test]
+    fn invalid_numeric_fields_are_rejected() {
+        let cases = [
+            ("x|1000|30000|1|0|broker-a", "ckQueueOffset", "x"),
+            ("0|soon|30000|1|0|broker-a", "popTime", "soon"),
+            ("0|1000|long|1|0|broker-a", "invisibleTime", "long"),
+            (
+                "0|1000|30000|9999999999|0|broker-a",
+                "reviveQueueId",
+                "9999999999",
+            ),
+        ];
+        for (extra_info, field, value) in cases {
+            assert_eq!(
+                parse(extra_info),
+                Err(AckParseError::InvalidField {
+                    field,
+                    value: value.to_string(),
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn every_failure_answers_message_illegal() {
+        for error in [
+            AckParseError::Empty,
+            AckParseError::Truncated {
+                fields: 1,
+                expected: ACK_HANDLE_MIN_FIELDS,
+            },
+            AckParseError::MissingField("popTime"),
+            AckParseError::InvalidField {
+                field: "popTime",
+                value: String::from("soon"),
+            },
+        ] {
+            assert_eq!(error.response_code(), ResponseCode::MessageIllegal);
+        }
+    }
+}
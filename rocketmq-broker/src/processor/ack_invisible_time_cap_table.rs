@@ -0,0 +1,86 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use dashmap::DashMap;
+
+/// Per consumer group max invisible time applied to acks, updatable at runtime by admin.
+///
+/// Acks read the table on every call, so an updated cap takes effect for the next ack.
+#[derive(Default)]
+pub(crate) struct AckInvisibleTimeCapTable {
+    cap_table: DashMap<CheetahString, i64>,
+}
+
+impl AckInvisibleTimeCapTable {
+    /// Sets the cap of `group`, a non-positive `cap_millis` removes it.
+    pub fn update(&self, group: &CheetahString, cap_millis: i64) {
+        if cap_millis <= 0 {
+            self.cap_table.remove(group);
+        } else {
+            self.cap_table.insert(group.clone(), cap_millis);
+        }
+    }
+
+    pub fn get_cap(&self, group: &CheetahString) -> Option<i64> {
+        self.cap_table.get(group).map(|cap| *cap)
+    }
+
+    /// Returns `invisible_time` limited to the cap of `group`, if any.
+    pub fn clamp(&self, group: &CheetahString, invisible_time: i64) -> i64 {
+        match self.get_cap(group) {
+            Some(cap) => invisible_time.min(cap),
+            None => invisible_time,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamp_without_cap_keeps_invisible_time() {
+        let table = AckInvisibleTimeCapTable::default();
+        let group = CheetahString::from_static_str("test_group");
+        assert_eq!(table.clamp(&group, 60_000), 60_000);
+    }
+
+    #[test]
+    fn clamp_uses_cap_updated_at_runtime() {
+        let table = AckInvisibleTimeCapTable::default();
+        let group = CheetahString::from_static_str("test_group");
+        table.update(&group, 30_000);
+        assert_eq!(table.clamp(&group, 60_000), 30_000);
+        assert_eq!(table.clamp(&group, 10_000), 10_000);
+
+        table.update(&group, 5_000);
+        assert_eq!(table.clamp(&group, 60_000), 5_000);
+
+        table.update(&group, 0);
+        assert_eq!(table.get_cap(&group), None);
+        assert_eq!(table.clamp(&group, 60_000), 60_000);
+    }
+
+    #[test]
+    fn caps_are_per_group() {
+        let table = AckInvisibleTimeCapTable::default();
+        let group = CheetahString::from_static_str("test_group");
+        let other_group = CheetahString::from_static_str("other_group");
+        table.update(&group, 30_000);
+        assert_eq!(table.clamp(&other_group, 60_000), 60_000);
+    }
+}
@@ -16,79 +16,511 @@
  */
 #![allow(unused_variables)]
 
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::net::IpAddr;
 use std::net::SocketAddr;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 
 use bytes::Bytes;
 use cheetah_string::CheetahString;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use rocketmq_common::common::attribute::ack_cleaned_offset_policy::AckCleanedOffsetPolicy;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::config::TopicConfig;
+use rocketmq_common::common::constant::PermName;
+use rocketmq_common::common::key_builder::KeyBuilder;
 use rocketmq_common::common::key_builder::POP_ORDER_REVIVE_QUEUE;
+use rocketmq_common::common::message::message_accessor::MessageAccessor;
 use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::mix_all;
 use rocketmq_common::common::pop_ack_constants::PopAckConstants;
 use rocketmq_common::common::FAQUrl;
+use rocketmq_common::SubscriptionGroupAttributes;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::net::response_stream::is_stream_response_requested;
+use rocketmq_remoting::net::response_stream::ResponseFrameSink;
+use rocketmq_remoting::net::response_stream::ResponseStream;
 use rocketmq_remoting::protocol::body::batch_ack::BatchAck;
 use rocketmq_remoting::protocol::body::batch_ack_message_request_body::BatchAckMessageRequestBody;
+use rocketmq_remoting::protocol::body::batch_ack_result::AckedOffsets;
+use rocketmq_remoting::protocol::body::batch_ack_result::AckedOffsetsEncoding;
+use rocketmq_remoting::protocol::body::batch_ack_result::BatchAckDetail;
+use rocketmq_remoting::protocol::body::batch_ack_result::BatchAckResult;
+use rocketmq_remoting::protocol::body::batch_ack_result::BATCH_ACK_DETAIL;
 use rocketmq_remoting::protocol::header::ack_message_request_header::AckMessageRequestHeader;
-use rocketmq_remoting::protocol::header::extra_info_util::ExtraInfoUtil;
+use rocketmq_remoting::protocol::header::end_two_phase_ack_request_header::EndTwoPhaseAckRequestHeader;
+use rocketmq_remoting::protocol::heartbeat::pop_ack_protocol_version::PopAckProtocolVersion;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::subscription::subscription_group_config::SubscriptionGroupConfig;
 use rocketmq_remoting::protocol::RemotingDeserializable;
 use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_rust::ArcMut;
-use rocketmq_store::base::message_status_enum::PutMessageStatus;
+use rocketmq_store::base::message_result::PutMessageResult;
 use rocketmq_store::log_file::MessageStore;
 use rocketmq_store::pop::ack_msg::AckMsg;
 use rocketmq_store::pop::batch_ack_msg::BatchAckMsg;
+use rocketmq_store::pop::set_revive_body_crc;
+use rocketmq_store::pop::set_revive_host_flags;
 use rocketmq_store::pop::AckMessage;
+use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
+use rocketmq_store::store::running_flags::RunningFlags;
+use thiserror::Error;
+use tokio::sync::Semaphore;
 use tracing::error;
+use tracing::info;
+use tracing::instrument;
+use tracing::warn;
+use tracing::Span;
 
 use crate::broker_error::BrokerError::BrokerCommonError;
 use crate::broker_error::BrokerError::BrokerRemotingError;
+use crate::client::manager::consumer_manager::ConsumerManager;
+use crate::failover::escape_bridge::transform_send_result2put_result;
 use crate::failover::escape_bridge::EscapeBridge;
+use crate::failover::origin_offset_reconciler::reconcile_origin_offset;
+use crate::load_balance::pop_sticky_assignment_manager::PopStickyAssignmentManager;
+use crate::mqtrace::ack_message_context::AckMessageContext;
+use crate::mqtrace::ack_message_hook::AckMessageHook;
+use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
+use crate::offset::manager::consumer_order_info_manager::ConsumerOrderInfoManager;
+use crate::offset::manager::consumer_order_info_manager::OrderedAckCommit;
+use crate::processor::ack_failure_log::AckFailureLogSampler;
+use crate::processor::ack_handle::parse_ack_handle;
+use crate::processor::ack_handle::real_ack_topic;
+use crate::processor::ack_handle::ParsedAckHandle;
+use crate::processor::ack_invisible_time_cap_table::AckInvisibleTimeCapTable;
+use crate::processor::ack_parity_telemetry::AckParityTelemetry;
+use crate::processor::ack_parity_telemetry::ParityGap;
+use crate::processor::ack_priority_gate::AckPriorityGate;
+use crate::processor::ack_processing_switch::AckProcessingSwitch;
+use crate::processor::ack_put_limiter::AckPutLimiter;
+use crate::processor::ack_request_handler::AckRequestHandler;
+use crate::processor::ack_request_handler::AckRequestHandlerTable;
+use crate::processor::ack_store_latency::AckKind;
+use crate::processor::ack_store_latency::AckStoreLatency;
+use crate::processor::pop_ack_unique_id_cache::AckIdClaim;
+use crate::processor::pop_ack_unique_id_cache::PopAckUniqueIdCache;
+use crate::processor::pop_consumer_flow_controller::PopConsumerFlowController;
+use crate::processor::pop_inflight_message_counter::InFlightDecrement;
 use crate::processor::pop_inflight_message_counter::PopInflightMessageCounter;
 use crate::processor::pop_message_processor::PopMessageProcessor;
+use crate::processor::pop_message_processor::QueueLockManager;
+use crate::processor::pop_revive_queue_selector::is_revive_sharding_enabled;
+use crate::processor::pop_revive_queue_selector::revive_queue_num_of;
+use crate::processor::pop_revive_queue_selector::select_revive_queue_id;
+use crate::processor::processor_service::pop_buffer_merge_service::is_put_ok;
 use crate::processor::processor_service::pop_buffer_merge_service::PopBufferMergeService;
+use crate::processor::send_message_processor::DLQ_NUMS_PER_GROUP;
+use crate::processor::two_phase_ack_table::TwoPhaseAckError;
+use crate::processor::two_phase_ack_table::TwoPhaseAckTable;
+use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
 use crate::topic::manager::topic_config_manager::TopicConfigManager;
 
 pub struct AckMessageProcessor<MS> {
+    broker_config: Arc<BrokerConfig>,
     topic_config_manager: TopicConfigManager,
+    subscription_group_manager: Arc<SubscriptionGroupManager<MS>>,
+    consumer_offset_manager: Arc<ConsumerOffsetManager>,
+    consumer_order_info_manager: Arc<ConsumerOrderInfoManager<MS>>,
     message_store: ArcMut<MS>,
     pop_buffer_merge_service: ArcMut<PopBufferMergeService>,
     escape_bridge: ArcMut<EscapeBridge<MS>>,
+    pop_message_processor: ArcMut<PopMessageProcessor<MS>>,
     store_host: SocketAddr,
     pop_inflight_message_counter: Arc<PopInflightMessageCounter>,
+    pop_sticky_assignment_manager: Arc<PopStickyAssignmentManager>,
+    consumer_manager: Arc<ConsumerManager>,
+    pop_ack_unique_id_cache: Arc<PopAckUniqueIdCache>,
+    /// Retry messages moved to the DLQ recently, so that a resent ack does not move them again.
+    pop_dlq_move_cache: Arc<PopAckUniqueIdCache>,
+    ack_priority_gate: Arc<AckPriorityGate>,
+    ack_invisible_time_cap_table: Arc<AckInvisibleTimeCapTable>,
+    ack_parity_telemetry: Arc<AckParityTelemetry>,
+    ack_store_latency: Arc<AckStoreLatency>,
+    ack_failure_log_sampler: Arc<AckFailureLogSampler>,
+    /// Bounds the ack puts to the revive topic in flight, shared by every clone.
+    ack_put_limiter: Arc<AckPutLimiter>,
+    pop_consumer_flow_controller: Arc<PopConsumerFlowController>,
+    two_phase_ack_table: Arc<TwoPhaseAckTable>,
+    ack_processing_switch: Arc<AckProcessingSwitch>,
+    broker_stats_manager: Arc<BrokerStatsManager>,
+    ack_message_hooks: Arc<Vec<Box<dyn AckMessageHook>>>,
+    /// Handlers of the request codes registered beside the ack codes.
+    ack_request_handlers: Arc<AckRequestHandlerTable>,
 }
 
-impl<MS> AckMessageProcessor<MS>
+/// Every clone shares the state of the processor, so that the acks of a batch can be appended
+/// concurrently.
+impl<MS> Clone for AckMessageProcessor<MS> {
+    fn clone(&self) -> Self {
+        AckMessageProcessor {
+            broker_config: self.broker_config.clone(),
+            topic_config_manager: self.topic_config_manager.clone(),
+            subscription_group_manager: self.subscription_group_manager.clone(),
+            consumer_offset_manager: self.consumer_offset_manager.clone(),
+            consumer_order_info_manager: self.consumer_order_info_manager.clone(),
+            message_store: self.message_store.clone(),
+            pop_buffer_merge_service: self.pop_buffer_merge_service.clone(),
+            escape_bridge: self.escape_bridge.clone(),
+            pop_message_processor: self.pop_message_processor.clone(),
+            store_host: self.store_host,
+            pop_inflight_message_counter: self.pop_inflight_message_counter.clone(),
+            pop_sticky_assignment_manager: self.pop_sticky_assignment_manager.clone(),
+            consumer_manager: self.consumer_manager.clone(),
+            pop_ack_unique_id_cache: self.pop_ack_unique_id_cache.clone(),
+            pop_dlq_move_cache: self.pop_dlq_move_cache.clone(),
+            ack_priority_gate: self.ack_priority_gate.clone(),
+            ack_invisible_time_cap_table: self.ack_invisible_time_cap_table.clone(),
+            ack_parity_telemetry: self.ack_parity_telemetry.clone(),
+            ack_store_latency: self.ack_store_latency.clone(),
+            ack_failure_log_sampler: self.ack_failure_log_sampler.clone(),
+            ack_put_limiter: self.ack_put_limiter.clone(),
+            pop_consumer_flow_controller: self.pop_consumer_flow_controller.clone(),
+            two_phase_ack_table: self.two_phase_ack_table.clone(),
+            ack_processing_switch: self.ack_processing_switch.clone(),
+            broker_stats_manager: self.broker_stats_manager.clone(),
+            ack_message_hooks: self.ack_message_hooks.clone(),
+            ack_request_handlers: self.ack_request_handlers.clone(),
+        }
+    }
+}
+
+/// Builds an [`AckMessageProcessor`], every dependency but the hooks and request handlers is
+/// required and [`build`](Self::build) names the first one left unset.
+pub struct AckMessageProcessorBuilder<MS> {
+    topic_config_manager: Option<TopicConfigManager>,
+    subscription_group_manager: Option<Arc<SubscriptionGroupManager<MS>>>,
+    consumer_offset_manager: Option<Arc<ConsumerOffsetManager>>,
+    consumer_order_info_manager: Option<Arc<ConsumerOrderInfoManager<MS>>>,
+    message_store: Option<ArcMut<MS>>,
+    escape_bridge: Option<ArcMut<EscapeBridge<MS>>>,
+    pop_message_processor: Option<ArcMut<PopMessageProcessor<MS>>>,
+    broker_config: Option<Arc<BrokerConfig>>,
+    pop_inflight_message_counter: Option<Arc<PopInflightMessageCounter>>,
+    pop_sticky_assignment_manager: Option<Arc<PopStickyAssignmentManager>>,
+    consumer_manager: Option<Arc<ConsumerManager>>,
+    ack_invisible_time_cap_table: Option<Arc<AckInvisibleTimeCapTable>>,
+    pop_consumer_flow_controller: Option<Arc<PopConsumerFlowController>>,
+    ack_processing_switch: Option<Arc<AckProcessingSwitch>>,
+    pop_buffer_merge_service: Option<ArcMut<PopBufferMergeService>>,
+    broker_stats_manager: Option<Arc<BrokerStatsManager>>,
+    store_host: Option<SocketAddr>,
+    ack_message_hooks: Vec<Box<dyn AckMessageHook>>,
+    ack_request_handlers: Vec<(RequestCode, Box<dyn AckRequestHandler>)>,
+}
+
+/// Why an [`AckMessageProcessor`] could not be built.
+#[derive(Debug, Error)]
+pub enum AckMessageProcessorBuildError {
+    #[error("AckMessageProcessor requires {0}, not set")]
+    MissingDependency(&'static str),
+
+    #[error("store host {0} is not routable and brokerIP1 {1} can not replace it")]
+    UnroutableStoreHost(SocketAddr, CheetahString),
+
+    #[error("request code {0:?} is handled by AckMessageProcessor itself")]
+    ReservedRequestCode(RequestCode),
+}
+
+impl<MS> Default for AckMessageProcessorBuilder<MS> {
+    fn default() -> Self {
+        AckMessageProcessorBuilder {
+            topic_config_manager: None,
+            subscription_group_manager: None,
+            consumer_offset_manager: None,
+            consumer_order_info_manager: None,
+            message_store: None,
+            escape_bridge: None,
+            pop_message_processor: None,
+            broker_config: None,
+            pop_inflight_message_counter: None,
+            pop_sticky_assignment_manager: None,
+            consumer_manager: None,
+            ack_invisible_time_cap_table: None,
+            pop_consumer_flow_controller: None,
+            ack_processing_switch: None,
+            pop_buffer_merge_service: None,
+            broker_stats_manager: None,
+            store_host: None,
+            ack_message_hooks: Vec::new(),
+            ack_request_handlers: Vec::new(),
+        }
+    }
+}
+
+impl<MS> AckMessageProcessorBuilder<MS>
 where
     MS: MessageStore,
 {
-    pub fn new(
-        topic_config_manager: TopicConfigManager,
-        message_store: ArcMut<MS>,
-        escape_bridge: ArcMut<EscapeBridge<MS>>,
-        broker_config: Arc<BrokerConfig>,
+    pub fn topic_config_manager(mut self, topic_config_manager: TopicConfigManager) -> Self {
+        self.topic_config_manager = Some(topic_config_manager);
+        self
+    }
+
+    pub fn subscription_group_manager(
+        mut self,
+        subscription_group_manager: Arc<SubscriptionGroupManager<MS>>,
+    ) -> Self {
+        self.subscription_group_manager = Some(subscription_group_manager);
+        self
+    }
+
+    pub fn consumer_offset_manager(
+        mut self,
+        consumer_offset_manager: Arc<ConsumerOffsetManager>,
+    ) -> Self {
+        self.consumer_offset_manager = Some(consumer_offset_manager);
+        self
+    }
+
+    pub fn consumer_order_info_manager(
+        mut self,
+        consumer_order_info_manager: Arc<ConsumerOrderInfoManager<MS>>,
+    ) -> Self {
+        self.consumer_order_info_manager = Some(consumer_order_info_manager);
+        self
+    }
+
+    pub fn message_store(mut self, message_store: ArcMut<MS>) -> Self {
+        self.message_store = Some(message_store);
+        self
+    }
+
+    pub fn escape_bridge(mut self, escape_bridge: ArcMut<EscapeBridge<MS>>) -> Self {
+        self.escape_bridge = Some(escape_bridge);
+        self
+    }
+
+    pub fn pop_message_processor(
+        mut self,
+        pop_message_processor: ArcMut<PopMessageProcessor<MS>>,
+    ) -> Self {
+        self.pop_message_processor = Some(pop_message_processor);
+        self
+    }
+
+    pub fn broker_config(mut self, broker_config: Arc<BrokerConfig>) -> Self {
+        self.broker_config = Some(broker_config);
+        self
+    }
+
+    pub fn pop_inflight_message_counter(
+        mut self,
         pop_inflight_message_counter: Arc<PopInflightMessageCounter>,
-        store_host: SocketAddr,
-    ) -> AckMessageProcessor<MS> {
-        AckMessageProcessor {
+    ) -> Self {
+        self.pop_inflight_message_counter = Some(pop_inflight_message_counter);
+        self
+    }
+
+    pub fn pop_sticky_assignment_manager(
+        mut self,
+        pop_sticky_assignment_manager: Arc<PopStickyAssignmentManager>,
+    ) -> Self {
+        self.pop_sticky_assignment_manager = Some(pop_sticky_assignment_manager);
+        self
+    }
+
+    pub fn consumer_manager(mut self, consumer_manager: Arc<ConsumerManager>) -> Self {
+        self.consumer_manager = Some(consumer_manager);
+        self
+    }
+
+    pub fn ack_invisible_time_cap_table(
+        mut self,
+        ack_invisible_time_cap_table: Arc<AckInvisibleTimeCapTable>,
+    ) -> Self {
+        self.ack_invisible_time_cap_table = Some(ack_invisible_time_cap_table);
+        self
+    }
+
+    pub fn pop_consumer_flow_controller(
+        mut self,
+        pop_consumer_flow_controller: Arc<PopConsumerFlowController>,
+    ) -> Self {
+        self.pop_consumer_flow_controller = Some(pop_consumer_flow_controller);
+        self
+    }
+
+    pub fn ack_processing_switch(
+        mut self,
+        ack_processing_switch: Arc<AckProcessingSwitch>,
+    ) -> Self {
+        self.ack_processing_switch = Some(ack_processing_switch);
+        self
+    }
+
+    pub fn pop_buffer_merge_service(
+        mut self,
+        pop_buffer_merge_service: ArcMut<PopBufferMergeService>,
+    ) -> Self {
+        self.pop_buffer_merge_service = Some(pop_buffer_merge_service);
+        self
+    }
+
+    pub fn broker_stats_manager(mut self, broker_stats_manager: Arc<BrokerStatsManager>) -> Self {
+        self.broker_stats_manager = Some(broker_stats_manager);
+        self
+    }
+
+    pub fn store_host(mut self, store_host: SocketAddr) -> Self {
+        self.store_host = Some(store_host);
+        self
+    }
+
+    /// Registers `hook` to observe the persisted acks, hooks are called in registration order.
+    pub fn ack_message_hook(mut self, hook: impl AckMessageHook) -> Self {
+        self.ack_message_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Registers `handler` for `request_code`, a code the processor does not handle itself. The
+    /// last handler registered for a code handles it.
+    pub fn ack_request_handler(
+        mut self,
+        request_code: RequestCode,
+        handler: impl AckRequestHandler,
+    ) -> Self {
+        self.ack_request_handlers
+            .push((request_code, Box::new(handler)));
+        self
+    }
+
+    pub fn build(self) -> Result<AckMessageProcessor<MS>, AckMessageProcessorBuildError> {
+        fn required<T>(
+            value: Option<T>,
+            name: &'static str,
+        ) -> Result<T, AckMessageProcessorBuildError> {
+            value.ok_or(AckMessageProcessorBuildError::MissingDependency(name))
+        }
+        let topic_config_manager = required(self.topic_config_manager, "topic_config_manager")?;
+        let subscription_group_manager = required(
+            self.subscription_group_manager,
+            "subscription_group_manager",
+        )?;
+        let consumer_offset_manager =
+            required(self.consumer_offset_manager, "consumer_offset_manager")?;
+        let consumer_order_info_manager = required(
+            self.consumer_order_info_manager,
+            "consumer_order_info_manager",
+        )?;
+        let message_store = required(self.message_store, "message_store")?;
+        let escape_bridge = required(self.escape_bridge, "escape_bridge")?;
+        let pop_message_processor = required(self.pop_message_processor, "pop_message_processor")?;
+        let broker_config = required(self.broker_config, "broker_config")?;
+        let pop_inflight_message_counter = required(
+            self.pop_inflight_message_counter,
+            "pop_inflight_message_counter",
+        )?;
+        let pop_sticky_assignment_manager = required(
+            self.pop_sticky_assignment_manager,
+            "pop_sticky_assignment_manager",
+        )?;
+        let consumer_manager = required(self.consumer_manager, "consumer_manager")?;
+        let ack_invisible_time_cap_table = required(
+            self.ack_invisible_time_cap_table,
+            "ack_invisible_time_cap_table",
+        )?;
+        let pop_consumer_flow_controller = required(
+            self.pop_consumer_flow_controller,
+            "pop_consumer_flow_controller",
+        )?;
+        let ack_processing_switch = required(self.ack_processing_switch, "ack_processing_switch")?;
+        let pop_buffer_merge_service =
+            required(self.pop_buffer_merge_service, "pop_buffer_merge_service")?;
+        let broker_stats_manager = required(self.broker_stats_manager, "broker_stats_manager")?;
+        let store_host = required(self.store_host, "store_host")?;
+        let store_host = resolve_store_host(store_host, &broker_config)?;
+        let mut ack_request_handlers = AckRequestHandlerTable::default();
+        for (request_code, handler) in self.ack_request_handlers {
+            if is_ack_request_code(request_code) {
+                return Err(AckMessageProcessorBuildError::ReservedRequestCode(
+                    request_code,
+                ));
+            }
+            ack_request_handlers.register(request_code, handler);
+        }
+        let pop_ack_unique_id_cache = Arc::new(PopAckUniqueIdCache::new(
+            broker_config.pop_ack_unique_id_cache_size,
+            broker_config.pop_ack_dedup_window_millis,
+            broker_config.enable_pop_ack_unique_id_collision_fallback,
+        ));
+        let pop_dlq_move_cache = Arc::new(PopAckUniqueIdCache::new(
+            broker_config.pop_ack_unique_id_cache_size,
+            i64::MAX as u64,
+            false,
+        ));
+        let ack_priority_gate = Arc::new(AckPriorityGate::new(
+            broker_config.ack_processing_max_concurrency,
+            broker_config.ack_priority_aging_millis,
+        ));
+        let two_phase_ack_table = Arc::new(TwoPhaseAckTable::new(
+            broker_config.two_phase_ack_timeout_millis,
+        ));
+        let ack_parity_telemetry = Arc::new(AckParityTelemetry::new(
+            broker_config.enable_ack_parity_telemetry,
+        ));
+        let ack_put_limiter = Arc::new(AckPutLimiter::new(
+            broker_config.max_concurrent_ack_puts,
+            Duration::from_millis(broker_config.ack_put_permit_timeout_millis),
+        ));
+        let ack_failure_log_sampler = Arc::new(AckFailureLogSampler::new(
+            broker_config.ack_failure_log_sample_rate,
+        ));
+        Ok(AckMessageProcessor {
+            broker_config,
             topic_config_manager,
+            subscription_group_manager,
+            consumer_offset_manager,
+            consumer_order_info_manager,
             message_store,
-            /* need to implement PopBufferMergeService */
-            pop_buffer_merge_service: ArcMut::new(PopBufferMergeService),
+            pop_buffer_merge_service,
             escape_bridge,
+            pop_message_processor,
             store_host,
             pop_inflight_message_counter,
-        }
+            pop_sticky_assignment_manager,
+            consumer_manager,
+            pop_ack_unique_id_cache,
+            pop_dlq_move_cache,
+            ack_priority_gate,
+            ack_invisible_time_cap_table,
+            ack_parity_telemetry,
+            ack_store_latency: Arc::new(AckStoreLatency::new()),
+            ack_failure_log_sampler,
+            ack_put_limiter,
+            pop_consumer_flow_controller,
+            two_phase_ack_table,
+            ack_processing_switch,
+            broker_stats_manager,
+            ack_message_hooks: Arc::new(self.ack_message_hooks),
+            ack_request_handlers: Arc::new(ack_request_handlers),
+        })
+    }
+}
+
+impl<MS> AckMessageProcessor<MS>
+where
+    MS: MessageStore,
+{
+    pub fn builder() -> AckMessageProcessorBuilder<MS> {
+        AckMessageProcessorBuilder::default()
     }
 
+    #[instrument(skip_all, fields(opaque = request.opaque(), request_code = ?request_code))]
     pub async fn process_request(
         &mut self,
         channel: Channel,
@@ -96,20 +528,68 @@ where
         request_code: RequestCode,
         request: RemotingCommand,
     ) -> crate::Result<Option<RemotingCommand>> {
-        match request_code {
-            RequestCode::AckMessage => self.process_ack(channel, ctx, request, true).await,
-            RequestCode::BatchAckMessage => {
-                self.process_batch_ack(channel, ctx, request, true).await
+        if let Some(response) = check_ack_processing_enabled(&self.ack_processing_switch) {
+            return Ok(Some(response));
+        }
+        if let Some(response) = check_not_draining(&self.pop_buffer_merge_service) {
+            return Ok(Some(response));
+        }
+        if let Some(response) = check_store_recovered(
+            self.message_store.get_running_flags(),
+            self.broker_config.reject_ack_during_store_recovery,
+        ) {
+            return Ok(Some(response));
+        }
+        if let Some(response) = check_broker_writeable(&self.broker_config) {
+            return Ok(Some(response));
+        }
+        let timeout_millis = self.broker_config.ack_process_timeout_millis;
+        let process = async {
+            match request_code {
+                RequestCode::AckMessage => self.process_ack(channel, ctx, request, true).await,
+                RequestCode::BatchAckMessage => {
+                    self.process_batch_ack(channel, ctx, request, true).await
+                }
+                RequestCode::EndTwoPhaseAck => {
+                    self.process_end_two_phase_ack(channel, request).await
+                }
+                // the ack codes above are matched first, a registered code costs them nothing
+                _ => match self.ack_request_handlers.get(request_code) {
+                    Some(handler) => handler.handle(channel, ctx, request).await,
+                    None => Ok(Some(
+                        RemotingCommand::create_response_command_with_code_remark(
+                            ResponseCode::MessageIllegal,
+                            format!(
+                                "request code not supported, request code: {:?}",
+                                request_code
+                            ),
+                        ),
+                    )),
+                },
             }
-            _ => Ok(Some(
-                RemotingCommand::create_response_command_with_code_remark(
-                    ResponseCode::MessageIllegal,
-                    format!(
-                        "request code not supported, request code: {:?}",
-                        request_code
+        };
+        if timeout_millis == 0 {
+            return process.await;
+        }
+        // a put still pending is dropped with the request, its in-flight count and the claim on
+        // its unique id are given back
+        match tokio::time::timeout(Duration::from_millis(timeout_millis), process).await {
+            Ok(result) => result,
+            Err(_) => {
+                warn!(
+                    "ack process timeout, more than {}ms, request code: {:?}",
+                    timeout_millis, request_code
+                );
+                Ok(Some(
+                    RemotingCommand::create_response_command_with_code_remark(
+                        ResponseCode::SystemBusy,
+                        format!(
+                            "ack process timeout, more than {}ms, try again later",
+                            timeout_millis
+                        ),
                     ),
-                ),
-            )),
+                ))
+            }
         }
     }
 }
@@ -118,6 +598,7 @@ impl<MS> AckMessageProcessor<MS>
 where
     MS: MessageStore,
 {
+    #[instrument(skip_all, fields(consumer_group, topic, queue_id))]
     async fn process_ack(
         &mut self,
         channel: Channel,
@@ -128,6 +609,17 @@ where
         let request_header = request
             .decode_command_custom_header::<AckMessageRequestHeader>()
             .map_err(BrokerRemotingError)?;
+        record_ack_span_fields(
+            &request_header.consumer_group,
+            &request_header.topic,
+            request_header.queue_id,
+        );
+        if let Some(response) = check_extra_info_length(
+            request_header.extra_info.as_str(),
+            self.broker_config.max_ack_extra_info_length,
+        ) {
+            return Ok(Some(response));
+        }
         let topic_config = self
             .topic_config_manager
             .select_topic_config(&request_header.topic);
@@ -143,7 +635,19 @@ where
                 ),
             ));
         }
+        if let Some(response) = check_subscription_group_exists(
+            &request_header.consumer_group,
+            self.subscription_group_manager
+                .contains_subscription_group(&request_header.consumer_group)
+                || mix_all::is_sys_consumer_group(&request_header.consumer_group),
+            self.broker_config.auto_create_subscription_group,
+        ) {
+            return Ok(Some(response));
+        }
         let topic_config = topic_config.unwrap();
+        if let Some(response) = check_topic_readable(&topic_config) {
+            return Ok(Some(response));
+        }
         if request_header.queue_id >= topic_config.read_queue_nums as i32
             || request_header.queue_id < 0
         {
@@ -161,34 +665,82 @@ where
                 ),
             ));
         }
-        let min_offset = self
-            .message_store
-            .get_min_offset_in_queue(&request_header.topic, request_header.queue_id);
-        let max_offset = self
-            .message_store
-            .get_max_offset_in_queue(&request_header.topic, request_header.queue_id);
+        let (min_offset, max_offset) = read_queue_offset_range(
+            || {
+                self.message_store
+                    .get_offset_range_in_queue(&request_header.topic, request_header.queue_id)
+            },
+            || {
+                self.message_store
+                    .get_min_offset_in_queue(&request_header.topic, request_header.queue_id)
+            },
+        );
         if request_header.offset < min_offset || request_header.offset > max_offset {
-            let error_msg = format!(
-                "request offset not in queue offset range, request offset: {}, min offset: {}, \
-                 max offset: {}",
-                request_header.offset, min_offset, max_offset
+            self.broker_stats_manager.inc_group_ack_out_of_range_nums(
+                &request_header.consumer_group,
+                &request_header.topic,
+                1,
             );
-            return Ok(Some(
-                RemotingCommand::create_response_command_with_code_remark(
-                    ResponseCode::NoMessage,
-                    error_msg,
-                ),
-            ));
         }
+        if request_header.offset < min_offset {
+            let subscription_group_config = self
+                .subscription_group_manager
+                .find_subscription_group_config_inner(&request_header.consumer_group);
+            return Ok(Some(ack_cleaned_offset(
+                get_ack_cleaned_offset_policy(subscription_group_config.as_ref()),
+                &self.consumer_offset_manager,
+                channel.remote_address(),
+                &request_header,
+                min_offset,
+                max_offset,
+            )));
+        }
+        if request_header.offset > max_offset {
+            return Ok(Some(ack_offset_ahead(
+                &request_header,
+                min_offset,
+                max_offset,
+            )));
+        }
+        let revive_shard_key = if is_revive_sharding_enabled(&topic_config) {
+            request.get_ext_fields().and_then(|ext_fields| {
+                ext_fields
+                    .get(MessageConst::PROPERTY_SHARDING_KEY)
+                    .filter(|shard_key| !shard_key.is_empty())
+                    .cloned()
+            })
+        } else {
+            None
+        };
+        let subscription_group_config = self
+            .subscription_group_manager
+            .find_subscription_group_config_inner(&request_header.consumer_group);
+        if is_two_phase_ack_enabled(subscription_group_config.as_ref()) {
+            let ack_transaction_id = self
+                .two_phase_ack_table
+                .prepare(request_header, revive_shard_key);
+            let mut response = RemotingCommand::create_response_command();
+            response.add_ext_field(ACK_TRANSACTION_ID, ack_transaction_id);
+            return Ok(Some(response));
+        }
+        let _permit = self.ack_priority_gate.acquire(1).await;
         let mut response = RemotingCommand::create_response_command();
-        self.append_ack(Some(request_header), &mut response, None, &channel, None)
-            .await;
+        self.append_ack(
+            Some(request_header),
+            &mut response,
+            None,
+            &channel,
+            None,
+            revive_shard_key,
+            &mut 0,
+        )
+        .await;
         Ok(Some(response))
     }
 
     async fn process_batch_ack(
         &mut self,
-        _channel: Channel,
+        channel: Channel,
         _ctx: ConnectionHandlerContext,
         request: RemotingCommand,
         _broker_allow_suspend: bool,
@@ -205,15 +757,175 @@ where
                 ResponseCode::NoMessage,
             )));
         }
+        if let Some(ack) = req_body.acks.iter().find(|ack| {
+            is_two_phase_ack_enabled(
+                self.subscription_group_manager
+                    .find_subscription_group_config_inner(&ack.consumer_group)
+                    .as_ref(),
+            )
+        }) {
+            return Ok(Some(
+                RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::IllegalOperation,
+                    format!(
+                        "batch ack is not supported by two-phase ack group {}",
+                        ack.consumer_group
+                    ),
+                ),
+            ));
+        }
+        if let Some(response) = check_batch_ack_bit_set_size(
+            &req_body.acks,
+            self.broker_config.batch_ack_max_bit_set_size,
+        ) {
+            return Ok(Some(response));
+        }
+        if let Some(response) =
+            check_batch_ack_count(&req_body.acks, self.broker_config.batch_ack_max_count)
+        {
+            return Ok(Some(response));
+        }
+        let ack_count = req_body
+            .acks
+            .iter()
+            .map(|ack| ack.bit_set.0.count_ones())
+            .sum();
+        let _permit = self.ack_priority_gate.acquire(ack_count).await;
         let mut response = RemotingCommand::create_response_command();
+        let mut stream = (self.broker_config.ack_stream_chunk_size > 0
+            && is_stream_response_requested(&request))
+        .then(|| {
+            (
+                ResponseStream::new(channel.clone(), request.opaque()),
+                AckStreamProgress::new(self.broker_config.ack_stream_chunk_size),
+            )
+        });
+        let encoding = batch_ack_result_encoding(&self.broker_config);
+        let mut skipped_offsets = 0;
+        let mut result = BatchAckResult::default();
+        let mut detail = is_batch_ack_detail_requested(&request).then(BatchAckDetail::default);
         let broker_name = &req_body.broker_name;
-        for ack in req_body.acks {
-            self.append_ack(None, &mut response, Some(ack), &_channel, Some(broker_name))
-                .await;
+        // acks of different revive queues are stored concurrently, those of a revive queue in
+        // order, so that the revive service reads them as they were sent
+        let semaphore = Semaphore::new(self.broker_config.batch_ack_revive_put_concurrency.max(1));
+        let processor = &*self;
+        let channel = &channel;
+        let append_group = |group: Vec<(usize, BatchAck)>| {
+            let mut processor = processor.clone();
+            async move {
+                let mut response = RemotingCommand::create_response_command();
+                let mut skipped_offsets = 0;
+                let mut appended = Vec::with_capacity(group.len());
+                for (index, ack) in group {
+                    let (topic, queue_id) = (ack.topic.clone(), ack.queue_id);
+                    let stored = processor
+                        .append_ack(
+                            None,
+                            &mut response,
+                            Some(ack),
+                            channel,
+                            Some(broker_name),
+                            None,
+                            &mut skipped_offsets,
+                        )
+                        .await;
+                    appended.push((index, topic, queue_id, stored));
+                }
+                (response, skipped_offsets, appended)
+            }
+        };
+        let mut pending = run_groups_concurrently(
+            group_batch_acks_by_revive_queue(req_body.acks),
+            &semaphore,
+            &append_group,
+        );
+        let mut appended = Vec::new();
+        while let Some((group_response, group_skipped_offsets, group_appended)) =
+            pending.next().await
+        {
+            if response.code() == ResponseCode::Success as i32
+                && group_response.code() != ResponseCode::Success as i32
+            {
+                response.set_code_ref(ResponseCode::from(group_response.code()));
+                if let Some(remark) = group_response.remark() {
+                    response.set_remark_mut(remark.clone());
+                }
+            }
+            skipped_offsets += group_skipped_offsets;
+            if let Some((stream, progress)) = stream.as_mut() {
+                for (_, _, _, stored) in group_appended.iter() {
+                    let acked = stored.as_ref().map_or(0, |offsets| offsets.len());
+                    send_ack_progress(stream, progress, acked).await;
+                }
+            }
+            appended.extend(group_appended);
+        }
+        appended.sort_by_key(|(index, _, _, _)| *index);
+        for (index, topic, queue_id, stored) in appended {
+            if let Some(detail) = detail.as_mut() {
+                match stored {
+                    Some(_) => detail.succeeded.push(index as u32),
+                    None => detail.failed.push(index as u32),
+                }
+            }
+            let acked_offsets = stored.unwrap_or_default();
+            if !acked_offsets.is_empty() {
+                result
+                    .acked
+                    .push(AckedOffsets::new(topic, queue_id, &acked_offsets, encoding));
+            }
+        }
+        result.skipped_count = skipped_offsets as u64;
+        result.detail = detail;
+        if let Ok(body) = result.encode() {
+            response.set_body_mut_ref(body);
+        }
+        if let Some((stream, progress)) = stream {
+            response = stream.finish(progress.finish(response));
+        }
+        Ok(Some(response))
+    }
+
+    /// Ends an ack prepared by a two-phase ack group, persisting it on commit and releasing
+    /// it otherwise.
+    async fn process_end_two_phase_ack(
+        &mut self,
+        channel: Channel,
+        request: RemotingCommand,
+    ) -> crate::Result<Option<RemotingCommand>> {
+        let request_header = request
+            .decode_command_custom_header::<EndTwoPhaseAckRequestHeader>()
+            .map_err(BrokerRemotingError)?;
+        let group = &request_header.consumer_group;
+        let id = &request_header.ack_transaction_id;
+        if !request_header.commit {
+            return Ok(Some(match self.two_phase_ack_table.abort(group, id) {
+                Ok(()) => RemotingCommand::create_response_command(),
+                Err(e) => two_phase_ack_failed(e, id),
+            }));
         }
+        let prepared = match self.two_phase_ack_table.confirm(group, id) {
+            Ok(prepared) => prepared,
+            Err(e) => return Ok(Some(two_phase_ack_failed(e, id))),
+        };
+        let _permit = self.ack_priority_gate.acquire(1).await;
+        let mut response = RemotingCommand::create_response_command();
+        self.append_ack(
+            Some(prepared.request_header),
+            &mut response,
+            None,
+            &channel,
+            None,
+            prepared.revive_shard_key,
+            &mut 0,
+        )
+        .await;
         Ok(Some(response))
     }
 
+    /// Appends an ack, or the acks of a batch, to the revive topic and returns the offsets
+    /// acked, or `None` if the ack could not be stored and the client has to retry it.
+    #[instrument(skip_all, fields(consumer_group, topic, queue_id))]
     async fn append_ack(
         &mut self,
         request_header: Option<AckMessageRequestHeader>,
@@ -221,7 +933,14 @@ where
         batch_ack: Option<BatchAck>,
         channel: &Channel,
         broker_name: Option<&CheetahString>,
-    ) {
+        revive_shard_key: Option<CheetahString>,
+        skipped_offsets: &mut usize,
+    ) -> Option<Vec<i64>> {
+        let ack_kind = if batch_ack.is_some() {
+            AckKind::Batch
+        } else {
+            AckKind::Single
+        };
         //handle single ack
         let (
             consume_group,
@@ -231,25 +950,42 @@ where
             start_offset,
             ack_offset,
             pop_time,
+            revive_pop_time,
             invisible_time,
-            ack_count,
+            acked_offsets,
             mut ack_msg,
             broker_name,
         ) = if let Some(request_header) = request_header {
-            let extra_info =
-                ExtraInfoUtil::split(request_header.extra_info.as_str()).unwrap_or_default();
-            let broker_name =
-                ExtraInfoUtil::get_broker_name(extra_info.as_slice()).unwrap_or_default();
+            let ParsedAckHandle {
+                ck_queue_offset: start_offset,
+                pop_time,
+                invisible_time,
+                revive_qid: r_qid,
+                retry,
+                broker_name,
+            } = parse_ack_extra_info(
+                channel.pop_ack_protocol_version(),
+                request_header.extra_info.as_str(),
+                response,
+            )?;
             let consume_group = request_header.consumer_group.clone();
-            let topic = request_header.topic.clone();
+            let topic = match real_ack_topic(&request_header.topic, &consume_group, &retry) {
+                Ok(topic) => topic,
+                Err(e) => {
+                    warn!("ack rejected, {}", e);
+                    response.set_code_ref(e.response_code());
+                    response.set_remark_mut(e.to_string());
+                    return None;
+                }
+            };
             let qid = request_header.queue_id;
-            let r_qid = ExtraInfoUtil::get_revive_qid(extra_info.as_slice()).unwrap_or_default();
-            let start_offset =
-                ExtraInfoUtil::get_ck_queue_offset(extra_info.as_slice()).unwrap_or_default();
             let ack_offset = request_header.offset;
-            let pop_time = ExtraInfoUtil::get_pop_time(extra_info.as_slice()).unwrap_or_default();
-            let invisible_time =
-                ExtraInfoUtil::get_invisible_time(extra_info.as_slice()).unwrap_or_default();
+            let revive_pop_time = correct_ack_pop_time(
+                pop_time,
+                request_header.observed_pop_time,
+                self.broker_config.ack_pop_time_skew_tolerance_millis,
+                response,
+            )?;
             if r_qid == POP_ORDER_REVIVE_QUEUE {
                 self.ack_orderly(
                     topic,
@@ -260,11 +996,18 @@ where
                     invisible_time,
                     channel,
                     response,
-                );
-                return;
+                )
+                .await;
+                return Some(Vec::new());
             }
+            let r_qid = match revive_shard_key.as_ref() {
+                Some(shard_key) => select_revive_queue_id(
+                    shard_key,
+                    self.revive_queue_num_of_topic(&topic, &consume_group),
+                ),
+                None => r_qid,
+            };
             let ack = AckMsg::default();
-            let ack_count = 1;
             (
                 consume_group,
                 topic,
@@ -273,47 +1016,76 @@ where
                 start_offset,
                 ack_offset,
                 pop_time,
+                revive_pop_time,
                 invisible_time,
-                ack_count,
+                vec![ack_offset],
                 Box::new(ack) as Box<dyn AckMessage + Send>,
-                CheetahString::from(broker_name),
+                broker_name,
             )
         } else {
             //handle batch ack
             let batch_ack = batch_ack.unwrap();
             let consume_group = batch_ack.consumer_group.clone();
-            let topic = CheetahString::from(
-                ExtraInfoUtil::get_real_topic_with_retry(
-                    batch_ack.topic.as_str(),
-                    batch_ack.consumer_group.as_str(),
-                    batch_ack.retry.as_str(),
-                )
-                .unwrap_or_default(),
-            );
+            let topic = match real_ack_topic(&batch_ack.topic, &consume_group, &batch_ack.retry) {
+                Ok(topic) => topic,
+                Err(e) => {
+                    warn!("batch ack rejected, {}", e);
+                    response.set_code_ref(e.response_code());
+                    response.set_remark_mut(e.to_string());
+                    return None;
+                }
+            };
             let qid = batch_ack.queue_id;
             let r_qid = batch_ack.revive_queue_id;
             let start_offset = batch_ack.start_offset;
             let akc_offset = -1;
             let pop_time = batch_ack.pop_time;
             let invisible_time = batch_ack.invisible_time;
-            let min_offset = self.message_store.get_min_offset_in_queue(&topic, qid);
-            let max_offset = self.message_store.get_max_offset_in_queue(&topic, qid);
-            if min_offset == -1 || max_offset == -1 {
-                //error!("Illegal topic or queue found when batch ack {:?}", batch_ack);
-                return;
+            if !self.message_store.queue_exists(&topic, qid) {
+                let error_info = format!(
+                    "Illegal topic or queue found when batch ack, topic={}, queueId={}, client={}",
+                    topic,
+                    qid,
+                    channel.remote_address()
+                );
+                warn!("{}", error_info);
+                response.set_code_ref(ResponseCode::SystemError);
+                response.set_remark_mut(error_info);
+                return None;
             }
+            let (min_offset, max_offset) = read_queue_offset_range(
+                || self.message_store.get_offset_range_in_queue(&topic, qid),
+                || self.message_store.get_min_offset_in_queue(&topic, qid),
+            );
 
             let mut batch_ack_msg = BatchAckMsg::default();
 
-            let bit_set = &batch_ack.bit_set.0;
-            for i in bit_set.iter_ones() {
-                if i == usize::MAX {
-                    break;
-                }
-                let offset = batch_ack.start_offset + i as i64;
-                if offset < min_offset || offset > max_offset {
-                    continue;
-                }
+            let (offsets, skipped) = select_batch_ack_offsets(
+                batch_ack.bit_set.0.iter_ones(),
+                batch_ack.start_offset,
+                min_offset,
+                max_offset,
+            );
+            if skipped > 0 {
+                warn!(
+                    "batch ack skipped {} offsets out of queue offset range, topic={}, \
+                     queueId={}, startOffset={}, minOffset={}, maxOffset={}, client={}",
+                    skipped,
+                    topic,
+                    qid,
+                    start_offset,
+                    min_offset,
+                    max_offset,
+                    channel.remote_address()
+                );
+                *skipped_offsets += skipped;
+                self.broker_stats_manager.inc_group_ack_out_of_range_nums(
+                    &consume_group,
+                    &topic,
+                    skipped as i32,
+                );
+            }
+            for offset in offsets {
                 if r_qid == POP_ORDER_REVIVE_QUEUE {
                     self.ack_orderly(
                         topic.clone(),
@@ -324,18 +1096,16 @@ where
                         invisible_time,
                         channel,
                         response,
-                    );
+                    )
+                    .await;
                 } else {
                     batch_ack_msg.ack_offset_list.push(offset);
                 }
             }
             if r_qid == POP_ORDER_REVIVE_QUEUE || batch_ack_msg.ack_offset_list.is_empty() {
-                return;
-            }
-            if r_qid == POP_ORDER_REVIVE_QUEUE || batch_ack_msg.ack_offset_list.is_empty() {
-                return;
+                return Some(Vec::new());
             }
-            let ack_count = batch_ack_msg.ack_offset_list.len();
+            let acked_offsets = batch_ack_msg.ack_offset_list.clone();
             //let ack = batch_ack_msg.ack_msg;
             (
                 consume_group,
@@ -345,101 +1115,3874 @@ where
                 start_offset,
                 -1,
                 pop_time,
+                pop_time,
                 invisible_time,
-                ack_count,
+                acked_offsets,
                 Box::new(batch_ack_msg) as Box<dyn AckMessage + Send>,
                 broker_name.unwrap().clone(),
             )
         };
 
-        //this.brokerController.getBrokerStatsManager().incBrokerAckNums(ackCount);
-        //this.brokerController.getBrokerStatsManager().incGroupAckNums(consumeGroup,topic,
-        // ackCount);
+        record_ack_span_fields(&consume_group, &topic, qid);
+        check_ack_revive_qid(
+            r_qid,
+            self.revive_queue_num_of_topic(&topic, &consume_group),
+            response,
+        )?;
+        let invisible_time = clamp_ack_invisible_time(
+            invisible_time,
+            self.broker_config.min_ack_invisible_time_millis,
+            self.broker_config.max_ack_invisible_time_millis,
+            response,
+        )?;
         ack_msg.set_consumer_group(consume_group.clone());
         ack_msg.set_topic(topic.clone());
         ack_msg.set_queue_id(qid);
         ack_msg.set_start_offset(start_offset);
         ack_msg.set_ack_offset(ack_offset);
         ack_msg.set_pop_time(pop_time);
-        ack_msg.set_broker_name(broker_name);
+        ack_msg.set_broker_name(broker_name.clone());
+        let ack_body = match ack_msg.encode_body() {
+            Ok(ack_body) => Bytes::from(ack_body),
+            Err(e) => {
+                error!(
+                    "encode ack msg failed, uniqueId={}, error={}",
+                    ack_msg.unique_id(),
+                    e
+                );
+                response.set_code_ref(ResponseCode::SystemError);
+                response.set_remark_mut(format!("encode ack msg failed: {}", e));
+                return None;
+            }
+        };
+        // a client retrying an ack already stored gets the same answer without a second write
+        let unique_id = match self.pop_ack_unique_id_cache.claim(
+            ack_msg.unique_id(),
+            &ack_body,
+            get_current_millis() as i64,
+        ) {
+            AckIdClaim::Claimed(unique_id) => unique_id,
+            AckIdClaim::Collided(unique_id) => {
+                self.broker_stats_manager
+                    .inc_broker_ack_unique_id_collision_nums();
+                unique_id
+            }
+            AckIdClaim::Duplicate => {
+                info!("duplicate ack skipped, uniqueId={}", ack_msg.unique_id());
+                return Some(acked_offsets);
+            }
+        };
+        // given back unless the ack is stored, even if this ack is dropped before its put is done
+        let claim = self.pop_ack_unique_id_cache.release_on_drop(&unique_id);
+        let ack_count = acked_offsets.len() as i32;
+        self.broker_stats_manager.inc_broker_ack_nums(ack_count);
+        self.broker_stats_manager
+            .inc_group_ack_nums(&consume_group, &topic, ack_count);
         if self
             .pop_buffer_merge_service
             .add_ack(r_qid, ack_msg.as_ref())
         {
-            return;
+            claim.cancel();
+            let in_flight = self.pop_inflight_message_counter.decrement_on_drop(
+                &topic,
+                &consume_group,
+                pop_time,
+                qid,
+                acked_offsets.len() as i64,
+            );
+            self.on_acked(
+                &consume_group,
+                &topic,
+                &broker_name,
+                qid,
+                channel,
+                &acked_offsets,
+                in_flight,
+                None,
+            )
+            .await;
+            return Some(acked_offsets);
         }
+        self.ack_parity_telemetry
+            .record(ParityGap::BufferMergeFallthrough);
         let mut inner = MessageExtBrokerInner::default();
-        inner.set_topic(topic.clone());
-        inner.message_ext_inner.queue_id = qid;
-        if let Some(batch_ack) = ack_msg.as_any().downcast_ref::<BatchAckMsg>() {
-            inner.set_body(Bytes::from(batch_ack.encode().unwrap()));
-            inner.set_tags(CheetahString::from_static_str(
-                PopAckConstants::BATCH_ACK_TAG,
-            ));
-            inner.put_property(
-                CheetahString::from_static_str(
-                    MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX,
-                ),
-                CheetahString::from(PopMessageProcessor::gen_batch_ack_unique_id(batch_ack)),
-            );
-        } else if let Some(ack_msg) = ack_msg.as_any().downcast_ref::<AckMsg>() {
-            inner.set_body(Bytes::from(ack_msg.encode().unwrap()));
-            inner.set_tags(CheetahString::from_static_str(PopAckConstants::ACK_TAG));
+        inner.set_topic(CheetahString::from_string(
+            PopAckConstants::build_cluster_revive_topic(
+                self.broker_config
+                    .broker_identity
+                    .broker_cluster_name
+                    .as_str(),
+            ),
+        ));
+        inner.message_ext_inner.queue_id = r_qid;
+        if let Some(shard_key) = revive_shard_key {
+            // lets the revive reader recompute the revive queue of the checkpoint
             inner.put_property(
-                CheetahString::from_static_str(
-                    MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX,
-                ),
-                CheetahString::from(PopMessageProcessor::gen_ack_unique_id(
-                    ack_msg as &dyn AckMessage,
-                )),
+                CheetahString::from_static_str(MessageConst::PROPERTY_SHARDING_KEY),
+                shard_key,
             );
         }
+        set_ack_body(&mut inner, ack_msg.ack_tag(), ack_body);
         inner.message_ext_inner.born_timestamp = get_current_millis() as i64;
         inner.message_ext_inner.store_host = self.store_host;
-        inner.set_delay_time_ms((pop_time + invisible_time) as u64);
+        set_revive_host_flags(&mut inner);
+        let invisible_time = self
+            .ack_invisible_time_cap_table
+            .clamp(&consume_group, invisible_time);
+        inner.set_delay_time_ms((revive_pop_time + invisible_time) as u64);
         inner.put_property(
             CheetahString::from_static_str(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX),
-            CheetahString::from(PopMessageProcessor::gen_ack_unique_id(ack_msg.as_ref())),
+            CheetahString::from(unique_id.as_str()),
         );
+        if self.broker_config.enable_ack_property_enrichment {
+            enrich_ack_properties(&self.broker_config, &mut inner);
+        }
         inner.properties_string =
             message_decoder::message_properties_to_string(inner.get_properties());
-        let put_message_result = self
-            .escape_bridge
-            .put_message_to_specific_queue(inner)
-            .await;
-        match put_message_result.put_message_status() {
-            PutMessageStatus::PutOk
-            | PutMessageStatus::FlushDiskTimeout
-            | PutMessageStatus::FlushSlaveTimeout
-            | PutMessageStatus::SlaveNotAvailable => {}
-            _ => {
+        let route = AckWriteRoute::of(&self.broker_config, &broker_name);
+        if route == AckWriteRoute::Unavailable {
+            warn!(
+                "broker is read-only and can not forward ack to master, \
+                 enableSlaveActingMaster={}, enableRemoteEscape={}",
+                self.broker_config.enable_slave_acting_master,
+                self.broker_config.enable_remote_escape
+            );
+            response.set_code_ref(ResponseCode::ServiceNotAvailable);
+            response.set_remark_mut("broker is read-only, ack must be sent to master");
+            return None;
+        }
+        // held until the put and its retries are done
+        let Some(put_permit) = self.ack_put_limiter.acquire().await else {
+            warn!(
+                "too many ack puts in flight, max: {}, ack of {} rejected",
+                self.ack_put_limiter.max_concurrent_puts(),
+                unique_id
+            );
+            response.set_code_ref(ResponseCode::SystemBusy);
+            response.set_remark_mut(format!(
+                "too many ack puts in flight, more than {}, try again later",
+                self.ack_put_limiter.max_concurrent_puts()
+            ));
+            return None;
+        };
+        // given back even if this ack is dropped while its put is pending, as it may be stored
+        let in_flight = self.pop_inflight_message_counter.decrement_on_drop(
+            &topic,
+            &consume_group,
+            pop_time,
+            qid,
+            acked_offsets.len() as i64,
+        );
+        let put_message_result = put_ack_msg_with_retry(
+            self.broker_config.revive_ack_msg_retry_times,
+            ACK_PUT_RETRY_BACKOFF,
+            || {
+                let mut escape_bridge = self.escape_bridge.clone();
+                let inner = &inner;
+                let broker_name = &broker_name;
+                let ack_store_latency = &self.ack_store_latency;
+                async move {
+                    let begin = Instant::now();
+                    let put_message_result = if route == AckWriteRoute::Remote {
+                        put_ack_msg_to_broker(escape_bridge.as_mut(), inner, broker_name).await
+                    } else {
+                        escape_bridge
+                            .put_message_to_specific_queue(copy_ack_msg(inner))
+                            .await
+                    };
+                    ack_store_latency.record(ack_kind, begin.elapsed());
+                    put_message_result
+                }
+            },
+        )
+        .await;
+        drop(put_permit);
+        if !is_put_ok(put_message_result.put_message_status()) {
+            if let Some(failures) = self.ack_failure_log_sampler.sample() {
                 error!(
-                    "put ack msg error:{:?}",
-                    put_message_result.put_message_status()
+                    "put ack msg error:{:?}, failed acks so far: {}",
+                    put_message_result.put_message_status(),
+                    failures
                 );
             }
+            // the client retries an ack that did not stick, otherwise the messages stay in
+            // flight until they are revived
+            if route == AckWriteRoute::Master {
+                response.set_code_ref(ResponseCode::ServiceNotAvailable);
+                response.set_remark_mut(format!(
+                    "forward ack to master failed, status: {:?}",
+                    put_message_result.put_message_status()
+                ));
+            } else {
+                response.set_code_ref(ResponseCode::SystemError);
+                response.set_remark_mut(format!(
+                    "put ack msg to revive topic failed, status: {:?}",
+                    put_message_result.put_message_status()
+                ));
+            }
+            in_flight.cancel();
+            return None;
         }
-        self.pop_inflight_message_counter
-            .decrement_in_flight_message_num(
-                &topic,
-                &consume_group,
-                pop_time,
-                qid,
-                ack_count as i64,
-            );
+        claim.cancel();
+        self.on_acked(
+            &consume_group,
+            &topic,
+            &broker_name,
+            qid,
+            channel,
+            &acked_offsets,
+            in_flight,
+            put_message_result.queue_offset(),
+        )
+        .await;
+        Some(acked_offsets)
+    }
+
+    /// Returns the number of revive queues of `topic`. A pop retry topic counts as the topic it
+    /// retries, its messages are popped, and get their revive queue, along with the ones of it.
+    fn revive_queue_num_of_topic(&self, topic: &CheetahString, consumer_group: &str) -> u32 {
+        let retry_prefix = format!("{}{}", mix_all::RETRY_GROUP_TOPIC_PREFIX, consumer_group);
+        let topic_config = if topic.len() > retry_prefix.len() && topic.starts_with(&retry_prefix) {
+            self.topic_config_manager
+                .select_topic_config(&CheetahString::from_string(KeyBuilder::parse_normal_topic(
+                    topic,
+                    consumer_group,
+                )))
+        } else {
+            self.topic_config_manager.select_topic_config(topic)
+        };
+        revive_queue_num_of(topic_config.as_ref(), self.broker_config.revive_queue_num)
     }
 
-    fn ack_orderly(
+    /// Accounts for the messages at `offsets` once their ack is stored, at
+    /// `revive_queue_offset` if known, or merged into its buffered checkpoint, `in_flight` gives
+    /// back their inflight number.
+    async fn on_acked(
         &mut self,
-        topic: CheetahString,
-        consume_group: CheetahString,
-        q_id: i32,
-        ack_offset: i64,
-        pop_time: i64,
-        invisible_time: i64,
+        group: &CheetahString,
+        topic: &CheetahString,
+        broker_name: &CheetahString,
+        queue_id: i32,
         channel: &Channel,
-        response: &mut RemotingCommand,
+        offsets: &[i64],
+        in_flight: InFlightDecrement,
+        revive_queue_offset: Option<i64>,
+    ) {
+        // an ack of its owner on a sticky queue means it is still consuming it, keep the lease
+        // alive
+        if self.broker_config.enable_pop_sticky_assignment {
+            let client_id = self
+                .consumer_manager
+                .get_consumer_group_info(group)
+                .and_then(|group_info| group_info.find_channel_by_channel(channel))
+                .map(|channel_info| channel_info.client_id().clone());
+            if let Some(client_id) = client_id {
+                let broker_name = if broker_name.is_empty() {
+                    &self.broker_config.broker_identity.broker_name
+                } else {
+                    broker_name
+                };
+                self.pop_sticky_assignment_manager.renew(
+                    group,
+                    &MessageQueue::from_parts(topic.clone(), broker_name.clone(), queue_id),
+                    &client_id,
+                    get_current_millis(),
+                );
+            }
+        }
+        drop(in_flight);
+        if !self.ack_message_hooks.is_empty() {
+            let context = AckMessageContext {
+                consumer_group: group.clone(),
+                topic: topic.clone(),
+                queue_id,
+                ack_offset: offsets.first().copied().unwrap_or(-1),
+                ack_count: offsets.len(),
+                revive_queue_offset,
+            };
+            for hook in self.ack_message_hooks.iter() {
+                hook.ack_persisted(&context);
+            }
+        }
+        // the queue of an escaped message is owned by the broker it was popped from
+        match reconcile_origin_offset(
+            self.escape_bridge.as_ref(),
+            self.broker_config.broker_identity.broker_name.as_str(),
+            broker_name,
+            group,
+            topic,
+            queue_id,
+            offsets,
+        )
+        .await
+        {
+            Ok(Some(offset)) => info!(
+                "reconciled offset of escaped ack on origin broker {}, group={}, topic={}, \
+                 queueId={}, offset={}",
+                broker_name, group, topic, queue_id, offset
+            ),
+            Ok(None) => {}
+            Err(e) => warn!(
+                "reconcile offset on origin broker {} failed, group={}, topic={}, queueId={}, \
+                 error={}",
+                broker_name, group, topic, queue_id, e
+            ),
+        }
+        // acks flowing back free room for the next pops of the consumer
+        self.pop_consumer_flow_controller.record_acked(
+            group,
+            channel.remote_address(),
+            offsets.len() as u64,
+        );
+        // only a message redelivered from the retry topic can have exhausted its retries
+        if self.broker_config.enable_pop_retry_dlq_fallback
+            && topic.starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX)
+        {
+            self.move_exhausted_to_dlq(group, topic, queue_id, offsets)
+                .await;
+        }
+    }
+
+    /// Copies the messages at `offsets` to the DLQ topic of `group` when they were redelivered
+    /// as many times as the group allows, so that they are kept once the ack ends their
+    /// redelivery. The offsets are read in a single pass and a message is moved only once.
+    async fn move_exhausted_to_dlq(
+        &mut self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        offsets: &[i64],
     ) {
-        unimplemented!("ack_orderly")
+        let (Some(&min_offset), Some(&max_offset)) = (offsets.iter().min(), offsets.iter().max())
+        else {
+            return;
+        };
+        let max_reconsume_times = self
+            .subscription_group_manager
+            .find_subscription_group_config_inner(group)
+            .unwrap_or_default()
+            .retry_max_times();
+        let Some(get_message_result) = self
+            .message_store
+            .get_message(
+                group,
+                topic,
+                queue_id,
+                min_offset,
+                (max_offset - min_offset + 1) as i32,
+                i32::MAX,
+                None,
+            )
+            .await
+        else {
+            return;
+        };
+        let exhausted: Vec<MessageExt> = get_message_result
+            .message_mapped_list()
+            .iter()
+            .filter_map(|mapped| {
+                message_decoder::decode(&mut mapped.get_bytes()?, true, false, false, false, false)
+            })
+            .filter(|msg_ext| {
+                offsets.contains(&msg_ext.queue_offset)
+                    && is_pop_retry_exhausted(msg_ext.reconsume_times, max_reconsume_times)
+            })
+            .collect();
+        if exhausted.is_empty() {
+            return;
+        }
+        let dlq_topic = CheetahString::from_string(mix_all::get_dlq_topic(group));
+        if self
+            .topic_config_manager
+            .create_topic_in_send_message_back_method(
+                &dlq_topic,
+                DLQ_NUMS_PER_GROUP as i32,
+                PermName::PERM_WRITE | PermName::PERM_READ,
+                false,
+                0,
+            )
+            .is_none()
+        {
+            warn!(
+                "create dlq topic {} failed, keep {} messages",
+                dlq_topic,
+                exhausted.len()
+            );
+            return;
+        }
+        for msg_ext in exhausted {
+            let offset = msg_ext.queue_offset;
+            let move_id = format!("{}@{}@{}@{}", group, topic, queue_id, offset);
+            if self.pop_dlq_move_cache.claim(
+                move_id.clone(),
+                &Bytes::new(),
+                get_current_millis() as i64,
+            ) == AckIdClaim::Duplicate
+            {
+                continue;
+            }
+            let inner = build_pop_dlq_message(&msg_ext, dlq_topic.clone(), self.store_host);
+            let put_message_result = self.escape_bridge.put_message(inner).await;
+            if is_put_ok(put_message_result.put_message_status()) {
+                info!(
+                    "move pop message to dlq, group={}, topic={}, queueId={}, offset={}, \
+                     reconsumeTimes={}",
+                    group, topic, queue_id, offset, msg_ext.reconsume_times
+                );
+            } else {
+                self.pop_dlq_move_cache.release(&move_id);
+                error!(
+                    "move pop message to dlq failed, group={}, topic={}, queueId={}, offset={}, \
+                     status={:?}",
+                    group,
+                    topic,
+                    queue_id,
+                    offset,
+                    put_message_result.put_message_status()
+                );
+            }
+        }
+    }
+
+    async fn ack_orderly(
+        &mut self,
+        topic: CheetahString,
+        consume_group: CheetahString,
+        q_id: i32,
+        ack_offset: i64,
+        pop_time: i64,
+        _invisible_time: i64,
+        channel: &Channel,
+        response: &mut RemotingCommand,
+    ) {
+        let old_offset = self
+            .consumer_offset_manager
+            .query_offset(&consume_group, &topic, q_id);
+        if ack_offset < old_offset {
+            return;
+        }
+        let queue_lock_manager = self.pop_message_processor.queue_lock_manager();
+        // the queue is locked while a pop reads it, the client retries the ack meanwhile
+        if !queue_lock_manager
+            .try_lock(&topic, &consume_group, q_id)
+            .await
+        {
+            response.set_code_ref(ResponseCode::SystemBusy);
+            response.set_remark_mut(format!(
+                "queue is locked, try ack again later, key:{}",
+                QueueLockManager::build_lock_key(&topic, &consume_group, q_id)
+            ));
+            return;
+        }
+        let applied = commit_orderly_ack(
+            &self.consumer_offset_manager,
+            || {
+                self.consumer_order_info_manager.commit_and_next(
+                    &topic,
+                    &consume_group,
+                    q_id,
+                    ack_offset as u64,
+                    pop_time as u64,
+                )
+            },
+            channel.remote_address(),
+            &topic,
+            &consume_group,
+            q_id,
+            ack_offset,
+            response,
+        );
+        queue_lock_manager
+            .unlock(&topic, &consume_group, q_id)
+            .await;
+        if applied {
+            self.pop_inflight_message_counter
+                .decrement_in_flight_message_num(&topic, &consume_group, pop_time, q_id, 1);
+            self.ack_parity_telemetry
+                .record(ParityGap::OrderlyAckNotify);
+        }
+    }
+}
+
+/// Response ext field holding the number of offsets acked since the previous frame of a
+/// streamed batch ack response.
+const ACKED_COUNT: &str = "ackedCount";
+/// Final response ext field holding the number of offsets acked by a streamed batch ack.
+const TOTAL_ACKED_COUNT: &str = "totalAckedCount";
+
+/// Counts the offsets acked by a batch ack whose response is streamed, cutting a chunk every
+/// `chunk_size` offsets. Every offset ends up in exactly one chunk or in the final response.
+struct AckStreamProgress {
+    chunk_size: usize,
+    pending: usize,
+    total: usize,
+}
+
+impl AckStreamProgress {
+    fn new(chunk_size: usize) -> Self {
+        AckStreamProgress {
+            chunk_size,
+            pending: 0,
+            total: 0,
+        }
+    }
+
+    /// Records `acked` offsets and returns the size of the chunk to report, if one is full.
+    fn record(&mut self, acked: usize) -> Option<usize> {
+        self.pending += acked;
+        self.total += acked;
+        if self.pending < self.chunk_size {
+            return None;
+        }
+        Some(std::mem::take(&mut self.pending))
+    }
+
+    /// Puts back a chunk which could not be reported, so that it goes with the next one.
+    fn requeue(&mut self, chunk: usize) {
+        self.pending += chunk;
+    }
+
+    /// Reports the offsets acked since the last chunk, and the overall total, on the final
+    /// response.
+    fn finish(self, mut response: RemotingCommand) -> RemotingCommand {
+        response.add_ext_field(ACKED_COUNT, self.pending.to_string());
+        response.add_ext_field(TOTAL_ACKED_COUNT, self.total.to_string());
+        response
+    }
+}
+
+async fn send_ack_progress<S: ResponseFrameSink>(
+    stream: &mut ResponseStream<S>,
+    progress: &mut AckStreamProgress,
+    acked: usize,
+) {
+    let Some(chunk) = progress.record(acked) else {
+        return;
+    };
+    let mut frame = RemotingCommand::create_response_command();
+    frame.add_ext_field(ACKED_COUNT, chunk.to_string());
+    if let Err(e) = stream.send_partial(frame).await {
+        warn!("send batch ack progress frame failed: {}", e);
+        progress.requeue(chunk);
+    }
+}
+
+/// Commits the consumer offset of an orderly popped queue once `ack_offset` is acked, with the
+/// queue lock held. Returns whether the ack was applied to the pop holding the queue; a rejected
+/// ack has its error written to `response`, an ack below the committed offset is ignored.
+fn commit_orderly_ack(
+    consumer_offset_manager: &ConsumerOffsetManager,
+    commit_and_next: impl FnOnce() -> OrderedAckCommit,
+    client_host: SocketAddr,
+    topic: &CheetahString,
+    group: &CheetahString,
+    queue_id: i32,
+    ack_offset: i64,
+    response: &mut RemotingCommand,
+) -> bool {
+    let old_offset = consumer_offset_manager.query_offset(group, topic, queue_id);
+    if ack_offset < old_offset {
+        return false;
+    }
+    let lock_key = QueueLockManager::build_lock_key(topic, group, queue_id);
+    match commit_and_next() {
+        OrderedAckCommit::Next(next_offset) => {
+            if !consumer_offset_manager.has_offset_reset(group, topic, queue_id) {
+                consumer_offset_manager.commit_offset(
+                    client_host,
+                    group,
+                    topic,
+                    queue_id,
+                    next_offset,
+                );
+            }
+            true
+        }
+        // acked by a consumer which lost the queue to a later pop, nothing left to commit
+        OrderedAckCommit::PopTimeMismatch => true,
+        OrderedAckCommit::NotLocked => {
+            let error_info = format!(
+                "queue is not locked by an orderly pop, key:{}, commit:{}, {}",
+                lock_key, ack_offset, client_host
+            );
+            warn!("{}", error_info);
+            response.set_code_ref(ResponseCode::MessageIllegal);
+            response.set_remark_mut(error_info);
+            false
+        }
+        OrderedAckCommit::OffsetNotFound => {
+            let error_info = format!(
+                "offset is illegal, key:{}, old:{}, commit:{}, {}",
+                lock_key, old_offset, ack_offset, client_host
+            );
+            warn!("{}", error_info);
+            response.set_code_ref(ResponseCode::MessageIllegal);
+            response.set_remark_mut(error_info);
+            false
+        }
+    }
+}
+
+/// Rejects any ack while ack processing is switched off on this broker.
+fn check_ack_processing_enabled(switch: &AckProcessingSwitch) -> Option<RemotingCommand> {
+    if !switch.is_disabled() {
+        return None;
+    }
+    Some(RemotingCommand::create_response_command_with_code_remark(
+        ResponseCode::ServiceNotAvailable,
+        "ack processing disabled on this broker",
+    ))
+}
+
+/// Writes an ack to the revive topic of the broker `broker_name` owning its queue, or to the
+/// local revive topic if that broker can not be reached.
+async fn put_ack_msg_to_broker<MS>(
+    escape_bridge: &mut EscapeBridge<MS>,
+    inner: &MessageExtBrokerInner,
+    broker_name: &CheetahString,
+) -> PutMessageResult
+where
+    MS: MessageStore,
+{
+    match escape_bridge
+        .put_message_to_remote_broker(copy_ack_msg(inner), Some(broker_name.clone()))
+        .await
+    {
+        Ok(Some(send_result)) => return transform_send_result2put_result(Some(send_result)),
+        Ok(None) => warn!(
+            "put ack to broker {} failed, broker not reachable, put it locally",
+            broker_name
+        ),
+        Err(e) => warn!(
+            "put ack to broker {} failed, put it locally, error={}",
+            broker_name, e
+        ),
+    }
+    escape_bridge
+        .put_message_to_specific_queue(copy_ack_msg(inner))
+        .await
+}
+
+/// Sets the body and tag of the revive topic message carrying an ack, `ack_body` being the ack
+/// encoded by `AckMessage::encode_body`.
+fn set_ack_body(inner: &mut MessageExtBrokerInner, ack_tag: &'static str, ack_body: Bytes) {
+    inner.set_body(ack_body);
+    set_revive_body_crc(inner);
+    inner.set_tags(CheetahString::from_static_str(ack_tag));
+}
+
+/// Fills the fields of the current ack span once they are decoded, so that every log line of
+/// the ack can be told apart from the others.
+fn record_ack_span_fields(consumer_group: &str, topic: &str, queue_id: i32) {
+    let span = Span::current();
+    span.record("consumer_group", consumer_group);
+    span.record("topic", topic);
+    span.record("queue_id", queue_id);
+}
+
+/// Returns the store host stamped on revive messages: `store_host` itself, or the advertised
+/// `broker_ip` on the same port if the broker is bound to a wildcard address, and `None` if
+/// neither is routable.
+fn effective_store_host(store_host: SocketAddr, broker_ip: &str) -> Option<SocketAddr> {
+    if !store_host.ip().is_unspecified() {
+        return Some(store_host);
+    }
+    broker_ip
+        .parse::<IpAddr>()
+        .ok()
+        .filter(|ip| !ip.is_unspecified())
+        .map(|ip| SocketAddr::new(ip, store_host.port()))
+}
+
+/// Resolves the store host stamped on revive messages, failing with the offending address when
+/// neither it nor `brokerIP1` is routable.
+pub(crate) fn resolve_store_host(
+    store_host: SocketAddr,
+    broker_config: &BrokerConfig,
+) -> Result<SocketAddr, AckMessageProcessorBuildError> {
+    effective_store_host(store_host, &broker_config.broker_ip1).ok_or_else(|| {
+        AckMessageProcessorBuildError::UnroutableStoreHost(
+            store_host,
+            broker_config.broker_ip1.clone(),
+        )
+    })
+}
+
+/// Rejects an ack to a topic whose read permission is revoked, its messages can not be consumed
+/// and thus not be acked either.
+fn check_topic_readable(topic_config: &TopicConfig) -> Option<RemotingCommand> {
+    if PermName::is_readable(topic_config.perm) {
+        return None;
+    }
+    Some(RemotingCommand::create_response_command_with_code_remark(
+        ResponseCode::NoPermission,
+        format!(
+            "the topic[{}] acking message is forbidden",
+            topic_config.topic_name.as_deref().unwrap_or_default()
+        ),
+    ))
+}
+
+/// Rejects an ack for a consumer group that is neither configured on this broker nor created on
+/// demand, nobody would ever consume its entries in the revive topic.
+fn check_subscription_group_exists(
+    group: &CheetahString,
+    group_exists: bool,
+    auto_create_subscription_group: bool,
+) -> Option<RemotingCommand> {
+    if group_exists || auto_create_subscription_group {
+        return None;
+    }
+    Some(RemotingCommand::create_response_command_with_code_remark(
+        ResponseCode::SubscriptionGroupNotExist,
+        format!(
+            "subscription group [{}] does not exist, {}",
+            group,
+            FAQUrl::suggest_todo(FAQUrl::SUBSCRIPTION_GROUP_NOT_EXIST)
+        ),
+    ))
+}
+
+/// Returns `true` if the client asked for the outcome of every ack of its batch, older clients
+/// only get the acked offsets.
+fn is_batch_ack_detail_requested(request: &RemotingCommand) -> bool {
+    request
+        .get_ext_fields()
+        .and_then(|ext_fields| ext_fields.get(BATCH_ACK_DETAIL))
+        .is_some_and(|value| value.as_str() == "true")
+}
+
+/// Rejects any ack once the broker shuts down, the pop buffer is being drained to the revive
+/// topic and the store is about to be closed.
+fn check_not_draining(pop_buffer_merge_service: &PopBufferMergeService) -> Option<RemotingCommand> {
+    if !pop_buffer_merge_service.is_draining() {
+        return None;
+    }
+    Some(RemotingCommand::create_response_command_with_code_remark(
+        ResponseCode::ServiceNotAvailable,
+        "broker is shutting down, ack rejected",
+    ))
+}
+
+/// Asks the client to retry acks while the store is still recovering, its queue offset ranges
+/// are incomplete until then and would wrongly reject valid acks.
+fn check_store_recovered(
+    running_flags: &RunningFlags,
+    reject_during_recovery: bool,
+) -> Option<RemotingCommand> {
+    if !reject_during_recovery || !running_flags.is_recovering() {
+        return None;
+    }
+    Some(RemotingCommand::create_response_command_with_code_remark(
+        ResponseCode::SystemBusy,
+        "store recovering, try again later",
+    ))
+}
+
+/// Returns whether `request_code` is one of the codes the ack processor handles itself.
+fn is_ack_request_code(request_code: RequestCode) -> bool {
+    matches!(
+        request_code,
+        RequestCode::AckMessage | RequestCode::BatchAckMessage | RequestCode::EndTwoPhaseAck
+    )
+}
+
+/// Asks the client to retry acks while the broker permission is read-only, as an ack is a
+/// write to the revive topic and operators drain a broker by revoking its write permission.
+fn check_broker_writeable(broker_config: &BrokerConfig) -> Option<RemotingCommand> {
+    if PermName::is_writeable(broker_config.broker_permission) {
+        return None;
+    }
+    Some(RemotingCommand::create_response_command_with_code_remark(
+        ResponseCode::SystemBusy,
+        format!(
+            "the broker[{}-{}] is read-only, ack rejected",
+            broker_config.broker_identity.broker_name, broker_config.broker_ip1
+        ),
+    ))
+}
+
+/// Rejects a receipt handle longer than `max_length` before it gets split, as it comes from
+/// the client and is never that long when built by a broker. A `max_length` of 0 disables
+/// the check.
+fn check_extra_info_length(extra_info: &str, max_length: usize) -> Option<RemotingCommand> {
+    if max_length == 0 || extra_info.len() <= max_length {
+        return None;
+    }
+    Some(RemotingCommand::create_response_command_with_code_remark(
+        ResponseCode::MessageIllegal,
+        format!(
+            "extraInfo is too long, length: {}, max length: {}",
+            extra_info.len(),
+            max_length
+        ),
+    ))
+}
+
+/// Rejects a batch ack holding a bit set longer than `max_size` bits, as a pop never returns
+/// that many messages of a queue. A `max_size` of 0 disables the check.
+fn check_batch_ack_bit_set_size(acks: &[BatchAck], max_size: usize) -> Option<RemotingCommand> {
+    if max_size == 0 {
+        return None;
+    }
+    let ack = acks.iter().find(|ack| ack.bit_set.0.len() > max_size)?;
+    Some(RemotingCommand::create_response_command_with_code_remark(
+        ResponseCode::MessageIllegal,
+        format!(
+            "batch ack bit set is too large, topic: {}, queueId: {}, size: {}, max size: {}",
+            ack.topic,
+            ack.queue_id,
+            ack.bit_set.0.len(),
+            max_size
+        ),
+    ))
+}
+
+/// Rejects a batch ack request holding more than `max_count` acks, or acking more than
+/// `max_count` offsets in total, so that a single request cannot tie up the handler. A
+/// `max_count` of 0 disables the check.
+fn check_batch_ack_count(acks: &[BatchAck], max_count: usize) -> Option<RemotingCommand> {
+    if max_count == 0 {
+        return None;
+    }
+    let offset_count: usize = acks.iter().map(|ack| ack.bit_set.0.count_ones()).sum();
+    if acks.len() <= max_count && offset_count <= max_count {
+        return None;
+    }
+    Some(RemotingCommand::create_response_command_with_code_remark(
+        ResponseCode::MessageIllegal,
+        format!(
+            "batch ack is too large, acks: {}, offsets: {}, max count: {}, split it into smaller \
+             requests",
+            acks.len(),
+            offset_count,
+            max_count
+        ),
+    ))
+}
+
+/// Maps the set bits of a batch ack to queue offsets, keeping those within
+/// `[min_offset, max_offset]`. Returns the kept offsets and the number of offsets skipped.
+fn select_batch_ack_offsets(
+    set_bits: impl IntoIterator<Item = usize>,
+    start_offset: i64,
+    min_offset: i64,
+    max_offset: i64,
+) -> (Vec<i64>, usize) {
+    let mut offsets = Vec::new();
+    let mut skipped = 0;
+    for bit in set_bits {
+        let offset = start_offset + bit as i64;
+        if offset < min_offset || offset > max_offset {
+            skipped += 1;
+        } else {
+            offsets.push(offset);
+        }
+    }
+    (offsets, skipped)
+}
+
+/// Tells whether a message redelivered `reconsume_times` times used up the retries of its
+/// group.
+fn is_pop_retry_exhausted(reconsume_times: i32, max_reconsume_times: i32) -> bool {
+    reconsume_times >= max_reconsume_times
+}
+
+/// Builds the copy of `msg_ext` written to `dlq_topic`, the same way a consumer sending a
+/// message back after its last retry does.
+fn build_pop_dlq_message(
+    msg_ext: &MessageExt,
+    dlq_topic: CheetahString,
+    store_host: SocketAddr,
+) -> MessageExtBrokerInner {
+    let mut inner = MessageExtBrokerInner::default();
+    inner.set_topic(dlq_topic);
+    if let Some(body) = msg_ext.get_body() {
+        inner.set_body(body.clone());
+    }
+    inner.set_flag(msg_ext.get_flag());
+    MessageAccessor::set_properties(&mut inner, msg_ext.get_properties().clone());
+    if msg_ext
+        .get_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_RETRY_TOPIC,
+        ))
+        .is_none()
+    {
+        MessageAccessor::put_property(
+            &mut inner,
+            CheetahString::from_static_str(MessageConst::PROPERTY_RETRY_TOPIC),
+            msg_ext.get_topic().clone(),
+        );
+    }
+    let origin_msg_id =
+        MessageAccessor::get_origin_message_id(msg_ext).unwrap_or_else(|| msg_ext.msg_id.clone());
+    MessageAccessor::set_origin_message_id(&mut inner, origin_msg_id);
+    inner.set_delay_time_level(0);
+    inner.tags_code = MessageExtBrokerInner::tags_string_to_tags_code(
+        msg_ext.get_tags().unwrap_or_default().as_str(),
+    );
+    inner.message_ext_inner.queue_id = 0;
+    inner.message_ext_inner.sys_flag = msg_ext.sys_flag;
+    inner.message_ext_inner.born_timestamp = msg_ext.born_timestamp;
+    inner.message_ext_inner.born_host = msg_ext.born_host;
+    inner.message_ext_inner.store_host = store_host;
+    set_revive_host_flags(&mut inner);
+    inner.message_ext_inner.reconsume_times = msg_ext.reconsume_times;
+    inner.properties_string = message_decoder::message_properties_to_string(inner.get_properties());
+    inner
+}
+
+/// Pause between two attempts to put an ack message to the revive topic.
+const ACK_PUT_RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Puts an ack message with `put`, which is retried up to `retry_times` times with a
+/// `backoff` pause while the put is not successful, and returns the last result.
+async fn put_ack_msg_with_retry<F, Fut>(
+    retry_times: u32,
+    backoff: Duration,
+    mut put: F,
+) -> PutMessageResult
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = PutMessageResult>,
+{
+    let mut put_message_result = put().await;
+    for attempt in 1..=retry_times {
+        if is_put_ok(put_message_result.put_message_status()) {
+            break;
+        }
+        warn!(
+            "put ack msg failed, status: {:?}, retry {}/{}",
+            put_message_result.put_message_status(),
+            attempt,
+            retry_times
+        );
+        tokio::time::sleep(backoff).await;
+        put_message_result = put().await;
+    }
+    put_message_result
+}
+
+/// Copies an ack message for another put attempt, as a put consumes the message. The encoded
+/// buffer is left out so that the store encodes the copy again.
+fn copy_ack_msg(inner: &MessageExtBrokerInner) -> MessageExtBrokerInner {
+    MessageExtBrokerInner {
+        message_ext_inner: inner.message_ext_inner.clone(),
+        properties_string: inner.properties_string.clone(),
+        tags_code: inner.tags_code,
+        encoded_buff: None,
+        encode_completed: false,
+        version: inner.version,
+    }
+}
+
+/// Parses the receipt handle of a single ack, a handle which can not be parsed gets the
+/// response code of its [`AckParseError`].
+fn parse_ack_extra_info(
+    version: PopAckProtocolVersion,
+    extra_info: &str,
+    response: &mut RemotingCommand,
+) -> Option<ParsedAckHandle> {
+    match parse_ack_handle(version, extra_info) {
+        Ok(handle) => Some(handle),
+        Err(e) => {
+            warn!("ack rejected, {}", e);
+            response.set_code_ref(e.response_code());
+            response.set_remark_mut(e.to_string());
+            None
+        }
+    }
+}
+
+/// Limits the invisible time of an ack to `[min, max]`, so that a client can not keep its
+/// messages from ever being revived. A negative invisible time gets a `MessageIllegal` response.
+fn clamp_ack_invisible_time(
+    invisible_time: i64,
+    min: i64,
+    max: i64,
+    response: &mut RemotingCommand,
+) -> Option<i64> {
+    if invisible_time < 0 {
+        warn!(
+            "ack rejected, invisible time {} is negative",
+            invisible_time
+        );
+        response.set_code_ref(ResponseCode::MessageIllegal);
+        response.set_remark_mut(format!("invisibleTime {} is negative", invisible_time));
+        return None;
+    }
+    let clamped = invisible_time.min(max).max(min);
+    if clamped != invisible_time {
+        info!(
+            "ack invisible time {} clamped to {}, range [{}, {}]",
+            invisible_time, clamped, min, max
+        );
+    }
+    Some(clamped)
+}
+
+/// Rejects an ack whose revive queue id is outside of the revive queues of its topic, no
+/// checkpoint of the topic can live in such a queue.
+fn check_ack_revive_qid(
+    r_qid: i32,
+    revive_queue_num: u32,
+    response: &mut RemotingCommand,
+) -> Option<()> {
+    if r_qid >= 0 && (r_qid as u32) < revive_queue_num.max(1) {
+        return Some(());
+    }
+    warn!(
+        "ack rejected, revive queue id {} out of range [0, {})",
+        r_qid, revive_queue_num
+    );
+    response.set_code_ref(ResponseCode::MessageIllegal);
+    response.set_remark_mut(format!(
+        "reviveQueueId {} out of range, the topic has {} revive queues",
+        r_qid, revive_queue_num
+    ));
+    None
+}
+
+/// Returns the pop time the revive delay of an ack is computed from. `pop_time` comes from the
+/// handle and is stamped by the pop broker clock; when the client sends the pop time it
+/// observed, the skew between both is corrected, as long as it stays within `tolerance`.
+/// A larger skew gets a `MessageIllegal` response.
+fn correct_ack_pop_time(
+    pop_time: i64,
+    observed_pop_time: Option<i64>,
+    tolerance: i64,
+    response: &mut RemotingCommand,
+) -> Option<i64> {
+    let observed_pop_time = match observed_pop_time {
+        Some(observed_pop_time) => observed_pop_time,
+        None => return Some(pop_time),
+    };
+    let skew = observed_pop_time - pop_time;
+    if skew.abs() > tolerance {
+        warn!(
+            "ack rejected, observed pop time {} is {}ms away from pop time {}, tolerance {}ms",
+            observed_pop_time, skew, pop_time, tolerance
+        );
+        response.set_code_ref(ResponseCode::MessageIllegal);
+        response.set_remark_mut(format!(
+            "observedPopTime {} is out of the tolerated skew of {}ms from popTime {}",
+            observed_pop_time, tolerance, pop_time
+        ));
+        return None;
+    }
+    // the delay is pop time + invisible time, so shifting the pop time by the skew corrects it
+    Some(observed_pop_time)
+}
+
+/// Stamps the identity of this broker (cluster, broker id and zone) onto an ack message,
+/// so that downstream consumers of the revive topic can tell where the ack came from.
+fn enrich_ack_properties(broker_config: &BrokerConfig, inner: &mut MessageExtBrokerInner) {
+    let broker_identity = &broker_config.broker_identity;
+    inner.put_property(
+        CheetahString::from_static_str(MessageConst::PROPERTY_CLUSTER),
+        broker_identity.broker_cluster_name.clone(),
+    );
+    inner.put_property(
+        CheetahString::from_static_str(MessageConst::PROPERTY_BROKER_ID),
+        CheetahString::from_string(broker_identity.broker_id.to_string()),
+    );
+    if !broker_config.zone_name.is_empty() {
+        inner.put_property(
+            CheetahString::from_static_str(mix_all::ZONE_NAME),
+            broker_config.zone_name.clone(),
+        );
+    }
+}
+
+/// Where the revive message of an ack is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AckWriteRoute {
+    /// Written to the broker owning the queue of the ack, another broker than this one.
+    Remote,
+    /// Written into the local store, this broker is a master.
+    Local,
+    /// This broker is read-only (a slave), the ack is forwarded to the master.
+    Master,
+    /// This broker is read-only and forwarding to the master is disabled.
+    Unavailable,
+}
+
+impl AckWriteRoute {
+    fn of(broker_config: &BrokerConfig, ack_broker_name: &str) -> Self {
+        if !ack_broker_name.is_empty()
+            && ack_broker_name != broker_config.broker_identity.broker_name.as_str()
+        {
+            AckWriteRoute::Remote
+        } else if broker_config.broker_identity.broker_id == mix_all::MASTER_ID {
+            AckWriteRoute::Local
+        } else if broker_config.enable_slave_acting_master && broker_config.enable_remote_escape {
+            AckWriteRoute::Master
+        } else {
+            AckWriteRoute::Unavailable
+        }
+    }
+}
+
+fn get_ack_cleaned_offset_policy(
+    subscription_group_config: Option<&SubscriptionGroupConfig>,
+) -> AckCleanedOffsetPolicy {
+    let attribute = &SubscriptionGroupAttributes::ACK_CLEANED_OFFSET_POLICY_ATTRIBUTE;
+    subscription_group_config
+        .and_then(|config| config.attributes().get(attribute.get_name()))
+        .and_then(|value| AckCleanedOffsetPolicy::from_str(value).ok())
+        .unwrap_or_default()
+}
+
+/// Encoding of the offsets in the batch ack response, `None` to pick the most compact one per
+/// queue.
+fn batch_ack_result_encoding(broker_config: &BrokerConfig) -> Option<AckedOffsetsEncoding> {
+    broker_config
+        .batch_ack_result_encoding
+        .parse::<AckedOffsetsEncoding>()
+        .ok()
+}
+
+/// Response ext field holding the id of an ack prepared by a two-phase ack group.
+const ACK_TRANSACTION_ID: &str = "ackTransactionId";
+
+fn is_two_phase_ack_enabled(subscription_group_config: Option<&SubscriptionGroupConfig>) -> bool {
+    subscription_group_config
+        .and_then(|config| {
+            config
+                .attributes()
+                .get(SubscriptionGroupAttributes::ACK_TWO_PHASE_ATTRIBUTE.get_name())
+        })
+        .is_some_and(|value| value.eq_ignore_ascii_case("true"))
+}
+
+fn two_phase_ack_failed(error: TwoPhaseAckError, id: &CheetahString) -> RemotingCommand {
+    RemotingCommand::create_response_command_with_code_remark(
+        ResponseCode::TransactionFailed,
+        format!("{}, ackTransactionId: {}", error, id),
+    )
+}
+
+/// Builds the response to an ack whose offset is below `min_offset`, the acked message has
+/// already been cleaned so there is nothing left to revive.
+fn ack_cleaned_offset(
+    policy: AckCleanedOffsetPolicy,
+    consumer_offset_manager: &ConsumerOffsetManager,
+    client_host: SocketAddr,
+    request_header: &AckMessageRequestHeader,
+    min_offset: i64,
+    max_offset: i64,
+) -> RemotingCommand {
+    match policy {
+        AckCleanedOffsetPolicy::Reject => {
+            RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::NoMessage,
+                format!(
+                    "request offset below min offset, message already consumed or expired, \
+                     request offset: {}, min offset: {}, max offset: {}",
+                    request_header.offset, min_offset, max_offset
+                ),
+            )
+        }
+        AckCleanedOffsetPolicy::Ack => {
+            let consumer_offset = consumer_offset_manager.query_offset(
+                &request_header.consumer_group,
+                &request_header.topic,
+                request_header.queue_id,
+            );
+            if consumer_offset < min_offset {
+                consumer_offset_manager.commit_offset(
+                    client_host,
+                    &request_header.consumer_group,
+                    &request_header.topic,
+                    request_header.queue_id,
+                    min_offset,
+                );
+            }
+            RemotingCommand::create_response_command()
+        }
+    }
+}
+
+/// Builds the response to an ack whose offset is above `max_offset`. The message may not be
+/// dispatched yet, so the client is told to retry later rather than to drop the ack.
+fn ack_offset_ahead(
+    request_header: &AckMessageRequestHeader,
+    min_offset: i64,
+    max_offset: i64,
+) -> RemotingCommand {
+    RemotingCommand::create_response_command_with_code_remark(
+        ResponseCode::BrokerDispatchNotComplete,
+        format!(
+            "request offset above max offset, message not dispatched yet, request offset: {}, min \
+             offset: {}, max offset: {}",
+            request_header.offset, min_offset, max_offset
+        ),
+    )
+}
+
+/// Splits the acks of a batch by revive queue. Every ack keeps its position in the batch, and
+/// the acks of a revive queue keep their order.
+fn group_batch_acks_by_revive_queue(acks: Vec<BatchAck>) -> Vec<Vec<(usize, BatchAck)>> {
+    let mut groups = BTreeMap::<i32, Vec<(usize, BatchAck)>>::new();
+    for (index, ack) in acks.into_iter().enumerate() {
+        groups
+            .entry(ack.revive_queue_id)
+            .or_default()
+            .push((index, ack));
+    }
+    groups.into_values().collect()
+}
+
+/// Runs `f` on every group, no more groups at a time than `semaphore` has permits, and yields
+/// the outputs as the groups complete.
+fn run_groups_concurrently<'a, T, R, F, Fut>(
+    groups: Vec<T>,
+    semaphore: &'a Semaphore,
+    f: &'a F,
+) -> FuturesUnordered<impl Future<Output = R> + 'a>
+where
+    T: 'a,
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = R> + 'a,
+{
+    groups
+        .into_iter()
+        .map(|group| async move {
+            let _permit = semaphore
+                .acquire()
+                .await
+                .expect("batch ack semaphore is never closed");
+            f(group).await
+        })
+        .collect()
+}
+
+/// Max attempts to obtain a consistent min/max offset pair of a queue.
+const READ_OFFSET_RANGE_MAX_ATTEMPTS: usize = 3;
+
+/// Reads the min/max offset of a queue so that both values belong to the same
+/// snapshot. While a commitlog file is rolling over, the min offset may move
+/// after the range is read, or be observed ahead of a stale max offset. In that
+/// case the range is read again, up to `READ_OFFSET_RANGE_MAX_ATTEMPTS` times.
+fn read_queue_offset_range(
+    get_offset_range: impl Fn() -> (i64, i64),
+    get_min_offset: impl Fn() -> i64,
+) -> (i64, i64) {
+    let mut range = get_offset_range();
+    for _ in 1..READ_OFFSET_RANGE_MAX_ATTEMPTS {
+        let min_offset = get_min_offset();
+        if min_offset == range.0 && min_offset <= range.1 {
+            break;
+        }
+        range = get_offset_range();
+    }
+    range
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicI64;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use bitvec::prelude::BitVec;
+    use bitvec::prelude::Lsb0;
+    use rocketmq_common::common::config_manager::ConfigManager;
+    use rocketmq_common::common::message::message_single::Message;
+    use rocketmq_common::common::server::config::ServerConfig;
+    use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
+    use rocketmq_common::TopicAttributes;
+    use rocketmq_remoting::protocol::body::batch_ack::SerializableBitVec;
+    use rocketmq_remoting::protocol::header::extra_info_util::ExtraInfoUtil;
+    use rocketmq_remoting::protocol::header::pop_message_request_header::PopMessageRequestHeader;
+    use rocketmq_remoting::protocol::header::pop_message_response_header::PopMessageResponseHeader;
+    use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
+    use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContextWrapper;
+    use rocketmq_store::base::message_status_enum::PutMessageStatus;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+    use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
+    use rocketmq_store::pop::decode_ack_body;
+    use rocketmq_store::pop::is_revive_body_intact;
+
+    use super::*;
+    use crate::broker_runtime::BrokerRuntimeInner;
+    use crate::client::default_consumer_ids_change_listener::DefaultConsumerIdsChangeListener;
+    use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
+    use crate::out_api::broker_outer_api::BrokerOuterAPI;
+    use crate::processor::ack_request_handler::AckRequestFuture;
+    use crate::processor::pop_message_processor::inner_queue_offsets;
+    use crate::topic::manager::topic_queue_mapping_manager::TopicQueueMappingManager;
+    use crate::topic::manager::topic_route_info_manager::TopicRouteInfoManager;
+    use crate::util::test_channel::test_channel;
+    use crate::util::test_message_store::TestMessageStore;
+
+    #[derive(Default)]
+    struct VecSink(Vec<RemotingCommand>);
+
+    impl ResponseFrameSink for VecSink {
+        async fn send_frame(&mut self, frame: RemotingCommand) -> rocketmq_remoting::Result<()> {
+            self.0.push(frame);
+            Ok(())
+        }
+    }
+
+    fn acked_count(command: &RemotingCommand, key: &str) -> usize {
+        command.get_ext_fields().unwrap()[key].parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn large_batch_ack_streams_progress_frames_summing_to_total() {
+        let mut stream = ResponseStream::new(VecSink::default(), 7);
+        let mut progress = AckStreamProgress::new(1024);
+        // 64 acks of 500 offsets each, as for a batch spanning 32000 offsets
+        for _ in 0..64 {
+            send_ack_progress(&mut stream, &mut progress, 500).await;
+        }
+        let response = stream.finish(progress.finish(RemotingCommand::create_response_command()));
+
+        let frames = stream.into_sink().0;
+        assert_eq!(frames.len(), 21);
+        assert_eq!(
+            response.get_ext_fields().unwrap()["frameCount"],
+            frames.len().to_string()
+        );
+        let streamed: usize = frames.iter().map(|f| acked_count(f, ACKED_COUNT)).sum();
+        assert_eq!(streamed + acked_count(&response, ACKED_COUNT), 32000);
+        assert_eq!(acked_count(&response, TOTAL_ACKED_COUNT), 32000);
+    }
+
+    #[test]
+    fn check_extra_info_length_rejects_oversized_handle() {
+        let extra_info = "0|".repeat(512 * 1024);
+        let response = check_extra_info_length(&extra_info, 1024).unwrap();
+        assert_eq!(response.code(), ResponseCode::MessageIllegal as i32);
+        // the handle is neither split nor echoed back to the client
+        assert!(response.remark().unwrap().len() < 128);
+    }
+
+    #[tokio::test]
+    async fn put_ack_msg_is_retried_until_store_accepts_it() {
+        let mut statuses = vec![
+            PutMessageStatus::PutOk,
+            PutMessageStatus::CreateMappedFileFailed,
+        ];
+        let mut attempts = 0;
+        let put_message_result = put_ack_msg_with_retry(3, Duration::ZERO, || {
+            attempts += 1;
+            let status = statuses.pop().unwrap();
+            async move { PutMessageResult::new_default(status) }
+        })
+        .await;
+        assert_eq!(attempts, 2);
+        assert_eq!(
+            put_message_result.put_message_status(),
+            PutMessageStatus::PutOk
+        );
+    }
+
+    #[tokio::test]
+    async fn put_ack_msg_gives_up_after_retry_times() {
+        let mut attempts = 0;
+        let put_message_result = put_ack_msg_with_retry(3, Duration::ZERO, || {
+            attempts += 1;
+            async { PutMessageResult::new_default(PutMessageStatus::CreateMappedFileFailed) }
+        })
+        .await;
+        assert_eq!(attempts, 4);
+        assert_eq!(
+            put_message_result.put_message_status(),
+            PutMessageStatus::CreateMappedFileFailed
+        );
+    }
+
+    #[test]
+    fn parse_ack_extra_info_rejects_truncated_handle() {
+        let mut response = RemotingCommand::create_response_command();
+        let extra_info =
+            parse_ack_extra_info(PopAckProtocolVersion::V1, "0|1000|30000", &mut response);
+        assert!(extra_info.is_none());
+        assert_eq!(response.code(), ResponseCode::MessageIllegal as i32);
+        assert!(response
+            .remark()
+            .is_some_and(|remark| remark.starts_with("extraInfo is malformed")));
+
+        let mut response = RemotingCommand::create_response_command();
+        assert!(parse_ack_extra_info(PopAckProtocolVersion::V1, "", &mut response).is_none());
+        assert_eq!(response.code(), ResponseCode::MessageIllegal as i32);
+    }
+
+    #[test]
+    fn ack_invisible_time_is_clamped_to_configured_range() {
+        let mut response = RemotingCommand::create_response_command();
+        assert_eq!(
+            clamp_ack_invisible_time(500, 1_000, 60_000, &mut response),
+            Some(1_000)
+        );
+        assert_eq!(
+            clamp_ack_invisible_time(30_000, 1_000, 60_000, &mut response),
+            Some(30_000)
+        );
+        assert_eq!(
+            clamp_ack_invisible_time(i64::MAX, 1_000, 60_000, &mut response),
+            Some(60_000)
+        );
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+
+        assert_eq!(
+            clamp_ack_invisible_time(-1, 1_000, 60_000, &mut response),
+            None
+        );
+        assert_eq!(response.code(), ResponseCode::MessageIllegal as i32);
+    }
+
+    #[test]
+    fn ack_pop_time_skew_is_corrected_within_tolerance() {
+        let mut response = RemotingCommand::create_response_command();
+        // no observed pop time, the handle pop time is used as is
+        assert_eq!(
+            correct_ack_pop_time(10_000, None, 5_000, &mut response),
+            Some(10_000)
+        );
+        // the pop broker clock runs 3s behind, the revive delay moves 3s later
+        assert_eq!(
+            correct_ack_pop_time(10_000, Some(13_000), 5_000, &mut response),
+            Some(13_000)
+        );
+        // and 5s ahead, right at the tolerance
+        assert_eq!(
+            correct_ack_pop_time(10_000, Some(5_000), 5_000, &mut response),
+            Some(5_000)
+        );
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+
+        assert_eq!(
+            correct_ack_pop_time(10_000, Some(15_001), 5_000, &mut response),
+            None
+        );
+        assert_eq!(response.code(), ResponseCode::MessageIllegal as i32);
+    }
+
+    #[test]
+    fn parse_ack_extra_info_accepts_complete_handle() {
+        let mut response = RemotingCommand::create_response_command();
+        let handle = parse_ack_extra_info(
+            PopAckProtocolVersion::V1,
+            "0|1000|30000|1|0|broker-a|0|3",
+            &mut response,
+        )
+        .unwrap();
+        assert_eq!(handle.broker_name.as_str(), "broker-a");
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+    }
+
+    fn batch_ack(bit_set: BitVec<u64, Lsb0>) -> BatchAck {
+        BatchAck {
+            consumer_group: CheetahString::from("test_group"),
+            topic: CheetahString::from("test_topic"),
+            retry: CheetahString::from("0"),
+            start_offset: 100,
+            queue_id: 1,
+            revive_queue_id: 0,
+            pop_time: 0,
+            invisible_time: 0,
+            bit_set: SerializableBitVec(bit_set),
+        }
+    }
+
+    #[test]
+    fn check_batch_ack_bit_set_size_rejects_oversized_bit_set() {
+        let acks = vec![
+            batch_ack(BitVec::repeat(false, 64)),
+            batch_ack(BitVec::repeat(false, 4096)),
+        ];
+        let response = check_batch_ack_bit_set_size(&acks, 1024).unwrap();
+        assert_eq!(response.code(), ResponseCode::MessageIllegal as i32);
+        assert!(response
+            .remark()
+            .is_some_and(|remark| remark.contains("size: 4096, max size: 1024")));
+        assert!(check_batch_ack_bit_set_size(&acks[..1], 1024).is_none());
+        assert!(check_batch_ack_bit_set_size(&acks, 0).is_none());
+    }
+
+    #[test]
+    fn check_batch_ack_count_rejects_too_many_acks_or_offsets() {
+        let too_many_acks: Vec<_> = (0..5)
+            .map(|_| batch_ack(BitVec::repeat(false, 64)))
+            .collect();
+        let response = check_batch_ack_count(&too_many_acks, 4).unwrap();
+        assert_eq!(response.code(), ResponseCode::MessageIllegal as i32);
+        assert!(response
+            .remark()
+            .is_some_and(|remark| remark.contains("acks: 5, offsets: 0, max count: 4")));
+
+        let too_many_offsets = vec![
+            batch_ack(BitVec::repeat(true, 3)),
+            batch_ack(BitVec::repeat(true, 2)),
+        ];
+        let response = check_batch_ack_count(&too_many_offsets, 4).unwrap();
+        assert!(response
+            .remark()
+            .is_some_and(|remark| remark.contains("acks: 2, offsets: 5, max count: 4")));
+
+        assert!(check_batch_ack_count(&too_many_offsets[..1], 4).is_none());
+        assert!(check_batch_ack_count(&too_many_offsets, 0).is_none());
+    }
+
+    #[test]
+    fn select_batch_ack_offsets_reports_out_of_range_offsets() {
+        let mut bit_set: BitVec<u64, Lsb0> = BitVec::repeat(false, 128);
+        for bit in [0, 3, 10, 11, 100] {
+            bit_set.set(bit, true);
+        }
+        let ack = batch_ack(bit_set);
+        // the queue holds offsets 101..=110, so bit 0 is cleaned and bits 11 and 100 are
+        // beyond max_offset - start_offset
+        let (offsets, skipped) =
+            select_batch_ack_offsets(ack.bit_set.0.iter_ones(), ack.start_offset, 101, 110);
+        assert_eq!(offsets, vec![103, 110]);
+        assert_eq!(skipped, 3);
+    }
+
+    #[test]
+    fn queue_exists_when_its_offsets_are_known() {
+        let message_store = TestMessageStore::default();
+        let topic = CheetahString::from_static_str("test_topic");
+        assert!(!message_store.queue_exists(&topic, 0));
+        message_store.set_offset_range("test_topic", 0, 0, 10);
+        assert!(message_store.queue_exists(&topic, 0));
+        assert!(!message_store.queue_exists(&topic, 1));
+    }
+
+    #[test]
+    fn batch_acks_are_grouped_by_revive_queue_in_order() {
+        let acks = [2, 0, 2, 1, 0]
+            .into_iter()
+            .enumerate()
+            .map(|(queue_id, revive_queue_id)| BatchAck {
+                queue_id: queue_id as i32,
+                revive_queue_id,
+                ..batch_ack(BitVec::repeat(false, 8))
+            })
+            .collect();
+        let groups = group_batch_acks_by_revive_queue(acks)
+            .into_iter()
+            .map(|group| {
+                group
+                    .into_iter()
+                    .map(|(index, ack)| (index, ack.queue_id))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            vec![vec![(1, 1), (4, 4)], vec![(3, 3)], vec![(0, 0), (2, 2)]]
+        );
+    }
+
+    #[tokio::test]
+    async fn batch_ack_groups_are_appended_concurrently() {
+        // 64 acks over 8 revive queues take 8 sequential puts instead of 64
+        let groups = (0..8)
+            .map(|revive_queue_id| (0..8).map(|i| (revive_queue_id, i)).collect())
+            .collect::<Vec<Vec<(i32, i32)>>>();
+        let semaphore = Semaphore::new(8);
+        // no put is released before every revive queue has one in flight, so a sequential
+        // append never gets past it
+        let barrier = tokio::sync::Barrier::new(8);
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+        let sequential_awaits = AtomicUsize::new(0);
+        let append_group = |group: Vec<(i32, i32)>| {
+            let (barrier, in_flight, max_in_flight, sequential_awaits) =
+                (&barrier, &in_flight, &max_in_flight, &sequential_awaits);
+            async move {
+                let mut appended = Vec::new();
+                for (round, ack) in group.into_iter().enumerate() {
+                    let now_in_flight = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(now_in_flight, Ordering::SeqCst);
+                    if round == 0 {
+                        barrier.wait().await;
+                    }
+                    tokio::task::yield_now().await;
+                    sequential_awaits.fetch_max(round + 1, Ordering::SeqCst);
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    appended.push(ack);
+                }
+                appended
+            }
+        };
+        let pending = run_groups_concurrently(groups, &semaphore, &append_group);
+        let appended = tokio::time::timeout(Duration::from_secs(5), pending.collect::<Vec<_>>())
+            .await
+            .expect("batch ack groups were appended one after another");
+
+        assert_eq!(appended.len(), 8);
+        for group in appended {
+            let revive_queue_id = group[0].0;
+            assert_eq!(
+                group,
+                (0..8).map(|i| (revive_queue_id, i)).collect::<Vec<_>>()
+            );
+        }
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 8);
+        assert_eq!(sequential_awaits.load(Ordering::SeqCst), 8);
+    }
+
+    #[tokio::test]
+    async fn batch_ack_groups_respect_the_concurrency_bound() {
+        let groups = (0..6).map(|i| vec![i]).collect::<Vec<Vec<i32>>>();
+        let semaphore = Semaphore::new(2);
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+        let append_group = |group: Vec<i32>| {
+            let (in_flight, max_in_flight) = (&in_flight, &max_in_flight);
+            async move {
+                let now_in_flight = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(now_in_flight, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                group
+            }
+        };
+        let appended = run_groups_concurrently(groups, &semaphore, &append_group)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(appended.len(), 6);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn message_at_max_reconsume_times_lands_in_dlq_topic() {
+        let group = CheetahString::from("test_group");
+        let mut msg_ext = MessageExt::default();
+        msg_ext.set_topic(CheetahString::from_string(mix_all::get_retry_topic(
+            group.as_str(),
+        )));
+        msg_ext.set_body(Bytes::from_static(b"payload"));
+        msg_ext.set_tags(CheetahString::from("tag_a"));
+        msg_ext.msg_id = CheetahString::from("msg-id");
+        msg_ext.reconsume_times = 16;
+
+        assert!(!is_pop_retry_exhausted(15, 16));
+        assert!(is_pop_retry_exhausted(msg_ext.reconsume_times, 16));
+
+        let store_host = "127.0.0.1:10911".parse().unwrap();
+        let inner = build_pop_dlq_message(
+            &msg_ext,
+            CheetahString::from_string(mix_all::get_dlq_topic(group.as_str())),
+            store_host,
+        );
+        assert_eq!(inner.get_topic().as_str(), "%DLQ%test_group");
+        assert_eq!(inner.message_ext_inner.queue_id, 0);
+        assert_eq!(inner.get_body().unwrap().as_ref(), b"payload");
+        assert_eq!(inner.message_ext_inner.reconsume_times, 16);
+        assert_eq!(inner.message_ext_inner.store_host, store_host);
+        assert_eq!(
+            MessageAccessor::get_origin_message_id(&inner)
+                .unwrap()
+                .as_str(),
+            "msg-id"
+        );
+        assert_eq!(
+            inner
+                .get_property(&CheetahString::from_static_str(
+                    MessageConst::PROPERTY_RETRY_TOPIC
+                ))
+                .unwrap(),
+            *msg_ext.get_topic()
+        );
+    }
+
+    #[test]
+    fn check_extra_info_length_accepts_regular_handle() {
+        let extra_info = ExtraInfoUtil::build_extra_info_with_msg_queue_offset(
+            100,
+            1700000000000,
+            30000,
+            3,
+            "test_topic",
+            "broker-a",
+            1,
+            105,
+        );
+        assert!(check_extra_info_length(&extra_info, 1024).is_none());
+        assert!(check_extra_info_length(&"0".repeat(4096), 0).is_none());
+    }
+
+    #[test]
+    fn enrich_ack_properties_stamps_broker_context() {
+        let mut broker_config = BrokerConfig::default();
+        broker_config.broker_identity.broker_cluster_name = CheetahString::from_static_str("c1");
+        broker_config.broker_identity.broker_id = 2;
+        broker_config.zone_name = CheetahString::from_static_str("zone-a");
+        let mut inner = MessageExtBrokerInner::default();
+        enrich_ack_properties(&broker_config, &mut inner);
+        inner.properties_string =
+            message_decoder::message_properties_to_string(inner.get_properties());
+
+        let properties =
+            message_decoder::string_to_message_properties(Some(&inner.properties_string));
+        assert_eq!(
+            properties.get(MessageConst::PROPERTY_CLUSTER),
+            Some(&CheetahString::from_static_str("c1"))
+        );
+        assert_eq!(
+            properties.get(MessageConst::PROPERTY_BROKER_ID),
+            Some(&CheetahString::from_static_str("2"))
+        );
+        assert_eq!(
+            properties.get(mix_all::ZONE_NAME),
+            Some(&CheetahString::from_static_str("zone-a"))
+        );
+    }
+
+    #[test]
+    fn enrich_ack_properties_skips_empty_zone() {
+        let broker_config = BrokerConfig::default();
+        let mut inner = MessageExtBrokerInner::default();
+        enrich_ack_properties(&broker_config, &mut inner);
+        assert!(inner
+            .get_property(&CheetahString::from_static_str(mix_all::ZONE_NAME))
+            .is_none());
+        assert_eq!(
+            inner.get_property(&CheetahString::from_static_str(
+                MessageConst::PROPERTY_CLUSTER
+            )),
+            Some(broker_config.broker_identity.broker_cluster_name.clone())
+        );
+    }
+
+    fn ack_request_header(offset: i64) -> AckMessageRequestHeader {
+        AckMessageRequestHeader {
+            consumer_group: CheetahString::from_static_str("test_group"),
+            topic: CheetahString::from_static_str("test_topic"),
+            queue_id: 0,
+            extra_info: CheetahString::empty(),
+            offset,
+            observed_pop_time: None,
+            topic_request_header: None,
+        }
+    }
+
+    #[test]
+    fn get_ack_cleaned_offset_policy_defaults_to_reject() {
+        assert_eq!(
+            get_ack_cleaned_offset_policy(None),
+            AckCleanedOffsetPolicy::Reject
+        );
+        assert_eq!(
+            get_ack_cleaned_offset_policy(Some(&SubscriptionGroupConfig::default())),
+            AckCleanedOffsetPolicy::Reject
+        );
+    }
+
+    #[test]
+    fn get_ack_cleaned_offset_policy_reads_group_attribute() {
+        let mut config = SubscriptionGroupConfig::default();
+        config.set_attributes(HashMap::from([(
+            CheetahString::from(
+                SubscriptionGroupAttributes::ACK_CLEANED_OFFSET_POLICY_ATTRIBUTE.get_name(),
+            ),
+            CheetahString::from_static_str("ACK"),
+        )]));
+        assert_eq!(
+            get_ack_cleaned_offset_policy(Some(&config)),
+            AckCleanedOffsetPolicy::Ack
+        );
+    }
+
+    #[test]
+    fn batch_ack_result_encoding_defaults_to_most_compact() {
+        let mut broker_config = BrokerConfig::default();
+        assert_eq!(batch_ack_result_encoding(&broker_config), None);
+        broker_config.batch_ack_result_encoding = CheetahString::from_static_str("list");
+        assert_eq!(
+            batch_ack_result_encoding(&broker_config),
+            Some(AckedOffsetsEncoding::List)
+        );
+    }
+
+    #[test]
+    fn ack_processing_switch_blocks_and_reenables_acks() {
+        let switch = AckProcessingSwitch::default();
+        assert!(check_ack_processing_enabled(&switch).is_none());
+
+        switch.set_disabled(true);
+        let response = check_ack_processing_enabled(&switch).unwrap();
+        assert_eq!(response.code(), ResponseCode::ServiceNotAvailable as i32);
+        assert_eq!(
+            response.remark().map(|remark| remark.as_str()),
+            Some("ack processing disabled on this broker")
+        );
+
+        switch.set_disabled(false);
+        assert!(check_ack_processing_enabled(&switch).is_none());
+    }
+
+    #[test]
+    fn acks_of_unknown_group_are_rejected_without_auto_creation() {
+        let group = CheetahString::from_static_str("unknown_group");
+        assert!(check_subscription_group_exists(&group, true, false).is_none());
+        assert!(check_subscription_group_exists(&group, false, true).is_none());
+
+        let response = check_subscription_group_exists(&group, false, false).unwrap();
+        assert_eq!(
+            response.code(),
+            ResponseCode::SubscriptionGroupNotExist as i32
+        );
+        assert!(response.remark().unwrap().contains("unknown_group"));
+    }
+
+    #[test]
+    fn batch_ack_detail_is_opt_in() {
+        let mut request = RemotingCommand::create_remoting_command(RequestCode::BatchAckMessage);
+        assert!(!is_batch_ack_detail_requested(&request));
+        request.add_ext_field(BATCH_ACK_DETAIL, "true");
+        assert!(is_batch_ack_detail_requested(&request));
+    }
+
+    #[test]
+    fn acks_to_unreadable_topic_are_rejected() {
+        let readable = TopicConfig::with_perm(
+            "test_topic",
+            4,
+            4,
+            PermName::PERM_READ | PermName::PERM_WRITE,
+        );
+        assert!(check_topic_readable(&readable).is_none());
+
+        let write_only = TopicConfig::with_perm("test_topic", 4, 4, PermName::PERM_WRITE);
+        let response = check_topic_readable(&write_only).unwrap();
+        assert_eq!(response.code(), ResponseCode::NoPermission as i32);
+        assert_eq!(
+            response.remark().map(|remark| remark.as_str()),
+            Some("the topic[test_topic] acking message is forbidden")
+        );
+    }
+
+    #[test]
+    fn builder_names_missing_dependency() {
+        let result = AckMessageProcessor::<DefaultMessageStore>::builder()
+            .broker_config(Arc::new(BrokerConfig::default()))
+            .store_host("127.0.0.1:10911".parse().unwrap())
+            .build();
+        let Err(error) = result else {
+            panic!("an incomplete builder must not build");
+        };
+        assert_eq!(
+            error.to_string(),
+            "AckMessageProcessor requires topic_config_manager, not set"
+        );
+    }
+
+    #[test]
+    fn wildcard_store_host_is_replaced_by_broker_ip() {
+        let store_host: SocketAddr = "192.168.0.1:10911".parse().unwrap();
+        assert_eq!(
+            effective_store_host(store_host, "10.0.0.1"),
+            Some(store_host)
+        );
+
+        let wildcard: SocketAddr = "0.0.0.0:10911".parse().unwrap();
+        assert_eq!(
+            effective_store_host(wildcard, "10.0.0.1"),
+            Some("10.0.0.1:10911".parse().unwrap())
+        );
+        assert_eq!(effective_store_host(wildcard, "0.0.0.0"), None);
+        assert_eq!(effective_store_host(wildcard, "not-an-ip"), None);
+    }
+
+    #[test]
+    fn unroutable_store_host_error_names_the_address() {
+        let broker_config = BrokerConfig {
+            broker_ip1: CheetahString::from_static_str("0.0.0.0"),
+            ..BrokerConfig::default()
+        };
+        let wildcard: SocketAddr = "0.0.0.0:10911".parse().unwrap();
+        let Err(error) = resolve_store_host(wildcard, &broker_config) else {
+            panic!("a wildcard store host without a routable brokerIP1 must be rejected");
+        };
+        assert_eq!(
+            error.to_string(),
+            "store host 0.0.0.0:10911 is not routable and brokerIP1 0.0.0.0 can not replace it"
+        );
+    }
+
+    #[test]
+    fn acks_are_rejected_while_pop_buffer_drains() {
+        let service = PopBufferMergeService::new(
+            Arc::new(BrokerConfig::default()),
+            "127.0.0.1:10911".parse().unwrap(),
+        );
+        assert!(check_not_draining(&service).is_none());
+
+        service.begin_drain();
+        let response = check_not_draining(&service).unwrap();
+        assert_eq!(response.code(), ResponseCode::ServiceNotAvailable as i32);
+    }
+
+    #[test]
+    fn acks_are_retried_until_store_recovery_finishes() {
+        let running_flags = RunningFlags::new();
+        running_flags.make_recovering(true);
+        let response = check_store_recovered(&running_flags, true).unwrap();
+        assert_eq!(response.code(), ResponseCode::SystemBusy as i32);
+        assert_eq!(
+            response.remark().map(|remark| remark.as_str()),
+            Some("store recovering, try again later")
+        );
+        assert!(check_store_recovered(&running_flags, false).is_none());
+
+        running_flags.make_recovering(false);
+        assert!(check_store_recovered(&running_flags, true).is_none());
+    }
+
+    #[test]
+    fn acks_are_retried_while_broker_is_read_only() {
+        let mut broker_config = BrokerConfig {
+            broker_permission: PermName::PERM_READ,
+            ..BrokerConfig::default()
+        };
+        let response = check_broker_writeable(&broker_config).unwrap();
+        assert_eq!(response.code(), ResponseCode::SystemBusy as i32);
+        assert!(response.remark().unwrap().contains("read-only"));
+
+        broker_config.broker_permission = PermName::PERM_READ | PermName::PERM_WRITE;
+        assert!(check_broker_writeable(&broker_config).is_none());
+    }
+
+    #[test]
+    fn two_phase_ack_is_enabled_by_group_attribute() {
+        assert!(!is_two_phase_ack_enabled(None));
+        let mut config = SubscriptionGroupConfig::default();
+        assert!(!is_two_phase_ack_enabled(Some(&config)));
+        config.set_attributes(HashMap::from([(
+            CheetahString::from(SubscriptionGroupAttributes::ACK_TWO_PHASE_ATTRIBUTE.get_name()),
+            CheetahString::from_static_str("true"),
+        )]));
+        assert!(is_two_phase_ack_enabled(Some(&config)));
+    }
+
+    #[test]
+    fn orderly_ack_commits_next_offset_of_queue() {
+        let consumer_offset_manager =
+            ConsumerOffsetManager::new(Arc::new(BrokerConfig::default()), None);
+        let topic = CheetahString::from_static_str("topic");
+        let group = CheetahString::from_static_str("group");
+        let mut response = RemotingCommand::create_response_command();
+        assert!(commit_orderly_ack(
+            &consumer_offset_manager,
+            || OrderedAckCommit::Next(13),
+            "127.0.0.1:10911".parse().unwrap(),
+            &topic,
+            &group,
+            0,
+            12,
+            &mut response,
+        ));
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        assert_eq!(consumer_offset_manager.query_offset(&group, &topic, 0), 13);
+    }
+
+    #[test]
+    fn orderly_ack_below_committed_offset_is_ignored() {
+        let consumer_offset_manager =
+            ConsumerOffsetManager::new(Arc::new(BrokerConfig::default()), None);
+        let topic = CheetahString::from_static_str("topic");
+        let group = CheetahString::from_static_str("group");
+        let client_host = "127.0.0.1:10911".parse().unwrap();
+        consumer_offset_manager.commit_offset(client_host, &group, &topic, 0, 20);
+        let mut response = RemotingCommand::create_response_command();
+        assert!(!commit_orderly_ack(
+            &consumer_offset_manager,
+            || panic!("an ack below the committed offset must not reach the order info"),
+            client_host,
+            &topic,
+            &group,
+            0,
+            12,
+            &mut response,
+        ));
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        assert_eq!(consumer_offset_manager.query_offset(&group, &topic, 0), 20);
+    }
+
+    #[test]
+    fn orderly_ack_without_queue_lock_is_rejected() {
+        let consumer_offset_manager =
+            ConsumerOffsetManager::new(Arc::new(BrokerConfig::default()), None);
+        let topic = CheetahString::from_static_str("topic");
+        let group = CheetahString::from_static_str("group");
+        let mut response = RemotingCommand::create_response_command();
+        assert!(!commit_orderly_ack(
+            &consumer_offset_manager,
+            || OrderedAckCommit::NotLocked,
+            "127.0.0.1:10911".parse().unwrap(),
+            &topic,
+            &group,
+            0,
+            12,
+            &mut response,
+        ));
+        assert_eq!(response.code(), ResponseCode::MessageIllegal as i32);
+        assert!(response
+            .remark()
+            .is_some_and(|remark| remark.starts_with("queue is not locked by an orderly pop")));
+        assert_eq!(consumer_offset_manager.query_offset(&group, &topic, 0), -1);
+    }
+
+    #[test]
+    fn ack_cleaned_offset_rejects_by_default() {
+        let consumer_offset_manager =
+            ConsumerOffsetManager::new(Arc::new(BrokerConfig::default()), None);
+        let header = ack_request_header(5);
+        let response = ack_cleaned_offset(
+            AckCleanedOffsetPolicy::Reject,
+            &consumer_offset_manager,
+            "127.0.0.1:10911".parse().unwrap(),
+            &header,
+            10,
+            20,
+        );
+        assert_eq!(response.code(), ResponseCode::NoMessage as i32);
+        assert!(response
+            .remark()
+            .is_some_and(|remark| remark.contains("below min offset")));
+        assert_eq!(
+            consumer_offset_manager.query_offset(&header.consumer_group, &header.topic, 0),
+            -1
+        );
+    }
+
+    #[test]
+    fn ack_offset_ahead_asks_to_retry_later() {
+        let response = ack_offset_ahead(&ack_request_header(21), 10, 20);
+        assert_eq!(
+            response.code(),
+            ResponseCode::BrokerDispatchNotComplete as i32
+        );
+        assert!(response
+            .remark()
+            .is_some_and(|remark| remark.contains("above max offset")));
+    }
+
+    #[test]
+    fn ack_cleaned_offset_acks_and_advances_offset() {
+        let consumer_offset_manager =
+            ConsumerOffsetManager::new(Arc::new(BrokerConfig::default()), None);
+        let header = ack_request_header(5);
+        let response = ack_cleaned_offset(
+            AckCleanedOffsetPolicy::Ack,
+            &consumer_offset_manager,
+            "127.0.0.1:10911".parse().unwrap(),
+            &header,
+            10,
+            20,
+        );
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        assert_eq!(
+            consumer_offset_manager.query_offset(&header.consumer_group, &header.topic, 0),
+            10
+        );
+    }
+
+    #[test]
+    fn ack_write_route_is_local_on_master() {
+        let broker_config = BrokerConfig::default();
+        assert_eq!(AckWriteRoute::of(&broker_config, ""), AckWriteRoute::Local);
+        let broker_name = broker_config.broker_identity.broker_name.clone();
+        assert_eq!(
+            AckWriteRoute::of(&broker_config, &broker_name),
+            AckWriteRoute::Local
+        );
+    }
+
+    #[test]
+    fn ack_write_route_is_remote_for_foreign_broker() {
+        let mut broker_config = BrokerConfig::default();
+        assert_eq!(
+            AckWriteRoute::of(&broker_config, "foreign_broker"),
+            AckWriteRoute::Remote
+        );
+        broker_config.broker_identity.broker_id = 1;
+        assert_eq!(
+            AckWriteRoute::of(&broker_config, "foreign_broker"),
+            AckWriteRoute::Remote
+        );
+    }
+
+    #[test]
+    fn ack_write_route_redirects_to_master_on_slave() {
+        let mut broker_config = BrokerConfig::default();
+        broker_config.broker_identity.broker_id = 1;
+        broker_config.enable_slave_acting_master = true;
+        broker_config.enable_remote_escape = true;
+        assert_eq!(AckWriteRoute::of(&broker_config, ""), AckWriteRoute::Master);
+    }
+
+    #[test]
+    fn ack_write_route_is_unavailable_on_slave_without_escape() {
+        let mut broker_config = BrokerConfig::default();
+        broker_config.broker_identity.broker_id = 1;
+        broker_config.enable_slave_acting_master = true;
+        broker_config.enable_remote_escape = false;
+        assert_eq!(
+            AckWriteRoute::of(&broker_config, ""),
+            AckWriteRoute::Unavailable
+        );
+    }
+
+    #[test]
+    fn offset_range_in_queue_matches_min_and_max_offsets() {
+        let message_store = TestMessageStore::default();
+        message_store.set_offset_range(TEST_TOPIC, 1, 7, 42);
+        let topic = CheetahString::from_static_str(TEST_TOPIC);
+
+        for queue_id in [0, 1] {
+            assert_eq!(
+                message_store.get_offset_range_in_queue(&topic, queue_id),
+                (
+                    message_store.get_min_offset_in_queue(&topic, queue_id),
+                    message_store.get_max_offset_in_queue(&topic, queue_id)
+                )
+            );
+        }
+        assert_eq!(message_store.get_offset_range_in_queue(&topic, 1), (7, 42));
+    }
+
+    #[test]
+    fn read_queue_offset_range_returns_stable_range() {
+        let (min_offset, max_offset) = read_queue_offset_range(|| (10, 20), || 10);
+        assert_eq!(min_offset, 10);
+        assert_eq!(max_offset, 20);
+    }
+
+    #[test]
+    fn read_queue_offset_range_retries_on_rollover() {
+        // the min offset moves forward once, as if the oldest file got deleted right after
+        // a rollover, and the max offset advances along with the newly rolled file
+        let min_reads = AtomicUsize::new(0);
+        let max_offset = AtomicI64::new(100);
+        let get_min_offset = || {
+            if min_reads.fetch_add(1, Ordering::SeqCst) == 0 {
+                0
+            } else {
+                50
+            }
+        };
+        let (min_offset, max) = read_queue_offset_range(
+            || (get_min_offset(), max_offset.fetch_add(10, Ordering::SeqCst)),
+            get_min_offset,
+        );
+        assert_eq!(min_offset, 50);
+        assert_eq!(max, 110);
+    }
+
+    #[test]
+    fn read_queue_offset_range_retries_when_min_ahead_of_max() {
+        // max offset of the just rolled file is not yet visible
+        let max_reads = AtomicUsize::new(0);
+        let (min_offset, max_offset) = read_queue_offset_range(
+            || {
+                if max_reads.fetch_add(1, Ordering::SeqCst) == 0 {
+                    (100, 90)
+                } else {
+                    (100, 120)
+                }
+            },
+            || 100,
+        );
+        assert_eq!(min_offset, 100);
+        assert_eq!(max_offset, 120);
+    }
+
+    #[test]
+    fn read_queue_offset_range_gives_up_after_max_attempts() {
+        let min_reads = AtomicI64::new(0);
+        let max_reads = AtomicUsize::new(0);
+        let get_min_offset = || min_reads.fetch_add(1, Ordering::SeqCst);
+        let (min_offset, _) = read_queue_offset_range(
+            || {
+                max_reads.fetch_add(1, Ordering::SeqCst);
+                (get_min_offset(), 100)
+            },
+            get_min_offset,
+        );
+        // the range of the last attempt, the min offset moved at every read
+        assert_eq!(min_offset, 4);
+        assert_eq!(
+            max_reads.load(Ordering::SeqCst),
+            READ_OFFSET_RANGE_MAX_ATTEMPTS
+        );
+    }
+
+    /// An ack kind the processor knows nothing about, delegating its fields to an `AckMsg`.
+    struct TaggedAckMsg {
+        ack_msg: AckMsg,
+        tag: &'static str,
+    }
+
+    impl AckMessage for TaggedAckMsg {
+        fn ack_offset(&self) -> i64 {
+            self.ack_msg.ack_offset()
+        }
+
+        fn set_ack_offset(&mut self, ack_offset: i64) {
+            self.ack_msg.set_ack_offset(ack_offset);
+        }
+
+        fn start_offset(&self) -> i64 {
+            self.ack_msg.start_offset()
+        }
+
+        fn set_start_offset(&mut self, start_offset: i64) {
+            self.ack_msg.set_start_offset(start_offset);
+        }
+
+        fn consumer_group(&self) -> &CheetahString {
+            self.ack_msg.consumer_group()
+        }
+
+        fn set_consumer_group(&mut self, consumer_group: CheetahString) {
+            self.ack_msg.set_consumer_group(consumer_group);
+        }
+
+        fn topic(&self) -> &CheetahString {
+            self.ack_msg.topic()
+        }
+
+        fn set_topic(&mut self, topic: CheetahString) {
+            self.ack_msg.set_topic(topic);
+        }
+
+        fn queue_id(&self) -> i32 {
+            self.ack_msg.queue_id()
+        }
+
+        fn set_queue_id(&mut self, queue_id: i32) {
+            self.ack_msg.set_queue_id(queue_id);
+        }
+
+        fn pop_time(&self) -> i64 {
+            self.ack_msg.pop_time()
+        }
+
+        fn set_pop_time(&mut self, pop_time: i64) {
+            self.ack_msg.set_pop_time(pop_time);
+        }
+
+        fn broker_name(&self) -> &CheetahString {
+            self.ack_msg.broker_name()
+        }
+
+        fn set_broker_name(&mut self, broker_name: CheetahString) {
+            self.ack_msg.set_broker_name(broker_name);
+        }
+
+        fn ack_tag(&self) -> &'static str {
+            self.tag
+        }
+
+        fn encode_body(&self) -> Result<Vec<u8>, rocketmq_common::error::Error> {
+            Ok(format!("{}:{}", self.tag, self.ack_msg.ack_offset).into_bytes())
+        }
+
+        fn unique_id(&self) -> String {
+            format!("{}@{}", self.ack_msg.unique_id(), self.tag)
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+    }
+
+    #[test]
+    fn ack_body_is_stamped_with_its_crc() {
+        let mut inner = MessageExtBrokerInner::default();
+        let ack_msg = AckMsg::default();
+        set_ack_body(
+            &mut inner,
+            ack_msg.ack_tag(),
+            Bytes::from(ack_msg.encode_body().unwrap()),
+        );
+        assert!(inner
+            .get_property(&CheetahString::from_static_str(
+                PopAckConstants::REVIVE_BODY_CRC
+            ))
+            .is_some());
+        assert!(is_revive_body_intact(&inner.message_ext_inner));
+    }
+
+    #[test]
+    fn ack_body_is_encoded_by_the_ack_itself() {
+        let ack_msg = AckMsg {
+            ack_offset: 7,
+            ..Default::default()
+        };
+        let mut inner = MessageExtBrokerInner::default();
+        set_ack_body(
+            &mut inner,
+            ack_msg.ack_tag(),
+            Bytes::from(ack_msg.encode_body().unwrap()),
+        );
+        assert_eq!(inner.get_tags().unwrap().as_str(), PopAckConstants::ACK_TAG);
+        assert_eq!(
+            inner.get_body().unwrap().as_ref(),
+            ack_msg.encode_body().unwrap()
+        );
+
+        let batch_ack_msg = BatchAckMsg {
+            ack_msg: AckMsg::default(),
+            ack_offset_list: vec![1, 2],
+        };
+        let mut inner = MessageExtBrokerInner::default();
+        set_ack_body(
+            &mut inner,
+            batch_ack_msg.ack_tag(),
+            Bytes::from(batch_ack_msg.encode_body().unwrap()),
+        );
+        assert_eq!(
+            inner.get_tags().unwrap().as_str(),
+            PopAckConstants::BATCH_ACK_TAG
+        );
+        assert_eq!(
+            inner.get_body().unwrap().as_ref(),
+            batch_ack_msg.encode_body().unwrap()
+        );
+
+        let tagged_ack_msg = TaggedAckMsg {
+            ack_msg,
+            tag: "tAck",
+        };
+        let mut inner = MessageExtBrokerInner::default();
+        set_ack_body(
+            &mut inner,
+            tagged_ack_msg.ack_tag(),
+            Bytes::from(tagged_ack_msg.encode_body().unwrap()),
+        );
+        assert_eq!(inner.get_tags().unwrap().as_str(), "tAck");
+        assert_eq!(inner.get_body().unwrap().as_ref(), b"tAck:7");
+    }
+
+    pub(crate) const TEST_TOPIC: &str = "ack_test_topic";
+    pub(crate) const TEST_GROUP: &str = "ack_test_group";
+
+    /// An ack processor over a [`TestMessageStore`]. It is built and dropped outside of any
+    /// runtime, as the remoting client of its escape bridge owns one, and drives requests
+    /// through `process_request` on a runtime of its own.
+    pub(crate) struct TestBroker {
+        pub(crate) runtime: tokio::runtime::Runtime,
+        pub(crate) broker_config: Arc<BrokerConfig>,
+        pub(crate) processor: AckMessageProcessor<TestMessageStore>,
+        pub(crate) message_store: ArcMut<TestMessageStore>,
+    }
+
+    impl TestBroker {
+        pub(crate) fn new(broker_config: BrokerConfig) -> Self {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            // the broker stats manager starts its tasks on construction
+            let _guard = runtime.enter();
+            let broker_config = Arc::new(broker_config);
+            let broker_outer_api =
+                Arc::new(BrokerOuterAPI::new(Arc::new(TokioClientConfig::default())));
+            let topic_route_info_manager = Arc::new(TopicRouteInfoManager::new(
+                broker_outer_api.clone(),
+                broker_config.clone(),
+            ));
+            let topic_config_manager = TopicConfigManager::new(
+                broker_config.clone(),
+                Arc::new(BrokerRuntimeInner {
+                    broker_out_api: broker_outer_api.clone(),
+                    broker_config: broker_config.clone(),
+                    message_store_config: Arc::new(MessageStoreConfig::default()),
+                    server_config: Arc::new(ServerConfig::default()),
+                    topic_queue_mapping_manager: Arc::new(TopicQueueMappingManager::default()),
+                }),
+            );
+            topic_config_manager.put_topic_config(TopicConfig::with_queues(TEST_TOPIC, 4, 4));
+            let subscription_group_manager =
+                Arc::new(SubscriptionGroupManager::new(broker_config.clone(), None));
+            let message_store = ArcMut::new(TestMessageStore::default());
+            message_store.set_offset_range(TEST_TOPIC, 0, 0, 100);
+            let mut escape_bridge = ArcMut::new(EscapeBridge::new(
+                broker_config.clone(),
+                topic_route_info_manager,
+                broker_outer_api,
+            ));
+            escape_bridge.start(Some(message_store.clone()));
+            let store_host: SocketAddr = "127.0.0.1:10911".parse().unwrap();
+            let consumer_offset_manager =
+                Arc::new(ConsumerOffsetManager::new(broker_config.clone(), None));
+            let pop_buffer_merge_service = ArcMut::new(PopBufferMergeService::new(
+                broker_config.clone(),
+                store_host,
+            ));
+            let pop_inflight_message_counter =
+                Arc::new(PopInflightMessageCounter::new(Arc::new(AtomicU64::new(0))));
+            let pop_consumer_flow_controller = Arc::new(PopConsumerFlowController::default());
+            let consumer_order_info_manager = Arc::new(ConsumerOrderInfoManager::new(
+                broker_config.clone(),
+                Arc::new(topic_config_manager.clone()),
+                subscription_group_manager.clone(),
+            ));
+            let pop_message_processor = ArcMut::new(PopMessageProcessor::new(
+                broker_config.clone(),
+                topic_config_manager.clone(),
+                subscription_group_manager.clone(),
+                consumer_offset_manager.clone(),
+                consumer_order_info_manager.clone(),
+                Arc::new(ConsumerFilterManager::default()),
+                message_store.clone(),
+                escape_bridge.clone(),
+                pop_buffer_merge_service.clone(),
+                pop_inflight_message_counter.clone(),
+                pop_consumer_flow_controller.clone(),
+                store_host,
+            ));
+            let processor = AckMessageProcessor::builder()
+                .topic_config_manager(topic_config_manager.clone())
+                .subscription_group_manager(subscription_group_manager.clone())
+                .consumer_offset_manager(consumer_offset_manager)
+                .consumer_order_info_manager(consumer_order_info_manager)
+                .message_store(message_store.clone())
+                .escape_bridge(escape_bridge)
+                .pop_message_processor(pop_message_processor)
+                .broker_config(broker_config.clone())
+                .pop_inflight_message_counter(pop_inflight_message_counter)
+                .pop_sticky_assignment_manager(Arc::new(PopStickyAssignmentManager::new(
+                    broker_config.pop_sticky_assignment_lease_millis,
+                )))
+                .consumer_manager(Arc::new(ConsumerManager::new_with_broker_stats(
+                    Box::new(DefaultConsumerIdsChangeListener::default()),
+                    broker_config.clone(),
+                )))
+                .ack_invisible_time_cap_table(Arc::new(AckInvisibleTimeCapTable::default()))
+                .pop_consumer_flow_controller(pop_consumer_flow_controller)
+                .ack_processing_switch(Arc::new(AckProcessingSwitch::new(false)))
+                .pop_buffer_merge_service(pop_buffer_merge_service)
+                .broker_stats_manager(Arc::new(BrokerStatsManager::new(broker_config.clone())))
+                .store_host(store_host)
+                .build()
+                .unwrap();
+            drop(_guard);
+            TestBroker {
+                runtime,
+                broker_config,
+                processor,
+                message_store,
+            }
+        }
+
+        fn ack(&mut self, offset: i64) -> RemotingCommand {
+            let broker_name = self.broker_config.broker_identity.broker_name.clone();
+            self.ack_popped_from(&broker_name, get_current_millis() as i64, offset)
+        }
+
+        /// Acks a message popped at `pop_time` from queue 0 of the test topic on broker
+        /// `broker_name`.
+        fn ack_popped_from(
+            &mut self,
+            broker_name: &str,
+            pop_time: i64,
+            offset: i64,
+        ) -> RemotingCommand {
+            self.ack_of_topic(TEST_TOPIC, broker_name, pop_time, offset)
+        }
+
+        /// Acks a message popped orderly from queue 0 of the test topic.
+        fn ack_orderly_popped(&mut self, offset: i64) -> RemotingCommand {
+            self.ack_orderly_popped_at(get_current_millis() as i64, offset)
+        }
+
+        /// Acks a message popped orderly at `pop_time` from queue 0 of the test topic.
+        fn ack_orderly_popped_at(&mut self, pop_time: i64, offset: i64) -> RemotingCommand {
+            let broker_name = self.broker_config.broker_identity.broker_name.clone();
+            let extra_info = ExtraInfoUtil::build_extra_info(
+                offset,
+                pop_time,
+                30_000,
+                POP_ORDER_REVIVE_QUEUE,
+                TEST_TOPIC,
+                &broker_name,
+                0,
+            );
+            let request_header = AckMessageRequestHeader {
+                consumer_group: CheetahString::from_static_str(TEST_GROUP),
+                topic: CheetahString::from_static_str(TEST_TOPIC),
+                queue_id: 0,
+                extra_info: CheetahString::from_string(extra_info),
+                offset,
+                observed_pop_time: None,
+                topic_request_header: None,
+            };
+            let mut request =
+                RemotingCommand::create_request_command(RequestCode::AckMessage, request_header);
+            request.make_custom_header_to_net();
+            self.process(RequestCode::AckMessage, request)
+        }
+
+        /// Tries to lock queue 0 of the test topic for the test group, as a pop reading it does.
+        fn try_lock_queue(&self) -> bool {
+            let queue_lock_manager = self.processor.pop_message_processor.queue_lock_manager();
+            self.runtime.block_on(queue_lock_manager.try_lock(
+                &CheetahString::from_static_str(TEST_TOPIC),
+                &CheetahString::from_static_str(TEST_GROUP),
+                0,
+            ))
+        }
+
+        /// Acks a message popped at `pop_time` from queue 0 of `topic` on broker `broker_name`.
+        fn ack_of_topic(
+            &mut self,
+            topic: &str,
+            broker_name: &str,
+            pop_time: i64,
+            offset: i64,
+        ) -> RemotingCommand {
+            self.ack_in_revive_queue(topic, broker_name, pop_time, 0, offset)
+        }
+
+        /// Acks a message popped at `pop_time` from queue 0 of `topic` on broker `broker_name`
+        /// whose checkpoint went to revive queue `revive_qid`.
+        fn ack_in_revive_queue(
+            &mut self,
+            topic: &str,
+            broker_name: &str,
+            pop_time: i64,
+            revive_qid: i32,
+            offset: i64,
+        ) -> RemotingCommand {
+            let request = Self::ack_request(topic, broker_name, pop_time, revive_qid, offset);
+            self.process(RequestCode::AckMessage, request)
+        }
+
+        /// Builds the ack of a message popped at `pop_time` from queue 0 of `topic` on broker
+        /// `broker_name` whose checkpoint went to revive queue `revive_qid`.
+        fn ack_request(
+            topic: &str,
+            broker_name: &str,
+            pop_time: i64,
+            revive_qid: i32,
+            offset: i64,
+        ) -> RemotingCommand {
+            let extra_info = ExtraInfoUtil::build_extra_info(
+                0,
+                pop_time,
+                30_000,
+                revive_qid,
+                topic,
+                broker_name,
+                0,
+            );
+            let request_header = AckMessageRequestHeader {
+                consumer_group: CheetahString::from_static_str(TEST_GROUP),
+                topic: CheetahString::from_string(topic.to_string()),
+                queue_id: 0,
+                extra_info: CheetahString::from_string(extra_info),
+                offset,
+                observed_pop_time: None,
+                topic_request_header: None,
+            };
+            let mut request =
+                RemotingCommand::create_request_command(RequestCode::AckMessage, request_header);
+            request.make_custom_header_to_net();
+            request
+        }
+
+        /// Acks `offsets` of a checkpoint popped at `pop_time` and starting at offset 10 of
+        /// queue 0 of the test topic in a single batch ack.
+        fn batch_ack(&mut self, pop_time: i64, offsets: &[i64]) -> RemotingCommand {
+            self.batch_ack_with_retry("0", pop_time, offsets)
+        }
+
+        /// Batch acks as `batch_ack` does, with the `retry` flag of the receipt handles.
+        fn batch_ack_with_retry(
+            &mut self,
+            retry: &str,
+            pop_time: i64,
+            offsets: &[i64],
+        ) -> RemotingCommand {
+            let mut bit_set = BitVec::<u64, Lsb0>::repeat(false, 64);
+            for offset in offsets {
+                bit_set.set((offset - 10) as usize, true);
+            }
+            let body = BatchAckMessageRequestBody {
+                broker_name: self.broker_config.broker_identity.broker_name.clone(),
+                acks: vec![BatchAck {
+                    consumer_group: CheetahString::from_static_str(TEST_GROUP),
+                    topic: CheetahString::from_static_str(TEST_TOPIC),
+                    retry: CheetahString::from_string(retry.to_string()),
+                    start_offset: 10,
+                    queue_id: 0,
+                    revive_queue_id: 0,
+                    pop_time,
+                    invisible_time: 30_000,
+                    bit_set: SerializableBitVec(bit_set),
+                }],
+            };
+            let request = RemotingCommand::create_remoting_command(RequestCode::BatchAckMessage)
+                .set_body(body.encode().unwrap());
+            self.process(RequestCode::BatchAckMessage, request)
+        }
+
+        pub(crate) fn pop_message_processor(
+            &self,
+        ) -> ArcMut<PopMessageProcessor<TestMessageStore>> {
+            self.processor.pop_message_processor.clone()
+        }
+
+        /// Makes messages tagged `tags` readable from offset 0 of queue 0 of the test topic.
+        pub(crate) fn store_messages(&self, tags: &[&str]) {
+            for (offset, tag) in tags.iter().enumerate() {
+                let mut msg_ext = MessageExt::default();
+                msg_ext.set_topic(CheetahString::from_static_str(TEST_TOPIC));
+                msg_ext.set_tags(CheetahString::from_string(tag.to_string()));
+                msg_ext.set_body(Bytes::from_static(b"popped"));
+                msg_ext.queue_offset = offset as i64;
+                self.message_store.add_stored_message(msg_ext);
+            }
+        }
+
+        /// Pops up to `max_msg_nums` messages of queue 0 of the test topic matching the tag
+        /// expression `exp`, for a consumer advertising `processing_capacity`.
+        fn pop(
+            &mut self,
+            max_msg_nums: u32,
+            exp: &str,
+            processing_capacity: Option<u32>,
+        ) -> RemotingCommand {
+            self.pop_with(PopMessageRequestHeader {
+                consumer_group: CheetahString::from_static_str(TEST_GROUP),
+                topic: CheetahString::from_static_str(TEST_TOPIC),
+                queue_id: 0,
+                max_msg_nums,
+                invisible_time: 30_000,
+                exp: Some(CheetahString::from_string(exp.to_string())),
+                processing_capacity,
+                ..Default::default()
+            })
+        }
+
+        /// Pops queue 0 of the test topic orderly, as the attempt `attempt_id` of a consumer.
+        fn pop_orderly(&mut self, attempt_id: &str, invisible_time: u64) -> RemotingCommand {
+            self.pop_with(PopMessageRequestHeader {
+                consumer_group: CheetahString::from_static_str(TEST_GROUP),
+                topic: CheetahString::from_static_str(TEST_TOPIC),
+                queue_id: 0,
+                max_msg_nums: 32,
+                invisible_time,
+                order: Some(true),
+                attempt_id: Some(CheetahString::from_string(attempt_id.to_string())),
+                ..Default::default()
+            })
+        }
+
+        pub(crate) fn pop_with(
+            &mut self,
+            request_header: PopMessageRequestHeader,
+        ) -> RemotingCommand {
+            self.try_pop_with(request_header)
+                .expect("pop held waiting for messages")
+        }
+
+        /// Pops as `request_header` asks, `None` when the pop is held until messages arrive.
+        pub(crate) fn try_pop_with(
+            &mut self,
+            request_header: PopMessageRequestHeader,
+        ) -> Option<RemotingCommand> {
+            let mut request =
+                RemotingCommand::create_request_command(RequestCode::PopMessage, request_header);
+            request.make_custom_header_to_net();
+            let mut pop_message_processor = self.processor.pop_message_processor.clone();
+            self.runtime.block_on(async {
+                let channel = test_channel().await;
+                let ctx = ArcMut::new(ConnectionHandlerContextWrapper::new(channel.clone()));
+                pop_message_processor
+                    .process_request(
+                        channel,
+                        ArcMut::downgrade(&ctx),
+                        RequestCode::PopMessage,
+                        request,
+                    )
+                    .await
+                    .unwrap()
+            })
+        }
+
+        fn process(
+            &mut self,
+            request_code: RequestCode,
+            request: RemotingCommand,
+        ) -> RemotingCommand {
+            self.process_within(request_code, request, Duration::from_secs(60))
+                .expect("request not answered in time")
+        }
+
+        /// Handles `request`, dropping it mid-flight when it is not answered within
+        /// `timeout`, as a cancelled connection task does.
+        fn process_within(
+            &mut self,
+            request_code: RequestCode,
+            request: RemotingCommand,
+            timeout: Duration,
+        ) -> Option<RemotingCommand> {
+            let processor = &mut self.processor;
+            self.runtime.block_on(async {
+                let channel = test_channel().await;
+                // receipt handles built by ExtraInfoUtil are space separated
+                channel.set_pop_ack_protocol_version(PopAckProtocolVersion::V2);
+                let ctx = ArcMut::new(ConnectionHandlerContextWrapper::new(channel.clone()));
+                tokio::time::timeout(
+                    timeout,
+                    processor.process_request(
+                        channel,
+                        ArcMut::downgrade(&ctx),
+                        request_code,
+                        request,
+                    ),
+                )
+                .await
+                .ok()
+                .map(|response| response.unwrap().unwrap())
+            })
+        }
+    }
+
+    #[test]
+    fn slave_forwards_ack_to_master_instead_of_writing_locally() {
+        let mut broker_config = BrokerConfig {
+            enable_slave_acting_master: true,
+            enable_remote_escape: true,
+            revive_ack_msg_retry_times: 0,
+            ..BrokerConfig::default()
+        };
+        broker_config.broker_identity.broker_id = 1;
+        let mut broker = TestBroker::new(broker_config);
+
+        let response = broker.ack(10);
+
+        // no name server knows the master, the forward fails and the client retries the ack
+        assert_eq!(response.code(), ResponseCode::ServiceNotAvailable as i32);
+        assert!(response
+            .remark()
+            .unwrap()
+            .starts_with("forward ack to master failed"));
+        assert!(broker.message_store.put_messages().is_empty());
+    }
+
+    #[test]
+    fn enriched_properties_reach_the_stored_ack_message() {
+        let broker_config = BrokerConfig {
+            enable_ack_property_enrichment: true,
+            zone_name: CheetahString::from_static_str("zone-a"),
+            ..BrokerConfig::default()
+        };
+        let mut broker = TestBroker::new(broker_config);
+
+        let response = broker.ack(10);
+
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        let stored = broker.message_store.put_messages();
+        assert_eq!(stored.len(), 1);
+        let property = |name: &'static str| {
+            stored[0]
+                .get_property(&CheetahString::from_static_str(name))
+                .unwrap()
+        };
+        assert_eq!(
+            property(MessageConst::PROPERTY_CLUSTER),
+            broker.broker_config.broker_identity.broker_cluster_name
+        );
+        assert_eq!(
+            property(MessageConst::PROPERTY_BROKER_ID).as_str(),
+            broker.broker_config.broker_identity.broker_id.to_string()
+        );
+        assert_eq!(property(mix_all::ZONE_NAME).as_str(), "zone-a");
+    }
+
+    #[test]
+    fn escaped_ack_is_stored_even_when_its_origin_broker_is_unreachable() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+
+        let response = broker.ack_popped_from("origin-broker", get_current_millis() as i64, 10);
+
+        // the offset commit forwarded to the origin broker fails without a name server, the
+        // ack is still stored locally
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        let stored = broker.message_store.put_messages();
+        assert_eq!(stored.len(), 1);
+        let ack_msg = AckMsg::decode_body(stored[0].get_body().unwrap()).unwrap();
+        assert_eq!(ack_msg.broker_name.as_str(), "origin-broker");
+        assert_eq!(ack_msg.ack_offset, 10);
+        // the offset of the origin broker is not committed locally
+        assert_eq!(
+            broker.processor.consumer_offset_manager.query_offset(
+                &CheetahString::from_static_str(TEST_GROUP),
+                &CheetahString::from_static_str(TEST_TOPIC),
+                0
+            ),
+            -1
+        );
+    }
+
+    #[test]
+    fn batch_ack_is_stored_under_its_batch_unique_id() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+
+        let response = broker.batch_ack(get_current_millis() as i64, &[11, 13]);
+
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        let stored = broker.message_store.put_messages();
+        assert_eq!(stored.len(), 1);
+        let batch_ack_msg = BatchAckMsg::decode_body(stored[0].get_body().unwrap()).unwrap();
+        assert_eq!(batch_ack_msg.ack_offset_list, vec![11, 13]);
+        let unique_id = stored[0]
+            .get_property(&CheetahString::from_static_str(
+                MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX,
+            ))
+            .unwrap();
+        assert_eq!(unique_id.as_str(), batch_ack_msg.unique_id());
+        assert!(unique_id.starts_with("ack_test_topic@0@10@ack_test_group@"));
+        assert!(unique_id.ends_with("@bAck"));
+    }
+
+    #[test]
+    fn batch_ack_with_unparsable_retry_flag_is_rejected() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+
+        let response = broker.batch_ack_with_retry("x", get_current_millis() as i64, &[11]);
+
+        assert_eq!(response.code(), ResponseCode::MessageIllegal as i32);
+        assert_eq!(
+            response.remark().unwrap().as_str(),
+            "extraInfo has an invalid retry: x"
+        );
+        assert!(broker.message_store.put_messages().is_empty());
+    }
+
+    #[test]
+    fn batch_ack_of_unknown_queue_is_rejected() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+
+        // the pop retry topic of the test group has no queue
+        let response = broker.batch_ack_with_retry("1", get_current_millis() as i64, &[11]);
+
+        assert_eq!(response.code(), ResponseCode::SystemError as i32);
+        assert!(response
+            .remark()
+            .unwrap()
+            .starts_with("Illegal topic or queue found when batch ack"));
+        assert!(broker.message_store.put_messages().is_empty());
+    }
+
+    #[test]
+    fn batch_acks_of_one_checkpoint_are_told_apart() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+
+        let pop_time = get_current_millis() as i64;
+        assert_eq!(
+            broker.batch_ack(pop_time, &[11, 13]).code(),
+            ResponseCode::Success as i32
+        );
+        assert_eq!(
+            broker.batch_ack(pop_time, &[12, 14]).code(),
+            ResponseCode::Success as i32
+        );
+
+        // neither batch ack is taken for a resent one nor needs its id salted
+        let stored = broker.message_store.put_messages();
+        assert_eq!(stored.len(), 2);
+        let unique_ids: Vec<_> = stored
+            .iter()
+            .map(|msg| {
+                let batch_ack_msg = BatchAckMsg::decode_body(msg.get_body().unwrap()).unwrap();
+                let unique_id = msg
+                    .get_property(&CheetahString::from_static_str(
+                        MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX,
+                    ))
+                    .unwrap();
+                assert_eq!(unique_id.as_str(), batch_ack_msg.unique_id());
+                unique_id
+            })
+            .collect();
+        assert_ne!(unique_ids[0], unique_ids[1]);
+    }
+
+    #[test]
+    fn ack_on_read_only_broker_is_rejected_without_put() {
+        let broker_config = BrokerConfig {
+            broker_permission: PermName::PERM_READ,
+            ..BrokerConfig::default()
+        };
+        let mut broker = TestBroker::new(broker_config);
+
+        let response = broker.ack(10);
+
+        assert_eq!(response.code(), ResponseCode::SystemBusy as i32);
+        assert!(response.remark().unwrap().contains("read-only"));
+        assert!(broker.message_store.put_messages().is_empty());
+    }
+
+    #[test]
+    fn single_ack_of_a_retry_handle_applies_to_the_pop_retry_topic() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        let broker_name = broker.broker_config.broker_identity.broker_name.clone();
+        let retry_topic = CheetahString::from_string(KeyBuilder::build_pop_retry_topic_v1(
+            TEST_TOPIC, TEST_GROUP,
+        ));
+        broker
+            .message_store
+            .set_offset_range(retry_topic.as_str(), 0, 0, 100);
+        let pop_time = get_current_millis() as i64;
+        broker
+            .processor
+            .pop_inflight_message_counter
+            .increment_in_flight_message_num(
+                &retry_topic,
+                &CheetahString::from_static_str(TEST_GROUP),
+                0,
+                1,
+            );
+        // the handle is of the retry topic, the client acks with the topic it subscribed to
+        let request_header = AckMessageRequestHeader {
+            consumer_group: CheetahString::from_static_str(TEST_GROUP),
+            topic: CheetahString::from_static_str(TEST_TOPIC),
+            queue_id: 0,
+            extra_info: CheetahString::from_string(ExtraInfoUtil::build_extra_info(
+                0,
+                pop_time,
+                30_000,
+                0,
+                &retry_topic,
+                &broker_name,
+                0,
+            )),
+            offset: 10,
+            observed_pop_time: None,
+            topic_request_header: None,
+        };
+        let mut request =
+            RemotingCommand::create_request_command(RequestCode::AckMessage, request_header);
+        request.make_custom_header_to_net();
+
+        let response = broker.process(RequestCode::AckMessage, request);
+
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        let put_messages = broker.message_store.put_messages();
+        assert_eq!(put_messages.len(), 1);
+        let ack_msg = decode_ack_body(
+            put_messages[0].get_tags().unwrap().as_str(),
+            put_messages[0].get_body().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(ack_msg.topic(), &retry_topic);
+        assert_eq!(
+            broker
+                .processor
+                .pop_inflight_message_counter
+                .get_in_flight_message_num(
+                    &retry_topic,
+                    &CheetahString::from_static_str(TEST_GROUP),
+                    0
+                ),
+            0
+        );
+    }
+
+    #[test]
+    fn ack_dropped_while_its_put_is_pending_gives_back_in_flight() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        let broker_name = broker.broker_config.broker_identity.broker_name.clone();
+        let pop_time = get_current_millis() as i64;
+        broker
+            .processor
+            .pop_inflight_message_counter
+            .increment_in_flight_message_num(
+                &CheetahString::from_static_str(TEST_TOPIC),
+                &CheetahString::from_static_str(TEST_GROUP),
+                0,
+                2,
+            );
+        broker.message_store.hold_puts();
+
+        let request = TestBroker::ack_request(TEST_TOPIC, &broker_name, pop_time, 0, 10);
+        let response =
+            broker.process_within(RequestCode::AckMessage, request, Duration::from_millis(100));
+
+        assert!(response.is_none());
+        assert_eq!(broker.message_store.put_messages().len(), 1);
+        assert_eq!(in_flight_of_test_queue(&broker), 1);
+    }
+
+    #[test]
+    fn ack_stuck_in_its_put_times_out() {
+        let broker_config = BrokerConfig {
+            ack_process_timeout_millis: 50,
+            ..BrokerConfig::default()
+        };
+        let mut broker = TestBroker::new(broker_config);
+        broker
+            .processor
+            .pop_inflight_message_counter
+            .increment_in_flight_message_num(
+                &CheetahString::from_static_str(TEST_TOPIC),
+                &CheetahString::from_static_str(TEST_GROUP),
+                0,
+                1,
+            );
+        broker.message_store.hold_puts();
+
+        let response = broker.ack(10);
+
+        assert_eq!(response.code(), ResponseCode::SystemBusy as i32);
+        assert!(response
+            .remark()
+            .is_some_and(|remark| remark.contains("ack process timeout, more than 50ms")));
+        assert_eq!(broker.message_store.put_messages().len(), 1);
+        assert_eq!(in_flight_of_test_queue(&broker), 0);
+    }
+
+    #[test]
+    fn ack_retried_after_timeout_is_stored_within_dedup_window() {
+        let broker_config = BrokerConfig {
+            ack_process_timeout_millis: 50,
+            pop_ack_dedup_window_millis: 60_000,
+            ..BrokerConfig::default()
+        };
+        let mut broker = TestBroker::new(broker_config);
+        let broker_name = broker.broker_config.broker_identity.broker_name.clone();
+        let pop_time = get_current_millis() as i64;
+        broker.message_store.hold_puts();
+
+        let timed_out = broker.ack_popped_from(&broker_name, pop_time, 10);
+        broker.message_store.release_puts();
+        let retried = broker.ack_popped_from(&broker_name, pop_time, 10);
+
+        assert_eq!(timed_out.code(), ResponseCode::SystemBusy as i32);
+        assert_eq!(retried.code(), ResponseCode::Success as i32);
+        // the retry is not taken for a duplicate of the ack whose put was dropped
+        assert_eq!(broker.message_store.put_messages().len(), 2);
+    }
+
+    #[test]
+    fn oversized_batch_ack_is_rejected_before_any_put() {
+        let broker_config = BrokerConfig {
+            batch_ack_max_count: 4,
+            ..BrokerConfig::default()
+        };
+        let mut broker = TestBroker::new(broker_config);
+
+        let response = broker.batch_ack(get_current_millis() as i64, &[10, 11, 12, 13, 14]);
+
+        assert_eq!(response.code(), ResponseCode::MessageIllegal as i32);
+        assert!(response
+            .remark()
+            .is_some_and(|remark| remark.contains("split it into smaller requests")));
+        assert!(broker.message_store.put_messages().is_empty());
+    }
+
+    #[test]
+    fn ack_resent_within_dedup_window_is_stored_once() {
+        let broker_config = BrokerConfig {
+            pop_ack_dedup_window_millis: 60_000,
+            ..BrokerConfig::default()
+        };
+        let mut broker = TestBroker::new(broker_config);
+        let broker_name = broker.broker_config.broker_identity.broker_name.clone();
+        let pop_time = get_current_millis() as i64;
+
+        let first = broker.ack_popped_from(&broker_name, pop_time, 10);
+        let resent = broker.ack_popped_from(&broker_name, pop_time, 10);
+
+        assert_eq!(first.code(), ResponseCode::Success as i32);
+        assert_eq!(resent.code(), ResponseCode::Success as i32);
+        assert_eq!(broker.message_store.put_messages().len(), 1);
+    }
+
+    #[test]
+    fn resent_ack_moves_exhausted_retry_message_to_dlq_once() {
+        let broker_config = BrokerConfig {
+            enable_pop_retry_dlq_fallback: true,
+            ..BrokerConfig::default()
+        };
+        let mut broker = TestBroker::new(broker_config);
+        let broker_name = broker.broker_config.broker_identity.broker_name.clone();
+        let retry_topic = mix_all::get_retry_topic(TEST_GROUP);
+        let dlq_topic = mix_all::get_dlq_topic(TEST_GROUP);
+        for topic in [&retry_topic, &dlq_topic] {
+            broker
+                .processor
+                .topic_config_manager
+                .put_topic_config(TopicConfig::with_queues(topic.as_str(), 1, 1));
+        }
+        broker
+            .message_store
+            .set_offset_range(retry_topic.as_str(), 0, 0, 100);
+        let mut msg_ext = MessageExt::default();
+        msg_ext.set_topic(CheetahString::from_string(retry_topic.clone()));
+        msg_ext.set_body(Bytes::from_static(b"exhausted"));
+        msg_ext.queue_offset = 10;
+        msg_ext.reconsume_times = SubscriptionGroupConfig::default().retry_max_times();
+        broker.message_store.add_stored_message(msg_ext);
+        let pop_time = get_current_millis() as i64;
+
+        let first = broker.ack_of_topic(&retry_topic, &broker_name, pop_time, 10);
+        let resent = broker.ack_of_topic(&retry_topic, &broker_name, pop_time, 10);
+
+        assert_eq!(first.code(), ResponseCode::Success as i32);
+        assert_eq!(resent.code(), ResponseCode::Success as i32);
+        let dlq_messages = broker.message_store.put_messages_of(&dlq_topic);
+        assert_eq!(dlq_messages.len(), 1);
+        assert_eq!(dlq_messages[0].get_body().unwrap().as_ref(), b"exhausted");
+    }
+
+    #[test]
+    fn dlq_fallback_reads_only_acks_of_retry_topics() {
+        let broker_config = BrokerConfig {
+            enable_pop_retry_dlq_fallback: true,
+            ..BrokerConfig::default()
+        };
+        let mut broker = TestBroker::new(broker_config);
+
+        assert_eq!(broker.ack(10).code(), ResponseCode::Success as i32);
+        assert_eq!(
+            broker
+                .batch_ack(get_current_millis() as i64, &[11, 12, 13])
+                .code(),
+            ResponseCode::Success as i32
+        );
+        assert_eq!(broker.message_store.get_message_reads(), 0);
+    }
+
+    #[test]
+    fn orderly_ack_of_locked_queue_is_retried_instead_of_waiting() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        assert!(broker.try_lock_queue());
+
+        let response = broker.ack_orderly_popped(10);
+
+        assert_eq!(response.code(), ResponseCode::SystemBusy as i32);
+        assert!(response
+            .remark()
+            .unwrap()
+            .starts_with("queue is locked, try ack again later"));
+        assert_eq!(
+            broker.processor.consumer_offset_manager.query_offset(
+                &CheetahString::from_static_str(TEST_GROUP),
+                &CheetahString::from_static_str(TEST_TOPIC),
+                0
+            ),
+            -1
+        );
+    }
+
+    #[test]
+    fn orderly_ack_releases_queue_lock() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+
+        let response = broker.ack_orderly_popped(10);
+
+        assert_ne!(response.code(), ResponseCode::SystemBusy as i32);
+        assert!(broker.try_lock_queue());
+    }
+
+    #[test]
+    fn master_writes_ack_to_local_revive_topic() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+
+        let response = broker.ack(10);
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        let revive_topic = PopAckConstants::build_cluster_revive_topic(
+            broker
+                .broker_config
+                .broker_identity
+                .broker_cluster_name
+                .as_str(),
+        );
+        assert_eq!(broker.message_store.put_messages_of(&revive_topic).len(), 1);
+    }
+
+    #[test]
+    fn parity_telemetry_counts_fallthrough_and_orderly_acks() {
+        let broker_config = BrokerConfig {
+            enable_ack_parity_telemetry: true,
+            ..BrokerConfig::default()
+        };
+        let mut broker = TestBroker::new(broker_config);
+        broker.store_messages(&["TagA"]);
+
+        assert_eq!(broker.ack(10).code(), ResponseCode::Success as i32);
+        let response_header = pop_response_header(broker.pop_orderly("attempt-1", 30_000));
+        let response = broker.ack_orderly_popped_at(response_header.pop_time as i64, 0);
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+
+        assert_eq!(
+            broker.processor.ack_parity_telemetry.snapshot(),
+            vec![
+                (ParityGap::OrderlyAckNotify, 1),
+                (ParityGap::BufferMergeFallthrough, 1)
+            ]
+        );
+    }
+
+    fn popped_messages(response: &RemotingCommand) -> Vec<MessageExt> {
+        let mut body = response.body().clone().unwrap_or_default();
+        message_decoder::decodes_batch(&mut body, true, false)
+    }
+
+    /// The queue offsets of the messages a pop handed out, each message of an inner batch on
+    /// its own.
+    fn popped_queue_offsets(response: &RemotingCommand) -> Vec<i64> {
+        let mut queue_offsets = Vec::new();
+        for msg in popped_messages(response) {
+            let offsets = inner_queue_offsets(&msg);
+            if offsets.end - offsets.start > 1 {
+                // the body holds exactly the messages the batch tells
+                let inner_num = message_decoder::count_inner_msg_num(msg.get_body().cloned());
+                assert_eq!(inner_num as i64, offsets.end - offsets.start);
+            }
+            queue_offsets.extend(offsets);
+        }
+        queue_offsets
+    }
+
+    /// A store entry of the test topic holding `num` messages from offset `base`.
+    fn inner_batch(base: i64, num: i64) -> MessageExt {
+        let messages = (base..base + num)
+            .map(|queue_offset| Message::new(TEST_TOPIC, queue_offset.to_string().as_bytes()))
+            .collect::<Vec<_>>();
+        let mut batch = MessageExt::default();
+        batch.set_topic(CheetahString::from_static_str(TEST_TOPIC));
+        batch.set_body(message_decoder::encode_messages(&messages));
+        batch.sys_flag = MessageSysFlag::INNER_BATCH_FLAG;
+        batch.queue_offset = base;
+        batch.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_INNER_NUM),
+            CheetahString::from_string(num.to_string()),
+        );
+        batch.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_INNER_BASE),
+            CheetahString::from_string(base.to_string()),
+        );
+        batch
+    }
+
+    fn pop_response_header(mut response: RemotingCommand) -> PopMessageResponseHeader {
+        response.make_custom_header_to_net();
+        response
+            .decode_command_custom_header::<PopMessageResponseHeader>()
+            .unwrap()
+    }
+
+    fn in_flight_of_test_queue(broker: &TestBroker) -> i64 {
+        broker
+            .processor
+            .pop_inflight_message_counter
+            .get_in_flight_message_num(
+                &CheetahString::from_static_str(TEST_TOPIC),
+                &CheetahString::from_static_str(TEST_GROUP),
+                0,
+            )
+    }
+
+    #[test]
+    fn pop_delivers_only_messages_matching_the_subscription() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        broker.store_messages(&["TagA", "TagB", "TagA"]);
+
+        let response = broker.pop(32, "TagA", None);
+
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        let offsets: Vec<i64> = popped_messages(&response)
+            .iter()
+            .map(|msg_ext| msg_ext.queue_offset)
+            .collect();
+        assert_eq!(offsets, vec![0, 2]);
+        assert_eq!(in_flight_of_test_queue(&broker), 2);
+        // the filtered message is skipped for good
+        assert_eq!(
+            broker.processor.consumer_offset_manager.query_offset(
+                &CheetahString::from_static_str(TEST_GROUP),
+                &CheetahString::from_static_str(TEST_TOPIC),
+                0
+            ),
+            3
+        );
+        // without buffering the checkpoint goes straight to the revive topic
+        let revive_topic = PopAckConstants::build_cluster_revive_topic(
+            broker
+                .broker_config
+                .broker_identity
+                .broker_cluster_name
+                .as_str(),
+        );
+        let stored = broker.message_store.put_messages_of(&revive_topic);
+        assert_eq!(stored.len(), 1);
+        assert_eq!(
+            stored[0].get_tags().unwrap().as_str(),
+            PopAckConstants::CK_TAG
+        );
+    }
+
+    #[test]
+    fn pop_delivers_no_more_than_the_consumer_can_process() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        broker.store_messages(&["TagA"; 8]);
+
+        let response = broker.pop(32, "*", Some(3));
+
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        assert_eq!(popped_messages(&response).len(), 3);
+        let response_header = pop_response_header(response);
+        assert_eq!(response_header.rest_num, 97);
+    }
+
+    #[test]
+    fn pop_of_empty_queue_times_out() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+
+        let response = broker.pop(32, "*", None);
+
+        assert_eq!(response.code(), ResponseCode::PollingTimeout as i32);
+        assert!(broker.message_store.put_messages().is_empty());
+    }
+
+    #[test]
+    fn ack_of_popped_message_merges_into_buffered_check_point() {
+        let broker_config = BrokerConfig {
+            enable_pop_buffer_merge: true,
+            ..BrokerConfig::default()
+        };
+        let mut broker = TestBroker::new(broker_config);
+        let broker_name = broker.broker_config.broker_identity.broker_name.clone();
+        broker.store_messages(&["TagA", "TagA", "TagA"]);
+        let response = broker.pop(32, "*", None);
+        let response_header = pop_response_header(response);
+        assert_eq!(response_header.revive_qid, 0);
+        assert_eq!(broker.processor.pop_buffer_merge_service.buffered_num(), 1);
+        assert_eq!(in_flight_of_test_queue(&broker), 3);
+
+        let response = broker.ack_popped_from(&broker_name, response_header.pop_time as i64, 1);
+
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        // neither the checkpoint nor the ack reached the revive topic
+        assert!(broker.message_store.put_messages().is_empty());
+        assert_eq!(in_flight_of_test_queue(&broker), 2);
+    }
+
+    #[test]
+    fn ack_of_inner_batch_message_leaves_its_siblings_unacked() {
+        let broker_config = BrokerConfig {
+            enable_pop_buffer_merge: true,
+            ..BrokerConfig::default()
+        };
+        let mut broker = TestBroker::new(broker_config);
+        let broker_name = broker.broker_config.broker_identity.broker_name.clone();
+        // a store entry holding the messages at offsets 0 to 2, then a single message
+        broker.message_store.add_stored_message(inner_batch(0, 3));
+        let mut single = MessageExt::default();
+        single.set_topic(CheetahString::from_static_str(TEST_TOPIC));
+        single.set_body(Bytes::from_static(b"single"));
+        single.queue_offset = 3;
+        broker.message_store.add_stored_message(single);
+        let response_header = pop_response_header(broker.pop(32, "*", None));
+        assert_eq!(in_flight_of_test_queue(&broker), 4);
+        let pop_time = response_header.pop_time as i64;
+
+        for offset in [1, 3] {
+            let response = broker.ack_popped_from(&broker_name, pop_time, offset);
+            assert_eq!(response.code(), ResponseCode::Success as i32);
+        }
+
+        let flushes = broker.processor.pop_buffer_merge_service.scan_all();
+        assert_eq!(flushes.len(), 1);
+        assert_eq!(flushes[0].ack_offsets(), &[1, 3]);
+        // the messages 0 and 2 of the batch are still revived
+        assert!(!flushes[0].wrapper().is_all_acked());
+        assert_eq!(in_flight_of_test_queue(&broker), 2);
+    }
+
+    #[test]
+    fn inner_batch_beyond_a_check_point_is_carried_over_to_the_next_pop() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        // a store entry holding the messages at offsets 0 to 39
+        broker.message_store.add_stored_message(inner_batch(0, 40));
+        let response = broker.pop(32, "*", None);
+        // the first 32 messages fill the checkpoint, only they are handed out
+        assert_eq!(popped_queue_offsets(&response), (0..32).collect::<Vec<_>>());
+        assert_eq!(in_flight_of_test_queue(&broker), 32);
+        assert_eq!(
+            broker.processor.consumer_offset_manager.query_offset(
+                &CheetahString::from_static_str(TEST_GROUP),
+                &CheetahString::from_static_str(TEST_TOPIC),
+                0
+            ),
+            32
+        );
+
+        // the next pop reads the rest of the batch from where the first one stopped
+        let response = broker.pop(32, "*", None);
+        assert_eq!(
+            popped_queue_offsets(&response),
+            (32..40).collect::<Vec<_>>()
+        );
+        let response_header = pop_response_header(response);
+        let start_offset_info =
+            ExtraInfoUtil::parse_start_offset_info(&response_header.start_offset_info.unwrap())
+                .unwrap();
+        assert_eq!(
+            start_offset_info.get(&ExtraInfoUtil::get_start_offset_info_map_key(TEST_TOPIC, 0)),
+            Some(&32)
+        );
+        assert_eq!(in_flight_of_test_queue(&broker), 40);
+    }
+
+    #[test]
+    fn pop_of_fewer_messages_than_a_batch_hands_out_only_what_it_asked_for() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        broker.message_store.add_stored_message(inner_batch(0, 4));
+        broker.message_store.add_stored_message(inner_batch(4, 4));
+
+        let mut popped = Vec::new();
+        for expected in [0..3, 3..6, 6..8] {
+            let response = broker.pop(3, "*", None);
+            let queue_offsets = popped_queue_offsets(&response);
+            assert_eq!(queue_offsets, expected.collect::<Vec<_>>());
+            assert_eq!(
+                pop_response_header(response).msg_offset_info.is_some(),
+                !queue_offsets.is_empty()
+            );
+            popped.extend(queue_offsets);
+        }
+        // every message is handed out once
+        assert_eq!(popped, (0..8).collect::<Vec<_>>());
+        assert_eq!(in_flight_of_test_queue(&broker), 8);
+    }
+
+    #[test]
+    fn message_not_flagged_as_inner_batch_is_popped_as_one_message() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        let mut unflagged = inner_batch(0, 4);
+        unflagged.sys_flag = 0;
+        broker.message_store.add_stored_message(unflagged);
+
+        let response = broker.pop(32, "*", None);
+
+        assert_eq!(popped_messages(&response).len(), 1);
+        assert_eq!(in_flight_of_test_queue(&broker), 1);
+    }
+
+    #[test]
+    fn ack_is_rejected_as_busy_while_every_put_slot_is_taken() {
+        let broker_config = BrokerConfig {
+            max_concurrent_ack_puts: 1,
+            ack_put_permit_timeout_millis: 20,
+            ..BrokerConfig::default()
+        };
+        let mut broker = TestBroker::new(broker_config);
+        let ack_put_limiter = broker.processor.ack_put_limiter.clone();
+        let held = broker.runtime.block_on(ack_put_limiter.acquire()).unwrap();
+
+        let response = broker.ack(10);
+
+        assert_eq!(response.code(), ResponseCode::SystemBusy as i32);
+        assert_eq!(
+            response.remark().map(|remark| remark.as_str()),
+            Some("too many ack puts in flight, more than 1, try again later")
+        );
+        assert!(broker.message_store.put_messages().is_empty());
+        assert_eq!(ack_put_limiter.inflight_num(), 1);
+
+        // the ack is not remembered as a duplicate and goes through once a slot is free
+        drop(held);
+        let response = broker.ack(10);
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        assert_eq!(broker.message_store.put_messages().len(), 1);
+        assert_eq!(ack_put_limiter.inflight_num(), 0);
+    }
+
+    #[test]
+    fn failed_ack_put_is_reported_to_the_client() {
+        let broker_config = BrokerConfig {
+            revive_ack_msg_retry_times: 1,
+            ..BrokerConfig::default()
+        };
+        let mut broker = TestBroker::new(broker_config);
+        broker.message_store.push_put_statuses([
+            PutMessageStatus::CreateMappedFileFailed,
+            PutMessageStatus::CreateMappedFileFailed,
+        ]);
+
+        let response = broker.ack(10);
+
+        assert_eq!(response.code(), ResponseCode::SystemError as i32);
+        assert_eq!(
+            response.remark().map(|remark| remark.as_str()),
+            Some("put ack msg to revive topic failed, status: CreateMappedFileFailed")
+        );
+        assert_eq!(broker.message_store.put_messages().len(), 2);
+
+        // the client retries the ack, which is not mistaken for a duplicate
+        let response = broker.ack(10);
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        assert_eq!(broker.message_store.put_messages().len(), 3);
+    }
+
+    #[test]
+    fn failed_ack_puts_are_sampled_before_being_logged() {
+        let broker_config = BrokerConfig {
+            revive_ack_msg_retry_times: 1,
+            ack_failure_log_sample_rate: 4,
+            ..BrokerConfig::default()
+        };
+        let mut broker = TestBroker::new(broker_config);
+        broker
+            .message_store
+            .push_put_statuses([PutMessageStatus::CreateMappedFileFailed; 10]);
+
+        for offset in 10..15 {
+            assert_eq!(broker.ack(offset).code(), ResponseCode::SystemError as i32);
+        }
+
+        // the failures 1 and 5 are logged
+        assert_eq!(broker.processor.ack_failure_log_sampler.failures(), 5);
+        assert_eq!(broker.processor.ack_failure_log_sampler.sample(), None);
+    }
+
+    /// Records the acks it observes.
+    #[derive(Clone, Default)]
+    struct RecordingAckMessageHook(Arc<parking_lot::Mutex<Vec<AckMessageContext>>>);
+
+    impl AckMessageHook for RecordingAckMessageHook {
+        fn hook_name(&self) -> &str {
+            "RecordingAckMessageHook"
+        }
+
+        fn ack_persisted(&self, context: &AckMessageContext) {
+            self.0.lock().push(context.clone());
+        }
+    }
+
+    #[test]
+    fn ack_of_ipv6_broker_decodes_back_its_store_host() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        let store_host: SocketAddr = "[::1]:10911".parse().unwrap();
+        broker.processor.store_host = store_host;
+
+        assert_eq!(broker.ack(10).code(), ResponseCode::Success as i32);
+        let put = broker.message_store.put_messages().remove(0);
+        let mut bytes = message_decoder::encode(&put, false).unwrap();
+        let decoded =
+            message_decoder::decode(&mut bytes, true, false, false, false, false).unwrap();
+        assert_eq!(decoded.store_host, store_host);
+    }
+
+    struct RemarkingRequestHandler;
+
+    impl AckRequestHandler for RemarkingRequestHandler {
+        fn handle(
+            &self,
+            _channel: Channel,
+            _ctx: ConnectionHandlerContext,
+            request: RemotingCommand,
+        ) -> AckRequestFuture {
+            Box::pin(async move {
+                Ok(Some(
+                    RemotingCommand::create_response_command_with_code_remark(
+                        ResponseCode::Success,
+                        format!("handled {}", request.code()),
+                    ),
+                ))
+            })
+        }
+    }
+
+    #[test]
+    fn registered_request_code_is_dispatched_to_its_handler() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        let mut handlers = AckRequestHandlerTable::default();
+        handlers.register(
+            RequestCode::QueryAssignment,
+            Box::new(RemarkingRequestHandler),
+        );
+        broker.processor.ack_request_handlers = Arc::new(handlers);
+
+        let request = RemotingCommand::create_remoting_command(RequestCode::QueryAssignment);
+        let response = broker.process(RequestCode::QueryAssignment, request);
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        assert_eq!(
+            response.remark().unwrap().as_str(),
+            format!("handled {}", RequestCode::QueryAssignment as i32)
+        );
+
+        // a code nothing is registered for is still rejected
+        let request = RemotingCommand::create_remoting_command(RequestCode::PopMessage);
+        let response = broker.process(RequestCode::PopMessage, request);
+        assert_eq!(response.code(), ResponseCode::MessageIllegal as i32);
+        // the ack codes are handled by the processor itself
+        assert!(is_ack_request_code(RequestCode::BatchAckMessage));
+        assert_eq!(broker.ack(10).code(), ResponseCode::Success as i32);
+    }
+
+    #[test]
+    fn ack_message_hook_observes_every_persisted_ack_once() {
+        let broker_config = BrokerConfig {
+            revive_ack_msg_retry_times: 1,
+            ..BrokerConfig::default()
+        };
+        let mut broker = TestBroker::new(broker_config);
+        let hook = RecordingAckMessageHook::default();
+        broker.processor.ack_message_hooks = Arc::new(vec![Box::new(hook.clone())]);
+        let pop_time = get_current_millis() as i64;
+
+        assert_eq!(broker.ack(10).code(), ResponseCode::Success as i32);
+        assert_eq!(
+            broker.batch_ack(pop_time, &[12, 14]).code(),
+            ResponseCode::Success as i32
+        );
+        // the put and its retry fail
+        broker
+            .message_store
+            .push_put_statuses([PutMessageStatus::CreateMappedFileFailed; 2]);
+        assert_eq!(broker.ack(11).code(), ResponseCode::SystemError as i32);
+
+        let contexts = hook.0.lock().clone();
+        let acks: Vec<_> = contexts
+            .iter()
+            .map(|context| (context.ack_offset, context.ack_count))
+            .collect();
+        assert_eq!(acks, vec![(10, 1), (12, 2)]);
+        assert!(contexts.iter().all(|context| context.topic == TEST_TOPIC
+            && context.consumer_group == TEST_GROUP
+            && context.queue_id == 0));
+    }
+
+    #[test]
+    fn ack_message_hook_sees_where_the_ack_landed_in_its_revive_queue() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        let hook = RecordingAckMessageHook::default();
+        broker.processor.ack_message_hooks = Arc::new(vec![Box::new(hook.clone())]);
+
+        assert_eq!(broker.ack(10).code(), ResponseCode::Success as i32);
+        assert_eq!(broker.ack(11).code(), ResponseCode::Success as i32);
+
+        let revive_queue_offsets: Vec<_> = hook
+            .0
+            .lock()
+            .iter()
+            .map(|context| context.revive_queue_offset)
+            .collect();
+        assert_eq!(revive_queue_offsets, vec![Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn ack_honors_revive_queue_num_of_its_topic() {
+        const WIDE_TOPIC: &str = "ack_wide_revive_topic";
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        let mut topic_config = TopicConfig::with_queues(WIDE_TOPIC, 4, 4);
+        topic_config.attributes.insert(
+            CheetahString::from(TopicAttributes::REVIVE_QUEUE_NUM_ATTRIBUTE.get_name()),
+            CheetahString::from_static_str("16"),
+        );
+        broker
+            .processor
+            .topic_config_manager
+            .put_topic_config(topic_config);
+        broker.message_store.set_offset_range(WIDE_TOPIC, 0, 0, 100);
+        let broker_name = broker.broker_config.broker_identity.broker_name.clone();
+        let pop_time = get_current_millis() as i64;
+
+        let response = broker.ack_in_revive_queue(WIDE_TOPIC, &broker_name, pop_time, 12, 10);
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        let revive_topic = PopAckConstants::build_cluster_revive_topic(
+            broker
+                .broker_config
+                .broker_identity
+                .broker_cluster_name
+                .as_str(),
+        );
+        let stored = broker.message_store.put_messages_of(&revive_topic);
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].queue_id, 12);
+
+        let response = broker.ack_in_revive_queue(WIDE_TOPIC, &broker_name, pop_time, 16, 11);
+        assert_eq!(response.code(), ResponseCode::MessageIllegal as i32);
+
+        // the test topic keeps the 8 revive queues of the broker
+        let response = broker.ack_in_revive_queue(TEST_TOPIC, &broker_name, pop_time, 12, 10);
+        assert_eq!(response.code(), ResponseCode::MessageIllegal as i32);
+        assert!(response
+            .remark()
+            .unwrap()
+            .starts_with("reviveQueueId 12 out of range"));
+        assert_eq!(broker.message_store.put_messages_of(&revive_topic).len(), 1);
+    }
+
+    #[test]
+    fn out_of_range_acks_are_counted_per_group() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        let out_of_range_nums = |broker: &TestBroker| {
+            broker
+                .processor
+                .broker_stats_manager
+                .get_group_ack_out_of_range_nums(TEST_GROUP, TEST_TOPIC)
+        };
+
+        broker.ack(10);
+        assert_eq!(out_of_range_nums(&broker), 0);
+
+        // ahead of the queue, then below it once the queue got cleaned up to offset 20
+        broker.ack(150);
+        assert_eq!(out_of_range_nums(&broker), 1);
+        broker
+            .message_store
+            .set_offset_range(TEST_TOPIC, 0, 20, 100);
+        broker.ack(10);
+        assert_eq!(out_of_range_nums(&broker), 2);
+
+        broker.batch_ack(get_current_millis() as i64, &[11, 12, 25]);
+        assert_eq!(out_of_range_nums(&broker), 4);
+    }
+
+    fn test_queue_offset(broker: &TestBroker) -> i64 {
+        broker.processor.consumer_offset_manager.query_offset(
+            &CheetahString::from_static_str(TEST_GROUP),
+            &CheetahString::from_static_str(TEST_TOPIC),
+            0,
+        )
+    }
+
+    fn is_test_queue_blocked_for(broker: &TestBroker, attempt_id: &str) -> bool {
+        broker.processor.consumer_order_info_manager.check_block(
+            attempt_id,
+            &CheetahString::from_static_str(TEST_TOPIC),
+            &CheetahString::from_static_str(TEST_GROUP),
+            0,
+            30_000,
+        )
+    }
+
+    #[test]
+    fn orderly_pop_holds_queue_until_its_messages_are_acked() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        broker.store_messages(&["TagA", "TagA"]);
+
+        let response = broker.pop_orderly("attempt-1", 30_000);
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        assert_eq!(popped_messages(&response).len(), 2);
+        let response_header = pop_response_header(response);
+        assert_eq!(response_header.revive_qid, POP_ORDER_REVIVE_QUEUE as u32);
+        // no checkpoint is written and the offset waits for the acks
+        assert!(broker.message_store.put_messages().is_empty());
+        assert_eq!(test_queue_offset(&broker), -1);
+        assert_eq!(
+            broker.pop_orderly("attempt-2", 30_000).code(),
+            ResponseCode::PollingTimeout as i32
+        );
+
+        let pop_time = response_header.pop_time as i64;
+        let response = broker.ack_orderly_popped_at(pop_time, 0);
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        assert_eq!(test_queue_offset(&broker), 1);
+        assert!(is_test_queue_blocked_for(&broker, "attempt-2"));
+
+        let response = broker.ack_orderly_popped_at(pop_time, 1);
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        assert_eq!(test_queue_offset(&broker), 2);
+        assert!(!is_test_queue_blocked_for(&broker, "attempt-2"));
+    }
+
+    #[test]
+    fn orderly_pop_redelivers_unacked_messages_with_their_consumed_count() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        broker.store_messages(&["TagA", "TagA"]);
+        let response = broker.pop_orderly("attempt-1", 1);
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        std::thread::sleep(Duration::from_millis(5));
+
+        let response = broker.pop_orderly("attempt-2", 30_000);
+
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        let offsets: Vec<i64> = popped_messages(&response)
+            .iter()
+            .map(|msg_ext| msg_ext.queue_offset)
+            .collect();
+        assert_eq!(offsets, vec![0, 1]);
+        let response_header = pop_response_header(response);
+        let order_count_info = ExtraInfoUtil::parse_order_count_info(
+            response_header.order_count_info.unwrap().as_str(),
+        )
+        .unwrap();
+        assert_eq!(
+            order_count_info.get(&ExtraInfoUtil::get_queue_offset_map_key(TEST_TOPIC, 0, 1)),
+            Some(&1)
+        );
+        assert_eq!(
+            order_count_info.get(&ExtraInfoUtil::get_start_offset_info_map_key(TEST_TOPIC, 0)),
+            Some(&1)
+        );
+    }
+
+    #[test]
+    fn orderly_pop_of_queue_held_by_the_same_attempt_is_not_blocked() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        broker.store_messages(&["TagA"]);
+        assert_eq!(
+            broker.pop_orderly("attempt-1", 30_000).code(),
+            ResponseCode::Success as i32
+        );
+
+        let response = broker.pop_orderly("attempt-1", 30_000);
+
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        assert_eq!(popped_messages(&response).len(), 1);
+    }
+
+    #[test]
+    fn released_queue_is_popped_by_any_attempt() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        broker.store_messages(&["TagA"]);
+        broker.pop_orderly("attempt-1", 30_000);
+        assert!(is_test_queue_blocked_for(&broker, "attempt-2"));
+
+        broker.processor.consumer_order_info_manager.clear_block(
+            &CheetahString::from_static_str(TEST_TOPIC),
+            &CheetahString::from_static_str(TEST_GROUP),
+            0,
+        );
+
+        assert!(!is_test_queue_blocked_for(&broker, "attempt-2"));
+        assert_eq!(
+            broker.pop_orderly("attempt-2", 30_000).code(),
+            ResponseCode::Success as i32
+        );
+    }
+
+    #[test]
+    fn held_queues_survive_a_reload_of_the_order_info() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        broker.store_messages(&["TagA"]);
+        broker.pop_orderly("attempt-1", 30_000);
+        let consumer_order_info_manager = broker.processor.consumer_order_info_manager.clone();
+        let persisted = consumer_order_info_manager.encode_pretty(false);
+        consumer_order_info_manager.clear_block(
+            &CheetahString::from_static_str(TEST_TOPIC),
+            &CheetahString::from_static_str(TEST_GROUP),
+            0,
+        );
+
+        consumer_order_info_manager.decode(&persisted);
+
+        assert!(is_test_queue_blocked_for(&broker, "attempt-2"));
+        assert!(!is_test_queue_blocked_for(&broker, "attempt-1"));
     }
 }
@@ -0,0 +1,123 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt::Display;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use tracing::info;
+
+/// A branch of the Java `AckMessageProcessor` which is not implemented here yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ParityGap {
+    /// An orderly ack which does not wake the pops held on its queue, as `ackOrderly` does in
+    /// Java once the next offset of the queue is committed.
+    OrderlyAckNotify,
+    /// An ack which the pop buffer did not merge and which falls through to the revive topic.
+    BufferMergeFallthrough,
+}
+
+impl ParityGap {
+    const ALL: [ParityGap; 2] = [
+        ParityGap::OrderlyAckNotify,
+        ParityGap::BufferMergeFallthrough,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl Display for ParityGap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ParityGap::OrderlyAckNotify => "orderly_ack_notify",
+            ParityGap::BufferMergeFallthrough => "buffer_merge_fallthrough",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Counts how often ack processing goes through a branch which is still a stub compared to
+/// the Java broker, to tell which of them are worth implementing first.
+///
+/// When disabled, recording a hit is a single branch. When enabled, it is an atomic increment
+/// and a log line at every power of two hits, so a busy branch does not flood the log.
+pub(crate) struct AckParityTelemetry {
+    enabled: bool,
+    hits: [AtomicU64; ParityGap::ALL.len()],
+}
+
+impl AckParityTelemetry {
+    pub fn new(enabled: bool) -> Self {
+        AckParityTelemetry {
+            enabled,
+            hits: Default::default(),
+        }
+    }
+
+    pub fn record(&self, gap: ParityGap) {
+        if !self.enabled {
+            return;
+        }
+        let hits = self.hits[gap.index()].fetch_add(1, Ordering::Relaxed) + 1;
+        if hits.is_power_of_two() {
+            info!(
+                "ack processing hit java parity gap {}, total hits: {}",
+                gap, hits
+            );
+        }
+    }
+
+    pub fn hits(&self, gap: ParityGap) -> u64 {
+        self.hits[gap.index()].load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of hits of every gap, in declaration order.
+    pub fn snapshot(&self) -> Vec<(ParityGap, u64)> {
+        ParityGap::ALL
+            .iter()
+            .map(|gap| (*gap, self.hits(*gap)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_telemetry_records_nothing() {
+        let telemetry = AckParityTelemetry::new(false);
+        telemetry.record(ParityGap::BufferMergeFallthrough);
+        assert_eq!(telemetry.hits(ParityGap::BufferMergeFallthrough), 0);
+    }
+
+    #[test]
+    fn enabled_telemetry_counts_each_gap() {
+        let telemetry = AckParityTelemetry::new(true);
+        telemetry.record(ParityGap::BufferMergeFallthrough);
+        telemetry.record(ParityGap::BufferMergeFallthrough);
+        telemetry.record(ParityGap::OrderlyAckNotify);
+        assert_eq!(
+            telemetry.snapshot(),
+            vec![
+                (ParityGap::OrderlyAckNotify, 1),
+                (ParityGap::BufferMergeFallthrough, 2)
+            ]
+        );
+    }
+}
@@ -0,0 +1,244 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use rocketmq_common::TimeUtils::get_current_millis;
+use tokio::sync::oneshot;
+
+/// Limits the number of acks processed concurrently.
+///
+/// While a slot is free and nobody is waiting, acks are admitted in arrival order. Once the
+/// gate is saturated, a released slot is handed to the waiting ack with the highest priority:
+/// acks releasing more inflight messages go first, and every `aging_millis` spent waiting adds
+/// one to the priority so that small acks are not starved.
+pub(crate) struct AckPriorityGate {
+    max_concurrency: usize,
+    aging_millis: u64,
+    state: Mutex<GateState>,
+}
+
+struct GateState {
+    available: usize,
+    next_seq: u64,
+    waiters: Vec<Waiter>,
+}
+
+struct Waiter {
+    ack_count: usize,
+    enqueue_time: u64,
+    seq: u64,
+    sender: oneshot::Sender<AckPermit>,
+}
+
+/// Slot held while processing an ack, given back to the gate on drop.
+pub(crate) struct AckPermit {
+    gate: Option<Arc<AckPriorityGate>>,
+}
+
+impl AckPriorityGate {
+    /// `max_concurrency` of zero disables the gate.
+    pub fn new(max_concurrency: usize, aging_millis: u64) -> Self {
+        AckPriorityGate {
+            max_concurrency,
+            aging_millis,
+            state: Mutex::new(GateState {
+                available: max_concurrency,
+                next_seq: 0,
+                waiters: Vec::new(),
+            }),
+        }
+    }
+
+    pub async fn acquire(self: &Arc<Self>, ack_count: usize) -> AckPermit {
+        if self.max_concurrency == 0 {
+            return AckPermit { gate: None };
+        }
+        let receiver = {
+            let mut state = self.state.lock();
+            if state.available > 0 && state.waiters.is_empty() {
+                state.available -= 1;
+                return AckPermit {
+                    gate: Some(self.clone()),
+                };
+            }
+            let (sender, receiver) = oneshot::channel();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.waiters.push(Waiter {
+                ack_count,
+                enqueue_time: get_current_millis(),
+                seq,
+                sender,
+            });
+            receiver
+        };
+        // the sender is only dropped together with the gate
+        receiver.await.unwrap_or(AckPermit { gate: None })
+    }
+
+    pub fn waiting_num(&self) -> usize {
+        self.state.lock().waiters.len()
+    }
+
+    fn release(self: &Arc<Self>) {
+        loop {
+            let waiter = {
+                let mut state = self.state.lock();
+                match select_waiter(&state.waiters, get_current_millis(), self.aging_millis) {
+                    Some(index) => state.waiters.swap_remove(index),
+                    None => {
+                        state.available += 1;
+                        return;
+                    }
+                }
+            };
+            let permit = AckPermit {
+                gate: Some(self.clone()),
+            };
+            match waiter.sender.send(permit) {
+                Ok(()) => return,
+                // the waiting ack was cancelled, hand the slot to the next one
+                Err(mut permit) => {
+                    permit.gate.take();
+                }
+            }
+        }
+    }
+}
+
+impl Drop for AckPermit {
+    fn drop(&mut self) {
+        if let Some(gate) = self.gate.take() {
+            gate.release();
+        }
+    }
+}
+
+/// Returns the index of the waiter with the highest aged priority, the earliest one on ties.
+fn select_waiter(waiters: &[Waiter], now: u64, aging_millis: u64) -> Option<usize> {
+    waiters
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| {
+            priority(a, now, aging_millis)
+                .cmp(&priority(b, now, aging_millis))
+                .then_with(|| b.seq.cmp(&a.seq))
+        })
+        .map(|(index, _)| index)
+}
+
+fn priority(waiter: &Waiter, now: u64, aging_millis: u64) -> u64 {
+    let age_bonus = now
+        .saturating_sub(waiter.enqueue_time)
+        .checked_div(aging_millis)
+        .unwrap_or(0);
+    waiter.ack_count as u64 + age_bonus
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn waiter(ack_count: usize, enqueue_time: u64, seq: u64) -> Waiter {
+        let (sender, _) = oneshot::channel();
+        Waiter {
+            ack_count,
+            enqueue_time,
+            seq,
+            sender,
+        }
+    }
+
+    #[tokio::test]
+    async fn acquire_is_immediate_when_not_saturated() {
+        let gate = Arc::new(AckPriorityGate::new(2, 1000));
+        let _first = gate.acquire(1).await;
+        let _second = gate.acquire(1).await;
+        assert_eq!(gate.waiting_num(), 0);
+    }
+
+    #[tokio::test]
+    async fn disabled_gate_never_blocks() {
+        let gate = Arc::new(AckPriorityGate::new(0, 1000));
+        let _first = gate.acquire(1).await;
+        let _second = gate.acquire(1).await;
+        assert_eq!(gate.waiting_num(), 0);
+    }
+
+    #[tokio::test]
+    async fn saturated_gate_prefers_larger_acks() {
+        let gate = Arc::new(AckPriorityGate::new(1, 60_000));
+        let held = gate.acquire(1).await;
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut handles = Vec::new();
+        for ack_count in [1usize, 10, 5] {
+            let waiting_gate = gate.clone();
+            let order = order.clone();
+            handles.push(tokio::spawn(async move {
+                let _permit = waiting_gate.acquire(ack_count).await;
+                order.lock().push(ack_count);
+            }));
+            while gate.waiting_num() < handles.len() {
+                tokio::task::yield_now().await;
+            }
+        }
+        drop(held);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(*order.lock(), vec![10, 5, 1]);
+    }
+
+    #[tokio::test]
+    async fn cancelled_waiter_does_not_leak_slot() {
+        let gate = Arc::new(AckPriorityGate::new(1, 60_000));
+        let held = gate.acquire(1).await;
+        let waiting = {
+            let gate = gate.clone();
+            tokio::spawn(async move {
+                let _permit = gate.acquire(1).await;
+            })
+        };
+        while gate.waiting_num() < 1 {
+            tokio::task::yield_now().await;
+        }
+        waiting.abort();
+        let _ = waiting.await;
+        drop(held);
+        let _permit = gate.acquire(1).await;
+        assert_eq!(gate.waiting_num(), 0);
+    }
+
+    #[test]
+    fn select_waiter_breaks_ties_in_arrival_order() {
+        let waiters = vec![waiter(3, 0, 0), waiter(3, 0, 1)];
+        assert_eq!(select_waiter(&waiters, 0, 1000), Some(0));
+    }
+
+    #[test]
+    fn select_waiter_ages_small_acks() {
+        let waiters = vec![waiter(1, 0, 0), waiter(5, 9_000, 1)];
+        assert_eq!(select_waiter(&waiters, 9_000, 1000), Some(0));
+        assert_eq!(select_waiter(&waiters, 3_000, 1000), Some(1));
+    }
+
+    #[test]
+    fn select_waiter_returns_none_when_empty() {
+        assert_eq!(select_waiter(&[], 0, 1000), None);
+    }
+}
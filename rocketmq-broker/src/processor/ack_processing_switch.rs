@@ -0,0 +1,60 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+/// Broker wide switch to stop ack processing, e.g. to freeze offsets during an incident
+/// investigation, without stopping the broker.
+///
+/// Acks are checked against the switch as they arrive, so turning it on does not interrupt an
+/// ack already being appended.
+#[derive(Default)]
+pub(crate) struct AckProcessingSwitch {
+    disabled: AtomicBool,
+}
+
+impl AckProcessingSwitch {
+    pub fn new(disabled: bool) -> Self {
+        AckProcessingSwitch {
+            disabled: AtomicBool::new(disabled),
+        }
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled.load(Ordering::Acquire)
+    }
+
+    /// Turns ack processing off or back on, returning whether it was off before.
+    pub fn set_disabled(&self, disabled: bool) -> bool {
+        self.disabled.swap(disabled, Ordering::AcqRel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_disabled_returns_previous_state() {
+        let switch = AckProcessingSwitch::default();
+        assert!(!switch.is_disabled());
+        assert!(!switch.set_disabled(true));
+        assert!(switch.is_disabled());
+        assert!(switch.set_disabled(false));
+        assert!(!switch.is_disabled());
+    }
+}
@@ -0,0 +1,92 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::time::Duration;
+
+use tokio::sync::Semaphore;
+use tokio::sync::SemaphorePermit;
+
+/// Bounds the number of ack puts to the revive topic in flight at once, so that a flood of acks
+/// waits for the store instead of piling puts up on it.
+pub(crate) struct AckPutLimiter {
+    max_concurrent_puts: usize,
+    permits: Semaphore,
+    acquire_timeout: Duration,
+}
+
+/// Slot held while an ack is put to the revive topic, given back to the limiter on drop.
+pub(crate) struct AckPutPermit<'a> {
+    _permit: Option<SemaphorePermit<'a>>,
+}
+
+impl AckPutLimiter {
+    /// `max_concurrent_puts` of zero disables the limit.
+    pub fn new(max_concurrent_puts: usize, acquire_timeout: Duration) -> Self {
+        AckPutLimiter {
+            max_concurrent_puts,
+            permits: Semaphore::new(max_concurrent_puts),
+            acquire_timeout,
+        }
+    }
+
+    /// Waits for a free slot, returns `None` if none was freed within the acquire timeout.
+    pub async fn acquire(&self) -> Option<AckPutPermit<'_>> {
+        if self.max_concurrent_puts == 0 {
+            return Some(AckPutPermit { _permit: None });
+        }
+        match tokio::time::timeout(self.acquire_timeout, self.permits.acquire()).await {
+            // the semaphore is never closed
+            Ok(permit) => Some(AckPutPermit {
+                _permit: permit.ok(),
+            }),
+            Err(_) => None,
+        }
+    }
+
+    pub fn max_concurrent_puts(&self) -> usize {
+        self.max_concurrent_puts
+    }
+
+    /// Number of puts in flight.
+    pub fn inflight_num(&self) -> usize {
+        self.max_concurrent_puts - self.permits.available_permits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn acquire_times_out_while_every_slot_is_taken() {
+        let limiter = AckPutLimiter::new(2, Duration::from_millis(20));
+        let first = limiter.acquire().await.unwrap();
+        let _second = limiter.acquire().await.unwrap();
+        assert_eq!(limiter.inflight_num(), 2);
+        assert!(limiter.acquire().await.is_none());
+
+        drop(first);
+        assert!(limiter.acquire().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn disabled_limiter_never_blocks() {
+        let limiter = AckPutLimiter::new(0, Duration::from_millis(20));
+        let _first = limiter.acquire().await.unwrap();
+        let _second = limiter.acquire().await.unwrap();
+        assert_eq!(limiter.inflight_num(), 0);
+    }
+}
@@ -0,0 +1,61 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use futures::future::BoxFuture;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
+
+/// The response an [`AckRequestHandler`] answers a request with.
+pub type AckRequestFuture = BoxFuture<'static, crate::Result<Option<RemotingCommand>>>;
+
+/// Handles a request code dispatched by the ack processor beside the ack codes it handles
+/// itself, e.g. a pop admin code querying the state of acks.
+///
+/// # Requirements
+///
+/// Implementors must be thread-safe (`Sync + Send`) and support static lifetimes (`'static`).
+pub trait AckRequestHandler: Sync + Send + 'static {
+    /// Handles `request`, once the ack processor has checked it is accepting requests at all.
+    fn handle(
+        &self,
+        channel: Channel,
+        ctx: ConnectionHandlerContext,
+        request: RemotingCommand,
+    ) -> AckRequestFuture;
+}
+
+/// The [`AckRequestHandler`]s of the ack processor, by request code.
+#[derive(Default)]
+pub(crate) struct AckRequestHandlerTable {
+    handlers: HashMap<i32, Box<dyn AckRequestHandler>>,
+}
+
+impl AckRequestHandlerTable {
+    /// Registers `handler` for `request_code`, replacing the one registered before.
+    pub fn register(&mut self, request_code: RequestCode, handler: Box<dyn AckRequestHandler>) {
+        self.handlers.insert(request_code.into(), handler);
+    }
+
+    pub fn get(&self, request_code: RequestCode) -> Option<&dyn AckRequestHandler> {
+        self.handlers
+            .get(&i32::from(request_code))
+            .map(|handler| handler.as_ref())
+    }
+}
@@ -0,0 +1,160 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt::Display;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use tracing::info;
+
+/// Upper bounds of the latency buckets, in milliseconds, the last bucket holds everything
+/// slower.
+const BUCKET_BOUNDS_MILLIS: [u64; 11] = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 2_000];
+
+/// Samples recorded before the first latency log line.
+const MIN_LOGGED_SAMPLES: u64 = 1_024;
+
+/// The kind of ack written to the revive topic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum AckKind {
+    Single,
+    Batch,
+}
+
+impl AckKind {
+    const ALL: [AckKind; 2] = [AckKind::Single, AckKind::Batch];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl Display for AckKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            AckKind::Single => "single",
+            AckKind::Batch => "batch",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Histogram of the time taken to write an ack to the revive topic, by ack kind, to tell
+/// whether redeliveries come from slow revive writes.
+///
+/// Recording a sample is an atomic increment. The p99 of a kind is logged at every power of two
+/// samples once enough of them are recorded, so the log is not flooded.
+pub(crate) struct AckStoreLatency {
+    buckets: [[AtomicU64; BUCKET_BOUNDS_MILLIS.len() + 1]; AckKind::ALL.len()],
+}
+
+impl AckStoreLatency {
+    pub fn new() -> Self {
+        AckStoreLatency {
+            buckets: Default::default(),
+        }
+    }
+
+    pub fn record(&self, kind: AckKind, latency: Duration) {
+        let millis = latency.as_millis();
+        let bucket = BUCKET_BOUNDS_MILLIS
+            .iter()
+            .position(|bound| millis <= *bound as u128)
+            .unwrap_or(BUCKET_BOUNDS_MILLIS.len());
+        self.buckets[kind.index()][bucket].fetch_add(1, Ordering::Relaxed);
+        let count = self.count(kind);
+        if count >= MIN_LOGGED_SAMPLES && count.is_power_of_two() {
+            info!(
+                "{} ack store latency p99: {:?}, samples: {}",
+                kind,
+                self.percentile(kind, 0.99),
+                count
+            );
+        }
+    }
+
+    /// Number of samples recorded for `kind`.
+    pub fn count(&self, kind: AckKind) -> u64 {
+        self.buckets[kind.index()]
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Returns the upper bound of the bucket holding the `percentile` (in `0.0..=1.0`) latency of
+    /// `kind`, `Duration::MAX` if it is slower than the last bucket and `None` without samples.
+    pub fn percentile(&self, kind: AckKind, percentile: f64) -> Option<Duration> {
+        let counts = self.buckets[kind.index()]
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .collect::<Vec<_>>();
+        let total = counts.iter().sum::<u64>();
+        if total == 0 {
+            return None;
+        }
+        let rank = ((total as f64 * percentile).ceil() as u64).clamp(1, total);
+        let mut seen = 0;
+        for (bucket, count) in counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(
+                    BUCKET_BOUNDS_MILLIS
+                        .get(bucket)
+                        .map_or(Duration::MAX, |bound| Duration::from_millis(*bound)),
+                );
+            }
+        }
+        Some(Duration::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_histogram_has_no_percentile() {
+        let latency = AckStoreLatency::new();
+        assert_eq!(latency.count(AckKind::Single), 0);
+        assert_eq!(latency.percentile(AckKind::Single, 0.99), None);
+    }
+
+    #[test]
+    fn percentile_is_tracked_per_ack_kind() {
+        let latency = AckStoreLatency::new();
+        for _ in 0..99 {
+            latency.record(AckKind::Single, Duration::from_micros(800));
+        }
+        latency.record(AckKind::Single, Duration::from_millis(150));
+        latency.record(AckKind::Batch, Duration::from_secs(5));
+
+        assert_eq!(latency.count(AckKind::Single), 100);
+        assert_eq!(
+            latency.percentile(AckKind::Single, 0.99),
+            Some(Duration::from_millis(1))
+        );
+        assert_eq!(
+            latency.percentile(AckKind::Single, 1.0),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(latency.count(AckKind::Batch), 1);
+        assert_eq!(
+            latency.percentile(AckKind::Batch, 0.99),
+            Some(Duration::MAX)
+        );
+    }
+}
@@ -35,6 +35,8 @@ use crate::client::manager::consumer_manager::ConsumerManager;
 use crate::client::rebalance::rebalance_lock_manager::RebalanceLockManager;
 use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
 use crate::out_api::broker_outer_api::BrokerOuterAPI;
+use crate::processor::ack_invisible_time_cap_table::AckInvisibleTimeCapTable;
+use crate::processor::ack_processing_switch::AckProcessingSwitch;
 use crate::processor::admin_broker_processor::batch_mq_handler::BatchMqHandler;
 use crate::processor::admin_broker_processor::broker_config_request_handler::BrokerConfigRequestHandler;
 use crate::processor::admin_broker_processor::consumer_request_handler::ConsumerRequestHandler;
@@ -76,6 +78,8 @@ impl AdminBrokerProcessor {
         rebalance_lock_manager: Arc<RebalanceLockManager>,
         broker_member_group: Arc<BrokerMemberGroup>,
         pop_inflight_message_counter: Arc<PopInflightMessageCounter>,
+        ack_invisible_time_cap_table: Arc<AckInvisibleTimeCapTable>,
+        ack_processing_switch: Arc<AckProcessingSwitch>,
     ) -> Self {
         let inner = Inner {
             broker_config,
@@ -86,6 +90,8 @@ impl AdminBrokerProcessor {
             topic_queue_mapping_manager,
             default_message_store,
             pop_inflight_message_counter,
+            ack_invisible_time_cap_table,
+            ack_processing_switch,
             schedule_message_service,
             broker_stats,
             consume_manager,
@@ -143,6 +149,11 @@ impl AdminBrokerProcessor {
                     .update_broker_config(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::UpdateAckProcessingSwitch => {
+                self.broker_config_request_handler
+                    .update_ack_processing_switch(channel, ctx, request_code, request)
+                    .await
+            }
             RequestCode::GetBrokerConfig => {
                 self.broker_config_request_handler
                     .get_broker_config(channel, ctx, request_code, request)
@@ -168,6 +179,31 @@ impl AdminBrokerProcessor {
                     .get_consume_stats(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::UpdateAckInvisibleTimeCap => {
+                self.consumer_request_handler
+                    .update_ack_invisible_time_cap(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::QueryPopInflightMessageNum => {
+                self.consumer_request_handler
+                    .query_pop_inflight_message_num(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::QueryReviveQueueAcks => {
+                self.consumer_request_handler
+                    .query_revive_queue_acks(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::QueryReviveLag => {
+                self.consumer_request_handler
+                    .query_revive_lag(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::QueryConsumerLag => {
+                self.consumer_request_handler
+                    .query_consumer_lag(channel, ctx, request_code, request)
+                    .await
+            }
             RequestCode::GetAllConsumerOffset => {
                 self.consumer_request_handler
                     .get_all_consumer_offset(channel, ctx, request_code, request)
@@ -183,6 +219,11 @@ impl AdminBrokerProcessor {
                     .get_broker_runtime_info(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::ViewBrokerStatsData => {
+                self.broker_config_request_handler
+                    .view_broker_stats_data(channel, ctx, request_code, request)
+                    .await
+            }
             RequestCode::QueryTopicConsumeByWho => {
                 self.topic_request_handler
                     .query_topic_consume_by_who(channel, ctx, request_code, request)
@@ -203,6 +244,16 @@ impl AdminBrokerProcessor {
                     .get_min_offset(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::ResetGroupOffsetAllQueues => {
+                self.offset_request_handler
+                    .reset_group_offset_all_queues(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::TrimTopicBeforeOffset => {
+                self.offset_request_handler
+                    .trim_topic_before_offset(channel, ctx, request_code, request)
+                    .await
+            }
 
             RequestCode::LockBatchMq => {
                 self.batch_mq_handler
@@ -242,6 +293,8 @@ struct Inner {
     topic_queue_mapping_manager: Arc<TopicQueueMappingManager>,
     default_message_store: ArcMut<DefaultMessageStore>,
     pop_inflight_message_counter: Arc<PopInflightMessageCounter>,
+    ack_invisible_time_cap_table: Arc<AckInvisibleTimeCapTable>,
+    ack_processing_switch: Arc<AckProcessingSwitch>,
     schedule_message_service: ScheduleMessageService,
     broker_stats: Option<Arc<BrokerStats<DefaultMessageStore>>>,
     consume_manager: Arc<ConsumerManager>,
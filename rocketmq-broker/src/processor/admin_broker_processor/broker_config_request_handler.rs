@@ -20,13 +20,22 @@ use std::collections::HashMap;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::mix_all;
 use rocketmq_common::common::mq_version::RocketMqVersion;
+use rocketmq_common::common::stats::stats_item::StatsItem;
+use rocketmq_common::common::stats::stats_snapshot::StatsSnapshot;
 use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::admin::response_body_format::ResponseBodyFormat;
+use rocketmq_remoting::protocol::body::broker_item::BrokerStatsItem;
 use rocketmq_remoting::protocol::body::kv_table::KVTable;
+use rocketmq_remoting::protocol::header::update_ack_processing_switch_request_header::UpdateAckProcessingSwitchRequestHeader;
+use rocketmq_remoting::protocol::header::view_broker_stats_data_request_header::ViewBrokerStatsDataRequestHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::subscription::broker_stats_data::BrokerStatsData;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_store::log_file::MessageStore;
 use sysinfo::Disks;
+use tracing::warn;
 
 use crate::processor::admin_broker_processor::Inner;
 
@@ -51,6 +60,39 @@ impl BrokerConfigRequestHandler {
         todo!()
     }
 
+    pub async fn update_ack_processing_switch(
+        &mut self,
+        channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let response = RemotingCommand::create_response_command();
+        let request_header = match request
+            .decode_command_custom_header::<UpdateAckProcessingSwitchRequestHeader>()
+        {
+            Ok(header) => header,
+            Err(e) => {
+                return Some(
+                    response
+                        .set_code(ResponseCode::SystemError)
+                        .set_remark(format!("decode request header failed, {}", e)),
+                );
+            }
+        };
+        let was_disabled = self
+            .inner
+            .ack_processing_switch
+            .set_disabled(request_header.ack_processing_disabled);
+        warn!(
+            "update ack processing switch, disabled: {} -> {}, caller={}",
+            was_disabled,
+            request_header.ack_processing_disabled,
+            channel.remote_address()
+        );
+        Some(response)
+    }
+
     pub async fn get_broker_config(
         &mut self,
         _channel: Channel,
@@ -83,19 +125,68 @@ impl BrokerConfigRequestHandler {
         Some(response)
     }
 
+    /// Answers the minute, hour and day snapshots of one broker stats item, such as the ack
+    /// counters, in the body format requested by the caller.
+    pub async fn view_broker_stats_data(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let mut response = RemotingCommand::create_response_command();
+        let request_header =
+            match request.decode_command_custom_header::<ViewBrokerStatsDataRequestHeader>() {
+                Ok(header) => header,
+                Err(e) => {
+                    return Some(
+                        response
+                            .set_code(ResponseCode::SystemError)
+                            .set_remark(format!("decode request header failed, {}", e)),
+                    );
+                }
+            };
+        let stats_item = match self
+            .inner
+            .broker_stats_manager
+            .get_stats_item(&request_header.stats_name, &request_header.stats_key)
+        {
+            Some(stats_item) => stats_item,
+            None => {
+                return Some(
+                    response
+                        .set_code(ResponseCode::SystemError)
+                        .set_remark(format!(
+                            "The stats <{}> <{}> not exist",
+                            request_header.stats_name, request_header.stats_key
+                        )),
+                );
+            }
+        };
+        let body = ResponseBodyFormat::from_request(&request)
+            .encode(&broker_stats_data_of(&stats_item))
+            .expect("broker stats data encode failed");
+        response.set_body_mut_ref(body);
+        Some(response)
+    }
+
     pub async fn get_broker_runtime_info(
         &mut self,
         _channel: Channel,
         _ctx: ConnectionHandlerContext,
         _request_code: RequestCode,
-        _request: RemotingCommand,
+        request: RemotingCommand,
     ) -> Option<RemotingCommand> {
         let mut response = RemotingCommand::create_response_command();
         let runtime_info = self.prepare_runtime_info();
         let key_value_table = KVTable {
             table: runtime_info,
         };
-        response.set_body_mut_ref(serde_json::to_string(&key_value_table).unwrap());
+        response.set_body_mut_ref(
+            ResponseBodyFormat::from_request(&request)
+                .encode(&key_value_table)
+                .unwrap(),
+        );
         Some(response)
     }
 
@@ -292,3 +383,47 @@ impl BrokerConfigRequestHandler {
         true
     }
 }
+
+fn broker_stats_data_of(stats_item: &StatsItem) -> BrokerStatsData {
+    let item_of = |snapshot: StatsSnapshot| {
+        BrokerStatsItem::new(snapshot.get_sum(), snapshot.get_tps(), snapshot.get_avgpt())
+    };
+    BrokerStatsData::new(
+        item_of(stats_item.get_stats_data_in_minute()),
+        item_of(stats_item.get_stats_data_in_hour()),
+        item_of(stats_item.get_stats_data_in_day()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_remoting::protocol::RemotingDeserializable;
+    use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
+
+    use super::*;
+
+    #[test]
+    fn broker_ack_stats_round_trip_in_both_formats() {
+        let stats_item = StatsItem::new(BrokerStatsManager::BROKER_ACK_NUMS, "DefaultCluster");
+        stats_item.add_value(8, 2);
+        let stats_data = broker_stats_data_of(&stats_item);
+
+        for format in [ResponseBodyFormat::Compact, ResponseBodyFormat::Json] {
+            let body = format.encode(&stats_data).unwrap();
+            assert_eq!(body.contains(&b'\n'), format == ResponseBodyFormat::Json);
+            let decoded = BrokerStatsData::decode(&body).unwrap();
+            assert_eq!(
+                decoded.get_stats_minute().get_sum(),
+                stats_data.get_stats_minute().get_sum()
+            );
+            assert_eq!(
+                decoded.get_stats_hour().get_tps(),
+                stats_data.get_stats_hour().get_tps()
+            );
+            assert_eq!(
+                decoded.get_stats_day().get_avgpt(),
+                stats_data.get_stats_day().get_avgpt()
+            );
+        }
+    }
+}
@@ -15,26 +15,51 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
 use std::collections::HashSet;
 
+use cheetah_string::CheetahString;
 use rocketmq_common::common::config_manager::ConfigManager;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::pop_ack_constants::PopAckConstants;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::admin::consume_stats::ConsumeStats;
 use rocketmq_remoting::protocol::admin::offset_wrapper::OffsetWrapper;
+use rocketmq_remoting::protocol::admin::response_body_format::ResponseBodyFormat;
 use rocketmq_remoting::protocol::body::connection::Connection;
 use rocketmq_remoting::protocol::body::consumer_connection::ConsumerConnection;
+use rocketmq_remoting::protocol::body::consumer_lag_body::ConsumerLagBody;
+use rocketmq_remoting::protocol::body::consumer_lag_body::GroupConsumeLag;
+use rocketmq_remoting::protocol::body::consumer_lag_body::QueueConsumeLag;
+use rocketmq_remoting::protocol::body::pop_inflight_message_num_body::PopInflightMessageNumBody;
+use rocketmq_remoting::protocol::body::revive_lag_body::ReviveLagBody;
+use rocketmq_remoting::protocol::body::revive_lag_body::ReviveQueueLag;
+use rocketmq_remoting::protocol::body::revive_queue_acks_body::ReviveQueueAck;
+use rocketmq_remoting::protocol::body::revive_queue_acks_body::ReviveQueueAcksBody;
 use rocketmq_remoting::protocol::header::get_consume_stats_request_header::GetConsumeStatsRequestHeader;
 use rocketmq_remoting::protocol::header::get_consumer_connection_list_request_header::GetConsumerConnectionListRequestHeader;
+use rocketmq_remoting::protocol::header::query_pop_inflight_message_num_request_header::QueryPopInflightMessageNumRequestHeader;
+use rocketmq_remoting::protocol::header::query_revive_queue_acks_request_header::QueryReviveQueueAcksRequestHeader;
+use rocketmq_remoting::protocol::header::update_ack_invisible_time_cap_request_header::UpdateAckInvisibleTimeCapRequestHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_store::log_file::MessageStore;
+use rocketmq_store::pop::batch_ack_msg::BatchAckMsg;
+use rocketmq_store::pop::decode_ack_body;
+use rocketmq_store::pop::is_revive_body_intact;
+use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
+use tracing::info;
 use tracing::warn;
 
+use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
 use crate::processor::admin_broker_processor::Inner;
+use crate::processor::pop_revive_queue_selector::revive_queue_num_of;
 
 #[derive(Clone)]
 pub(super) struct ConsumerRequestHandler {
@@ -215,11 +240,223 @@ impl ConsumerRequestHandler {
             let new_consume_tps = consume_stats.get_consume_tps() + consume_tps;
             consume_stats.set_consume_tps(new_consume_tps);
         }
-        let body = consume_stats.encode().expect("consume stats encode failed");
+        let body = ResponseBodyFormat::from_request(&request)
+            .encode(&consume_stats)
+            .expect("consume stats encode failed");
         response.set_body_mut_ref(body);
         Some(response)
     }
 
+    pub async fn query_pop_inflight_message_num(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let mut response = RemotingCommand::create_response_command();
+        let request_header = match request
+            .decode_command_custom_header::<QueryPopInflightMessageNumRequestHeader>()
+        {
+            Ok(header) => header,
+            Err(e) => {
+                return Some(
+                    response
+                        .set_code(ResponseCode::SystemError)
+                        .set_remark(format!("decode request header failed, {}", e)),
+                );
+            }
+        };
+        let counter = &self.inner.pop_inflight_message_counter;
+        let queue_in_flight_num_table = match request_header.queue_id {
+            Some(queue_id) => {
+                let num = counter.get_in_flight_message_num(
+                    &request_header.topic,
+                    &request_header.consumer_group,
+                    queue_id,
+                );
+                HashMap::from([(queue_id, num)])
+            }
+            None => counter.get_queue_in_flight_message_num(
+                &request_header.topic,
+                &request_header.consumer_group,
+            ),
+        };
+        let body = PopInflightMessageNumBody::new(
+            request_header.topic,
+            request_header.consumer_group,
+            queue_in_flight_num_table,
+        );
+        let body = ResponseBodyFormat::from_request(&request)
+            .encode(&body)
+            .expect("pop inflight message num encode failed");
+        response.set_body_mut_ref(body);
+        Some(response)
+    }
+
+    /// Reads the next messages of a revive queue and answers the acks of the requested group and
+    /// topic among them. The revive queue is only read, its consume offset is left untouched.
+    pub async fn query_revive_queue_acks(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let response = RemotingCommand::create_response_command();
+        let request_header =
+            match request.decode_command_custom_header::<QueryReviveQueueAcksRequestHeader>() {
+                Ok(header) => header,
+                Err(e) => {
+                    return Some(
+                        response
+                            .set_code(ResponseCode::SystemError)
+                            .set_remark(format!("decode request header failed, {}", e)),
+                    );
+                }
+            };
+        let broker_config = &self.inner.broker_config;
+        let revive_queue_id = request_header.revive_queue_id;
+        let revive_queue_num = revive_queue_num_of(
+            self.inner
+                .topic_config_manager
+                .select_topic_config(&request_header.topic)
+                .as_ref(),
+            broker_config.revive_queue_num,
+        );
+        if revive_queue_id < 0 || revive_queue_id as u32 >= revive_queue_num {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(format!(
+                        "revive queue id {} out of range [0, {})",
+                        revive_queue_id, revive_queue_num
+                    )),
+            );
+        }
+        let max_num = request_header
+            .max_num
+            .unwrap_or(broker_config.revive_queue_inspect_max_num)
+            .clamp(1, broker_config.revive_queue_inspect_max_num.max(1));
+        let revive_topic = CheetahString::from_string(PopAckConstants::build_cluster_revive_topic(
+            broker_config.broker_identity.broker_cluster_name.as_str(),
+        ));
+        let message_store = &self.inner.default_message_store;
+        let min_offset = message_store.get_min_offset_in_queue(&revive_topic, revive_queue_id);
+        let offset = request_header.offset.unwrap_or(min_offset).max(min_offset);
+        let mut body = ReviveQueueAcksBody {
+            revive_topic: revive_topic.clone(),
+            revive_queue_id,
+            next_offset: offset,
+            acks: Vec::new(),
+        };
+        if let Some(get_message_result) = message_store
+            .get_message(
+                &CheetahString::from_static_str(PopAckConstants::REVIVE_GROUP),
+                &revive_topic,
+                revive_queue_id,
+                offset,
+                max_num,
+                i32::MAX,
+                None,
+            )
+            .await
+        {
+            body.next_offset = get_message_result.next_begin_offset();
+            body.acks = get_message_result
+                .message_mapped_list()
+                .iter()
+                .filter_map(|mapped| {
+                    message_decoder::decode(
+                        &mut mapped.get_bytes()?,
+                        true,
+                        false,
+                        false,
+                        false,
+                        false,
+                    )
+                })
+                .filter(|msg_ext| is_intact_revive_msg(msg_ext, &self.inner.broker_stats_manager))
+                .filter_map(|msg_ext| {
+                    revive_queue_ack_of(
+                        &msg_ext,
+                        &request_header.topic,
+                        &request_header.consumer_group,
+                    )
+                })
+                .collect();
+        }
+        let body = ResponseBodyFormat::from_request(&request)
+            .encode(&body)
+            .expect("revive queue acks encode failed");
+        Some(response.set_body(body))
+    }
+
+    /// Reports how many checkpoints and acks every revive queue holds past the offset the
+    /// revive service consumes it from. Topics overriding the revive queue count may use more
+    /// queues than the broker default, every one of them is reported.
+    pub async fn query_revive_lag(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let response = RemotingCommand::create_response_command();
+        let broker_config = &self.inner.broker_config;
+        let revive_queue_num = self
+            .inner
+            .topic_config_manager
+            .topic_config_table()
+            .lock()
+            .values()
+            .map(|topic_config| {
+                revive_queue_num_of(Some(topic_config), broker_config.revive_queue_num)
+            })
+            .fold(broker_config.revive_queue_num, u32::max);
+        let revive_topic = CheetahString::from_string(PopAckConstants::build_cluster_revive_topic(
+            broker_config.broker_identity.broker_cluster_name.as_str(),
+        ));
+        let body = ReviveLagBody {
+            queues: revive_queue_lags(
+                self.inner.default_message_store.as_ref(),
+                &self.inner.consumer_offset_manager,
+                &revive_topic,
+                revive_queue_num,
+            ),
+            revive_topic,
+        };
+        let body = ResponseBodyFormat::from_request(&request)
+            .encode(&body)
+            .expect("revive lag encode failed");
+        Some(response.set_body(body))
+    }
+
+    /// Reports the consume lag of every group with committed offsets, flagging the groups
+    /// lagging more than `slow_consumer_lag_threshold` messages so monitoring can alert on them.
+    pub async fn query_consumer_lag(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let response = RemotingCommand::create_response_command();
+        let lag_threshold = self.inner.broker_config.slow_consumer_lag_threshold;
+        let body = ConsumerLagBody {
+            lag_threshold,
+            groups: group_consume_lags(
+                self.inner.default_message_store.as_ref(),
+                &self.inner.consumer_offset_manager,
+                lag_threshold,
+            ),
+        };
+        let body = ResponseBodyFormat::from_request(&request)
+            .encode(&body)
+            .expect("consumer lag encode failed");
+        Some(response.set_body(body))
+    }
+
     pub async fn get_all_consumer_offset(
         &mut self,
         _channel: Channel,
@@ -240,4 +477,367 @@ impl ConsumerRequestHandler {
             )
         }
     }
+
+    pub async fn update_ack_invisible_time_cap(
+        &mut self,
+        channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let response = RemotingCommand::create_response_command();
+        let request_header = match request
+            .decode_command_custom_header::<UpdateAckInvisibleTimeCapRequestHeader>()
+        {
+            Ok(header) => header,
+            Err(e) => {
+                return Some(
+                    response
+                        .set_code(ResponseCode::SystemError)
+                        .set_remark(format!("decode request header failed, {}", e)),
+                );
+            }
+        };
+        info!(
+            "update ack invisible time cap, group={}, cap={}ms, caller={}",
+            request_header.consumer_group,
+            request_header.invisible_time_cap_millis,
+            channel.remote_address()
+        );
+        self.inner.ack_invisible_time_cap_table.update(
+            &request_header.consumer_group,
+            request_header.invisible_time_cap_millis,
+        );
+        Some(response)
+    }
+}
+
+/// Returns the lag of the revive group on the first `revive_queue_num` queues of
+/// `revive_topic`. A queue the revive group never consumed is consumed from its min offset.
+fn revive_queue_lags<MS: MessageStore>(
+    message_store: &MS,
+    consumer_offset_manager: &ConsumerOffsetManager,
+    revive_topic: &CheetahString,
+    revive_queue_num: u32,
+) -> Vec<ReviveQueueLag> {
+    let revive_group = CheetahString::from_static_str(PopAckConstants::REVIVE_GROUP);
+    (0..revive_queue_num as i32)
+        .map(|revive_queue_id| {
+            let max_offset = message_store
+                .get_max_offset_in_queue(revive_topic, revive_queue_id)
+                .max(0);
+            let mut consumed_offset =
+                consumer_offset_manager.query_offset(&revive_group, revive_topic, revive_queue_id);
+            if consumed_offset < 0 {
+                consumed_offset = message_store
+                    .get_min_offset_in_queue(revive_topic, revive_queue_id)
+                    .max(0);
+            }
+            ReviveQueueLag {
+                revive_queue_id,
+                max_offset,
+                consumed_offset,
+                lag: (max_offset - consumed_offset).max(0),
+            }
+        })
+        .collect()
+}
+
+/// Checks the body of the revive queue message `msg_ext` against its CRC. A corrupt message is
+/// logged and counted, to be skipped rather than failing its decoding.
+fn is_intact_revive_msg(msg_ext: &MessageExt, broker_stats_manager: &BrokerStatsManager) -> bool {
+    if is_revive_body_intact(msg_ext) {
+        return true;
+    }
+    warn!(
+        "skip corrupt revive message, topic={}, queueId={}, queueOffset={}, tag={:?}",
+        msg_ext.get_topic(),
+        msg_ext.queue_id,
+        msg_ext.queue_offset,
+        msg_ext.get_tags()
+    );
+    broker_stats_manager.inc_broker_revive_corrupt_nums();
+    false
+}
+
+/// Returns the lag of every group on the queues it committed offsets on, the max offset of a
+/// queue minus the offset committed on it, ordered by group then by topic and queue id.
+fn group_consume_lags<MS: MessageStore>(
+    message_store: &MS,
+    consumer_offset_manager: &ConsumerOffsetManager,
+    lag_threshold: i64,
+) -> Vec<GroupConsumeLag> {
+    let mut groups: Vec<GroupConsumeLag> = consumer_offset_manager
+        .get_group_topic_map()
+        .into_iter()
+        .map(|(group, topics)| {
+            let mut queues: Vec<QueueConsumeLag> = topics
+                .into_iter()
+                .flat_map(|topic| {
+                    consumer_offset_manager
+                        .query_offsets(&group, &topic)
+                        .into_iter()
+                        .map(move |(queue_id, consumer_offset)| {
+                            (topic.clone(), queue_id, consumer_offset)
+                        })
+                })
+                .map(|(topic, queue_id, consumer_offset)| {
+                    let max_offset = message_store
+                        .get_max_offset_in_queue(&topic, queue_id)
+                        .max(0);
+                    QueueConsumeLag {
+                        lag: (max_offset - consumer_offset).max(0),
+                        topic,
+                        queue_id,
+                        max_offset,
+                        consumer_offset,
+                    }
+                })
+                .collect();
+            queues.sort_by(|a, b| (&a.topic, a.queue_id).cmp(&(&b.topic, b.queue_id)));
+            let lag = queues.iter().map(|queue| queue.lag).sum();
+            GroupConsumeLag {
+                group,
+                lag,
+                slow: lag > lag_threshold,
+                queues,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| a.group.cmp(&b.group));
+    groups
+}
+
+/// Describes the ack carried by the revive queue message `msg_ext` if it is one of `group` on
+/// `topic`.
+fn revive_queue_ack_of(
+    msg_ext: &MessageExt,
+    topic: &CheetahString,
+    group: &CheetahString,
+) -> Option<ReviveQueueAck> {
+    let tag = msg_ext.get_tags()?;
+    let ack_msg = decode_ack_body(tag.as_str(), msg_ext.get_body()?)?;
+    if ack_msg.topic() != topic || ack_msg.consumer_group() != group {
+        return None;
+    }
+    let ack_offsets = match ack_msg.as_any().downcast_ref::<BatchAckMsg>() {
+        Some(batch_ack_msg) => batch_ack_msg.ack_offset_list.clone(),
+        None => vec![ack_msg.ack_offset()],
+    };
+    // the ack is delivered to the revive queue reader once the messages turn visible again
+    let revive_time = msg_ext.get_delay_time_ms() as i64;
+    Some(ReviveQueueAck {
+        revive_offset: msg_ext.queue_offset,
+        tag,
+        topic: ack_msg.topic().clone(),
+        consumer_group: ack_msg.consumer_group().clone(),
+        queue_id: ack_msg.queue_id(),
+        broker_name: ack_msg.broker_name().clone(),
+        start_offset: ack_msg.start_offset(),
+        ack_offsets,
+        pop_time: ack_msg.pop_time(),
+        invisible_time: (revive_time - ack_msg.pop_time()).max(0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+    use rocketmq_store::pop::ack_msg::AckMsg;
+    use rocketmq_store::pop::set_revive_body_crc;
+    use rocketmq_store::pop::AckMessage;
+
+    use super::*;
+    use crate::util::test_message_store::TestMessageStore;
+
+    fn revive_msg(ack_msg: &dyn AckMessage, revive_offset: i64) -> MessageExt {
+        let mut msg_ext = MessageExt::default();
+        msg_ext.set_tags(CheetahString::from_static_str(ack_msg.ack_tag()));
+        msg_ext.set_body(Bytes::from(ack_msg.encode_body().unwrap()));
+        msg_ext.set_delay_time_ms((ack_msg.pop_time() + 30_000) as u64);
+        msg_ext.queue_offset = revive_offset;
+        msg_ext
+    }
+
+    fn ack_msg(topic: &'static str, group: &'static str) -> AckMsg {
+        AckMsg {
+            ack_offset: 101,
+            start_offset: 100,
+            consumer_group: CheetahString::from_static_str(group),
+            topic: CheetahString::from_static_str(topic),
+            queue_id: 2,
+            pop_time: 1_000,
+            broker_name: CheetahString::from_static_str("broker-a"),
+        }
+    }
+
+    #[test]
+    fn revive_queue_ack_describes_single_and_batch_acks() {
+        let topic = CheetahString::from_static_str("test_topic");
+        let group = CheetahString::from_static_str("test_group");
+
+        let ack = revive_queue_ack_of(
+            &revive_msg(&ack_msg("test_topic", "test_group"), 7),
+            &topic,
+            &group,
+        )
+        .unwrap();
+        assert_eq!(ack.revive_offset, 7);
+        assert_eq!(ack.tag.as_str(), PopAckConstants::ACK_TAG);
+        assert_eq!(ack.ack_offsets, vec![101]);
+        assert_eq!(ack.start_offset, 100);
+        assert_eq!(ack.pop_time, 1_000);
+        assert_eq!(ack.invisible_time, 30_000);
+
+        let batch_ack_msg = BatchAckMsg {
+            ack_msg: ack_msg("test_topic", "test_group"),
+            ack_offset_list: vec![101, 103],
+        };
+        let ack = revive_queue_ack_of(&revive_msg(&batch_ack_msg, 8), &topic, &group).unwrap();
+        assert_eq!(ack.tag.as_str(), PopAckConstants::BATCH_ACK_TAG);
+        assert_eq!(ack.ack_offsets, vec![101, 103]);
+        assert_eq!(ack.queue_id, 2);
+        assert_eq!(ack.broker_name.as_str(), "broker-a");
+    }
+
+    #[test]
+    fn revive_queue_ack_skips_other_groups_and_checkpoints() {
+        let topic = CheetahString::from_static_str("test_topic");
+        let group = CheetahString::from_static_str("test_group");
+        assert!(revive_queue_ack_of(
+            &revive_msg(&ack_msg("test_topic", "other_group"), 7),
+            &topic,
+            &group,
+        )
+        .is_none());
+        assert!(revive_queue_ack_of(
+            &revive_msg(&ack_msg("other_topic", "test_group"), 7),
+            &topic,
+            &group,
+        )
+        .is_none());
+
+        let mut check_point = revive_msg(&ack_msg("test_topic", "test_group"), 7);
+        check_point.set_tags(CheetahString::from_static_str(PopAckConstants::CK_TAG));
+        assert!(revive_queue_ack_of(&check_point, &topic, &group).is_none());
+    }
+
+    #[tokio::test]
+    async fn corrupt_revive_message_is_skipped_and_counted() {
+        let broker_stats_manager = BrokerStatsManager::new(Arc::new(BrokerConfig::default()));
+        let mut inner = MessageExtBrokerInner::default();
+        inner.set_body(Bytes::from(
+            ack_msg("test_topic", "test_group").encode_body().unwrap(),
+        ));
+        set_revive_body_crc(&mut inner);
+        let mut msg_ext = inner.message_ext_inner;
+        assert!(is_intact_revive_msg(&msg_ext, &broker_stats_manager));
+        assert_eq!(broker_stats_manager.get_broker_revive_corrupt_nums(), 0);
+
+        let mut body = msg_ext.get_body().unwrap().to_vec();
+        body[0] ^= 0x01;
+        msg_ext.set_body(Bytes::from(body));
+        assert!(!is_intact_revive_msg(&msg_ext, &broker_stats_manager));
+        assert_eq!(broker_stats_manager.get_broker_revive_corrupt_nums(), 1);
+    }
+
+    #[test]
+    fn revive_lag_grows_with_the_revive_queue() {
+        let revive_topic = CheetahString::from_static_str("rmq_sys_REVIVE_LOG_DefaultCluster");
+        let message_store = TestMessageStore::default();
+        message_store.set_offset_range(&revive_topic, 0, 0, 10);
+        message_store.set_offset_range(&revive_topic, 1, 2, 5);
+        let consumer_offset_manager =
+            ConsumerOffsetManager::new(Arc::new(BrokerConfig::default()), None);
+        consumer_offset_manager.commit_offset(
+            "127.0.0.1:10911".parse().unwrap(),
+            &CheetahString::from_static_str(PopAckConstants::REVIVE_GROUP),
+            &revive_topic,
+            0,
+            4,
+        );
+
+        let lags = revive_queue_lags(&message_store, &consumer_offset_manager, &revive_topic, 2);
+        assert_eq!(
+            lags,
+            vec![
+                ReviveQueueLag {
+                    revive_queue_id: 0,
+                    max_offset: 10,
+                    consumed_offset: 4,
+                    lag: 6,
+                },
+                // never consumed, from its min offset
+                ReviveQueueLag {
+                    revive_queue_id: 1,
+                    max_offset: 5,
+                    consumed_offset: 2,
+                    lag: 3,
+                },
+            ]
+        );
+
+        // checkpoints keep coming while the revive service is stuck
+        message_store.set_offset_range(&revive_topic, 0, 0, 250);
+        let lags = revive_queue_lags(&message_store, &consumer_offset_manager, &revive_topic, 2);
+        assert_eq!(lags[0].lag, 246);
+        assert_eq!(lags[1].lag, 3);
+    }
+
+    #[test]
+    fn group_consume_lags_flag_the_groups_over_the_threshold() {
+        let topic_a = CheetahString::from_static_str("LagTopicA");
+        let topic_b = CheetahString::from_static_str("LagTopicB");
+        let fast_group = CheetahString::from_static_str("FastGroup");
+        let slow_group = CheetahString::from_static_str("SlowGroup");
+        let message_store = TestMessageStore::default();
+        message_store.set_offset_range(&topic_a, 0, 0, 100);
+        message_store.set_offset_range(&topic_a, 1, 0, 60);
+        message_store.set_offset_range(&topic_b, 0, 0, 500);
+        let consumer_offset_manager =
+            ConsumerOffsetManager::new(Arc::new(BrokerConfig::default()), None);
+        let client_host = "127.0.0.1:10911".parse().unwrap();
+        consumer_offset_manager.commit_offset(client_host, &fast_group, &topic_a, 0, 98);
+        consumer_offset_manager.commit_offset(client_host, &fast_group, &topic_a, 1, 60);
+        consumer_offset_manager.commit_offset(client_host, &slow_group, &topic_a, 1, 10);
+        consumer_offset_manager.commit_offset(client_host, &slow_group, &topic_b, 0, 300);
+
+        let lags = group_consume_lags(&message_store, &consumer_offset_manager, 100);
+        assert_eq!(lags.len(), 2);
+        assert_eq!(lags[0].group, fast_group);
+        assert_eq!(lags[0].lag, 2);
+        assert!(!lags[0].slow);
+        assert_eq!(lags[1].group, slow_group);
+        assert_eq!(lags[1].lag, 250);
+        assert!(lags[1].slow);
+        assert_eq!(
+            lags[1].queues,
+            vec![
+                QueueConsumeLag {
+                    topic: topic_a.clone(),
+                    queue_id: 1,
+                    max_offset: 60,
+                    consumer_offset: 10,
+                    lag: 50,
+                },
+                QueueConsumeLag {
+                    topic: topic_b.clone(),
+                    queue_id: 0,
+                    max_offset: 500,
+                    consumer_offset: 300,
+                    lag: 200,
+                },
+            ]
+        );
+
+        // the slow group caught up
+        consumer_offset_manager.commit_offset(client_host, &slow_group, &topic_b, 0, 500);
+        let lags = group_consume_lags(&message_store, &consumer_offset_manager, 100);
+        assert_eq!(lags[1].lag, 50);
+        assert!(!lags[1].slow);
+    }
 }
@@ -17,18 +17,24 @@
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::body::reset_group_offset_body::QueueOffsetReset;
+use rocketmq_remoting::protocol::body::reset_group_offset_body::ResetGroupOffsetBody;
 use rocketmq_remoting::protocol::header::get_max_offset_request_header::GetMaxOffsetRequestHeader;
 use rocketmq_remoting::protocol::header::get_max_offset_response_header::GetMaxOffsetResponseHeader;
 use rocketmq_remoting::protocol::header::get_min_offset_request_header::GetMinOffsetRequestHeader;
 use rocketmq_remoting::protocol::header::get_min_offset_response_header::GetMinOffsetResponseHeader;
 use rocketmq_remoting::protocol::header::message_operation_header::TopicRequestHeaderTrait;
+use rocketmq_remoting::protocol::header::reset_group_offset_request_header::ResetGroupOffsetRequestHeader;
+use rocketmq_remoting::protocol::header::trim_topic_request_header::TrimTopicRequestHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_context::TopicQueueMappingContext;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_utils::TopicQueueMappingUtils;
+use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::rpc::rpc_client::RpcClient;
 use rocketmq_remoting::rpc::rpc_request::RpcRequest;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_store::log_file::MessageStore;
+use tracing::info;
 
 use crate::processor::admin_broker_processor::Inner;
 
@@ -110,6 +116,156 @@ impl OffsetRequestHandler {
             response_header,
         ))
     }
+    pub async fn reset_group_offset_all_queues(
+        &mut self,
+        channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let response = RemotingCommand::create_response_command();
+        let request_header =
+            match request.decode_command_custom_header::<ResetGroupOffsetRequestHeader>() {
+                Ok(header) => header,
+                Err(e) => {
+                    return Some(
+                        response
+                            .set_code(ResponseCode::SystemError)
+                            .set_remark(format!("decode request header failed, {}", e)),
+                    );
+                }
+            };
+        let target = match OffsetResetTarget::parse(
+            request_header.reset_type.as_str(),
+            request_header.timestamp,
+            request_header.fallback_to_earliest.unwrap_or(false),
+        ) {
+            Some(target) => target,
+            None => {
+                return Some(
+                    response
+                        .set_code(ResponseCode::SystemError)
+                        .set_remark(format!(
+                            "invalid reset type {}, timestamp {:?}",
+                            request_header.reset_type, request_header.timestamp
+                        )),
+                );
+            }
+        };
+        let topic = &request_header.topic;
+        let group = &request_header.group;
+        let Some(topic_config) = self.inner.topic_config_manager.select_topic_config(topic) else {
+            return Some(
+                response
+                    .set_code(ResponseCode::TopicNotExist)
+                    .set_remark(format!("topic {} does not exist", topic)),
+            );
+        };
+        let message_store = &self.inner.default_message_store;
+        let offsets = plan_offset_reset(
+            topic_config.read_queue_nums as i32,
+            target,
+            |queue_id| message_store.get_min_offset_in_queue(topic, queue_id),
+            |queue_id| message_store.get_max_offset_in_queue(topic, queue_id),
+            |queue_id, offset| message_store.get_message_store_timestamp(topic, queue_id, offset),
+        );
+        let changes = self
+            .inner
+            .consumer_offset_manager
+            .reset_offsets(group, topic, &offsets);
+        for &(queue_id, before, after) in &changes {
+            info!(
+                "reset consumer offset, group={}, topic={}, queueId={}, target={:?}, {} -> {}, \
+                 caller={}",
+                group,
+                topic,
+                queue_id,
+                target,
+                before,
+                after,
+                channel.remote_address()
+            );
+        }
+        let body = ResetGroupOffsetBody {
+            queues: changes
+                .into_iter()
+                .map(|(queue_id, offset_before, offset)| QueueOffsetReset {
+                    queue_id,
+                    offset_before,
+                    offset,
+                })
+                .collect(),
+        };
+        match body.encode() {
+            Ok(body) => Some(response.set_body(body)),
+            Err(e) => Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(format!("encode reset offsets failed, {}", e)),
+            ),
+        }
+    }
+
+    pub async fn trim_topic_before_offset(
+        &mut self,
+        channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let response = RemotingCommand::create_response_command();
+        let request_header = match request.decode_command_custom_header::<TrimTopicRequestHeader>()
+        {
+            Ok(header) => header,
+            Err(e) => {
+                return Some(
+                    response
+                        .set_code(ResponseCode::SystemError)
+                        .set_remark(format!("decode request header failed, {}", e)),
+                );
+            }
+        };
+        let topic = &request_header.topic;
+        let queue_id = request_header.queue_id;
+        let Some(topic_config) = self.inner.topic_config_manager.select_topic_config(topic) else {
+            return Some(
+                response
+                    .set_code(ResponseCode::TopicNotExist)
+                    .set_remark(format!("topic {} does not exist", topic)),
+            );
+        };
+        if queue_id < 0
+            || queue_id
+                >= topic_config
+                    .write_queue_nums
+                    .max(topic_config.read_queue_nums) as i32
+        {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(format!(
+                        "queue {} of topic {} does not exist",
+                        queue_id, topic
+                    )),
+            );
+        }
+        let message_store = &self.inner.default_message_store;
+        let before = message_store.get_min_offset_in_queue(topic, queue_id);
+        let offset = message_store.trim_consume_queue(topic, queue_id, request_header.offset);
+        info!(
+            "trim topic queue, topic={}, queueId={}, requested={}, min offset {} -> {}, caller={}",
+            topic,
+            queue_id,
+            request_header.offset,
+            before,
+            offset,
+            channel.remote_address()
+        );
+        Some(RemotingCommand::create_response_command_with_header(
+            GetMinOffsetResponseHeader { offset },
+        ))
+    }
+
     /*
     async fn handle_get_min_offset(
         &mut self,
@@ -259,3 +415,216 @@ impl OffsetRequestHandler {
         ))
     }
 }
+
+/// Position to reset the offsets of a consumer group to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OffsetResetTarget {
+    Earliest,
+    Latest,
+    /// The first message stored at or after `timestamp`, a queue holding none is reset to its
+    /// earliest offset if `fallback_to_earliest` is set, to its latest one otherwise.
+    Timestamp {
+        timestamp: i64,
+        fallback_to_earliest: bool,
+    },
+}
+
+impl OffsetResetTarget {
+    fn parse(reset_type: &str, timestamp: Option<i64>, fallback_to_earliest: bool) -> Option<Self> {
+        match (reset_type.to_ascii_uppercase().as_str(), timestamp) {
+            ("EARLIEST", _) => Some(OffsetResetTarget::Earliest),
+            ("LATEST", _) => Some(OffsetResetTarget::Latest),
+            ("TIMESTAMP", Some(timestamp)) if timestamp >= 0 => {
+                Some(OffsetResetTarget::Timestamp {
+                    timestamp,
+                    fallback_to_earliest,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Computes the offset to reset every queue of a topic to, as `(queue_id, offset)`.
+fn plan_offset_reset(
+    queue_nums: i32,
+    target: OffsetResetTarget,
+    min_offset: impl Fn(i32) -> i64,
+    max_offset: impl Fn(i32) -> i64,
+    store_timestamp: impl Fn(i32, i64) -> i64,
+) -> Vec<(i32, i64)> {
+    (0..queue_nums)
+        .map(|queue_id| {
+            let min = min_offset(queue_id).max(0);
+            let max = max_offset(queue_id).max(min);
+            let offset = match target {
+                OffsetResetTarget::Earliest => min,
+                OffsetResetTarget::Latest => max,
+                OffsetResetTarget::Timestamp {
+                    timestamp,
+                    fallback_to_earliest,
+                } => {
+                    let offset = search_offset_by_timestamp(min, max, timestamp, |offset| {
+                        store_timestamp(queue_id, offset)
+                    });
+                    if offset == max && fallback_to_earliest {
+                        min
+                    } else {
+                        offset
+                    }
+                }
+            };
+            (queue_id, offset)
+        })
+        .collect()
+}
+
+/// Returns the first offset in `[min, max)` whose message was stored at or after `timestamp`,
+/// or `max` if there is none. Store timestamps grow with the offset in a queue.
+fn search_offset_by_timestamp(
+    min: i64,
+    max: i64,
+    timestamp: i64,
+    store_timestamp: impl Fn(i64) -> i64,
+) -> i64 {
+    let (mut low, mut high) = (min, max);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if store_timestamp(mid) < timestamp {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use cheetah_string::CheetahString;
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+
+    use super::*;
+    use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
+
+    #[test]
+    fn parse_offset_reset_target() {
+        assert_eq!(
+            OffsetResetTarget::parse("earliest", None, false),
+            Some(OffsetResetTarget::Earliest)
+        );
+        assert_eq!(
+            OffsetResetTarget::parse("LATEST", Some(5), false),
+            Some(OffsetResetTarget::Latest)
+        );
+        assert_eq!(
+            OffsetResetTarget::parse("TIMESTAMP", Some(5), true),
+            Some(OffsetResetTarget::Timestamp {
+                timestamp: 5,
+                fallback_to_earliest: true
+            })
+        );
+        assert_eq!(OffsetResetTarget::parse("TIMESTAMP", None, false), None);
+        assert_eq!(OffsetResetTarget::parse("NEWEST", None, false), None);
+    }
+
+    #[test]
+    fn search_offset_by_timestamp_finds_first_message_at_or_after() {
+        // message at offset n was stored at 1000 + 10 * n
+        let store_timestamp = |offset: i64| 1000 + 10 * offset;
+        assert_eq!(search_offset_by_timestamp(0, 10, 0, store_timestamp), 0);
+        assert_eq!(search_offset_by_timestamp(0, 10, 1050, store_timestamp), 5);
+        assert_eq!(search_offset_by_timestamp(0, 10, 1051, store_timestamp), 6);
+        assert_eq!(search_offset_by_timestamp(0, 10, 9999, store_timestamp), 10);
+        assert_eq!(search_offset_by_timestamp(3, 3, 1050, store_timestamp), 3);
+    }
+
+    #[test]
+    fn reset_multi_queue_topic_to_timestamp() {
+        let manager = ConsumerOffsetManager::new(Arc::new(BrokerConfig::default()), None);
+        let group = CheetahString::from("test_group");
+        let topic = CheetahString::from("test_topic");
+        let client_host = "127.0.0.1:10911".parse().unwrap();
+        for queue_id in 0..3 {
+            manager.commit_offset(client_host, &group, &topic, queue_id, 50);
+        }
+
+        // queue n holds offsets [10 * n, 100) stored every 10ms, starting at 1000 + n
+        let offsets = plan_offset_reset(
+            4,
+            OffsetResetTarget::Timestamp {
+                timestamp: 1300,
+                fallback_to_earliest: false,
+            },
+            |queue_id| 10 * queue_id as i64,
+            |_| 100,
+            |queue_id, offset| 1000 + queue_id as i64 + 10 * offset,
+        );
+        assert_eq!(offsets, vec![(0, 30), (1, 30), (2, 30), (3, 30)]);
+
+        let changes = manager.reset_offsets(&group, &topic, &offsets);
+        assert_eq!(
+            changes,
+            vec![(0, 50, 30), (1, 50, 30), (2, 50, 30), (3, -1, 30)]
+        );
+        for queue_id in 0..4 {
+            assert_eq!(manager.query_offset(&group, &topic, queue_id), 30);
+        }
+    }
+
+    #[test]
+    fn reset_to_past_future_and_empty_queue_timestamps() {
+        // queues 0 and 1 hold offsets [10, 20) stored every 10ms from 1000, queue 2 is empty
+        let min_offset = |queue_id: i32| if queue_id == 2 { 7 } else { 10 };
+        let max_offset = |queue_id: i32| if queue_id == 2 { 7 } else { 20 };
+        let store_timestamp = |_: i32, offset: i64| 1000 + 10 * (offset - 10);
+        let plan = |timestamp, fallback_to_earliest| {
+            plan_offset_reset(
+                3,
+                OffsetResetTarget::Timestamp {
+                    timestamp,
+                    fallback_to_earliest,
+                },
+                min_offset,
+                max_offset,
+                store_timestamp,
+            )
+        };
+
+        // before the first message, every message is consumed again
+        assert_eq!(plan(500, false), vec![(0, 10), (1, 10), (2, 7)]);
+        // after the last message, nothing is consumed again but with the fallback
+        assert_eq!(plan(5000, false), vec![(0, 20), (1, 20), (2, 7)]);
+        assert_eq!(plan(5000, true), vec![(0, 10), (1, 10), (2, 7)]);
+        // a queue holding a message at the timestamp ignores the fallback
+        assert_eq!(plan(1050, true), vec![(0, 15), (1, 15), (2, 7)]);
+    }
+
+    #[test]
+    fn plan_offset_reset_to_earliest_and_latest() {
+        let min_offset = |queue_id: i32| queue_id as i64;
+        let max_offset = |queue_id: i32| 100 + queue_id as i64;
+        assert_eq!(
+            plan_offset_reset(
+                2,
+                OffsetResetTarget::Earliest,
+                min_offset,
+                max_offset,
+                |_, _| 0
+            ),
+            vec![(0, 0), (1, 1)]
+        );
+        assert_eq!(
+            plan_offset_reset(
+                2,
+                OffsetResetTarget::Latest,
+                min_offset,
+                max_offset,
+                |_, _| 0
+            ),
+            vec![(0, 100), (1, 101)]
+        );
+    }
+}
@@ -28,6 +28,7 @@ use rocketmq_common::common::TopicFilterType;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::admin::response_body_format::ResponseBodyFormat;
 use rocketmq_remoting::protocol::admin::topic_offset::TopicOffset;
 use rocketmq_remoting::protocol::admin::topic_stats_table::TopicStatsTable;
 use rocketmq_remoting::protocol::body::create_topic_list_request_body::CreateTopicListRequestBody;
@@ -485,8 +486,8 @@ impl TopicRequestHandler {
         }
         topic_stats_table.set_offset_table(map);
         response.set_body_mut_ref(
-            topic_stats_table
-                .encode()
+            ResponseBodyFormat::from_request(&request)
+                .encode(&topic_stats_table)
                 .expect("encode TopicStatsTable failed"),
         );
         Some(response)
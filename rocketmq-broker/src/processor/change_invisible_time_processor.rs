@@ -43,6 +43,8 @@ use rocketmq_store::base::message_status_enum::PutMessageStatus;
 use rocketmq_store::log_file::MessageStore;
 use rocketmq_store::pop::ack_msg::AckMsg;
 use rocketmq_store::pop::pop_check_point::PopCheckPoint;
+use rocketmq_store::pop::set_revive_host_flags;
+use rocketmq_store::pop::AckMessage;
 use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
 use tracing::error;
 use tracing::info;
@@ -50,6 +52,7 @@ use tracing::info;
 use crate::failover::escape_bridge::EscapeBridge;
 use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
 use crate::offset::manager::consumer_order_info_manager::ConsumerOrderInfoManager;
+use crate::processor::pop_message_processor::gen_ck_unique_id;
 use crate::processor::pop_message_processor::PopMessageProcessor;
 use crate::processor::processor_service::pop_buffer_merge_service::PopBufferMergeService;
 use crate::topic::manager::topic_config_manager::TopicConfigManager;
@@ -65,7 +68,7 @@ pub struct ChangeInvisibleTimeProcessor<MS> {
     escape_bridge: ArcMut<EscapeBridge<MS>>,
     revive_topic: CheetahString,
     store_host: SocketAddr,
-    pop_message_processor: ArcMut<PopMessageProcessor>,
+    pop_message_processor: ArcMut<PopMessageProcessor<MS>>,
 }
 
 impl<MS> ChangeInvisibleTimeProcessor<MS> {
@@ -78,7 +81,7 @@ impl<MS> ChangeInvisibleTimeProcessor<MS> {
         broker_stats_manager: Arc<BrokerStatsManager>,
         pop_buffer_merge_service: ArcMut<PopBufferMergeService>,
         escape_bridge: ArcMut<EscapeBridge<MS>>,
-        pop_message_processor: ArcMut<PopMessageProcessor>,
+        pop_message_processor: ArcMut<PopMessageProcessor<MS>>,
     ) -> Self {
         let revive_topic = PopAckConstants::build_cluster_revive_topic(
             broker_config.broker_identity.broker_cluster_name.as_str(),
@@ -280,18 +283,19 @@ where
         }
         let mut inner = MessageExtBrokerInner::default();
         inner.set_topic(self.revive_topic.clone());
-        inner.set_body(Bytes::from(ack_msg.encode()?));
+        inner.set_body(Bytes::from(ack_msg.encode_body()?));
         inner.message_ext_inner.queue_id = rq_id;
         inner.set_tags(CheetahString::from_static_str(PopAckConstants::ACK_TAG));
         inner.message_ext_inner.born_timestamp = get_current_millis() as i64;
         inner.message_ext_inner.born_host = self.store_host;
         inner.message_ext_inner.store_host = self.store_host;
+        set_revive_host_flags(&mut inner);
         let deliver_time_ms = ExtraInfoUtil::get_pop_time(extra_info)?
             + ExtraInfoUtil::get_invisible_time(extra_info)?;
         inner.set_delay_time_ms(deliver_time_ms as u64);
         inner.message_ext_inner.put_property(
             CheetahString::from_static_str(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX),
-            CheetahString::from(PopMessageProcessor::gen_ack_unique_id(&ack_msg)),
+            CheetahString::from(ack_msg.unique_id()),
         );
         inner.properties_string =
             message_decoder::message_properties_to_string(inner.get_properties());
@@ -349,6 +353,7 @@ where
         inner.message_ext_inner.born_timestamp = get_current_millis() as i64;
         inner.message_ext_inner.born_host = self.store_host;
         inner.message_ext_inner.store_host = self.store_host;
+        set_revive_host_flags(&mut inner);
         let deliver_time_ms = ck.get_revive_time() - PopAckConstants::ACK_TIME_INTERVAL;
         let deliver_time_ms = if deliver_time_ms > 0 {
             deliver_time_ms as u64
@@ -358,7 +363,7 @@ where
         inner.set_delay_time_ms(deliver_time_ms);
         inner.message_ext_inner.put_property(
             CheetahString::from_static_str(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX),
-            CheetahString::from(PopMessageProcessor::gen_ck_unique_id(&ck)),
+            CheetahString::from(gen_ck_unique_id(&ck)),
         );
         inner.properties_string =
             message_decoder::message_properties_to_string(inner.get_properties());
@@ -388,6 +393,7 @@ where
         extra_info: &[String],
     ) -> crate::Result<Option<RemotingCommand>> {
         let pop_time = ExtraInfoUtil::get_pop_time(extra_info)?;
+        let revive_qid = ExtraInfoUtil::get_revive_qid(extra_info)?;
         let old_offset = self.consumer_offset_manager.query_offset(
             &request_header.consumer_group,
             &request_header.topic,
@@ -396,22 +402,35 @@ where
         if old_offset > request_header.offset {
             return Ok(Some(RemotingCommand::create_response_command()));
         }
-        while !self
-            .pop_message_processor
-            .queue_lock_manager()
+        let queue_lock_manager = self.pop_message_processor.queue_lock_manager();
+        if !queue_lock_manager
             .try_lock(
-                &request_header.consumer_group,
                 &request_header.topic,
+                &request_header.consumer_group,
                 request_header.queue_id,
             )
             .await
-        {}
+        {
+            return Ok(Some(
+                RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::SystemBusy,
+                    "queue is locked, try changing invisible time again later",
+                ),
+            ));
+        }
         let old_offset = self.consumer_offset_manager.query_offset(
             &request_header.consumer_group,
             &request_header.topic,
             request_header.queue_id,
         );
         if old_offset > request_header.offset {
+            queue_lock_manager
+                .unlock(
+                    &request_header.topic,
+                    &request_header.consumer_group,
+                    request_header.queue_id,
+                )
+                .await;
             return Ok(Some(RemotingCommand::create_response_command()));
         }
         let next_visible_time = get_current_millis() + request_header.invisible_time as u64;
@@ -423,17 +442,15 @@ where
             pop_time as u64,
             next_visible_time,
         );
-        let revive_qid = ExtraInfoUtil::get_revive_qid(extra_info)?;
         let response_header = ChangeInvisibleTimeResponseHeader {
             pop_time: pop_time as u64,
             revive_qid,
             invisible_time: (next_visible_time as i64) - pop_time,
         };
-        self.pop_message_processor
-            .queue_lock_manager()
+        queue_lock_manager
             .unlock(
-                &request_header.consumer_group,
                 &request_header.topic,
+                &request_header.consumer_group,
                 request_header.queue_id,
             )
             .await;
@@ -31,6 +31,7 @@ use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::header::unregister_client_request_header::UnregisterClientRequestHeader;
 use rocketmq_remoting::protocol::heartbeat::consume_type::ConsumeType;
 use rocketmq_remoting::protocol::heartbeat::heartbeat_data::HeartbeatData;
+use rocketmq_remoting::protocol::heartbeat::pop_ack_protocol_version::PopAckProtocolVersion;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_store::log_file::MessageStore;
@@ -39,6 +40,7 @@ use tracing::info;
 use crate::client::client_channel_info::ClientChannelInfo;
 use crate::client::manager::consumer_manager::ConsumerManager;
 use crate::client::manager::producer_manager::ProducerManager;
+use crate::load_balance::pop_sticky_assignment_manager::PopStickyAssignmentManager;
 use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
 use crate::topic::manager::topic_config_manager::TopicConfigManager;
 
@@ -53,6 +55,7 @@ pub struct ClientManageProcessor<MS> {
     topic_config_manager: TopicConfigManager,
     subscription_group_manager: Arc<SubscriptionGroupManager<MS>>,
     broker_config: Arc<BrokerConfig>,
+    pop_sticky_assignment_manager: Arc<PopStickyAssignmentManager>,
 }
 
 impl<MS> ClientManageProcessor<MS>
@@ -65,6 +68,7 @@ where
         consumer_manager: Arc<ConsumerManager>,
         topic_config_manager: TopicConfigManager,
         subscription_group_manager: Arc<SubscriptionGroupManager<MS>>,
+        pop_sticky_assignment_manager: Arc<PopStickyAssignmentManager>,
     ) -> Self {
         Self {
             consumer_group_heartbeat_table: Arc::new(parking_lot::RwLock::new(HashMap::new())),
@@ -73,6 +77,7 @@ where
             topic_config_manager,
             subscription_group_manager,
             broker_config,
+            pop_sticky_assignment_manager,
         }
     }
 }
@@ -122,8 +127,19 @@ where
                 .unregister_producer(group, &client_channel_info, &ctx);
         }
 
-        if let Some(ref _group) = request_header.consumer_group {
-            unimplemented!()
+        if let Some(ref group) = request_header.consumer_group {
+            let is_notify_consumer_ids_changed_enable = self
+                .subscription_group_manager
+                .find_subscription_group_config(group)
+                .map_or(true, |config| config.notify_consumer_ids_changed_enable());
+            self.consumer_manager.unregister_consumer(
+                group,
+                &client_channel_info,
+                is_notify_consumer_ids_changed_enable,
+            );
+            // the queues leased to the client are assignable right away instead of on expiry
+            self.pop_sticky_assignment_manager
+                .release_client(&request_header.client_id);
         }
 
         Some(RemotingCommand::create_response_command())
@@ -145,8 +161,17 @@ where
             request.language(),
             request.version(),
         );
+        let pop_ack_protocol_version = negotiate_pop_ack_protocol_version(&channel, &request);
         if heartbeat_data.heartbeat_fingerprint != 0 {
-            return self.heart_beat_v2(&channel, &ctx, heartbeat_data, client_channel_info);
+            return self
+                .heart_beat_v2(&channel, &ctx, heartbeat_data, client_channel_info)
+                .map(|mut response| {
+                    response.add_ext_field(
+                        PopAckProtocolVersion::EXT_FIELD_KEY,
+                        pop_ack_protocol_version.to_string(),
+                    );
+                    response
+                });
         }
 
         //do consumer data handle
@@ -221,6 +246,10 @@ where
         let mut response_command = RemotingCommand::create_response_command();
         response_command.add_ext_field(IS_SUPPORT_HEART_BEAT_V2.to_string(), true.to_string());
         response_command.add_ext_field(IS_SUB_CHANGE.to_string(), true.to_string());
+        response_command.add_ext_field(
+            PopAckProtocolVersion::EXT_FIELD_KEY,
+            pop_ack_protocol_version.to_string(),
+        );
         Some(response_command)
     }
 
@@ -245,3 +274,25 @@ where
         Some(response_command)
     }
 }
+
+/// Stores on the channel the pop/ack protocol version requested by the client heartbeat, so
+/// that later acks on the connection are parsed accordingly.
+fn negotiate_pop_ack_protocol_version(
+    channel: &Channel,
+    request: &RemotingCommand,
+) -> PopAckProtocolVersion {
+    let requested = request
+        .get_ext_fields()
+        .and_then(|ext_fields| ext_fields.get(PopAckProtocolVersion::EXT_FIELD_KEY))
+        .map(|value| value.as_str());
+    let version = PopAckProtocolVersion::negotiate(requested);
+    if version != channel.pop_ack_protocol_version() {
+        info!(
+            "negotiated pop ack protocol version {} with {}",
+            version,
+            channel.remote_address()
+        );
+        channel.set_pop_ack_protocol_version(version);
+    }
+    version
+}
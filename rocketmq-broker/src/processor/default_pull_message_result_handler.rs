@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 use std::any::Any;
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
@@ -22,7 +23,9 @@ use bytes::Bytes;
 use bytes::BytesMut;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::message::message_decoder;
 use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::mix_all::MASTER_ID;
 use rocketmq_common::common::sys_flag::pull_sys_flag::PullSysFlag;
 use rocketmq_common::TimeUtils::get_current_millis;
@@ -55,6 +58,7 @@ use tracing::warn;
 use crate::client::manager::consumer_manager::ConsumerManager;
 use crate::long_polling::long_polling_service::pull_request_hold_service::PullRequestHoldService;
 use crate::long_polling::pull_request::PullRequest;
+use crate::mqtrace::broker_trace_hook::is_traced;
 use crate::mqtrace::consume_message_context::ConsumeMessageContext;
 use crate::mqtrace::consume_message_hook::ConsumeMessageHook;
 use crate::offset::manager::broadcast_offset_manager::BroadcastOffsetManager;
@@ -144,6 +148,7 @@ impl PullMessageResultHandler for DefaultPullMessageResultHandler {
             &get_message_result,
             broker_allow_suspend,
             code,
+            client_address.as_str(),
         );
         {
             let response_header = response
@@ -327,6 +332,7 @@ impl DefaultPullMessageResultHandler {
         get_message_result: &GetMessageResult,
         broker_allow_suspend: bool,
         response_code: ResponseCode,
+        client_address: &str,
     ) {
         if self.has_consume_message_hook() {
             let ext_fields = request.get_ext_fields().unwrap();
@@ -349,6 +355,7 @@ impl DefaultPullMessageResultHandler {
                 .clone_from(&request_header.consumer_group);
             context.topic.clone_from(&request_header.topic);
             context.queue_id = Some(request_header.queue_id);
+            context.client_host = CheetahString::from(client_address);
             context.account_auth_type = auth_type;
             context.account_owner_parent = owner_parent;
             context.account_owner_self = owner_self;
@@ -371,6 +378,7 @@ impl DefaultPullMessageResultHandler {
                     context.rcv_msg_num = get_message_result.message_count();
                     context.rcv_msg_size = get_message_result.buffer_total_size();
                     context.commercial_rcv_msg_num = get_message_result.msg_count4_commercial();
+                    context.message_ids = traced_message_ids(get_message_result);
                 }
                 ResponseCode::PullNotFound => {
                     if !broker_allow_suspend {
@@ -598,3 +606,25 @@ impl DefaultPullMessageResultHandler {
         }
     }
 }
+
+/// Collects the unique ids of the traced messages of `get_message_result`, keyed to their
+/// queue offsets, so the consume hooks can record their delivery.
+fn traced_message_ids(get_message_result: &GetMessageResult) -> HashMap<String, i64> {
+    let mut message_ids = HashMap::new();
+    for mapped in get_message_result.message_mapped_list() {
+        let Some(msg_ext) = mapped.get_bytes().and_then(|mut bytes| {
+            message_decoder::decode(&mut bytes, false, false, false, false, false)
+        }) else {
+            continue;
+        };
+        if !is_traced(msg_ext.properties()) {
+            continue;
+        }
+        let msg_id = msg_ext
+            .properties()
+            .get(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX)
+            .unwrap_or(msg_ext.msg_id());
+        message_ids.insert(msg_id.to_string(), msg_ext.queue_offset());
+    }
+    message_ids
+}
@@ -76,7 +76,7 @@ where
 {
     pub async fn process_request(
         &mut self,
-        _channel: Channel,
+        channel: Channel,
         _ctx: ConnectionHandlerContext,
         _request_code: RequestCode,
         request: RemotingCommand,
@@ -90,9 +90,18 @@ where
                 ResponseCode::SlaveNotAvailable,
             ));
         }
+        // a half message left pending stays in the half queue until it is checked back
         if request_header.from_transaction_check {
             match request_header.commit_or_rollback {
-                MessageSysFlag::TRANSACTION_NOT_TYPE => return None,
+                MessageSysFlag::TRANSACTION_NOT_TYPE => {
+                    warn!(
+                        "Check producer[{}] transaction state, but it's pending status. \
+                         RequestHeader: {:?}",
+                        channel.remote_address(),
+                        request_header
+                    );
+                    return None;
+                }
                 MessageSysFlag::TRANSACTION_COMMIT_TYPE
                 | MessageSysFlag::TRANSACTION_ROLLBACK_TYPE => {
                     //nothing to do, can add some log
@@ -101,7 +110,15 @@ where
             }
         } else {
             match request_header.commit_or_rollback {
-                MessageSysFlag::TRANSACTION_NOT_TYPE => return None,
+                MessageSysFlag::TRANSACTION_NOT_TYPE => {
+                    warn!(
+                        "The producer[{}] end transaction in sending message, and it's pending \
+                         status. RequestHeader: {:?}",
+                        channel.remote_address(),
+                        request_header
+                    );
+                    return None;
+                }
                 MessageSysFlag::TRANSACTION_COMMIT_TYPE
                 | MessageSysFlag::TRANSACTION_ROLLBACK_TYPE => {
                     //nothing to do, can add some log
@@ -131,7 +148,7 @@ where
                 }
                 let res =
                     self.check_prepare_message(result.prepare_message.as_ref(), &request_header);
-                if ResponseCode::from(res.code()) != ResponseCode::Success {
+                if ResponseCode::from(res.code()) == ResponseCode::Success {
                     let mut msg_inner =
                         end_message_transaction(result.prepare_message.as_ref().unwrap());
                     msg_inner.message_ext_inner.sys_flag = MessageSysFlag::reset_transaction_value(
@@ -392,7 +409,23 @@ fn end_message_transaction(msg_ext: &MessageExt) -> MessageExtBrokerInner {
 
 #[cfg(test)]
 mod tests {
+    use rocketmq_common::common::config::TopicConfig;
+    use rocketmq_common::common::server::config::ServerConfig;
+    use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
+    use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContextWrapper;
+    use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
+
     use super::*;
+    use crate::broker_runtime::BrokerRuntimeInner;
+    use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
+    use crate::out_api::broker_outer_api::BrokerOuterAPI;
+    use crate::topic::manager::topic_config_manager::TopicConfigManager;
+    use crate::topic::manager::topic_queue_mapping_manager::TopicQueueMappingManager;
+    use crate::transaction::queue::default_transactional_message_service::DefaultTransactionalMessageService;
+    use crate::transaction::queue::transactional_message_bridge::TransactionalMessageBridge;
+    use crate::transaction::transactional_message_check_listener::TransactionalMessageCheckListener;
+    use crate::util::test_channel::test_channel;
+    use crate::util::test_message_store::TestMessageStore;
 
     #[test]
     fn end_message_transaction_with_valid_message() {
@@ -475,4 +508,283 @@ mod tests {
         assert!(msg_inner.get_topic().is_empty());
         assert_eq!(msg_inner.message_ext_inner.queue_id, 0);
     }
+
+    const TEST_TOPIC: &str = "TransactionTopic";
+    const TEST_GROUP: &str = "PID_TRANSACTION";
+
+    type TestTransactionalMessageService = DefaultTransactionalMessageService<TestMessageStore>;
+
+    #[derive(Default)]
+    struct RecordingCheckListener {
+        checked: Vec<MessageExt>,
+        discarded: Vec<MessageExt>,
+    }
+
+    impl TransactionalMessageCheckListener for RecordingCheckListener {
+        fn resolve_half_msg(&mut self, msg_ext: MessageExt) {
+            self.checked.push(msg_ext);
+        }
+
+        async fn resolve_discard_msg(&mut self, msg_ext: MessageExt) {
+            self.discarded.push(msg_ext);
+        }
+    }
+
+    struct TestTransaction {
+        runtime: tokio::runtime::Runtime,
+        processor: EndTransactionProcessor<TestTransactionalMessageService, TestMessageStore>,
+        service: ArcMut<TestTransactionalMessageService>,
+        message_store: ArcMut<TestMessageStore>,
+    }
+
+    impl TestTransaction {
+        fn new() -> Self {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            // the broker stats manager starts its tasks on construction
+            let _guard = runtime.enter();
+            let broker_config = Arc::new(BrokerConfig::default());
+            let broker_outer_api =
+                Arc::new(BrokerOuterAPI::new(Arc::new(TokioClientConfig::default())));
+            let topic_config_manager = TopicConfigManager::new(
+                broker_config.clone(),
+                Arc::new(BrokerRuntimeInner {
+                    broker_out_api: broker_outer_api,
+                    broker_config: broker_config.clone(),
+                    message_store_config: Arc::new(MessageStoreConfig::default()),
+                    server_config: Arc::new(ServerConfig::default()),
+                    topic_queue_mapping_manager: Arc::new(TopicQueueMappingManager::default()),
+                }),
+            );
+            topic_config_manager.put_topic_config(TopicConfig::with_queues(
+                TransactionalMessageUtil::build_half_topic(),
+                1,
+                1,
+            ));
+            let message_store = ArcMut::new(TestMessageStore::default());
+            message_store.keep_puts();
+            // the op queue exists before any removal is written to it
+            message_store.set_offset_range(TransactionalMessageUtil::build_op_topic(), 0, 0, 0);
+            let bridge = TransactionalMessageBridge::new(
+                message_store.clone(),
+                Arc::new(BrokerStatsManager::new(broker_config.clone())),
+                ConsumerOffsetManager::new(broker_config.clone(), None),
+                broker_config.clone(),
+                topic_config_manager,
+                "127.0.0.1:10911".parse().unwrap(),
+            );
+            let service = ArcMut::new(DefaultTransactionalMessageService::new(bridge));
+            let processor = EndTransactionProcessor::new(
+                Arc::new(MessageStoreConfig::default()),
+                broker_config,
+                service.clone(),
+                message_store.clone(),
+            );
+            drop(_guard);
+            TestTransaction {
+                runtime,
+                processor,
+                service,
+                message_store,
+            }
+        }
+
+        /// Sends half message `key` of the test topic born `age_millis` ago, returns it as
+        /// stored.
+        fn prepare(&mut self, key: &'static str, age_millis: u64) -> MessageExt {
+            let mut msg_inner = MessageExtBrokerInner::default();
+            msg_inner.set_topic(CheetahString::from_static_str(TEST_TOPIC));
+            msg_inner.message_ext_inner.queue_id = 1;
+            msg_inner.set_body(bytes::Bytes::from_static(b"transaction"));
+            msg_inner.message_ext_inner.born_timestamp = (get_current_millis() - age_millis) as i64;
+            for (name, value) in [
+                (MessageConst::PROPERTY_PRODUCER_GROUP, TEST_GROUP),
+                (MessageConst::PROPERTY_TRANSACTION_PREPARED, "true"),
+                (MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX, key),
+            ] {
+                MessageAccessor::put_property(
+                    &mut msg_inner,
+                    CheetahString::from_static_str(name),
+                    CheetahString::from_static_str(value),
+                );
+            }
+            let service = &mut self.service;
+            let put_message_result = self
+                .runtime
+                .block_on(async { service.prepare_message(msg_inner).await });
+            let wrote_offset = put_message_result
+                .append_message_result()
+                .unwrap()
+                .wrote_offset;
+            self.message_store
+                .look_message_by_offset(wrote_offset)
+                .unwrap()
+        }
+
+        /// Ends the transaction of `half_msg` with `commit_or_rollback`.
+        fn end(
+            &mut self,
+            half_msg: &MessageExt,
+            commit_or_rollback: i32,
+            from_transaction_check: bool,
+        ) -> Option<RemotingCommand> {
+            let request_header = EndTransactionRequestHeader {
+                topic: CheetahString::from_static_str(TEST_TOPIC),
+                producer_group: CheetahString::from_static_str(TEST_GROUP),
+                tran_state_table_offset: half_msg.queue_offset as u64,
+                commit_log_offset: half_msg.commit_log_offset as u64,
+                commit_or_rollback,
+                from_transaction_check,
+                msg_id: half_msg.msg_id().clone(),
+                ..Default::default()
+            };
+            let mut request = RemotingCommand::create_request_command(
+                RequestCode::EndTransaction,
+                request_header,
+            );
+            request.make_custom_header_to_net();
+            let processor = &mut self.processor;
+            self.runtime.block_on(async {
+                let channel = test_channel().await;
+                let ctx = ArcMut::new(ConnectionHandlerContextWrapper::new(channel.clone()));
+                processor
+                    .process_request(
+                        channel,
+                        ArcMut::downgrade(&ctx),
+                        RequestCode::EndTransaction,
+                        request,
+                    )
+                    .await
+            })
+        }
+
+        /// Half queue offsets removed so far, once the buffered removals are written.
+        fn removed_offsets(&self) -> Vec<String> {
+            self.runtime.block_on(self.service.batch_send_op_message());
+            self.message_store
+                .put_messages_of(TransactionalMessageUtil::build_op_topic())
+                .iter()
+                .map(|msg| String::from_utf8_lossy(msg.get_body().unwrap()).into_owned())
+                .collect()
+        }
+
+        fn check(&mut self, transaction_check_max: i32, listener: &mut RecordingCheckListener) {
+            let service = &mut self.service;
+            self.runtime
+                .block_on(async { service.check(6_000, transaction_check_max, listener).await });
+        }
+    }
+
+    fn key(msg_ext: &MessageExt) -> Option<CheetahString> {
+        msg_ext.get_user_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX,
+        ))
+    }
+
+    fn check_times(msg_ext: &MessageExt) -> Option<CheetahString> {
+        msg_ext.get_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_TRANSACTION_CHECK_TIMES,
+        ))
+    }
+
+    #[test]
+    fn commit_puts_half_message_to_its_topic_and_removes_it() {
+        let mut transaction = TestTransaction::new();
+        let half_msg = transaction.prepare("committed", 0);
+
+        let response = transaction
+            .end(&half_msg, MessageSysFlag::TRANSACTION_COMMIT_TYPE, false)
+            .unwrap();
+
+        assert_eq!(ResponseCode::from(response.code()), ResponseCode::Success);
+        let committed = transaction.message_store.put_messages_of(TEST_TOPIC);
+        assert_eq!(committed.len(), 1);
+        assert_eq!(committed[0].queue_id, 1);
+        assert_eq!(
+            committed[0].get_body().unwrap().as_ref(),
+            b"transaction".as_slice()
+        );
+        assert!(committed[0]
+            .get_property(&CheetahString::from_static_str(
+                MessageConst::PROPERTY_TRANSACTION_PREPARED
+            ))
+            .is_none());
+        assert_eq!(transaction.removed_offsets(), vec!["0,"]);
+    }
+
+    #[test]
+    fn rollback_only_removes_half_message() {
+        let mut transaction = TestTransaction::new();
+        let half_msg = transaction.prepare("rolled back", 0);
+
+        let response = transaction
+            .end(&half_msg, MessageSysFlag::TRANSACTION_ROLLBACK_TYPE, false)
+            .unwrap();
+
+        assert_eq!(ResponseCode::from(response.code()), ResponseCode::Success);
+        assert!(transaction
+            .message_store
+            .put_messages_of(TEST_TOPIC)
+            .is_empty());
+        assert_eq!(transaction.removed_offsets(), vec!["0,"]);
+    }
+
+    #[test]
+    fn unknown_transaction_is_checked_back_until_discarded() {
+        let mut transaction = TestTransaction::new();
+        let mut listener = RecordingCheckListener::default();
+        let committed = transaction.prepare("committed", 60_000);
+        let unknown = transaction.prepare("unknown", 60_000);
+        transaction.end(&committed, MessageSysFlag::TRANSACTION_COMMIT_TYPE, false);
+        assert!(transaction
+            .end(&unknown, MessageSysFlag::TRANSACTION_NOT_TYPE, false)
+            .is_none());
+
+        // only the unknown half message is checked back, through a copy put back to its queue
+        transaction.check(2, &mut listener);
+        assert_eq!(listener.checked.len(), 1);
+        assert_eq!(key(&listener.checked[0]), key(&unknown));
+        assert_eq!(check_times(&listener.checked[0]).as_deref(), Some("1"));
+        assert_eq!(listener.checked[0].queue_offset, 2);
+
+        transaction.check(2, &mut listener);
+        assert_eq!(listener.checked.len(), 2);
+        assert_eq!(check_times(&listener.checked[1]).as_deref(), Some("2"));
+
+        // checked too many times
+        transaction.check(2, &mut listener);
+        assert_eq!(listener.checked.len(), 2);
+        assert_eq!(listener.discarded.len(), 1);
+        assert_eq!(key(&listener.discarded[0]), key(&unknown));
+        assert_eq!(
+            transaction.message_store.put_messages_of(TEST_TOPIC).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn checked_back_transaction_is_committed_by_its_producer() {
+        let mut transaction = TestTransaction::new();
+        let mut listener = RecordingCheckListener::default();
+        transaction.prepare("due", 60_000);
+        transaction.prepare("not due", 0);
+
+        transaction.check(15, &mut listener);
+        assert_eq!(listener.checked.len(), 1);
+        let checked = listener.checked.pop().unwrap();
+        assert_eq!(key(&checked).as_deref(), Some("due"));
+        let response = transaction
+            .end(&checked, MessageSysFlag::TRANSACTION_COMMIT_TYPE, true)
+            .unwrap();
+
+        assert_eq!(ResponseCode::from(response.code()), ResponseCode::Success);
+        assert_eq!(
+            transaction.message_store.put_messages_of(TEST_TOPIC).len(),
+            1
+        );
+        transaction.check(15, &mut listener);
+        assert!(listener.checked.is_empty());
+    }
 }
@@ -0,0 +1,526 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Pop, ack and revive against a [`DefaultMessageStore`] backed by a temporary directory.
+//!
+//! Slower than the unit tests of the processors, run with
+//! `cargo test -p rocketmq-broker --features pop_integration_test`.
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use cheetah_string::CheetahString;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::config::TopicConfig;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::pop_ack_constants::PopAckConstants;
+use rocketmq_common::common::server::config::ServerConfig;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::header::ack_message_request_header::AckMessageRequestHeader;
+use rocketmq_remoting::protocol::header::extra_info_util::ExtraInfoUtil;
+use rocketmq_remoting::protocol::header::pop_message_request_header::PopMessageRequestHeader;
+use rocketmq_remoting::protocol::header::pop_message_response_header::PopMessageResponseHeader;
+use rocketmq_remoting::protocol::heartbeat::pop_ack_protocol_version::PopAckProtocolVersion;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
+use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContextWrapper;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::base::message_status_enum::PutMessageStatus;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use rocketmq_store::log_file::MessageStore;
+use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
+use rocketmq_store::pop::decode_ack_body;
+use rocketmq_store::pop::pop_check_point::PopCheckPoint;
+use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
+
+use crate::broker_runtime::BrokerRuntimeInner;
+use crate::client::default_consumer_ids_change_listener::DefaultConsumerIdsChangeListener;
+use crate::client::manager::consumer_manager::ConsumerManager;
+use crate::failover::escape_bridge::EscapeBridge;
+use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
+use crate::load_balance::pop_sticky_assignment_manager::PopStickyAssignmentManager;
+use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
+use crate::offset::manager::consumer_order_info_manager::ConsumerOrderInfoManager;
+use crate::out_api::broker_outer_api::BrokerOuterAPI;
+use crate::processor::ack_invisible_time_cap_table::AckInvisibleTimeCapTable;
+use crate::processor::ack_message_processor::AckMessageProcessor;
+use crate::processor::ack_processing_switch::AckProcessingSwitch;
+use crate::processor::pop_consumer_flow_controller::PopConsumerFlowController;
+use crate::processor::pop_inflight_message_counter::PopInflightMessageCounter;
+use crate::processor::pop_message_processor::PopMessageProcessor;
+use crate::processor::processor_service::pop_buffer_merge_service::PopBufferMergeService;
+use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
+use crate::topic::manager::topic_config_manager::TopicConfigManager;
+use crate::topic::manager::topic_queue_mapping_manager::TopicQueueMappingManager;
+use crate::topic::manager::topic_route_info_manager::TopicRouteInfoManager;
+use crate::util::test_channel::test_channel;
+
+const TOPIC: &str = "pop_it_topic";
+const GROUP: &str = "pop_it_group";
+const INVISIBLE_TIME: u64 = 1_000;
+
+/// A broker popping and acking through its processors over a store in a temporary directory,
+/// removed on drop.
+struct PopAckReviveHarness {
+    runtime: tokio::runtime::Runtime,
+    store_dir: std::path::PathBuf,
+    broker_config: Arc<BrokerConfig>,
+    message_store: ArcMut<DefaultMessageStore>,
+    escape_bridge: ArcMut<EscapeBridge<DefaultMessageStore>>,
+    pop_buffer_merge_service: ArcMut<PopBufferMergeService>,
+    pop_message_processor: ArcMut<PopMessageProcessor<DefaultMessageStore>>,
+    ack_message_processor: AckMessageProcessor<DefaultMessageStore>,
+}
+
+impl PopAckReviveHarness {
+    fn new(broker_config: BrokerConfig) -> Self {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .unwrap();
+        let _guard = runtime.enter();
+        let store_dir = std::env::temp_dir().join(format!(
+            "rocketmq-pop-it-{}-{}",
+            std::process::id(),
+            get_current_millis()
+        ));
+        let broker_config = Arc::new(broker_config);
+        let message_store_config = Arc::new(MessageStoreConfig {
+            store_path_root_dir: CheetahString::from_string(
+                store_dir.to_string_lossy().into_owned(),
+            ),
+            mapped_file_size_commit_log: 16 * 1024 * 1024,
+            ..MessageStoreConfig::default()
+        });
+        let broker_outer_api =
+            Arc::new(BrokerOuterAPI::new(Arc::new(TokioClientConfig::default())));
+        let topic_route_info_manager = Arc::new(TopicRouteInfoManager::new(
+            broker_outer_api.clone(),
+            broker_config.clone(),
+        ));
+        let mut topic_config_manager = TopicConfigManager::new(
+            broker_config.clone(),
+            Arc::new(BrokerRuntimeInner {
+                broker_out_api: broker_outer_api.clone(),
+                broker_config: broker_config.clone(),
+                message_store_config: message_store_config.clone(),
+                server_config: Arc::new(ServerConfig::default()),
+                topic_queue_mapping_manager: Arc::new(TopicQueueMappingManager::default()),
+            }),
+        );
+        topic_config_manager.put_topic_config(TopicConfig::with_queues(TOPIC, 1, 1));
+        let broker_stats_manager = Arc::new(BrokerStatsManager::new(broker_config.clone()));
+        let mut message_store = ArcMut::new(DefaultMessageStore::new(
+            message_store_config,
+            broker_config.clone(),
+            topic_config_manager.topic_config_table(),
+            Some(broker_stats_manager.clone()),
+            false,
+        ));
+        let message_store_clone = message_store.clone();
+        message_store.set_message_store_arc(Some(message_store_clone));
+        topic_config_manager.set_message_store(Some(message_store.clone()));
+        assert!(runtime.block_on(message_store.load()));
+        message_store.start().unwrap();
+
+        let subscription_group_manager =
+            Arc::new(SubscriptionGroupManager::new(broker_config.clone(), None));
+        let mut escape_bridge = ArcMut::new(EscapeBridge::new(
+            broker_config.clone(),
+            topic_route_info_manager,
+            broker_outer_api,
+        ));
+        escape_bridge.start(Some(message_store.clone()));
+        let store_host: SocketAddr = "127.0.0.1:10911".parse().unwrap();
+        let consumer_offset_manager = Arc::new(ConsumerOffsetManager::new(
+            broker_config.clone(),
+            Some(message_store.clone()),
+        ));
+        let pop_buffer_merge_service = ArcMut::new(PopBufferMergeService::new(
+            broker_config.clone(),
+            store_host,
+        ));
+        let pop_inflight_message_counter =
+            Arc::new(PopInflightMessageCounter::new(Arc::new(AtomicU64::new(0))));
+        let pop_consumer_flow_controller = Arc::new(PopConsumerFlowController::default());
+        let consumer_order_info_manager = Arc::new(ConsumerOrderInfoManager::new(
+            broker_config.clone(),
+            Arc::new(topic_config_manager.clone()),
+            subscription_group_manager.clone(),
+        ));
+        let pop_message_processor = ArcMut::new(PopMessageProcessor::new(
+            broker_config.clone(),
+            topic_config_manager.clone(),
+            subscription_group_manager.clone(),
+            consumer_offset_manager.clone(),
+            consumer_order_info_manager.clone(),
+            Arc::new(ConsumerFilterManager::default()),
+            message_store.clone(),
+            escape_bridge.clone(),
+            pop_buffer_merge_service.clone(),
+            pop_inflight_message_counter.clone(),
+            pop_consumer_flow_controller.clone(),
+            store_host,
+        ));
+        let ack_message_processor = AckMessageProcessor::builder()
+            .topic_config_manager(topic_config_manager.clone())
+            .subscription_group_manager(subscription_group_manager.clone())
+            .consumer_offset_manager(consumer_offset_manager)
+            .consumer_order_info_manager(consumer_order_info_manager)
+            .message_store(message_store.clone())
+            .escape_bridge(escape_bridge.clone())
+            .pop_message_processor(pop_message_processor.clone())
+            .broker_config(broker_config.clone())
+            .pop_inflight_message_counter(pop_inflight_message_counter)
+            .pop_sticky_assignment_manager(Arc::new(PopStickyAssignmentManager::new(
+                broker_config.pop_sticky_assignment_lease_millis,
+            )))
+            .consumer_manager(Arc::new(ConsumerManager::new_with_broker_stats(
+                Box::new(DefaultConsumerIdsChangeListener::default()),
+                broker_config.clone(),
+            )))
+            .ack_invisible_time_cap_table(Arc::new(AckInvisibleTimeCapTable::default()))
+            .pop_consumer_flow_controller(pop_consumer_flow_controller)
+            .ack_processing_switch(Arc::new(AckProcessingSwitch::new(false)))
+            .pop_buffer_merge_service(pop_buffer_merge_service.clone())
+            .broker_stats_manager(broker_stats_manager)
+            .store_host(store_host)
+            .build()
+            .unwrap();
+        drop(_guard);
+        PopAckReviveHarness {
+            runtime,
+            store_dir,
+            broker_config,
+            message_store,
+            escape_bridge,
+            pop_buffer_merge_service,
+            pop_message_processor,
+            ack_message_processor,
+        }
+    }
+
+    /// Sends messages with `bodies` to the only queue of the topic and waits for them to be
+    /// dispatched to its consume queue.
+    fn send(&mut self, bodies: &[&'static str]) {
+        let topic = CheetahString::from_static_str(TOPIC);
+        let mut message_store = self.message_store.clone();
+        self.runtime.block_on(async {
+            for body in bodies {
+                let mut inner = MessageExtBrokerInner::default();
+                inner.set_topic(topic.clone());
+                inner.set_body(Bytes::from_static(body.as_bytes()));
+                inner.message_ext_inner.queue_id = 0;
+                inner.message_ext_inner.born_timestamp = get_current_millis() as i64;
+                inner.message_ext_inner.born_host = "127.0.0.1:10000".parse().unwrap();
+                inner.message_ext_inner.store_host = "127.0.0.1:10911".parse().unwrap();
+                inner.properties_string =
+                    message_decoder::message_properties_to_string(inner.get_properties());
+                let result = message_store.put_message(inner).await;
+                assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+            }
+        });
+        self.wait_dispatched();
+        assert_eq!(
+            self.message_store.get_max_offset_in_queue(&topic, 0),
+            bodies.len() as i64
+        );
+    }
+
+    /// Waits for the reput of the store to dispatch every message of the commit log.
+    fn wait_dispatched(&self) {
+        let message_store = self.message_store.clone();
+        self.runtime
+            .block_on(wait_until(|| message_store.dispatch_behind_bytes() == 0));
+    }
+
+    /// Pops up to `max_msg_nums` messages of the topic, invisible for [`INVISIBLE_TIME`].
+    fn pop(&mut self, max_msg_nums: u32) -> Popped {
+        let request_header = PopMessageRequestHeader {
+            consumer_group: CheetahString::from_static_str(GROUP),
+            topic: CheetahString::from_static_str(TOPIC),
+            queue_id: 0,
+            max_msg_nums,
+            invisible_time: INVISIBLE_TIME,
+            poll_time: 0,
+            ..Default::default()
+        };
+        let mut request =
+            RemotingCommand::create_request_command(RequestCode::PopMessage, request_header);
+        request.make_custom_header_to_net();
+        let mut pop_message_processor = self.pop_message_processor.clone();
+        let response = self.runtime.block_on(async {
+            let channel = test_channel().await;
+            let ctx = ArcMut::new(ConnectionHandlerContextWrapper::new(channel.clone()));
+            pop_message_processor
+                .process_request(
+                    channel,
+                    ArcMut::downgrade(&ctx),
+                    RequestCode::PopMessage,
+                    request,
+                )
+                .await
+                .unwrap()
+                .unwrap()
+        });
+        assert_eq!(response.code(), ResponseCode::Success as i32);
+        let mut body = response.body().clone().unwrap_or_default();
+        let messages = message_decoder::decodes_batch(&mut body, true, false);
+        let mut response = response;
+        response.make_custom_header_to_net();
+        let response_header = response
+            .decode_command_custom_header::<PopMessageResponseHeader>()
+            .unwrap();
+        let start_offset_info = response_header.start_offset_info.unwrap_or_default();
+        Popped {
+            pop_time: response_header.pop_time as i64,
+            revive_qid: response_header.revive_qid as i32,
+            // a single queue is popped, its start offset is the one of the checkpoint
+            ck_offset: *ExtraInfoUtil::parse_start_offset_info(start_offset_info.as_str())
+                .unwrap()
+                .values()
+                .next()
+                .unwrap(),
+            messages,
+        }
+    }
+
+    /// Acks a popped message with the receipt handle a client builds from the pop response.
+    fn ack(&mut self, popped: &Popped, msg: &MessageExt) -> RemotingCommand {
+        let extra_info = ExtraInfoUtil::build_extra_info(
+            popped.ck_offset,
+            popped.pop_time,
+            INVISIBLE_TIME as i64,
+            popped.revive_qid,
+            TOPIC,
+            self.broker_config.broker_identity.broker_name.as_str(),
+            msg.queue_id,
+        );
+        let request_header = AckMessageRequestHeader {
+            consumer_group: CheetahString::from_static_str(GROUP),
+            topic: CheetahString::from_static_str(TOPIC),
+            queue_id: msg.queue_id,
+            extra_info: CheetahString::from_string(extra_info),
+            offset: msg.queue_offset,
+            observed_pop_time: None,
+            topic_request_header: None,
+        };
+        let mut request =
+            RemotingCommand::create_request_command(RequestCode::AckMessage, request_header);
+        request.make_custom_header_to_net();
+        let processor = &mut self.ack_message_processor;
+        self.runtime.block_on(async {
+            let channel = test_channel().await;
+            channel.set_pop_ack_protocol_version(PopAckProtocolVersion::V2);
+            let ctx = ArcMut::new(ConnectionHandlerContextWrapper::new(channel.clone()));
+            processor
+                .process_request(
+                    channel,
+                    ArcMut::downgrade(&ctx),
+                    RequestCode::AckMessage,
+                    request,
+                )
+                .await
+                .unwrap()
+                .unwrap()
+        })
+    }
+
+    /// Reconciles the revive queues as their reader does: the queue offsets of every checkpoint
+    /// due at `now` which no ack of the revive queues covers are revived.
+    fn revive_due(&self, now: i64) -> Vec<i64> {
+        let revive_topic = CheetahString::from_string(PopAckConstants::build_cluster_revive_topic(
+            self.broker_config
+                .broker_identity
+                .broker_cluster_name
+                .as_str(),
+        ));
+        self.wait_dispatched();
+        let mut check_points = Vec::new();
+        let mut acked = HashSet::new();
+        for revive_qid in 0..self.broker_config.revive_queue_num as i32 {
+            for msg_ext in self.read_queue(&revive_topic, revive_qid) {
+                let tag = msg_ext.get_tags().unwrap_or_default();
+                let body = msg_ext.get_body().cloned().unwrap_or_default();
+                if tag == PopAckConstants::CK_TAG {
+                    check_points.push(serde_json::from_slice::<PopCheckPoint>(&body).unwrap());
+                } else if let Some(ack_msg) = decode_ack_body(tag.as_str(), &body) {
+                    let ack_offsets = match ack_msg
+                        .as_any()
+                        .downcast_ref::<rocketmq_store::pop::batch_ack_msg::BatchAckMsg>(
+                    ) {
+                        Some(batch_ack_msg) => batch_ack_msg.ack_offset_list.clone(),
+                        None => vec![ack_msg.ack_offset()],
+                    };
+                    acked.extend(ack_offsets);
+                }
+            }
+        }
+        let mut revived = check_points
+            .iter()
+            .filter(|ck| ck.get_revive_time() <= now)
+            .flat_map(|ck| (0..ck.num).map(|index| ck.ack_offset_by_index(index)))
+            .filter(|offset| !acked.contains(offset))
+            .collect::<Vec<_>>();
+        revived.sort_unstable();
+        revived
+    }
+
+    /// Returns the bodies of the messages of the topic at `offsets`.
+    fn bodies_at(&self, offsets: &[i64]) -> Vec<String> {
+        let topic = CheetahString::from_static_str(TOPIC);
+        let messages = self.read_queue(&topic, 0);
+        offsets
+            .iter()
+            .map(|offset| {
+                let msg_ext = messages
+                    .iter()
+                    .find(|msg_ext| msg_ext.queue_offset == *offset)
+                    .unwrap();
+                String::from_utf8(msg_ext.get_body().unwrap().to_vec()).unwrap()
+            })
+            .collect()
+    }
+
+    fn read_queue(&self, topic: &CheetahString, queue_id: i32) -> Vec<MessageExt> {
+        self.runtime.block_on(async {
+            self.message_store
+                .get_message(
+                    &CheetahString::from_static_str(PopAckConstants::REVIVE_GROUP),
+                    topic,
+                    queue_id,
+                    0,
+                    1024,
+                    i32::MAX,
+                    None,
+                )
+                .await
+                .map(|get_message_result| {
+                    get_message_result
+                        .message_mapped_list()
+                        .iter()
+                        .filter_map(|mapped| {
+                            message_decoder::decode(
+                                &mut mapped.get_bytes()?,
+                                true,
+                                true,
+                                false,
+                                false,
+                                false,
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        })
+    }
+}
+
+/// Messages of a pop and the fields of the pop response their receipt handles are built from.
+struct Popped {
+    pop_time: i64,
+    revive_qid: i32,
+    ck_offset: i64,
+    messages: Vec<MessageExt>,
+}
+
+impl Drop for PopAckReviveHarness {
+    fn drop(&mut self) {
+        let _guard = self.runtime.enter();
+        self.message_store.shutdown();
+        let _ = std::fs::remove_dir_all(&self.store_dir);
+    }
+}
+
+async fn wait_until(mut condition: impl FnMut() -> bool) {
+    for _ in 0..500 {
+        if condition() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("condition not met within 5s");
+}
+
+#[test]
+fn unacked_messages_are_revived_after_invisible_time_and_acked_ones_are_not() {
+    let mut harness = PopAckReviveHarness::new(BrokerConfig::default());
+    harness.send(&["m0", "m1", "m2", "m3"]);
+
+    let popped = harness.pop(4);
+    let messages = popped.messages.clone();
+    assert_eq!(messages.len(), 4);
+    for msg in [&messages[0], &messages[2]] {
+        assert_eq!(
+            harness.ack(&popped, msg).code(),
+            ResponseCode::Success as i32
+        );
+    }
+
+    // nothing is revived while the messages are invisible
+    assert!(harness.revive_due(get_current_millis() as i64).is_empty());
+
+    let after_invisible_time = get_current_millis() as i64 + INVISIBLE_TIME as i64 + 1;
+    let revived = harness.revive_due(after_invisible_time);
+    assert_eq!(
+        revived,
+        vec![messages[1].queue_offset, messages[3].queue_offset]
+    );
+    assert_eq!(harness.bodies_at(&revived), vec!["m1", "m3"]);
+}
+
+#[test]
+fn buffered_acks_are_reconciled_with_their_check_point() {
+    let mut harness = PopAckReviveHarness::new(BrokerConfig {
+        enable_pop_buffer_merge: true,
+        ..BrokerConfig::default()
+    });
+    harness.send(&["m0", "m1", "m2"]);
+
+    let popped = harness.pop(3);
+    let messages = popped.messages.clone();
+    assert_eq!(messages.len(), 3);
+    assert_eq!(
+        harness.ack(&popped, &messages[1]).code(),
+        ResponseCode::Success as i32
+    );
+
+    // the checkpoint and the ack merged in the buffer reach the revive queue on flush
+    let mut escape_bridge = harness.escape_bridge.clone();
+    let left = harness.runtime.block_on(
+        harness
+            .pop_buffer_merge_service
+            .drain(escape_bridge.as_mut(), Duration::from_secs(5)),
+    );
+    assert_eq!(left, 0);
+    let after_invisible_time = get_current_millis() as i64 + INVISIBLE_TIME as i64 + 1;
+    let revived = harness.revive_due(after_invisible_time);
+    assert_eq!(
+        revived,
+        vec![messages[0].queue_offset, messages[2].queue_offset]
+    );
+    assert_eq!(harness.bodies_at(&revived), vec!["m0", "m2"]);
+}
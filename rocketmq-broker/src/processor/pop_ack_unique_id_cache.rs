@@ -0,0 +1,377 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use parking_lot::Mutex;
+use rocketmq_common::common::pop_ack_constants::PopAckConstants;
+use tracing::warn;
+
+/// Remembers the unique ids recently claimed by ack messages together with the content they
+/// were claimed for.
+///
+/// A client retrying an ack within the dedup window claims the same id with the same content
+/// and is told not to store it again. Ack messages are also deduplicated downstream by their
+/// unique id, so two different acks sharing an id would silently lose one of them. Such a
+/// collision never takes the id from its first claim and is reported to the caller; when the
+/// fallback is enabled the later ack is given a salted id so that both acks survive. Ids are
+/// meant not to collide in the first place, the fallback only guards against a flawed id.
+pub(crate) struct PopAckUniqueIdCache {
+    capacity: usize,
+    dedup_window_millis: i64,
+    enable_collision_fallback: bool,
+    inner: Mutex<CacheInner>,
+}
+
+/// Outcome of claiming a unique id for an ack message.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum AckIdClaim {
+    /// The ack is to be stored under this id.
+    Claimed(String),
+    /// A different ack holds the id, the ack is to be stored under this id, salted if the
+    /// fallback is enabled.
+    Collided(String),
+    /// The same ack was claimed within the dedup window and must not be stored again.
+    Duplicate,
+}
+
+/// The claimed ids and the order they are evicted in. A released id leaves a stale item in
+/// `insertion_order`, told apart from the item of a later claim by its sequence number and
+/// skipped when reached, so that a release does not scan the whole order.
+#[derive(Default)]
+struct CacheInner {
+    entries: HashMap<String, CacheEntry>,
+    insertion_order: VecDeque<(u64, String)>,
+    next_seq: u64,
+}
+
+struct CacheEntry {
+    content: Bytes,
+    claimed_at: i64,
+    /// Sequence number of the `insertion_order` item of this entry.
+    seq: u64,
+}
+
+impl PopAckUniqueIdCache {
+    pub fn new(capacity: usize, dedup_window_millis: u64, enable_collision_fallback: bool) -> Self {
+        PopAckUniqueIdCache {
+            capacity,
+            dedup_window_millis: dedup_window_millis as i64,
+            enable_collision_fallback,
+            inner: Mutex::new(CacheInner::default()),
+        }
+    }
+
+    /// Claims `unique_id` at `now` for an ack message with the given content.
+    ///
+    /// A repeated ack (same id and same content) keeps its id so it can still be deduplicated,
+    /// and is a [`AckIdClaim::Duplicate`] when claimed again within the dedup window. A
+    /// different ack with an already claimed id is a [`AckIdClaim::Collided`] and, if the
+    /// fallback is enabled, gets `unique_id@<n>` with the smallest free `n`.
+    pub fn claim(&self, unique_id: String, content: &Bytes, now: i64) -> AckIdClaim {
+        if self.capacity == 0 {
+            return AckIdClaim::Claimed(unique_id);
+        }
+        let mut inner = self.inner.lock();
+        if let Some(claim) = inner.claim(&unique_id, content, now, self) {
+            return claim;
+        }
+        warn!("ack unique id collision detected, uniqueId={}", unique_id);
+        if !self.enable_collision_fallback {
+            return AckIdClaim::Collided(unique_id);
+        }
+        let mut salt = 1u64;
+        loop {
+            let salted_id = format!("{}{}{}", unique_id, PopAckConstants::SPLIT, salt);
+            match inner.claim(&salted_id, content, now, self) {
+                Some(AckIdClaim::Claimed(salted_id)) => return AckIdClaim::Collided(salted_id),
+                Some(claim) => return claim,
+                None => salt += 1,
+            }
+        }
+    }
+
+    /// Gives up the claim on `unique_id` after its ack failed to be stored, so that a retry of
+    /// the client is stored.
+    pub fn release(&self, unique_id: &str) {
+        self.inner.lock().remove(unique_id);
+    }
+
+    /// Returns a guard releasing the claim on `unique_id` once dropped. An ack holds it until
+    /// its put succeeded, so the claim is given back even when the ack handling is cancelled
+    /// mid-flight.
+    pub fn release_on_drop(self: &Arc<Self>, unique_id: &str) -> AckIdRelease {
+        AckIdRelease {
+            cache: Some(self.clone()),
+            unique_id: unique_id.to_string(),
+        }
+    }
+}
+
+/// Pending release of a claimed unique id, see [`PopAckUniqueIdCache::release_on_drop`].
+pub(crate) struct AckIdRelease {
+    cache: Option<Arc<PopAckUniqueIdCache>>,
+    unique_id: String,
+}
+
+impl AckIdRelease {
+    /// Drops the guard without releasing, the id stays claimed.
+    pub fn cancel(mut self) {
+        self.cache = None;
+    }
+}
+
+impl Drop for AckIdRelease {
+    fn drop(&mut self) {
+        if let Some(cache) = self.cache.take() {
+            cache.release(&self.unique_id);
+        }
+    }
+}
+
+impl CacheInner {
+    /// Claims `unique_id` for `content`, returns `None` if another content holds it.
+    fn claim(
+        &mut self,
+        unique_id: &str,
+        content: &Bytes,
+        now: i64,
+        cache: &PopAckUniqueIdCache,
+    ) -> Option<AckIdClaim> {
+        match self.entries.get_mut(unique_id) {
+            None => {
+                self.insert(unique_id.to_string(), content.clone(), now, cache.capacity);
+                Some(AckIdClaim::Claimed(unique_id.to_string()))
+            }
+            Some(entry) if entry.content == *content => {
+                if now - entry.claimed_at < cache.dedup_window_millis {
+                    return Some(AckIdClaim::Duplicate);
+                }
+                entry.claimed_at = now;
+                Some(AckIdClaim::Claimed(unique_id.to_string()))
+            }
+            Some(_) => None,
+        }
+    }
+
+    fn insert(&mut self, unique_id: String, content: Bytes, now: i64, capacity: usize) {
+        while self.entries.len() >= capacity {
+            match self.insertion_order.pop_front() {
+                Some((seq, oldest)) => {
+                    if self.is_live(seq, &oldest) {
+                        self.entries.remove(&oldest);
+                    }
+                }
+                None => break,
+            }
+        }
+        self.skip_stale_front();
+        // stale items behind a live one are only reached by eviction, the order is compacted
+        // once they outnumber the entries so that it does not grow without eviction
+        if self.insertion_order.len() >= 2 * capacity {
+            let entries = &self.entries;
+            self.insertion_order
+                .retain(|(seq, id)| entries.get(id).is_some_and(|entry| entry.seq == *seq));
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.insertion_order.push_back((seq, unique_id.clone()));
+        self.entries.insert(
+            unique_id,
+            CacheEntry {
+                content,
+                claimed_at: now,
+                seq,
+            },
+        );
+    }
+
+    fn is_live(&self, seq: u64, unique_id: &str) -> bool {
+        self.entries
+            .get(unique_id)
+            .is_some_and(|entry| entry.seq == seq)
+    }
+
+    fn skip_stale_front(&mut self) {
+        while let Some((seq, oldest)) = self.insertion_order.front() {
+            if self.is_live(*seq, oldest) {
+                break;
+            }
+            self.insertion_order.pop_front();
+        }
+    }
+
+    /// Forgets `unique_id`, a later claim of it is then ordered as a new entry. Its item in
+    /// the eviction order is left stale.
+    fn remove(&mut self, unique_id: &str) {
+        self.entries.remove(unique_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claimed(id: &str) -> AckIdClaim {
+        AckIdClaim::Claimed(id.to_string())
+    }
+
+    #[test]
+    fn claim_keeps_id_for_first_ack() {
+        let cache = PopAckUniqueIdCache::new(16, 0, true);
+        let claim = cache.claim("id".to_string(), &Bytes::from_static(b"a"), 0);
+        assert_eq!(claim, claimed("id"));
+    }
+
+    #[test]
+    fn claim_keeps_id_for_repeated_ack_outside_window() {
+        let cache = PopAckUniqueIdCache::new(16, 1_000, true);
+        let content = Bytes::from_static(b"a");
+        cache.claim("id".to_string(), &content, 0);
+        assert_eq!(
+            cache.claim("id".to_string(), &content, 1_000),
+            claimed("id")
+        );
+        assert_eq!(
+            cache.claim("id".to_string(), &content, 1_500),
+            AckIdClaim::Duplicate
+        );
+    }
+
+    #[test]
+    fn repeated_ack_within_window_is_a_duplicate() {
+        let cache = PopAckUniqueIdCache::new(16, 1_000, false);
+        let content = Bytes::from_static(b"a");
+        assert_eq!(cache.claim("id".to_string(), &content, 0), claimed("id"));
+        assert_eq!(
+            cache.claim("id".to_string(), &content, 500),
+            AckIdClaim::Duplicate
+        );
+    }
+
+    #[test]
+    fn colliding_ack_keeps_first_claim() {
+        let cache = PopAckUniqueIdCache::new(16, 1_000, false);
+        let first = Bytes::from_static(b"a");
+        cache.claim("id".to_string(), &first, 0);
+        assert_eq!(
+            cache.claim("id".to_string(), &Bytes::from_static(b"b"), 1),
+            AckIdClaim::Collided("id".to_string())
+        );
+        // the colliding ack did not take over the id, the first ack is still deduplicated
+        assert_eq!(
+            cache.claim("id".to_string(), &first, 2),
+            AckIdClaim::Duplicate
+        );
+    }
+
+    #[test]
+    fn claim_salts_colliding_ack() {
+        let cache = PopAckUniqueIdCache::new(16, 1_000, true);
+        let first = cache.claim("id".to_string(), &Bytes::from_static(b"a"), 0);
+        let second = cache.claim("id".to_string(), &Bytes::from_static(b"b"), 0);
+        let third = cache.claim("id".to_string(), &Bytes::from_static(b"c"), 0);
+        assert_eq!(first, claimed("id"));
+        assert_eq!(second, AckIdClaim::Collided("id@1".to_string()));
+        assert_eq!(third, AckIdClaim::Collided("id@2".to_string()));
+
+        // a retried colliding ack is still deduplicated under its salted id
+        let retried = cache.claim("id".to_string(), &Bytes::from_static(b"b"), 1);
+        assert_eq!(retried, AckIdClaim::Duplicate);
+    }
+
+    #[test]
+    fn released_ack_is_claimed_again() {
+        let cache = PopAckUniqueIdCache::new(16, 1_000, false);
+        let content = Bytes::from_static(b"a");
+        cache.claim("id".to_string(), &content, 0);
+        cache.release("id");
+        assert_eq!(cache.claim("id".to_string(), &content, 1), claimed("id"));
+    }
+
+    #[test]
+    fn release_on_drop_releases_unless_cancelled() {
+        let cache = Arc::new(PopAckUniqueIdCache::new(16, 1_000, false));
+        let content = Bytes::from_static(b"a");
+        cache.claim("id".to_string(), &content, 0);
+        drop(cache.release_on_drop("id"));
+        assert_eq!(cache.claim("id".to_string(), &content, 1), claimed("id"));
+
+        cache.release_on_drop("id").cancel();
+        assert_eq!(
+            cache.claim("id".to_string(), &content, 2),
+            AckIdClaim::Duplicate
+        );
+    }
+
+    #[test]
+    fn released_and_reclaimed_ack_is_evicted_as_newest_entry() {
+        let cache = PopAckUniqueIdCache::new(2, 1_000, false);
+        let content = Bytes::from_static(b"a");
+        cache.claim("id1".to_string(), &content, 0);
+        cache.claim("id2".to_string(), &Bytes::from_static(b"b"), 0);
+        cache.release("id1");
+        cache.claim("id1".to_string(), &content, 1);
+        assert_eq!(cache.inner.lock().insertion_order.len(), 2);
+
+        // id2 is now the oldest entry and the one evicted
+        cache.claim("id3".to_string(), &Bytes::from_static(b"c"), 2);
+        assert_eq!(
+            cache.claim("id1".to_string(), &content, 3),
+            AckIdClaim::Duplicate
+        );
+        assert_eq!(cache.inner.lock().insertion_order.len(), 2);
+    }
+
+    #[test]
+    fn released_ids_do_not_grow_the_eviction_order() {
+        let cache = PopAckUniqueIdCache::new(4, 1_000, false);
+        let content = Bytes::from_static(b"a");
+        cache.claim("kept".to_string(), &content, 0);
+        for i in 0..100 {
+            let unique_id = format!("id{}", i);
+            cache.claim(unique_id.clone(), &content, i);
+            cache.release(&unique_id);
+        }
+        assert!(cache.inner.lock().insertion_order.len() < 8);
+        // the entry claimed before the released ones is still deduplicated
+        assert_eq!(
+            cache.claim("kept".to_string(), &content, 100),
+            AckIdClaim::Duplicate
+        );
+    }
+
+    #[test]
+    fn claim_evicts_oldest_entry() {
+        let cache = PopAckUniqueIdCache::new(2, 0, true);
+        cache.claim("id1".to_string(), &Bytes::from_static(b"a"), 0);
+        cache.claim("id2".to_string(), &Bytes::from_static(b"b"), 0);
+        cache.claim("id3".to_string(), &Bytes::from_static(b"c"), 0);
+        let claim = cache.claim("id1".to_string(), &Bytes::from_static(b"d"), 0);
+        assert_eq!(claim, claimed("id1"));
+    }
+
+    #[test]
+    fn zero_window_disables_dedup() {
+        let cache = PopAckUniqueIdCache::new(16, 0, false);
+        let content = Bytes::from_static(b"a");
+        assert_eq!(cache.claim("id".to_string(), &content, 0), claimed("id"));
+        assert_eq!(cache.claim("id".to_string(), &content, 0), claimed("id"));
+    }
+}
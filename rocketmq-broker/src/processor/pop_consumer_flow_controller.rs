@@ -0,0 +1,152 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::net::SocketAddr;
+
+use cheetah_string::CheetahString;
+use dashmap::DashMap;
+use rocketmq_common::TimeUtils::get_current_millis;
+
+/// Weight of the latest sample in the ack rate average.
+const ACK_RATE_SMOOTHING: f64 = 0.2;
+
+/// Flow control state of a pop consumer, identified by its group and connection.
+#[derive(Debug, Default)]
+struct ConsumerFlowState {
+    /// Latest processing capacity advertised by the consumer.
+    capacity: Option<u32>,
+    /// Messages delivered and not acked yet, as far as this broker knows.
+    in_flight: u64,
+    /// Smoothed rate at which the consumer acks, in messages per second.
+    ack_rate: f64,
+    last_ack_time: u64,
+}
+
+/// Caps the messages delivered to pop consumers by the processing capacity they advertise.
+///
+/// A consumer under local backpressure advertises a low capacity in its pop requests. Messages
+/// delivered to it count against that capacity until they are acked, so the acks flowing back
+/// free room for the next pops. A consumer which never advertised a capacity is not limited.
+#[derive(Default)]
+pub(crate) struct PopConsumerFlowController {
+    consumers: DashMap<(CheetahString, SocketAddr), ConsumerFlowState>,
+}
+
+impl PopConsumerFlowController {
+    /// Returns how many messages at most to deliver for a pop asking for `max_msg_nums`,
+    /// after recording the capacity advertised with it, if any.
+    ///
+    /// At least one message is allowed, so that a consumer whose acks got lost is not starved
+    /// until its messages are revived.
+    pub fn cap_max_msg_nums(
+        &self,
+        group: &CheetahString,
+        consumer: SocketAddr,
+        max_msg_nums: u32,
+        capacity: Option<u32>,
+    ) -> u32 {
+        let mut state = self.consumers.entry((group.clone(), consumer)).or_default();
+        if capacity.is_some() {
+            state.capacity = capacity;
+        }
+        match state.capacity {
+            Some(capacity) => {
+                let headroom = (capacity as u64).saturating_sub(state.in_flight).max(1);
+                max_msg_nums.min(headroom.min(u32::MAX as u64) as u32)
+            }
+            None => max_msg_nums,
+        }
+    }
+
+    pub fn record_delivered(&self, group: &CheetahString, consumer: SocketAddr, num: u64) {
+        if let Some(mut state) = self.consumers.get_mut(&(group.clone(), consumer)) {
+            state.in_flight += num;
+        }
+    }
+
+    pub fn record_acked(&self, group: &CheetahString, consumer: SocketAddr, num: u64) {
+        self.record_acked_at(group, consumer, num, get_current_millis());
+    }
+
+    fn record_acked_at(&self, group: &CheetahString, consumer: SocketAddr, num: u64, now: u64) {
+        let Some(mut state) = self.consumers.get_mut(&(group.clone(), consumer)) else {
+            return;
+        };
+        state.in_flight = state.in_flight.saturating_sub(num);
+        if state.last_ack_time > 0 && now > state.last_ack_time {
+            let rate = num as f64 * 1000.0 / (now - state.last_ack_time) as f64;
+            state.ack_rate = if state.ack_rate == 0.0 {
+                rate
+            } else {
+                ACK_RATE_SMOOTHING * rate + (1.0 - ACK_RATE_SMOOTHING) * state.ack_rate
+            };
+        }
+        state.last_ack_time = now;
+    }
+
+    /// Smoothed ack rate of a consumer, in messages per second.
+    pub fn ack_rate(&self, group: &CheetahString, consumer: SocketAddr) -> f64 {
+        self.consumers
+            .get(&(group.clone(), consumer))
+            .map_or(0.0, |state| state.ack_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn consumer() -> SocketAddr {
+        "127.0.0.1:20000".parse().unwrap()
+    }
+
+    #[test]
+    fn consumer_without_capacity_is_not_limited() {
+        let controller = PopConsumerFlowController::default();
+        let group = CheetahString::from_static_str("test_group");
+        assert_eq!(
+            controller.cap_max_msg_nums(&group, consumer(), 32, None),
+            32
+        );
+    }
+
+    #[test]
+    fn in_flight_messages_count_against_capacity_until_acked() {
+        let controller = PopConsumerFlowController::default();
+        let group = CheetahString::from_static_str("test_group");
+        assert_eq!(
+            controller.cap_max_msg_nums(&group, consumer(), 32, Some(10)),
+            10
+        );
+        controller.record_delivered(&group, consumer(), 10);
+        // the capacity is remembered, at least one message is still allowed
+        assert_eq!(controller.cap_max_msg_nums(&group, consumer(), 32, None), 1);
+        controller.record_acked(&group, consumer(), 6);
+        assert_eq!(controller.cap_max_msg_nums(&group, consumer(), 32, None), 6);
+    }
+
+    #[test]
+    fn ack_rate_follows_acks() {
+        let controller = PopConsumerFlowController::default();
+        let group = CheetahString::from_static_str("test_group");
+        controller.cap_max_msg_nums(&group, consumer(), 32, Some(100));
+        controller.record_acked_at(&group, consumer(), 1, 1000);
+        controller.record_acked_at(&group, consumer(), 10, 2000);
+        assert_eq!(controller.ack_rate(&group, consumer()), 10.0);
+        controller.record_acked_at(&group, consumer(), 20, 3000);
+        assert_eq!(controller.ack_rate(&group, consumer()), 12.0);
+    }
+}
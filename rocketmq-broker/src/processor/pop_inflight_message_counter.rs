@@ -31,6 +31,9 @@ type PopInflightMessageCounterMap =
 pub(crate) struct PopInflightMessageCounter {
     should_start_time: Arc<AtomicU64>,
     topic_in_flight_message_num: PopInflightMessageCounterMap,
+    /// Number of decremented messages which were not counted inflight, acks of messages popped
+    /// before a restart of the broker or acked twice.
+    phantom_ack_num: AtomicU64,
 }
 
 impl PopInflightMessageCounter {
@@ -40,6 +43,7 @@ impl PopInflightMessageCounter {
         PopInflightMessageCounter {
             should_start_time,
             topic_in_flight_message_num: Arc::new(Mutex::new(HashMap::new())),
+            phantom_ack_num: AtomicU64::new(0),
         }
     }
 
@@ -95,11 +99,22 @@ impl PopInflightMessageCounter {
         queue_id: i32,
         delta: i64,
     ) {
+        if delta <= 0 {
+            return;
+        }
         let key = Self::build_key(topic, group);
         let mut map = self.topic_in_flight_message_num.lock();
+        // an unknown key is left absent rather than stored negative, which later increments
+        // would only bring back to zero
+        let mut in_flight = 0;
         if let Some(queue_num) = map.get_mut(&key) {
             if let Some(counter) = queue_num.get(&queue_id) {
-                if counter.fetch_add(-delta, Ordering::SeqCst) <= 0 {
+                // updates hold the map lock, so an over-decrement can be clamped at zero
+                // without racing with another update of the counter
+                in_flight = counter.load(Ordering::SeqCst).max(0);
+                let remaining = (in_flight - delta).max(0);
+                counter.store(remaining, Ordering::SeqCst);
+                if remaining == 0 {
                     queue_num.remove(&queue_id);
                 }
             }
@@ -107,6 +122,15 @@ impl PopInflightMessageCounter {
                 map.remove(&key);
             }
         }
+        if delta > in_flight {
+            self.phantom_ack_num
+                .fetch_add((delta - in_flight) as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the number of decremented messages which were not counted inflight.
+    pub fn phantom_ack_num(&self) -> u64 {
+        self.phantom_ack_num.load(Ordering::Relaxed)
     }
 
     pub fn clear_in_flight_message_num_by_group_name(&self, group: &CheetahString) {
@@ -169,7 +193,7 @@ impl PopInflightMessageCounter {
         }
     }
 
-    pub fn get_group_pop_in_flight_message_num(
+    pub fn get_in_flight_message_num(
         &self,
         topic: &CheetahString,
         group: &CheetahString,
@@ -185,6 +209,56 @@ impl PopInflightMessageCounter {
         0
     }
 
+    /// Returns the inflight number of every queue of `group` on `topic` holding inflight
+    /// messages. The numbers are read under a single lock, so they belong to the same snapshot.
+    pub fn get_queue_in_flight_message_num(
+        &self,
+        topic: &CheetahString,
+        group: &CheetahString,
+    ) -> HashMap<i32, i64> {
+        let key = Self::build_key(topic, group);
+        let map = self.topic_in_flight_message_num.lock();
+        map.get(&key)
+            .map(|queue_counter| {
+                queue_counter
+                    .iter()
+                    .map(|(queue_id, counter)| (*queue_id, counter.load(Ordering::SeqCst).max(0)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn get_group_in_flight_message_num(
+        &self,
+        topic: &CheetahString,
+        group: &CheetahString,
+    ) -> i64 {
+        self.get_queue_in_flight_message_num(topic, group)
+            .values()
+            .sum()
+    }
+
+    /// Returns a guard decrementing the inflight number of `queue_id` by `delta` once dropped,
+    /// like `decrement_in_flight_message_num` does. An ack holds it while its put is pending,
+    /// so the number is given back even when the ack handling is cancelled mid-flight.
+    pub fn decrement_on_drop(
+        self: &Arc<Self>,
+        topic: &CheetahString,
+        group: &CheetahString,
+        pop_time: i64,
+        queue_id: i32,
+        delta: i64,
+    ) -> InFlightDecrement {
+        InFlightDecrement {
+            counter: Some(self.clone()),
+            topic: topic.clone(),
+            group: group.clone(),
+            pop_time,
+            queue_id,
+            delta,
+        }
+    }
+
     fn split_key(key: &CheetahString) -> Option<(CheetahString, CheetahString)> {
         let parts: Vec<&str> = key.split(Self::TOPIC_GROUP_SEPARATOR).collect();
         if parts.len() == 2 {
@@ -199,6 +273,38 @@ impl PopInflightMessageCounter {
     }
 }
 
+/// Pending decrement of an inflight number, see
+/// [`PopInflightMessageCounter::decrement_on_drop`].
+pub(crate) struct InFlightDecrement {
+    counter: Option<Arc<PopInflightMessageCounter>>,
+    topic: CheetahString,
+    group: CheetahString,
+    pop_time: i64,
+    queue_id: i32,
+    delta: i64,
+}
+
+impl InFlightDecrement {
+    /// Drops the guard without decrementing, the messages stay inflight.
+    pub fn cancel(mut self) {
+        self.counter = None;
+    }
+}
+
+impl Drop for InFlightDecrement {
+    fn drop(&mut self) {
+        if let Some(counter) = self.counter.take() {
+            counter.decrement_in_flight_message_num(
+                &self.topic,
+                &self.group,
+                self.pop_time,
+                self.queue_id,
+                self.delta,
+            );
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::atomic::AtomicU64;
@@ -217,10 +323,7 @@ mod tests {
         let topic = CheetahString::from("test_topic");
         let group = CheetahString::from("test_group");
         counter.increment_in_flight_message_num(&topic, &group, 1, 5);
-        assert_eq!(
-            counter.get_group_pop_in_flight_message_num(&topic, &group, 1),
-            5
-        );
+        assert_eq!(counter.get_in_flight_message_num(&topic, &group, 1), 5);
     }
 
     #[test]
@@ -230,25 +333,78 @@ mod tests {
         let group = CheetahString::from("test_group");
         counter.increment_in_flight_message_num(&topic, &group, 1, 5);
         counter.decrement_in_flight_message_num(&topic, &group, 0, 1, 3);
+        assert_eq!(counter.get_in_flight_message_num(&topic, &group, 1), 2);
+    }
+
+    #[test]
+    fn over_decrement_is_clamped_at_zero() {
+        let counter = setup_counter();
+        let topic = CheetahString::from("test_topic");
+        let group = CheetahString::from("test_group");
+        counter.increment_in_flight_message_num(&topic, &group, 1, 2);
+        counter.decrement_in_flight_message_num(&topic, &group, 0, 1, 5);
+        assert_eq!(counter.get_in_flight_message_num(&topic, &group, 1), 0);
+        assert_eq!(counter.phantom_ack_num(), 3);
+        counter.increment_in_flight_message_num(&topic, &group, 1, 3);
+        assert_eq!(counter.get_in_flight_message_num(&topic, &group, 1), 3);
+    }
+
+    #[test]
+    fn decrement_of_never_incremented_key_stays_at_zero() {
+        let counter = setup_counter();
+        let topic = CheetahString::from("test_topic");
+        let group = CheetahString::from("test_group");
+        counter.increment_in_flight_message_num(&topic, &group, 0, 1);
+
+        // popped before a restart: neither the group nor the queue were counted
+        counter.decrement_in_flight_message_num(&topic, &CheetahString::from("other"), 0, 0, 2);
+        counter.decrement_in_flight_message_num(&topic, &group, 0, 1, 4);
+
+        assert_eq!(counter.get_in_flight_message_num(&topic, &group, 1), 0);
         assert_eq!(
-            counter.get_group_pop_in_flight_message_num(&topic, &group, 1),
-            2
+            counter.get_queue_in_flight_message_num(&topic, &group),
+            HashMap::from([(0, 1)])
         );
+        assert_eq!(
+            counter.get_group_in_flight_message_num(&topic, &CheetahString::from("other")),
+            0
+        );
+        assert_eq!(counter.phantom_ack_num(), 6);
+
+        counter.increment_in_flight_message_num(&topic, &group, 1, 2);
+        assert_eq!(counter.get_in_flight_message_num(&topic, &group, 1), 2);
     }
 
     #[test]
-    fn clear_in_flight_message_num_by_group_name_clears_correctly() {
+    fn group_in_flight_message_num_sums_queues() {
         let counter = setup_counter();
         let topic = CheetahString::from("test_topic");
         let group = CheetahString::from("test_group");
+        counter.increment_in_flight_message_num(&topic, &group, 0, 2);
         counter.increment_in_flight_message_num(&topic, &group, 1, 5);
-        counter.clear_in_flight_message_num_by_group_name(&group);
+        counter.increment_in_flight_message_num(&topic, &CheetahString::from("other"), 1, 7);
+        counter.decrement_in_flight_message_num(&topic, &group, 0, 1, 1);
+        assert_eq!(
+            counter.get_queue_in_flight_message_num(&topic, &group),
+            HashMap::from([(0, 2), (1, 4)])
+        );
+        assert_eq!(counter.get_group_in_flight_message_num(&topic, &group), 6);
         assert_eq!(
-            counter.get_group_pop_in_flight_message_num(&topic, &group, 1),
+            counter.get_group_in_flight_message_num(&CheetahString::from("other_topic"), &group),
             0
         );
     }
 
+    #[test]
+    fn clear_in_flight_message_num_by_group_name_clears_correctly() {
+        let counter = setup_counter();
+        let topic = CheetahString::from("test_topic");
+        let group = CheetahString::from("test_group");
+        counter.increment_in_flight_message_num(&topic, &group, 1, 5);
+        counter.clear_in_flight_message_num_by_group_name(&group);
+        assert_eq!(counter.get_in_flight_message_num(&topic, &group, 1), 0);
+    }
+
     #[test]
     fn clear_in_flight_message_num_by_topic_name_clears_correctly() {
         let counter = setup_counter();
@@ -256,10 +412,7 @@ mod tests {
         let group = CheetahString::from("test_group");
         counter.increment_in_flight_message_num(&topic, &group, 1, 5);
         counter.clear_in_flight_message_num_by_topic_name(&topic);
-        assert_eq!(
-            counter.get_group_pop_in_flight_message_num(&topic, &group, 1),
-            0
-        );
+        assert_eq!(counter.get_in_flight_message_num(&topic, &group, 1), 0);
     }
 
     #[test]
@@ -269,10 +422,7 @@ mod tests {
         let group = CheetahString::from("test_group");
         counter.increment_in_flight_message_num(&topic, &group, 1, 5);
         counter.clear_in_flight_message_num(&topic, &group, 1);
-        assert_eq!(
-            counter.get_group_pop_in_flight_message_num(&topic, &group, 1),
-            0
-        );
+        assert_eq!(counter.get_in_flight_message_num(&topic, &group, 1), 0);
     }
 
     #[test]
@@ -296,9 +446,22 @@ mod tests {
         };
         counter.increment_in_flight_message_num(&topic, &group, 1, 5);
         counter.decrement_in_flight_message_num_checkpoint(&checkpoint);
-        assert_eq!(
-            counter.get_group_pop_in_flight_message_num(&topic, &group, 1),
-            4
-        );
+        assert_eq!(counter.get_in_flight_message_num(&topic, &group, 1), 4);
+    }
+
+    #[test]
+    fn decrement_on_drop_decrements_unless_cancelled() {
+        let counter = Arc::new(setup_counter());
+        let topic = CheetahString::from("test_topic");
+        let group = CheetahString::from("test_group");
+        counter.increment_in_flight_message_num(&topic, &group, 1, 5);
+
+        let decrement = counter.decrement_on_drop(&topic, &group, 0, 1, 2);
+        assert_eq!(counter.get_in_flight_message_num(&topic, &group, 1), 5);
+        drop(decrement);
+        assert_eq!(counter.get_in_flight_message_num(&topic, &group, 1), 3);
+
+        counter.decrement_on_drop(&topic, &group, 0, 1, 2).cancel();
+        assert_eq!(counter.get_in_flight_message_num(&topic, &group, 1), 3);
     }
 }
@@ -15,100 +15,824 @@
  * limitations under the License.
  */
 use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::ops::Range;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
+use bytes::Buf;
+use bytes::Bytes;
+use bytes::BytesMut;
 use cheetah_string::CheetahString;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::config::TopicConfig;
+use rocketmq_common::common::constant::consume_init_mode::ConsumeInitMode;
+use rocketmq_common::common::constant::PermName;
+use rocketmq_common::common::key_builder::KeyBuilder;
+use rocketmq_common::common::key_builder::POP_ORDER_REVIVE_QUEUE;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_single::tags_string2tags_code;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::pop_ack_constants::PopAckConstants;
+use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
+use rocketmq_common::common::FAQUrl;
+use rocketmq_common::utils::crc32_utils::crc32;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::filter::filter_api::FilterAPI;
+use rocketmq_remoting::protocol::header::extra_info_util::ExtraInfoUtil;
+use rocketmq_remoting::protocol::header::pop_message_request_header::PopMessageRequestHeader;
+use rocketmq_remoting::protocol::header::pop_message_response_header::PopMessageResponseHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
-use rocketmq_store::pop::batch_ack_msg::BatchAckMsg;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::filter::MessageFilter;
+use rocketmq_store::log_file::MessageStore;
 use rocketmq_store::pop::pop_check_point::PopCheckPoint;
-use rocketmq_store::pop::AckMessage;
 use tokio::sync::Mutex;
+use tracing::debug;
+use tracing::error;
 use tracing::info;
+use tracing::warn;
 
+use crate::broker_error::BrokerError::BrokerRemotingError;
+use crate::failover::escape_bridge::EscapeBridge;
+use crate::filter::expression_message_filter::ExpressionMessageFilter;
+use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
+use crate::long_polling::long_polling_service::pop_long_polling_service::PopLongPollingService;
+use crate::long_polling::long_polling_service::pop_long_polling_service::PopRequest;
+use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
+use crate::offset::manager::consumer_order_info_manager::ConsumerOrderInfoManager;
+use crate::processor::pop_consumer_flow_controller::PopConsumerFlowController;
+use crate::processor::pop_inflight_message_counter::PopInflightMessageCounter;
+use crate::processor::pop_revive_queue_selector::revive_queue_num_of;
+use crate::processor::processor_service::pop_buffer_merge_service::is_put_ok;
+use crate::processor::processor_service::pop_buffer_merge_service::PopBufferMergeService;
+use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
+use crate::topic::manager::topic_config_manager::TopicConfigManager;
+
+/// The most messages a single pop may ask for, and so the most a checkpoint holds.
+const MAX_POP_MSG_NUMS: u32 = 32;
+
+pub struct PopMessageProcessor<MS> {
+    broker_config: Arc<BrokerConfig>,
+    topic_config_manager: TopicConfigManager,
+    subscription_group_manager: Arc<SubscriptionGroupManager<MS>>,
+    consumer_offset_manager: Arc<ConsumerOffsetManager>,
+    consumer_order_info_manager: Arc<ConsumerOrderInfoManager<MS>>,
+    consumer_filter_manager: Arc<ConsumerFilterManager>,
+    message_store: ArcMut<MS>,
+    escape_bridge: ArcMut<EscapeBridge<MS>>,
+    pop_buffer_merge_service: ArcMut<PopBufferMergeService>,
+    pop_inflight_message_counter: Arc<PopInflightMessageCounter>,
+    pop_consumer_flow_controller: Arc<PopConsumerFlowController>,
+    queue_lock_manager: Arc<QueueLockManager>,
+    pop_long_polling_service: Arc<PopLongPollingService>,
+    store_host: SocketAddr,
+    ck_message_number: AtomicU64,
+}
+
+/// What a pop collected so far from the queues it read.
 #[derive(Default)]
-pub struct PopMessageProcessor {}
+struct PopResult {
+    body: BytesMut,
+    message_count: u32,
+    rest_num: i64,
+    start_offset_info: String,
+    msg_offset_info: String,
+    order_count_info: String,
+}
+
+impl<MS> PopMessageProcessor<MS>
+where
+    MS: MessageStore,
+{
+    pub fn new(
+        broker_config: Arc<BrokerConfig>,
+        topic_config_manager: TopicConfigManager,
+        subscription_group_manager: Arc<SubscriptionGroupManager<MS>>,
+        consumer_offset_manager: Arc<ConsumerOffsetManager>,
+        consumer_order_info_manager: Arc<ConsumerOrderInfoManager<MS>>,
+        consumer_filter_manager: Arc<ConsumerFilterManager>,
+        message_store: ArcMut<MS>,
+        escape_bridge: ArcMut<EscapeBridge<MS>>,
+        pop_buffer_merge_service: ArcMut<PopBufferMergeService>,
+        pop_inflight_message_counter: Arc<PopInflightMessageCounter>,
+        pop_consumer_flow_controller: Arc<PopConsumerFlowController>,
+        store_host: SocketAddr,
+    ) -> Self {
+        let pop_long_polling_service =
+            Arc::new(PopLongPollingService::new(broker_config.pop_polling_size));
+        PopMessageProcessor {
+            broker_config,
+            topic_config_manager,
+            subscription_group_manager,
+            consumer_offset_manager,
+            consumer_order_info_manager,
+            consumer_filter_manager,
+            message_store,
+            escape_bridge,
+            pop_buffer_merge_service,
+            pop_inflight_message_counter,
+            pop_consumer_flow_controller,
+            queue_lock_manager: Arc::new(QueueLockManager::new()),
+            pop_long_polling_service,
+            store_host,
+            ck_message_number: AtomicU64::new(0),
+        }
+    }
+
+    /// Starts cleaning the queue locks left unused and answering the held pops whose poll time
+    /// is over.
+    pub fn start(&self) {
+        self.queue_lock_manager.clone().start();
+        self.pop_long_polling_service.clone().start();
+    }
 
-impl PopMessageProcessor {
     pub async fn process_request(
         &mut self,
-        _channel: Channel,
-        _ctx: ConnectionHandlerContext,
+        channel: Channel,
+        ctx: ConnectionHandlerContext,
         _request_code: RequestCode,
-        _request: RemotingCommand,
+        request: RemotingCommand,
     ) -> crate::Result<Option<RemotingCommand>> {
-        unimplemented!("PopMessageProcessor process_request")
+        let mut request_header = request
+            .decode_command_custom_header::<PopMessageRequestHeader>()
+            .map_err(BrokerRemotingError)?;
+        let topic_config = self
+            .topic_config_manager
+            .select_topic_config(&request_header.topic);
+        if let Some(response) =
+            self.check_pop_request(&channel, &request_header, topic_config.as_ref())
+        {
+            return Ok(Some(response));
+        }
+        let Some(message_filter) = self.build_message_filter(&request_header) else {
+            return Ok(Some(
+                RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::SubscriptionParseFailed,
+                    "parse the consumer's subscription failed",
+                ),
+            ));
+        };
+        let topic_config = topic_config.unwrap();
+        request_header.max_msg_nums = flow_controlled_max_msg_nums(
+            &self.pop_consumer_flow_controller,
+            channel.remote_address(),
+            &request_header,
+        );
+        let response = self
+            .pop(&channel, &request_header, &topic_config, &message_filter)
+            .await;
+        // a pop finding no message waits for messages until its poll time is over
+        let expired = request_header.born_time + request_header.poll_time;
+        if response.code() != ResponseCode::PollingTimeout as i32
+            || request_header.poll_time == 0
+            || expired <= get_current_millis()
+        {
+            return Ok(Some(response));
+        }
+        let pop_request = PopRequest::new(request, channel, ctx, expired);
+        if !self.pop_long_polling_service.polling(
+            &request_header.topic,
+            request_header.queue_id,
+            pop_request,
+        ) {
+            return Ok(Some(
+                RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::PollingFull,
+                    "too many pops waiting for messages, pop again later",
+                ),
+            ));
+        }
+        Ok(None)
+    }
+
+    /// Pops again the pops held on `queue_id` of `topic` as messages arrived in it, a pop still
+    /// finding no message is held again.
+    pub fn notify_message_arriving(this: &ArcMut<Self>, topic: &CheetahString, queue_id: i32)
+    where
+        MS: Send + Sync + 'static,
+    {
+        for pop_request in this
+            .pop_long_polling_service
+            .notify_message_arriving(topic, queue_id)
+        {
+            let mut processor = this.clone();
+            tokio::spawn(async move {
+                let response = processor
+                    .process_request(
+                        pop_request.channel().clone(),
+                        pop_request.ctx().clone(),
+                        RequestCode::PopMessage,
+                        pop_request.request().clone(),
+                    )
+                    .await
+                    .unwrap_or_else(|error| {
+                        Some(RemotingCommand::create_response_command_with_code_remark(
+                            ResponseCode::SystemError,
+                            error.to_string(),
+                        ))
+                    });
+                if let Some(response) = response {
+                    processor
+                        .pop_long_polling_service
+                        .write_response(&pop_request, response)
+                        .await;
+                }
+            });
+        }
+    }
+
+    pub(crate) fn pop_long_polling_service(&self) -> &PopLongPollingService {
+        &self.pop_long_polling_service
     }
 
     pub fn queue_lock_manager(&self) -> &QueueLockManager {
-        unimplemented!("PopMessageProcessor QueueLockManager")
+        &self.queue_lock_manager
+    }
+
+    /// Rejects a pop request the broker can not serve, `topic_config` is the config of the
+    /// topic popped.
+    fn check_pop_request(
+        &self,
+        channel: &Channel,
+        request_header: &PopMessageRequestHeader,
+        topic_config: Option<&TopicConfig>,
+    ) -> Option<RemotingCommand> {
+        if !PermName::is_readable(self.broker_config.broker_permission) {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::NoPermission,
+                format!(
+                    "the broker[{}] popping message is forbidden",
+                    self.broker_config.broker_ip1
+                ),
+            ));
+        }
+        if request_header.max_msg_nums > MAX_POP_MSG_NUMS {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::MessageIllegal,
+                format!(
+                    "the broker[{}] pop message is illegal, the max message num is {}",
+                    self.broker_config.broker_ip1, MAX_POP_MSG_NUMS
+                ),
+            ));
+        }
+        let Some(topic_config) = topic_config else {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::TopicNotExist,
+                format!(
+                    "topic[{}] not exist, apply first please! {}",
+                    request_header.topic,
+                    FAQUrl::suggest_todo(FAQUrl::APPLY_TOPIC_URL)
+                ),
+            ));
+        };
+        if !PermName::is_readable(topic_config.perm) {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::NoPermission,
+                format!(
+                    "the topic[{}] popping message is forbidden",
+                    request_header.topic
+                ),
+            ));
+        }
+        if request_header.queue_id >= topic_config.read_queue_nums as i32 {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::MessageIllegal,
+                format!(
+                    "queueId[{}] is illegal, topic:[{}] topicConfig.readQueueNums:[{}] \
+                     consumer:[{}]",
+                    request_header.queue_id,
+                    request_header.topic,
+                    topic_config.read_queue_nums,
+                    channel.remote_address()
+                ),
+            ));
+        }
+        let Some(subscription_group_config) = self
+            .subscription_group_manager
+            .find_subscription_group_config(&request_header.consumer_group)
+        else {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SubscriptionGroupNotExist,
+                format!(
+                    "subscription group [{}] does not exist, {}",
+                    request_header.consumer_group,
+                    FAQUrl::suggest_todo(FAQUrl::SUBSCRIPTION_GROUP_NOT_EXIST)
+                ),
+            ));
+        };
+        if !subscription_group_config.consume_enable() {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::NoPermission,
+                format!(
+                    "subscription group no permission, {}",
+                    request_header.consumer_group
+                ),
+            ));
+        }
+        None
+    }
+
+    /// Builds the filter of the subscription carried by a pop request.
+    fn build_message_filter(
+        &self,
+        request_header: &PopMessageRequestHeader,
+    ) -> Option<ExpressionMessageFilter> {
+        let sub_string = request_header.exp.clone().unwrap_or_default();
+        let Ok(subscription_data) = FilterAPI::build(
+            &request_header.topic,
+            &sub_string,
+            request_header.exp_type.clone(),
+        ) else {
+            error!(
+                "parse the consumer's subscription[{}] error, group: {}",
+                sub_string, request_header.consumer_group
+            );
+            return None;
+        };
+        let consumer_filter_data = ConsumerFilterManager::build(
+            request_header.topic.clone(),
+            request_header.consumer_group.clone(),
+            request_header.exp.clone(),
+            request_header.exp_type.clone(),
+            request_header.born_time,
+        );
+        Some(ExpressionMessageFilter::new(
+            Some(subscription_data),
+            consumer_filter_data,
+            self.consumer_filter_manager.clone(),
+        ))
+    }
+
+    /// Pops the messages of the queues of a topic, then of its retry topic while the request
+    /// asks for more, and writes a checkpoint for every queue messages were taken from. An
+    /// orderly pop holds the queues it read in the consumer order info instead, and has no
+    /// retry topic as its messages are redelivered in place.
+    async fn pop(
+        &mut self,
+        channel: &Channel,
+        request_header: &PopMessageRequestHeader,
+        topic_config: &TopicConfig,
+        message_filter: &ExpressionMessageFilter,
+    ) -> RemotingCommand {
+        let pop_time = get_current_millis() as i64;
+        let order = request_header.order == Some(true);
+        let revive_qid = if order {
+            POP_ORDER_REVIVE_QUEUE
+        } else {
+            (self.ck_message_number.fetch_add(1, Ordering::Relaxed)
+                % revive_queue_num_of(Some(topic_config), self.broker_config.revive_queue_num)
+                    .max(1) as u64) as i32
+        };
+        let mut pop_result = PopResult::default();
+        let queue_ids = if request_header.queue_id < 0 {
+            (0..topic_config.read_queue_nums as i32).collect::<Vec<_>>()
+        } else {
+            vec![request_header.queue_id]
+        };
+        for queue_id in queue_ids {
+            self.pop_msg_from_queue(
+                channel,
+                request_header,
+                &request_header.topic,
+                queue_id,
+                Some(message_filter),
+                revive_qid,
+                pop_time,
+                &mut pop_result,
+            )
+            .await;
+        }
+        // retried messages passed the filter on their first delivery
+        let retry_topic = CheetahString::from_string(KeyBuilder::build_pop_retry_topic_default(
+            &request_header.topic,
+            &request_header.consumer_group,
+        ));
+        if let Some(retry_topic_config) = self
+            .topic_config_manager
+            .select_topic_config(&retry_topic)
+            .filter(|_| !order)
+        {
+            for queue_id in 0..retry_topic_config.read_queue_nums as i32 {
+                self.pop_msg_from_queue(
+                    channel,
+                    request_header,
+                    &retry_topic,
+                    queue_id,
+                    None,
+                    revive_qid,
+                    pop_time,
+                    &mut pop_result,
+                )
+                .await;
+            }
+        }
+        if pop_result.message_count == 0 {
+            return RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::PollingTimeout,
+                "no new message, pop again later",
+            );
+        }
+        let response_header = PopMessageResponseHeader {
+            pop_time: pop_time as u64,
+            invisible_time: request_header.invisible_time,
+            revive_qid: revive_qid as u32,
+            rest_num: pop_result.rest_num.max(0) as u64,
+            start_offset_info: Some(CheetahString::from_string(pop_result.start_offset_info)),
+            msg_offset_info: Some(CheetahString::from_string(pop_result.msg_offset_info)),
+            order_count_info: order
+                .then(|| CheetahString::from_string(pop_result.order_count_info)),
+        };
+        RemotingCommand::create_response_command()
+            .set_command_custom_header(response_header)
+            .set_body(pop_result.body.freeze())
+    }
+
+    /// Pops the messages of one queue matching `message_filter` into `pop_result`. A queue
+    /// locked by another pop, or held by another attempt of an orderly pop, is skipped, its
+    /// messages only count as left.
+    async fn pop_msg_from_queue(
+        &mut self,
+        channel: &Channel,
+        request_header: &PopMessageRequestHeader,
+        topic: &CheetahString,
+        queue_id: i32,
+        message_filter: Option<&dyn MessageFilter>,
+        revive_qid: i32,
+        pop_time: i64,
+        pop_result: &mut PopResult,
+    ) {
+        let group = &request_header.consumer_group;
+        let max_offset = self.message_store.get_max_offset_in_queue(topic, queue_id);
+        if pop_result.message_count >= request_header.max_msg_nums
+            || !self
+                .queue_lock_manager
+                .try_lock(topic, group, queue_id)
+                .await
+        {
+            let offset = self
+                .consumer_offset_manager
+                .query_offset(group, topic, queue_id);
+            pop_result.rest_num += max_offset - offset.max(0);
+            return;
+        }
+        let order = request_header.order == Some(true);
+        let attempt_id = request_header
+            .attempt_id
+            .as_ref()
+            .map_or("", |attempt_id| attempt_id.as_str());
+        if order
+            && self
+                .consumer_offset_manager
+                .has_offset_reset(group, topic, queue_id)
+        {
+            // the offsets handed out before the reset are consumed again
+            self.consumer_order_info_manager
+                .clear_block(topic, group, queue_id);
+        }
+        let offset = self.get_pop_offset(topic, group, queue_id, request_header.init_mode);
+        if order
+            && self.consumer_order_info_manager.check_block(
+                attempt_id,
+                topic,
+                group,
+                queue_id,
+                request_header.invisible_time,
+            )
+        {
+            pop_result.rest_num += max_offset - offset;
+            self.queue_lock_manager.unlock(topic, group, queue_id).await;
+            return;
+        }
+        let get_message_result = self
+            .message_store
+            .get_message(
+                group,
+                topic,
+                queue_id,
+                offset,
+                (request_header.max_msg_nums - pop_result.message_count) as i32,
+                i32::MAX,
+                None,
+            )
+            .await;
+        let Some(get_message_result) = get_message_result else {
+            pop_result.rest_num += max_offset - offset;
+            self.queue_lock_manager.unlock(topic, group, queue_id).await;
+            return;
+        };
+        let mut next_offset = get_message_result.next_begin_offset().max(offset);
+        let mut ck = PopCheckPoint {
+            start_offset: offset,
+            pop_time,
+            invisible_time: request_header.invisible_time as i64,
+            bit_map: 0,
+            num: 0,
+            queue_id,
+            topic: topic.clone(),
+            cid: group.clone(),
+            revive_offset: 0,
+            queue_offset_diff: vec![],
+            broker_name: Some(self.broker_config.broker_identity.broker_name.clone()),
+            re_put_times: None,
+        };
+        let mut body = BytesMut::new();
+        let mut msg_offsets = Vec::new();
+        for mapped in get_message_result.message_mapped_list() {
+            let Some(bytes) = mapped.get_bytes() else {
+                continue;
+            };
+            let Some(msg_ext) =
+                message_decoder::decode(&mut bytes.clone(), true, false, false, false, false)
+            else {
+                continue;
+            };
+            let matched = message_filter.map_or(true, |message_filter| {
+                is_pop_message_matched(
+                    message_filter,
+                    Some(tags_string2tags_code(msg_ext.get_tags().as_ref())),
+                    msg_ext.get_properties(),
+                )
+            });
+            if !matched {
+                continue;
+            }
+            // every message of an inner batch is acked on its own, the messages read before by
+            // a pop that had no room left for them are skipped
+            let entry_offsets = inner_queue_offsets(&msg_ext);
+            let first_offset = entry_offsets.start.max(offset);
+            if first_offset >= entry_offsets.end {
+                continue;
+            }
+            // the pop hands out no more than what was asked, or let through by flow control
+            let room = (request_header.max_msg_nums - pop_result.message_count) as i64
+                - msg_offsets.len() as i64;
+            if room <= 0 {
+                next_offset = first_offset;
+                break;
+            }
+            let taken_offsets = first_offset..entry_offsets.end.min(first_offset + room);
+            if taken_offsets == entry_offsets {
+                body.extend_from_slice(&bytes);
+            } else {
+                // only the messages the checkpoint records are handed out
+                let Some(cut) = cut_inner_batch(&bytes, taken_offsets.clone()) else {
+                    warn!(
+                        "can not cut the inner batch at offset {} of {}:{}, left to the next pop",
+                        entry_offsets.start, topic, queue_id
+                    );
+                    next_offset = first_offset;
+                    break;
+                };
+                body.extend_from_slice(&cut);
+            }
+            for queue_offset in taken_offsets.clone() {
+                ck.add_diff((queue_offset - offset) as i32);
+                ck.num += 1;
+                msg_offsets.push(queue_offset);
+            }
+            if taken_offsets.end < entry_offsets.end {
+                next_offset = taken_offsets.end;
+                break;
+            }
+        }
+        if order {
+            // the offset is committed as the messages are acked
+            if !msg_offsets.is_empty() {
+                self.consumer_order_info_manager.update(
+                    attempt_id,
+                    topic,
+                    group,
+                    queue_id,
+                    pop_time as u64,
+                    request_header.invisible_time,
+                    msg_offsets.iter().map(|offset| *offset as u64).collect(),
+                    &mut pop_result.order_count_info,
+                );
+            }
+        } else {
+            if !msg_offsets.is_empty() && !self.append_check_point(ck, revive_qid).await {
+                // the messages are not handed out without a checkpoint to revive them
+                self.queue_lock_manager.unlock(topic, group, queue_id).await;
+                return;
+            }
+            self.consumer_offset_manager.commit_offset(
+                channel.remote_address(),
+                group,
+                topic,
+                queue_id,
+                next_offset,
+            );
+        }
+        self.queue_lock_manager.unlock(topic, group, queue_id).await;
+        pop_result.rest_num += max_offset - next_offset;
+        if msg_offsets.is_empty() {
+            return;
+        }
+        self.pop_inflight_message_counter
+            .increment_in_flight_message_num(topic, group, queue_id, msg_offsets.len() as i64);
+        ExtraInfoUtil::build_start_offset_info(
+            &mut pop_result.start_offset_info,
+            topic,
+            queue_id,
+            offset,
+        );
+        pop_result.message_count += msg_offsets.len() as u32;
+        ExtraInfoUtil::build_msg_offset_info(
+            &mut pop_result.msg_offset_info,
+            topic,
+            queue_id,
+            msg_offsets,
+        );
+        pop_result.body.extend_from_slice(&body);
+    }
+
+    /// Returns the offset a pop reads a queue from: the committed offset of the group, or the
+    /// one given by `init_mode` for a group that never consumed the queue.
+    fn get_pop_offset(
+        &self,
+        topic: &CheetahString,
+        group: &CheetahString,
+        queue_id: i32,
+        init_mode: i32,
+    ) -> i64 {
+        let offset = self
+            .consumer_offset_manager
+            .query_offset(group, topic, queue_id);
+        if offset >= 0 {
+            return offset;
+        }
+        if init_mode == ConsumeInitMode::MIN {
+            self.message_store.get_min_offset_in_queue(topic, queue_id)
+        } else {
+            // the last message is popped, there is none in an empty queue
+            (self.message_store.get_max_offset_in_queue(topic, queue_id) - 1).max(0)
+        }
+    }
+
+    /// Buffers the checkpoint of a pop, or writes it to the revive topic when it can not be
+    /// buffered. Returns `false` if the checkpoint could not be written.
+    async fn append_check_point(&mut self, ck: PopCheckPoint, revive_qid: i32) -> bool {
+        if self
+            .pop_buffer_merge_service
+            .add_ck(ck.clone(), revive_qid, -1, false)
+        {
+            return true;
+        }
+        let inner = self.pop_buffer_merge_service.build_ck_msg(&ck, revive_qid);
+        let status = self
+            .escape_bridge
+            .put_message_to_specific_queue(inner)
+            .await
+            .put_message_status();
+        if !is_put_ok(status) {
+            error!(
+                "put ck to revive topic error, {}, status: {:?}",
+                gen_ck_unique_id(&ck),
+                status
+            );
+            return false;
+        }
+        true
     }
 }
 
-impl PopMessageProcessor {
-    pub fn gen_ack_unique_id(ack_msg: &dyn AckMessage) -> String {
-        format!(
-            "{}{}{}{}{}{}{}{}{}{}{}{}{}",
-            ack_msg.topic(),
-            PopAckConstants::SPLIT,
-            ack_msg.queue_id(),
-            PopAckConstants::SPLIT,
-            ack_msg.ack_offset(),
-            PopAckConstants::SPLIT,
-            ack_msg.consumer_group(),
-            PopAckConstants::SPLIT,
-            ack_msg.pop_time(),
-            PopAckConstants::SPLIT,
-            ack_msg.broker_name(),
-            PopAckConstants::SPLIT,
-            PopAckConstants::ACK_TAG
-        )
+/// Returns the number of messages to deliver for a pop request, limited by the processing
+/// capacity the consumer advertises and by the messages it has not acked yet.
+pub(crate) fn flow_controlled_max_msg_nums(
+    pop_consumer_flow_controller: &PopConsumerFlowController,
+    consumer: SocketAddr,
+    request_header: &PopMessageRequestHeader,
+) -> u32 {
+    let max_msg_nums = pop_consumer_flow_controller.cap_max_msg_nums(
+        &request_header.consumer_group,
+        consumer,
+        request_header.max_msg_nums,
+        request_header.processing_capacity,
+    );
+    if max_msg_nums < request_header.max_msg_nums {
+        debug!(
+            "pop flow controlled, group={}, consumer={}, maxMsgNums {} -> {}, ackRate={:.1}/s",
+            request_header.consumer_group,
+            consumer,
+            request_header.max_msg_nums,
+            max_msg_nums,
+            pop_consumer_flow_controller.ack_rate(&request_header.consumer_group, consumer)
+        );
     }
+    max_msg_nums
+}
 
-    pub fn gen_batch_ack_unique_id(batch_ack_msg: &BatchAckMsg) -> String {
-        format!(
-            "{}{}{}{}{:?}{}{}{}{}{}{}",
-            batch_ack_msg.ack_msg.topic,
-            PopAckConstants::SPLIT,
-            batch_ack_msg.ack_msg.queue_id,
-            PopAckConstants::SPLIT,
-            batch_ack_msg.ack_offset_list,
-            PopAckConstants::SPLIT,
-            batch_ack_msg.ack_msg.consumer_group,
-            PopAckConstants::SPLIT,
-            batch_ack_msg.ack_msg.pop_time,
-            PopAckConstants::SPLIT,
-            PopAckConstants::BATCH_ACK_TAG
-        )
+/// Tells whether a message read for a pop consumer is delivered to it. The consume queue
+/// check covers tag subscriptions, a SQL92 subscription is evaluated against the message
+/// properties.
+pub(crate) fn is_pop_message_matched(
+    message_filter: &dyn MessageFilter,
+    tags_code: Option<i64>,
+    properties: &HashMap<CheetahString, CheetahString>,
+) -> bool {
+    message_filter.is_matched_by_consume_queue(tags_code, None)
+        && message_filter.is_matched_by_commit_log(None, Some(properties))
+}
+
+/// Returns the queue offsets of the messages held by a stored message: an inner batch holds
+/// `INNER_NUM` messages from its `INNER_BASE` offset, any other message only itself.
+///
+/// Only a message flagged as an inner batch is taken as one. The batch consume queue keeps the
+/// message count of a batch in a short, a message whose `INNER_NUM` does not fit in one is taken
+/// as a single message.
+pub(crate) fn inner_queue_offsets(msg_ext: &MessageExt) -> Range<i64> {
+    if !MessageSysFlag::check(msg_ext.sys_flag, MessageSysFlag::INNER_BATCH_FLAG) {
+        return msg_ext.queue_offset..msg_ext.queue_offset + 1;
+    }
+    let property = |name: &'static str| {
+        msg_ext
+            .get_property(&CheetahString::from_static_str(name))
+            .and_then(|value| value.parse::<i64>().ok())
+    };
+    let num = property(MessageConst::PROPERTY_INNER_NUM)
+        .filter(|num| (2..=i16::MAX as i64).contains(num));
+    let base = property(MessageConst::PROPERTY_INNER_BASE)
+        .filter(|base| *base >= 0)
+        .unwrap_or(msg_ext.queue_offset);
+    match num.and_then(|num| base.checked_add(num)) {
+        Some(end) => base..end,
+        None => msg_ext.queue_offset..msg_ext.queue_offset + 1,
     }
+}
 
-    pub fn gen_ck_unique_id(ck: &PopCheckPoint) -> String {
-        format!(
-            "{}{}{}{}{}{}{}{}{}{}{}{}{}",
-            ck.topic,
-            PopAckConstants::SPLIT,
-            ck.queue_id,
-            PopAckConstants::SPLIT,
-            ck.start_offset,
-            PopAckConstants::SPLIT,
-            ck.cid,
-            PopAckConstants::SPLIT,
-            ck.pop_time,
-            PopAckConstants::SPLIT,
-            ck.broker_name
-                .as_ref()
-                .map_or("null".to_string(), |x| x.to_string()),
-            PopAckConstants::SPLIT,
-            PopAckConstants::CK_TAG
-        )
+/// Cuts the stored inner batch `bytes` down to its messages at `queue_offsets`, re-encoded as
+/// an inner batch of its own with an uncompressed body. Returns `None` if the body doesn't hold
+/// the messages its `INNER_NUM` tells.
+pub(crate) fn cut_inner_batch(bytes: &Bytes, queue_offsets: Range<i64>) -> Option<Bytes> {
+    let mut msg_ext = message_decoder::decode(&mut bytes.clone(), true, true, false, false, false)?;
+    let entry_offsets = inner_queue_offsets(&msg_ext);
+    if queue_offsets.start < entry_offsets.start || queue_offsets.end > entry_offsets.end {
+        return None;
+    }
+    let mut body = msg_ext.get_body()?.clone();
+    let mut inner_messages = Vec::new();
+    while body.has_remaining() {
+        if body.len() < 4 {
+            return None;
+        }
+        let size = (&body[..4]).get_i32();
+        if size < 4 || size as usize > body.len() {
+            return None;
+        }
+        inner_messages.push(body.split_to(size as usize));
+    }
+    if inner_messages.len() as i64 != entry_offsets.end - entry_offsets.start {
+        return None;
     }
+    let cut_body = inner_messages[(queue_offsets.start - entry_offsets.start) as usize
+        ..(queue_offsets.end - entry_offsets.start) as usize]
+        .concat();
+    msg_ext.set_body_crc(crc32(&cut_body));
+    msg_ext.set_body(Bytes::from(cut_body));
+    msg_ext.set_sys_flag(msg_ext.sys_flag & !MessageSysFlag::COMPRESSED_FLAG);
+    msg_ext.queue_offset = queue_offsets.start;
+    msg_ext.put_property(
+        CheetahString::from_static_str(MessageConst::PROPERTY_INNER_NUM),
+        CheetahString::from_string((queue_offsets.end - queue_offsets.start).to_string()),
+    );
+    msg_ext.put_property(
+        CheetahString::from_static_str(MessageConst::PROPERTY_INNER_BASE),
+        CheetahString::from_string(queue_offsets.start.to_string()),
+    );
+    // the stored size leads the encoded message
+    msg_ext.store_size = 0;
+    msg_ext.store_size = message_decoder::encode(&msg_ext, false).ok()?.len() as i32;
+    message_decoder::encode(&msg_ext, false).ok()
+}
+
+pub fn gen_ck_unique_id(ck: &PopCheckPoint) -> String {
+    format!(
+        "{}{}{}{}{}{}{}{}{}{}{}{}{}",
+        ck.topic,
+        PopAckConstants::SPLIT,
+        ck.queue_id,
+        PopAckConstants::SPLIT,
+        ck.start_offset,
+        PopAckConstants::SPLIT,
+        ck.cid,
+        PopAckConstants::SPLIT,
+        ck.pop_time,
+        PopAckConstants::SPLIT,
+        ck.broker_name
+            .as_ref()
+            .map_or("null".to_string(), |x| x.to_string()),
+        PopAckConstants::SPLIT,
+        PopAckConstants::CK_TAG
+    )
 }
 
 struct TimedLock {
@@ -231,45 +955,162 @@ impl QueueLockManager {
 
 #[cfg(test)]
 mod tests {
+    use std::any::Any;
+    use std::error::Error;
+
     use cheetah_string::CheetahString;
-    use rocketmq_store::pop::ack_msg::AckMsg;
+    use rocketmq_common::common::filter::expression_type::ExpressionType;
+    use rocketmq_filter::expression::evaluation_context::EvaluationContext;
+    use rocketmq_filter::expression::Expression;
+    use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
 
     use super::*;
+    use crate::filter::consumer_filter_data::ConsumerFilterData;
+    use crate::filter::expression_message_filter::ExpressionMessageFilter;
+    use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
+    use crate::processor::ack_message_processor::tests::TestBroker;
+    use crate::processor::ack_message_processor::tests::TEST_GROUP;
+    use crate::processor::ack_message_processor::tests::TEST_TOPIC;
 
-    #[test]
-    fn gen_ack_unique_id_formats_correctly() {
-        let ack_msg = AckMsg {
-            ack_offset: 123,
-            start_offset: 456,
-            consumer_group: CheetahString::from_static_str("test_group"),
-            topic: CheetahString::from_static_str("test_topic"),
-            queue_id: 1,
-            pop_time: 789,
-            broker_name: CheetahString::from_static_str("test_broker"),
+    /// Compiled form of `property BETWEEN low AND high`, null when the property is missing.
+    struct Between {
+        property: &'static str,
+        low: i64,
+        high: i64,
+    }
+
+    impl Expression for Between {
+        fn evaluate(
+            &self,
+            context: &dyn EvaluationContext,
+        ) -> Result<Box<dyn Any>, Box<dyn Error>> {
+            let value = context
+                .get(self.property)
+                .and_then(|value| value.downcast_ref::<CheetahString>())
+                .map(|value| value.parse::<i64>())
+                .transpose()?;
+            Ok(match value {
+                Some(value) => Box::new((self.low..=self.high).contains(&value)),
+                None => Box::new(()),
+            })
+        }
+    }
+
+    fn sql92_filter(expression: &str, compiled: Between) -> ExpressionMessageFilter {
+        let subscription_data = SubscriptionData {
+            sub_string: CheetahString::from(expression),
+            expression_type: CheetahString::from_static_str(ExpressionType::SQL92),
+            ..Default::default()
         };
-        let result = PopMessageProcessor::gen_ack_unique_id(&ack_msg);
-        let expected = "test_topic@1@123@test_group@789@test_broker@ack";
-        assert_eq!(result, expected);
+        let mut consumer_filter_data = ConsumerFilterData::default();
+        consumer_filter_data.set_expression(Some(CheetahString::from(expression)));
+        consumer_filter_data
+            .set_expression_type(Some(CheetahString::from_static_str(ExpressionType::SQL92)));
+        consumer_filter_data.set_compiled_expression(Some(Arc::new(Box::new(compiled))));
+        ExpressionMessageFilter::new(
+            Some(subscription_data),
+            Some(consumer_filter_data),
+            Arc::new(ConsumerFilterManager::default()),
+        )
+    }
+
+    fn properties(a: Option<&str>) -> HashMap<CheetahString, CheetahString> {
+        let mut properties = HashMap::new();
+        properties.insert(CheetahString::from("b"), CheetahString::from("3"));
+        if let Some(a) = a {
+            properties.insert(CheetahString::from("a"), CheetahString::from(a));
+        }
+        properties
     }
 
     #[test]
-    fn gen_batch_ack_unique_id_formats_correctly() {
-        let ack_msg = AckMsg {
-            ack_offset: 123,
-            start_offset: 456,
+    fn sql92_subscription_filters_pop_messages_by_properties() {
+        let filter = sql92_filter(
+            "a BETWEEN 1 AND 5",
+            Between {
+                property: "a",
+                low: 1,
+                high: 5,
+            },
+        );
+        for (a, matched) in [
+            ("1", true),
+            ("3", true),
+            ("5", true),
+            ("0", false),
+            ("6", false),
+        ] {
+            assert_eq!(
+                is_pop_message_matched(&filter, Some(0), &properties(Some(a))),
+                matched,
+                "a = {}",
+                a
+            );
+        }
+        // a message without the referenced property does not match
+        assert!(!is_pop_message_matched(&filter, Some(0), &properties(None)));
+        // nor does one whose property can not be evaluated
+        assert!(!is_pop_message_matched(
+            &filter,
+            Some(0),
+            &properties(Some("x"))
+        ));
+    }
+
+    #[test]
+    fn low_capacity_signal_reduces_delivered_message_count() {
+        let pop_consumer_flow_controller = PopConsumerFlowController::default();
+        let consumer: SocketAddr = "127.0.0.1:20000".parse().unwrap();
+        let mut request_header = PopMessageRequestHeader {
             consumer_group: CheetahString::from_static_str("test_group"),
             topic: CheetahString::from_static_str("test_topic"),
-            queue_id: 1,
-            pop_time: 789,
-            broker_name: CheetahString::from_static_str("test_broker"),
+            max_msg_nums: 32,
+            ..Default::default()
         };
-        let batch_ack_msg = BatchAckMsg {
-            ack_msg,
-            ack_offset_list: vec![1, 2, 3],
+        assert_eq!(
+            flow_controlled_max_msg_nums(&pop_consumer_flow_controller, consumer, &request_header),
+            32
+        );
+
+        request_header.processing_capacity = Some(4);
+        assert_eq!(
+            flow_controlled_max_msg_nums(&pop_consumer_flow_controller, consumer, &request_header),
+            4
+        );
+    }
+
+    #[test]
+    fn inner_num_out_of_range_is_taken_as_a_single_message() {
+        let inner_batch = |inner_num: &str| {
+            let mut msg_ext = MessageExt {
+                queue_offset: 7,
+                sys_flag: MessageSysFlag::INNER_BATCH_FLAG,
+                ..MessageExt::default()
+            };
+            msg_ext.put_property(
+                CheetahString::from_static_str(MessageConst::PROPERTY_INNER_NUM),
+                CheetahString::from_string(inner_num.to_string()),
+            );
+            msg_ext.put_property(
+                CheetahString::from_static_str(MessageConst::PROPERTY_INNER_BASE),
+                CheetahString::from_static_str("7"),
+            );
+            msg_ext
         };
-        let result = PopMessageProcessor::gen_batch_ack_unique_id(&batch_ack_msg);
-        let expected = "test_topic@1@[1, 2, 3]@test_group@789@bAck";
-        assert_eq!(result, expected);
+        assert_eq!(inner_queue_offsets(&inner_batch("40")), 7..47);
+        for inner_num in ["-3", "0", "x", "40000", &i64::MAX.to_string()] {
+            assert_eq!(
+                inner_queue_offsets(&inner_batch(inner_num)),
+                7..8,
+                "INNER_NUM = {}",
+                inner_num
+            );
+        }
+
+        // the properties of a message not flagged as an inner batch are not trusted
+        let mut unflagged = inner_batch("40");
+        unflagged.sys_flag = 0;
+        assert_eq!(inner_queue_offsets(&unflagged), 7..8);
     }
 
     #[test]
@@ -288,7 +1129,7 @@ mod tests {
             queue_offset_diff: vec![],
             re_put_times: None,
         };
-        let result = PopMessageProcessor::gen_ck_unique_id(&ck);
+        let result = gen_ck_unique_id(&ck);
         let expected = "test_topic@1@456@test_cid@789@test_broker@ck";
         assert_eq!(result, expected);
     }
@@ -390,4 +1231,117 @@ mod tests {
         let removed_count = manager.clean_unused_locks(15).await;
         assert_eq!(removed_count, 0);
     }
+
+    #[test]
+    fn pop_of_empty_queue_is_held_until_messages_arrive() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        let pop_message_processor = broker.pop_message_processor();
+
+        let response = broker.try_pop_with(PopMessageRequestHeader {
+            consumer_group: CheetahString::from_static_str(TEST_GROUP),
+            topic: CheetahString::from_static_str(TEST_TOPIC),
+            queue_id: 0,
+            max_msg_nums: 32,
+            invisible_time: 30_000,
+            poll_time: 60_000,
+            born_time: get_current_millis(),
+            ..Default::default()
+        });
+        assert!(response.is_none());
+        assert_eq!(
+            pop_message_processor
+                .pop_long_polling_service()
+                .polling_num(),
+            1
+        );
+
+        // an arrival in another queue leaves the pop waiting
+        broker.runtime.block_on(async {
+            PopMessageProcessor::notify_message_arriving(
+                &pop_message_processor,
+                &CheetahString::from_static_str(TEST_TOPIC),
+                1,
+            );
+        });
+        assert_eq!(
+            pop_message_processor
+                .pop_long_polling_service()
+                .polling_num(),
+            1
+        );
+
+        broker.store_messages(&["TagA"; 2]);
+        broker.runtime.block_on(async {
+            PopMessageProcessor::notify_message_arriving(
+                &pop_message_processor,
+                &CheetahString::from_static_str(TEST_TOPIC),
+                0,
+            );
+            // the pop is popped again on a task of its own
+            while broker.message_store.put_messages().is_empty() {
+                tokio::task::yield_now().await;
+            }
+        });
+        assert_eq!(
+            pop_message_processor
+                .pop_long_polling_service()
+                .polling_num(),
+            0
+        );
+        let revive_topic = PopAckConstants::build_cluster_revive_topic(
+            broker
+                .broker_config
+                .broker_identity
+                .broker_cluster_name
+                .as_str(),
+        );
+        let stored = broker.message_store.put_messages_of(&revive_topic);
+        assert_eq!(stored.len(), 1);
+        assert_eq!(
+            stored[0].get_tags().unwrap().as_str(),
+            PopAckConstants::CK_TAG
+        );
+    }
+
+    #[test]
+    fn held_pop_is_answered_when_its_poll_time_is_over() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+        let pop_message_processor = broker.pop_message_processor();
+        let born_time = get_current_millis();
+
+        let response = broker.try_pop_with(PopMessageRequestHeader {
+            consumer_group: CheetahString::from_static_str(TEST_GROUP),
+            topic: CheetahString::from_static_str(TEST_TOPIC),
+            queue_id: 0,
+            max_msg_nums: 32,
+            invisible_time: 30_000,
+            poll_time: 100,
+            born_time,
+            ..Default::default()
+        });
+        assert!(response.is_none());
+
+        let polling_service = pop_message_processor.pop_long_polling_service();
+        assert!(polling_service.take_expired(born_time + 99).is_empty());
+        assert_eq!(polling_service.take_expired(born_time + 100).len(), 1);
+        assert_eq!(polling_service.polling_num(), 0);
+    }
+
+    #[test]
+    fn pop_past_its_poll_time_is_not_held() {
+        let mut broker = TestBroker::new(BrokerConfig::default());
+
+        let response = broker.pop_with(PopMessageRequestHeader {
+            consumer_group: CheetahString::from_static_str(TEST_GROUP),
+            topic: CheetahString::from_static_str(TEST_TOPIC),
+            queue_id: 0,
+            max_msg_nums: 32,
+            invisible_time: 30_000,
+            poll_time: 100,
+            born_time: get_current_millis() - 1_000,
+            ..Default::default()
+        });
+
+        assert_eq!(response.code(), ResponseCode::PollingTimeout as i32);
+    }
 }
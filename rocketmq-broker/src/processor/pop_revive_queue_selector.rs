@@ -0,0 +1,138 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use rocketmq_common::common::config::TopicConfig;
+use rocketmq_common::common::hasher::string_hasher::JavaStringHasher;
+use rocketmq_common::TopicAttributes;
+
+/// Returns whether the revive queue of the acks of `topic_config` is selected by the shard key
+/// of the acked message instead of the revive queue id carried in the receipt handle.
+pub(crate) fn is_revive_sharding_enabled(topic_config: &TopicConfig) -> bool {
+    topic_config
+        .attributes
+        .get(TopicAttributes::REVIVE_SHARDING_ATTRIBUTE.get_name())
+        .is_some_and(|value| value.eq_ignore_ascii_case("true"))
+}
+
+/// Returns the number of revive queues the checkpoints and acks of `topic_config` are spread
+/// over: the `revive.queue.num` attribute of the topic, or `default_num`, the revive queue count
+/// of the broker, when the topic has none.
+pub(crate) fn revive_queue_num_of(topic_config: Option<&TopicConfig>, default_num: u32) -> u32 {
+    topic_config
+        .and_then(|topic_config| {
+            topic_config
+                .attributes
+                .get(TopicAttributes::REVIVE_QUEUE_NUM_ATTRIBUTE.get_name())
+        })
+        .and_then(|value| TopicAttributes::REVIVE_QUEUE_NUM_ATTRIBUTE.parse(value))
+        .filter(|revive_queue_num| *revive_queue_num > 0)
+        .map_or(default_num, |revive_queue_num| revive_queue_num as u32)
+}
+
+/// Maps a shard key onto one of `revive_queue_num` revive queues.
+///
+/// The checkpoint of a popped message and its acks are reconciled per revive queue, so the
+/// revive reader and whoever writes the checkpoint must select the queue with this very
+/// function for a sharded topic.
+pub(crate) fn select_revive_queue_id(shard_key: &str, revive_queue_num: u32) -> i32 {
+    if revive_queue_num == 0 {
+        return 0;
+    }
+    let hash = JavaStringHasher::new().hash_str(shard_key);
+    (hash.unsigned_abs() % revive_queue_num) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use cheetah_string::CheetahString;
+
+    use super::*;
+
+    #[test]
+    fn same_shard_key_selects_same_revive_queue() {
+        let first = select_revive_queue_id("order-42", 8);
+        for _ in 0..10 {
+            assert_eq!(select_revive_queue_id("order-42", 8), first);
+        }
+        assert!((0..8).contains(&first));
+    }
+
+    #[test]
+    fn shard_keys_spread_over_revive_queues() {
+        let selected = (0..100)
+            .map(|index| select_revive_queue_id(&format!("key-{}", index), 8))
+            .collect::<std::collections::HashSet<_>>();
+        assert!(selected.len() > 1);
+        assert!(selected.iter().all(|qid| (0..8).contains(qid)));
+    }
+
+    #[test]
+    fn zero_revive_queue_num_selects_first_queue() {
+        assert_eq!(select_revive_queue_id("order-42", 0), 0);
+    }
+
+    #[test]
+    fn revive_sharding_is_disabled_by_default() {
+        let mut topic_config = TopicConfig::default();
+        assert!(!is_revive_sharding_enabled(&topic_config));
+        topic_config.attributes.insert(
+            CheetahString::from(TopicAttributes::REVIVE_SHARDING_ATTRIBUTE.get_name()),
+            CheetahString::from_static_str("true"),
+        );
+        assert!(is_revive_sharding_enabled(&topic_config));
+    }
+
+    fn topic_config_with_revive_queue_num(value: &str) -> TopicConfig {
+        let mut topic_config = TopicConfig::default();
+        topic_config.attributes.insert(
+            CheetahString::from(TopicAttributes::REVIVE_QUEUE_NUM_ATTRIBUTE.get_name()),
+            CheetahString::from(value),
+        );
+        topic_config
+    }
+
+    #[test]
+    fn revive_queue_num_defaults_to_broker_setting() {
+        assert_eq!(revive_queue_num_of(None, 8), 8);
+        assert_eq!(revive_queue_num_of(Some(&TopicConfig::default()), 8), 8);
+        assert_eq!(
+            revive_queue_num_of(Some(&topic_config_with_revive_queue_num("0")), 8),
+            8
+        );
+    }
+
+    #[test]
+    fn revive_queue_num_of_topic_overrides_broker_setting() {
+        assert_eq!(
+            revive_queue_num_of(Some(&topic_config_with_revive_queue_num("32")), 8),
+            32
+        );
+        assert_eq!(
+            revive_queue_num_of(Some(&topic_config_with_revive_queue_num("2")), 8),
+            2
+        );
+    }
+
+    #[test]
+    fn malformed_revive_queue_num_falls_back_to_broker_setting() {
+        for value in ["-4", "many", "100000"] {
+            assert_eq!(
+                revive_queue_num_of(Some(&topic_config_with_revive_queue_num(value)), 8),
+                8
+            );
+        }
+    }
+}
@@ -15,4 +15,5 @@
  * limitations under the License.
  */
 pub(crate) mod pop_buffer_merge_service;
+pub(crate) mod pop_buffer_wal;
 pub(super) mod pop_revive_service;
@@ -14,13 +14,1212 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI32;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
 
+use bytes::Bytes;
+use cheetah_string::CheetahString;
+use dashmap::DashMap;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::pop_ack_constants::PopAckConstants;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::protocol::RemotingSerializable;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::base::message_status_enum::PutMessageStatus;
+use rocketmq_store::log_file::MessageStore;
+use rocketmq_store::pop::ack_msg::AckMsg;
+use rocketmq_store::pop::batch_ack_msg::BatchAckMsg;
+use rocketmq_store::pop::pop_check_point::PopCheckPoint;
+use rocketmq_store::pop::set_revive_body_crc;
+use rocketmq_store::pop::set_revive_host_flags;
 use rocketmq_store::pop::AckMessage;
+use tokio::sync::Notify;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
 
-pub(crate) struct PopBufferMergeService;
+use crate::broker_path_config_helper::get_pop_buffer_wal_path;
+use crate::failover::escape_bridge::EscapeBridge;
+use crate::processor::pop_message_processor::gen_ck_unique_id;
+use crate::processor::processor_service::pop_buffer_wal::PopBufferWal;
+use crate::processor::processor_service::pop_buffer_wal::PopBufferWalRecord;
+use crate::processor::processor_service::pop_buffer_wal::PopBufferWalWriter;
+
+const SCAN_INTERVAL_MILLIS: u64 = 5;
+/// Records appended to the write-ahead log before it is rewritten with a snapshot of the buffer.
+const WAL_COMPACT_RECORDS: usize = 10_000;
+
+/// Merges the acks of popped messages into their checkpoints in memory, so that a checkpoint
+/// acked before it has to be revived never reaches the revive topic.
+///
+/// Checkpoints are buffered by the pop path with [`add_ck`](Self::add_ck) and acks are merged into
+/// them with [`add_ack`](Self::add_ack). A background task flushes a checkpoint once it is fully
+/// acked or has stayed `popCkStayBufferTime` in the buffer: a fully acked checkpoint that was
+/// never stored is just dropped, any other one is written to the revive topic together with the
+/// acks merged into it so far.
+///
+/// When more than `popCkBufferAckHighWaterMark` acks are held in the buffer, its oldest
+/// checkpoints are flushed early and nothing more is buffered until fewer than
+/// `popCkBufferAckLowWaterMark` acks are left, so a burst of acks cannot exhaust the memory.
+///
+/// On broker shutdown the buffer is [`drain`](Self::drain)ed: nothing is buffered any more and
+/// every checkpoint left is written to the revive topic before the store is closed.
+///
+/// With `enablePopBufferMergeWal` every change of the buffer is appended to a write-ahead log
+/// first, a crashed broker [`recover`](Self::recover)s its buffer from it on startup.
+pub(crate) struct PopBufferMergeService {
+    broker_config: Arc<BrokerConfig>,
+    buffer: DashMap<CheetahString, Arc<PopCheckPointWrapper>>,
+    revive_topic: CheetahString,
+    store_host: SocketAddr,
+    shutdown: Arc<Notify>,
+    draining: AtomicBool,
+    under_pressure: AtomicBool,
+    wal: Option<PopBufferWal>,
+}
+
+/// A checkpoint taken out of the buffer, with what is left to write to the revive topic.
+pub(crate) struct PopBufferFlush {
+    wrapper: Arc<PopCheckPointWrapper>,
+    store_ck: bool,
+    ack_offsets: Vec<i64>,
+}
 
 impl PopBufferMergeService {
-    pub fn add_ack(&mut self, _revive_qid: i32, _ack_msg: &dyn AckMessage) -> bool {
-        unimplemented!("Not implemented yet");
+    pub fn new(broker_config: Arc<BrokerConfig>, store_host: SocketAddr) -> Self {
+        let revive_topic = CheetahString::from_string(PopAckConstants::build_cluster_revive_topic(
+            broker_config.broker_identity.broker_cluster_name.as_str(),
+        ));
+        let wal = if broker_config.enable_pop_buffer_merge_wal {
+            let path = get_pop_buffer_wal_path(broker_config.store_path_root_dir.as_str());
+            match PopBufferWal::open(&path) {
+                Ok(wal) => Some(wal),
+                Err(e) => {
+                    error!("PopBufferMergeService: open wal {} error, {}", path, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        PopBufferMergeService {
+            broker_config,
+            buffer: DashMap::new(),
+            revive_topic,
+            store_host,
+            shutdown: Arc::new(Notify::new()),
+            draining: AtomicBool::new(false),
+            under_pressure: AtomicBool::new(false),
+            wal,
+        }
+    }
+
+    /// Rebuilds the buffer from the write-ahead log left by the previous run, returns the number
+    /// of checkpoints buffered again.
+    ///
+    /// Checkpoints and acks the log records as written to the revive topic are not buffered
+    /// again, so nothing is written to the revive topic twice.
+    pub fn recover(&self) -> usize {
+        let Some(wal) = &self.wal else {
+            return 0;
+        };
+        let mut wal_writer = wal.lock();
+        let records = match wal.read_records() {
+            Ok(records) => records,
+            Err(e) => {
+                error!("PopBufferMergeService: read wal error, {}", e);
+                return 0;
+            }
+        };
+        let mut wrappers = HashMap::new();
+        for record in records {
+            match record {
+                PopBufferWalRecord::Ck {
+                    revive_queue_id,
+                    revive_queue_offset,
+                    just_offset,
+                    ck,
+                } => {
+                    let wrapper = PopCheckPointWrapper::new(
+                        revive_queue_id,
+                        revive_queue_offset,
+                        ck,
+                        just_offset,
+                    );
+                    wrappers.insert(wrapper.merge_key().clone(), wrapper);
+                }
+                PopBufferWalRecord::Ack {
+                    merge_key,
+                    ack_offsets,
+                } => {
+                    if let Some(wrapper) = wrappers.get(&merge_key) {
+                        for offset in ack_offsets {
+                            wrapper.mark_acked(offset);
+                        }
+                    }
+                }
+                PopBufferWalRecord::CkStored { merge_key } => {
+                    if let Some(wrapper) = wrappers.get(&merge_key) {
+                        wrapper.set_ck_stored(true);
+                    }
+                }
+                PopBufferWalRecord::AckStored {
+                    merge_key,
+                    ack_offsets,
+                } => {
+                    if let Some(wrapper) = wrappers.get(&merge_key) {
+                        for offset in ack_offsets {
+                            let index = wrapper.ck().index_of_ack(offset);
+                            if (0..i32::BITS as i32).contains(&index) {
+                                mark_bit_cas(wrapper.to_store_bits(), index as u32);
+                            }
+                        }
+                    }
+                }
+                PopBufferWalRecord::Removed { merge_key } => {
+                    wrappers.remove(&merge_key);
+                }
+            }
+        }
+        let mut recovered = 0;
+        for (merge_key, wrapper) in wrappers {
+            // a stored checkpoint without pending acks is already in the revive topic as a whole
+            if wrapper.is_ck_stored() && wrapper.pending_ack_offsets().is_empty() {
+                continue;
+            }
+            self.buffer.insert(merge_key, Arc::new(wrapper));
+            recovered += 1;
+        }
+        self.rewrite_wal(&mut wal_writer);
+        info!(
+            "PopBufferMergeService: recovered {} checkpoints from wal",
+            recovered
+        );
+        recovered
+    }
+
+    /// Buffers the checkpoint of a pop, returns `false` if buffering is disabled, the buffer is
+    /// full or being drained and the checkpoint has to be stored directly.
+    pub fn add_ck(
+        &self,
+        ck: PopCheckPoint,
+        revive_queue_id: i32,
+        revive_queue_offset: i64,
+        just_offset: bool,
+    ) -> bool {
+        if !self.is_buffering() {
+            return false;
+        }
+        // held until the checkpoint is buffered, a rewrite of the log must not miss it
+        let mut wal_writer = self.wal.as_ref().map(PopBufferWal::lock);
+        if let Some(wal_writer) = wal_writer.as_mut() {
+            let record = PopBufferWalRecord::Ck {
+                revive_queue_id,
+                revive_queue_offset,
+                just_offset,
+                ck: ck.clone(),
+            };
+            if let Err(e) = wal_writer.append(&record) {
+                error!("PopBufferMergeService: append ck to wal error, {}", e);
+                return false;
+            }
+        }
+        let wrapper =
+            PopCheckPointWrapper::new(revive_queue_id, revive_queue_offset, ck, just_offset);
+        self.buffer
+            .insert(wrapper.merge_key().clone(), Arc::new(wrapper));
+        true
+    }
+
+    /// Merges an ack into its buffered checkpoint, returns `false` if the ack has to be stored
+    /// directly: buffering is disabled, the buffer is full or being drained or the checkpoint is
+    /// not buffered.
+    pub fn add_ack(&self, revive_qid: i32, ack_msg: &dyn AckMessage) -> bool {
+        if !self.is_buffering() {
+            return false;
+        }
+        let merge_key = gen_merge_key(
+            ack_msg.topic(),
+            ack_msg.consumer_group(),
+            ack_msg.queue_id(),
+            ack_msg.start_offset(),
+            ack_msg.pop_time(),
+            ack_msg.broker_name(),
+        );
+        let Some(wrapper) = self
+            .buffer
+            .get(&merge_key)
+            .map(|entry| entry.value().clone())
+        else {
+            return false;
+        };
+        if wrapper.is_just_offset() || wrapper.revive_queue_id() != revive_qid {
+            return false;
+        }
+        let ack_offset = ack_msg.ack_offset();
+        let ack_offsets = match ack_msg.as_any().downcast_ref::<BatchAckMsg>() {
+            Some(batch_ack) => batch_ack.ack_offset_list.as_slice(),
+            None => std::slice::from_ref(&ack_offset),
+        };
+        let mut all_marked = true;
+        for offset in ack_offsets {
+            all_marked &= wrapper.mark_acked(*offset);
+        }
+        // the checkpoint may have been taken out by a flush before the bits were set, the ack
+        // is only merged if it is still buffered
+        let merged = all_marked
+            && self
+                .buffer
+                .get(&merge_key)
+                .is_some_and(|entry| Arc::ptr_eq(entry.value(), &wrapper));
+        if !merged {
+            return false;
+        }
+        self.append_wal(PopBufferWalRecord::Ack {
+            merge_key,
+            ack_offsets: ack_offsets.to_vec(),
+        })
+    }
+
+    /// Number of checkpoints held in the buffer.
+    pub fn buffered_num(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns `true` once the buffer is drained for shutdown.
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Acquire)
+    }
+
+    /// Number of acks merged into the buffered checkpoints.
+    pub fn buffered_ack_num(&self) -> usize {
+        self.buffer
+            .iter()
+            .map(|entry| entry.value().acked_num())
+            .sum()
+    }
+
+    /// Returns `true` while the buffer holds too many acks and is being flushed early.
+    pub fn is_under_pressure(&self) -> bool {
+        self.under_pressure.load(Ordering::Acquire)
+    }
+
+    fn is_buffering(&self) -> bool {
+        self.broker_config.enable_pop_buffer_merge
+            && !self.is_draining()
+            && !self.is_full()
+            && !self.is_under_pressure()
+    }
+
+    fn is_full(&self) -> bool {
+        self.buffer.len() >= self.broker_config.pop_ck_max_buffer_size
+    }
+
+    /// Takes the checkpoints due at `now` out of the buffer, and the oldest ones too if it holds
+    /// too many acks, returns those with something left to write to the revive topic.
+    pub fn scan(&self, now: i64) -> Vec<PopBufferFlush> {
+        let mut flushes = self.scan_due(now);
+        flushes.extend(self.scan_on_pressure());
+        flushes
+    }
+
+    fn scan_due(&self, now: i64) -> Vec<PopBufferFlush> {
+        let stay_time = self.broker_config.pop_ck_stay_buffer_time as i64;
+        let due = self
+            .buffer
+            .iter()
+            .filter(|entry| {
+                let wrapper = entry.value();
+                wrapper.is_all_acked() || now - wrapper.ck().pop_time > stay_time
+            })
+            .map(|entry| entry.key().clone())
+            .collect::<Vec<_>>();
+        due.into_iter()
+            .filter_map(|merge_key| self.take(&merge_key))
+            .collect()
+    }
+
+    /// Takes the oldest checkpoints out of the buffer once it holds `popCkBufferAckHighWaterMark`
+    /// acks, until fewer than `popCkBufferAckLowWaterMark` are left.
+    fn scan_on_pressure(&self) -> Vec<PopBufferFlush> {
+        let high_water_mark = self.broker_config.pop_ck_buffer_ack_high_water_mark;
+        if high_water_mark == 0 {
+            return Vec::new();
+        }
+        // an empty buffer is always below it
+        let low_water_mark = self
+            .broker_config
+            .pop_ck_buffer_ack_low_water_mark
+            .clamp(1, high_water_mark);
+        let mut buffered_ack_num = self.buffered_ack_num();
+        if !self.is_under_pressure() {
+            if buffered_ack_num < high_water_mark {
+                return Vec::new();
+            }
+            warn!(
+                "PopBufferMergeService: {} acks buffered, flushing the oldest checkpoints",
+                buffered_ack_num
+            );
+            self.under_pressure.store(true, Ordering::Release);
+        }
+        let mut oldest = self
+            .buffer
+            .iter()
+            .map(|entry| {
+                let wrapper = entry.value();
+                (
+                    wrapper.ck().pop_time,
+                    entry.key().clone(),
+                    wrapper.acked_num(),
+                )
+            })
+            .collect::<Vec<_>>();
+        oldest.sort_unstable_by_key(|(pop_time, _, _)| *pop_time);
+        let mut flushes = Vec::new();
+        for (_, merge_key, acked_num) in oldest {
+            if buffered_ack_num < low_water_mark {
+                break;
+            }
+            flushes.extend(self.take(&merge_key));
+            // acks merged since the count was taken are not part of it
+            buffered_ack_num = buffered_ack_num.saturating_sub(acked_num);
+        }
+        if buffered_ack_num < low_water_mark {
+            info!(
+                "PopBufferMergeService: {} acks buffered, buffering again",
+                buffered_ack_num
+            );
+            self.under_pressure.store(false, Ordering::Release);
+        }
+        flushes
+    }
+
+    /// Takes every checkpoint out of the buffer, due or not.
+    pub fn scan_all(&self) -> Vec<PopBufferFlush> {
+        let merge_keys = self
+            .buffer
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect::<Vec<_>>();
+        merge_keys
+            .into_iter()
+            .filter_map(|merge_key| self.take(&merge_key))
+            .collect()
+    }
+
+    fn take(&self, merge_key: &CheetahString) -> Option<PopBufferFlush> {
+        let (_, wrapper) = self.buffer.remove(merge_key)?;
+        let flush = PopBufferFlush::of(wrapper);
+        if flush.is_none() {
+            self.append_wal(PopBufferWalRecord::Removed {
+                merge_key: merge_key.clone(),
+            });
+        }
+        flush
+    }
+
+    /// Appends `record` to the write-ahead log if there is one, returns `false` if it could not
+    /// be appended.
+    fn append_wal(&self, record: PopBufferWalRecord) -> bool {
+        let Some(wal) = &self.wal else {
+            return true;
+        };
+        if let Err(e) = wal.lock().append(&record) {
+            error!("PopBufferMergeService: append to wal error, {}", e);
+            return false;
+        }
+        true
+    }
+
+    /// Rewrites the write-ahead log with a snapshot of the buffer once enough records have been
+    /// appended to it.
+    fn compact_wal(&self) {
+        let Some(wal) = &self.wal else {
+            return;
+        };
+        let mut wal_writer = wal.lock();
+        if wal_writer.appended() >= WAL_COMPACT_RECORDS {
+            self.rewrite_wal(&mut wal_writer);
+        }
+    }
+
+    fn rewrite_wal(&self, wal_writer: &mut PopBufferWalWriter) {
+        let mut records = Vec::with_capacity(self.buffer.len());
+        for entry in self.buffer.iter() {
+            entry.value().snapshot_into(&mut records);
+        }
+        if let Err(e) = wal_writer.rewrite(&records) {
+            error!("PopBufferMergeService: rewrite wal error, {}", e);
+        }
+    }
+
+    /// Stops buffering, checkpoints and acks arriving from now on are stored directly.
+    pub fn begin_drain(&self) {
+        self.draining.store(true, Ordering::Release);
+    }
+
+    pub fn shutdown(&mut self) {
+        self.shutdown.notify_waiters();
+    }
+}
+
+impl PopBufferMergeService {
+    pub fn start<MS>(&mut self, this: ArcMut<Self>, escape_bridge: ArcMut<EscapeBridge<MS>>)
+    where
+        MS: MessageStore + Send + Sync,
+    {
+        tokio::spawn(async move {
+            let mut escape_bridge = escape_bridge;
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(tokio::time::Duration::from_millis(SCAN_INTERVAL_MILLIS)) => {}
+                    _ = this.shutdown.notified() => {
+                        info!("PopBufferMergeService: shutdown..........");
+                        break;
+                    }
+                }
+                for flush in this.scan(get_current_millis() as i64) {
+                    this.flush(escape_bridge.as_mut(), flush).await;
+                }
+                this.compact_wal();
+            }
+        });
+    }
+
+    /// Stops buffering and writes every buffered checkpoint and its acks to the revive topic,
+    /// retrying failed puts until `timeout` has elapsed. Returns the number of checkpoints that
+    /// could not be written and are lost.
+    pub async fn drain<MS>(&self, escape_bridge: &mut EscapeBridge<MS>, timeout: Duration) -> usize
+    where
+        MS: MessageStore,
+    {
+        self.begin_drain();
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            for flush in self.scan_all() {
+                self.flush(escape_bridge, flush).await;
+            }
+            // a failed put puts its checkpoint back into the buffer
+            if self.buffer.is_empty() || tokio::time::Instant::now() >= deadline {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(SCAN_INTERVAL_MILLIS)).await;
+        }
+        let left = self.buffer.len();
+        if left > 0 {
+            warn!(
+                "PopBufferMergeService: drain timeout after {:?}, {} checkpoints not written",
+                timeout, left
+            );
+        } else {
+            info!("PopBufferMergeService: buffer drained");
+        }
+        left
+    }
+
+    async fn flush<MS>(&self, escape_bridge: &mut EscapeBridge<MS>, flush: PopBufferFlush)
+    where
+        MS: MessageStore,
+    {
+        let wrapper = flush.wrapper;
+        if flush.store_ck {
+            let status = escape_bridge
+                .put_message_to_specific_queue(
+                    self.build_ck_msg(wrapper.ck(), wrapper.revive_queue_id()),
+                )
+                .await
+                .put_message_status();
+            if !is_put_ok(status) {
+                error!(
+                    "PopBufferMergeService: put ck to revive topic error, {}, status: {:?}",
+                    wrapper.merge_key(),
+                    status
+                );
+                // keep it buffered and retry with the next scan
+                self.buffer.insert(wrapper.merge_key().clone(), wrapper);
+                return;
+            }
+            wrapper.set_ck_stored(true);
+            self.append_wal(PopBufferWalRecord::CkStored {
+                merge_key: wrapper.merge_key().clone(),
+            });
+        }
+        if flush.ack_offsets.is_empty() {
+            return;
+        }
+        let status = escape_bridge
+            .put_message_to_specific_queue(self.build_ack_msg(&wrapper, &flush.ack_offsets))
+            .await
+            .put_message_status();
+        if !is_put_ok(status) {
+            error!(
+                "PopBufferMergeService: put ack to revive topic error, {}, status: {:?}",
+                wrapper.merge_key(),
+                status
+            );
+            self.buffer.insert(wrapper.merge_key().clone(), wrapper);
+            return;
+        }
+        for offset in &flush.ack_offsets {
+            let index = wrapper.ck().index_of_ack(*offset);
+            mark_bit_cas(wrapper.to_store_bits(), index as u32);
+        }
+        self.append_wal(PopBufferWalRecord::AckStored {
+            merge_key: wrapper.merge_key().clone(),
+            ack_offsets: flush.ack_offsets,
+        });
+    }
+
+    /// Builds the revive topic message storing `ck`, revived from queue `revive_queue_id`.
+    pub(crate) fn build_ck_msg(
+        &self,
+        ck: &PopCheckPoint,
+        revive_queue_id: i32,
+    ) -> MessageExtBrokerInner {
+        let mut inner = MessageExtBrokerInner::default();
+        inner.set_topic(self.revive_topic.clone());
+        inner.set_body(Bytes::from(ck.encode().unwrap_or_default()));
+        set_revive_body_crc(&mut inner);
+        inner.message_ext_inner.queue_id = revive_queue_id;
+        inner.set_tags(CheetahString::from_static_str(PopAckConstants::CK_TAG));
+        inner.message_ext_inner.born_timestamp = get_current_millis() as i64;
+        inner.message_ext_inner.born_host = self.store_host;
+        inner.message_ext_inner.store_host = self.store_host;
+        set_revive_host_flags(&mut inner);
+        inner.set_delay_time_ms(
+            (ck.get_revive_time() - PopAckConstants::ACK_TIME_INTERVAL).max(0) as u64,
+        );
+        inner.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX),
+            CheetahString::from(gen_ck_unique_id(ck)),
+        );
+        inner.properties_string =
+            message_decoder::message_properties_to_string(inner.get_properties());
+        inner
+    }
+
+    fn build_ack_msg(
+        &self,
+        wrapper: &PopCheckPointWrapper,
+        ack_offsets: &[i64],
+    ) -> MessageExtBrokerInner {
+        let ck = wrapper.ck();
+        let ack_msg = AckMsg {
+            ack_offset: ack_offsets[0],
+            start_offset: ck.start_offset,
+            consumer_group: ck.cid.clone(),
+            topic: ck.topic.clone(),
+            queue_id: ck.queue_id,
+            pop_time: ck.pop_time,
+            broker_name: ck.broker_name.clone().unwrap_or_default(),
+        };
+        let mut inner = MessageExtBrokerInner::default();
+        let (body, tag, unique_id) = if ack_offsets.len() == 1 {
+            (
+                ack_msg.encode_body(),
+                PopAckConstants::ACK_TAG,
+                ack_msg.unique_id(),
+            )
+        } else {
+            let batch_ack_msg = BatchAckMsg {
+                ack_msg,
+                ack_offset_list: ack_offsets.to_vec(),
+            };
+            (
+                batch_ack_msg.encode_body(),
+                PopAckConstants::BATCH_ACK_TAG,
+                batch_ack_msg.unique_id(),
+            )
+        };
+        inner.set_topic(self.revive_topic.clone());
+        inner.set_body(Bytes::from(body.unwrap_or_default()));
+        set_revive_body_crc(&mut inner);
+        inner.message_ext_inner.queue_id = wrapper.revive_queue_id();
+        inner.set_tags(CheetahString::from_static_str(tag));
+        inner.message_ext_inner.born_timestamp = get_current_millis() as i64;
+        inner.message_ext_inner.born_host = self.store_host;
+        inner.message_ext_inner.store_host = self.store_host;
+        set_revive_host_flags(&mut inner);
+        inner.set_delay_time_ms(ck.get_revive_time() as u64);
+        inner.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX),
+            CheetahString::from(unique_id),
+        );
+        inner.properties_string =
+            message_decoder::message_properties_to_string(inner.get_properties());
+        inner
+    }
+}
+
+impl PopBufferFlush {
+    fn of(wrapper: Arc<PopCheckPointWrapper>) -> Option<Self> {
+        if wrapper.is_just_offset() {
+            return None;
+        }
+        let all_acked = wrapper.is_all_acked();
+        if all_acked && !wrapper.is_ck_stored() {
+            // acked before it had to be revived, nothing to write
+            return None;
+        }
+        let ack_offsets = wrapper.pending_ack_offsets();
+        let store_ck = !wrapper.is_ck_stored();
+        if !store_ck && ack_offsets.is_empty() {
+            return None;
+        }
+        Some(PopBufferFlush {
+            wrapper,
+            store_ck,
+            ack_offsets,
+        })
+    }
+
+    pub fn wrapper(&self) -> &PopCheckPointWrapper {
+        &self.wrapper
+    }
+
+    pub fn store_ck(&self) -> bool {
+        self.store_ck
+    }
+
+    pub fn ack_offsets(&self) -> &[i64] {
+        &self.ack_offsets
+    }
+}
+
+pub(crate) fn is_put_ok(status: PutMessageStatus) -> bool {
+    matches!(
+        status,
+        PutMessageStatus::PutOk
+            | PutMessageStatus::FlushDiskTimeout
+            | PutMessageStatus::FlushSlaveTimeout
+            | PutMessageStatus::SlaveNotAvailable
+    )
+}
+
+fn gen_merge_key(
+    topic: &str,
+    cid: &str,
+    queue_id: i32,
+    start_offset: i64,
+    pop_time: i64,
+    broker_name: &str,
+) -> CheetahString {
+    CheetahString::from_string(format!(
+        "{}{}{}{}{}{}",
+        topic, cid, queue_id, start_offset, pop_time, broker_name
+    ))
+}
+
+/// A checkpoint held in the pop buffer, with the ack bits merged into it so far.
+///
+/// Acks for the same checkpoint may arrive concurrently from several clients, e.g. while a
+/// queue is being rebalanced, so the bits are only ever updated by compare-and-swap and no
+/// ack can overwrite another one.
+pub(crate) struct PopCheckPointWrapper {
+    revive_queue_id: i32,
+    revive_queue_offset: AtomicI64,
+    ck: PopCheckPoint,
+    bits: AtomicI32,
+    to_store_bits: AtomicI32,
+    merge_key: CheetahString,
+    just_offset: bool,
+    ck_stored: AtomicBool,
+}
+
+impl PopCheckPointWrapper {
+    pub fn new(
+        revive_queue_id: i32,
+        revive_queue_offset: i64,
+        ck: PopCheckPoint,
+        just_offset: bool,
+    ) -> Self {
+        let merge_key = gen_merge_key(
+            &ck.topic,
+            &ck.cid,
+            ck.queue_id,
+            ck.start_offset,
+            ck.pop_time,
+            ck.broker_name.as_deref().unwrap_or_default(),
+        );
+        PopCheckPointWrapper {
+            revive_queue_id,
+            revive_queue_offset: AtomicI64::new(revive_queue_offset),
+            bits: AtomicI32::new(0),
+            to_store_bits: AtomicI32::new(0),
+            merge_key,
+            just_offset,
+            ck_stored: AtomicBool::new(false),
+            ck,
+        }
+    }
+
+    /// Merges the ack of `ack_offset` into this checkpoint, returns `false` if the offset does
+    /// not belong to it.
+    pub fn mark_acked(&self, ack_offset: i64) -> bool {
+        let index = self.ck.index_of_ack(ack_offset);
+        if index < 0 || index >= self.ck.num as i32 || index >= i32::BITS as i32 {
+            return false;
+        }
+        mark_bit_cas(&self.bits, index as u32);
+        true
+    }
+
+    /// Offsets acked but not written to the revive topic yet.
+    pub fn pending_ack_offsets(&self) -> Vec<i64> {
+        let bits = self.bits.load(Ordering::Acquire);
+        let to_store_bits = self.to_store_bits.load(Ordering::Acquire);
+        self.offsets_of(bits & !to_store_bits)
+    }
+
+    fn offsets_of(&self, bits: i32) -> Vec<i64> {
+        (0..self.ck.num.min(i32::BITS as u8))
+            .filter(|index| get_bit(bits, *index as u32))
+            .map(|index| self.ck.ack_offset_by_index(index))
+            .collect()
+    }
+
+    /// Appends the records rebuilding this checkpoint to `records`.
+    fn snapshot_into(&self, records: &mut Vec<PopBufferWalRecord>) {
+        records.push(PopBufferWalRecord::Ck {
+            revive_queue_id: self.revive_queue_id,
+            revive_queue_offset: self.revive_queue_offset(),
+            just_offset: self.just_offset,
+            ck: self.ck.clone(),
+        });
+        let ack_offsets = self.offsets_of(self.bits.load(Ordering::Acquire));
+        if !ack_offsets.is_empty() {
+            records.push(PopBufferWalRecord::Ack {
+                merge_key: self.merge_key.clone(),
+                ack_offsets,
+            });
+        }
+        if self.is_ck_stored() {
+            records.push(PopBufferWalRecord::CkStored {
+                merge_key: self.merge_key.clone(),
+            });
+        }
+        let stored_offsets = self.offsets_of(self.to_store_bits.load(Ordering::Acquire));
+        if !stored_offsets.is_empty() {
+            records.push(PopBufferWalRecord::AckStored {
+                merge_key: self.merge_key.clone(),
+                ack_offsets: stored_offsets,
+            });
+        }
+    }
+
+    /// Number of acks merged into this checkpoint.
+    pub fn acked_num(&self) -> usize {
+        self.bits.load(Ordering::Acquire).count_ones() as usize
+    }
+
+    /// Returns `true` once every message of the checkpoint has been acked.
+    pub fn is_all_acked(&self) -> bool {
+        let bits = self.bits.load(Ordering::Acquire);
+        (0..self.ck.num.min(i32::BITS as u8) as u32).all(|index| get_bit(bits, index))
+    }
+
+    pub fn revive_queue_id(&self) -> i32 {
+        self.revive_queue_id
+    }
+
+    pub fn revive_queue_offset(&self) -> i64 {
+        self.revive_queue_offset.load(Ordering::Acquire)
+    }
+
+    pub fn set_revive_queue_offset(&self, revive_queue_offset: i64) {
+        self.revive_queue_offset
+            .store(revive_queue_offset, Ordering::Release);
+    }
+
+    pub fn ck(&self) -> &PopCheckPoint {
+        &self.ck
+    }
+
+    pub fn bits(&self) -> &AtomicI32 {
+        &self.bits
+    }
+
+    pub fn to_store_bits(&self) -> &AtomicI32 {
+        &self.to_store_bits
+    }
+
+    pub fn merge_key(&self) -> &CheetahString {
+        &self.merge_key
+    }
+
+    pub fn is_just_offset(&self) -> bool {
+        self.just_offset
+    }
+
+    pub fn is_ck_stored(&self) -> bool {
+        self.ck_stored.load(Ordering::Acquire)
+    }
+
+    pub fn set_ck_stored(&self, ck_stored: bool) {
+        self.ck_stored.store(ck_stored, Ordering::Release);
+    }
+}
+
+/// Sets bit `index` of `set_bits` without losing bits set concurrently by other threads.
+pub(crate) fn mark_bit_cas(set_bits: &AtomicI32, index: u32) {
+    let mask = 1i32 << index;
+    let mut bits = set_bits.load(Ordering::Acquire);
+    while bits & mask == 0 {
+        match set_bits.compare_exchange_weak(bits, bits | mask, Ordering::AcqRel, Ordering::Acquire)
+        {
+            Ok(_) => return,
+            Err(current) => bits = current,
+        }
+    }
+}
+
+#[inline]
+fn get_bit(bits: i32, index: u32) -> bool {
+    bits & (1i32 << index) != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+
+    fn merge_service(configure: impl FnOnce(&mut BrokerConfig)) -> PopBufferMergeService {
+        let mut broker_config = BrokerConfig {
+            enable_pop_buffer_merge: true,
+            ..Default::default()
+        };
+        configure(&mut broker_config);
+        PopBufferMergeService::new(Arc::new(broker_config), "127.0.0.1:10911".parse().unwrap())
+    }
+
+    fn ack_of(ck: &PopCheckPoint, ack_offset: i64) -> AckMsg {
+        AckMsg {
+            ack_offset,
+            start_offset: ck.start_offset,
+            consumer_group: ck.cid.clone(),
+            topic: ck.topic.clone(),
+            queue_id: ck.queue_id,
+            pop_time: ck.pop_time,
+            broker_name: ck.broker_name.clone().unwrap_or_default(),
+        }
+    }
+
+    fn wal_root_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!(
+            "rocketmq-pop-buffer-merge-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.to_string_lossy().into_owned()
+    }
+
+    fn check_point(start_offset: i64, num: u8) -> PopCheckPoint {
+        PopCheckPoint {
+            start_offset,
+            num,
+            topic: CheetahString::from_static_str("test_topic"),
+            cid: CheetahString::from_static_str("test_group"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn mark_acked_rejects_offset_outside_check_point() {
+        let wrapper = PopCheckPointWrapper::new(0, 0, check_point(100, 4), false);
+        assert!(!wrapper.mark_acked(99));
+        assert!(!wrapper.mark_acked(104));
+        assert_eq!(wrapper.bits().load(Ordering::Acquire), 0);
+    }
+
+    #[test]
+    fn mark_acked_sets_bit_of_offset() {
+        let wrapper = PopCheckPointWrapper::new(0, 0, check_point(100, 4), false);
+        assert!(wrapper.mark_acked(102));
+        assert_eq!(wrapper.bits().load(Ordering::Acquire), 0b100);
+        assert!(!wrapper.is_all_acked());
+    }
+
+    #[test]
+    fn concurrent_acks_are_merged_without_lost_updates() {
+        let num = 32u8;
+        let wrapper = Arc::new(PopCheckPointWrapper::new(
+            0,
+            0,
+            check_point(1000, num),
+            false,
+        ));
+        let handles = (0..16)
+            .map(|thread_index| {
+                let wrapper = wrapper.clone();
+                thread::spawn(move || {
+                    // overlapping ranges, every offset is acked by several threads
+                    for round in 0..1000 {
+                        let offset = 1000 + ((thread_index * 3 + round) % (num as i64 / 2));
+                        let offset = if thread_index % 2 == 0 {
+                            offset
+                        } else {
+                            offset + num as i64 / 2
+                        };
+                        wrapper.mark_acked(offset);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(wrapper.bits().load(Ordering::Acquire), -1);
+        assert!(wrapper.is_all_acked());
+    }
+
+    #[test]
+    fn merge_key_identifies_check_point() {
+        let wrapper = PopCheckPointWrapper::new(0, 0, check_point(100, 4), false);
+        assert_eq!(wrapper.merge_key().as_str(), "test_topictest_group01000");
+    }
+
+    #[test]
+    fn fully_acked_check_point_is_merged_and_dropped() {
+        let service = merge_service(|_| {});
+        let ck = check_point(100, 4);
+        assert!(service.add_ck(ck.clone(), 2, 0, false));
+
+        assert!(service.add_ack(2, &ack_of(&ck, 100)));
+        let batch_ack = BatchAckMsg {
+            ack_msg: ack_of(&ck, -1),
+            ack_offset_list: vec![101, 102, 103],
+        };
+        assert!(service.add_ack(2, &batch_ack));
+
+        // nothing reaches the revive topic, the checkpoint is just dropped
+        assert!(service.scan(ck.pop_time).is_empty());
+        assert_eq!(service.buffered_num(), 0);
+    }
+
+    #[test]
+    fn partially_acked_check_point_is_flushed_after_stay_time() {
+        let service = merge_service(|config| config.pop_ck_stay_buffer_time = 1_000);
+        let ck = check_point(100, 4);
+        assert!(service.add_ck(ck.clone(), 2, 0, false));
+        assert!(service.add_ack(2, &ack_of(&ck, 101)));
+        assert!(service.add_ack(2, &ack_of(&ck, 103)));
+
+        assert!(service.scan(ck.pop_time + 1_000).is_empty());
+        let flushes = service.scan(ck.pop_time + 1_001);
+        assert_eq!(flushes.len(), 1);
+        assert!(flushes[0].store_ck());
+        assert_eq!(flushes[0].ack_offsets(), &[101, 103]);
+        assert_eq!(service.buffered_num(), 0);
+
+        // a late ack no longer finds the checkpoint and is stored directly
+        assert!(!service.add_ack(2, &ack_of(&ck, 100)));
+    }
+
+    #[test]
+    fn add_ack_falls_back_when_buffer_is_full() {
+        let service = merge_service(|config| config.pop_ck_max_buffer_size = 1);
+        let ck = check_point(100, 4);
+        assert!(service.add_ck(ck.clone(), 2, 0, false));
+        assert!(!service.add_ck(check_point(200, 4), 2, 0, false));
+        assert_eq!(service.buffered_num(), 1);
+
+        assert!(!service.add_ack(2, &ack_of(&ck, 100)));
+    }
+
+    #[test]
+    fn oldest_check_points_are_flushed_early_past_high_water_mark() {
+        let service = merge_service(|config| {
+            config.pop_ck_stay_buffer_time = 60_000;
+            config.pop_ck_buffer_ack_high_water_mark = 6;
+            config.pop_ck_buffer_ack_low_water_mark = 3;
+        });
+        let cks = [300, 100, 200].map(|start_offset| PopCheckPoint {
+            pop_time: start_offset * 10,
+            ..check_point(start_offset, 4)
+        });
+        for ck in &cks {
+            assert!(service.add_ck(ck.clone(), 2, 0, false));
+            assert!(service.add_ack(2, &ack_of(ck, ck.start_offset)));
+        }
+        // below the high water mark nothing is due yet
+        assert!(service.scan(1_000).is_empty());
+
+        assert!(service.add_ack(2, &ack_of(&cks[0], 301)));
+        assert!(service.add_ack(2, &ack_of(&cks[1], 101)));
+        assert!(service.add_ack(2, &ack_of(&cks[2], 201)));
+        assert_eq!(service.buffered_ack_num(), 6);
+        let mut flushes = service.scan(1_000);
+        flushes.sort_by_key(|flush| flush.ack_offsets().first().copied());
+        // the two oldest check points are flushed, leaving fewer acks than the low water mark
+        assert_eq!(flushes.len(), 2);
+        assert_eq!(flushes[0].ack_offsets(), &[100, 101]);
+        assert_eq!(flushes[1].ack_offsets(), &[200, 201]);
+        assert_eq!(service.buffered_num(), 1);
+        assert_eq!(service.buffered_ack_num(), 2);
+
+        assert!(!service.is_under_pressure());
+        assert!(service.add_ack(2, &ack_of(&cks[0], 302)));
+        assert!(service.add_ck(check_point(400, 4), 2, 0, false));
+    }
+
+    #[test]
+    fn add_ack_falls_back_when_disabled_or_check_point_unknown() {
+        let ck = check_point(100, 4);
+        let disabled = merge_service(|config| config.enable_pop_buffer_merge = false);
+        assert!(!disabled.add_ck(ck.clone(), 2, 0, false));
+        assert!(!disabled.add_ack(2, &ack_of(&ck, 100)));
+
+        let service = merge_service(|_| {});
+        assert!(!service.add_ack(2, &ack_of(&ck, 100)));
+        assert!(service.add_ck(ck.clone(), 2, 0, false));
+        assert!(!service.add_ack(2, &ack_of(&ck, 104)));
+        assert!(!service.add_ack(3, &ack_of(&ck, 100)));
+    }
+
+    #[test]
+    fn drain_takes_every_check_point_and_stops_buffering() {
+        let service = merge_service(|config| config.pop_ck_stay_buffer_time = 60_000);
+        let cks = [
+            check_point(100, 4),
+            check_point(200, 4),
+            check_point(300, 4),
+        ];
+        for ck in &cks {
+            assert!(service.add_ck(ck.clone(), 2, 0, false));
+        }
+        assert!(service.add_ack(2, &ack_of(&cks[0], 100)));
+        assert!(service.add_ack(2, &ack_of(&cks[1], 201)));
+        assert!(service.add_ack(2, &ack_of(&cks[1], 203)));
+        assert!(service.add_ack(2, &ack_of(&cks[2], 302)));
+        // none of them is due yet
+        assert!(service.scan(cks[0].pop_time).is_empty());
+
+        service.begin_drain();
+        assert!(service.is_draining());
+        assert!(!service.add_ack(2, &ack_of(&cks[0], 101)));
+        assert!(!service.add_ck(check_point(400, 4), 2, 0, false));
+
+        let mut flushes = service.scan_all();
+        flushes.sort_by_key(|flush| flush.ack_offsets().first().copied());
+        assert_eq!(flushes.len(), 3);
+        assert!(flushes.iter().all(PopBufferFlush::store_ck));
+        assert_eq!(flushes[0].ack_offsets(), &[100]);
+        assert_eq!(flushes[1].ack_offsets(), &[201, 203]);
+        assert_eq!(flushes[2].ack_offsets(), &[302]);
+        assert_eq!(service.buffered_num(), 0);
+    }
+
+    #[test]
+    fn buffered_acks_survive_a_restart_with_wal() {
+        let root_dir = wal_root_dir("restart");
+        let configure = |config: &mut BrokerConfig| {
+            config.enable_pop_buffer_merge_wal = true;
+            config.store_path_root_dir = CheetahString::from_string(root_dir.clone());
+            config.pop_ck_stay_buffer_time = 60_000;
+        };
+        let cks = [
+            check_point(100, 4),
+            check_point(200, 4),
+            check_point(300, 4),
+        ];
+        {
+            let service = merge_service(configure);
+            for ck in &cks {
+                assert!(service.add_ck(ck.clone(), 2, 0, false));
+            }
+            for offset in 100..104 {
+                assert!(service.add_ack(2, &ack_of(&cks[0], offset)));
+            }
+            assert!(service.add_ack(2, &ack_of(&cks[1], 201)));
+            assert!(service.add_ack(2, &ack_of(&cks[1], 203)));
+            // the fully acked checkpoint is dropped, the others are still buffered
+            assert!(service.scan(cks[0].pop_time).is_empty());
+            assert_eq!(service.buffered_num(), 2);
+            // the broker crashes, the buffer is never drained
+        }
+
+        let service = merge_service(configure);
+        assert_eq!(service.buffered_num(), 0);
+        assert_eq!(service.recover(), 2);
+        let mut flushes = service.scan_all();
+        flushes.sort_by_key(|flush| flush.wrapper.ck().start_offset);
+        assert_eq!(flushes.len(), 2);
+        assert!(flushes.iter().all(PopBufferFlush::store_ck));
+        assert_eq!(flushes[0].ack_offsets(), &[201, 203]);
+        assert!(flushes[1].ack_offsets().is_empty());
+
+        // the compacted log recovers the same buffer once more
+        let service = merge_service(configure);
+        assert_eq!(service.recover(), 2);
+        let _ = std::fs::remove_dir_all(&root_dir);
+    }
+
+    #[test]
+    fn recovery_skips_what_is_already_in_the_revive_queue() {
+        let root_dir = wal_root_dir("dedup");
+        let configure = |config: &mut BrokerConfig| {
+            config.enable_pop_buffer_merge_wal = true;
+            config.store_path_root_dir = CheetahString::from_string(root_dir.clone());
+        };
+        let partly_stored = PopCheckPointWrapper::new(2, 0, check_point(100, 4), false);
+        let fully_stored = PopCheckPointWrapper::new(2, 0, check_point(200, 4), false);
+        {
+            let wal = PopBufferWal::open(get_pop_buffer_wal_path(&root_dir)).unwrap();
+            let mut wal_writer = wal.lock();
+            for wrapper in [&partly_stored, &fully_stored] {
+                wal_writer
+                    .append(&PopBufferWalRecord::Ck {
+                        revive_queue_id: 2,
+                        revive_queue_offset: 0,
+                        just_offset: false,
+                        ck: wrapper.ck().clone(),
+                    })
+                    .unwrap();
+                wal_writer
+                    .append(&PopBufferWalRecord::Ack {
+                        merge_key: wrapper.merge_key().clone(),
+                        ack_offsets: vec![wrapper.ck().start_offset, wrapper.ck().start_offset + 1],
+                    })
+                    .unwrap();
+                wal_writer
+                    .append(&PopBufferWalRecord::CkStored {
+                        merge_key: wrapper.merge_key().clone(),
+                    })
+                    .unwrap();
+            }
+            wal_writer
+                .append(&PopBufferWalRecord::AckStored {
+                    merge_key: partly_stored.merge_key().clone(),
+                    ack_offsets: vec![100],
+                })
+                .unwrap();
+            wal_writer
+                .append(&PopBufferWalRecord::AckStored {
+                    merge_key: fully_stored.merge_key().clone(),
+                    ack_offsets: vec![200, 201],
+                })
+                .unwrap();
+        }
+
+        let service = merge_service(configure);
+        assert_eq!(service.recover(), 1);
+        let flushes = service.scan_all();
+        assert_eq!(flushes.len(), 1);
+        assert!(!flushes[0].store_ck());
+        assert_eq!(flushes[0].ack_offsets(), &[101]);
+        let _ = std::fs::remove_dir_all(&root_dir);
     }
 }
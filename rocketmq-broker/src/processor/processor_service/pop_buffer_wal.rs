@@ -0,0 +1,220 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use parking_lot::MutexGuard;
+use rocketmq_store::pop::pop_check_point::PopCheckPoint;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::warn;
+
+/// A change of the pop buffer, appended to the write-ahead log as one JSON line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum PopBufferWalRecord {
+    /// A checkpoint was buffered.
+    #[serde(rename_all = "camelCase")]
+    Ck {
+        revive_queue_id: i32,
+        revive_queue_offset: i64,
+        just_offset: bool,
+        ck: PopCheckPoint,
+    },
+    /// Acks were merged into a buffered checkpoint.
+    #[serde(rename_all = "camelCase")]
+    Ack {
+        merge_key: CheetahString,
+        ack_offsets: Vec<i64>,
+    },
+    /// The checkpoint was written to the revive topic.
+    #[serde(rename_all = "camelCase")]
+    CkStored { merge_key: CheetahString },
+    /// Acks merged into the checkpoint were written to the revive topic.
+    #[serde(rename_all = "camelCase")]
+    AckStored {
+        merge_key: CheetahString,
+        ack_offsets: Vec<i64>,
+    },
+    /// The checkpoint left the buffer with nothing to write to the revive topic.
+    #[serde(rename_all = "camelCase")]
+    Removed { merge_key: CheetahString },
+}
+
+/// Write-ahead log of the pop buffer, replayed on startup so that acks merged into buffered
+/// checkpoints survive a broker crash.
+///
+/// Records are only ever appended, each one is synced to disk before the change it describes is
+/// acknowledged. The log is rewritten with a snapshot of the buffer from time to time to keep it
+/// from growing without bound.
+pub(crate) struct PopBufferWal {
+    path: PathBuf,
+    writer: Mutex<PopBufferWalWriter>,
+}
+
+pub(crate) struct PopBufferWalWriter {
+    path: PathBuf,
+    file: File,
+    appended: usize,
+}
+
+impl PopBufferWal {
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = open_append(&path)?;
+        Ok(PopBufferWal {
+            writer: Mutex::new(PopBufferWalWriter {
+                path: path.clone(),
+                file,
+                appended: 0,
+            }),
+            path,
+        })
+    }
+
+    /// Reads the records of the log in the order they were appended. A record torn by a crash
+    /// ends the log, it was never acknowledged.
+    pub fn read_records(&self) -> io::Result<Vec<PopBufferWalRecord>> {
+        let reader = BufReader::new(File::open(&self.path)?);
+        let mut records = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(record) => records.push(record),
+                Err(e) => {
+                    warn!(
+                        "PopBufferWal: torn record in {}, replay stops here: {}",
+                        self.path.display(),
+                        e
+                    );
+                    break;
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    /// Locks the log, changes of the buffer have to be made while holding the lock so that a
+    /// rewrite never misses one.
+    pub fn lock(&self) -> MutexGuard<'_, PopBufferWalWriter> {
+        self.writer.lock()
+    }
+}
+
+impl PopBufferWalWriter {
+    pub fn append(&mut self, record: &PopBufferWalRecord) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.file.sync_data()?;
+        self.appended += 1;
+        Ok(())
+    }
+
+    /// Number of records appended since the log was opened or last rewritten.
+    pub fn appended(&self) -> usize {
+        self.appended
+    }
+
+    /// Replaces the whole log with `records`.
+    pub fn rewrite(&mut self, records: &[PopBufferWalRecord]) -> io::Result<()> {
+        let tmp_path = self.path.with_extension("wal.tmp");
+        let mut tmp = File::create(&tmp_path)?;
+        for record in records {
+            let mut line = serde_json::to_vec(record)?;
+            line.push(b'\n');
+            tmp.write_all(&line)?;
+        }
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        self.file = open_append(&self.path)?;
+        self.appended = 0;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wal_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("rocketmq-pop-buffer-wal-{}", std::process::id()))
+            .join(name)
+    }
+
+    fn ack(merge_key: &'static str, ack_offsets: Vec<i64>) -> PopBufferWalRecord {
+        PopBufferWalRecord::Ack {
+            merge_key: CheetahString::from_static_str(merge_key),
+            ack_offsets,
+        }
+    }
+
+    #[test]
+    fn torn_record_ends_the_log() {
+        let path = wal_path("torn.wal");
+        let _ = fs::remove_file(&path);
+        let wal = PopBufferWal::open(&path).unwrap();
+        wal.lock().append(&ack("a", vec![1])).unwrap();
+        wal.lock().append(&ack("b", vec![2, 3])).unwrap();
+        let mut file = open_append(&path).unwrap();
+        file.write_all(b"{\"type\":\"ack\",\"mergeK").unwrap();
+
+        assert_eq!(
+            wal.read_records().unwrap(),
+            vec![ack("a", vec![1]), ack("b", vec![2, 3])]
+        );
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn rewrite_replaces_the_log() {
+        let path = wal_path("rewrite.wal");
+        let _ = fs::remove_file(&path);
+        let wal = PopBufferWal::open(&path).unwrap();
+        wal.lock().append(&ack("a", vec![1])).unwrap();
+        assert_eq!(wal.lock().appended(), 1);
+
+        wal.lock().rewrite(&[ack("b", vec![2])]).unwrap();
+        assert_eq!(wal.lock().appended(), 0);
+        wal.lock().append(&ack("c", vec![3])).unwrap();
+        assert_eq!(
+            wal.read_records().unwrap(),
+            vec![ack("b", vec![2]), ack("c", vec![3])]
+        );
+        let _ = fs::remove_file(&path);
+    }
+}
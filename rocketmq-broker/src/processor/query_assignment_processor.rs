@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 use crate::load_balance::message_request_mode_manager::MessageRequestModeManager;
+use crate::load_balance::pop_sticky_assignment_manager::PopStickyAssignmentManager;
  use cheetah_string::CheetahString;
  use rocketmq_client_rust::consumer::allocate_message_queue_strategy::AllocateMessageQueueStrategy;
  use rocketmq_client_rust::consumer::rebalance_strategy::allocate_message_queue_averagely::AllocateMessageQueueAveragely;
@@ -34,6 +35,7 @@ use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_common::common::message::message_queue_assignment::MessageQueueAssignment;
 use rocketmq_common::common::mix_all;
 use rocketmq_common::common::mix_all::RETRY_GROUP_TOPIC_PREFIX;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::protocol::body::query_assignment_request_body::QueryAssignmentRequestBody;
 use rocketmq_remoting::protocol::body::query_assignment_response_body::QueryAssignmentResponseBody;
@@ -53,6 +55,7 @@ pub struct QueryAssignmentProcessor {
     broker_config: Arc<BrokerConfig>,
     topic_route_info_manager: Arc<TopicRouteInfoManager>,
     consumer_manager: Arc<ConsumerManager>,
+    pop_sticky_assignment_manager: Arc<PopStickyAssignmentManager>,
 }
 
 impl QueryAssignmentProcessor {
@@ -61,6 +64,7 @@ impl QueryAssignmentProcessor {
         broker_config: Arc<BrokerConfig>,
         topic_route_info_manager: Arc<TopicRouteInfoManager>,
         consumer_manager: Arc<ConsumerManager>,
+        pop_sticky_assignment_manager: Arc<PopStickyAssignmentManager>,
     ) -> Self {
         let allocate_message_queue_averagely: Arc<dyn AllocateMessageQueueStrategy> =
             Arc::new(AllocateMessageQueueAveragely);
@@ -84,6 +88,7 @@ impl QueryAssignmentProcessor {
             broker_config,
             topic_route_info_manager,
             consumer_manager,
+            pop_sticky_assignment_manager,
         }
     }
 }
@@ -271,6 +276,20 @@ impl QueryAssignmentProcessor {
                             cid_all.as_slice(),
                             set_message_request_mode_request_body.pop_share_queue_num,
                         )
+                        .map(|allocated| {
+                            if self.broker_config.enable_pop_sticky_assignment {
+                                self.pop_sticky_assignment_manager.assign(
+                                    topic,
+                                    consumer_group,
+                                    client_id,
+                                    allocated,
+                                    mq_all.as_slice(),
+                                    get_current_millis(),
+                                )
+                            } else {
+                                allocated
+                            }
+                        })
                     } else {
                         match strategy.allocate(
                             consumer_group,
@@ -86,6 +86,7 @@ use crate::subscription::manager::subscription_group_manager::SubscriptionGroupM
 use crate::topic::manager::topic_config_manager::TopicConfigManager;
 use crate::topic::manager::topic_queue_mapping_manager::TopicQueueMappingManager;
 use crate::transaction::transactional_message_service::TransactionalMessageService;
+use crate::util::hook_utils::HookUtils;
 
 pub struct SendMessageProcessor<MS, TS> {
     inner: ArcMut<Inner<MS, TS>>,
@@ -99,7 +100,11 @@ where
     TS: TransactionalMessageService,
 {
     pub fn has_send_message_hook(&self) -> bool {
-        self.inner.send_message_hook_vec.is_empty()
+        !self.inner.send_message_hook_vec.is_empty()
+    }
+
+    pub fn register_send_message_hook(&mut self, hook: Box<dyn SendMessageHook>) {
+        self.inner.send_message_hook_vec.push(hook);
     }
 
     fn clear_reserved_properties(request_header: &mut SendMessageRequestHeader) {
@@ -558,6 +563,30 @@ where
         let topic = message_ext.topic().clone();
         let transaction_id =
             MessageClientIDSetter::get_uniq_id(&message_ext.message_ext_inner.message);
+        if !send_transaction_prepare_message {
+            let timer_message_store = self.inner.message_store.get_timer_message_store();
+            if let Some(put_message_result) = HookUtils::handle_timer_message(
+                timer_message_store.as_ref(),
+                timer_message_store.message_store_config(),
+                &mut message_ext,
+            ) {
+                return Ok(self
+                    .handle_put_message_result(
+                        put_message_result,
+                        response,
+                        &request,
+                        topic.as_str(),
+                        transaction_id,
+                        &mut send_message_context,
+                        ctx,
+                        queue_id,
+                        start,
+                        &mut mapping_context,
+                        MessageType::NormalMsg,
+                    )
+                    .await);
+            }
+        }
         if self.inner.broker_config.async_send_enable {
             let put_message_handle = if send_transaction_prepare_message {
                 let mut transactional_message_service =
@@ -771,6 +800,7 @@ where
             let msg_id = response_header.msg_id().to_string();
             let queue_id = Some(response_header.queue_id());
             let queue_offset = Some(response_header.queue_offset());
+            let response_code = response.code();
             if let Some(mut ctx) = ctx.upgrade() {
                 ctx.write(response.set_opaque(request.opaque())).await;
             }
@@ -799,6 +829,10 @@ where
                 send_message_context.account_owner_self = owner_self.unwrap_or_default();
                 send_message_context.send_msg_size = wrote_size;
                 send_message_context.send_msg_num = msg_num;
+                send_message_context.code = response_code;
+                send_message_context.is_success = true;
+                self.inner
+                    .execute_send_message_hook_after(None, send_message_context);
             }
             None
         } else {
@@ -823,6 +857,11 @@ where
                 send_message_context.account_owner_self = owner_self.unwrap_or_default();
                 send_message_context.send_msg_size = wrote_size;
                 send_message_context.send_msg_num = msg_num;
+                send_message_context.code = response.code();
+                send_message_context.error_msg = response.remark().cloned().unwrap_or_default();
+                send_message_context.is_success = false;
+                self.inner
+                    .execute_send_message_hook_after(None, send_message_context);
             }
             Some(response)
         }
@@ -960,7 +999,7 @@ where
     }
 }
 
-const DLQ_NUMS_PER_GROUP: u32 = 1;
+pub(crate) const DLQ_NUMS_PER_GROUP: u32 = 1;
 
 pub(crate) struct Inner<MS, TS> {
     pub(crate) topic_config_manager: TopicConfigManager,
@@ -985,7 +1024,7 @@ where
 {
     #[inline]
     pub fn has_send_message_hook(&self) -> bool {
-        self.send_message_hook_vec.is_empty()
+        !self.send_message_hook_vec.is_empty()
     }
 
     #[inline]
@@ -1327,10 +1366,12 @@ where
             CheetahString::from_static_str(MessageConst::PROPERTY_MSG_REGION),
             CheetahString::from_string(self.broker_config.region_id().to_string()),
         );
-        properties.insert(
-            CheetahString::from_static_str(MessageConst::PROPERTY_TRACE_SWITCH),
-            CheetahString::from_string(self.broker_config.trace_on.to_string()),
-        );
+        // a producer may turn the trace of its message off
+        properties
+            .entry(CheetahString::from_static_str(
+                MessageConst::PROPERTY_TRACE_SWITCH,
+            ))
+            .or_insert_with(|| CheetahString::from_string(self.broker_config.trace_on.to_string()));
         request_header.properties = Some(MessageDecoder::message_properties_to_string(&properties));
 
         if let Some(unique_key) =
@@ -0,0 +1,240 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::protocol::header::ack_message_request_header::AckMessageRequestHeader;
+
+/// An ack reserved by a two-phase ack consumer group, waiting to be confirmed or aborted.
+pub(crate) struct PreparedAck {
+    pub request_header: AckMessageRequestHeader,
+    pub revive_shard_key: Option<CheetahString>,
+    prepared_at: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum TwoPhaseAckError {
+    /// No ack was prepared with this id for this group, or it was already confirmed or aborted.
+    Unknown,
+    /// The ack was not confirmed in time and has been released.
+    Expired,
+}
+
+impl Display for TwoPhaseAckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TwoPhaseAckError::Unknown => write!(f, "unknown ack transaction"),
+            TwoPhaseAckError::Expired => write!(f, "ack transaction expired"),
+        }
+    }
+}
+
+/// Acks prepared by consumer groups with the `ack.two.phase` attribute enabled.
+///
+/// For such a group an ack only reserves the commit, and returns an id. The client then ends
+/// the ack with that id once its own transaction is settled:
+/// - confirm persists the ack, exactly as a one-phase ack would have been;
+/// - abort releases it, nothing is persisted and the message is redelivered once its invisible time
+///   elapses, as if it was never acked;
+/// - an ack neither confirmed nor aborted within the timeout is aborted, and a late confirm fails
+///   with [`TwoPhaseAckError::Expired`]. The timeout should be well below the invisible time,
+///   otherwise the message may be redelivered while its ack is still prepared.
+///
+/// Prepared acks are held in memory only and are lost, i.e. aborted, on broker restart.
+pub(crate) struct TwoPhaseAckTable {
+    timeout_millis: u64,
+    next_id: AtomicU64,
+    inner: Mutex<TableInner>,
+}
+
+#[derive(Default)]
+struct TableInner {
+    prepared: HashMap<CheetahString, PreparedAck>,
+    /// Ids in preparation order, so expired acks are found from the front.
+    prepare_order: VecDeque<(u64, CheetahString)>,
+}
+
+impl TwoPhaseAckTable {
+    pub fn new(timeout_millis: u64) -> Self {
+        TwoPhaseAckTable {
+            timeout_millis,
+            next_id: AtomicU64::new(0),
+            inner: Mutex::new(TableInner::default()),
+        }
+    }
+
+    /// Reserves an ack and returns the id to end it with.
+    pub fn prepare(
+        &self,
+        request_header: AckMessageRequestHeader,
+        revive_shard_key: Option<CheetahString>,
+    ) -> CheetahString {
+        self.prepare_at(request_header, revive_shard_key, get_current_millis())
+    }
+
+    /// Takes the ack prepared with `id` by `group`, to persist it.
+    pub fn confirm(
+        &self,
+        group: &CheetahString,
+        id: &CheetahString,
+    ) -> Result<PreparedAck, TwoPhaseAckError> {
+        self.take_at(group, id, get_current_millis())
+    }
+
+    /// Releases the ack prepared with `id` by `group`.
+    pub fn abort(&self, group: &CheetahString, id: &CheetahString) -> Result<(), TwoPhaseAckError> {
+        self.take_at(group, id, get_current_millis()).map(|_| ())
+    }
+
+    pub fn prepared_num(&self) -> usize {
+        self.inner.lock().prepared.len()
+    }
+
+    fn prepare_at(
+        &self,
+        request_header: AckMessageRequestHeader,
+        revive_shard_key: Option<CheetahString>,
+        now: u64,
+    ) -> CheetahString {
+        let seq = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let id = CheetahString::from_string(format!("{:x}{:016x}", now, seq));
+        let mut inner = self.inner.lock();
+        self.expire(&mut inner, now);
+        inner.prepare_order.push_back((now, id.clone()));
+        inner.prepared.insert(
+            id.clone(),
+            PreparedAck {
+                request_header,
+                revive_shard_key,
+                prepared_at: now,
+            },
+        );
+        id
+    }
+
+    fn take_at(
+        &self,
+        group: &CheetahString,
+        id: &CheetahString,
+        now: u64,
+    ) -> Result<PreparedAck, TwoPhaseAckError> {
+        let mut inner = self.inner.lock();
+        match inner.prepared.get(id) {
+            Some(prepared) if prepared.request_header.consumer_group == *group => {}
+            _ => return Err(TwoPhaseAckError::Unknown),
+        }
+        let prepared = inner.prepared.remove(id).unwrap();
+        self.expire(&mut inner, now);
+        if self.is_expired(prepared.prepared_at, now) {
+            return Err(TwoPhaseAckError::Expired);
+        }
+        Ok(prepared)
+    }
+
+    fn expire(&self, inner: &mut TableInner, now: u64) {
+        while let Some((prepared_at, _)) = inner.prepare_order.front() {
+            if !self.is_expired(*prepared_at, now) {
+                break;
+            }
+            let (_, id) = inner.prepare_order.pop_front().unwrap();
+            inner.prepared.remove(&id);
+        }
+    }
+
+    fn is_expired(&self, prepared_at: u64, now: u64) -> bool {
+        now.saturating_sub(prepared_at) >= self.timeout_millis
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ack_request_header(offset: i64) -> AckMessageRequestHeader {
+        AckMessageRequestHeader {
+            consumer_group: CheetahString::from_static_str("test_group"),
+            topic: CheetahString::from_static_str("test_topic"),
+            queue_id: 0,
+            extra_info: CheetahString::from_static_str("0 0 0 0 0 broker-a 0 0"),
+            offset,
+            observed_pop_time: None,
+            topic_request_header: None,
+        }
+    }
+
+    fn group() -> CheetahString {
+        CheetahString::from_static_str("test_group")
+    }
+
+    #[test]
+    fn confirm_returns_prepared_ack_once() {
+        let table = TwoPhaseAckTable::new(1000);
+        let id = table.prepare_at(ack_request_header(5), None, 100);
+        let prepared = table.take_at(&group(), &id, 200).unwrap();
+        assert_eq!(prepared.request_header.offset, 5);
+        assert_eq!(
+            table.take_at(&group(), &id, 300).err(),
+            Some(TwoPhaseAckError::Unknown)
+        );
+        assert_eq!(table.prepared_num(), 0);
+    }
+
+    #[test]
+    fn abort_releases_prepared_ack() {
+        let table = TwoPhaseAckTable::new(60_000);
+        let id = table.prepare(ack_request_header(5), None);
+        assert_eq!(table.abort(&group(), &id), Ok(()));
+        assert_eq!(
+            table.confirm(&group(), &id).err(),
+            Some(TwoPhaseAckError::Unknown)
+        );
+    }
+
+    #[test]
+    fn ack_of_another_group_is_unknown() {
+        let table = TwoPhaseAckTable::new(60_000);
+        let id = table.prepare(ack_request_header(5), None);
+        let other_group = CheetahString::from_static_str("other_group");
+        assert_eq!(
+            table.abort(&other_group, &id),
+            Err(TwoPhaseAckError::Unknown)
+        );
+        assert_eq!(table.prepared_num(), 1);
+    }
+
+    #[test]
+    fn timed_out_ack_is_released() {
+        let table = TwoPhaseAckTable::new(1000);
+        let expired_id = table.prepare_at(ack_request_header(5), None, 100);
+        assert_eq!(
+            table.take_at(&group(), &expired_id, 1100).err(),
+            Some(TwoPhaseAckError::Expired)
+        );
+
+        // acks timing out are released by later preparations, even if never ended
+        table.prepare_at(ack_request_header(6), None, 200);
+        let id = table.prepare_at(ack_request_header(7), None, 1500);
+        assert_eq!(table.prepared_num(), 1);
+        assert!(table.take_at(&group(), &id, 1600).is_ok());
+    }
+}
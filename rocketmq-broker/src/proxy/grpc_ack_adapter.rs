@@ -0,0 +1,394 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Serves the acks of gRPC proxy clients with the ack processor of the remoting protocol.
+//!
+//! The request and response types mirror the `AckMessage` messages of the `apache.rocketmq.v2`
+//! gRPC protocol, a gRPC service decodes its requests into them and hands them to
+//! [`GrpcAckAdapter::ack_message`].
+
+use cheetah_string::CheetahString;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::header::ack_message_request_header::AckMessageRequestHeader;
+use rocketmq_remoting::protocol::header::extra_info_util::ExtraInfoUtil;
+use rocketmq_remoting::protocol::heartbeat::pop_ack_protocol_version::PopAckProtocolVersion;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
+use rocketmq_store::log_file::MessageStore;
+use tracing::warn;
+
+use crate::processor::ack_message_processor::AckMessageProcessor;
+
+/// Status codes of the gRPC protocol answered to an ack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Code {
+    Ok = 20000,
+    MultipleResults = 30000,
+    BadRequest = 40000,
+    InvalidReceiptHandle = 40012,
+    TooManyRequests = 42900,
+    InternalServerError = 50001,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Status {
+    pub code: Code,
+    pub message: String,
+}
+
+impl Status {
+    fn ok() -> Self {
+        Status {
+            code: Code::Ok,
+            message: String::from("OK"),
+        }
+    }
+
+    /// Status of a response of the remoting ack processor.
+    fn of_remoting_response(response: &RemotingCommand) -> Self {
+        let code = match ResponseCode::from(response.code()) {
+            ResponseCode::Success => return Status::ok(),
+            ResponseCode::MessageIllegal => Code::BadRequest,
+            ResponseCode::SystemBusy => Code::TooManyRequests,
+            _ => Code::InternalServerError,
+        };
+        Status {
+            code,
+            message: response
+                .remark()
+                .map(|remark| remark.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Resource {
+    pub name: CheetahString,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct AckMessageEntry {
+    pub message_id: CheetahString,
+    pub receipt_handle: CheetahString,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct AckMessageRequest {
+    pub group: Resource,
+    pub topic: Resource,
+    pub entries: Vec<AckMessageEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct AckMessageResultEntry {
+    pub message_id: CheetahString,
+    pub receipt_handle: CheetahString,
+    pub status: Status,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct AckMessageResponse {
+    pub status: Status,
+    pub entries: Vec<AckMessageResultEntry>,
+}
+
+/// Number of receipt handle fields of a gRPC ack, the last ones being the queue id and the
+/// queue offset of the acked message.
+const RECEIPT_HANDLE_MIN_FIELDS: usize = 8;
+
+/// Acks the entries of gRPC ack requests one by one through
+/// [`AckMessageProcessor::process_request`], so that gRPC clients get the very checks and
+/// revive writes of remoting clients.
+pub(crate) struct GrpcAckAdapter<MS> {
+    ack_message_processor: AckMessageProcessor<MS>,
+}
+
+impl<MS> GrpcAckAdapter<MS>
+where
+    MS: MessageStore,
+{
+    pub fn new(ack_message_processor: AckMessageProcessor<MS>) -> Self {
+        GrpcAckAdapter {
+            ack_message_processor,
+        }
+    }
+
+    pub async fn ack_message(
+        &mut self,
+        channel: Channel,
+        ctx: ConnectionHandlerContext,
+        request: AckMessageRequest,
+    ) -> AckMessageResponse {
+        let mut entries = Vec::with_capacity(request.entries.len());
+        for entry in request.entries.iter() {
+            let status = match to_remoting_ack(
+                &request.group,
+                &request.topic,
+                entry,
+                channel.pop_ack_protocol_version(),
+            ) {
+                Ok(ack_request) => {
+                    self.process_ack(channel.clone(), ctx.clone(), ack_request)
+                        .await
+                }
+                Err(status) => status,
+            };
+            entries.push(AckMessageResultEntry {
+                message_id: entry.message_id.clone(),
+                receipt_handle: entry.receipt_handle.clone(),
+                status,
+            });
+        }
+        AckMessageResponse {
+            status: response_status(&entries),
+            entries,
+        }
+    }
+
+    async fn process_ack(
+        &mut self,
+        channel: Channel,
+        ctx: ConnectionHandlerContext,
+        request: RemotingCommand,
+    ) -> Status {
+        match self
+            .ack_message_processor
+            .process_request(channel, ctx, RequestCode::AckMessage, request)
+            .await
+        {
+            Ok(Some(response)) => Status::of_remoting_response(&response),
+            Ok(None) => Status::ok(),
+            Err(e) => {
+                warn!("grpc ack failed, {}", e);
+                Status {
+                    code: Code::InternalServerError,
+                    message: e.to_string(),
+                }
+            }
+        }
+    }
+}
+
+/// Builds the remoting ack of an entry. A gRPC receipt handle carries the fields of an
+/// `extraInfo` separated by [`PopAckProtocolVersion::V2`], followed by the queue id and the
+/// offset of the message; they are re-encoded with the version of the channel the processor
+/// reads them with.
+fn to_remoting_ack(
+    group: &Resource,
+    topic: &Resource,
+    entry: &AckMessageEntry,
+    version: PopAckProtocolVersion,
+) -> Result<RemotingCommand, Status> {
+    let invalid_receipt_handle = |reason: String| Status {
+        code: Code::InvalidReceiptHandle,
+        message: format!(
+            "receipt handle {} is invalid, {}",
+            entry.receipt_handle, reason
+        ),
+    };
+    let fields = PopAckProtocolVersion::V2
+        .split_extra_info(entry.receipt_handle.as_str())
+        .map_err(|e| invalid_receipt_handle(e.to_string()))?;
+    if fields.len() < RECEIPT_HANDLE_MIN_FIELDS {
+        return Err(invalid_receipt_handle(format!(
+            "fields: {}, expected at least: {}",
+            fields.len(),
+            RECEIPT_HANDLE_MIN_FIELDS
+        )));
+    }
+    let queue_id =
+        ExtraInfoUtil::get_queue_id(&fields).map_err(|e| invalid_receipt_handle(e.to_string()))?;
+    let offset = ExtraInfoUtil::get_queue_offset(&fields)
+        .map_err(|e| invalid_receipt_handle(e.to_string()))?;
+    let request_header = AckMessageRequestHeader {
+        consumer_group: group.name.clone(),
+        topic: topic.name.clone(),
+        queue_id,
+        extra_info: CheetahString::from_string(version.join_extra_info(&fields)),
+        offset,
+        observed_pop_time: None,
+        topic_request_header: None,
+    };
+    let mut request =
+        RemotingCommand::create_request_command(RequestCode::AckMessage, request_header);
+    request.make_custom_header_to_net();
+    Ok(request)
+}
+
+/// Status of a whole ack request: the status of its entries if they all share one,
+/// `MultipleResults` otherwise.
+fn response_status(entries: &[AckMessageResultEntry]) -> Status {
+    match entries.split_first() {
+        None => Status::ok(),
+        Some((first, rest))
+            if rest
+                .iter()
+                .all(|entry| entry.status.code == first.status.code) =>
+        {
+            first.status.clone()
+        }
+        Some(_) => Status {
+            code: Code::MultipleResults,
+            message: String::from("ack results of the entries differ"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::pop_ack_constants::PopAckConstants;
+    use rocketmq_common::TimeUtils::get_current_millis;
+    use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContextWrapper;
+    use rocketmq_rust::ArcMut;
+
+    use super::*;
+    use crate::processor::ack_message_processor::tests::TestBroker;
+    use crate::processor::ack_message_processor::tests::TEST_GROUP;
+    use crate::processor::ack_message_processor::tests::TEST_TOPIC;
+    use crate::util::test_channel::test_channel;
+
+    fn receipt_handle(broker_name: &str, offset: i64) -> CheetahString {
+        CheetahString::from_string(ExtraInfoUtil::build_extra_info_with_msg_queue_offset(
+            0,
+            get_current_millis() as i64,
+            30_000,
+            0,
+            TEST_TOPIC,
+            broker_name,
+            0,
+            offset,
+        ))
+    }
+
+    fn ack_request(receipt_handles: Vec<CheetahString>) -> AckMessageRequest {
+        AckMessageRequest {
+            group: Resource {
+                name: CheetahString::from_static_str(TEST_GROUP),
+            },
+            topic: Resource {
+                name: CheetahString::from_static_str(TEST_TOPIC),
+            },
+            entries: receipt_handles
+                .into_iter()
+                .enumerate()
+                .map(|(index, receipt_handle)| AckMessageEntry {
+                    message_id: CheetahString::from_string(format!("msg-{}", index)),
+                    receipt_handle,
+                })
+                .collect(),
+        }
+    }
+
+    fn ack(
+        broker: &TestBroker,
+        version: PopAckProtocolVersion,
+        request: AckMessageRequest,
+    ) -> AckMessageResponse {
+        let mut adapter = GrpcAckAdapter::new(broker.processor.clone());
+        broker.runtime.block_on(async {
+            let channel = test_channel().await;
+            channel.set_pop_ack_protocol_version(version);
+            let ctx = ArcMut::new(ConnectionHandlerContextWrapper::new(channel.clone()));
+            adapter
+                .ack_message(channel, ArcMut::downgrade(&ctx), request)
+                .await
+        })
+    }
+
+    fn revive_puts(broker: &TestBroker) -> usize {
+        let revive_topic = PopAckConstants::build_cluster_revive_topic(
+            broker
+                .broker_config
+                .broker_identity
+                .broker_cluster_name
+                .as_str(),
+        );
+        broker.message_store.put_messages_of(&revive_topic).len()
+    }
+
+    #[test]
+    fn grpc_ack_is_put_to_revive_topic() {
+        let broker = TestBroker::new(BrokerConfig::default());
+        let broker_name = broker.broker_config.broker_identity.broker_name.clone();
+
+        // the processor reads the handle with the version of the channel, whatever it is
+        for (offset, version) in [
+            (10, PopAckProtocolVersion::V1),
+            (11, PopAckProtocolVersion::V2),
+        ] {
+            let response = ack(
+                &broker,
+                version,
+                ack_request(vec![receipt_handle(&broker_name, offset)]),
+            );
+            assert_eq!(response.status.code, Code::Ok);
+            assert_eq!(response.entries[0].status.code, Code::Ok);
+            assert_eq!(response.entries[0].message_id.as_str(), "msg-0");
+        }
+        assert_eq!(revive_puts(&broker), 2);
+    }
+
+    #[test]
+    fn invalid_receipt_handle_is_reported_per_entry() {
+        let broker = TestBroker::new(BrokerConfig::default());
+        let broker_name = broker.broker_config.broker_identity.broker_name.clone();
+
+        let response = ack(
+            &broker,
+            PopAckProtocolVersion::V2,
+            ack_request(vec![
+                receipt_handle(&broker_name, 10),
+                CheetahString::from_static_str("0 1000 30000"),
+            ]),
+        );
+
+        assert_eq!(response.status.code, Code::MultipleResults);
+        assert_eq!(response.entries[0].status.code, Code::Ok);
+        assert_eq!(response.entries[1].status.code, Code::InvalidReceiptHandle);
+        assert_eq!(revive_puts(&broker), 1);
+    }
+
+    #[test]
+    fn rejected_ack_maps_to_bad_request() {
+        let broker = TestBroker::new(BrokerConfig::default());
+        let broker_name = broker.broker_config.broker_identity.broker_name.clone();
+        // revive queue 99 is out of the 8 revive queues of the broker
+        let handle = ExtraInfoUtil::build_extra_info_with_msg_queue_offset(
+            0,
+            get_current_millis() as i64,
+            30_000,
+            99,
+            TEST_TOPIC,
+            &broker_name,
+            0,
+            10,
+        );
+
+        let response = ack(
+            &broker,
+            PopAckProtocolVersion::V2,
+            ack_request(vec![CheetahString::from_string(handle)]),
+        );
+
+        assert_eq!(response.status.code, Code::BadRequest);
+        assert!(response.status.message.starts_with("reviveQueueId 99"));
+        assert_eq!(revive_puts(&broker), 0);
+    }
+}
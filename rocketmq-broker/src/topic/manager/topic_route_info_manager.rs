@@ -303,6 +303,17 @@ impl TopicRouteInfoManager {
         broker_addr
     }
 
+    /// Returns `true` if the name server routes register a slave of broker `broker_name`.
+    pub fn has_slave(&self, broker_name: &CheetahString) -> bool {
+        self.broker_addr_table
+            .get(broker_name)
+            .is_some_and(|broker_addrs| {
+                broker_addrs
+                    .keys()
+                    .any(|broker_id| *broker_id != mix_all::MASTER_ID)
+            })
+    }
+
     pub async fn get_topic_subscribe_info(
         &self,
         topic: &CheetahString,
@@ -70,8 +70,14 @@ impl<MS> DefaultTransactionalMessageCheckListener<MS> {
 
 impl<MS> TransactionalMessageCheckListener for DefaultTransactionalMessageCheckListener<MS>
 where
-    MS: MessageStore,
+    MS: MessageStore + Send + Sync + 'static,
 {
+    fn resolve_half_msg(&mut self, msg_ext: MessageExt) {
+        if let Err(e) = self.inner.resolve_half_msg(msg_ext) {
+            warn!("Check back half message failed: {:?}", e);
+        }
+    }
+
     async fn resolve_discard_msg(&mut self, msg_ext: MessageExt) {
         error!(
             "MsgExt:{} has been checked too many times, so discard it by moving it to system \
@@ -15,27 +15,39 @@
  * limitations under the License.
  */
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
 
+use cheetah_string::CheetahString;
+use rocketmq_client_rust::consumer::pull_status::PullStatus;
 use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_common::common::message::message_single::Message;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::MessageAccessor::MessageAccessor;
+use rocketmq_common::MessageDecoder;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::protocol::header::end_transaction_request_header::EndTransactionRequestHeader;
 use rocketmq_store::base::message_result::PutMessageResult;
+use rocketmq_store::base::message_status_enum::PutMessageStatus;
 use rocketmq_store::log_file::MessageStore;
 use tokio::sync::Mutex;
 use tracing::error;
+use tracing::info;
 use tracing::warn;
 
 use crate::transaction::operation_result::OperationResult;
 use crate::transaction::queue::message_queue_op_context::MessageQueueOpContext;
+use crate::transaction::queue::transactional_message_bridge::get_op_queue_by_half;
 use crate::transaction::queue::transactional_message_bridge::TransactionalMessageBridge;
 use crate::transaction::queue::transactional_message_util::TransactionalMessageUtil;
 use crate::transaction::queue::transactional_op_batch_service::TransactionalOpBatchService;
 use crate::transaction::transaction_metrics::TransactionMetrics;
+use crate::transaction::transactional_message_check_listener::TransactionalMessageCheckListener;
 use crate::transaction::transactional_message_service::TransactionalMessageService;
 
 const PULL_MSG_RETRY_NUMBER: i32 = 1;
@@ -130,6 +142,7 @@ where
             }
         }
 
+        mq_context.total_size_add_and_get(-((sb.len() - more_data_length) as i32));
         if sb.is_empty() {
             return None;
         }
@@ -140,6 +153,216 @@ where
             sb.as_bytes(),
         ))
     }
+
+    /// Writes the removals buffered in the delete contexts to the op queues.
+    pub async fn batch_send_op_message(&self) {
+        let queue_ids: Vec<i32> = self.delete_context.lock().await.keys().copied().collect();
+        for queue_id in queue_ids {
+            let Some(msg) = self.get_op_message(queue_id, None).await else {
+                continue;
+            };
+            if !self
+                .transactional_message_bridge
+                .write_op(queue_id, msg)
+                .await
+            {
+                error!(
+                    "Transaction batch op message write failed. queueId is {}",
+                    queue_id
+                );
+            }
+        }
+    }
+
+    /// Reads the op queue from `op_offset`, returns the half queue offsets removed at or after
+    /// `half_offset` and, for every op message, its queue offset along with the largest half
+    /// queue offset it removes.
+    async fn read_op_queue(
+        &self,
+        queue_id: i32,
+        mut op_offset: i64,
+        half_offset: i64,
+    ) -> (HashSet<i64>, Vec<(i64, i64)>, i64) {
+        let mut removed = HashSet::new();
+        let mut op_messages = Vec::new();
+        loop {
+            let Some(pull_result) = self
+                .transactional_message_bridge
+                .get_op_message(queue_id, op_offset, OP_MSG_PULL_NUMS)
+                .await
+            else {
+                break;
+            };
+            let msgs = pull_result.msg_found_list.unwrap_or_default();
+            if pull_result.pull_status != PullStatus::Found || msgs.is_empty() {
+                break;
+            }
+            for msg in &msgs {
+                let msg = &msg.message_ext_inner;
+                let mut max_removed = -1;
+                if msg.get_tags().as_deref() == Some(TransactionalMessageUtil::REMOVE_TAG) {
+                    let body = msg.get_body().map(|body| body.to_vec()).unwrap_or_default();
+                    for offset in String::from_utf8_lossy(&body)
+                        .split(TransactionalMessageUtil::OFFSET_SEPARATOR)
+                        .filter_map(|offset| offset.trim().parse::<i64>().ok())
+                    {
+                        max_removed = max_removed.max(offset);
+                        if offset >= half_offset {
+                            removed.insert(offset);
+                        }
+                    }
+                } else {
+                    error!("Found a illegal tag in opMessageExt= {} ", msg);
+                }
+                op_messages.push((msg.queue_offset, max_removed));
+            }
+            op_offset = pull_result.next_begin_offset as i64;
+        }
+        (removed, op_messages, op_offset)
+    }
+
+    /// Puts `msg_ext` back at the end of its half queue with its check times, it then refers to
+    /// the new copy.
+    async fn put_back_half_msg_queue(&self, msg_ext: &mut MessageExt) -> bool {
+        let mut msg_inner = TransactionalMessageBridge::<MS>::renew_half_message_inner(msg_ext);
+        msg_inner.properties_string =
+            MessageDecoder::message_properties_to_string(msg_inner.get_properties());
+        let put_message_result = self
+            .transactional_message_bridge
+            .put_message_return_result(msg_inner)
+            .await;
+        if put_message_result.put_message_status() != PutMessageStatus::PutOk {
+            error!(
+                "PutBackToHalfQueueReturnResult write failed, topic: {}, queueId: {}, msgId: {}",
+                msg_ext.get_topic(),
+                msg_ext.queue_id,
+                msg_ext.msg_id()
+            );
+            return false;
+        }
+        if let Some(append_message_result) = put_message_result.append_message_result() {
+            msg_ext.queue_offset = append_message_result.logics_offset;
+            msg_ext.commit_log_offset = append_message_result.wrote_offset;
+            if let Some(msg_id) = append_message_result.msg_id.as_ref() {
+                msg_ext.set_msg_id(CheetahString::from_string(msg_id.clone()));
+            }
+        }
+        true
+    }
+
+    /// Checks back the half messages of queue `half_queue` found neither committed nor rolled
+    /// back, starting from its consume offset.
+    async fn check_half_queue<L: TransactionalMessageCheckListener>(
+        &self,
+        half_queue: &MessageQueue,
+        transaction_timeout: u64,
+        transaction_check_max: i32,
+        listener: &mut L,
+    ) {
+        let start_time = get_current_millis();
+        let queue_id = half_queue.get_queue_id();
+        let op_queue = get_op_queue_by_half(
+            queue_id,
+            self.transactional_message_bridge
+                .broker_config
+                .broker_name
+                .clone(),
+        );
+        let half_offset = self
+            .transactional_message_bridge
+            .fetch_consume_offset(half_queue);
+        let op_offset = self
+            .transactional_message_bridge
+            .fetch_consume_offset(&op_queue);
+        if half_offset < 0 || op_offset < 0 {
+            error!(
+                "MessageQueue: {} illegal offset read: {}, op offset: {},skip this queue",
+                half_queue, half_offset, op_offset
+            );
+            return;
+        }
+        let (removed, op_messages, next_op_offset) =
+            self.read_op_queue(queue_id, op_offset, half_offset).await;
+        // half messages put back by this check are left to the next one
+        let half_max_offset = self
+            .transactional_message_bridge
+            .message_store
+            .get_max_offset_in_queue(half_queue.get_topic_cs(), queue_id);
+
+        let mut offset = half_offset;
+        while offset < half_max_offset {
+            if get_current_millis() - start_time > MAX_PROCESS_TIME_LIMIT as u64 {
+                info!(
+                    "Queue={} process time reach max={}",
+                    half_queue, MAX_PROCESS_TIME_LIMIT
+                );
+                break;
+            }
+            if removed.contains(&offset) {
+                offset += 1;
+                continue;
+            }
+            let Some(mut msg_ext) = self
+                .transactional_message_bridge
+                .get_half_message(queue_id, offset, PULL_MSG_RETRY_NUMBER)
+                .await
+                .and_then(|pull_result| pull_result.msg_found_list)
+                .and_then(|mut msgs| (!msgs.is_empty()).then(|| msgs.swap_remove(0)))
+                .map(|msg| msg.message_ext_inner.clone())
+            else {
+                break;
+            };
+
+            let check_times = msg_ext
+                .get_property(&CheetahString::from_static_str(
+                    MessageConst::PROPERTY_TRANSACTION_CHECK_TIMES,
+                ))
+                .and_then(|times| times.parse::<i32>().ok())
+                .unwrap_or(0);
+            if check_times >= transaction_check_max {
+                listener.resolve_discard_msg(msg_ext).await;
+                offset += 1;
+                continue;
+            }
+
+            let check_immunity_time = msg_ext
+                .get_user_property(&CheetahString::from_static_str(
+                    MessageConst::PROPERTY_CHECK_IMMUNITY_TIME_IN_SECONDS,
+                ))
+                .map_or(transaction_timeout, |time| {
+                    TransactionalMessageUtil::get_immunity_time(time.as_str(), transaction_timeout)
+                });
+            // half messages are stored in born order, the ones after are not due either
+            if (get_current_millis() as i64) - msg_ext.born_timestamp < check_immunity_time as i64 {
+                break;
+            }
+
+            MessageAccessor::put_property(
+                &mut msg_ext,
+                CheetahString::from_static_str(MessageConst::PROPERTY_TRANSACTION_CHECK_TIMES),
+                CheetahString::from_string((check_times + 1).to_string()),
+            );
+            if !self.put_back_half_msg_queue(&mut msg_ext).await {
+                break;
+            }
+            listener.resolve_half_msg(msg_ext);
+            offset += 1;
+        }
+
+        if offset != half_offset {
+            self.transactional_message_bridge
+                .update_consume_offset(half_queue, offset);
+        }
+        // op messages only removing half messages behind the new offset are done with
+        let new_op_offset = op_messages
+            .iter()
+            .find(|(_, max_removed)| *max_removed >= offset)
+            .map_or(next_op_offset, |(op_offset, _)| *op_offset);
+        if new_op_offset != op_offset {
+            self.transactional_message_bridge
+                .update_consume_offset(&op_queue, new_op_offset);
+        }
+    }
 }
 
 impl<MS> TransactionalMessageService for DefaultTransactionalMessageService<MS>
@@ -221,8 +444,33 @@ where
         self.get_half_message_by_offset(request_header.commit_log_offset as i64)
     }
 
-    fn check(&self, _transaction_timeout: u64, _transaction_check_max: i32) {
-        todo!()
+    async fn check<L: TransactionalMessageCheckListener>(
+        &mut self,
+        transaction_timeout: u64,
+        transaction_check_max: i32,
+        listener: &mut L,
+    ) {
+        // removals still buffered would have their half messages checked back
+        self.batch_send_op_message().await;
+        let half_queues = self.transactional_message_bridge.fetch_message_queues(
+            &CheetahString::from_static_str(TransactionalMessageUtil::build_half_topic()),
+        );
+        if half_queues.is_empty() {
+            warn!(
+                "The queue of topic is empty :{}",
+                TransactionalMessageUtil::build_half_topic()
+            );
+            return;
+        }
+        for half_queue in half_queues {
+            self.check_half_queue(
+                &half_queue,
+                transaction_timeout,
+                transaction_check_max,
+                listener,
+            )
+            .await;
+        }
     }
 
     fn open(&self) -> bool {
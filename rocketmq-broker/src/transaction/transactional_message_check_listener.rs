@@ -18,8 +18,17 @@
 use rocketmq_common::common::message::message_ext::MessageExt;
 
 /// Trait defining the listener for transactional message checks.
-/// This trait provides a method for resolving discarded messages.
-pub trait TransactionalMessageCheckListener {
+/// This trait provides methods for checking back half messages and resolving discarded ones.
+#[trait_variant::make(TransactionalMessageCheckListener: Send)]
+pub trait TransactionalMessageCheckListenerLocal: Sync + 'static {
+    /// Asks the producer of a half message whose transaction is still unknown for its state,
+    /// the producer answers with an end transaction request.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg_ext` - The half message to check back
+    fn resolve_half_msg(&mut self, msg_ext: MessageExt);
+
     /// Attempts to resolve a discarded message, typically called when a transaction
     /// message needs cleanup or final disposition.
     ///
@@ -14,6 +14,79 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::Arc;
 
-#[derive(Clone)]
-pub struct TransactionalMessageCheckService;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_rust::ArcMut;
+use tokio::sync::Notify;
+use tracing::info;
+
+use crate::transaction::transactional_message_check_listener::TransactionalMessageCheckListener;
+use crate::transaction::transactional_message_service::TransactionalMessageService;
+
+/// Periodically checks back with their producers the half messages whose transaction is still
+/// unknown.
+pub struct TransactionalMessageCheckService<TS, L> {
+    broker_config: Arc<BrokerConfig>,
+    transactional_message_service: ArcMut<TS>,
+    listener: ArcMut<L>,
+    shutdown: Arc<Notify>,
+}
+
+impl<TS, L> TransactionalMessageCheckService<TS, L>
+where
+    TS: TransactionalMessageService,
+    L: TransactionalMessageCheckListener,
+{
+    pub fn new(
+        broker_config: Arc<BrokerConfig>,
+        transactional_message_service: ArcMut<TS>,
+        listener: ArcMut<L>,
+    ) -> Self {
+        Self {
+            broker_config,
+            transactional_message_service,
+            listener,
+            shutdown: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn start(&self) {
+        let broker_config = self.broker_config.clone();
+        let mut transactional_message_service = self.transactional_message_service.clone();
+        let mut listener = self.listener.clone();
+        let shutdown = self.shutdown.clone();
+        tokio::spawn(async move {
+            info!("TransactionalMessageCheckService started");
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(tokio::time::Duration::from_millis(
+                        broker_config.transaction_check_interval,
+                    )) => {}
+                    _ = shutdown.notified() => {
+                        info!("TransactionalMessageCheckService: shutdown..........");
+                        break;
+                    }
+                }
+                let begin = get_current_millis();
+                info!("Begin to check prepare message, begin time:{}", begin);
+                transactional_message_service
+                    .check(
+                        broker_config.transaction_timeout,
+                        broker_config.transaction_check_max,
+                        listener.as_mut(),
+                    )
+                    .await;
+                info!(
+                    "End to check prepare message, consumed time:{}",
+                    get_current_millis() - begin
+                );
+            }
+        });
+    }
+
+    pub fn shutdown(&self) {
+        self.shutdown.notify_waiters();
+    }
+}
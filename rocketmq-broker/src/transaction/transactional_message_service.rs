@@ -21,6 +21,7 @@ use rocketmq_store::base::message_result::PutMessageResult;
 
 use crate::transaction::operation_result::OperationResult;
 use crate::transaction::transaction_metrics::TransactionMetrics;
+use crate::transaction::transactional_message_check_listener::TransactionalMessageCheckListener;
 
 /// Trait defining the local transactional message service.
 /// This trait provides methods for preparing, committing, rolling back, and checking transactional
@@ -86,17 +87,20 @@ pub trait TransactionalMessageServiceLocal: Sync + 'static {
     fn rollback_message(&mut self, request_header: &EndTransactionRequestHeader)
         -> OperationResult;
 
-    /// Checks the state of transactional messages.
+    /// Checks the state of transactional messages: half messages neither committed nor rolled
+    /// back within the transaction timeout are checked back with their producer through the
+    /// listener, those checked too many times are discarded.
     ///
     /// # Arguments
     ///
     /// * `transaction_timeout` - The timeout for the transaction.
     /// * `transaction_check_max` - The maximum number of transaction checks.
-    fn check(
-        &self,
+    /// * `listener` - The listener checking back and discarding half messages.
+    async fn check<L: TransactionalMessageCheckListener>(
+        &mut self,
         transaction_timeout: u64,
         transaction_check_max: i32,
-        // listener: AbstractTransactionalMessageCheckListener,
+        listener: &mut L,
     );
 
     /// Opens the transactional message service.
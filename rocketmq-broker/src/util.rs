@@ -16,3 +16,7 @@
  */
 
 pub(crate) mod hook_utils;
+#[cfg(test)]
+pub(crate) mod test_channel;
+#[cfg(test)]
+pub(crate) mod test_message_store;
@@ -175,18 +175,10 @@ impl HookUtils {
         if tran_type == MessageSysFlag::TRANSACTION_NOT_TYPE
             || tran_type == MessageSysFlag::TRANSACTION_COMMIT_TYPE
         {
-            if !Self::is_rolled_timer_message(msg) && Self::check_if_timer_message(msg) {
-                if !message_store_config.timer_wheel_enable {
-                    // wheel timer is not enabled, reject the message
-                    return Some(PutMessageResult::new_default(
-                        PutMessageStatus::WheelTimerNotEnable,
-                    ));
-                }
-                if let Some(transform_res) =
-                    Self::transform_timer_message(timer_message_store, message_store_config, msg)
-                {
-                    return Some(transform_res);
-                }
+            if let Some(result) =
+                Self::handle_timer_message(timer_message_store, message_store_config, msg)
+            {
+                return Some(result);
             }
             // Delay Delivery
             if msg.message_ext_inner.message.get_delay_time_level() > 0 {
@@ -196,6 +188,25 @@ impl HookUtils {
         None
     }
 
+    /// Routes a message carrying a delivery time to the timer topic, the timer message store
+    /// puts it back to its real topic once the time is reached.
+    pub fn handle_timer_message(
+        timer_message_store: &TimerMessageStore,
+        message_store_config: &Arc<MessageStoreConfig>,
+        msg: &mut MessageExtBrokerInner,
+    ) -> Option<PutMessageResult> {
+        if Self::is_rolled_timer_message(msg) || !Self::check_if_timer_message(msg) {
+            return None;
+        }
+        if !message_store_config.timer_wheel_enable {
+            // wheel timer is not enabled, reject the message
+            return Some(PutMessageResult::new_default(
+                PutMessageStatus::WheelTimerNotEnable,
+            ));
+        }
+        Self::transform_timer_message(timer_message_store, message_store_config, msg)
+    }
+
     fn is_rolled_timer_message(msg: &MessageExtBrokerInner) -> bool {
         timer_message_store::TIMER_TOPIC == msg.topic()
     }
@@ -262,19 +273,25 @@ impl HookUtils {
     ) -> Option<PutMessageResult> {
         let delay_level = msg.message_ext_inner.message.get_delay_time_level();
         let deliver_ms = match msg.property(MessageConst::PROPERTY_TIMER_DELAY_SEC) {
-            Some(delay_sec) => get_current_millis() + delay_sec.parse::<u64>().unwrap() * 1000,
+            Some(delay_sec) => delay_sec
+                .parse::<u64>()
+                .ok()
+                .map(|delay_sec| get_current_millis() + delay_sec * 1000),
             None => match msg.property(MessageConst::PROPERTY_TIMER_DELAY_MS) {
-                Some(delay_ms) => get_current_millis() + delay_ms.parse::<u64>().unwrap(),
-                None => match msg.property(MessageConst::PROPERTY_TIMER_DELIVER_MS) {
-                    Some(deliver_ms) => deliver_ms.parse::<u64>().unwrap(),
-                    None => {
-                        return Some(PutMessageResult::new_default(
-                            PutMessageStatus::WheelTimerMsgIllegal,
-                        ))
-                    }
-                },
+                Some(delay_ms) => delay_ms
+                    .parse::<u64>()
+                    .ok()
+                    .map(|delay_ms| get_current_millis() + delay_ms),
+                None => msg
+                    .property(MessageConst::PROPERTY_TIMER_DELIVER_MS)
+                    .and_then(|deliver_ms| deliver_ms.parse::<u64>().ok()),
             },
         };
+        let Some(deliver_ms) = deliver_ms else {
+            return Some(PutMessageResult::new_default(
+                PutMessageStatus::WheelTimerMsgIllegal,
+            ));
+        };
 
         if deliver_ms > get_current_millis() {
             if delay_level <= 0
@@ -286,13 +303,7 @@ impl HookUtils {
                 ));
             }
 
-            let timer_precision_ms = message_store_config.timer_precision_ms;
-            let deliver_ms = if deliver_ms % timer_precision_ms == 0 {
-                deliver_ms - timer_precision_ms
-            } else {
-                (deliver_ms / timer_precision_ms) * timer_precision_ms
-            };
-
+            // the timer wheel delivers at the exact time, no need to round to the precision
             if timer_message_store.is_reject(deliver_ms) {
                 return Some(PutMessageResult::new_default(
                     PutMessageStatus::WheelTimerFlowControl,
@@ -401,6 +412,24 @@ mod tests {
 
     use super::*;
 
+    fn timer_message(property: &'static str, value: String) -> MessageExtBrokerInner {
+        let mut msg = MessageExtBrokerInner::default();
+        msg.message_ext_inner.message.topic = "test_topic".into();
+        msg.message_ext_inner.queue_id = 3;
+        msg.message_ext_inner.message.properties.insert(
+            CheetahString::from_static_str(property),
+            CheetahString::from_string(value),
+        );
+        msg
+    }
+
+    fn timer_wheel_config(timer_wheel_enable: bool) -> Arc<MessageStoreConfig> {
+        Arc::new(MessageStoreConfig {
+            timer_wheel_enable,
+            ..MessageStoreConfig::default()
+        })
+    }
+
     #[test]
     fn check_inner_batch_returns_message_illegal_when_inner_batch_flag_is_set_but_cq_type_is_not_batch_cq(
     ) {
@@ -418,4 +447,71 @@ mod tests {
             PutMessageStatus::MessageIllegal
         );
     }
+
+    #[test]
+    fn handle_timer_message_routes_message_to_timer_topic_at_exact_deliver_time() {
+        let deliver_ms = get_current_millis() + 60_123;
+        let mut msg = timer_message(
+            MessageConst::PROPERTY_TIMER_DELIVER_MS,
+            deliver_ms.to_string(),
+        );
+
+        let result = HookUtils::handle_timer_message(
+            &TimerMessageStore::new_empty(),
+            &timer_wheel_config(true),
+            &mut msg,
+        );
+
+        assert!(result.is_none());
+        assert_eq!(msg.topic(), timer_message_store::TIMER_TOPIC);
+        assert_eq!(msg.message_ext_inner.queue_id, 0);
+        assert_eq!(
+            msg.property(MessageConst::PROPERTY_TIMER_OUT_MS).unwrap(),
+            deliver_ms.to_string()
+        );
+        assert_eq!(
+            msg.property(MessageConst::PROPERTY_REAL_TOPIC).unwrap(),
+            "test_topic"
+        );
+        assert_eq!(
+            msg.property(MessageConst::PROPERTY_REAL_QUEUE_ID).unwrap(),
+            "3"
+        );
+    }
+
+    #[test]
+    fn handle_timer_message_rejects_timer_message_when_timer_wheel_is_disabled() {
+        let mut msg = timer_message(MessageConst::PROPERTY_TIMER_DELAY_SEC, "10".to_string());
+
+        let result = HookUtils::handle_timer_message(
+            &TimerMessageStore::new_empty(),
+            &timer_wheel_config(false),
+            &mut msg,
+        );
+
+        assert_eq!(
+            result.unwrap().put_message_status(),
+            PutMessageStatus::WheelTimerNotEnable
+        );
+        assert_eq!(msg.topic(), "test_topic");
+    }
+
+    #[test]
+    fn handle_timer_message_rejects_malformed_deliver_time() {
+        let mut msg = timer_message(
+            MessageConst::PROPERTY_TIMER_DELIVER_MS,
+            "tomorrow".to_string(),
+        );
+
+        let result = HookUtils::handle_timer_message(
+            &TimerMessageStore::new_empty(),
+            &timer_wheel_config(true),
+            &mut msg,
+        );
+
+        assert_eq!(
+            result.unwrap().put_message_status(),
+            PutMessageStatus::WheelTimerMsgIllegal
+        );
+    }
 }
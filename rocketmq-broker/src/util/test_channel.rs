@@ -0,0 +1,41 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A [`Channel`] over a loopback connection for processor tests, nothing is ever read from it.
+
+use std::collections::HashMap;
+
+use rocketmq_remoting::connection::Connection;
+use rocketmq_remoting::net::channel::Channel;
+use rocketmq_rust::ArcMut;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+
+/// Opens a loopback connection and wraps its client side in a [`Channel`], which must be done
+/// from within a runtime.
+pub(crate) async fn test_channel() -> Channel {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let remote_address = listener.local_addr().unwrap();
+    let stream = TcpStream::connect(remote_address).await.unwrap();
+    let local_address = stream.local_addr().unwrap();
+    Channel::new(
+        local_address,
+        remote_address,
+        Connection::new(stream),
+        ArcMut::new(HashMap::new()),
+    )
+}
@@ -0,0 +1,470 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+#![allow(unused_variables)]
+
+//! An in-memory [`MessageStore`] for processor tests: queues only have an offset range, puts
+//! are recorded instead of being written and their status can be scripted. Messages added to
+//! a queue are read back through a mapped file in a temporary directory.
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+use rocketmq_common::common::message::message_batch::MessageExtBatch;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_store::base::get_message_result::GetMessageResult;
+use rocketmq_store::base::message_result::AppendMessageResult;
+use rocketmq_store::base::message_result::PutMessageResult;
+use rocketmq_store::base::message_status_enum::AppendMessageStatus;
+use rocketmq_store::base::message_status_enum::GetMessageStatus;
+use rocketmq_store::base::message_status_enum::PutMessageStatus;
+use rocketmq_store::base::query_message_result::QueryMessageResult;
+use rocketmq_store::base::select_result::SelectMappedBufferResult;
+use rocketmq_store::filter::MessageFilter;
+use rocketmq_store::hook::put_message_hook::BoxedPutMessageHook;
+use rocketmq_store::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use rocketmq_store::log_file::mapped_file::MappedFile;
+use rocketmq_store::log_file::ArcConsumeQueue;
+use rocketmq_store::log_file::DispatchRequest;
+use rocketmq_store::log_file::MessageStore;
+use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
+use rocketmq_store::store::running_flags::RunningFlags;
+use rocketmq_store::timer::timer_message_store::TimerMessageStore;
+
+use crate::processor::pop_message_processor::inner_queue_offsets;
+
+#[derive(Default)]
+pub(crate) struct TestMessageStore {
+    running_flags: RunningFlags,
+    offset_ranges: Mutex<HashMap<(CheetahString, i32), (i64, i64)>>,
+    put_statuses: Mutex<VecDeque<PutMessageStatus>>,
+    put_messages: Mutex<Vec<MessageExt>>,
+    stored_messages: Mutex<HashMap<(CheetahString, i32, i64), MessageExt>>,
+    get_message_reads: AtomicU64,
+    puts_held: AtomicBool,
+    puts_kept: AtomicBool,
+    put_message_hook_list: Arc<RwLock<Vec<BoxedPutMessageHook>>>,
+}
+
+static TEST_STORE_ID: AtomicU64 = AtomicU64::new(0);
+
+impl TestMessageStore {
+    /// Makes queue `queue_id` of `topic` hold the offsets `[min_offset, max_offset]`.
+    pub(crate) fn set_offset_range(
+        &self,
+        topic: &str,
+        queue_id: i32,
+        min_offset: i64,
+        max_offset: i64,
+    ) {
+        self.offset_ranges.lock().insert(
+            (CheetahString::from_string(topic.to_string()), queue_id),
+            (min_offset, max_offset),
+        );
+    }
+
+    /// Answers the next puts with `statuses`, in order, the puts after them succeed.
+    pub(crate) fn push_put_statuses(&self, statuses: impl IntoIterator<Item = PutMessageStatus>) {
+        self.put_statuses.lock().extend(statuses);
+    }
+
+    /// Makes the next puts record their message and then never complete, as puts stuck in a
+    /// store do.
+    pub(crate) fn hold_puts(&self) {
+        self.puts_held.store(true, Ordering::Relaxed);
+    }
+
+    /// Makes the next puts complete again, the puts held so far stay stuck.
+    pub(crate) fn release_puts(&self) {
+        self.puts_held.store(false, Ordering::Relaxed);
+    }
+
+    /// Makes the messages put successfully from now on readable at the queue offset and the
+    /// commit log offset they are put at.
+    pub(crate) fn keep_puts(&self) {
+        self.puts_kept.store(true, Ordering::Relaxed);
+    }
+
+    /// Messages put so far, whatever the status they were answered with.
+    pub(crate) fn put_messages(&self) -> Vec<MessageExt> {
+        self.put_messages.lock().clone()
+    }
+
+    /// Messages put to `topic` so far.
+    pub(crate) fn put_messages_of(&self, topic: &str) -> Vec<MessageExt> {
+        self.put_messages
+            .lock()
+            .iter()
+            .filter(|msg| msg.get_topic().as_str() == topic)
+            .cloned()
+            .collect()
+    }
+
+    /// Makes `msg` readable at its queue offset in its queue.
+    pub(crate) fn add_stored_message(&self, msg: MessageExt) {
+        self.stored_messages.lock().insert(
+            (msg.get_topic().clone(), msg.queue_id, msg.queue_offset),
+            msg,
+        );
+    }
+
+    /// Number of `get_message` calls so far.
+    pub(crate) fn get_message_reads(&self) -> u64 {
+        self.get_message_reads.load(Ordering::Relaxed)
+    }
+
+    fn offset_range(&self, topic: &CheetahString, queue_id: i32) -> (i64, i64) {
+        self.offset_ranges
+            .lock()
+            .get(&(topic.clone(), queue_id))
+            .copied()
+            .unwrap_or((-1, -1))
+    }
+}
+
+impl MessageStore for TestMessageStore {
+    async fn load(&mut self) -> bool {
+        true
+    }
+
+    fn start(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn shutdown(&mut self) {}
+
+    fn set_confirm_offset(&mut self, phy_offset: i64) {}
+
+    fn get_max_phy_offset(&self) -> i64 {
+        0
+    }
+
+    fn set_broker_init_max_offset(&mut self, broker_init_max_offset: i64) {}
+
+    fn get_state_machine_version(&self) -> i64 {
+        0
+    }
+
+    async fn put_message(&mut self, msg: MessageExtBrokerInner) -> PutMessageResult {
+        // every message put to the queue before takes an offset below, every message put
+        // before a commit log offset below
+        let (queue_offset, wrote_offset) = {
+            let mut put_messages = self.put_messages.lock();
+            let queue_offset = put_messages
+                .iter()
+                .filter(|put| put.get_topic() == msg.get_topic() && put.queue_id == msg.queue_id())
+                .count() as i64;
+            let wrote_offset = put_messages.len() as i64;
+            put_messages.push(msg.message_ext_inner.clone());
+            (queue_offset, wrote_offset)
+        };
+        if self.puts_held.load(Ordering::Relaxed) {
+            std::future::pending::<()>().await;
+        }
+        let status = self
+            .put_statuses
+            .lock()
+            .pop_front()
+            .unwrap_or(PutMessageStatus::PutOk);
+        if status != PutMessageStatus::PutOk {
+            return PutMessageResult::new_default(status);
+        }
+        if self.puts_kept.load(Ordering::Relaxed) {
+            let mut stored = msg.message_ext_inner;
+            stored.queue_offset = queue_offset;
+            stored.commit_log_offset = wrote_offset;
+            let (min_offset, _) = self.offset_range(stored.get_topic(), stored.queue_id);
+            self.set_offset_range(
+                stored.get_topic(),
+                stored.queue_id,
+                min_offset.max(0),
+                queue_offset + 1,
+            );
+            self.add_stored_message(stored);
+        }
+        PutMessageResult::new_append_result(
+            status,
+            Some(AppendMessageResult {
+                status: AppendMessageStatus::PutOk,
+                wrote_offset,
+                logics_offset: queue_offset,
+                ..Default::default()
+            }),
+        )
+    }
+
+    async fn put_messages(&mut self, msg_batch: MessageExtBatch) -> PutMessageResult {
+        PutMessageResult::new_default(PutMessageStatus::UnknownError)
+    }
+
+    fn truncate_files(&mut self, offset_to_truncate: i64) -> bool {
+        false
+    }
+
+    fn get_running_flags(&self) -> &RunningFlags {
+        &self.running_flags
+    }
+
+    fn is_shutdown(&self) -> bool {
+        false
+    }
+
+    fn get_put_message_hook_list(&self) -> Arc<RwLock<Vec<BoxedPutMessageHook>>> {
+        self.put_message_hook_list.clone()
+    }
+
+    fn set_put_message_hook(&self, put_message_hook: BoxedPutMessageHook) {
+        self.put_message_hook_list.write().push(put_message_hook);
+    }
+
+    fn get_broker_stats_manager(&self) -> Option<Arc<BrokerStatsManager>> {
+        None
+    }
+
+    fn dispatch_behind_bytes(&self) -> i64 {
+        0
+    }
+
+    fn get_min_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        self.offset_range(topic, queue_id).0
+    }
+
+    fn trim_consume_queue(&self, topic: &CheetahString, queue_id: i32, offset: i64) -> i64 {
+        let mut offset_ranges = self.offset_ranges.lock();
+        let Some((min_offset, max_offset)) = offset_ranges.get_mut(&(topic.clone(), queue_id))
+        else {
+            return -1;
+        };
+        *min_offset = (*min_offset).max(offset.min(*max_offset));
+        *min_offset
+    }
+
+    fn get_max_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        self.offset_range(topic, queue_id).1
+    }
+
+    fn get_max_offset_in_queue_committed(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        committed: bool,
+    ) -> i64 {
+        self.offset_range(topic, queue_id).1
+    }
+
+    async fn get_message(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+        max_total_msg_size: i32,
+        message_filter: Option<&dyn MessageFilter>,
+    ) -> Option<GetMessageResult> {
+        self.get_message_reads.fetch_add(1, Ordering::Relaxed);
+        let stored_messages = self.stored_messages.lock();
+        let encoded: Vec<_> = (offset..offset + max_msg_nums as i64)
+            .filter_map(|queue_offset| {
+                let mut msg = stored_messages
+                    .get(&(topic.clone(), queue_id, queue_offset))
+                    .or_else(|| {
+                        // a read from inside an inner batch starts with the whole batch
+                        (queue_offset == offset)
+                            .then(|| {
+                                stored_messages.values().find(|msg| {
+                                    msg.get_topic() == topic
+                                        && msg.queue_id == queue_id
+                                        && inner_queue_offsets(msg).contains(&offset)
+                                })
+                            })
+                            .flatten()
+                    })?
+                    .clone();
+                // an inner batch spans the queue offsets of its messages
+                let batch_num = msg
+                    .get_property(&CheetahString::from_static_str(
+                        MessageConst::PROPERTY_INNER_NUM,
+                    ))
+                    .and_then(|num| num.parse::<i32>().ok())
+                    .unwrap_or(1);
+                // the stored size leads the encoded message
+                msg.store_size = message_decoder::encode(&msg, false).ok()?.len() as i32;
+                Some((
+                    msg.queue_offset,
+                    batch_num,
+                    message_decoder::encode(&msg, false).ok()?,
+                ))
+            })
+            .collect();
+        if encoded.is_empty() {
+            return None;
+        }
+        let dir = std::env::temp_dir().join(format!(
+            "rocketmq-test-message-store-{}-{}",
+            std::process::id(),
+            TEST_STORE_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file_name = dir.join(format!("{:020}", 0));
+        let file_size = encoded.iter().map(|(_, _, bytes)| bytes.len() as u64).sum();
+        let mapped_file = Arc::new(DefaultMappedFile::new(
+            CheetahString::from_string(file_name.to_string_lossy().into_owned()),
+            file_size,
+        ));
+        let mut result = GetMessageResult::new();
+        let (min_offset, max_offset) = self.offset_range(topic, queue_id);
+        result.set_min_offset(min_offset);
+        result.set_max_offset(max_offset);
+        result.set_next_begin_offset(
+            encoded
+                .last()
+                .map_or(offset, |(last, batch_num, _)| last + *batch_num as i64),
+        );
+        let mut position = 0;
+        for (queue_offset, batch_num, bytes) in encoded {
+            mapped_file.append_message_bytes(&bytes);
+            result.add_message(
+                SelectMappedBufferResult {
+                    start_offset: position,
+                    size: bytes.len() as i32,
+                    mapped_file: Some(mapped_file.clone()),
+                    is_in_cache: true,
+                },
+                queue_offset as u64,
+                batch_num,
+            );
+            position += bytes.len() as u64;
+        }
+        result.set_status(Some(GetMessageStatus::Found));
+        // the mapping outlives the files
+        let _ = std::fs::remove_dir_all(&dir);
+        Some(result)
+    }
+
+    fn check_in_mem_by_consume_offset(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_offset: i64,
+        batch_size: i32,
+    ) -> bool {
+        true
+    }
+
+    fn notify_message_arrive_if_necessary(&self, dispatch_request: &mut DispatchRequest) {}
+
+    fn find_consume_queue(&self, topic: &CheetahString, queue_id: i32) -> Option<ArcConsumeQueue> {
+        None
+    }
+
+    fn delete_topics(&mut self, delete_topics: Vec<&CheetahString>) -> i32 {
+        0
+    }
+
+    async fn query_message(
+        &self,
+        topic: &CheetahString,
+        key: &CheetahString,
+        max_num: i32,
+        begin_timestamp: i64,
+        end_timestamp: i64,
+    ) -> Option<QueryMessageResult> {
+        None
+    }
+
+    async fn select_one_message_by_offset(
+        &self,
+        commit_log_offset: i64,
+    ) -> Option<SelectMappedBufferResult> {
+        None
+    }
+
+    async fn select_one_message_by_offset_with_size(
+        &self,
+        commit_log_offset: i64,
+        size: i32,
+    ) -> Option<SelectMappedBufferResult> {
+        None
+    }
+
+    fn look_message_by_offset(&self, commit_log_offset: i64) -> Option<MessageExt> {
+        self.stored_messages
+            .lock()
+            .values()
+            .find(|msg| msg.commit_log_offset == commit_log_offset)
+            .cloned()
+    }
+
+    fn look_message_by_offset_with_size(
+        &self,
+        commit_log_offset: i64,
+        size: i32,
+    ) -> Option<MessageExt> {
+        None
+    }
+
+    fn get_message_store_timestamp(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_queue_offset: i64,
+    ) -> i64 {
+        0
+    }
+
+    fn get_runtime_info(&self) -> HashMap<String, String> {
+        HashMap::new()
+    }
+
+    fn lock_time_mills(&self) -> i64 {
+        0
+    }
+
+    fn get_earliest_message_time(&self) -> i64 {
+        0
+    }
+
+    fn get_timer_message_store(&self) -> Arc<TimerMessageStore> {
+        Arc::new(TimerMessageStore::new_empty())
+    }
+
+    fn set_timer_message_store(&mut self, timer_message_store: Arc<TimerMessageStore>) {}
+
+    fn remain_transient_store_buffer_nums(&self) -> i32 {
+        0
+    }
+
+    fn remain_how_many_data_to_commit(&self) -> i64 {
+        0
+    }
+
+    fn remain_how_many_data_to_flush(&self) -> i64 {
+        0
+    }
+}
@@ -30,6 +30,7 @@ use rocketmq_common::TimeUtils::get_current_nano;
 use rocketmq_remoting::protocol::namespace_util::NamespaceUtil;
 use rocketmq_remoting::protocol::request_type::RequestType;
 use rocketmq_remoting::protocol::LanguageCode;
+use rocketmq_remoting::protocol::SerializeType;
 
 use crate::base::access_channel::AccessChannel;
 
@@ -64,9 +65,13 @@ pub struct ClientConfig {
     pub use_tls: bool,
     pub socks_proxy_config: CheetahString,
     pub mq_client_api_timeout: u64,
+    /// How long a topic route queried from the name server is reused, 0 disables the cache.
+    pub topic_route_cache_ttl_millis: u64,
     pub detect_timeout: u32,
     pub detect_interval: u32,
     pub language: LanguageCode,
+    /// Serialize type of the request headers, JSON or the compact ROCKETMQ format.
+    pub serialize_type: SerializeType,
     pub enable_stream_request_type: bool,
     pub send_latency_enable: bool,
     pub start_detector_enable: bool,
@@ -122,9 +127,11 @@ impl ClientConfig {
                 .unwrap_or_else(|_| "{}".to_string())
                 .into(),
             mq_client_api_timeout: Duration::from_secs(3).as_millis() as u64,
+            topic_route_cache_ttl_millis: Duration::from_secs(3).as_millis() as u64,
             detect_timeout: 200,
             detect_interval: Duration::from_secs(2).as_millis() as u32,
             language: LanguageCode::RUST,
+            serialize_type: SerializeType::ROCKETMQ,
             enable_stream_request_type: false,
             send_latency_enable: env::var(SEND_LATENCY_ENABLE)
                 .unwrap_or_else(|_| "false".to_string())
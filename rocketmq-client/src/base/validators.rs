@@ -19,11 +19,13 @@ use std::collections::HashMap;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::constant::PermName;
+use rocketmq_common::common::message::message_single::Message;
 use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::topic::TopicValidator;
 use rocketmq_remoting::code::response_code::ResponseCode;
 
+use crate::client_error::ClientErr;
 use crate::mq_client_err;
 use crate::producer::default_mq_producer::ProducerConfig;
 use crate::Result;
@@ -111,6 +113,63 @@ impl Validators {
         Ok(())
     }
 
+    /// Checks the size of a message as it is sent, its body, compressed if it is, along with its
+    /// encoded properties, so that a message the broker would reject is not sent at all.
+    pub fn check_message_send_size(
+        body_len: usize,
+        properties: &str,
+        max_message_size: u32,
+    ) -> std::result::Result<(), ClientErr> {
+        let size = body_len + properties.len();
+        if size > max_message_size as usize {
+            return Err(ClientErr::new_with_code(
+                ResponseCode::MessageIllegal as i32,
+                format!(
+                    "the message size {} (body {} + properties {}) over max value, MAX: {}",
+                    size,
+                    body_len,
+                    properties.len(),
+                    max_message_size
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the messages sent in batches are all of the same topic and none of them is
+    /// delayed, a batch being stored as a whole in a single queue of a single topic.
+    pub fn check_batch_messages(msgs: &[Message]) -> std::result::Result<(), ClientErr> {
+        let Some(first) = msgs.first() else {
+            return Err(ClientErr::new_with_code(
+                ResponseCode::MessageIllegal as i32,
+                "the batch has no message".to_string(),
+            ));
+        };
+        for msg in msgs {
+            if msg.get_topic() != first.get_topic() {
+                return Err(ClientErr::new_with_code(
+                    ResponseCode::MessageIllegal as i32,
+                    format!(
+                        "the messages of a batch must share a topic, got {} and {}",
+                        first.get_topic(),
+                        msg.get_topic()
+                    ),
+                ));
+            }
+            if msg.get_delay_time_level() > 0
+                || msg.get_delay_time_sec() > 0
+                || msg.get_delay_time_ms() > 0
+                || msg.get_deliver_time_ms() > 0
+            {
+                return Err(ClientErr::new_with_code(
+                    ResponseCode::MessageIllegal as i32,
+                    "delayed messages are not supported for batching".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
     pub fn check_topic(topic: &str) -> Result<()> {
         if topic.trim().is_empty() {
             return mq_client_err!("The specified topic is blank");
@@ -210,6 +269,54 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn check_message_send_size_counts_body_and_properties() {
+        let properties = "KEYS\u{1}order-1\u{2}";
+        let max_message_size = 1024;
+        let body_len = max_message_size as usize - properties.len();
+        assert!(
+            Validators::check_message_send_size(body_len, properties, max_message_size).is_ok()
+        );
+
+        let result =
+            Validators::check_message_send_size(body_len + 1, properties, max_message_size);
+        match result {
+            Err(err) => {
+                assert_eq!(err.response_code(), ResponseCode::MessageIllegal as i32)
+            }
+            _ => panic!("an oversized message must be rejected"),
+        }
+    }
+
+    #[test]
+    fn check_batch_messages_rejects_mixed_topics() {
+        let msgs = vec![
+            Message::new("TopicA", b"a"),
+            Message::new("TopicA", b"b"),
+            Message::new("TopicB", b"c"),
+        ];
+        match Validators::check_batch_messages(&msgs) {
+            Err(err) => {
+                assert_eq!(err.response_code(), ResponseCode::MessageIllegal as i32)
+            }
+            _ => panic!("a batch of mixed topics must be rejected"),
+        }
+        assert!(Validators::check_batch_messages(&msgs[..2]).is_ok());
+    }
+
+    #[test]
+    fn check_batch_messages_rejects_delayed_and_empty_batches() {
+        let mut delayed = Message::new("TopicA", b"b");
+        delayed.set_delay_time_level(3);
+        assert!(
+            Validators::check_batch_messages(&[Message::new("TopicA", b"a"), delayed]).is_err()
+        );
+        let mut timed = Message::new("TopicA", b"b");
+        timed.set_delay_time_ms(5_000);
+        assert!(Validators::check_batch_messages(&[timed]).is_err());
+        assert!(Validators::check_batch_messages(&[]).is_err());
+    }
+
     #[test]
     fn check_topic_blank_topic() {
         let result = Validators::check_topic("");
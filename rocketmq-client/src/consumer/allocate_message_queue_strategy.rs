@@ -21,6 +21,13 @@ use crate::Result;
 
 /// Trait for allocating message queues to consumers in a consumer group.
 /// This trait is implemented by different strategies for message queue allocation.
+///
+/// A custom strategy is registered on the consumer with
+/// `DefaultMQPushConsumerBuilder::allocate_message_queue_strategy`, and called by the rebalance
+/// of every consumer of the group. It is given the queues of the topic and the ids of the
+/// consumers of the group, both sorted, so every consumer sees the same lists. The allocations
+/// made for every consumer of the group should not overlap and should cover all the queues,
+/// otherwise queues are consumed twice or not at all.
 pub trait AllocateMessageQueueStrategy: Send + Sync {
     /// Allocates message queues to a consumer in a consumer group.
     ///
@@ -38,6 +38,7 @@ use tracing::warn;
 use crate::base::client_config::ClientConfig;
 use crate::consumer::consumer_impl::consume_message_service::ConsumeMessageServiceTrait;
 use crate::consumer::consumer_impl::default_mq_push_consumer_impl::DefaultMQPushConsumerImpl;
+use crate::consumer::consumer_impl::message_dedup_filter::MessageDedupFilter;
 use crate::consumer::consumer_impl::pop_process_queue::PopProcessQueue;
 use crate::consumer::consumer_impl::process_queue::ProcessQueue;
 use crate::consumer::default_mq_push_consumer::ConsumerConfig;
@@ -54,6 +55,7 @@ pub struct ConsumeMessageConcurrentlyService {
     pub(crate) consumer_group: CheetahString,
     pub(crate) message_listener: ArcBoxMessageListenerConcurrently,
     pub(crate) consume_runtime: RocketMQRuntime,
+    pub(crate) dedup_filter: Option<Arc<MessageDedupFilter>>,
 }
 
 impl ConsumeMessageConcurrentlyService {
@@ -63,6 +65,7 @@ impl ConsumeMessageConcurrentlyService {
         consumer_group: CheetahString,
         message_listener: ArcBoxMessageListenerConcurrently,
         default_mqpush_consumer_impl: Option<ArcMut<DefaultMQPushConsumerImpl>>,
+        dedup_filter: Option<Arc<MessageDedupFilter>>,
     ) -> Self {
         let consume_thread = consumer_config.consume_thread_max;
         let consumer_group_tag = format!("{}_{}", "ConsumeMessageThread_", consumer_group);
@@ -76,6 +79,7 @@ impl ConsumeMessageConcurrentlyService {
                 consume_thread as usize,
                 consumer_group_tag.as_str(),
             ),
+            dedup_filter,
         }
     }
 }
@@ -118,6 +122,9 @@ impl ConsumeMessageConcurrentlyService {
                 ack_index = -1;
             }
         }
+        if let Some(dedup_filter) = self.dedup_filter.as_ref() {
+            dedup_filter.forget(&consume_request.msgs[(ack_index + 1) as usize..]);
+        }
 
         match self.consumer_config.message_model {
             MessageModel::Broadcasting => {
@@ -164,17 +171,28 @@ impl ConsumeMessageConcurrentlyService {
                 }
             }
         }
-        let offset = consume_request
-            .process_queue
-            .remove_message(&consume_request.msgs)
-            .await;
-        if offset >= 0 && !consume_request.process_queue.is_dropped() {
+        self.remove_consumed_message(
+            &consume_request.process_queue,
+            &consume_request.message_queue,
+            &consume_request.msgs,
+        )
+        .await;
+    }
+
+    async fn remove_consumed_message(
+        &mut self,
+        process_queue: &ProcessQueue,
+        message_queue: &MessageQueue,
+        msgs: &[ArcMut<MessageClientExt>],
+    ) {
+        let offset = process_queue.remove_message(msgs).await;
+        if offset >= 0 && !process_queue.is_dropped() {
             let default_mqpush_consumer_impl = self.default_mqpush_consumer_impl.as_mut().unwrap();
             default_mqpush_consumer_impl
                 .offset_store
                 .as_mut()
                 .unwrap()
-                .update_offset(&consume_request.message_queue, offset, true)
+                .update_offset(message_queue, offset, true)
                 .await;
         }
     }
@@ -378,6 +396,21 @@ impl ConsumeRequest {
         default_mqpush_consumer_impl
             .reset_retry_and_namespace(&mut self.msgs, consumer_group.as_str());
 
+        if let Some(dedup_filter) = consume_message_concurrently_service.dedup_filter.clone() {
+            let duplicates = dedup_filter.filter(&mut self.msgs);
+            if !duplicates.is_empty() {
+                info!(
+                    "drop {} duplicate messages before consuming, group={} {}",
+                    duplicates.len(),
+                    self.consumer_group,
+                    self.message_queue,
+                );
+                consume_message_concurrently_service
+                    .remove_consumed_message(&self.process_queue, &self.message_queue, &duplicates)
+                    .await;
+            }
+        }
+
         let mut consume_message_context = None;
 
         let begin_timestamp = Instant::now();
@@ -466,6 +499,14 @@ impl ConsumeRequest {
                 "the message queue not be able to consume, because it's dropped. group={} {}",
                 self.consumer_group, self.message_queue,
             );
+            // the messages are pulled again by whoever takes the queue over
+            if status == Some(ConsumeConcurrentlyStatus::ReconsumeLater) {
+                if let Some(dedup_filter) =
+                    consume_message_concurrently_service.dedup_filter.as_ref()
+                {
+                    dedup_filter.forget(&self.msgs);
+                }
+            }
         } else {
             let this = consume_message_concurrently_service.clone();
 
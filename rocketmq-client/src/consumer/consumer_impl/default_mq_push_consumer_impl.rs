@@ -71,6 +71,7 @@ use crate::consumer::consumer_impl::consume_message_pop_concurrently_service::Co
 use crate::consumer::consumer_impl::consume_message_pop_orderly_service::ConsumeMessagePopOrderlyService;
 use crate::consumer::consumer_impl::consume_message_service::ConsumeMessagePopServiceGeneral;
 use crate::consumer::consumer_impl::consume_message_service::ConsumeMessageServiceGeneral;
+use crate::consumer::consumer_impl::message_dedup_filter::MessageDedupFilter;
 use crate::consumer::consumer_impl::pop_request::PopRequest;
 use crate::consumer::consumer_impl::pull_api_wrapper::PullAPIWrapper;
 use crate::consumer::consumer_impl::pull_request::PullRequest;
@@ -146,6 +147,7 @@ pub struct DefaultMQPushConsumerImpl {
     queue_max_span_flow_control_times: u64,
     pub(crate) pop_delay_level: Arc<[i32; 16]>,
     default_mqpush_consumer_impl: Option<ArcMut<DefaultMQPushConsumerImpl>>,
+    dedup_filter: Option<Arc<MessageDedupFilter>>,
 }
 
 impl DefaultMQPushConsumerImpl {
@@ -178,12 +180,20 @@ impl DefaultMQPushConsumerImpl {
                 10, 30, 60, 120, 180, 240, 300, 360, 420, 480, 540, 600, 1200, 1800, 3600, 7200,
             ]),
             default_mqpush_consumer_impl: None,
+            dedup_filter: None,
         };
         let wrapper = ArcMut::downgrade(&this.rebalance_impl);
         this.rebalance_impl.set_rebalance_impl(wrapper);
         this
     }
 
+    /// The number of redelivered duplicates dropped before reaching the listener.
+    pub fn dropped_duplicate_count(&self) -> u64 {
+        self.dedup_filter
+            .as_ref()
+            .map_or(0, |dedup_filter| dedup_filter.dropped_count())
+    }
+
     pub fn set_default_mqpush_consumer_impl(
         &mut self,
         default_mqpush_consumer_impl: ArcMut<DefaultMQPushConsumerImpl>,
@@ -281,6 +291,12 @@ impl DefaultMQPushConsumerImpl {
                             .clone()
                             .unwrap();
                         self.consume_orderly = false;
+                        if self.consumer_config.dedup_enable {
+                            self.dedup_filter = Some(Arc::new(MessageDedupFilter::new(
+                                self.consumer_config.dedup_window_millis,
+                                self.consumer_config.dedup_window_size,
+                            )));
+                        }
                         let consume_message_concurrently_service =
                             ArcMut::new(ConsumeMessageConcurrentlyService::new(
                                 self.client_config.clone(),
@@ -288,6 +304,7 @@ impl DefaultMQPushConsumerImpl {
                                 self.consumer_config.consumer_group.clone(),
                                 listener.clone().expect("listener is None"),
                                 self.default_mqpush_consumer_impl.clone(),
+                                self.dedup_filter.clone(),
                             ));
                         self.consume_message_service =
                             Some(ArcMut::new(ConsumeMessageServiceGeneral::new(
@@ -1443,6 +1460,7 @@ impl DefaultMQPushConsumerImpl {
             queue_id,
             extra_info,
             offset: queue_offset,
+            observed_pop_time: None,
             topic_request_header: Some(TopicRequestHeader {
                 rpc_request_header: Some(RpcRequestHeader {
                     broker_name: Some(broker_name.clone()),
@@ -0,0 +1,207 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::common::message::message_client_ext::MessageClientExt;
+use rocketmq_common::common::message::message_client_id_setter::MessageClientIDSetter;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_rust::ArcMut;
+
+/// Drops the messages whose `UNIQ_KEY` was already handed to the listener within the window.
+///
+/// A key stays in the window for `window_millis`, or until `window_size` newer keys push it
+/// out. Messages without a `UNIQ_KEY` are never dropped.
+pub(crate) struct MessageDedupFilter {
+    window_millis: u64,
+    window_size: usize,
+    window: Mutex<DedupWindow>,
+    dropped: AtomicU64,
+}
+
+#[derive(Default)]
+struct DedupWindow {
+    seen: HashMap<CheetahString, u64>,
+    order: VecDeque<(CheetahString, u64)>,
+}
+
+impl DedupWindow {
+    fn evict(&mut self, now: u64, window_millis: u64, window_size: usize) {
+        while let Some((key, seen_at)) = self.order.front() {
+            if self.seen.len() <= window_size && seen_at + window_millis > now {
+                break;
+            }
+            // a forgotten key seen again later has a newer entry in `order`
+            if self.seen.get(key) == Some(seen_at) {
+                self.seen.remove(key);
+            }
+            self.order.pop_front();
+        }
+    }
+}
+
+impl MessageDedupFilter {
+    pub(crate) fn new(window_millis: u64, window_size: usize) -> Self {
+        Self {
+            window_millis,
+            window_size,
+            window: Mutex::new(DedupWindow::default()),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Removes the duplicates from `msgs` and returns them, the kept messages are recorded in
+    /// the window.
+    pub(crate) fn filter(
+        &self,
+        msgs: &mut Vec<ArcMut<MessageClientExt>>,
+    ) -> Vec<ArcMut<MessageClientExt>> {
+        self.filter_at(msgs, get_current_millis())
+    }
+
+    fn filter_at(
+        &self,
+        msgs: &mut Vec<ArcMut<MessageClientExt>>,
+        now: u64,
+    ) -> Vec<ArcMut<MessageClientExt>> {
+        let mut window = self.window.lock();
+        window.evict(now, self.window_millis, self.window_size);
+        let mut duplicates = Vec::new();
+        for msg in std::mem::take(msgs) {
+            match MessageClientIDSetter::get_uniq_id(&msg.message_ext_inner) {
+                Some(key) if window.seen.contains_key(&key) => duplicates.push(msg),
+                Some(key) => {
+                    window.seen.insert(key.clone(), now);
+                    window.order.push_back((key, now));
+                    msgs.push(msg);
+                }
+                None => msgs.push(msg),
+            }
+        }
+        window.evict(now, self.window_millis, self.window_size);
+        self.dropped
+            .fetch_add(duplicates.len() as u64, Ordering::Relaxed);
+        duplicates
+    }
+
+    /// Takes the messages out of the window, so that they pass again when they are redelivered
+    /// after the listener failed them.
+    pub(crate) fn forget(&self, msgs: &[ArcMut<MessageClientExt>]) {
+        let mut window = self.window.lock();
+        for msg in msgs {
+            if let Some(key) = MessageClientIDSetter::get_uniq_id(&msg.message_ext_inner) {
+                window.seen.remove(&key);
+            }
+        }
+    }
+
+    /// The number of duplicates dropped so far.
+    pub(crate) fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::message::message_ext::MessageExt;
+    use rocketmq_common::common::message::MessageConst;
+    use rocketmq_common::common::message::MessageTrait;
+
+    use super::*;
+
+    fn message(key: &str) -> ArcMut<MessageClientExt> {
+        let mut msg = MessageExt::default();
+        msg.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX),
+            CheetahString::from(key),
+        );
+        ArcMut::new(MessageClientExt::new(msg))
+    }
+
+    fn keys(msgs: &[ArcMut<MessageClientExt>]) -> Vec<CheetahString> {
+        msgs.iter()
+            .filter_map(|msg| MessageClientIDSetter::get_uniq_id(&msg.message_ext_inner))
+            .collect()
+    }
+
+    #[test]
+    fn listener_sees_each_key_once() {
+        let filter = MessageDedupFilter::new(60_000, 1024);
+        let mut consumed = Vec::new();
+        for batch in [vec!["a", "b", "a"], vec!["b", "c"], vec!["c", "a", "d"]] {
+            let mut msgs = batch.into_iter().map(message).collect();
+            filter.filter_at(&mut msgs, 1_000);
+            consumed.extend(keys(&msgs));
+        }
+
+        assert_eq!(consumed, vec!["a", "b", "c", "d"]);
+        assert_eq!(filter.dropped_count(), 4);
+    }
+
+    #[test]
+    fn keys_leave_the_window_after_window_millis() {
+        let filter = MessageDedupFilter::new(1_000, 1024);
+        let mut msgs = vec![message("a")];
+        filter.filter_at(&mut msgs, 1_000);
+
+        let mut msgs = vec![message("a")];
+        assert_eq!(filter.filter_at(&mut msgs, 1_999).len(), 1);
+        let mut msgs = vec![message("a")];
+        assert!(filter.filter_at(&mut msgs, 2_000).is_empty());
+        assert_eq!(keys(&msgs), vec!["a"]);
+    }
+
+    #[test]
+    fn oldest_keys_leave_the_window_beyond_window_size() {
+        let filter = MessageDedupFilter::new(60_000, 2);
+        let mut msgs = vec![message("a"), message("b"), message("c")];
+        filter.filter_at(&mut msgs, 1_000);
+
+        let mut msgs = vec![message("a"), message("b"), message("c")];
+        let duplicates = filter.filter_at(&mut msgs, 1_001);
+        assert_eq!(keys(&msgs), vec!["a"]);
+        assert_eq!(keys(&duplicates), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn forgotten_keys_pass_again() {
+        let filter = MessageDedupFilter::new(60_000, 1024);
+        let mut msgs = vec![message("a"), message("b")];
+        filter.filter_at(&mut msgs, 1_000);
+        filter.forget(&msgs[1..]);
+
+        let mut msgs = vec![message("a"), message("b")];
+        filter.filter_at(&mut msgs, 1_001);
+        assert_eq!(keys(&msgs), vec!["b"]);
+    }
+
+    #[test]
+    fn messages_without_uniq_key_are_kept() {
+        let filter = MessageDedupFilter::new(60_000, 1024);
+        let mut msgs = vec![
+            ArcMut::new(MessageClientExt::new(MessageExt::default())),
+            ArcMut::new(MessageClientExt::new(MessageExt::default())),
+        ];
+        assert!(filter.filter_at(&mut msgs, 1_000).is_empty());
+        assert_eq!(msgs.len(), 2);
+    }
+}
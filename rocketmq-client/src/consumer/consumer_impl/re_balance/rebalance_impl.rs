@@ -810,20 +810,15 @@ where
                     );
                     return true;
                 }
-                if mq_set.is_some() && cid_all.is_some() {
-                    let mq_set = mq_set.unwrap();
-                    let mut mq_all = mq_set.iter().cloned().collect::<Vec<MessageQueue>>();
-                    mq_all.sort();
-                    let mut ci_all = cid_all.unwrap();
-                    ci_all.sort();
-
+                if let (Some(mq_set), Some(ci_all)) = (mq_set, cid_all) {
                     let strategy = self.allocate_message_queue_strategy.as_ref().unwrap();
                     let strategy_name = strategy.get_name();
-                    let allocate_result = match strategy.allocate(
+                    let allocate_result_set = match allocate_message_queues(
+                        strategy.as_ref(),
                         self.consumer_group.as_ref().unwrap(),
                         self.client_instance.as_ref().unwrap().client_id.as_ref(),
-                        mq_all.as_slice(),
-                        ci_all.as_slice(),
+                        mq_set,
+                        &ci_all,
                     ) {
                         Ok(value) => value,
                         Err(e) => {
@@ -838,9 +833,6 @@ where
                             return false;
                         }
                     };
-                    let allocate_result_set = allocate_result
-                        .into_iter()
-                        .collect::<HashSet<MessageQueue>>();
                     let changed = self
                         .update_process_queue_table_in_rebalance(
                             topic,
@@ -1133,3 +1125,104 @@ where
         result
     }
 }
+
+/// Allocates the queues of `mq_set` to the consumer `current_cid` of `consumer_group` with
+/// `strategy`. The queues and the consumer ids are sorted first, every consumer of the group
+/// handing the strategy the same lists. The error of the strategy is boxed to keep the result
+/// small.
+pub(crate) fn allocate_message_queues(
+    strategy: &dyn AllocateMessageQueueStrategy,
+    consumer_group: &CheetahString,
+    current_cid: &CheetahString,
+    mq_set: &HashSet<MessageQueue>,
+    cid_all: &[CheetahString],
+) -> Result<HashSet<MessageQueue>, Box<MQClientError>> {
+    let mut mq_all = mq_set.iter().cloned().collect::<Vec<MessageQueue>>();
+    mq_all.sort();
+    let mut cid_all = cid_all.to_vec();
+    cid_all.sort();
+    Ok(strategy
+        .allocate(consumer_group, current_cid, &mq_all, &cid_all)
+        .map_err(Box::new)?
+        .into_iter()
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pins the queues of broker `a` to the consumer `cid_a`, the other queues to the others.
+    struct PinningStrategy;
+
+    impl AllocateMessageQueueStrategy for PinningStrategy {
+        fn allocate(
+            &self,
+            _consumer_group: &CheetahString,
+            current_cid: &CheetahString,
+            mq_all: &[MessageQueue],
+            cid_all: &[CheetahString],
+        ) -> crate::Result<Vec<MessageQueue>> {
+            let others: Vec<&CheetahString> = cid_all
+                .iter()
+                .filter(|cid| cid.as_str() != "cid_a")
+                .collect();
+            Ok(mq_all
+                .iter()
+                .filter(|mq| {
+                    if mq.get_broker_name() == "a" {
+                        return current_cid == "cid_a";
+                    }
+                    let index = mq.get_queue_id() as usize % others.len();
+                    others[index] == current_cid
+                })
+                .cloned()
+                .collect())
+        }
+
+        fn get_name(&self) -> &'static str {
+            "PINNING"
+        }
+    }
+
+    #[test]
+    fn rebalance_allocates_with_the_registered_strategy() {
+        let mq_set: HashSet<MessageQueue> = ["a", "b"]
+            .iter()
+            .flat_map(|broker| {
+                (0..4).map(move |queue_id| MessageQueue::from_parts("topic", *broker, queue_id))
+            })
+            .collect();
+        let cid_all = [
+            CheetahString::from("cid_c"),
+            CheetahString::from("cid_a"),
+            CheetahString::from("cid_b"),
+        ];
+        let group = CheetahString::from("group");
+        let allocate = |cid: &str| {
+            allocate_message_queues(
+                &PinningStrategy,
+                &group,
+                &CheetahString::from(cid),
+                &mq_set,
+                &cid_all,
+            )
+            .unwrap()
+        };
+
+        let pinned = allocate("cid_a");
+        assert_eq!(pinned.len(), 4);
+        assert!(pinned.iter().all(|mq| mq.get_broker_name() == "a"));
+        // the others share broker b, seeing the consumers sorted
+        let cid_b: HashSet<i32> = allocate("cid_b")
+            .iter()
+            .map(|mq| mq.get_queue_id())
+            .collect();
+        let cid_c: HashSet<i32> = allocate("cid_c")
+            .iter()
+            .map(|mq| mq.get_queue_id())
+            .collect();
+        assert_eq!(cid_b, HashSet::from([0, 2]));
+        assert_eq!(cid_c, HashSet::from([1, 3]));
+    }
+}
@@ -91,6 +91,11 @@ pub struct ConsumerConfig {
     pub(crate) trace_dispatcher: Option<Arc<Box<dyn TraceDispatcher + Send + Sync>>>,
     pub(crate) client_rebalance: bool,
     pub(crate) rpc_hook: Option<Arc<Box<dyn RPCHook>>>,
+    /// Drops the messages redelivered with a `UNIQ_KEY` the concurrently listener consumed
+    /// within the dedup window.
+    pub(crate) dedup_enable: bool,
+    pub(crate) dedup_window_millis: u64,
+    pub(crate) dedup_window_size: usize,
 }
 
 impl ConsumerConfig {
@@ -222,6 +227,18 @@ impl ConsumerConfig {
         &self.rpc_hook
     }
 
+    pub fn dedup_enable(&self) -> bool {
+        self.dedup_enable
+    }
+
+    pub fn dedup_window_millis(&self) -> u64 {
+        self.dedup_window_millis
+    }
+
+    pub fn dedup_window_size(&self) -> usize {
+        self.dedup_window_size
+    }
+
     pub fn set_consumer_group(&mut self, consumer_group: CheetahString) {
         self.consumer_group = consumer_group;
     }
@@ -377,6 +394,18 @@ impl ConsumerConfig {
     pub fn set_rpc_hook(&mut self, rpc_hook: Option<Arc<Box<dyn RPCHook>>>) {
         self.rpc_hook = rpc_hook;
     }
+
+    pub fn set_dedup_enable(&mut self, dedup_enable: bool) {
+        self.dedup_enable = dedup_enable;
+    }
+
+    pub fn set_dedup_window_millis(&mut self, dedup_window_millis: u64) {
+        self.dedup_window_millis = dedup_window_millis;
+    }
+
+    pub fn set_dedup_window_size(&mut self, dedup_window_size: usize) {
+        self.dedup_window_size = dedup_window_size;
+    }
 }
 
 impl Default for ConsumerConfig {
@@ -421,6 +450,9 @@ impl Default for ConsumerConfig {
             trace_dispatcher: None,
             client_rebalance: true,
             rpc_hook: None,
+            dedup_enable: false,
+            dedup_window_millis: 1000 * 60 * 5,
+            dedup_window_size: 100_000,
         }
     }
 }
@@ -683,4 +715,14 @@ impl DefaultMQPushConsumer {
     pub fn set_consume_from_where(&mut self, consume_from_where: ConsumeFromWhere) {
         self.consumer_config.consume_from_where = consume_from_where;
     }
+
+    /// The number of redelivered duplicates dropped before reaching the listener, always 0
+    /// unless `dedup_enable` is set.
+    pub fn dropped_duplicate_count(&self) -> u64 {
+        self.default_mqpush_consumer_impl
+            .as_ref()
+            .map_or(0, |default_mqpush_consumer_impl| {
+                default_mqpush_consumer_impl.dropped_duplicate_count()
+            })
+    }
 }
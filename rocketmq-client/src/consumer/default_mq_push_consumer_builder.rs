@@ -67,6 +67,9 @@ pub struct DefaultMQPushConsumerBuilder {
     trace_dispatcher: Option<Arc<Box<dyn TraceDispatcher + Send + Sync>>>,
     client_rebalance: Option<bool>,
     rpc_hook: Option<Arc<Box<dyn RPCHook>>>,
+    dedup_enable: Option<bool>,
+    dedup_window_millis: Option<u64>,
+    dedup_window_size: Option<usize>,
 }
 
 impl Default for DefaultMQPushConsumerBuilder {
@@ -105,6 +108,9 @@ impl Default for DefaultMQPushConsumerBuilder {
             trace_dispatcher: None,
             client_rebalance: None,
             rpc_hook: None,
+            dedup_enable: None,
+            dedup_window_millis: None,
+            dedup_window_size: None,
         }
     }
 }
@@ -312,6 +318,21 @@ impl DefaultMQPushConsumerBuilder {
         self
     }
 
+    pub fn dedup_enable(mut self, dedup_enable: bool) -> Self {
+        self.dedup_enable = Some(dedup_enable);
+        self
+    }
+
+    pub fn dedup_window_millis(mut self, dedup_window_millis: u64) -> Self {
+        self.dedup_window_millis = Some(dedup_window_millis);
+        self
+    }
+
+    pub fn dedup_window_size(mut self, dedup_window_size: usize) -> Self {
+        self.dedup_window_size = Some(dedup_window_size);
+        self
+    }
+
     // Build method to create a ConsumerConfig instance
     pub fn build(mut self) -> DefaultMQPushConsumer {
         let mut consumer_config = ConsumerConfig::default();
@@ -410,6 +431,15 @@ impl DefaultMQPushConsumerBuilder {
             consumer_config.client_rebalance = client_rebalance;
         }
         consumer_config.rpc_hook = self.rpc_hook.clone();
+        if let Some(dedup_enable) = self.dedup_enable {
+            consumer_config.dedup_enable = dedup_enable;
+        }
+        if let Some(dedup_window_millis) = self.dedup_window_millis {
+            consumer_config.dedup_window_millis = dedup_window_millis;
+        }
+        if let Some(dedup_window_size) = self.dedup_window_size {
+            consumer_config.dedup_window_size = dedup_window_size;
+        }
 
         let mut consumer = DefaultMQPushConsumer::new(
             self.client_config.take().unwrap_or_default(),
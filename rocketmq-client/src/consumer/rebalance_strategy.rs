@@ -19,6 +19,7 @@ pub mod allocate_message_queue_averagely_by_circle;
 pub mod allocate_message_queue_by_config;
 pub mod allocate_message_queue_by_machine_room;
 pub mod allocate_message_queue_by_machine_room_nearby;
+pub mod allocate_message_queue_consistent_hash;
 
 use std::collections::HashSet;
 
@@ -0,0 +1,164 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+
+use crate::consumer::allocate_message_queue_strategy::AllocateMessageQueueStrategy;
+use crate::consumer::rebalance_strategy::check;
+
+/// Allocates the queues over a hash ring of the consumers, each of them placed on the ring
+/// `virtual_node_cnt` times. A consumer joining or leaving the group only moves the queues
+/// between it and its neighbours on the ring, the others stay with their consumer.
+pub struct AllocateMessageQueueConsistentHash {
+    virtual_node_cnt: usize,
+}
+
+impl AllocateMessageQueueConsistentHash {
+    pub fn new(virtual_node_cnt: usize) -> Self {
+        AllocateMessageQueueConsistentHash {
+            virtual_node_cnt: virtual_node_cnt.max(1),
+        }
+    }
+}
+
+impl Default for AllocateMessageQueueConsistentHash {
+    fn default() -> Self {
+        AllocateMessageQueueConsistentHash::new(10)
+    }
+}
+
+impl AllocateMessageQueueStrategy for AllocateMessageQueueConsistentHash {
+    fn allocate(
+        &self,
+        consumer_group: &CheetahString,
+        current_cid: &CheetahString,
+        mq_all: &[MessageQueue],
+        cid_all: &[CheetahString],
+    ) -> crate::Result<Vec<MessageQueue>> {
+        let mut result = Vec::new();
+        if !check(consumer_group, current_cid, mq_all, cid_all)? {
+            return Ok(result);
+        }
+        let mut ring = BTreeMap::new();
+        for cid in cid_all {
+            for replica_index in 0..self.virtual_node_cnt {
+                ring.insert(hash(&format!("{}-{}", cid, replica_index)), cid);
+            }
+        }
+        for mq in mq_all {
+            let key = hash(&mq.to_string());
+            let node = ring
+                .range(key..)
+                .next()
+                .or_else(|| ring.iter().next())
+                .map(|(_, cid)| *cid);
+            if node == Some(current_cid) {
+                result.push(mq.clone());
+            }
+        }
+        Ok(result)
+    }
+
+    #[inline]
+    fn get_name(&self) -> &'static str {
+        "CONSISTENT_HASH"
+    }
+}
+
+/// FNV-1a mixed by the murmur3 finalizer, so keys differing in their last characters land far
+/// apart on the ring. Every client of a group must place the keys the same way, so no hasher
+/// seeded per process or changing across releases is used.
+fn hash(key: &str) -> u64 {
+    let mut h = key.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^ (h >> 33)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn mq_all(num: i32) -> Vec<MessageQueue> {
+        (0..num)
+            .map(|queue_id| MessageQueue::from_parts("topic", "broker", queue_id))
+            .collect()
+    }
+
+    fn cid_all(num: usize) -> Vec<CheetahString> {
+        (0..num)
+            .map(|i| CheetahString::from(format!("consumer{}", i)))
+            .collect()
+    }
+
+    fn allocation(
+        mq_all: &[MessageQueue],
+        cid_all: &[CheetahString],
+    ) -> HashMap<i32, CheetahString> {
+        let strategy = AllocateMessageQueueConsistentHash::default();
+        let mut allocation = HashMap::new();
+        for cid in cid_all {
+            for mq in strategy
+                .allocate(&CheetahString::from("group"), cid, mq_all, cid_all)
+                .unwrap()
+            {
+                assert!(
+                    allocation.insert(mq.get_queue_id(), cid.clone()).is_none(),
+                    "a queue is allocated to a single consumer"
+                );
+            }
+        }
+        allocation
+    }
+
+    #[test]
+    fn every_queue_is_allocated_once() {
+        let mq_all = mq_all(16);
+        let allocation = allocation(&mq_all, &cid_all(3));
+        assert_eq!(allocation.len(), 16);
+    }
+
+    #[test]
+    fn joining_consumer_only_takes_queues_over() {
+        let mq_all = mq_all(32);
+        let before = allocation(&mq_all, &cid_all(4));
+        let cid_all = cid_all(5);
+        let after = allocation(&mq_all, &cid_all);
+
+        for (queue_id, cid) in &after {
+            if before[queue_id] != *cid {
+                assert_eq!(*cid, cid_all[4]);
+            }
+        }
+    }
+
+    #[test]
+    fn get_name_returns_correct_name() {
+        assert_eq!(
+            AllocateMessageQueueConsistentHash::default().get_name(),
+            "CONSISTENT_HASH"
+        );
+    }
+}
@@ -230,7 +230,10 @@ impl MQClientInstance {
         let (tx, mut rx) = tokio::sync::broadcast::channel::<ConnectionNetEvent>(16);
 
         let mq_client_api_impl = ArcMut::new(MQClientAPIImpl::new(
-            Arc::new(TokioClientConfig::default()),
+            Arc::new(TokioClientConfig {
+                serialize_type: client_config.serialize_type,
+                ..Default::default()
+            }),
             ClientRemotingProcessor::new(instance.clone()),
             rpc_hook,
             client_config.clone(),
@@ -21,3 +21,4 @@ pub(crate) mod find_broker_result;
 pub(crate) mod mq_admin_impl;
 pub(crate) mod mq_client_api_impl;
 pub(crate) mod mq_client_manager;
+pub(crate) mod topic_route_cache;
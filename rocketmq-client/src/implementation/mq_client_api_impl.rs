@@ -18,6 +18,7 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use std::sync::atomic::AtomicU32;
 use std::sync::Arc;
+use std::time::Duration;
 use std::time::Instant;
 
 use cheetah_string::CheetahString;
@@ -112,6 +113,7 @@ use crate::factory::mq_client_instance::MQClientInstance;
 use crate::hook::send_message_context::SendMessageContext;
 use crate::implementation::client_remoting_processor::ClientRemotingProcessor;
 use crate::implementation::communication_mode::CommunicationMode;
+use crate::implementation::topic_route_cache::TopicRouteCache;
 use crate::mq_client_err;
 use crate::producer::producer_impl::default_mq_producer_impl::DefaultMQProducerImpl;
 use crate::producer::producer_impl::topic_publish_info::TopicPublishInfo;
@@ -133,6 +135,7 @@ pub struct MQClientAPIImpl {
     // client_remoting_processor: ClientRemotingProcessor,
     name_srv_addr: Option<String>,
     client_config: ClientConfig,
+    topic_route_cache: TopicRouteCache,
 }
 
 impl NameServerUpdateCallback for MQClientAPIImpl {
@@ -163,6 +166,9 @@ impl MQClientAPIImpl {
             )),
             //client_remoting_processor,
             name_srv_addr: None,
+            topic_route_cache: TopicRouteCache::new(Duration::from_millis(
+                client_config.topic_route_cache_ttl_millis,
+            )),
             client_config,
         }
     }
@@ -226,12 +232,43 @@ impl MQClientAPIImpl {
             .await
     }
 
+    /// Queries the route of `topic` from the name server, bypassing the route cache, and caches
+    /// it.
+    pub async fn refresh_topic_route_info_from_name_server(
+        &self,
+        topic: &str,
+        timeout_millis: u64,
+    ) -> Result<Option<TopicRouteData>> {
+        self.topic_route_cache
+            .get_or_query(
+                topic,
+                true,
+                self.query_topic_route_info(topic, timeout_millis, true),
+            )
+            .await
+    }
+
     #[inline]
     pub async fn get_topic_route_info_from_name_server_detail(
         &self,
         topic: &str,
         timeout_millis: u64,
         allow_topic_not_exist: bool,
+    ) -> Result<Option<TopicRouteData>> {
+        self.topic_route_cache
+            .get_or_query(
+                topic,
+                false,
+                self.query_topic_route_info(topic, timeout_millis, allow_topic_not_exist),
+            )
+            .await
+    }
+
+    async fn query_topic_route_info(
+        &self,
+        topic: &str,
+        timeout_millis: u64,
+        allow_topic_not_exist: bool,
     ) -> Result<Option<TopicRouteData>> {
         let request_header = GetRouteInfoRequestHeader {
             topic: CheetahString::from_slice(topic),
@@ -0,0 +1,222 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use std::time::Instant;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
+use tracing::warn;
+
+use crate::client_error::MQClientError;
+use crate::Result;
+
+/// Caches the routes of topics queried from the name server for `ttl`, so that lookups of the
+/// same topic in a row do not each go over the network.
+///
+/// A route whose query fails because the name server can not be reached is served from the
+/// cache even once expired, a producer keeps sending along the last route known rather than
+/// failing at once. A zero `ttl` disables the cache.
+pub(crate) struct TopicRouteCache {
+    ttl: Duration,
+    routes: Mutex<HashMap<CheetahString, (TopicRouteData, Instant)>>,
+}
+
+impl TopicRouteCache {
+    pub fn new(ttl: Duration) -> Self {
+        TopicRouteCache {
+            ttl,
+            routes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the route of `topic`, from the cache if it is fresh and `force_refresh` is not
+    /// set, otherwise queried by `query` and cached.
+    pub async fn get_or_query<F>(
+        &self,
+        topic: &str,
+        force_refresh: bool,
+        query: F,
+    ) -> Result<Option<TopicRouteData>>
+    where
+        F: Future<Output = Result<Option<TopicRouteData>>>,
+    {
+        if self.ttl.is_zero() {
+            return query.await;
+        }
+        if !force_refresh {
+            if let Some(route) = self.get_fresh(topic) {
+                return Ok(Some(route));
+            }
+        }
+        match query.await {
+            Ok(Some(route)) => {
+                self.routes.lock().insert(
+                    CheetahString::from_slice(topic),
+                    (route.clone(), Instant::now()),
+                );
+                Ok(Some(route))
+            }
+            Ok(None) => Ok(None),
+            Err(MQClientError::RemotingError(e)) => match self.get_stale(topic) {
+                Some(route) => {
+                    warn!(
+                        "get route of topic [{}] from name server failed, use the cached one, {}",
+                        topic, e
+                    );
+                    Ok(Some(route))
+                }
+                None => Err(MQClientError::RemotingError(e)),
+            },
+            Err(e) => {
+                // the name server answered, the topic is gone or its route not to be used
+                self.routes.lock().remove(topic);
+                Err(e)
+            }
+        }
+    }
+
+    fn get_fresh(&self, topic: &str) -> Option<TopicRouteData> {
+        self.routes
+            .lock()
+            .get(topic)
+            .filter(|(_, cached_at)| cached_at.elapsed() < self.ttl)
+            .map(|(route, _)| route.clone())
+    }
+
+    fn get_stale(&self, topic: &str) -> Option<TopicRouteData> {
+        self.routes
+            .lock()
+            .get(topic)
+            .map(|(route, _)| route.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use rocketmq_remoting::code::response_code::ResponseCode;
+    use rocketmq_remoting::remoting_error::RemotingError;
+
+    use super::*;
+    use crate::mq_client_err;
+
+    fn route(order_topic_conf: &str) -> TopicRouteData {
+        TopicRouteData {
+            order_topic_conf: Some(CheetahString::from_slice(order_topic_conf)),
+            ..Default::default()
+        }
+    }
+
+    /// Answers with `result`, counting the queries in `queries`.
+    async fn query(
+        queries: &AtomicUsize,
+        result: Result<Option<TopicRouteData>>,
+    ) -> Result<Option<TopicRouteData>> {
+        queries.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+
+    #[tokio::test]
+    async fn fresh_route_is_served_from_the_cache() {
+        let cache = TopicRouteCache::new(Duration::from_secs(60));
+        let queries = AtomicUsize::new(0);
+        let first = cache
+            .get_or_query("topic", false, query(&queries, Ok(Some(route("a")))))
+            .await
+            .unwrap();
+        let second = cache
+            .get_or_query("topic", false, query(&queries, Ok(Some(route("b")))))
+            .await
+            .unwrap();
+        assert_eq!(first, Some(route("a")));
+        assert_eq!(second, Some(route("a")));
+        assert_eq!(queries.load(Ordering::Relaxed), 1);
+
+        let refreshed = cache
+            .get_or_query("topic", true, query(&queries, Ok(Some(route("b")))))
+            .await
+            .unwrap();
+        assert_eq!(refreshed, Some(route("b")));
+        assert_eq!(queries.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn expired_route_is_queried_again() {
+        let cache = TopicRouteCache::new(Duration::from_millis(1));
+        let queries = AtomicUsize::new(0);
+        cache
+            .get_or_query("topic", false, query(&queries, Ok(Some(route("a")))))
+            .await
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        let route_b = cache
+            .get_or_query("topic", false, query(&queries, Ok(Some(route("b")))))
+            .await
+            .unwrap();
+        assert_eq!(route_b, Some(route("b")));
+        assert_eq!(queries.load(Ordering::Relaxed), 2);
+    }
+
+    fn unreachable() -> MQClientError {
+        MQClientError::RemotingError(RemotingError::RemotingConnectError(
+            "127.0.0.1:9876".to_string(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn stale_route_is_served_when_the_name_server_is_unreachable() {
+        let cache = TopicRouteCache::new(Duration::from_millis(1));
+        let queries = AtomicUsize::new(0);
+        assert!(cache
+            .get_or_query("topic", false, query(&queries, Err(unreachable())))
+            .await
+            .is_err());
+
+        cache
+            .get_or_query("topic", false, query(&queries, Ok(Some(route("a")))))
+            .await
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        let stale = cache
+            .get_or_query("topic", false, query(&queries, Err(unreachable())))
+            .await
+            .unwrap();
+        assert_eq!(stale, Some(route("a")));
+
+        // a topic the name server no longer knows is not served from the cache
+        let not_exist = cache
+            .get_or_query(
+                "topic",
+                false,
+                query(
+                    &queries,
+                    mq_client_err!(ResponseCode::TopicNotExist, "topic not exist"),
+                ),
+            )
+            .await;
+        assert!(not_exist.is_err());
+        assert!(cache
+            .get_or_query("topic", false, query(&queries, Err(unreachable())))
+            .await
+            .is_err());
+    }
+}
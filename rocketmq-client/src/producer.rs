@@ -21,6 +21,7 @@ pub mod message_queue_selector;
 pub mod mq_producer;
 pub mod produce_accumulator;
 pub mod producer_impl;
+pub(crate) mod queue_batch;
 pub mod request_callback;
 pub(crate) mod request_future_holder;
 pub(crate) mod request_response_future;
@@ -44,6 +44,7 @@ use crate::producer::default_mq_produce_builder::DefaultMQProducerBuilder;
 use crate::producer::mq_producer::MQProducer;
 use crate::producer::produce_accumulator::ProduceAccumulator;
 use crate::producer::producer_impl::default_mq_producer_impl::DefaultMQProducerImpl;
+use crate::producer::queue_batch::group_by_queue;
 use crate::producer::send_callback::SendMessageCallback;
 use crate::producer::send_result::SendResult;
 use crate::producer::transaction_send_result::TransactionSendResult;
@@ -1023,6 +1024,20 @@ impl MQProducer for DefaultMQProducer {
         Ok(result.expect("SendResult should not be None"))
     }
 
+    async fn send_batch_by_queue(&mut self, msgs: Vec<Message>) -> Result<Vec<SendResult>> {
+        Validators::check_batch_messages(&msgs)?;
+        let topic = msgs[0].get_topic().clone();
+        let mqs = self.fetch_publish_message_queues(&topic).await?;
+        if mqs.is_empty() {
+            return mq_client_err!(format!("no queue to publish topic {} to", topic));
+        }
+        let mut send_results = Vec::new();
+        for (mq, batch) in group_by_queue(msgs, &mqs, rand::random::<usize>()) {
+            send_results.push(self.send_batch_to_queue(batch, mq).await?);
+        }
+        Ok(send_results)
+    }
+
     async fn send_batch_with_timeout(
         &mut self,
         msgs: Vec<Message>,
@@ -573,6 +573,20 @@ pub trait MQProducerLocal {
     where
         F: Fn(Option<&SendResult>, Option<&dyn std::error::Error>) + Send + Sync + 'static;
 
+    /// Sends a batch of messages split by the queue each of them is sent to, one batch per
+    /// queue. Messages sharing a sharding key go to the same queue, the others are spread over
+    /// the queues of the topic.
+    ///
+    /// # Arguments
+    ///
+    /// * `msgs` - A vector of messages of the same topic, none of them delayed.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<SendResult>>` - A result containing the send result of every batch, or the
+    ///   error of the first batch failing to be sent.
+    async fn send_batch_by_queue(&mut self, msgs: Vec<Message>) -> Result<Vec<SendResult>>;
+
     /// Sends a request message.
     ///
     /// # Type Parameters
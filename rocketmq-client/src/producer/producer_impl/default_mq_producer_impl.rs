@@ -997,6 +997,13 @@ impl DefaultMQProducerImpl {
                 sys_flag |= MessageSysFlag::TRANSACTION_PREPARED_TYPE;
             }
         }
+        // the body is checked as it is sent, compressed or not
+        let properties = MessageDecoder::message_properties_to_string(msg.get_properties());
+        Validators::check_message_send_size(
+            msg.get_body().map_or(0, |body| body.len()),
+            properties.as_str(),
+            self.producer_config.max_message_size(),
+        )?;
 
         if self.has_check_forbidden_hook() {
             let check_forbidden_context = CheckForbiddenContext {
@@ -1071,9 +1078,7 @@ impl DefaultMQProducerImpl {
             sys_flag,
             born_timestamp: get_current_millis() as i64,
             flag: msg.get_flag(),
-            properties: Some(MessageDecoder::message_properties_to_string(
-                msg.get_properties(),
-            )),
+            properties: Some(properties),
             reconsume_times: Some(0),
             unit_mode: Some(self.is_unit_mode()),
             batch: Some(batch),
@@ -0,0 +1,134 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_common::common::hasher::string_hasher::JavaStringHasher;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::message::message_single::Message;
+use rocketmq_common::common::message::MessageConst;
+
+/// Splits `msgs` by the queue of `mqs` each of them is sent to, keeping the order of the
+/// messages within a queue. A message with a sharding key goes to the queue its key hashes to,
+/// so messages sharing a key stay ordered, the others are spread over the queues round robin
+/// from `start_index`. The batches are returned in the order of `mqs`, empty ones left out.
+pub(crate) fn group_by_queue(
+    msgs: Vec<Message>,
+    mqs: &[MessageQueue],
+    start_index: usize,
+) -> Vec<(MessageQueue, Vec<Message>)> {
+    if mqs.is_empty() {
+        return Vec::new();
+    }
+    let mut batches: Vec<Vec<Message>> = vec![Vec::new(); mqs.len()];
+    let mut next_index = start_index;
+    for msg in msgs {
+        let index = match msg.get_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_SHARDING_KEY,
+        )) {
+            Some(sharding_key) => JavaStringHasher::new()
+                .hash_str(sharding_key.as_str())
+                .unsigned_abs() as usize,
+            None => {
+                next_index = next_index.wrapping_add(1);
+                next_index.wrapping_sub(1)
+            }
+        } % mqs.len();
+        batches[index].push(msg);
+    }
+    mqs.iter()
+        .cloned()
+        .zip(batches)
+        .filter(|(_, batch)| !batch.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::message::MessageTrait;
+
+    use super::*;
+
+    fn message(body: &str, sharding_key: Option<&str>) -> Message {
+        let mut msg = Message::new("BatchTopic", body.as_bytes());
+        if let Some(sharding_key) = sharding_key {
+            msg.put_property(
+                CheetahString::from_static_str(MessageConst::PROPERTY_SHARDING_KEY),
+                CheetahString::from(sharding_key),
+            );
+        }
+        msg
+    }
+
+    fn bodies(batch: &[Message]) -> Vec<&str> {
+        batch
+            .iter()
+            .map(|msg| std::str::from_utf8(msg.get_body().unwrap()).unwrap())
+            .collect()
+    }
+
+    fn queues(num: i32) -> Vec<MessageQueue> {
+        (0..num)
+            .map(|queue_id| MessageQueue::from_parts("BatchTopic", "broker-a", queue_id))
+            .collect()
+    }
+
+    #[test]
+    fn messages_without_sharding_key_are_spread_round_robin() {
+        let msgs = (0..5).map(|i| message(&i.to_string(), None)).collect();
+
+        let batches = group_by_queue(msgs, &queues(3), 1);
+
+        let grouped: Vec<(i32, Vec<&str>)> = batches
+            .iter()
+            .map(|(mq, batch)| (mq.get_queue_id(), bodies(batch)))
+            .collect();
+        assert_eq!(
+            grouped,
+            vec![(0, vec!["2"]), (1, vec!["0", "3"]), (2, vec!["1", "4"])]
+        );
+    }
+
+    #[test]
+    fn messages_sharing_a_sharding_key_go_to_the_same_queue_in_order() {
+        let msgs = vec![
+            message("a1", Some("order-a")),
+            message("b1", Some("order-b")),
+            message("a2", Some("order-a")),
+            message("b2", Some("order-b")),
+            message("a3", Some("order-a")),
+        ];
+
+        let batches = group_by_queue(msgs, &queues(4), 0);
+
+        let queue_of =
+            |key: &str| (JavaStringHasher::new().hash_str(key).unsigned_abs() % 4) as i32;
+        let keyed_bodies = |key: &str, prefix: char| -> Vec<&str> {
+            batches
+                .iter()
+                .filter(|(mq, _)| mq.get_queue_id() == queue_of(key))
+                .flat_map(|(_, batch)| bodies(batch))
+                .filter(|body| body.starts_with(prefix))
+                .collect()
+        };
+        assert_eq!(keyed_bodies("order-a", 'a'), vec!["a1", "a2", "a3"]);
+        assert_eq!(keyed_bodies("order-b", 'b'), vec!["b1", "b2"]);
+    }
+
+    #[test]
+    fn no_queue_no_batch() {
+        assert!(group_by_queue(vec![message("0", None)], &[], 0).is_empty());
+    }
+}
@@ -334,6 +334,10 @@ impl MQProducer for TransactionMQProducer {
         self.default_producer.send_batch(msgs).await
     }
 
+    async fn send_batch_by_queue(&mut self, msgs: Vec<Message>) -> Result<Vec<SendResult>> {
+        self.default_producer.send_batch_by_queue(msgs).await
+    }
+
     async fn send_batch_with_timeout(
         &mut self,
         msgs: Vec<Message>,
@@ -57,6 +57,7 @@ pub mod sys_flag;
 
 pub mod system_clock;
 pub mod thread;
+pub mod tls_config;
 pub mod topic;
 
 #[derive(Clone, Default, Eq, PartialEq, Copy)]
@@ -14,11 +14,14 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+pub mod ack_cleaned_offset_policy;
 pub mod attribute_enum;
+pub mod attribute_long_range;
 pub mod attribute_parser;
 pub mod attribute_util;
 pub mod cleanup_policy;
 pub mod cq_type;
+pub mod subscription_group_attributes;
 pub mod topic_attributes;
 pub mod topic_message_type;
 
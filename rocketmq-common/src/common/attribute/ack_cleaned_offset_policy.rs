@@ -0,0 +1,81 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt;
+use std::str::FromStr;
+
+/// What the broker does with an ack whose offset is below the min offset of the queue, i.e.
+/// the acked message has already been cleaned.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AckCleanedOffsetPolicy {
+    /// Reply `NoMessage` to the client.
+    #[default]
+    Reject,
+    /// Treat the ack as successful and advance the consumer offset to the min offset.
+    Ack,
+}
+
+impl fmt::Display for AckCleanedOffsetPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AckCleanedOffsetPolicy::Reject => write!(f, "REJECT"),
+            AckCleanedOffsetPolicy::Ack => write!(f, "ACK"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseAckCleanedOffsetPolicyError;
+
+impl fmt::Display for ParseAckCleanedOffsetPolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid ack cleaned offset policy")
+    }
+}
+
+impl FromStr for AckCleanedOffsetPolicy {
+    type Err = ParseAckCleanedOffsetPolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "REJECT" => Ok(AckCleanedOffsetPolicy::Reject),
+            "ACK" => Ok(AckCleanedOffsetPolicy::Ack),
+            _ => Err(ParseAckCleanedOffsetPolicyError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ack_cleaned_offset_policy_display() {
+        assert_eq!(AckCleanedOffsetPolicy::Reject.to_string(), "REJECT");
+        assert_eq!(AckCleanedOffsetPolicy::Ack.to_string(), "ACK");
+    }
+
+    #[test]
+    fn ack_cleaned_offset_policy_from_str_case_insensitive() {
+        assert_eq!("reject".parse(), Ok(AckCleanedOffsetPolicy::Reject));
+        assert_eq!("ACK".parse(), Ok(AckCleanedOffsetPolicy::Ack));
+    }
+
+    #[test]
+    fn ack_cleaned_offset_policy_from_str_invalid() {
+        assert!("invalid".parse::<AckCleanedOffsetPolicy>().is_err());
+    }
+}
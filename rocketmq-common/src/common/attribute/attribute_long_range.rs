@@ -0,0 +1,110 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::common::attribute::Attribute;
+use crate::common::attribute::AttributeTrait;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LongRangeAttribute {
+    pub(crate) attribute: Attribute,
+    pub(crate) min: i64,
+    pub(crate) max: i64,
+    pub(crate) default_value: i64,
+}
+
+impl AttributeTrait for LongRangeAttribute {
+    fn name(&self) -> String {
+        self.attribute.name.clone()
+    }
+
+    fn changeable(&self) -> bool {
+        self.attribute.changeable
+    }
+
+    fn verify(&self, value: &str) {
+        if self.parse(value).is_none() {
+            panic!(
+                "value of {} is not a number in range [{}, {}]: {}",
+                self.attribute.name, self.min, self.max, value
+            );
+        }
+    }
+}
+
+impl LongRangeAttribute {
+    pub fn get_name(&self) -> &str {
+        self.attribute.name.as_str()
+    }
+
+    pub fn get_default_value(&self) -> i64 {
+        self.default_value
+    }
+
+    pub fn get_min(&self) -> i64 {
+        self.min
+    }
+
+    pub fn get_max(&self) -> i64 {
+        self.max
+    }
+
+    /// Parses `value`, returning `None` if it is not a number within `[min, max]`.
+    pub fn parse(&self, value: &str) -> Option<i64> {
+        value
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|value| (self.min..=self.max).contains(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attribute() -> LongRangeAttribute {
+        LongRangeAttribute {
+            attribute: Attribute {
+                name: String::from("test.range"),
+                changeable: true,
+            },
+            min: 0,
+            max: 16,
+            default_value: 0,
+        }
+    }
+
+    #[test]
+    fn parse_accepts_values_within_range() {
+        let attribute = attribute();
+        assert_eq!(attribute.parse("0"), Some(0));
+        assert_eq!(attribute.parse(" 16 "), Some(16));
+    }
+
+    #[test]
+    fn parse_rejects_values_out_of_range_or_not_numbers() {
+        let attribute = attribute();
+        assert_eq!(attribute.parse("17"), None);
+        assert_eq!(attribute.parse("-1"), None);
+        assert_eq!(attribute.parse("eight"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "not a number in range")]
+    fn verify_panics_on_value_out_of_range() {
+        AttributeTrait::verify(&attribute(), "32");
+    }
+}
@@ -0,0 +1,58 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+
+use crate::common::attribute::ack_cleaned_offset_policy::AckCleanedOffsetPolicy;
+use crate::common::attribute::attribute_enum::EnumAttribute;
+use crate::common::attribute::Attribute;
+use crate::hashset;
+
+lazy_static! {
+    pub static ref ACK_CLEANED_OFFSET_POLICY_ATTRIBUTE: EnumAttribute = EnumAttribute {
+        attribute: Attribute {
+            name: String::from("ack.cleaned.offset.policy"),
+            changeable: true,
+        },
+        universe: hashset! {
+            AckCleanedOffsetPolicy::Reject.to_string(),
+            AckCleanedOffsetPolicy::Ack.to_string()
+        },
+        default_value: AckCleanedOffsetPolicy::Reject.to_string(),
+    };
+    pub static ref ACK_TWO_PHASE_ATTRIBUTE: EnumAttribute = EnumAttribute {
+        attribute: Attribute {
+            name: String::from("ack.two.phase"),
+            changeable: true,
+        },
+        universe: hashset! {String::from("true"), String::from("false")},
+        default_value: String::from("false"),
+    };
+    pub static ref ALL: HashMap<String, EnumAttribute> = {
+        let mut map = HashMap::<String, EnumAttribute>::new();
+        map.insert(
+            ACK_CLEANED_OFFSET_POLICY_ATTRIBUTE.get_name().to_string(),
+            ACK_CLEANED_OFFSET_POLICY_ATTRIBUTE.clone(),
+        );
+        map.insert(
+            ACK_TWO_PHASE_ATTRIBUTE.get_name().to_string(),
+            ACK_TWO_PHASE_ATTRIBUTE.clone(),
+        );
+        map
+    };
+}
@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use lazy_static::lazy_static;
 
 use crate::common::attribute::attribute_enum::EnumAttribute;
+use crate::common::attribute::attribute_long_range::LongRangeAttribute;
 use crate::common::attribute::topic_message_type::TopicMessageType;
 use crate::common::attribute::Attribute;
 use crate::hashset;
@@ -32,6 +33,25 @@ lazy_static! {
         universe: hashset! {String::from("BatchCQ"), String::from("SimpleCQ")},
         default_value: String::from("SimpleCQ"),
     };
+    pub static ref REVIVE_SHARDING_ATTRIBUTE: EnumAttribute = EnumAttribute {
+        attribute: Attribute {
+            name: String::from("revive.sharding"),
+            changeable: true,
+        },
+        universe: hashset! {String::from("true"), String::from("false")},
+        default_value: String::from("false"),
+    };
+    /// Number of revive queues the checkpoints and acks of a topic are spread over, `0` keeps
+    /// the revive queue count of the broker.
+    pub static ref REVIVE_QUEUE_NUM_ATTRIBUTE: LongRangeAttribute = LongRangeAttribute {
+        attribute: Attribute {
+            name: String::from("revive.queue.num"),
+            changeable: true,
+        },
+        min: 0,
+        max: 512,
+        default_value: 0,
+    };
     pub static ref ALL: HashMap<String, EnumAttribute> = {
         let mut map = HashMap::<String, EnumAttribute>::new();
         map.insert(
@@ -46,6 +66,10 @@ lazy_static! {
             TOPIC_MESSAGE_TYPE_ATTRIBUTE.get_name().to_string(),
             TOPIC_MESSAGE_TYPE_ATTRIBUTE.clone(),
         );
+        map.insert(
+            REVIVE_SHARDING_ATTRIBUTE.get_name().to_string(),
+            REVIVE_SHARDING_ATTRIBUTE.clone(),
+        );
         map
     };
 }
@@ -174,12 +174,65 @@ pub struct BrokerConfig {
     pub lock_in_strict_mode: bool,
     pub transaction_timeout: u64,
     pub transaction_op_msg_max_size: i32,
+    /// Times a half message is checked back with its producer before it is discarded.
+    pub transaction_check_max: i32,
+    /// Interval in milliseconds between two check-backs of the half messages.
+    pub transaction_check_interval: u64,
     pub default_message_request_mode: MessageRequestMode,
     pub default_pop_share_queue_num: i32,
     pub load_balance_poll_name_server_interval: u64,
     pub server_load_balancer_enable: bool,
     pub enable_remote_escape: bool,
     pub enable_pop_log: bool,
+    pub enable_pop_sticky_assignment: bool,
+    pub pop_sticky_assignment_lease_millis: u64,
+    pub enable_ack_property_enrichment: bool,
+    pub zone_name: CheetahString,
+    pub pop_ack_unique_id_cache_size: usize,
+    pub enable_pop_ack_unique_id_collision_fallback: bool,
+    pub ack_processing_max_concurrency: usize,
+    pub ack_priority_aging_millis: u64,
+    pub enable_ack_parity_telemetry: bool,
+    pub max_ack_extra_info_length: usize,
+    pub ack_stream_chunk_size: usize,
+    pub two_phase_ack_timeout_millis: u64,
+    pub batch_ack_result_encoding: CheetahString,
+    pub disable_ack_processing: bool,
+    pub reject_ack_during_store_recovery: bool,
+    pub enable_pop_buffer_merge: bool,
+    pub pop_ck_max_buffer_size: usize,
+    pub pop_ck_stay_buffer_time: u64,
+    pub revive_ack_msg_retry_times: u32,
+    pub batch_ack_max_bit_set_size: usize,
+    pub batch_ack_max_count: usize,
+    pub batch_ack_revive_put_concurrency: usize,
+    pub enable_pop_retry_dlq_fallback: bool,
+    pub pop_buffer_drain_timeout_millis: u64,
+    pub pop_ack_dedup_window_millis: u64,
+    pub min_ack_invisible_time_millis: i64,
+    pub max_ack_invisible_time_millis: i64,
+    pub enable_standalone_master_fast_path: bool,
+    pub ack_pop_time_skew_tolerance_millis: i64,
+    pub enable_pop_buffer_merge_wal: bool,
+    /// Once that many acks are held in the pop buffer, its oldest checkpoints are flushed early
+    /// and nothing is buffered until it holds fewer than `pop_ck_buffer_ack_low_water_mark`.
+    /// 0 disables flushing on pressure.
+    pub pop_ck_buffer_ack_high_water_mark: usize,
+    pub pop_ck_buffer_ack_low_water_mark: usize,
+    pub max_concurrent_ack_puts: usize,
+    pub ack_put_permit_timeout_millis: u64,
+    pub ack_process_timeout_millis: u64,
+    pub revive_queue_inspect_max_num: i32,
+    /// One of every that many acks failing to be stored is logged, the first one always is.
+    pub ack_failure_log_sample_rate: u64,
+    /// A group whose consume lag, summed over the queues it consumes, exceeds that many
+    /// messages is reported as a slow consumer.
+    pub slow_consumer_lag_threshold: i64,
+    /// The most pops held for a queue until messages arrive, more pops of the queue are
+    /// answered `PollingFull`.
+    pub pop_polling_size: usize,
+    /// Requests are checked against the accounts of `config/plainAcl.json` under the store root.
+    pub acl_enable: bool,
 }
 
 impl Default for BrokerConfig {
@@ -262,12 +315,54 @@ impl Default for BrokerConfig {
             lock_in_strict_mode: false,
             transaction_timeout: 6_000,
             transaction_op_msg_max_size: 4096,
+            transaction_check_max: 15,
+            transaction_check_interval: 30_000,
             default_message_request_mode: MessageRequestMode::Pull,
             default_pop_share_queue_num: -1,
             load_balance_poll_name_server_interval: 30_000,
             server_load_balancer_enable: true,
             enable_remote_escape: false,
             enable_pop_log: false,
+            enable_pop_sticky_assignment: false,
+            pop_sticky_assignment_lease_millis: 60_000,
+            enable_ack_property_enrichment: false,
+            zone_name: CheetahString::empty(),
+            pop_ack_unique_id_cache_size: 100_000,
+            enable_pop_ack_unique_id_collision_fallback: false,
+            ack_processing_max_concurrency: 0,
+            ack_priority_aging_millis: 1000,
+            enable_ack_parity_telemetry: false,
+            max_ack_extra_info_length: 1024,
+            ack_stream_chunk_size: 1024,
+            two_phase_ack_timeout_millis: 30_000,
+            batch_ack_result_encoding: CheetahString::from_static_str("AUTO"),
+            disable_ack_processing: false,
+            reject_ack_during_store_recovery: true,
+            enable_pop_buffer_merge: false,
+            pop_ck_max_buffer_size: 200_000,
+            pop_ck_stay_buffer_time: 10_000,
+            revive_ack_msg_retry_times: 3,
+            batch_ack_max_bit_set_size: 65_536,
+            batch_ack_max_count: 65_536,
+            batch_ack_revive_put_concurrency: 8,
+            enable_pop_retry_dlq_fallback: false,
+            pop_buffer_drain_timeout_millis: 3_000,
+            pop_ack_dedup_window_millis: 0,
+            min_ack_invisible_time_millis: 0,
+            max_ack_invisible_time_millis: 24 * 60 * 60 * 1000,
+            enable_standalone_master_fast_path: false,
+            ack_pop_time_skew_tolerance_millis: 5_000,
+            enable_pop_buffer_merge_wal: false,
+            pop_ck_buffer_ack_high_water_mark: 2_000_000,
+            pop_ck_buffer_ack_low_water_mark: 1_000_000,
+            max_concurrent_ack_puts: 1024,
+            ack_put_permit_timeout_millis: 1_000,
+            ack_process_timeout_millis: 10_000,
+            revive_queue_inspect_max_num: 256,
+            ack_failure_log_sample_rate: 100,
+            slow_consumer_lag_threshold: 100_000,
+            pop_polling_size: 1024,
+            acl_enable: false,
         }
     }
 }
@@ -502,6 +502,7 @@ impl MessageConst {
     pub const KEY_SEPARATOR: &'static str = " ";
     pub const PROPERTY_BORN_HOST: &'static str = "__BORNHOST";
     pub const PROPERTY_BORN_TIMESTAMP: &'static str = "BORN_TIMESTAMP";
+    pub const PROPERTY_BROKER_ID: &'static str = "BROKER_ID";
     pub const PROPERTY_BUYER_ID: &'static str = "BUYER_ID";
     pub const PROPERTY_CHECK_IMMUNITY_TIME_IN_SECONDS: &'static str =
         "CHECK_IMMUNITY_TIME_IN_SECONDS";
@@ -619,6 +620,7 @@ lazy_static! {
         set.insert(MessageConst::PROPERTY_REPLY_MESSAGE_ARRIVE_TIME);
         set.insert(MessageConst::PROPERTY_PUSH_REPLY_TIME);
         set.insert(MessageConst::PROPERTY_CLUSTER);
+        set.insert(MessageConst::PROPERTY_BROKER_ID);
         set.insert(MessageConst::PROPERTY_MESSAGE_TYPE);
         set.insert(MessageConst::PROPERTY_INNER_MULTI_QUEUE_OFFSET);
         set.insert(MessageConst::PROPERTY_TIMER_DELAY_MS);
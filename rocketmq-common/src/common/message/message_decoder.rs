@@ -677,7 +677,7 @@ pub fn encode(message_ext: &MessageExt, need_compress: bool) -> Result<Bytes> {
     }
 
     // 16 TOPIC
-    byte_buffer.put_i16(topic_len as i16);
+    byte_buffer.put_u8(topic_len as u8);
     byte_buffer.put_slice(topics);
 
     // 17 properties
@@ -875,6 +875,21 @@ mod tests {
         assert!(!bytes.is_empty());
     }
 
+    #[test]
+    fn encode_round_trips_through_decode() {
+        let mut message_ext = MessageExt::default();
+        message_ext.set_topic(CheetahString::from_static_str("test_topic"));
+        message_ext.set_body(Bytes::from("Hello, World!"));
+        message_ext.queue_offset = 10;
+        message_ext.reconsume_times = 3;
+        let mut bytes = encode(&message_ext, false).unwrap();
+        let decoded = decode(&mut bytes, true, false, false, false, false).unwrap();
+        assert_eq!(decoded.get_topic().as_str(), "test_topic");
+        assert_eq!(decoded.get_body().unwrap().as_ref(), b"Hello, World!");
+        assert_eq!(decoded.queue_offset, 10);
+        assert_eq!(decoded.reconsume_times, 3);
+    }
+
     #[test]
     fn encode_with_empty_body() {
         let mut message_ext = MessageExt::default();
@@ -31,6 +31,8 @@ impl PopAckConstants {
     pub const ACK_TAG: &'static str = "ack";
     pub const BATCH_ACK_TAG: &'static str = "bAck";
     pub const SPLIT: &'static str = "@";
+    /// Property of a revive topic message holding the CRC of its body.
+    pub const REVIVE_BODY_CRC: &'static str = "RBC";
 
     #[inline]
     pub fn build_cluster_revive_topic(cluster_name: &str) -> String {
@@ -17,11 +17,18 @@
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::common::tls_config::TlsConfig;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ServerConfig {
     pub listen_port: u32,
     pub bind_address: String,
+    /// Channels that neither read nor write for this long are closed by the server.
+    #[serde(default = "default_server_channel_max_idle_time_seconds")]
+    pub server_channel_max_idle_time_seconds: u64,
+    #[serde(default)]
+    pub tls_config: TlsConfig,
 }
 
 impl Default for ServerConfig {
@@ -29,10 +36,16 @@ impl Default for ServerConfig {
         ServerConfig {
             listen_port: 10911,
             bind_address: "0.0.0.0".to_string(),
+            server_channel_max_idle_time_seconds: default_server_channel_max_idle_time_seconds(),
+            tls_config: TlsConfig::default(),
         }
     }
 }
 
+fn default_server_channel_max_idle_time_seconds() -> u64 {
+    120
+}
+
 impl ServerConfig {
     pub fn bind_address(&self) -> String {
         self.bind_address.clone()
@@ -41,4 +54,8 @@ impl ServerConfig {
     pub fn listen_port(&self) -> u32 {
         self.listen_port
     }
+
+    pub fn server_channel_max_idle_time_seconds(&self) -> u64 {
+        self.server_channel_max_idle_time_seconds
+    }
 }
@@ -53,6 +53,19 @@ impl StatsItem {
         }
     }
 
+    pub fn add_value(&self, inc_value: u64, inc_times: u64) {
+        self.value.fetch_add(inc_value, Ordering::Relaxed);
+        self.times.fetch_add(inc_times, Ordering::Relaxed);
+    }
+
+    pub fn get_value(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    pub fn get_times(&self) -> u64 {
+        self.times.load(Ordering::Relaxed)
+    }
+
     pub fn compute_stats_data(cs_list: Arc<Mutex<LinkedList<CallSnapshot>>>) -> StatsSnapshot {
         let mut stats_snapshot = StatsSnapshot::new();
         let cs_list = cs_list.lock();
@@ -341,6 +354,15 @@ mod tests {
         assert_eq!(stats_item.stats_key, "TestKey");
     }
 
+    #[test]
+    fn add_value_accumulates_value_and_times() {
+        let stats_item = StatsItem::new("TestName", "TestKey");
+        stats_item.add_value(3, 1);
+        stats_item.add_value(5, 1);
+        assert_eq!(stats_item.get_value(), 8);
+        assert_eq!(stats_item.get_times(), 2);
+    }
+
     #[test]
     fn compute_stats_data_returns_correct_snapshot() {
         let cs_list = Arc::new(Mutex::new(LinkedList::new()));
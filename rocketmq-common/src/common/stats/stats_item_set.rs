@@ -14,17 +14,75 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::Arc;
+
+use dashmap::DashMap;
+
+use crate::common::stats::stats_item::StatsItem;
 use crate::common::stats::stats_snapshot::StatsSnapshot;
 
 #[derive(Debug)]
-pub struct StatsItemSet {}
+pub struct StatsItemSet {
+    stats_item_table: DashMap<String, Arc<StatsItem>>,
+    stats_name: String,
+}
 
 impl StatsItemSet {
-    pub fn new(_stats_name: String) -> Self {
-        StatsItemSet {}
+    pub fn new(stats_name: String) -> Self {
+        StatsItemSet {
+            stats_item_table: DashMap::new(),
+            stats_name,
+        }
+    }
+
+    pub fn get_stats_name(&self) -> &str {
+        &self.stats_name
+    }
+
+    pub fn add_value(&self, stats_key: &str, inc_value: u64, inc_times: u64) {
+        self.get_and_create_stats_item(stats_key)
+            .add_value(inc_value, inc_times);
+    }
+
+    pub fn get_stats_item(&self, stats_key: &str) -> Option<Arc<StatsItem>> {
+        self.stats_item_table
+            .get(stats_key)
+            .map(|item| Arc::clone(item.value()))
+    }
+
+    pub fn get_and_create_stats_item(&self, stats_key: &str) -> Arc<StatsItem> {
+        if let Some(stats_item) = self.stats_item_table.get(stats_key) {
+            return Arc::clone(stats_item.value());
+        }
+        Arc::clone(
+            self.stats_item_table
+                .entry(stats_key.to_string())
+                .or_insert_with(|| Arc::new(StatsItem::new(&self.stats_name, stats_key)))
+                .value(),
+        )
     }
 
     pub fn get_stats_data_in_minute(&self, stats_key: &str) -> StatsSnapshot {
-        StatsSnapshot::new()
+        match self.get_stats_item(stats_key) {
+            Some(stats_item) => stats_item.get_stats_data_in_minute(),
+            None => StatsSnapshot::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_value_creates_item_and_accumulates() {
+        let stats_set = StatsItemSet::new("TestName".to_string());
+        assert!(stats_set.get_stats_item("key").is_none());
+        stats_set.add_value("key", 2, 1);
+        stats_set.add_value("key", 3, 1);
+        let stats_item = stats_set.get_stats_item("key").unwrap();
+        assert_eq!(stats_item.get_value(), 5);
+        assert_eq!(stats_item.get_times(), 2);
+        assert!(stats_set.get_stats_item("other").is_none());
     }
 }
@@ -0,0 +1,56 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Whether remoting connections are encrypted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TlsMode {
+    /// Only plain connections are used.
+    #[default]
+    Disabled,
+    /// The server accepts both plain and TLS connections.
+    Permissive,
+    /// Only TLS connections are accepted.
+    Enforcing,
+}
+
+/// TLS settings shared by the remoting server and client.
+///
+/// A client uses TLS for every mode other than [`TlsMode::Disabled`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TlsConfig {
+    pub mode: TlsMode,
+    /// PEM certificate chain presented to the peer.
+    pub cert_path: Option<String>,
+    /// PEM private key of the certificate.
+    pub key_path: Option<String>,
+    /// PEM CA certificates the peer certificate is verified against. The server asks clients
+    /// for a certificate only when it is set.
+    pub trust_cert_path: Option<String>,
+    /// Name the client expects in the server certificate, the server IP by default.
+    pub server_name: Option<String>,
+}
+
+impl TlsConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.mode != TlsMode::Disabled
+    }
+}
@@ -29,6 +29,7 @@ use std::ops::DerefMut;
 use std::sync::Arc;
 use std::sync::Weak;
 
+pub use crate::common::attribute::subscription_group_attributes as SubscriptionGroupAttributes;
 pub use crate::common::attribute::topic_attributes as TopicAttributes;
 pub use crate::common::message::message_accessor as MessageAccessor;
 pub use crate::common::message::message_decoder as MessageDecoder;
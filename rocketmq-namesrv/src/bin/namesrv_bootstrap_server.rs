@@ -44,6 +44,7 @@ async fn main() -> anyhow::Result<()> {
         .set_server_config(ServerConfig {
             listen_port: args.port,
             bind_address: args.ip,
+            ..Default::default()
         })
         .build()
         .boot()
@@ -0,0 +1,110 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use cheetah_string::CheetahString;
+use ring::hmac;
+
+/// Extension field carrying the access key of the caller.
+pub const ACCESS_KEY: &str = "AccessKey";
+/// Extension field carrying the base64 encoded HMAC-SHA1 of the request content, keyed with
+/// the secret key of the caller.
+pub const SIGNATURE: &str = "Signature";
+
+/// Combines the content a request is signed over: the values of its extension fields sorted
+/// by key, the signature left out, followed by its body. Same as the Java client does, so
+/// either side can check the signature of the other.
+pub fn combine_request_content(
+    ext_fields: Option<&HashMap<CheetahString, CheetahString>>,
+    body: Option<&[u8]>,
+) -> Vec<u8> {
+    let sorted_fields = ext_fields
+        .into_iter()
+        .flatten()
+        .filter(|(key, _)| key.as_str() != SIGNATURE)
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect::<BTreeMap<_, _>>();
+    let mut content = Vec::new();
+    for value in sorted_fields.values() {
+        content.extend_from_slice(value.as_bytes());
+    }
+    if let Some(body) = body {
+        content.extend_from_slice(body);
+    }
+    content
+}
+
+/// Signs `content` with `secret_key`.
+pub fn calculate_signature(content: &[u8], secret_key: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret_key.as_bytes());
+    STANDARD.encode(hmac::sign(&key, content).as_ref())
+}
+
+/// Checks `signature` is the one of `content` signed with `secret_key`, the comparison taking
+/// the same time whatever the signature is.
+pub fn verify_signature(content: &[u8], secret_key: &str, signature: &str) -> bool {
+    let Ok(tag) = STANDARD.decode(signature) else {
+        return false;
+    };
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret_key.as_bytes());
+    hmac::verify(&key, content, &tag).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_is_hmac_sha1_in_base64() {
+        // the well known HMAC-SHA1 example
+        assert_eq!(
+            calculate_signature(b"The quick brown fox jumps over the lazy dog", "key"),
+            "3nybhbi3iqa8ino29wqQcBydtNk="
+        );
+    }
+
+    #[test]
+    fn content_is_sorted_fields_then_body() {
+        let mut ext_fields = HashMap::new();
+        ext_fields.insert(CheetahString::from("topic"), CheetahString::from("orders"));
+        ext_fields.insert(CheetahString::from(ACCESS_KEY), CheetahString::from("ak"));
+        ext_fields.insert(
+            CheetahString::from(SIGNATURE),
+            CheetahString::from("ignored"),
+        );
+        ext_fields.insert(CheetahString::from("queueId"), CheetahString::from("3"));
+
+        assert_eq!(
+            combine_request_content(Some(&ext_fields), Some(b"body")),
+            b"ak3ordersbody".to_vec()
+        );
+        assert_eq!(combine_request_content(None, None), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn verify_rejects_tampered_content_and_wrong_key() {
+        let signature = calculate_signature(b"content", "secret");
+        assert!(verify_signature(b"content", "secret", &signature));
+        assert!(!verify_signature(b"content!", "secret", &signature));
+        assert!(!verify_signature(b"content", "guess", &signature));
+        assert!(!verify_signature(b"content", "secret", "not base64"));
+        assert!(!verify_signature(b"content", "secret", "secret"));
+    }
+}
@@ -15,9 +15,11 @@
  * limitations under the License.
  */
 use std::collections::HashMap;
+use std::net::SocketAddr;
 
 use futures_util::SinkExt;
 use futures_util::StreamExt;
+use rocketmq_common::common::tls_config::TlsConfig;
 use rocketmq_rust::ArcMut;
 use tokio::sync::mpsc::Receiver;
 use tracing::error;
@@ -28,8 +30,11 @@ use crate::base::response_future::ResponseFuture;
 use crate::code::response_code::ResponseCode;
 use crate::connection::Connection;
 use crate::net::channel::Channel;
+use crate::net::response_stream::is_partial_response;
+use crate::net::tls;
 use crate::protocol::remoting_command::RemotingCommand;
 use crate::protocol::RemotingCommandType;
+use crate::protocol::SerializeType;
 use crate::remoting_error::RemotingError::ConnectionInvalid;
 use crate::remoting_error::RemotingError::Io;
 use crate::remoting_error::RemotingError::RemoteError;
@@ -106,6 +111,10 @@ async fn run_recv<PR: RequestProcessor>(mut client: ArcMut<ClientInner>, mut pro
                 // handle response
                 RemotingCommandType::RESPONSE => {
                     let opaque = msg.opaque();
+                    if is_partial_response(&msg) {
+                        // partial frames of a streamed response do not complete the request
+                        continue;
+                    }
                     if let Some(response_future) = client.response_table.remove(&opaque) {
                         let _ = response_future.tx.send(Ok(msg));
                     } else {
@@ -136,6 +145,8 @@ impl ClientInner {
         addr: T,
         processor: PR,
         tx: Option<&tokio::sync::broadcast::Sender<ConnectionNetEvent>>,
+        tls_config: &TlsConfig,
+        serialize_type: SerializeType,
     ) -> Result<(tokio::sync::mpsc::Sender<SendMessage>, ArcMut<ClientInner>)>
     where
         T: tokio::net::ToSocketAddrs,
@@ -148,7 +159,8 @@ impl ClientInner {
         let stream = tcp_stream?;
         let local_addr = stream.local_addr()?;
         let remote_address = stream.peer_addr()?;
-        let connection = Connection::new(stream);
+        let connection = tls::connect(stream, tls_config).await?;
+        connection.set_serialize_type(serialize_type);
         let response_table = ArcMut::new(HashMap::with_capacity(128));
         let channel = Channel::new(
             local_addr,
@@ -216,6 +228,7 @@ impl Client {
     /// # Arguments
     ///
     /// * `addr` - The address to connect to.
+    /// * `tls_config` - Whether and how the connection is encrypted.
     ///
     /// # Returns
     ///
@@ -224,6 +237,8 @@ impl Client {
         addr: T,
         processor: PR,
         tx: Option<&tokio::sync::broadcast::Sender<ConnectionNetEvent>>,
+        tls_config: &TlsConfig,
+        serialize_type: SerializeType,
     ) -> Result<Client>
     where
         T: tokio::net::ToSocketAddrs,
@@ -236,7 +251,8 @@ impl Client {
         Ok(Client {
             connection: Connection::new(tcp_stream?),
         })*/
-        let (tx, inner) = ClientInner::connect(addr, processor, tx).await?;
+        let (tx, inner) =
+            ClientInner::connect(addr, processor, tx, tls_config, serialize_type).await?;
         Ok(Client {
             //connection: inner.connection.clone(),
             inner,
@@ -344,6 +360,10 @@ impl Client {
         }
     }
 
+    pub fn remote_address(&self) -> SocketAddr {
+        self.inner.channel.remote_address()
+    }
+
     pub fn connection(&self) -> &Connection {
         self.inner.ctx.channel.connection_ref()
     }
@@ -16,6 +16,7 @@
  */
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::net::SocketAddr;
 use std::sync::atomic::AtomicI32;
 use std::sync::Arc;
 use std::time::Duration;
@@ -59,6 +60,7 @@ pub struct RocketmqDefaultClient<PR = DefaultRemotingRequestProcessor> {
     client_runtime: Arc<RocketMQRuntime>,
     processor: PR,
     tx: Option<tokio::sync::broadcast::Sender<ConnectionNetEvent>>,
+    rpc_hooks: Vec<Arc<Box<dyn RPCHook>>>,
 }
 impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
     pub fn new(tokio_client_config: Arc<TokioClientConfig>, processor: PR) -> Self {
@@ -80,11 +82,34 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
             client_runtime: Arc::new(RocketMQRuntime::new_multi(10, "client-thread")),
             processor,
             tx,
+            rpc_hooks: Vec::new(),
         }
     }
 }
 
 impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
+    fn do_before_rpc_hooks(
+        &self,
+        remote_addr: SocketAddr,
+        request: &mut RemotingCommand,
+    ) -> Result<()> {
+        for hook in &self.rpc_hooks {
+            hook.do_before_request(remote_addr, request)?;
+        }
+        Ok(())
+    }
+
+    fn do_after_rpc_hooks(
+        &self,
+        remote_addr: SocketAddr,
+        response: &mut RemotingCommand,
+    ) -> Result<()> {
+        for hook in &self.rpc_hooks {
+            hook.do_after_response(remote_addr, response)?;
+        }
+        Ok(())
+    }
+
     async fn get_and_create_nameserver_client(&self) -> Option<Client> {
         let mut addr = self.namesrv_addr_choosed.as_ref().clone();
         if let Some(ref addr) = addr {
@@ -182,7 +207,14 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
         let addr_inner = addr.to_string();
 
         match time::timeout(duration, async {
-            Client::connect(addr_inner, self.processor.clone(), self.tx.as_ref()).await
+            Client::connect(
+                addr_inner,
+                self.processor.clone(),
+                self.tx.as_ref(),
+                &self.tokio_client_config.tls_config,
+                self.tokio_client_config.serialize_type,
+            )
+            .await
         })
         .await
         {
@@ -255,11 +287,11 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RemotingService for Rocketmq
     }
 
     fn register_rpc_hook(&mut self, hook: Arc<Box<dyn RPCHook>>) {
-        todo!()
+        self.rpc_hooks.push(hook);
     }
 
     fn clear_rpc_hook(&mut self) {
-        todo!()
+        self.rpc_hooks.clear();
     }
 }
 
@@ -328,14 +360,16 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RemotingClient for RocketmqD
     async fn invoke_async(
         &self,
         addr: Option<&CheetahString>,
-        request: RemotingCommand,
+        mut request: RemotingCommand,
         timeout_millis: u64,
     ) -> Result<RemotingCommand> {
         let client = self.get_and_create_client(addr).await;
         match client {
             None => Err(RemotingError::RemoteError("get client failed".to_string())),
             Some(mut client) => {
-                match self
+                let remote_addr = client.remote_address();
+                self.do_before_rpc_hooks(remote_addr, &mut request)?;
+                let mut response = match self
                     .client_runtime
                     .get_handle()
                     .spawn(async move {
@@ -348,13 +382,15 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RemotingClient for RocketmqD
                 {
                     Ok(result) => match result {
                         Ok(response) => match response {
-                            Ok(value) => Ok(value),
-                            Err(e) => Err(RemotingError::RemoteError(e.to_string())),
+                            Ok(value) => value,
+                            Err(e) => return Err(RemotingError::RemoteError(e.to_string())),
                         },
-                        Err(err) => Err(RemotingError::RemoteError(err.to_string())),
+                        Err(err) => return Err(RemotingError::RemoteError(err.to_string())),
                     },
-                    Err(err) => Err(RemotingError::RemoteError(err.to_string())),
-                }
+                    Err(err) => return Err(RemotingError::RemoteError(err.to_string())),
+                };
+                self.do_after_rpc_hooks(remote_addr, &mut response)?;
+                Ok(response)
             }
         }
     }
@@ -362,7 +398,7 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RemotingClient for RocketmqD
     async fn invoke_oneway(
         &self,
         addr: &CheetahString,
-        request: RemotingCommand,
+        mut request: RemotingCommand,
         timeout_millis: u64,
     ) {
         let client = self.get_and_create_client(Some(addr)).await;
@@ -371,9 +407,15 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RemotingClient for RocketmqD
                 error!("get client failed");
             }
             Some(mut client) => {
+                if let Err(err) = self.do_before_rpc_hooks(client.remote_address(), &mut request) {
+                    error!(
+                        "oneway request to {} is aborted by a rpc hook: {}",
+                        addr, err
+                    );
+                    return;
+                }
                 self.client_runtime.get_handle().spawn(async move {
                     match time::timeout(Duration::from_millis(timeout_millis), async move {
-                        let mut request = request;
                         request.mark_oneway_rpc_ref();
                         client.send(request).await
                     })
@@ -128,7 +128,16 @@ pub enum RequestCode {
     GetTopicConfig = 351,
     GetSubscriptionGroupConfig = 352,
     UpdateAndGetGroupForbidden = 353,
+    UpdateAckInvisibleTimeCap = 354,
+    ResetGroupOffsetAllQueues = 355,
+    EndTwoPhaseAck = 356,
+    UpdateAckProcessingSwitch = 357,
+    QueryPopInflightMessageNum = 358,
+    QueryReviveQueueAcks = 359,
+    QueryReviveLag = 360,
     LitePullMessage = 361,
+    QueryConsumerLag = 362,
+    TrimTopicBeforeOffset = 363,
     QueryAssignment = 400,
     SetMessageRequestMode = 401,
     GetAllMessageRequestMode = 402,
@@ -271,7 +280,16 @@ impl From<i32> for RequestCode {
             351 => RequestCode::GetTopicConfig,
             352 => RequestCode::GetSubscriptionGroupConfig,
             353 => RequestCode::UpdateAndGetGroupForbidden,
+            354 => RequestCode::UpdateAckInvisibleTimeCap,
+            355 => RequestCode::ResetGroupOffsetAllQueues,
+            356 => RequestCode::EndTwoPhaseAck,
+            357 => RequestCode::UpdateAckProcessingSwitch,
+            358 => RequestCode::QueryPopInflightMessageNum,
+            359 => RequestCode::QueryReviveQueueAcks,
+            360 => RequestCode::QueryReviveLag,
             361 => RequestCode::LitePullMessage,
+            362 => RequestCode::QueryConsumerLag,
+            363 => RequestCode::TrimTopicBeforeOffset,
             400 => RequestCode::QueryAssignment,
             401 => RequestCode::SetMessageRequestMode,
             402 => RequestCode::GetAllMessageRequestMode,
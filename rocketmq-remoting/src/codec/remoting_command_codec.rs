@@ -15,36 +15,52 @@
  * limitations under the License.
  */
 
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
 use bytes::BufMut;
 use bytes::BytesMut;
 use tokio_util::codec::Decoder;
 use tokio_util::codec::Encoder;
 
 use crate::protocol::remoting_command::RemotingCommand;
+use crate::protocol::remoting_command::SERIALIZE_TYPE_CONFIG_IN_THIS_SERVER;
+use crate::protocol::RemotingCommandType;
+use crate::protocol::SerializeType;
 use crate::remoting_error::RemotingError;
 
-/// Encodes a `RemotingCommand` into a `BytesMut` buffer.
-///
-/// This method takes a `RemotingCommand` and a mutable reference to a `BytesMut` buffer as
-/// parameters. It first encodes the header of the `RemotingCommand` and calculates the lengths of
-/// the header and body. It then reserves the necessary space in the `BytesMut` buffer and writes
-/// the total length, serialize type, header, and body to the buffer.
-///
-/// # Arguments
-///
-/// * `item` - A `RemotingCommand` that is to be encoded.
-/// * `dst` - A mutable reference to a `BytesMut` buffer where the encoded command will be written.
-///
-/// # Returns
-///
-/// * `Result<(), Self::Error>` - Returns `Ok(())` if the encoding is successful, otherwise returns
-///   an `Err` with a `RemotingError`.
+/// The serialize type negotiated on a connection, shared by the connection and its codec.
 ///
-/// # Errors
-///
-/// This function will return an error if the encoding process fails.
+/// It starts as the serialize type configured for this process and then follows the requests
+/// read from the peer, so a peer asking for JSON headers gets its responses in JSON.
 #[derive(Debug, Clone)]
-pub struct RemotingCommandCodec;
+pub(crate) struct NegotiatedSerializeType(Arc<AtomicU8>);
+
+impl Default for NegotiatedSerializeType {
+    fn default() -> Self {
+        Self(Arc::new(AtomicU8::new(
+            SERIALIZE_TYPE_CONFIG_IN_THIS_SERVER.get_code(),
+        )))
+    }
+}
+
+impl NegotiatedSerializeType {
+    pub(crate) fn get(&self) -> SerializeType {
+        SerializeType::value_of(self.0.load(Ordering::Acquire)).unwrap_or(SerializeType::ROCKETMQ)
+    }
+
+    pub(crate) fn set(&self, serialize_type: SerializeType) {
+        self.0.store(serialize_type.get_code(), Ordering::Release);
+    }
+}
+
+/// Codec of the commands of a connection, the headers of the commands it writes are encoded in
+/// the serialize type negotiated on the connection.
+#[derive(Debug, Clone)]
+pub struct RemotingCommandCodec {
+    serialize_type: NegotiatedSerializeType,
+}
 
 impl Default for RemotingCommandCodec {
     fn default() -> Self {
@@ -54,7 +70,20 @@ impl Default for RemotingCommandCodec {
 
 impl RemotingCommandCodec {
     pub fn new() -> Self {
-        Self {}
+        Self::with_serialize_type(NegotiatedSerializeType::default())
+    }
+
+    pub(crate) fn with_serialize_type(serialize_type: NegotiatedSerializeType) -> Self {
+        Self { serialize_type }
+    }
+
+    /// The serialize type the headers of the commands written are encoded in.
+    pub fn serialize_type(&self) -> SerializeType {
+        self.serialize_type.get()
+    }
+
+    pub fn set_serialize_type(&self, serialize_type: SerializeType) {
+        self.serialize_type.set(serialize_type);
     }
 }
 
@@ -89,7 +118,14 @@ impl Decoder for RemotingCommandCodec {
     ///
     /// This function will return an error if the decoding process fails.
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        RemotingCommand::decode(src)
+        let cmd = RemotingCommand::decode(src)?;
+        // the peer asks for its responses in the serialize type of its requests
+        if let Some(cmd) = cmd.as_ref() {
+            if cmd.get_type() == RemotingCommandType::REQUEST {
+                self.serialize_type.set(cmd.serialize_type());
+            }
+        }
+        Ok(cmd)
         /* let read_to = src.len();
         if read_to < 4 {
             // Wait for more data when there are less than 4 bytes.
@@ -160,7 +196,7 @@ impl Encoder<RemotingCommand> for RemotingCommandCodec {
     ///
     /// This function will return an error if the encoding process fails.
     fn encode(&mut self, item: RemotingCommand, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        let mut item = item;
+        let mut item = item.set_serialize_type(self.serialize_type.get());
         item.fast_header_encode(dst);
         if let Some(body_inner) = item.get_body() {
             dst.put(body_inner.as_ref());
@@ -219,4 +255,81 @@ mod tests {
             .set_remark_option(Some("remark".to_string()));
         assert!(encoder.encode(command, &mut dst).is_ok());
     }
+
+    fn round_trip(serialize_type: SerializeType) -> RemotingCommand {
+        let mut codec = RemotingCommandCodec::new();
+        codec.set_serialize_type(serialize_type);
+        let mut dst = BytesMut::new();
+        let command = RemotingCommand::create_request_command(
+            1,
+            GetRouteInfoRequestHeader::new("TopicTest", Some(true)),
+        )
+        .set_body(Bytes::from("body"))
+        .set_remark_option(Some("remark".to_string()));
+        codec.encode(command, &mut dst).unwrap();
+        codec.decode(&mut dst).unwrap().unwrap()
+    }
+
+    #[test]
+    fn custom_header_round_trips_as_json() {
+        let decoded = round_trip(SerializeType::JSON);
+        assert_eq!(decoded.serialize_type(), SerializeType::JSON);
+        let header = decoded
+            .decode_command_custom_header::<GetRouteInfoRequestHeader>()
+            .unwrap();
+        assert_eq!(header.topic, "TopicTest");
+        assert_eq!(header.accept_standard_json_only, Some(true));
+        assert_eq!(decoded.remark().map(|r| r.as_str()), Some("remark"));
+        assert_eq!(decoded.body().as_deref(), Some(&b"body"[..]));
+    }
+
+    #[test]
+    fn custom_header_round_trips_as_rocketmq() {
+        let decoded = round_trip(SerializeType::ROCKETMQ);
+        assert_eq!(decoded.serialize_type(), SerializeType::ROCKETMQ);
+        let header = decoded
+            .decode_command_custom_header::<GetRouteInfoRequestHeader>()
+            .unwrap();
+        assert_eq!(header.topic, "TopicTest");
+        assert_eq!(header.accept_standard_json_only, Some(true));
+        assert_eq!(decoded.remark().map(|r| r.as_str()), Some("remark"));
+        assert_eq!(decoded.body().as_deref(), Some(&b"body"[..]));
+    }
+
+    #[test]
+    fn encode_without_custom_header_as_rocketmq() {
+        let mut codec = RemotingCommandCodec::new();
+        codec.set_serialize_type(SerializeType::ROCKETMQ);
+        let mut dst = BytesMut::new();
+        let command = RemotingCommand::create_response_command().set_opaque(7);
+        codec.encode(command, &mut dst).unwrap();
+        let decoded = codec.decode(&mut dst).unwrap().unwrap();
+        assert_eq!(decoded.opaque(), 7);
+        assert!(decoded
+            .get_ext_fields()
+            .is_none_or(|fields| fields.is_empty()));
+    }
+
+    #[test]
+    fn response_follows_serialize_type_of_request() {
+        let mut client = RemotingCommandCodec::new();
+        client.set_serialize_type(SerializeType::JSON);
+        let mut server = RemotingCommandCodec::new();
+        server.set_serialize_type(SerializeType::ROCKETMQ);
+
+        let mut dst = BytesMut::new();
+        let request = RemotingCommand::create_request_command(
+            1,
+            GetRouteInfoRequestHeader::new("TopicTest", None),
+        );
+        client.encode(request, &mut dst).unwrap();
+        server.decode(&mut dst).unwrap().unwrap();
+        assert_eq!(server.serialize_type(), SerializeType::JSON);
+
+        let response = RemotingCommand::create_response_command();
+        server.encode(response, &mut dst).unwrap();
+        let decoded = client.decode(&mut dst).unwrap().unwrap();
+        assert_eq!(decoded.serialize_type(), SerializeType::JSON);
+        assert_eq!(client.serialize_type(), SerializeType::JSON);
+    }
 }
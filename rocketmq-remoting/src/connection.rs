@@ -16,15 +16,28 @@
  */
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 
 use futures_util::stream::SplitSink;
 use futures_util::stream::SplitStream;
 use futures_util::StreamExt;
-use tokio::net::TcpStream;
+use rocketmq_common::TimeUtils::get_current_millis;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
 use tokio_util::codec::Framed;
 
+use crate::codec::remoting_command_codec::NegotiatedSerializeType;
 use crate::codec::remoting_command_codec::RemotingCommandCodec;
 use crate::protocol::remoting_command::RemotingCommand;
+use crate::protocol::SerializeType;
+
+/// Byte stream a `Connection` runs over, a plain `TcpStream` or a TLS session on top of one.
+pub trait Transport: AsyncRead + AsyncWrite + Unpin + Send + Sync {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + Sync> Transport for T {}
+
+type FramedTransport = Framed<Box<dyn Transport>, RemotingCommandCodec>;
 
 /// Send and receive `Frame` values from a remote peer.
 ///
@@ -41,14 +54,20 @@ use crate::protocol::remoting_command::RemotingCommand;
 pub struct Connection {
     /// The `Framed` instance used for reading from and writing to the TCP stream.
     /// It leverages the `RemotingCommandCodec` for encoding and decoding frames.
-    //pub(crate) framed: Framed<TcpStream, RemotingCommandCodec>,
-    pub(crate) writer: SplitSink<Framed<TcpStream, RemotingCommandCodec>, RemotingCommand>,
-    pub(crate) reader: SplitStream<Framed<TcpStream, RemotingCommandCodec>>,
+    //pub(crate) framed: FramedTransport,
+    pub(crate) writer: SplitSink<FramedTransport, RemotingCommand>,
+    pub(crate) reader: SplitStream<FramedTransport>,
 
     /// A boolean flag indicating the current state of the connection.
     /// `true` means the connection is in a good state, while `false` indicates
     /// there are issues with the connection.
     pub(crate) ok: bool,
+
+    /// When a frame was last read from or written to the peer, in milliseconds.
+    last_activity_millis: AtomicU64,
+
+    /// The serialize type the command headers are written in, shared with the codec.
+    serialize_type: NegotiatedSerializeType,
 }
 
 impl Hash for Connection {
@@ -58,13 +77,10 @@ impl Hash for Connection {
 
         // Use the addr: *const _ess of writer and reader to hash them (they serve as a unique
         // identifier for these components)
-        let writer_addr: *const SplitSink<
-            Framed<TcpStream, RemotingCommandCodec>,
-            RemotingCommand,
-        > = &self.writer
-            as *const SplitSink<Framed<TcpStream, RemotingCommandCodec>, RemotingCommand>;
-        let reader_addr: *const SplitStream<Framed<TcpStream, RemotingCommandCodec>> =
-            &self.reader as *const SplitStream<Framed<TcpStream, RemotingCommandCodec>>;
+        let writer_addr: *const SplitSink<FramedTransport, RemotingCommand> =
+            &self.writer as *const SplitSink<FramedTransport, RemotingCommand>;
+        let reader_addr: *const SplitStream<FramedTransport> =
+            &self.reader as *const SplitStream<FramedTransport>;
 
         writer_addr.hash(state);
         reader_addr.hash(state);
@@ -89,31 +105,60 @@ impl Connection {
     ///
     /// # Arguments
     ///
-    /// * `tcp_stream` - The `TcpStream` associated with the connection.
+    /// * `stream` - The `TcpStream`, or the TLS stream over it, associated with the connection.
     ///
     /// # Returns
     ///
     /// A new `Connection` instance.
-    pub fn new(tcp_stream: TcpStream) -> Connection {
-        let framed = Framed::with_capacity(tcp_stream, RemotingCommandCodec::new(), 1024 * 4);
+    pub fn new<T: Transport + 'static>(stream: T) -> Connection {
+        let transport: Box<dyn Transport> = Box::new(stream);
+        let serialize_type = NegotiatedSerializeType::default();
+        let framed = Framed::with_capacity(
+            transport,
+            RemotingCommandCodec::with_serialize_type(serialize_type.clone()),
+            1024 * 4,
+        );
         let (writer, reader) = framed.split();
         Self {
             writer,
             reader,
             ok: true,
+            last_activity_millis: AtomicU64::new(get_current_millis()),
+            serialize_type,
         }
     }
 }
 
 impl Connection {
-    /*pub fn framed(&self) -> &Framed<TcpStream, RemotingCommandCodec> {
+    /*pub fn framed(&self) -> &FramedTransport {
         &self.framed
     }*/
-    pub fn reader(&self) -> &SplitStream<Framed<TcpStream, RemotingCommandCodec>> {
+    pub fn reader(&self) -> &SplitStream<FramedTransport> {
         &self.reader
     }
 
-    pub fn writer(&self) -> &SplitSink<Framed<TcpStream, RemotingCommandCodec>, RemotingCommand> {
+    pub fn writer(&self) -> &SplitSink<FramedTransport, RemotingCommand> {
         &self.writer
     }
+
+    /// The serialize type the headers of the commands written to the peer are encoded in.
+    pub fn serialize_type(&self) -> SerializeType {
+        self.serialize_type.get()
+    }
+
+    /// Encodes the headers of the commands written from now on in `serialize_type`, a peer
+    /// honoring the negotiation answers in the same type.
+    pub fn set_serialize_type(&self, serialize_type: SerializeType) {
+        self.serialize_type.set(serialize_type);
+    }
+
+    /// Records that a frame was just read from or written to the peer.
+    pub fn touch(&self) {
+        self.last_activity_millis
+            .store(get_current_millis(), Ordering::Relaxed);
+    }
+
+    pub fn last_activity_millis(&self) -> u64 {
+        self.last_activity_millis.load(Ordering::Relaxed)
+    }
 }
@@ -30,6 +30,7 @@ pub mod remoting_error;
 pub use crate::protocol::rocketmq_serializable;
 use crate::remoting_error::RemotingError;
 
+pub mod acl;
 pub mod base;
 pub mod remoting;
 pub mod remoting_server;
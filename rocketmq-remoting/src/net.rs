@@ -16,3 +16,5 @@
  */
 
 pub mod channel;
+pub mod response_stream;
+pub mod tls;
@@ -20,6 +20,8 @@ use std::fmt::Display;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::net::SocketAddr;
+use std::sync::atomic::AtomicI32;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -32,6 +34,7 @@ use uuid::Uuid;
 
 use crate::base::response_future::ResponseFuture;
 use crate::connection::Connection;
+use crate::protocol::heartbeat::pop_ack_protocol_version::PopAckProtocolVersion;
 use crate::protocol::remoting_command::RemotingCommand;
 use crate::remoting_error::RemotingError;
 use crate::remoting_error::RemotingError::ChannelSendRequestFailed;
@@ -46,6 +49,7 @@ pub struct Channel {
     tx: tokio::sync::mpsc::Sender<ChannelMessage>,
     pub(crate) connection: ArcMut<Connection>,
     pub(crate) response_table: ArcMut<HashMap<i32, ResponseFuture>>,
+    pop_ack_protocol_version: Arc<AtomicI32>,
 }
 
 type ChannelMessage = (
@@ -68,7 +72,7 @@ pub(crate) async fn run_send(
             );
         }
         match connection.writer.send(request).await {
-            Ok(_) => {}
+            Ok(_) => connection.touch(),
             Err(error) => match error {
                 Io(error) => {
                     error!("send request failed: {}", error);
@@ -148,6 +152,9 @@ impl Channel {
             tx,
             connection,
             response_table,
+            pop_ack_protocol_version: Arc::new(AtomicI32::new(
+                PopAckProtocolVersion::default() as i32
+            )),
         }
     }
 }
@@ -173,6 +180,17 @@ impl Channel {
         self.channel_id.as_str()
     }
 
+    /// Pop/ack protocol version negotiated with the peer, shared by all clones of the channel.
+    pub fn pop_ack_protocol_version(&self) -> PopAckProtocolVersion {
+        PopAckProtocolVersion::from_i32(self.pop_ack_protocol_version.load(Ordering::Acquire))
+            .unwrap_or_default()
+    }
+
+    pub fn set_pop_ack_protocol_version(&self, version: PopAckProtocolVersion) {
+        self.pop_ack_protocol_version
+            .store(version as i32, Ordering::Release);
+    }
+
     pub fn connection(&self) -> ArcMut<Connection> {
         self.connection.clone()
     }
@@ -218,6 +236,16 @@ impl Channel {
         }
     }
 
+    /// Queues a response frame, such as a partial frame of a streamed response, which is
+    /// written behind the requests already queued on the channel.
+    pub async fn send_response_frame(&mut self, response: RemotingCommand) -> Result<()> {
+        if let Err(err) = self.tx.send((response, None, None)).await {
+            error!("send response frame failed: {}", err);
+            return Err(ChannelSendRequestFailed(err.to_string()));
+        }
+        Ok(())
+    }
+
     pub async fn send_one_way(
         &mut self,
         request: RemotingCommand,
@@ -0,0 +1,154 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Responses sent as several frames for a single request.
+//!
+//! A request opts in by carrying the [`STREAM_RESPONSE`] ext field. The processor then sends
+//! any number of partial frames, each marked with [`PARTIAL_RESPONSE`] and numbered by
+//! [`FRAME_INDEX`], before returning the final response as usual, which carries the number
+//! of partial frames sent before it in [`FRAME_COUNT`]. Partial frames are written through the
+//! channel queue while the final response is written directly, so a client must rely on
+//! [`FRAME_COUNT`] rather than on arrival order to know it got every frame.
+
+use crate::net::channel::Channel;
+use crate::protocol::remoting_command::RemotingCommand;
+use crate::Result;
+
+/// Request ext field asking for a streamed response.
+pub const STREAM_RESPONSE: &str = "streamResponse";
+/// Response ext field marking a partial frame.
+pub const PARTIAL_RESPONSE: &str = "partialResponse";
+/// Response ext field holding the index of a partial frame, starting at 0.
+pub const FRAME_INDEX: &str = "frameIndex";
+/// Final response ext field holding the number of partial frames sent before it.
+pub const FRAME_COUNT: &str = "frameCount";
+
+pub fn is_stream_response_requested(request: &RemotingCommand) -> bool {
+    request
+        .get_ext_fields()
+        .and_then(|ext_fields| ext_fields.get(STREAM_RESPONSE))
+        .is_some_and(|value| value.as_str() == "true")
+}
+
+pub fn is_partial_response(response: &RemotingCommand) -> bool {
+    response
+        .get_ext_fields()
+        .is_some_and(|ext_fields| ext_fields.contains_key(PARTIAL_RESPONSE))
+}
+
+/// Destination of the partial frames of a streamed response.
+#[trait_variant::make(ResponseFrameSink: Send)]
+pub trait LocalResponseFrameSink {
+    async fn send_frame(&mut self, frame: RemotingCommand) -> Result<()>;
+}
+
+impl ResponseFrameSink for Channel {
+    async fn send_frame(&mut self, frame: RemotingCommand) -> Result<()> {
+        self.send_response_frame(frame).await
+    }
+}
+
+/// Sends the partial frames of the response to the request with the given opaque.
+pub struct ResponseStream<S> {
+    sink: S,
+    opaque: i32,
+    frame_count: i32,
+}
+
+impl<S: ResponseFrameSink> ResponseStream<S> {
+    pub fn new(sink: S, opaque: i32) -> Self {
+        ResponseStream {
+            sink,
+            opaque,
+            frame_count: 0,
+        }
+    }
+
+    pub fn into_sink(self) -> S {
+        self.sink
+    }
+
+    pub fn frame_count(&self) -> i32 {
+        self.frame_count
+    }
+
+    pub async fn send_partial(&mut self, frame: RemotingCommand) -> Result<()> {
+        let mut frame = frame.mark_response_type().set_opaque(self.opaque);
+        frame.add_ext_field(PARTIAL_RESPONSE, true.to_string());
+        frame.add_ext_field(FRAME_INDEX, self.frame_count.to_string());
+        self.sink.send_frame(frame).await?;
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    /// Stamps the number of partial frames sent so far onto the final response.
+    pub fn finish(&self, mut response: RemotingCommand) -> RemotingCommand {
+        response.add_ext_field(FRAME_COUNT, self.frame_count.to_string());
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct VecSink(Vec<RemotingCommand>);
+
+    impl ResponseFrameSink for VecSink {
+        async fn send_frame(&mut self, frame: RemotingCommand) -> Result<()> {
+            self.0.push(frame);
+            Ok(())
+        }
+    }
+
+    fn ext_field(command: &RemotingCommand, key: &str) -> Option<String> {
+        command
+            .get_ext_fields()
+            .and_then(|ext_fields| ext_fields.get(key))
+            .map(|value| value.to_string())
+    }
+
+    #[test]
+    fn stream_response_requested_by_ext_field() {
+        let mut request = RemotingCommand::create_remoting_command(0);
+        assert!(!is_stream_response_requested(&request));
+        request.add_ext_field(STREAM_RESPONSE, "true");
+        assert!(is_stream_response_requested(&request));
+    }
+
+    #[tokio::test]
+    async fn partial_frames_are_numbered_and_counted() {
+        let mut stream = ResponseStream::new(VecSink::default(), 42);
+        for _ in 0..3 {
+            stream
+                .send_partial(RemotingCommand::create_response_command())
+                .await
+                .unwrap();
+        }
+        let response = stream.finish(RemotingCommand::create_response_command());
+        assert!(!is_partial_response(&response));
+        assert_eq!(ext_field(&response, FRAME_COUNT).as_deref(), Some("3"));
+
+        let frames = stream.into_sink().0;
+        assert_eq!(frames.len(), 3);
+        for (index, frame) in frames.iter().enumerate() {
+            assert!(is_partial_response(frame));
+            assert_eq!(frame.opaque(), 42);
+            assert_eq!(ext_field(frame, FRAME_INDEX), Some(index.to_string()));
+        }
+    }
+}
@@ -17,5 +17,6 @@
 
 pub mod consume_stats;
 pub mod offset_wrapper;
+pub mod response_body_format;
 pub mod topic_offset;
 pub mod topic_stats_table;
@@ -0,0 +1,141 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
+use serde::Serialize;
+
+use crate::protocol::remoting_command::RemotingCommand;
+
+/// Encoding of an admin response body, negotiated by the request.
+///
+/// Admin clients put [`ResponseBodyFormat::EXT_FIELD_KEY`] into the request ext fields to ask
+/// for a human readable body. Without it the broker keeps answering with the compact encoding
+/// produced by `RemotingSerializable::encode`. Both encodings are JSON, so a body in either
+/// format decodes through `RemotingDeserializable::decode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResponseBodyFormat {
+    #[default]
+    Compact,
+    Json,
+}
+
+impl ResponseBodyFormat {
+    pub const EXT_FIELD_KEY: &'static str = "bodyFormat";
+
+    /// Reads the format requested by `request`, falling back to [`ResponseBodyFormat::Compact`]
+    /// when the field is absent or unknown.
+    pub fn from_request(request: &RemotingCommand) -> Self {
+        request
+            .get_ext_fields()
+            .and_then(|fields| fields.get(Self::EXT_FIELD_KEY))
+            .map_or(ResponseBodyFormat::Compact, |value| {
+                Self::from_name(value.as_str())
+            })
+    }
+
+    pub fn from_name(name: &str) -> Self {
+        match name.to_ascii_uppercase().as_str() {
+            "JSON" => ResponseBodyFormat::Json,
+            _ => ResponseBodyFormat::Compact,
+        }
+    }
+
+    /// Marks `request` so that the broker answers with this format.
+    pub fn apply_to_request(&self, request: &mut RemotingCommand) {
+        let mut ext_fields = request.get_ext_fields().cloned().unwrap_or_default();
+        ext_fields.insert(
+            CheetahString::from_static_str(Self::EXT_FIELD_KEY),
+            CheetahString::from_string(self.to_string()),
+        );
+        *request = std::mem::take(request).set_ext_fields(ext_fields);
+    }
+
+    pub fn encode<T: Serialize>(&self, body: &T) -> Result<Vec<u8>, rocketmq_common::error::Error> {
+        match self {
+            ResponseBodyFormat::Compact => SerdeJsonUtils::to_json_vec(body),
+            ResponseBodyFormat::Json => SerdeJsonUtils::to_json_vec_pretty(body),
+        }
+    }
+}
+
+impl Display for ResponseBodyFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResponseBodyFormat::Compact => write!(f, "COMPACT"),
+            ResponseBodyFormat::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code::request_code::RequestCode;
+    use crate::protocol::admin::topic_offset::TopicOffset;
+    use crate::protocol::RemotingDeserializable;
+
+    fn topic_offset() -> TopicOffset {
+        let mut topic_offset = TopicOffset::new();
+        topic_offset.set_min_offset(1);
+        topic_offset.set_max_offset(10);
+        topic_offset.set_last_update_timestamp(100);
+        topic_offset
+    }
+
+    #[test]
+    fn request_without_field_uses_compact_format() {
+        let request = RemotingCommand::create_remoting_command(RequestCode::GetConsumeStats);
+        assert_eq!(
+            ResponseBodyFormat::from_request(&request),
+            ResponseBodyFormat::Compact
+        );
+    }
+
+    #[test]
+    fn request_can_ask_for_json_format() {
+        let mut request = RemotingCommand::create_remoting_command(RequestCode::GetConsumeStats);
+        ResponseBodyFormat::Json.apply_to_request(&mut request);
+        assert_eq!(
+            ResponseBodyFormat::from_request(&request),
+            ResponseBodyFormat::Json
+        );
+    }
+
+    #[test]
+    fn compact_format_round_trips() {
+        let body = ResponseBodyFormat::Compact.encode(&topic_offset()).unwrap();
+        assert!(!body.contains(&b'\n'));
+        let decoded = TopicOffset::decode(&body).unwrap();
+        assert_eq!(decoded.get_min_offset(), 1);
+        assert_eq!(decoded.get_max_offset(), 10);
+        assert_eq!(decoded.get_last_update_timestamp(), 100);
+    }
+
+    #[test]
+    fn json_format_round_trips() {
+        let body = ResponseBodyFormat::Json.encode(&topic_offset()).unwrap();
+        let text = String::from_utf8(body.clone()).unwrap();
+        assert!(text.contains("\n  \"minOffset\": 1"));
+        let decoded = TopicOffset::decode(&body).unwrap();
+        assert_eq!(decoded.get_min_offset(), 1);
+        assert_eq!(decoded.get_max_offset(), 10);
+        assert_eq!(decoded.get_last_update_timestamp(), 100);
+    }
+}
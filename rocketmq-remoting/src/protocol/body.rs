@@ -21,10 +21,12 @@ pub mod create_topic_list_request_body;
 pub mod get_consumer_listby_group_response_body;
 
 pub mod consumer_connection;
+pub mod consumer_lag_body;
 
 pub mod acl_info;
 pub mod batch_ack;
 pub mod batch_ack_message_request_body;
+pub mod batch_ack_result;
 pub mod broker_item;
 pub mod check_client_request_body;
 pub mod check_rocksdb_cqwrite_progress_response_body;
@@ -34,6 +36,7 @@ pub mod connection;
 pub mod consume_message_directly_result;
 pub mod group_list;
 pub mod kv_table;
+pub mod pop_inflight_message_num_body;
 pub mod pop_process_queue_info;
 pub mod process_queue_info;
 pub mod producer_connection;
@@ -41,7 +44,10 @@ pub mod query_assignment_request_body;
 pub mod query_assignment_response_body;
 pub mod queue_time_span;
 pub mod request;
+pub mod reset_group_offset_body;
 pub mod response;
+pub mod revive_lag_body;
+pub mod revive_queue_acks_body;
 pub mod set_message_request_mode_request_body;
 pub mod topic;
 pub mod topic_info_wrapper;
@@ -14,9 +14,12 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::mem::size_of;
+
 use bitvec::prelude::BitVec;
 use bitvec::prelude::Lsb0;
 use cheetah_string::CheetahString;
+use serde::de::Error;
 use serde::Deserialize;
 use serde::Deserializer;
 use serde::Serialize;
@@ -69,9 +72,24 @@ impl<'de> Deserialize<'de> for SerializableBitVec {
     where
         D: Deserializer<'de>,
     {
+        // the bytes are sent by the client, neither their length nor their alignment can be
+        // trusted to cast them to words in place
         let bytes: Vec<u8> = Vec::deserialize(deserializer)?;
-        let inner: &[u64] = bytemuck::cast_slice(bytes.as_slice());
-        Ok(SerializableBitVec(BitVec::<u64, Lsb0>::from_slice(inner)))
+        if bytes.len() % size_of::<u64>() != 0 {
+            return Err(D::Error::invalid_length(
+                bytes.len(),
+                &"a whole number of 64 bit words",
+            ));
+        }
+        let words = bytes
+            .chunks_exact(size_of::<u64>())
+            .map(|chunk| {
+                let mut word = [0u8; size_of::<u64>()];
+                word.copy_from_slice(chunk);
+                u64::from_ne_bytes(word)
+            })
+            .collect();
+        Ok(SerializableBitVec(BitVec::<u64, Lsb0>::from_vec(words)))
     }
 }
 
@@ -31,10 +31,15 @@ pub struct BatchAckMessageRequestBody {
 mod tests {
     use bitvec::prelude::*;
     use cheetah_string::CheetahString;
+    use rand::rngs::StdRng;
+    use rand::Rng;
+    use rand::SeedableRng;
     use serde_json;
 
     use super::*;
     use crate::protocol::body::batch_ack::SerializableBitVec;
+    use crate::protocol::RemotingDeserializable;
+    use crate::protocol::RemotingSerializable;
 
     #[test]
     fn batch_ack_message_request_body_serialization() {
@@ -92,4 +97,78 @@ mod tests {
         assert_eq!(body.acks.len(), 1);
         assert_eq!(body.acks[0].consumer_group, CheetahString::from(""));
     }
+
+    fn valid_body() -> Vec<u8> {
+        let mut bit_set = BitVec::<u64, Lsb0>::repeat(false, 128);
+        bit_set.set(3, true);
+        bit_set.set(70, true);
+        let body = BatchAckMessageRequestBody {
+            broker_name: CheetahString::from("broker1"),
+            acks: vec![BatchAck {
+                consumer_group: CheetahString::from("group1"),
+                topic: CheetahString::from("topic1"),
+                retry: CheetahString::from("0"),
+                start_offset: 100,
+                queue_id: 1,
+                revive_queue_id: 2,
+                pop_time: 123456789,
+                invisible_time: 30000,
+                bit_set: SerializableBitVec(bit_set),
+            }],
+        };
+        body.encode().unwrap()
+    }
+
+    #[test]
+    fn bit_set_of_partial_word_is_rejected() {
+        let json = r#"{"brokerName":"broker1","acks":[{"c":"group1","t":"topic1","r":"0","so":100,"q":1,"rq":2,"pt":1,"it":1,"b":[1,2,3]}]}"#;
+        assert!(BatchAckMessageRequestBody::decode(json.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn bit_set_is_decoded_from_unaligned_bytes() {
+        let body = BatchAckMessageRequestBody::decode(valid_body().as_slice()).unwrap();
+        let ones = body.acks[0].bit_set.0.iter_ones().collect::<Vec<_>>();
+        assert_eq!(ones, vec![3, 70]);
+    }
+
+    #[test]
+    fn decode_of_arbitrary_bytes_is_an_error_not_a_panic() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for _ in 0..10_000 {
+            let len = rng.gen_range(0..256);
+            let bytes = (0..len).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+            assert!(BatchAckMessageRequestBody::decode(bytes.as_slice()).is_err());
+        }
+    }
+
+    #[test]
+    fn decode_of_mutated_body_never_panics() {
+        let valid = valid_body();
+        let mut rng = StdRng::seed_from_u64(0xba7c);
+        for _ in 0..10_000 {
+            let mut bytes = valid.clone();
+            match rng.gen_range(0..3) {
+                0 => bytes.truncate(rng.gen_range(0..valid.len())),
+                1 => {
+                    for _ in 0..rng.gen_range(1..4) {
+                        let index = rng.gen_range(0..bytes.len());
+                        bytes[index] = rng.gen();
+                    }
+                }
+                _ => {
+                    // a digit of the bit set, or any other number, replaced by another one
+                    let digits = bytes
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, byte)| byte.is_ascii_digit())
+                        .map(|(index, _)| index)
+                        .collect::<Vec<_>>();
+                    let index = digits[rng.gen_range(0..digits.len())];
+                    bytes[index] = b'0' + rng.gen_range(0..10);
+                }
+            }
+            let _ = BatchAckMessageRequestBody::decode(bytes.as_slice());
+        }
+    }
 }
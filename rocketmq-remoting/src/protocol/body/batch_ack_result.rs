@@ -0,0 +1,252 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt::Display;
+use std::str::FromStr;
+
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Request ext field asking for the [`BatchAckDetail`] of a batch ack, `true` to get it.
+pub const BATCH_ACK_DETAIL: &str = "batchAckDetail";
+
+/// How the offsets of an [`AckedOffsets`] are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AckedOffsetsEncoding {
+    /// One bit per offset from `startOffset`, compact for contiguous offsets.
+    #[serde(rename = "BITSET")]
+    Bitset,
+    /// Every offset written out, compact for sparse offsets.
+    #[serde(rename = "LIST")]
+    List,
+}
+
+impl Display for AckedOffsetsEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AckedOffsetsEncoding::Bitset => write!(f, "BITSET"),
+            AckedOffsetsEncoding::List => write!(f, "LIST"),
+        }
+    }
+}
+
+impl FromStr for AckedOffsetsEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "BITSET" => Ok(AckedOffsetsEncoding::Bitset),
+            "LIST" => Ok(AckedOffsetsEncoding::List),
+            _ => Err(format!("unknown acked offsets encoding: {}", s)),
+        }
+    }
+}
+
+impl AckedOffsetsEncoding {
+    /// Returns the more compact encoding for `offsets`, sorted in ascending order: a bitset
+    /// takes a 64 bit word per 64 offsets of the range, a list a 64 bit word per offset.
+    pub fn most_compact(offsets: &[i64]) -> Self {
+        match (offsets.first(), offsets.last()) {
+            (Some(first), Some(last)) => {
+                let words = (last - first) as usize / 64 + 1;
+                if words <= offsets.len() {
+                    AckedOffsetsEncoding::Bitset
+                } else {
+                    AckedOffsetsEncoding::List
+                }
+            }
+            _ => AckedOffsetsEncoding::List,
+        }
+    }
+}
+
+/// Offsets of a queue acked by a batch ack.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AckedOffsets {
+    pub topic: CheetahString,
+    pub queue_id: i32,
+    pub encoding: AckedOffsetsEncoding,
+    /// Offset of the first bit of `bitSet`, unused by a list.
+    #[serde(default)]
+    pub start_offset: i64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bit_set: Vec<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub offsets: Vec<i64>,
+}
+
+impl AckedOffsets {
+    /// Encodes `offsets`, sorted in ascending order, with `encoding`, or with the most compact
+    /// encoding if `None`.
+    pub fn new(
+        topic: CheetahString,
+        queue_id: i32,
+        offsets: &[i64],
+        encoding: Option<AckedOffsetsEncoding>,
+    ) -> Self {
+        let encoding = encoding.unwrap_or_else(|| AckedOffsetsEncoding::most_compact(offsets));
+        let mut acked_offsets = AckedOffsets {
+            topic,
+            queue_id,
+            encoding,
+            start_offset: 0,
+            bit_set: Vec::new(),
+            offsets: Vec::new(),
+        };
+        match encoding {
+            AckedOffsetsEncoding::List => acked_offsets.offsets = offsets.to_vec(),
+            AckedOffsetsEncoding::Bitset => {
+                let start_offset = offsets.first().copied().unwrap_or_default();
+                let mut bit_set = Vec::new();
+                for offset in offsets {
+                    let bit = (offset - start_offset) as usize;
+                    if bit / 64 >= bit_set.len() {
+                        bit_set.resize(bit / 64 + 1, 0u64);
+                    }
+                    bit_set[bit / 64] |= 1 << (bit % 64);
+                }
+                acked_offsets.start_offset = start_offset;
+                acked_offsets.bit_set = bit_set;
+            }
+        }
+        acked_offsets
+    }
+
+    /// Decodes the acked offsets, in ascending order.
+    pub fn offsets(&self) -> Vec<i64> {
+        match self.encoding {
+            AckedOffsetsEncoding::List => self.offsets.clone(),
+            AckedOffsetsEncoding::Bitset => self
+                .bit_set
+                .iter()
+                .enumerate()
+                .flat_map(|(index, word)| {
+                    (0..64)
+                        .filter(move |bit| word & (1 << bit) != 0)
+                        .map(move |bit| self.start_offset + (index * 64 + bit) as i64)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Response body of a batch ack, listing the offsets acked per queue.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchAckResult {
+    pub acked: Vec<AckedOffsets>,
+    /// Number of requested offsets skipped as out of the offset range of their queue.
+    #[serde(default)]
+    pub skipped_count: u64,
+    /// Outcome of every ack of the request, only sent when asked for with [`BATCH_ACK_DETAIL`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<BatchAckDetail>,
+}
+
+/// Indexes, in the request, of the acks of a batch which were stored and of those which were
+/// not and have to be retried.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchAckDetail {
+    pub succeeded: Vec<u32>,
+    pub failed: Vec<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::RemotingDeserializable;
+    use crate::protocol::RemotingSerializable;
+
+    fn round_trip(acked_offsets: AckedOffsets) -> AckedOffsets {
+        let result = BatchAckResult {
+            acked: vec![acked_offsets],
+            skipped_count: 1,
+            detail: None,
+        };
+        let decoded = BatchAckResult::decode(&result.encode().unwrap()).unwrap();
+        assert_eq!(decoded, result);
+        decoded.acked.into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn dense_offsets_are_encoded_as_bitset() {
+        let offsets: Vec<i64> = (1000..1200).filter(|offset| offset % 7 != 0).collect();
+        let acked_offsets = AckedOffsets::new("test_topic".into(), 1, &offsets, None);
+        assert_eq!(acked_offsets.encoding, AckedOffsetsEncoding::Bitset);
+        assert_eq!(acked_offsets.bit_set.len(), 4);
+
+        let decoded = round_trip(acked_offsets);
+        assert_eq!(decoded.encoding, AckedOffsetsEncoding::Bitset);
+        assert_eq!(decoded.offsets(), offsets);
+    }
+
+    #[test]
+    fn sparse_offsets_are_encoded_as_list() {
+        let offsets = vec![10, 5_000, 90_000];
+        let acked_offsets = AckedOffsets::new("test_topic".into(), 1, &offsets, None);
+        assert_eq!(acked_offsets.encoding, AckedOffsetsEncoding::List);
+
+        let decoded = round_trip(acked_offsets);
+        assert_eq!(decoded.encoding, AckedOffsetsEncoding::List);
+        assert_eq!(decoded.offsets(), offsets);
+    }
+
+    #[test]
+    fn forced_encoding_is_used() {
+        let offsets = vec![10, 5_000];
+        let acked_offsets = AckedOffsets::new(
+            "test_topic".into(),
+            1,
+            &offsets,
+            Some(AckedOffsetsEncoding::Bitset),
+        );
+        assert_eq!(acked_offsets.encoding, AckedOffsetsEncoding::Bitset);
+        assert_eq!(round_trip(acked_offsets).offsets(), offsets);
+    }
+
+    #[test]
+    fn parse_acked_offsets_encoding() {
+        assert_eq!(
+            "bitset".parse::<AckedOffsetsEncoding>(),
+            Ok(AckedOffsetsEncoding::Bitset)
+        );
+        assert_eq!(
+            "LIST".parse::<AckedOffsetsEncoding>(),
+            Ok(AckedOffsetsEncoding::List)
+        );
+        assert!("AUTO".parse::<AckedOffsetsEncoding>().is_err());
+    }
+
+    #[test]
+    fn detail_is_only_encoded_when_set() {
+        let result = BatchAckResult::default();
+        let json = String::from_utf8(result.encode().unwrap()).unwrap();
+        assert!(!json.contains("detail"));
+
+        let result = BatchAckResult {
+            detail: Some(BatchAckDetail {
+                succeeded: vec![0, 2],
+                failed: vec![1],
+            }),
+            ..Default::default()
+        };
+        let decoded = BatchAckResult::decode(&result.encode().unwrap()).unwrap();
+        assert_eq!(decoded, result);
+    }
+}
@@ -0,0 +1,77 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// How far every consumer group of a broker is behind on the queues it consumes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsumerLagBody {
+    /// Lag over which a group is reported slow.
+    pub lag_threshold: i64,
+    pub groups: Vec<GroupConsumeLag>,
+}
+
+/// The lag of a consumer group, summed over the queues it committed offsets on.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupConsumeLag {
+    pub group: CheetahString,
+    pub lag: i64,
+    pub slow: bool,
+    pub queues: Vec<QueueConsumeLag>,
+}
+
+/// The messages of a queue stored past the offset a group committed on it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueConsumeLag {
+    pub topic: CheetahString,
+    pub queue_id: i32,
+    pub max_offset: i64,
+    pub consumer_offset: i64,
+    pub lag: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::RemotingDeserializable;
+    use crate::protocol::RemotingSerializable;
+
+    #[test]
+    fn consumer_lag_body_round_trips() {
+        let body = ConsumerLagBody {
+            lag_threshold: 100,
+            groups: vec![GroupConsumeLag {
+                group: CheetahString::from("SlowGroup"),
+                lag: 120,
+                slow: true,
+                queues: vec![QueueConsumeLag {
+                    topic: CheetahString::from("TopicTest"),
+                    queue_id: 2,
+                    max_offset: 200,
+                    consumer_offset: 80,
+                    lag: 120,
+                }],
+            }],
+        };
+        let decoded = ConsumerLagBody::decode(body.encode().unwrap().as_slice()).unwrap();
+        assert_eq!(decoded, body);
+    }
+}
@@ -0,0 +1,68 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Inflight pop message numbers of a consumer group on a topic, that is the number of messages
+/// popped and neither acked nor revived yet.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PopInflightMessageNumBody {
+    pub topic: CheetahString,
+    pub consumer_group: CheetahString,
+    /// Sum of the inflight numbers of the queues in `queue_in_flight_num_table`.
+    pub total_in_flight_num: i64,
+    /// Inflight number of each queue holding inflight messages.
+    pub queue_in_flight_num_table: HashMap<i32, i64>,
+}
+
+impl PopInflightMessageNumBody {
+    pub fn new(
+        topic: CheetahString,
+        consumer_group: CheetahString,
+        queue_in_flight_num_table: HashMap<i32, i64>,
+    ) -> Self {
+        PopInflightMessageNumBody {
+            topic,
+            consumer_group,
+            total_in_flight_num: queue_in_flight_num_table.values().sum(),
+            queue_in_flight_num_table,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::RemotingDeserializable;
+    use crate::protocol::RemotingSerializable;
+
+    #[test]
+    fn pop_inflight_message_num_body_sums_queues_and_round_trips() {
+        let body = PopInflightMessageNumBody::new(
+            CheetahString::from("test_topic"),
+            CheetahString::from("test_group"),
+            HashMap::from([(0, 3), (2, 4)]),
+        );
+        assert_eq!(body.total_in_flight_num, 7);
+        let decoded = PopInflightMessageNumBody::decode(body.encode().unwrap().as_slice()).unwrap();
+        assert_eq!(decoded, body);
+    }
+}
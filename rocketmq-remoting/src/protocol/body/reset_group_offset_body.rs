@@ -0,0 +1,62 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use serde::Deserialize;
+use serde::Serialize;
+
+/// The offsets a consumer group was reset to on every queue of a topic.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetGroupOffsetBody {
+    pub queues: Vec<QueueOffsetReset>,
+}
+
+/// The committed offset of a queue before and after a reset.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueOffsetReset {
+    pub queue_id: i32,
+    /// `-1` if the group had no offset committed on the queue.
+    pub offset_before: i64,
+    pub offset: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::RemotingDeserializable;
+    use crate::protocol::RemotingSerializable;
+
+    #[test]
+    fn reset_group_offset_body_round_trips() {
+        let body = ResetGroupOffsetBody {
+            queues: vec![
+                QueueOffsetReset {
+                    queue_id: 0,
+                    offset_before: 50,
+                    offset: 30,
+                },
+                QueueOffsetReset {
+                    queue_id: 1,
+                    offset_before: -1,
+                    offset: 30,
+                },
+            ],
+        };
+        let decoded = ResetGroupOffsetBody::decode(body.encode().unwrap().as_slice()).unwrap();
+        assert_eq!(decoded, body);
+    }
+}
@@ -0,0 +1,61 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// How far the revive service is behind on every revive queue of a broker.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviveLagBody {
+    pub revive_topic: CheetahString,
+    pub queues: Vec<ReviveQueueLag>,
+}
+
+/// The checkpoints and acks of a revive queue not consumed by the revive service yet. A lag
+/// growing over time means popped messages are neither acked nor revived as they should.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviveQueueLag {
+    pub revive_queue_id: i32,
+    pub max_offset: i64,
+    /// Offset the revive service consumes the queue from.
+    pub consumed_offset: i64,
+    pub lag: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::RemotingDeserializable;
+    use crate::protocol::RemotingSerializable;
+
+    #[test]
+    fn revive_lag_body_round_trips() {
+        let body = ReviveLagBody {
+            revive_topic: CheetahString::from("rmq_sys_REVIVE_LOG_DefaultCluster"),
+            queues: vec![ReviveQueueLag {
+                revive_queue_id: 1,
+                max_offset: 12,
+                consumed_offset: 4,
+                lag: 8,
+            }],
+        };
+        let decoded = ReviveLagBody::decode(body.encode().unwrap().as_slice()).unwrap();
+        assert_eq!(decoded, body);
+    }
+}
@@ -0,0 +1,82 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Acks of a consumer group on a topic read from a revive queue, without consuming it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviveQueueAcksBody {
+    pub revive_topic: CheetahString,
+    pub revive_queue_id: i32,
+    /// Revive queue offset to read the following acks from.
+    pub next_offset: i64,
+    pub acks: Vec<ReviveQueueAck>,
+}
+
+/// An ack waiting in a revive queue.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviveQueueAck {
+    /// Offset of the ack in the revive queue.
+    pub revive_offset: i64,
+    /// `ack` for a single ack, `bAck` for a batch ack.
+    pub tag: CheetahString,
+    pub topic: CheetahString,
+    pub consumer_group: CheetahString,
+    pub queue_id: i32,
+    pub broker_name: CheetahString,
+    /// Start offset of the checkpoint the ack belongs to.
+    pub start_offset: i64,
+    /// Queue offsets of the acked messages.
+    pub ack_offsets: Vec<i64>,
+    pub pop_time: i64,
+    /// Time the messages stay invisible after the pop, the ack is revived at
+    /// `pop_time + invisible_time`.
+    pub invisible_time: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::RemotingDeserializable;
+    use crate::protocol::RemotingSerializable;
+
+    #[test]
+    fn revive_queue_acks_body_round_trips() {
+        let body = ReviveQueueAcksBody {
+            revive_topic: CheetahString::from("rmq_sys_REVIVE_LOG_DefaultCluster"),
+            revive_queue_id: 1,
+            next_offset: 8,
+            acks: vec![ReviveQueueAck {
+                revive_offset: 7,
+                tag: CheetahString::from("bAck"),
+                topic: CheetahString::from("test_topic"),
+                consumer_group: CheetahString::from("test_group"),
+                queue_id: 2,
+                broker_name: CheetahString::from("broker-a"),
+                start_offset: 100,
+                ack_offsets: vec![101, 103],
+                pop_time: 1_000,
+                invisible_time: 30_000,
+            }],
+        };
+        let decoded = ReviveQueueAcksBody::decode(body.encode().unwrap().as_slice()).unwrap();
+        assert_eq!(decoded, body);
+    }
+}
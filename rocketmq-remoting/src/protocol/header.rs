@@ -26,6 +26,7 @@ pub mod create_topic_request_header;
 pub mod delete_subscription_group_request_header;
 pub mod delete_topic_request_header;
 pub mod end_transaction_request_header;
+pub mod end_two_phase_ack_request_header;
 pub mod extra_info_util;
 pub mod get_all_topic_config_response_header;
 pub mod get_consume_stats_request_header;
@@ -55,14 +56,21 @@ pub mod query_consumer_offset_request_header;
 pub mod query_consumer_offset_response_header;
 pub mod query_message_request_header;
 pub mod query_message_response_header;
+pub mod query_pop_inflight_message_num_request_header;
+pub mod query_revive_queue_acks_request_header;
 pub mod query_subscription_by_consumer_request_header;
 pub mod query_topic_consume_by_who_request_header;
 pub mod query_topics_by_consumer_request_header;
 pub mod reply_message_request_header;
+pub mod reset_group_offset_request_header;
 pub mod reset_offset_request_header;
 pub mod search_offset_response_header;
+pub mod trim_topic_request_header;
 pub mod unlock_batch_mq_request_header;
 pub mod unregister_client_request_header;
+pub mod update_ack_invisible_time_cap_request_header;
+pub mod update_ack_processing_switch_request_header;
 pub mod update_consumer_offset_header;
+pub mod view_broker_stats_data_request_header;
 pub mod view_message_request_header;
 pub mod view_message_response_header;
@@ -49,6 +49,15 @@ pub struct AckMessageRequestHeader {
     #[required]
     pub offset: i64,
 
+    /// Pop time observed by the client, used to correct the clock skew between the pop and
+    /// the ack broker (optional)
+    #[serde(
+        rename = "observedPopTime",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub observed_pop_time: Option<i64>,
+
     #[serde(flatten)]
     pub topic_request_header: Option<TopicRequestHeader>,
 }
@@ -68,6 +77,7 @@ mod tests {
             queue_id: 1,
             extra_info: CheetahString::from("extra_info"),
             offset: 12345,
+            observed_pop_time: None,
             topic_request_header: None,
         };
         let json = serde_json::to_string(&header).unwrap();
@@ -99,6 +109,21 @@ mod tests {
         assert!(header.topic_request_header.is_some());
     }
 
+    #[test]
+    fn deserialize_ack_message_request_header_with_observed_pop_time() {
+        let json = r#"{"consumerGroup":"test_group","topic":"test_topic","queueId":1,"extraInfo":"extra_info","offset":12345,"observedPopTime":1000}"#;
+        let header: AckMessageRequestHeader = serde_json::from_str(json).unwrap();
+        assert_eq!(header.observed_pop_time, Some(1000));
+        let header = AckMessageRequestHeader {
+            topic_request_header: None,
+            ..header
+        };
+        assert_eq!(
+            serde_json::to_string(&header).unwrap(),
+            r#"{"consumerGroup":"test_group","topic":"test_topic","queueId":1,"extraInfo":"extra_info","offset":12345,"observedPopTime":1000}"#
+        );
+    }
+
     #[test]
     fn serialize_ack_message_request_header_with_topic_request_header() {
         let header = AckMessageRequestHeader {
@@ -107,6 +132,7 @@ mod tests {
             queue_id: 1,
             extra_info: CheetahString::from("extra_info"),
             offset: 12345,
+            observed_pop_time: None,
             topic_request_header: None,
         };
         let json = serde_json::to_string(&header).unwrap();
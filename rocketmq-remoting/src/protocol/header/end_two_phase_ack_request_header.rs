@@ -0,0 +1,63 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_macros::RequestHeaderCodec;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Request header to confirm or abort an ack prepared for a two-phase ack consumer group.
+#[derive(Debug, Serialize, Deserialize, Clone, RequestHeaderCodec)]
+#[serde(rename_all = "camelCase")]
+pub struct EndTwoPhaseAckRequestHeader {
+    /// Consumer group name (required)
+    #[required]
+    pub consumer_group: CheetahString,
+
+    /// Id returned by the broker when the ack was prepared (required)
+    #[required]
+    pub ack_transaction_id: CheetahString,
+
+    /// Whether to persist the prepared ack, or to release it (required)
+    #[required]
+    pub commit: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_end_two_phase_ack_request_header() {
+        let header = EndTwoPhaseAckRequestHeader {
+            consumer_group: CheetahString::from("test_group"),
+            ack_transaction_id: CheetahString::from("18c2a3f0000000000000001"),
+            commit: true,
+        };
+        let json = serde_json::to_string(&header).unwrap();
+        let expected = r#"{"consumerGroup":"test_group","ackTransactionId":"18c2a3f0000000000000001","commit":true}"#;
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn deserialize_end_two_phase_ack_request_header() {
+        let json = r#"{"consumerGroup":"test_group","ackTransactionId":"abc","commit":false}"#;
+        let header: EndTwoPhaseAckRequestHeader = serde_json::from_str(json).unwrap();
+        assert_eq!(header.consumer_group, CheetahString::from("test_group"));
+        assert_eq!(header.ack_transaction_id, CheetahString::from("abc"));
+        assert!(!header.commit);
+    }
+}
@@ -46,6 +46,8 @@ pub struct PopMessageRequestHeader {
     pub exp: Option<CheetahString>,
     pub order: Option<bool>,
     pub attempt_id: Option<CheetahString>,
+    /// Number of messages the consumer can currently take, advertised for flow control
+    pub processing_capacity: Option<u32>,
 
     #[serde(flatten)]
     pub topic_request_header: Option<TopicRequestHeader>,
@@ -66,6 +68,7 @@ impl Default for PopMessageRequestHeader {
             exp: None,
             order: Some(false),
             attempt_id: None,
+            processing_capacity: None,
             topic_request_header: None,
         }
     }
@@ -115,6 +118,7 @@ mod tests {
         assert!(header.exp.is_none());
         assert_eq!(header.order, Some(false));
         assert!(header.attempt_id.is_none());
+        assert!(header.processing_capacity.is_none());
         assert!(header.topic_request_header.is_none());
     }
 
@@ -133,6 +137,7 @@ mod tests {
             exp: Some(CheetahString::from("exp1")),
             order: Some(true),
             attempt_id: Some(CheetahString::from("attempt1")),
+            processing_capacity: None,
             topic_request_header: None,
         };
         assert_eq!(
@@ -0,0 +1,54 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_macros::RequestHeaderCodec;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Request header to query the inflight pop message numbers of a consumer group on a topic.
+#[derive(Debug, Serialize, Deserialize, Clone, RequestHeaderCodec)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryPopInflightMessageNumRequestHeader {
+    /// Topic name (required)
+    #[required]
+    pub topic: CheetahString,
+
+    /// Consumer group name (required)
+    #[required]
+    pub consumer_group: CheetahString,
+
+    /// Queue to query, all queues of the topic when absent
+    pub queue_id: Option<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_query_pop_inflight_message_num_request_header() {
+        let json = r#"{"topic":"test_topic","consumerGroup":"test_group","queueId":1}"#;
+        let header: QueryPopInflightMessageNumRequestHeader = serde_json::from_str(json).unwrap();
+        assert_eq!(header.topic, CheetahString::from("test_topic"));
+        assert_eq!(header.consumer_group, CheetahString::from("test_group"));
+        assert_eq!(header.queue_id, Some(1));
+
+        let json = r#"{"topic":"test_topic","consumerGroup":"test_group"}"#;
+        let header: QueryPopInflightMessageNumRequestHeader = serde_json::from_str(json).unwrap();
+        assert_eq!(header.queue_id, None);
+    }
+}
@@ -0,0 +1,64 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_macros::RequestHeaderCodec;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Request header to inspect the acks of a consumer group on a topic waiting in a revive queue.
+#[derive(Debug, Serialize, Deserialize, Clone, RequestHeaderCodec)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryReviveQueueAcksRequestHeader {
+    /// Topic name (required)
+    #[required]
+    pub topic: CheetahString,
+
+    /// Consumer group name (required)
+    #[required]
+    pub consumer_group: CheetahString,
+
+    /// Revive queue to read (required)
+    #[required]
+    pub revive_queue_id: i32,
+
+    /// Revive queue offset to read from, the min offset of the queue when absent
+    pub offset: Option<i64>,
+
+    /// Number of revive queue messages to read, capped by the broker
+    pub max_num: Option<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_query_revive_queue_acks_request_header() {
+        let json = r#"{"topic":"test_topic","consumerGroup":"test_group","reviveQueueId":3,"offset":42,"maxNum":16}"#;
+        let header: QueryReviveQueueAcksRequestHeader = serde_json::from_str(json).unwrap();
+        assert_eq!(header.topic, CheetahString::from("test_topic"));
+        assert_eq!(header.consumer_group, CheetahString::from("test_group"));
+        assert_eq!(header.revive_queue_id, 3);
+        assert_eq!(header.offset, Some(42));
+        assert_eq!(header.max_num, Some(16));
+
+        let json = r#"{"topic":"test_topic","consumerGroup":"test_group","reviveQueueId":3}"#;
+        let header: QueryReviveQueueAcksRequestHeader = serde_json::from_str(json).unwrap();
+        assert_eq!(header.offset, None);
+        assert_eq!(header.max_num, None);
+    }
+}
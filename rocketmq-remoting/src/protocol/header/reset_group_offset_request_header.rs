@@ -0,0 +1,74 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_macros::RequestHeaderCodec;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Request header to reset the committed offsets of a consumer group on every queue of a topic.
+#[derive(Debug, Serialize, Deserialize, Clone, RequestHeaderCodec)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetGroupOffsetRequestHeader {
+    /// Topic whose queues are reset (required)
+    #[required]
+    pub topic: CheetahString,
+
+    /// Consumer group name (required)
+    #[required]
+    pub group: CheetahString,
+
+    /// Where to reset to, one of `EARLIEST`, `LATEST` or `TIMESTAMP` (required)
+    #[required]
+    pub reset_type: CheetahString,
+
+    /// Store timestamp in milliseconds to reset to, used with `TIMESTAMP`
+    pub timestamp: Option<i64>,
+
+    /// With `TIMESTAMP`, resets a queue holding no message stored at or after the timestamp to
+    /// its earliest offset rather than its latest one
+    pub fallback_to_earliest: Option<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_reset_group_offset_request_header() {
+        let header = ResetGroupOffsetRequestHeader {
+            topic: CheetahString::from("test_topic"),
+            group: CheetahString::from("test_group"),
+            reset_type: CheetahString::from("TIMESTAMP"),
+            timestamp: Some(1700000000000),
+            fallback_to_earliest: Some(true),
+        };
+        let json = serde_json::to_string(&header).unwrap();
+        let expected = r#"{"topic":"test_topic","group":"test_group","resetType":"TIMESTAMP","timestamp":1700000000000,"fallbackToEarliest":true}"#;
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn deserialize_reset_group_offset_request_header_without_timestamp() {
+        let json = r#"{"topic":"test_topic","group":"test_group","resetType":"LATEST"}"#;
+        let header: ResetGroupOffsetRequestHeader = serde_json::from_str(json).unwrap();
+        assert_eq!(header.topic, CheetahString::from("test_topic"));
+        assert_eq!(header.group, CheetahString::from("test_group"));
+        assert_eq!(header.reset_type, CheetahString::from("LATEST"));
+        assert_eq!(header.timestamp, None);
+        assert_eq!(header.fallback_to_earliest, None);
+    }
+}
@@ -0,0 +1,63 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_macros::RequestHeaderCodec;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Request header to trim a queue of a topic so that its minimum offset becomes `offset`.
+#[derive(Debug, Serialize, Deserialize, Clone, RequestHeaderCodec)]
+#[serde(rename_all = "camelCase")]
+pub struct TrimTopicRequestHeader {
+    /// Topic whose queue is trimmed (required)
+    #[required]
+    pub topic: CheetahString,
+
+    /// Queue identifier (required)
+    #[required]
+    pub queue_id: i32,
+
+    /// Offset the messages before which are removed, capped at the maximum offset (required)
+    #[required]
+    pub offset: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_trim_topic_request_header() {
+        let header = TrimTopicRequestHeader {
+            topic: CheetahString::from("test_topic"),
+            queue_id: 3,
+            offset: 100,
+        };
+        let json = serde_json::to_string(&header).unwrap();
+        let expected = r#"{"topic":"test_topic","queueId":3,"offset":100}"#;
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn deserialize_trim_topic_request_header() {
+        let json = r#"{"topic":"test_topic","queueId":1,"offset":42}"#;
+        let header: TrimTopicRequestHeader = serde_json::from_str(json).unwrap();
+        assert_eq!(header.topic, CheetahString::from("test_topic"));
+        assert_eq!(header.queue_id, 1);
+        assert_eq!(header.offset, 42);
+    }
+}
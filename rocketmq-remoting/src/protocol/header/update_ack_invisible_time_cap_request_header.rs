@@ -0,0 +1,57 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_macros::RequestHeaderCodec;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Request header to update the max invisible time applied to the acks of a consumer group.
+#[derive(Debug, Serialize, Deserialize, Clone, RequestHeaderCodec)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateAckInvisibleTimeCapRequestHeader {
+    /// Consumer group name (required)
+    #[required]
+    pub consumer_group: CheetahString,
+
+    /// Max invisible time in milliseconds, a non-positive value removes the cap (required)
+    #[required]
+    pub invisible_time_cap_millis: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_update_ack_invisible_time_cap_request_header() {
+        let header = UpdateAckInvisibleTimeCapRequestHeader {
+            consumer_group: CheetahString::from("test_group"),
+            invisible_time_cap_millis: 30_000,
+        };
+        let json = serde_json::to_string(&header).unwrap();
+        let expected = r#"{"consumerGroup":"test_group","invisibleTimeCapMillis":30000}"#;
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn deserialize_update_ack_invisible_time_cap_request_header() {
+        let json = r#"{"consumerGroup":"test_group","invisibleTimeCapMillis":30000}"#;
+        let header: UpdateAckInvisibleTimeCapRequestHeader = serde_json::from_str(json).unwrap();
+        assert_eq!(header.consumer_group, CheetahString::from("test_group"));
+        assert_eq!(header.invisible_time_cap_millis, 30_000);
+    }
+}
@@ -0,0 +1,49 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use rocketmq_macros::RequestHeaderCodec;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Request header to turn ack processing of a broker off or back on.
+#[derive(Debug, Serialize, Deserialize, Clone, RequestHeaderCodec)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateAckProcessingSwitchRequestHeader {
+    /// Whether acks are rejected by the broker (required)
+    #[required]
+    pub ack_processing_disabled: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_update_ack_processing_switch_request_header() {
+        let header = UpdateAckProcessingSwitchRequestHeader {
+            ack_processing_disabled: true,
+        };
+        let json = serde_json::to_string(&header).unwrap();
+        assert_eq!(json, r#"{"ackProcessingDisabled":true}"#);
+    }
+
+    #[test]
+    fn deserialize_update_ack_processing_switch_request_header() {
+        let json = r#"{"ackProcessingDisabled":false}"#;
+        let header: UpdateAckProcessingSwitchRequestHeader = serde_json::from_str(json).unwrap();
+        assert!(!header.ack_processing_disabled);
+    }
+}
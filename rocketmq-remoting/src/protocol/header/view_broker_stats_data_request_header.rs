@@ -0,0 +1,50 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_macros::RequestHeaderCodec;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Request header to read one stats item of a broker, e.g. its ack counters.
+#[derive(Debug, Serialize, Deserialize, Clone, RequestHeaderCodec)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewBrokerStatsDataRequestHeader {
+    /// Name of the stats set, e.g. `BROKER_ACK_NUMS` (required)
+    #[required]
+    pub stats_name: CheetahString,
+    /// Key of the item inside the set, e.g. the cluster name (required)
+    #[required]
+    pub stats_key: CheetahString,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_view_broker_stats_data_request_header() {
+        let header = ViewBrokerStatsDataRequestHeader {
+            stats_name: CheetahString::from_static_str("BROKER_ACK_NUMS"),
+            stats_key: CheetahString::from_static_str("DefaultCluster"),
+        };
+        let json = serde_json::to_string(&header).unwrap();
+        assert_eq!(
+            json,
+            r#"{"statsName":"BROKER_ACK_NUMS","statsKey":"DefaultCluster"}"#
+        );
+    }
+}
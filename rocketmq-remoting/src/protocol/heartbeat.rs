@@ -19,5 +19,6 @@ pub mod consume_type;
 pub mod consumer_data;
 pub mod heartbeat_data;
 pub mod message_model;
+pub mod pop_ack_protocol_version;
 pub mod producer_data;
 pub mod subscription_data;
@@ -0,0 +1,192 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt::Display;
+
+use rocketmq_common::common::message::MessageConst;
+
+use crate::protocol::header::extra_info_util::ExtraInfoUtil;
+use crate::remoting_error::RemotingError::IllegalArgument;
+
+/// Version of the pop/ack protocol spoken by a client, negotiated through the heartbeat.
+///
+/// The version decides how the receipt handle (extra info) of a popped message is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum PopAckProtocolVersion {
+    /// Handle fields separated by `|`, spoken by clients which never negotiated a version.
+    #[default]
+    V1 = 1,
+    /// Handle fields separated by [`MessageConst::KEY_SEPARATOR`], as built by
+    /// [`ExtraInfoUtil::build_extra_info`].
+    V2 = 2,
+}
+
+impl PopAckProtocolVersion {
+    /// Ext field carrying the version in the heartbeat request and response.
+    pub const EXT_FIELD_KEY: &'static str = "popAckProtocolVersion";
+
+    /// Highest version supported by this side.
+    pub const LATEST: PopAckProtocolVersion = PopAckProtocolVersion::V2;
+
+    pub fn from_i32(version: i32) -> Option<Self> {
+        match version {
+            1 => Some(PopAckProtocolVersion::V1),
+            2 => Some(PopAckProtocolVersion::V2),
+            _ => None,
+        }
+    }
+
+    /// Picks the version to use with a peer requesting `requested`: the requested one if
+    /// known, the latest one if the peer is newer, and `V1` otherwise.
+    pub fn negotiate(requested: Option<&str>) -> Self {
+        match requested.and_then(|value| value.parse::<i32>().ok()) {
+            Some(version) if version > Self::LATEST as i32 => Self::LATEST,
+            Some(version) => Self::from_i32(version).unwrap_or_default(),
+            None => PopAckProtocolVersion::V1,
+        }
+    }
+
+    /// Splits a receipt handle encoded with this version into its fields, which can then be
+    /// read with the getters of [`ExtraInfoUtil`].
+    pub fn split_extra_info(&self, extra_info: &str) -> crate::Result<Vec<String>> {
+        match self {
+            PopAckProtocolVersion::V1 => ExtraInfoUtil::split(extra_info),
+            PopAckProtocolVersion::V2 => {
+                if extra_info.is_empty() {
+                    return Err(IllegalArgument("split extraInfo is empty".to_string()));
+                }
+                Ok(extra_info
+                    .split(MessageConst::KEY_SEPARATOR)
+                    .map(String::from)
+                    .collect())
+            }
+        }
+    }
+
+    /// Encodes receipt handle fields with this version, the inverse of
+    /// [`split_extra_info`](Self::split_extra_info).
+    pub fn join_extra_info(&self, fields: &[String]) -> String {
+        match self {
+            PopAckProtocolVersion::V1 => fields.join("|"),
+            PopAckProtocolVersion::V2 => fields.join(MessageConst::KEY_SEPARATOR),
+        }
+    }
+}
+
+impl Display for PopAckProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", *self as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiate_defaults_to_v1() {
+        assert_eq!(
+            PopAckProtocolVersion::negotiate(None),
+            PopAckProtocolVersion::V1
+        );
+        assert_eq!(
+            PopAckProtocolVersion::negotiate(Some("abc")),
+            PopAckProtocolVersion::V1
+        );
+        assert_eq!(
+            PopAckProtocolVersion::negotiate(Some("0")),
+            PopAckProtocolVersion::V1
+        );
+    }
+
+    #[test]
+    fn negotiate_caps_to_latest() {
+        assert_eq!(
+            PopAckProtocolVersion::negotiate(Some("2")),
+            PopAckProtocolVersion::V2
+        );
+        assert_eq!(
+            PopAckProtocolVersion::negotiate(Some("99")),
+            PopAckProtocolVersion::LATEST
+        );
+    }
+
+    #[test]
+    fn v1_parses_pipe_separated_handle() {
+        let fields = PopAckProtocolVersion::V1
+            .split_extra_info("100|1700000000000|30000|3|0|broker-a|1|105")
+            .unwrap();
+        assert_eq!(ExtraInfoUtil::get_ck_queue_offset(&fields).unwrap(), 100);
+        assert_eq!(ExtraInfoUtil::get_pop_time(&fields).unwrap(), 1700000000000);
+        assert_eq!(ExtraInfoUtil::get_invisible_time(&fields).unwrap(), 30000);
+        assert_eq!(ExtraInfoUtil::get_revive_qid(&fields).unwrap(), 3);
+        assert_eq!(ExtraInfoUtil::get_broker_name(&fields).unwrap(), "broker-a");
+        assert_eq!(ExtraInfoUtil::get_queue_id(&fields).unwrap(), 1);
+        assert_eq!(ExtraInfoUtil::get_queue_offset(&fields).unwrap(), 105);
+    }
+
+    #[test]
+    fn v2_parses_handle_built_by_broker() {
+        let extra_info = ExtraInfoUtil::build_extra_info_with_msg_queue_offset(
+            100,
+            1700000000000,
+            30000,
+            3,
+            "test_topic",
+            "broker-a",
+            1,
+            105,
+        );
+        let fields = PopAckProtocolVersion::V2
+            .split_extra_info(&extra_info)
+            .unwrap();
+        assert_eq!(ExtraInfoUtil::get_ck_queue_offset(&fields).unwrap(), 100);
+        assert_eq!(ExtraInfoUtil::get_pop_time(&fields).unwrap(), 1700000000000);
+        assert_eq!(ExtraInfoUtil::get_invisible_time(&fields).unwrap(), 30000);
+        assert_eq!(ExtraInfoUtil::get_revive_qid(&fields).unwrap(), 3);
+        assert_eq!(ExtraInfoUtil::get_broker_name(&fields).unwrap(), "broker-a");
+        assert_eq!(ExtraInfoUtil::get_queue_id(&fields).unwrap(), 1);
+        assert_eq!(ExtraInfoUtil::get_queue_offset(&fields).unwrap(), 105);
+
+        // the same handle is a single field for a v1 client
+        let fields = PopAckProtocolVersion::V1
+            .split_extra_info(&extra_info)
+            .unwrap();
+        assert_eq!(fields.len(), 1);
+    }
+
+    #[test]
+    fn join_extra_info_round_trips_with_split() {
+        let handle = ExtraInfoUtil::build_extra_info(100, 1700000000000, 30000, 3, "t", "b", 1);
+        let fields = PopAckProtocolVersion::V2.split_extra_info(&handle).unwrap();
+        assert_eq!(PopAckProtocolVersion::V2.join_extra_info(&fields), handle);
+
+        let v1_handle = PopAckProtocolVersion::V1.join_extra_info(&fields);
+        assert_eq!(v1_handle, "100|1700000000000|30000|3|0|b|1");
+        assert_eq!(
+            PopAckProtocolVersion::V1
+                .split_extra_info(&v1_handle)
+                .unwrap(),
+            fields
+        );
+    }
+
+    #[test]
+    fn split_extra_info_rejects_empty_handle() {
+        assert!(PopAckProtocolVersion::V1.split_extra_info("").is_err());
+        assert!(PopAckProtocolVersion::V2.split_extra_info("").is_err());
+    }
+}
@@ -60,8 +60,7 @@ lazy_static! {
         });
         match protocol.as_str() {
             "JSON" => SerializeType::JSON,
-            "ROCKETMQ" => SerializeType::ROCKETMQ,
-            _ => SerializeType::JSON,
+            _ => SerializeType::ROCKETMQ,
         }
     };
 }
@@ -443,7 +442,7 @@ impl RemotingCommand {
                     SerializeType::ROCKETMQ,
                 );
                 dst[begin_index..begin_index + 4]
-                    .copy_from_slice(&(header_size as i32 + body_length).to_be_bytes());
+                    .copy_from_slice(&(4 + header_size as i32 + body_length).to_be_bytes());
                 dst[begin_index + 4..begin_index + 8]
                     .copy_from_slice(&serialize_type.to_be_bytes());
             }
@@ -642,9 +641,9 @@ impl RemotingCommand {
         key: impl Into<CheetahString>,
         value: impl Into<CheetahString>,
     ) -> &mut Self {
-        if let Some(ref mut ext) = self.ext_fields {
-            ext.insert(key.into(), value.into());
-        }
+        self.ext_fields
+            .get_or_insert_with(HashMap::new)
+            .insert(key.into(), value.into());
         self
     }
 
@@ -771,7 +770,7 @@ mod tests {
 
         assert_eq!(
             "{\"code\":1,\"language\":\"JAVA\",\"version\":0,\"opaque\":1,\"flag\":1,\"remark\":\"\
-             remark\",\"extFields\":{},\"serializeTypeCurrentRPC\":\"JSON\"}",
+             remark\",\"extFields\":{},\"serializeTypeCurrentRPC\":\"ROCKETMQ\"}",
             serde_json::to_string(&command).unwrap()
         );
     }
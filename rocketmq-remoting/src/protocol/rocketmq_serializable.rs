@@ -81,15 +81,16 @@ impl RocketMQSerializable {
         buf.put_i32(cmd.opaque());
         buf.put_i32(cmd.flag());
         if let Some(remark) = cmd.remark() {
-            Self::write_str(buf, true, remark.as_str());
+            Self::write_str(buf, false, remark.as_str());
         } else {
             buf.put_i32(0);
         }
         let map_len_index = buf.len();
         buf.put_i32(0);
-        let header = cmd.command_custom_header_mut().unwrap();
-        if header.support_fast_codec() {
-            header.encode_fast(buf);
+        if let Some(header) = cmd.command_custom_header_mut() {
+            if header.support_fast_codec() {
+                header.encode_fast(buf);
+            }
         }
         if let Some(ext_fields) = cmd.ext_fields() {
             ext_fields.iter().for_each(|(k, v)| {
@@ -97,7 +98,7 @@ impl RocketMQSerializable {
                     return;
                 }
                 Self::write_str(buf, true, k.as_str());
-                Self::write_str(buf, true, v.as_str());
+                Self::write_str(buf, false, v.as_str());
             });
         }
         let current_length = buf.len();
@@ -1,6 +1,10 @@
+use serde::Deserialize;
+use serde::Serialize;
+
 use crate::protocol::body::broker_item::BrokerStatsItem;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 /// Represents broker statistics over different time periods (minute, hour, day)
 pub struct BrokerStatsData {
     /// Statistics for the last minute
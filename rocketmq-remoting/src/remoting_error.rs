@@ -80,6 +80,9 @@ pub enum RemotingError {
 
     #[error("{0}")]
     IllegalArgument(String),
+
+    #[error("TLS error: {0}")]
+    TlsError(String),
 }
 
 #[cfg(test)]
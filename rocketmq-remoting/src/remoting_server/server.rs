@@ -22,6 +22,7 @@ use std::time::Duration;
 
 use futures::SinkExt;
 use rocketmq_common::common::server::config::ServerConfig;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_rust::ArcMut;
 use tokio::net::TcpListener;
 use tokio::net::TcpStream;
@@ -36,8 +37,9 @@ use tracing::warn;
 
 use crate::base::response_future::ResponseFuture;
 use crate::code::response_code::ResponseCode;
-use crate::connection::Connection;
 use crate::net::channel::Channel;
+use crate::net::response_stream::is_partial_response;
+use crate::net::tls::TlsServerContext;
 use crate::protocol::remoting_command::RemotingCommand;
 use crate::protocol::RemotingCommandType;
 use crate::remoting_error::RemotingError;
@@ -62,8 +64,10 @@ pub struct ConnectionHandler<RP> {
     shutdown: Shutdown,
     _shutdown_complete: mpsc::Sender<()>,
     conn_disconnect_notify: Option<broadcast::Sender<SocketAddr>>,
-    rpc_hooks: Arc<Vec<Box<dyn RPCHook>>>,
+    rpc_hooks: Arc<Vec<Arc<dyn RPCHook>>>,
     response_table: ArcMut<HashMap<i32, ResponseFuture>>,
+    /// The channel is closed once it neither reads nor writes for this long, zero disables it.
+    channel_max_idle: Duration,
 }
 
 impl<RP> Drop for ConnectionHandler<RP> {
@@ -105,14 +109,45 @@ impl<RP> ConnectionHandler<RP> {
         }
         Ok(())
     }
+
+    /// How long the channel may still stay silent, `None` once it has been idle for too long.
+    fn idle_remaining(&self) -> Option<Duration> {
+        if self.channel_max_idle.is_zero() {
+            return Some(Duration::MAX);
+        }
+        let idle_millis = get_current_millis()
+            .saturating_sub(self.channel.connection_ref().last_activity_millis());
+        self.channel_max_idle
+            .checked_sub(Duration::from_millis(idle_millis))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    async fn close_idle_channel(&mut self) {
+        warn!(
+            "channel[{}] idle for more than {:?}, close it",
+            self.channel.remote_address(),
+            self.channel_max_idle
+        );
+        let connection = &mut self.connection_handler_context.channel.connection;
+        connection.ok = false;
+        if let Err(err) = connection.writer.close().await {
+            warn!("close idle channel failed: {}", err);
+        }
+    }
 }
 
 impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
     async fn handle(&mut self) -> Result<()> {
         while !self.shutdown.is_shutdown {
+            let Some(idle_remaining) = self.idle_remaining() else {
+                self.close_idle_channel().await;
+                return Ok(());
+            };
             //Get the next frame from the connection.
             let frame = tokio::select! {
                 res = self.connection_handler_context.channel.connection.reader.next() => res,
+                //Writes may have kept the channel busy, so check again before closing it.
+                _ = time::sleep(idle_remaining) => continue,
                 _ = self.shutdown.recv() =>{
                     //If a shutdown signal is received, return from `handle`.
                     return Ok(());
@@ -126,8 +161,13 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
                     return Ok(());
                 }
             };
+            self.channel.connection_ref().touch();
             //handle response
             if cmd.get_type() == RemotingCommandType::RESPONSE {
+                if is_partial_response(&cmd) {
+                    // partial frames of a streamed response do not complete the request
+                    continue;
+                }
                 let future_response = self.response_table.remove(&cmd.opaque());
                 if let Some(future_response) = future_response {
                     let _ = future_response.tx.send(Ok(cmd));
@@ -143,10 +183,9 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
             let opaque = cmd.opaque();
             let oneway_rpc = cmd.is_oneway_rpc();
             //before handle request hooks
-            let exception = match self.do_before_rpc_hooks(&self.channel, Some(&mut cmd)) {
-                Ok(_) => None,
-                Err(error) => Some(error),
-            };
+            let exception = self
+                .do_before_rpc_hooks(&self.channel, Some(&mut cmd))
+                .err();
             //handle error if return have
             match self.handle_error(oneway_rpc, opaque, exception).await {
                 HandleErrorResult::Continue => continue,
@@ -167,10 +206,9 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
                 }
             };
 
-            let exception = match self.do_before_rpc_hooks(&self.channel, response.as_mut()) {
-                Ok(_) => None,
-                Err(error) => Some(error),
-            };
+            let exception = self
+                .do_after_rpc_hooks(&self.channel, response.as_mut())
+                .err();
 
             match self.handle_error(oneway_rpc, opaque, exception).await {
                 HandleErrorResult::Continue => continue,
@@ -183,7 +221,7 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
             let response = response.unwrap();
             tokio::select! {
                 result =self.connection_handler_context.channel.connection.writer.send(response.set_opaque(opaque)) => match result{
-                    Ok(_) =>{},
+                    Ok(_) => self.channel.connection_ref().touch(),
                     Err(err) => {
                         match err {
                             RemotingError::Io(io_error) => {
@@ -215,7 +253,7 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
                         RemotingCommand::create_response_command_with_code_remark(code, message);
                     tokio::select! {
                         result =self.connection_handler_context.channel.connection.writer.send(response.set_opaque(opaque)) => match result{
-                            Ok(_) =>{},
+                            Ok(_) => self.channel.connection_ref().touch(),
                             Err(err) => {
                                 match err {
                                     RemotingError::Io(io_error) => {
@@ -236,7 +274,7 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
                         );
                         tokio::select! {
                             result =self.connection_handler_context.channel.connection.writer.send(response.set_opaque(opaque)) => match result{
-                                Ok(_) =>{},
+                                Ok(_) => self.channel.connection_ref().touch(),
                                 Err(err) => {
                                     match err {
                                         RemotingError::Io(io_error) => {
@@ -288,7 +326,11 @@ struct ConnectionListener<RP> {
 
     request_processor: RP,
 
-    rpc_hooks: Arc<Vec<Box<dyn RPCHook>>>,
+    rpc_hooks: Arc<Vec<Arc<dyn RPCHook>>>,
+
+    channel_max_idle: Duration,
+
+    tls: TlsServerContext,
 }
 
 impl<RP: RequestProcessor + Sync + 'static + Clone> ConnectionListener<RP> {
@@ -308,31 +350,42 @@ impl<RP: RequestProcessor + Sync + 'static + Clone> ConnectionListener<RP> {
             let (socket, remote_addr) = self.accept().await?;
             info!("Accepted connection, client ip:{}", remote_addr);
             socket.set_nodelay(true).expect("set nodelay failed");
-
-            let response_table = ArcMut::new(HashMap::with_capacity(128));
-            let channel = Channel::new(
-                socket.local_addr()?,
-                remote_addr,
-                Connection::new(socket),
-                response_table.clone(),
-            );
-            //create per connection handler state
-            let mut handler = ConnectionHandler {
-                request_processor: self.request_processor.clone(),
-                //connection: Connection::new(socket, remote_addr),
-                connection_handler_context: ArcMut::new(ConnectionHandlerContextWrapper {
-                    // connection: Connection::new(socket),
-                    channel: channel.clone(),
-                }),
-                channel,
-                shutdown: Shutdown::new(self.notify_shutdown.subscribe()),
-                _shutdown_complete: self.shutdown_complete_tx.clone(),
-                conn_disconnect_notify: self.conn_disconnect_notify.clone(),
-                rpc_hooks: self.rpc_hooks.clone(),
-                response_table,
-            };
-
+            let local_addr = socket.local_addr()?;
+
+            let request_processor = self.request_processor.clone();
+            let shutdown = Shutdown::new(self.notify_shutdown.subscribe());
+            let shutdown_complete = self.shutdown_complete_tx.clone();
+            let conn_disconnect_notify = self.conn_disconnect_notify.clone();
+            let rpc_hooks = self.rpc_hooks.clone();
+            let channel_max_idle = self.channel_max_idle;
+            let tls = self.tls.clone();
             tokio::spawn(async move {
+                // the handshake runs here so a slow client cannot stall the accept loop
+                let connection = match tls.accept(socket).await {
+                    Ok(connection) => connection,
+                    Err(err) => {
+                        warn!("handshake with client[IP={}] failed: {}", remote_addr, err);
+                        drop(permit);
+                        return;
+                    }
+                };
+                let response_table = ArcMut::new(HashMap::with_capacity(128));
+                let channel =
+                    Channel::new(local_addr, remote_addr, connection, response_table.clone());
+                //create per connection handler state
+                let mut handler = ConnectionHandler {
+                    request_processor,
+                    connection_handler_context: ArcMut::new(ConnectionHandlerContextWrapper {
+                        channel: channel.clone(),
+                    }),
+                    channel,
+                    shutdown,
+                    _shutdown_complete: shutdown_complete,
+                    conn_disconnect_notify,
+                    rpc_hooks,
+                    response_table,
+                    channel_max_idle,
+                };
                 if let Err(err) = handler.handle().await {
                     error!(cause = ?err, "connection error");
                 }
@@ -377,6 +430,7 @@ impl<RP: RequestProcessor + Sync + 'static + Clone> ConnectionListener<RP> {
 
 pub struct RocketMQServer<RP> {
     config: Arc<ServerConfig>,
+    rpc_hooks: Vec<Arc<dyn RPCHook>>,
     _phantom_data: std::marker::PhantomData<RP>,
 }
 
@@ -384,9 +438,19 @@ impl<RP> RocketMQServer<RP> {
     pub fn new(config: Arc<ServerConfig>) -> Self {
         Self {
             config,
+            rpc_hooks: Vec::new(),
             _phantom_data: std::marker::PhantomData,
         }
     }
+
+    /// Registers a hook run around every inbound request, in registration order.
+    ///
+    /// A hook rejects a request by returning `RemotingError::AbortProcessError` from
+    /// `do_before_request`, the caller then gets a response with the given code and remark
+    /// instead of the request being dispatched.
+    pub fn register_rpc_hook(&mut self, hook: Arc<dyn RPCHook>) {
+        self.rpc_hooks.push(hook);
+    }
 }
 
 impl<RP: RequestProcessor + Sync + 'static + Clone> RocketMQServer<RP> {
@@ -401,13 +465,22 @@ impl<RP: RequestProcessor + Sync + 'static + Clone> RocketMQServer<RP> {
             "Bind local address: {}",
             format!("{}:{}", self.config.bind_address, self.config.listen_port)
         );
+        let tls = match TlsServerContext::new(&self.config.tls_config) {
+            Ok(tls) => tls,
+            Err(err) => {
+                error!("invalid TLS config, the server is not started: {}", err);
+                return;
+            }
+        };
         let (notify_conn_disconnect, _) = broadcast::channel::<SocketAddr>(100);
         run(
             listener,
             tokio::signal::ctrl_c(),
             request_processor,
             Some(notify_conn_disconnect),
-            vec![],
+            self.rpc_hooks.clone(),
+            Duration::from_secs(self.config.server_channel_max_idle_time_seconds),
+            tls,
         )
         .await;
     }
@@ -418,7 +491,9 @@ pub async fn run<RP: RequestProcessor + Sync + 'static + Clone>(
     shutdown: impl Future,
     request_processor: RP,
     conn_disconnect_notify: Option<broadcast::Sender<SocketAddr>>,
-    rpc_hooks: Vec<Box<dyn RPCHook>>,
+    rpc_hooks: Vec<Arc<dyn RPCHook>>,
+    channel_max_idle: Duration,
+    tls: TlsServerContext,
 ) {
     let (notify_shutdown, _) = broadcast::channel(1);
     let (shutdown_complete_tx, mut shutdown_complete_rx) = mpsc::channel(1);
@@ -431,6 +506,8 @@ pub async fn run<RP: RequestProcessor + Sync + 'static + Clone>(
         limit_connections: Arc::new(Semaphore::new(DEFAULT_MAX_CONNECTIONS)),
         request_processor,
         rpc_hooks: Arc::new(rpc_hooks),
+        channel_max_idle,
+        tls,
     };
 
     tokio::select! {
@@ -499,3 +576,126 @@ impl Shutdown {
         self.is_shutdown = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+    use crate::code::request_code::RequestCode;
+    use crate::code::response_code::ResponseCode;
+    use crate::connection::Connection;
+    use crate::request_processor::default_request_processor::DefaultRemotingRequestProcessor;
+
+    struct TokenHook;
+
+    impl RPCHook for TokenHook {
+        fn do_before_request(
+            &self,
+            _remote_addr: SocketAddr,
+            request: &mut RemotingCommand,
+        ) -> Result<()> {
+            match request.ext_fields().and_then(|fields| fields.get("token")) {
+                Some(token) if token == "secret" => Ok(()),
+                _ => Err(RemotingError::AbortProcessError(
+                    ResponseCode::NoPermission as i32,
+                    "missing or invalid token".to_string(),
+                )),
+            }
+        }
+
+        fn do_after_response(
+            &self,
+            _remote_addr: SocketAddr,
+            _response: &mut RemotingCommand,
+        ) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn start_server(
+        channel_max_idle: Duration,
+        rpc_hooks: Vec<Arc<dyn RPCHook>>,
+    ) -> (SocketAddr, broadcast::Receiver<SocketAddr>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (notify_conn_disconnect, disconnected) = broadcast::channel(4);
+        tokio::spawn(run(
+            listener,
+            std::future::pending::<()>(),
+            DefaultRemotingRequestProcessor,
+            Some(notify_conn_disconnect),
+            rpc_hooks,
+            channel_max_idle,
+            TlsServerContext::default(),
+        ));
+        (addr, disconnected)
+    }
+
+    #[tokio::test]
+    async fn idle_channel_is_closed_after_timeout() {
+        let (addr, mut disconnected) = start_server(Duration::from_millis(300), vec![]).await;
+        let mut client = TcpStream::connect(addr).await.unwrap();
+
+        let closed = time::timeout(Duration::from_secs(5), disconnected.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(closed, client.local_addr().unwrap());
+        let mut buf = [0u8; 1];
+        let read = time::timeout(Duration::from_secs(5), client.read(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(read, 0);
+    }
+
+    #[tokio::test]
+    async fn active_channel_is_kept_open() {
+        let (addr, mut disconnected) = start_server(Duration::from_millis(300), vec![]).await;
+        let mut client = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+        for _ in 0..6 {
+            client
+                .writer
+                .send(RemotingCommand::create_remoting_command(
+                    RequestCode::HeartBeat,
+                ))
+                .await
+                .unwrap();
+            time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(disconnected.try_recv().is_err());
+
+        assert!(time::timeout(Duration::from_secs(5), disconnected.recv())
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn rpc_hook_rejects_request_without_token() {
+        let (addr, _) = start_server(Duration::ZERO, vec![Arc::new(TokenHook)]).await;
+        let mut client = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+        let request = RemotingCommand::create_remoting_command(RequestCode::HeartBeat);
+        client.writer.send(request.set_opaque(1)).await.unwrap();
+        let response = time::timeout(Duration::from_secs(5), client.reader.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.opaque(), 1);
+        assert_eq!(response.code(), ResponseCode::NoPermission as i32);
+
+        let mut request = RemotingCommand::create_remoting_command(RequestCode::HeartBeat);
+        request.add_ext_field("token", "secret");
+        client.writer.send(request.set_opaque(2)).await.unwrap();
+        let response = time::timeout(Duration::from_secs(5), client.reader.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.opaque(), 2);
+        assert_eq!(response.code(), RequestCode::HeartBeat as i32);
+    }
+}
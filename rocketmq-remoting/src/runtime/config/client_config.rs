@@ -16,7 +16,10 @@
  */
 
 use lazy_static::lazy_static;
+use rocketmq_common::common::tls_config::TlsConfig;
 
+use crate::protocol::remoting_command::SERIALIZE_TYPE_CONFIG_IN_THIS_SERVER;
+use crate::protocol::SerializeType;
 use crate::runtime::config::net_system_config::NetSystemConfig;
 
 lazy_static! {
@@ -36,7 +39,9 @@ pub struct TokioClientConfig {
     pub client_socket_rcv_buf_size: i32,
     pub client_pooled_byte_buf_allocator_enable: bool,
     pub client_close_socket_if_timeout: bool,
-    //pub use_tls: bool,
+    pub tls_config: TlsConfig,
+    /// Serialize type of the command headers sent, the server answers in the same type.
+    pub serialize_type: SerializeType,
     pub socks_proxy_config: String,
     pub write_buffer_high_water_mark: i32,
     pub write_buffer_low_water_mark: i32,
@@ -61,7 +66,8 @@ impl Default for TokioClientConfig {
             client_socket_rcv_buf_size: NET_SYSTEM_CONFIG.socket_rcvbuf_size,
             client_pooled_byte_buf_allocator_enable: false,
             client_close_socket_if_timeout: NET_SYSTEM_CONFIG.client_close_socket_if_timeout,
-            //use_tls: TlsSystemConfig::is_tls_enabled(),
+            tls_config: TlsConfig::default(),
+            serialize_type: *SERIALIZE_TYPE_CONFIG_IN_THIS_SERVER,
             socks_proxy_config: "{}".to_string(),
             write_buffer_high_water_mark: NET_SYSTEM_CONFIG.write_buffer_high_water_mark_value,
             write_buffer_low_water_mark: NET_SYSTEM_CONFIG.write_buffer_low_water_mark,
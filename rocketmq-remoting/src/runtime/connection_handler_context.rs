@@ -45,7 +45,7 @@ impl ConnectionHandlerContextWrapper {
 
     pub async fn write(&mut self, cmd: RemotingCommand) {
         match self.channel.connection_mut().writer.send(cmd).await {
-            Ok(_) => {}
+            Ok(_) => self.channel.connection_ref().touch(),
             Err(error) => {
                 error!("send response failed: {}", error);
             }
@@ -148,6 +148,15 @@ impl PutMessageResult {
         self.append_message_result.as_ref()
     }
 
+    /// Offset the message was assigned in its consume queue, unknown unless it was appended to
+    /// the local store.
+    #[inline]
+    pub fn queue_offset(&self) -> Option<i64> {
+        self.append_message_result
+            .as_ref()
+            .map(|append_message_result| append_message_result.logics_offset)
+    }
+
     #[inline]
     pub fn remote_put(&self) -> bool {
         self.remote_put
@@ -205,6 +214,17 @@ mod put_message_result_tests {
         }
     }
 
+    #[test]
+    fn queue_offset_is_the_logics_offset_of_the_append() {
+        let result = PutMessageResult::new_append_result(
+            PutMessageStatus::PutOk,
+            Some(create_append_message_result(AppendMessageStatus::PutOk)),
+        );
+        assert_eq!(result.queue_offset(), Some(10));
+        let result = PutMessageResult::new(PutMessageStatus::PutOk, None, true);
+        assert_eq!(result.queue_offset(), None);
+    }
+
     #[test]
     fn is_ok_with_remote_put_and_put_ok_status() {
         let result = PutMessageResult::new(PutMessageStatus::PutOk, None, true);
@@ -38,15 +38,15 @@ pub struct SelectMappedBufferResult {
 impl SelectMappedBufferResult {
     /// Returns the buffer.
     pub fn get_buffer(&self) -> &[u8] {
-        self.mapped_file.as_ref().unwrap().get_mapped_file()
-            [self.start_offset as usize..(self.start_offset + self.size as u64) as usize]
-            .as_ref()
+        let mapped_file = self.mapped_file.as_ref().unwrap();
+        let pos = (self.start_offset - mapped_file.get_file_from_offset()) as usize;
+        mapped_file.get_mapped_file()[pos..pos + self.size as usize].as_ref()
     }
 
     pub fn get_buffer_slice_mut(&self) -> &mut [u8] {
-        self.mapped_file.as_ref().unwrap().get_mapped_file_mut()
-            [self.start_offset as usize..(self.start_offset + self.size as u64) as usize]
-            .as_mut()
+        let mapped_file = self.mapped_file.as_ref().unwrap();
+        let pos = (self.start_offset - mapped_file.get_file_from_offset()) as usize;
+        mapped_file.get_mapped_file_mut()[pos..pos + self.size as usize].as_mut()
     }
 
     pub fn get_bytes(&self) -> Option<Bytes> {
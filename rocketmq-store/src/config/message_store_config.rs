@@ -206,6 +206,8 @@ pub struct MessageStoreConfig {
     pub enable_rocksdb_log: bool,
     pub topic_queue_lock_num: usize,
     pub max_filter_message_size: i32,
+    pub commit_log_offload_enable: bool,
+    pub commit_log_offload_after_hours: usize,
 }
 
 impl Default for MessageStoreConfig {
@@ -242,7 +244,7 @@ impl Default for MessageStoreConfig {
             timer_enable_disruptor: false,
             timer_enable_check_metrics: false,
             timer_intercept_delay_level: false,
-            timer_max_delay_sec: 0,
+            timer_max_delay_sec: 3600 * 24 * 3,
             timer_wheel_enable: false,
             disappear_time_after_start: -1,
             timer_stop_enqueue: false,
@@ -392,6 +394,8 @@ impl Default for MessageStoreConfig {
             enable_rocksdb_log: false,
             topic_queue_lock_num: 32,
             max_filter_message_size: 16000,
+            commit_log_offload_enable: false,
+            commit_log_offload_after_hours: 72,
         }
     }
 }
@@ -1102,6 +1106,14 @@ impl MessageStoreConfig {
             "maxFilterMessageSize".into(),
             self.max_filter_message_size.to_string(),
         );
+        properties.insert(
+            "commitLogOffloadEnable".into(),
+            self.commit_log_offload_enable.to_string(),
+        );
+        properties.insert(
+            "commitLogOffloadAfterHours".into(),
+            self.commit_log_offload_after_hours.to_string(),
+        );
         properties
             .into_iter()
             .map(|(k, v)| (k.into(), v.into()))
@@ -286,6 +286,34 @@ impl MappedFileQueue {
         }
     }
 
+    /// Destroys the files holding only data before `offset`, the last file is always kept.
+    pub fn delete_files_before(&self, offset: i64) -> usize {
+        let mut mapped_files = self.mapped_files.write();
+        let expired = mapped_files[..mapped_files.len().saturating_sub(1)]
+            .iter()
+            .take_while(|mapped_file| {
+                (mapped_file.get_file_from_offset() + self.mapped_file_size) as i64 <= offset
+            })
+            .count();
+        for mapped_file in mapped_files.drain(..expired) {
+            mapped_file.destroy(1000);
+            if let Err(err) = fs::remove_file(mapped_file.get_file_name().as_str()) {
+                warn!(
+                    "remove mapped file {} failed: {}",
+                    mapped_file.get_file_name(),
+                    err
+                );
+                continue;
+            }
+            info!(
+                "delete mapped file {} before offset {}",
+                mapped_file.get_file_name(),
+                offset
+            );
+        }
+        expired
+    }
+
     pub(crate) fn delete_expired_file(&mut self, files: Vec<Arc<DefaultMappedFile>>) {
         let mut files = files;
         let read_guard = self.mapped_files.read();
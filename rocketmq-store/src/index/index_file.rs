@@ -18,7 +18,8 @@
 use std::sync::Arc;
 
 use bytes::Buf;
-use bytes::Bytes;
+use bytes::BufMut;
+use bytes::BytesMut;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::hasher::string_hasher::JavaStringHasher;
 
@@ -138,11 +139,7 @@ impl IndexFile {
             let slot_pos = key_hash as usize % self.hash_slot_num;
             let abs_slot_pos = INDEX_HEADER_SIZE + slot_pos * HASH_SLOT_SIZE;
 
-            let mut buffer = self
-                .mapped_file
-                .get_data(abs_slot_pos, abs_slot_pos + 4)
-                .unwrap();
-            let mut slot_value = buffer.get_i32();
+            let mut slot_value = self.read_i32(abs_slot_pos);
             if slot_value <= INVALID_INDEX || slot_value > self.index_header.get_index_count() {
                 slot_value = INVALID_INDEX;
             }
@@ -161,30 +158,15 @@ impl IndexFile {
                 + self.hash_slot_num * HASH_SLOT_SIZE
                 + self.index_header.get_index_count() as usize * INDEX_SIZE;
 
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&key_hash.to_be_bytes()),
-                abs_index_pos,
-                4,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&phy_offset.to_be_bytes()),
-                abs_index_pos,
-                8,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&(time_diff as i32).to_be_bytes()),
-                abs_index_pos,
-                4,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&slot_value.to_be_bytes()),
-                abs_index_pos,
-                4,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&self.index_header.get_index_count().to_be_bytes()),
+            let mut index_unit = BytesMut::with_capacity(INDEX_SIZE);
+            index_unit.put_i32(key_hash);
+            index_unit.put_i64(phy_offset);
+            index_unit.put_i32(time_diff as i32);
+            index_unit.put_i32(slot_value);
+            self.mapped_file.put_slice(&index_unit, abs_index_pos);
+            self.mapped_file.put_slice(
+                &self.index_header.get_index_count().to_be_bytes(),
                 abs_slot_pos,
-                4,
             );
 
             if self.index_header.get_index_count() <= 1 {
@@ -256,15 +238,12 @@ impl IndexFile {
         let slot_pos = key_hash as usize % self.hash_slot_num;
         let abs_slot_pos = INDEX_HEADER_SIZE + slot_pos * HASH_SLOT_SIZE;
 
-        let mut buffer = self
-            .mapped_file
-            .get_data(abs_slot_pos, abs_slot_pos + 4)
-            .unwrap();
-        let slot_value = buffer.get_i32();
+        let slot_value = self.read_i32(abs_slot_pos);
         if slot_value <= INVALID_INDEX
             || slot_value > self.index_header.get_index_count()
             || self.index_header.get_index_count() <= 1
         {
+            self.mapped_file.release();
             return;
         }
 
@@ -274,16 +253,13 @@ impl IndexFile {
                 + self.hash_slot_num * HASH_SLOT_SIZE
                 + next_index_to_read as usize * INDEX_SIZE;
 
-            let key_hash_read = buffer.slice(abs_index_pos..abs_index_pos + 4).get_i32();
-            let phy_offset_read = buffer
-                .slice(abs_index_pos + 4..abs_index_pos + 12)
-                .get_i64();
-            let time_diff = buffer
-                .slice(abs_index_pos + 12..abs_index_pos + 16)
-                .get_i32();
-            let prev_index_read = buffer
-                .slice(abs_index_pos + 16..abs_index_pos + 20)
-                .get_i32();
+            let Some(mut index_unit) = self.mapped_file.get_bytes(abs_index_pos, INDEX_SIZE) else {
+                break;
+            };
+            let key_hash_read = index_unit.get_i32();
+            let phy_offset_read = index_unit.get_i64();
+            let time_diff = index_unit.get_i32();
+            let prev_index_read = index_unit.get_i32();
 
             if time_diff < 0 {
                 break;
@@ -304,5 +280,12 @@ impl IndexFile {
 
             next_index_to_read = prev_index_read;
         }
+        self.mapped_file.release();
+    }
+
+    fn read_i32(&self, pos: usize) -> i32 {
+        self.mapped_file
+            .get_bytes(pos, 4)
+            .map_or(INVALID_INDEX, |mut buffer| buffer.get_i32())
     }
 }
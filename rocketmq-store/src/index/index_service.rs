@@ -145,36 +145,29 @@ impl IndexService {
         let max_num = max_num.min(self.message_store_config.max_msgs_num_batch as i32);
 
         let index_file_list = self.index_file_list.read();
-        let mut index = 0;
-        if !index_file_list.is_empty() {
-            for f in index_file_list.iter() {
-                let last_file = index == index_file_list.len() - 1;
-                if last_file {
-                    // Assuming IndexFile has methods to get end timestamp and phy offset
-                    index_last_update_timestamp = f.get_end_timestamp();
-                    index_last_update_phyoffset = f.get_end_phy_offset();
-                }
+        // newest files first, so the walk can stop once it reaches files older than `begin`
+        for (index, f) in index_file_list.iter().enumerate().rev() {
+            if index == index_file_list.len() - 1 {
+                index_last_update_timestamp = f.get_end_timestamp();
+                index_last_update_phyoffset = f.get_end_phy_offset();
+            }
 
-                // Assuming IndexFile has a method to check if the file's timestamps match the query
-                if f.is_time_matched(begin, end) {
-                    // Assuming IndexFile has a method to select physical offsets based on the query
-                    f.select_phy_offset(
-                        &mut phy_offsets,
-                        build_key(topic, key).as_str(),
-                        max_num as usize,
-                        begin,
-                        end,
-                    );
-                }
+            if f.is_time_matched(begin, end) {
+                f.select_phy_offset(
+                    &mut phy_offsets,
+                    build_key(topic, key).as_str(),
+                    max_num as usize,
+                    begin,
+                    end,
+                );
+            }
 
-                if f.get_begin_timestamp() < begin {
-                    break;
-                }
+            if f.get_begin_timestamp() < begin {
+                break;
+            }
 
-                if phy_offsets.len() as i32 >= max_num {
-                    break;
-                }
-                index += 1;
+            if phy_offsets.len() as i32 >= max_num {
+                break;
             }
         }
         QueryOffsetResult::new(
@@ -204,39 +197,37 @@ impl IndexService {
                     _ => (),
                 }
 
-                let mut index_file_new = None;
+                let mut index_file = index_file_inner;
                 if let Some(ref uniq_key) = dispatch_request.uniq_key {
-                    index_file_new = self.put_key(
-                        index_file_inner,
+                    match self.put_key(
+                        index_file,
                         dispatch_request,
                         build_key(topic, uniq_key.as_str()).as_str(),
-                    );
-                    if index_file_new.is_none() {
-                        error!(
-                            "putKey error commitlog {} uniqkey {}",
-                            dispatch_request.commit_log_offset, uniq_key
-                        );
-                        return;
+                    ) {
+                        Some(index_file_new) => index_file = index_file_new,
+                        None => {
+                            error!(
+                                "putKey error commitlog {} uniqkey {}",
+                                dispatch_request.commit_log_offset, uniq_key
+                            );
+                            return;
+                        }
                     }
                 }
 
-                if !keys.is_empty() {
-                    let keyset = keys.split(MessageConst::KEY_SEPARATOR);
-                    for key in keyset {
-                        if !key.is_empty() {
-                            let index_file = self.put_key(
-                                index_file_new.take().unwrap(),
-                                dispatch_request,
-                                build_key(topic, key).as_str(),
+                for key in keys.split(MessageConst::KEY_SEPARATOR) {
+                    if key.is_empty() {
+                        continue;
+                    }
+                    match self.put_key(index_file, dispatch_request, build_key(topic, key).as_str())
+                    {
+                        Some(index_file_new) => index_file = index_file_new,
+                        None => {
+                            error!(
+                                "putKey error commitlog {} key {}",
+                                dispatch_request.commit_log_offset, key
                             );
-                            if index_file.is_none() {
-                                error!(
-                                    "putKey error commitlog {} uniqkey {}",
-                                    dispatch_request.commit_log_offset,
-                                    dispatch_request.uniq_key.as_ref().unwrap()
-                                );
-                                return;
-                            }
+                            return;
                         }
                     }
                 }
@@ -371,3 +362,78 @@ fn build_key(topic: &str, key: &str) -> String {
     keys.push_str(key);
     keys
 }
+
+#[cfg(test)]
+mod tests {
+    use cheetah_string::CheetahString;
+
+    use super::*;
+
+    fn new_index_service(root_dir: &Path) -> IndexService {
+        let message_store_config = MessageStoreConfig {
+            store_path_root_dir: CheetahString::from_string(
+                root_dir.to_string_lossy().into_owned(),
+            ),
+            max_hash_slot_num: 64,
+            max_index_num: 256,
+            ..MessageStoreConfig::default()
+        };
+        let store_checkpoint = StoreCheckpoint::new(root_dir.join("checkpoint")).unwrap();
+        IndexService::new(Arc::new(message_store_config), Arc::new(store_checkpoint))
+    }
+
+    fn dispatch_request(
+        commit_log_offset: i64,
+        keys: &str,
+        uniq_key: &str,
+        store_timestamp: i64,
+    ) -> DispatchRequest {
+        DispatchRequest {
+            topic: CheetahString::from_static_str("IndexTopic"),
+            commit_log_offset,
+            keys: CheetahString::from_slice(keys),
+            uniq_key: Some(CheetahString::from_slice(uniq_key)),
+            store_timestamp,
+            success: true,
+            ..DispatchRequest::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn query_offset_returns_offsets_of_indexed_keys() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let index_service = new_index_service(temp_dir.path());
+        let now = get_current_millis() as i64;
+        index_service.build_index(&dispatch_request(100, "order-1 shared", "UNIQ-1", now));
+        index_service.build_index(&dispatch_request(200, "order-2 shared", "UNIQ-2", now + 1));
+
+        let result = index_service.query_offset("IndexTopic", "UNIQ-2", 32, 0, i64::MAX);
+        assert_eq!(result.get_phy_offsets(), &vec![200]);
+        let result = index_service.query_offset("IndexTopic", "order-1", 32, 0, i64::MAX);
+        assert_eq!(result.get_phy_offsets(), &vec![100]);
+
+        let mut result = index_service.query_offset("IndexTopic", "shared", 32, 0, i64::MAX);
+        result.get_phy_offsets_mut().sort();
+        assert_eq!(result.get_phy_offsets(), &vec![100, 200]);
+        assert_eq!(result.get_index_last_update_phyoffset(), 200);
+
+        let result = index_service.query_offset("OtherTopic", "shared", 32, 0, i64::MAX);
+        assert!(result.get_phy_offsets().is_empty());
+    }
+
+    #[tokio::test]
+    async fn query_offset_honours_time_range_and_max_num() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let index_service = new_index_service(temp_dir.path());
+        let now = get_current_millis() as i64;
+        for offset in 1..=4 {
+            index_service.build_index(&dispatch_request(offset * 100, "shared", "", now));
+        }
+
+        let result = index_service.query_offset("IndexTopic", "shared", 2, 0, i64::MAX);
+        assert_eq!(result.get_phy_offsets().len(), 2);
+
+        let result = index_service.query_offset("IndexTopic", "shared", 32, now + 60_000, i64::MAX);
+        assert!(result.get_phy_offsets().is_empty());
+    }
+}
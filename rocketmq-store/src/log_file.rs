@@ -25,19 +25,20 @@ use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use rocketmq_common::TimeUtils::get_current_millis;
 
-use crate::base::dispatch_request::DispatchRequest;
+pub use crate::base::dispatch_request::DispatchRequest;
 use crate::base::get_message_result::GetMessageResult;
 use crate::base::message_result::PutMessageResult;
 use crate::base::query_message_result::QueryMessageResult;
 use crate::base::select_result::SelectMappedBufferResult;
 use crate::filter::MessageFilter;
 use crate::hook::put_message_hook::BoxedPutMessageHook;
-use crate::queue::ArcConsumeQueue;
+pub use crate::queue::ArcConsumeQueue;
 use crate::stats::broker_stats_manager::BrokerStatsManager;
 use crate::store::running_flags::RunningFlags;
 use crate::timer::timer_message_store::TimerMessageStore;
 
 pub(crate) mod cold_data_check_service;
+pub mod cold_store;
 pub mod commit_log;
 pub mod flush_manager_impl;
 pub mod mapped_file;
@@ -193,6 +194,22 @@ pub trait RocketMQMessageStore: Sync + 'static {
     /// The minimum offset in the queue.
     fn get_min_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64;
 
+    /// Trim the queue so that its minimum offset becomes `offset`.
+    ///
+    /// The consume queue files entirely before `offset` are deleted, the commit log is shared by
+    /// all the queues and its files are only reclaimed once they expire.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic name.
+    /// * `queue_id` - The queue identifier.
+    /// * `offset` - The offset to trim the queue before, capped at the maximum offset.
+    ///
+    /// # Returns
+    ///
+    /// The minimum offset in the queue after the trim.
+    fn trim_consume_queue(&self, topic: &CheetahString, queue_id: i32, offset: i64) -> i64;
+
     /// Get the maximum offset in the queue.
     ///
     /// # Arguments
@@ -205,6 +222,25 @@ pub trait RocketMQMessageStore: Sync + 'static {
     /// The maximum offset in the queue.
     fn get_max_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64;
 
+    /// Get the minimum and maximum offsets in the queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic name.
+    /// * `queue_id` - The queue identifier.
+    ///
+    /// # Returns
+    ///
+    /// `(min_offset, max_offset)`, as returned by `get_min_offset_in_queue` and
+    /// `get_max_offset_in_queue`. A store able to read both from one lookup of the queue should
+    /// override it.
+    fn get_offset_range_in_queue(&self, topic: &CheetahString, queue_id: i32) -> (i64, i64) {
+        (
+            self.get_min_offset_in_queue(topic, queue_id),
+            self.get_max_offset_in_queue(topic, queue_id),
+        )
+    }
+
     /// Get the maximum committed offset in the queue.
     ///
     /// # Arguments
@@ -223,6 +259,22 @@ pub trait RocketMQMessageStore: Sync + 'static {
         committed: bool,
     ) -> i64;
 
+    /// Check whether a queue exists in the store.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic name.
+    /// * `queue_id` - The queue identifier.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the queue exists. By default a queue exists when both its minimum and maximum
+    /// offsets are known.
+    fn queue_exists(&self, topic: &CheetahString, queue_id: i32) -> bool {
+        self.get_min_offset_in_queue(topic, queue_id) != -1
+            && self.get_max_offset_in_queue(topic, queue_id) != -1
+    }
+
     /// Get a message asynchronously.
     ///
     /// # Arguments
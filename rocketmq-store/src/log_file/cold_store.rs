@@ -0,0 +1,402 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+use rocketmq_common::FileUtils;
+use rocketmq_common::TimeUtils::get_current_millis;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::base::select_result::SelectMappedBufferResult;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::consume_queue::mapped_file_queue::MappedFileQueue;
+use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use crate::log_file::mapped_file::MappedFile;
+
+/// How many offloaded segments are kept on local disk after being fetched back.
+const COLD_SEGMENT_CACHE_SIZE: usize = 2;
+
+/// Backend keeping the content of CommitLog segments that were moved off local disk.
+///
+/// Segments are addressed by their file name, which is the commit log offset they start at.
+pub trait ColdStore: Send + Sync {
+    fn upload(&self, segment_name: &str, data: &[u8]) -> std::io::Result<()>;
+
+    fn fetch(&self, segment_name: &str) -> std::io::Result<Vec<u8>>;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OffloadedSegment {
+    pub segment_name: String,
+    pub file_from_offset: i64,
+    pub size: i32,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OffloadManifest {
+    segments: Vec<OffloadedSegment>,
+}
+
+/// Moves old CommitLog segments to a [`ColdStore`] and serves reads of the offloaded ranges by
+/// fetching the segments back on demand.
+pub struct CommitLogOffloader {
+    cold_store: Arc<dyn ColdStore>,
+    mapped_file_size: u64,
+    manifest_path: String,
+    cache_dir: PathBuf,
+    segments: RwLock<BTreeMap<i64, OffloadedSegment>>,
+    cache: Mutex<VecDeque<Arc<DefaultMappedFile>>>,
+}
+
+impl CommitLogOffloader {
+    pub fn new(cold_store: Arc<dyn ColdStore>, message_store_config: &MessageStoreConfig) -> Self {
+        let root_dir = PathBuf::from(message_store_config.store_path_root_dir.as_str());
+        Self {
+            cold_store,
+            mapped_file_size: message_store_config.mapped_file_size_commit_log as u64,
+            manifest_path: root_dir
+                .join("config")
+                .join("commitLogOffload.json")
+                .to_string_lossy()
+                .into_owned(),
+            cache_dir: root_dir.join("commitlog_cold_cache"),
+            segments: RwLock::new(BTreeMap::new()),
+            cache: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn load(&self) -> bool {
+        let content = match FileUtils::file_to_string(self.manifest_path.as_str()) {
+            Ok(content) => content,
+            Err(err) => {
+                error!("load commit log offload manifest failed: {}", err);
+                return false;
+            }
+        };
+        if content.is_empty() {
+            return true;
+        }
+        match serde_json::from_str::<OffloadManifest>(content.as_str()) {
+            Ok(manifest) => {
+                let mut segments = self.segments.write();
+                for segment in manifest.segments {
+                    segments.insert(segment.file_from_offset, segment);
+                }
+                info!(
+                    "load commit log offload manifest, {} segments",
+                    segments.len()
+                );
+                true
+            }
+            Err(err) => {
+                error!("parse commit log offload manifest failed: {}", err);
+                false
+            }
+        }
+    }
+
+    fn persist(&self) -> std::io::Result<()> {
+        let manifest = OffloadManifest {
+            segments: self.segments.read().values().cloned().collect(),
+        };
+        let content = serde_json::to_string(&manifest)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        FileUtils::string_to_file(content.as_str(), self.manifest_path.as_str())
+    }
+
+    /// Offloads every full segment of `mapped_file_queue`, except the one being written, that was
+    /// last modified before `expired_before`. Returns how many segments were moved.
+    pub fn offload(&self, mapped_file_queue: &MappedFileQueue, expired_before: i64) -> usize {
+        let candidates: Vec<Arc<DefaultMappedFile>> = {
+            let mapped_files = mapped_file_queue.get_mapped_files();
+            let mapped_files = mapped_files.read();
+            let writable = mapped_files.len().saturating_sub(1);
+            mapped_files
+                .iter()
+                .take(writable)
+                .take_while(|mapped_file| {
+                    mapped_file.is_full()
+                        && mapped_file.get_last_modified_timestamp() < expired_before
+                })
+                .cloned()
+                .collect()
+        };
+
+        let mut offloaded = 0;
+        for mapped_file in candidates {
+            let segment_name = segment_name_of(mapped_file.get_file_name());
+            let size = mapped_file.get_read_position();
+            let Some(data) = mapped_file.get_bytes(0, size as usize) else {
+                break;
+            };
+            if let Err(err) = self.cold_store.upload(segment_name.as_str(), &data) {
+                warn!(
+                    "offload commit log segment {} failed: {}",
+                    segment_name, err
+                );
+                break;
+            }
+            self.segments.write().insert(
+                mapped_file.get_file_from_offset() as i64,
+                OffloadedSegment {
+                    segment_name,
+                    file_from_offset: mapped_file.get_file_from_offset() as i64,
+                    size,
+                },
+            );
+            if let Err(err) = self.persist() {
+                error!("persist commit log offload manifest failed: {}", err);
+                break;
+            }
+            mapped_file_queue
+                .get_mapped_files()
+                .write()
+                .retain(|local| local.as_ref() != mapped_file.as_ref());
+            if let Err(err) = fs::remove_file(mapped_file.get_file_name().as_str()) {
+                warn!(
+                    "remove offloaded commit log segment {} failed: {}",
+                    mapped_file.get_file_name(),
+                    err
+                );
+            }
+            info!(
+                "offloaded commit log segment {}, size {}",
+                mapped_file.get_file_name(),
+                size
+            );
+            offloaded += 1;
+        }
+        offloaded
+    }
+
+    /// Offloads the segments older than `offload_after_hours`.
+    pub fn offload_expired(
+        &self,
+        mapped_file_queue: &MappedFileQueue,
+        offload_after_hours: usize,
+    ) -> usize {
+        let expired_before =
+            get_current_millis() as i64 - offload_after_hours as i64 * 60 * 60 * 1000;
+        self.offload(mapped_file_queue, expired_before)
+    }
+
+    /// The lowest commit log offset kept in the cold store.
+    pub fn get_min_offset(&self) -> Option<i64> {
+        self.segments.read().keys().next().copied()
+    }
+
+    pub fn get_offloaded_segments(&self) -> Vec<OffloadedSegment> {
+        self.segments.read().values().cloned().collect()
+    }
+
+    pub fn is_offloaded(&self, offset: i64) -> bool {
+        self.segment_of(offset).is_some()
+    }
+
+    /// Reads `size` bytes at `offset`, or up to the end of the segment when `size` is `None`.
+    pub fn select_mapped_buffer(
+        &self,
+        offset: i64,
+        size: Option<i32>,
+    ) -> Option<SelectMappedBufferResult> {
+        let segment = self.segment_of(offset)?;
+        let mapped_file = self.fetch_segment(&segment)?;
+        let pos = (offset - segment.file_from_offset) as i32;
+        match size {
+            Some(size) => mapped_file.select_mapped_buffer_size(pos, size),
+            None => mapped_file.select_mapped_buffer(pos),
+        }
+    }
+
+    fn segment_of(&self, offset: i64) -> Option<OffloadedSegment> {
+        self.segments
+            .read()
+            .range(..=offset)
+            .next_back()
+            .map(|(_, segment)| segment)
+            .filter(|segment| offset < segment.file_from_offset + self.mapped_file_size as i64)
+            .cloned()
+    }
+
+    fn fetch_segment(&self, segment: &OffloadedSegment) -> Option<Arc<DefaultMappedFile>> {
+        let mut cache = self.cache.lock();
+        if let Some(mapped_file) = cache.iter().find(|mapped_file| {
+            mapped_file.get_file_from_offset() as i64 == segment.file_from_offset
+        }) {
+            return Some(mapped_file.clone());
+        }
+
+        let data = match self.cold_store.fetch(segment.segment_name.as_str()) {
+            Ok(data) => data,
+            Err(err) => {
+                error!(
+                    "fetch offloaded commit log segment {} failed: {}",
+                    segment.segment_name, err
+                );
+                return None;
+            }
+        };
+        let file_name = self.cache_dir.join(segment.segment_name.as_str());
+        let mapped_file = Arc::new(DefaultMappedFile::new(
+            CheetahString::from_string(file_name.to_string_lossy().into_owned()),
+            self.mapped_file_size,
+        ));
+        mapped_file.put_slice(&data, 0);
+        mapped_file.set_wrote_position(data.len() as i32);
+        mapped_file.set_committed_position(data.len() as i32);
+        mapped_file.set_flushed_position(data.len() as i32);
+
+        if cache.len() >= COLD_SEGMENT_CACHE_SIZE {
+            if let Some(evicted) = cache.pop_front() {
+                let _ = fs::remove_file(evicted.get_file_name().as_str());
+            }
+        }
+        cache.push_back(mapped_file.clone());
+        Some(mapped_file)
+    }
+}
+
+fn segment_name_of(file_name: &CheetahString) -> String {
+    PathBuf::from(file_name.as_str())
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bytes::Bytes;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MockColdStore {
+        segments: Mutex<HashMap<String, Vec<u8>>>,
+        fetches: Mutex<usize>,
+    }
+
+    impl ColdStore for MockColdStore {
+        fn upload(&self, segment_name: &str, data: &[u8]) -> std::io::Result<()> {
+            self.segments
+                .lock()
+                .insert(segment_name.to_string(), data.to_vec());
+            Ok(())
+        }
+
+        fn fetch(&self, segment_name: &str) -> std::io::Result<Vec<u8>> {
+            *self.fetches.lock() += 1;
+            self.segments
+                .lock()
+                .get(segment_name)
+                .cloned()
+                .ok_or_else(|| {
+                    std::io::Error::new(std::io::ErrorKind::NotFound, segment_name.to_string())
+                })
+        }
+    }
+
+    const SEGMENT_SIZE: u64 = 64;
+
+    fn config_for(root_dir: &std::path::Path) -> MessageStoreConfig {
+        MessageStoreConfig {
+            store_path_root_dir: CheetahString::from_string(
+                root_dir.to_string_lossy().into_owned(),
+            ),
+            mapped_file_size_commit_log: SEGMENT_SIZE as usize,
+            ..MessageStoreConfig::default()
+        }
+    }
+
+    fn commit_log_with_segments(config: &MessageStoreConfig, count: u64) -> MappedFileQueue {
+        let mut queue =
+            MappedFileQueue::new(config.get_store_path_commit_log(), SEGMENT_SIZE, None);
+        for index in 0..count {
+            let mapped_file = queue.try_create_mapped_file(index * SEGMENT_SIZE).unwrap();
+            let data: Vec<u8> = (0..SEGMENT_SIZE)
+                .map(|i| (index * 10 + i % 10) as u8)
+                .collect();
+            mapped_file.append_message_bytes(&Bytes::from(data));
+        }
+        queue
+    }
+
+    #[test]
+    fn offloaded_segments_are_read_through_the_cold_store() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = config_for(temp_dir.path());
+        let queue = commit_log_with_segments(&config, 3);
+        let cold_store = Arc::new(MockColdStore::default());
+        let offloader = CommitLogOffloader::new(cold_store.clone(), &config);
+
+        let expired_before = get_current_millis() as i64 + 1000;
+        assert_eq!(offloader.offload(&queue, expired_before), 2);
+        assert_eq!(queue.get_mapped_files_size(), 1);
+        assert_eq!(cold_store.segments.lock().len(), 2);
+        assert_eq!(offloader.get_min_offset(), Some(0));
+        assert!(offloader.is_offloaded(SEGMENT_SIZE as i64 + 5));
+        assert!(!offloader.is_offloaded(2 * SEGMENT_SIZE as i64));
+
+        let result = offloader
+            .select_mapped_buffer(SEGMENT_SIZE as i64 + 3, Some(4))
+            .unwrap();
+        assert_eq!(result.get_bytes().unwrap().as_ref(), &[13, 14, 15, 16]);
+        let result = offloader.select_mapped_buffer(60, None).unwrap();
+        assert_eq!(result.get_bytes().unwrap().as_ref(), &[0, 1, 2, 3]);
+
+        offloader
+            .select_mapped_buffer(SEGMENT_SIZE as i64, Some(1))
+            .unwrap();
+        assert_eq!(*cold_store.fetches.lock(), 2);
+    }
+
+    #[test]
+    fn offload_keeps_recent_segments_and_reloads_manifest() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = config_for(temp_dir.path());
+        let queue = commit_log_with_segments(&config, 2);
+        let cold_store = Arc::new(MockColdStore::default());
+        let offloader = CommitLogOffloader::new(cold_store.clone(), &config);
+
+        assert_eq!(offloader.offload_expired(&queue, 1), 0);
+        assert_eq!(queue.get_mapped_files_size(), 2);
+
+        assert_eq!(offloader.offload(&queue, i64::MAX), 1);
+        let reloaded = CommitLogOffloader::new(cold_store, &config);
+        assert!(reloaded.load());
+        assert_eq!(
+            reloaded.get_offloaded_segments(),
+            offloader.get_offloaded_segments()
+        );
+        let result = reloaded.select_mapped_buffer(1, Some(2)).unwrap();
+        assert_eq!(result.get_bytes().unwrap().as_ref(), &[1, 2]);
+    }
+}
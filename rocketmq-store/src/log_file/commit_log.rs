@@ -69,6 +69,8 @@ use crate::config::broker_role::BrokerRole;
 use crate::config::message_store_config::MessageStoreConfig;
 use crate::consume_queue::mapped_file_queue::MappedFileQueue;
 use crate::log_file::cold_data_check_service::ColdDataCheckService;
+use crate::log_file::cold_store::ColdStore;
+use crate::log_file::cold_store::CommitLogOffloader;
 use crate::log_file::flush_manager_impl::defalut_flush_manager::DefaultFlushManager;
 use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
 use crate::log_file::mapped_file::MappedFile;
@@ -200,6 +202,7 @@ pub struct CommitLog {
     //flush_manager: Arc<parking_lot::Mutex<DefaultFlushManager>>,
     begin_time_in_lock: Arc<AtomicU64>,
     cold_data_check_service: Arc<ColdDataCheckService>,
+    commit_log_offloader: Option<Arc<CommitLogOffloader>>,
 }
 
 impl CommitLog {
@@ -241,6 +244,7 @@ impl CommitLog {
             ))),
             begin_time_in_lock: Arc::new(AtomicU64::new(0)),
             cold_data_check_service: Arc::new(Default::default()),
+            commit_log_offloader: None,
         }
     }
 }
@@ -271,6 +275,9 @@ impl CommitLog {
     pub fn destroy(&mut self) {}
 
     pub fn get_message(&self, offset: i64, size: i32) -> Option<SelectMappedBufferResult> {
+        if let Some(offloader) = self.offloader_of(offset) {
+            return offloader.select_mapped_buffer(offset, Some(size));
+        }
         let mapped_file_size = self.message_store_config.mapped_file_size_commit_log;
         let mapped_file = self
             .mapped_file_queue
@@ -1099,6 +1106,13 @@ impl CommitLog {
     }
 
     pub fn get_min_offset(&self) -> i64 {
+        if let Some(min_offset) = self
+            .commit_log_offloader
+            .as_ref()
+            .and_then(|offloader| offloader.get_min_offset())
+        {
+            return min_offset;
+        }
         match self.mapped_file_queue.get_first_mapped_file() {
             None => -1,
             Some(mapped_file) => {
@@ -1125,6 +1139,9 @@ impl CommitLog {
         offset: i64,
         return_first_on_not_found: bool,
     ) -> Option<SelectMappedBufferResult> {
+        if let Some(offloader) = self.offloader_of(offset) {
+            return offloader.select_mapped_buffer(offset, None);
+        }
         let mapped_file_size = self.message_store_config.mapped_file_size_commit_log as i64;
         let mapped_file = self
             .mapped_file_queue
@@ -1141,6 +1158,30 @@ impl CommitLog {
         self.mapped_file_queue.check_self();
     }
 
+    /// Plugs in the backend old segments are offloaded to, and starts serving the ranges it
+    /// already holds.
+    pub fn set_cold_store(&mut self, cold_store: Arc<dyn ColdStore>) {
+        let offloader = CommitLogOffloader::new(cold_store, &self.message_store_config);
+        offloader.load();
+        self.commit_log_offloader = Some(Arc::new(offloader));
+    }
+
+    pub fn offload_cold_segments(&self) -> usize {
+        match self.commit_log_offloader {
+            None => 0,
+            Some(ref offloader) => offloader.offload_expired(
+                &self.mapped_file_queue,
+                self.message_store_config.commit_log_offload_after_hours,
+            ),
+        }
+    }
+
+    fn offloader_of(&self, offset: i64) -> Option<&Arc<CommitLogOffloader>> {
+        self.commit_log_offloader
+            .as_ref()
+            .filter(|offloader| offloader.is_offloaded(offset))
+    }
+
     pub fn lock_time_mills(&self) -> i64 {
         let begin = self
             .begin_time_in_lock
@@ -1242,7 +1283,7 @@ pub fn check_message_and_return_size(
     let properties_length = bytes.get_i16();
     let (tags_code, keys, uniq_key, properties_map) = if properties_length > 0 {
         let properties = bytes.copy_to_bytes(properties_length as usize);
-        let properties_content = String::from_utf8_lossy(topic_bytes.as_ref()).to_string();
+        let properties_content = String::from_utf8_lossy(properties.as_ref()).to_string();
         //need to optimize
         let properties_map =
             string_to_message_properties(Some(&CheetahString::from_string(properties_content)));
@@ -507,7 +507,12 @@ impl MappedFile for DefaultMappedFile {
     }
 
     fn get_last_modified_timestamp(&self) -> i64 {
-        todo!()
+        self.file
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_millis() as i64)
     }
 
     fn get_data(&self, pos: usize, size: usize) -> Option<bytes::Bytes> {
@@ -83,6 +83,7 @@ use crate::index::index_dispatch::CommitLogDispatcherBuildIndex;
 use crate::index::index_service::IndexService;
 use crate::kv::compaction_service::CompactionService;
 use crate::kv::compaction_store::CompactionStore;
+use crate::log_file::cold_store::ColdStore;
 use crate::log_file::commit_log;
 use crate::log_file::commit_log::CommitLog;
 use crate::log_file::mapped_file::MappedFile;
@@ -254,6 +255,10 @@ impl DefaultMessageStore {
     ) {
         self.message_store_arc = message_store_arc;
     }
+
+    pub fn set_cold_store(&mut self, cold_store: Arc<dyn ColdStore>) {
+        self.commit_log.set_cold_store(cold_store);
+    }
 }
 
 impl Drop for DefaultMessageStore {
@@ -291,6 +296,7 @@ impl DefaultMessageStore {
     }
 
     async fn recover(&mut self, last_exit_ok: bool) {
+        self.running_flags.make_recovering(true);
         let recover_concurrently = self.is_recover_concurrently();
         info!(
             "message store recover mode: {}",
@@ -333,6 +339,7 @@ impl DefaultMessageStore {
             recover_commit_log,
             recover_topic_queue_table
         );
+        self.running_flags.make_recovering(false);
     }
 
     pub fn recover_topic_queue_table(&mut self) {
@@ -460,6 +467,23 @@ impl DefaultMessageStore {
         });
     }
 
+    fn start_commit_log_offload(&self) {
+        if !self.message_store_config.commit_log_offload_enable {
+            return;
+        }
+        let message_store = self.message_store_arc.clone().unwrap();
+        let clean_resource_interval = self.message_store_config.clean_resource_interval as u64;
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_millis(clean_resource_interval));
+            interval.tick().await;
+            loop {
+                message_store.commit_log.offload_cold_segments();
+                interval.tick().await;
+            }
+        });
+    }
+
     fn check_self(&self) {
         self.commit_log.check_self();
         self.consume_queue_store.check_self();
@@ -612,6 +636,7 @@ impl MessageStore for DefaultMessageStore {
         );
 
         self.commit_log.start();
+        self.start_commit_log_offload();
 
         //self.add_schedule_task();
 
@@ -755,7 +780,7 @@ impl MessageStore for DefaultMessageStore {
     }
 
     fn dispatch_behind_bytes(&self) -> i64 {
-        0
+        self.reput_message_service.behind()
     }
 
     fn get_min_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
@@ -763,10 +788,25 @@ impl MessageStore for DefaultMessageStore {
             .get_min_offset_in_queue(topic, queue_id)
     }
 
+    fn trim_consume_queue(&self, topic: &CheetahString, queue_id: i32, offset: i64) -> i64 {
+        self.consume_queue_store
+            .trim_consume_queue(topic, queue_id, offset)
+    }
+
     fn get_max_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
         self.get_max_offset_in_queue_committed(topic, queue_id, true)
     }
 
+    fn get_offset_range_in_queue(&self, topic: &CheetahString, queue_id: i32) -> (i64, i64) {
+        let queue = self
+            .consume_queue_store
+            .find_or_create_consume_queue(topic, queue_id);
+        (
+            queue.get_min_offset_in_queue(),
+            queue.get_max_offset_in_queue(),
+        )
+    }
+
     fn get_max_offset_in_queue_committed(
         &self,
         topic: &CheetahString,
@@ -786,6 +826,14 @@ impl MessageStore for DefaultMessageStore {
         }
     }
 
+    fn queue_exists(&self, topic: &CheetahString, queue_id: i32) -> bool {
+        self.consume_queue_store
+            .get_consume_queue_table()
+            .lock()
+            .get(topic)
+            .is_some_and(|queues| queues.contains_key(&queue_id))
+    }
+
     async fn get_message(
         &self,
         group: &CheetahString,
@@ -983,6 +1031,8 @@ impl MessageStore for DefaultMessageStore {
                             );
                             status = GetMessageStatus::Found;
                             next_phy_file_start_offset = i64::MIN;
+                        } else {
+                            break;
                         }
                     }
                 }
@@ -1367,6 +1417,17 @@ impl ReputMessageService {
         self.reput_from_offset = Some(Arc::new(AtomicI64::new(reput_from_offset)));
     }
 
+    /// Returns the number of commit log bytes not dispatched to the consume queues yet.
+    pub fn behind(&self) -> i64 {
+        match &self.inner {
+            Some(inner) => {
+                inner.commit_log.get_confirm_offset()
+                    - inner.reput_from_offset.load(Ordering::Relaxed)
+            }
+            None => 0,
+        }
+    }
+
     pub fn start(
         &mut self,
         commit_log: Arc<CommitLog>,
@@ -1640,3 +1701,134 @@ impl CorrectLogicOffsetService {
         println!("correct logic offset service run unimplemented!")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use bytes::Bytes;
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+    use rocketmq_common::common::message::MessageTrait;
+
+    use super::*;
+
+    const TOPIC: &str = "TrimTopic";
+    const GROUP: &str = "TrimGroup";
+
+    async fn start_message_store(root_dir: &Path) -> ArcMut<DefaultMessageStore> {
+        let message_store_config = Arc::new(MessageStoreConfig {
+            store_path_root_dir: CheetahString::from_string(root_dir.to_string_lossy().into()),
+            mapped_file_size_commit_log: 4 * 1024 * 1024,
+            // four entries per consume queue file
+            mapped_file_size_consume_queue: 4 * 20,
+            ..MessageStoreConfig::default()
+        });
+        let mut message_store = ArcMut::new(DefaultMessageStore::new(
+            message_store_config,
+            Arc::new(BrokerConfig::default()),
+            Arc::new(parking_lot::Mutex::new(HashMap::new())),
+            None,
+            false,
+        ));
+        let message_store_clone = message_store.clone();
+        message_store.set_message_store_arc(Some(message_store_clone));
+        assert!(message_store.load().await);
+        message_store.start().unwrap();
+        message_store
+    }
+
+    async fn put_messages(message_store: &mut ArcMut<DefaultMessageStore>, count: usize) {
+        for _ in 0..count {
+            let mut msg = MessageExtBrokerInner::default();
+            msg.set_topic(CheetahString::from_static_str(TOPIC));
+            msg.set_body(Bytes::from_static(b"trim"));
+            msg.message_ext_inner.born_timestamp = get_current_millis() as i64;
+            msg.message_ext_inner.born_host = "127.0.0.1:10000".parse().unwrap();
+            msg.message_ext_inner.store_host = "127.0.0.1:10911".parse().unwrap();
+            let result = message_store.put_message(msg).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        let topic = CheetahString::from_static_str(TOPIC);
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while message_store.get_max_offset_in_queue(&topic, 0) < count as i64 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("messages are not dispatched in time");
+    }
+
+    async fn get_message(
+        message_store: &ArcMut<DefaultMessageStore>,
+        offset: i64,
+    ) -> GetMessageResult {
+        message_store
+            .get_message(
+                &CheetahString::from_static_str(GROUP),
+                &CheetahString::from_static_str(TOPIC),
+                0,
+                offset,
+                32,
+                MAX_PULL_MSG_SIZE,
+                None,
+            )
+            .await
+            .unwrap()
+    }
+
+    fn consume_queue_file_count(root_dir: &Path) -> usize {
+        fs::read_dir(root_dir.join("consumequeue").join(TOPIC).join("0"))
+            .unwrap()
+            .count()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reads_below_trimmed_offset_are_moved_to_new_min() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let mut message_store = start_message_store(root_dir.path()).await;
+        let topic = CheetahString::from_static_str(TOPIC);
+        put_messages(&mut message_store, 10).await;
+        assert_eq!(consume_queue_file_count(root_dir.path()), 3);
+
+        assert_eq!(message_store.trim_consume_queue(&topic, 0, 6), 6);
+        assert_eq!(message_store.get_min_offset_in_queue(&topic, 0), 6);
+        // the first file only holds entries before the new min
+        assert_eq!(consume_queue_file_count(root_dir.path()), 2);
+
+        for offset in [0, 5] {
+            let result = get_message(&message_store, offset).await;
+            assert_eq!(result.status(), Some(GetMessageStatus::OffsetTooSmall));
+            assert_eq!(result.next_begin_offset(), 6);
+            assert_eq!(result.message_count(), 0);
+        }
+        let result = get_message(&message_store, 6).await;
+        assert_eq!(result.status(), Some(GetMessageStatus::Found));
+        // a read stops at the end of the consume queue file holding the new min
+        assert_eq!(result.message_count(), 2);
+        assert_eq!(result.next_begin_offset(), 8);
+
+        // trimming never moves the min backwards nor past the max
+        assert_eq!(message_store.trim_consume_queue(&topic, 0, 2), 6);
+        assert_eq!(message_store.trim_consume_queue(&topic, 0, 100), 10);
+        message_store.shutdown();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn trimmed_offset_survives_restart() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let mut message_store = start_message_store(root_dir.path()).await;
+        let topic = CheetahString::from_static_str(TOPIC);
+        put_messages(&mut message_store, 10).await;
+        assert_eq!(message_store.trim_consume_queue(&topic, 0, 5), 5);
+        message_store.shutdown();
+        drop(message_store);
+
+        let message_store = start_message_store(root_dir.path()).await;
+        assert_eq!(message_store.get_min_offset_in_queue(&topic, 0), 5);
+        assert_eq!(message_store.get_max_offset_in_queue(&topic, 0), 10);
+        let result = get_message(&message_store, 4).await;
+        assert_eq!(result.status(), Some(GetMessageStatus::OffsetTooSmall));
+        assert_eq!(result.next_begin_offset(), 5);
+    }
+}
@@ -15,11 +15,65 @@
  * limitations under the License.
  */
 use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::pop_ack_constants::PopAckConstants;
+use rocketmq_common::error::Error;
+use rocketmq_common::utils::crc32_utils;
+use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::pop::ack_msg::AckMsg;
+use crate::pop::batch_ack_msg::BatchAckMsg;
 
 pub mod ack_msg;
 pub mod batch_ack_msg;
 pub mod pop_check_point;
 
+/// Version of the ack bodies written to the revive topic. Bodies written before the version was
+/// introduced carry none and are read as version 0, they have the same fields as version 1.
+pub const ACK_BODY_VERSION: u8 = 1;
+
+#[derive(Serialize)]
+struct VersionedAckBodyRef<'a, T> {
+    #[serde(rename = "v")]
+    version: u8,
+    #[serde(flatten)]
+    ack: &'a T,
+}
+
+#[derive(Deserialize)]
+struct VersionedAckBody<T> {
+    #[serde(rename = "v", default)]
+    version: u8,
+    #[serde(flatten)]
+    ack: T,
+}
+
+/// Encodes `ack` into a revive topic message body of the current [`ACK_BODY_VERSION`].
+pub(crate) fn encode_versioned_ack_body<T: Serialize>(ack: &T) -> Result<Vec<u8>, Error> {
+    SerdeJsonUtils::to_json_vec(&VersionedAckBodyRef {
+        version: ACK_BODY_VERSION,
+        ack,
+    })
+}
+
+/// Decodes a revive topic message body of any version up to the current one, a body of a later
+/// version written by a newer broker is rejected instead of being read wrongly.
+pub(crate) fn decode_versioned_ack_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, Error> {
+    let versioned = SerdeJsonUtils::from_json_slice::<VersionedAckBody<T>>(body)?;
+    if versioned.version > ACK_BODY_VERSION {
+        return Err(Error::UnsupportedOperationException(format!(
+            "ack body version {} is not supported, the latest supported version is {}",
+            versioned.version, ACK_BODY_VERSION
+        )));
+    }
+    Ok(versioned.ack)
+}
+
 /// A trait representing an acknowledgment message that can be converted to and from `Any`.
 pub trait AckMessage {
     fn ack_offset(&self) -> i64;
@@ -43,6 +97,16 @@ pub trait AckMessage {
     fn broker_name(&self) -> &CheetahString;
     fn set_broker_name(&mut self, broker_name: CheetahString);
 
+    /// Tag of the revive topic message carrying this ack.
+    fn ack_tag(&self) -> &'static str;
+
+    /// Encodes this ack into the body of its revive topic message.
+    fn encode_body(&self) -> Result<Vec<u8>, Error>;
+
+    /// Unique id of the revive topic message carrying this ack, also telling a resent ack apart
+    /// from a new one.
+    fn unique_id(&self) -> String;
+
     /// Converts the acknowledgment message to a reference of type `Any`.
     ///
     /// # Returns
@@ -57,3 +121,124 @@ pub trait AckMessage {
     /// A mutable reference to the acknowledgment message as `Any`.
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
 }
+
+/// Decodes the ack carried by a revive topic message tagged `tag`, the counterpart of
+/// [`AckMessage::encode_body`]. Returns `None` if the message does not carry an ack, e.g. a
+/// checkpoint, or its body can not be decoded.
+pub fn decode_ack_body(tag: &str, body: &[u8]) -> Option<Box<dyn AckMessage + Send + Sync>> {
+    match tag {
+        PopAckConstants::ACK_TAG => AckMsg::decode_body(body)
+            .ok()
+            .map(|ack_msg| Box::new(ack_msg) as Box<dyn AckMessage + Send + Sync>),
+        PopAckConstants::BATCH_ACK_TAG => BatchAckMsg::decode_body(body)
+            .ok()
+            .map(|batch_ack_msg| Box::new(batch_ack_msg) as Box<dyn AckMessage + Send + Sync>),
+        _ => None,
+    }
+}
+
+/// Stamps the revive topic message `inner` with the CRC of its body, checked by
+/// [`is_revive_body_intact`] once it is read back. The body must be set and the properties
+/// string built afterwards.
+pub fn set_revive_body_crc(inner: &mut MessageExtBrokerInner) {
+    let crc = crc32_utils::crc32(inner.get_body().map_or(&[][..], |body| body.as_ref()));
+    inner.put_property(
+        CheetahString::from_static_str(PopAckConstants::REVIVE_BODY_CRC),
+        CheetahString::from_string(crc.to_string()),
+    );
+}
+
+/// Flags the born and store hosts of the revive topic message `inner` that are IPv6 ones, as
+/// the encoders size each host by its flag and would otherwise cut an IPv6 address short.
+pub fn set_revive_host_flags(inner: &mut MessageExtBrokerInner) {
+    if inner.message_ext_inner.born_host.is_ipv6() {
+        inner.with_born_host_v6_flag();
+    }
+    if inner.message_ext_inner.store_host.is_ipv6() {
+        inner.with_store_host_v6_flag();
+    }
+}
+
+/// Returns whether the body of the revive topic message `msg_ext` still matches the CRC it was
+/// written with. A message written before the CRC was introduced carries none and is trusted.
+pub fn is_revive_body_intact(msg_ext: &MessageExt) -> bool {
+    let Some(crc) = msg_ext.get_property(&CheetahString::from_static_str(
+        PopAckConstants::REVIVE_BODY_CRC,
+    )) else {
+        return true;
+    };
+    let body = msg_ext.get_body().map_or(&[][..], |body| body.as_ref());
+    crc.parse::<u32>()
+        .is_ok_and(|crc| crc == crc32_utils::crc32(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+
+    fn test_ack_msg() -> AckMsg {
+        AckMsg {
+            ack_offset: 101,
+            start_offset: 100,
+            consumer_group: CheetahString::from_static_str("test_group"),
+            topic: CheetahString::from_static_str("test_topic"),
+            queue_id: 1,
+            pop_time: 789,
+            broker_name: CheetahString::from_static_str("test_broker"),
+        }
+    }
+
+    #[test]
+    fn decode_ack_body_reverses_encode_body() {
+        let ack_msg = test_ack_msg();
+        let decoded = decode_ack_body(ack_msg.ack_tag(), &ack_msg.encode_body().unwrap()).unwrap();
+        assert_eq!(decoded.ack_offset(), 101);
+        assert_eq!(decoded.topic().as_str(), "test_topic");
+
+        let batch_ack_msg = BatchAckMsg {
+            ack_msg: test_ack_msg(),
+            ack_offset_list: vec![101, 103],
+        };
+        let decoded = decode_ack_body(
+            batch_ack_msg.ack_tag(),
+            &batch_ack_msg.encode_body().unwrap(),
+        )
+        .unwrap();
+        let decoded = decoded.as_any().downcast_ref::<BatchAckMsg>().unwrap();
+        assert_eq!(decoded.ack_offset_list, vec![101, 103]);
+        assert_eq!(decoded.ack_msg.pop_time, 789);
+    }
+
+    #[test]
+    fn decode_ack_body_skips_other_messages() {
+        let ack_msg = test_ack_msg();
+        assert!(
+            decode_ack_body(PopAckConstants::CK_TAG, &ack_msg.encode_body().unwrap()).is_none()
+        );
+        assert!(decode_ack_body(PopAckConstants::ACK_TAG, b"not an ack").is_none());
+    }
+
+    #[test]
+    fn revive_body_crc_detects_a_flipped_byte() {
+        let body = test_ack_msg().encode_body().unwrap();
+        let mut inner = MessageExtBrokerInner::default();
+        inner.set_body(Bytes::from(body.clone()));
+        set_revive_body_crc(&mut inner);
+        let mut msg_ext = inner.message_ext_inner;
+        assert!(is_revive_body_intact(&msg_ext));
+
+        let mut corrupt_body = body;
+        corrupt_body[3] ^= 0x01;
+        msg_ext.set_body(Bytes::from(corrupt_body));
+        assert!(!is_revive_body_intact(&msg_ext));
+    }
+
+    #[test]
+    fn revive_body_without_crc_is_trusted() {
+        let mut msg_ext = MessageExt::default();
+        msg_ext.set_body(Bytes::from_static(b"written before crc"));
+        assert!(is_revive_body_intact(&msg_ext));
+    }
+}
@@ -17,9 +17,13 @@
 use std::fmt::Display;
 
 use cheetah_string::CheetahString;
+use rocketmq_common::common::pop_ack_constants::PopAckConstants;
+use rocketmq_common::error::Error;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::pop::decode_versioned_ack_body;
+use crate::pop::encode_versioned_ack_body;
 use crate::pop::AckMessage;
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -103,6 +107,33 @@ impl AckMessage for AckMsg {
         self.broker_name = broker_name;
     }
 
+    fn ack_tag(&self) -> &'static str {
+        PopAckConstants::ACK_TAG
+    }
+
+    fn encode_body(&self) -> Result<Vec<u8>, Error> {
+        encode_versioned_ack_body(self)
+    }
+
+    fn unique_id(&self) -> String {
+        format!(
+            "{}{}{}{}{}{}{}{}{}{}{}{}{}",
+            self.topic,
+            PopAckConstants::SPLIT,
+            self.queue_id,
+            PopAckConstants::SPLIT,
+            self.ack_offset,
+            PopAckConstants::SPLIT,
+            self.consumer_group,
+            PopAckConstants::SPLIT,
+            self.pop_time,
+            PopAckConstants::SPLIT,
+            self.broker_name,
+            PopAckConstants::SPLIT,
+            PopAckConstants::ACK_TAG
+        )
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -112,6 +143,13 @@ impl AckMessage for AckMsg {
     }
 }
 
+impl AckMsg {
+    /// Decodes the body of a revive topic message carrying an ack, with or without a version.
+    pub fn decode_body(body: &[u8]) -> Result<Self, Error> {
+        decode_versioned_ack_body(body)
+    }
+}
+
 impl Display for AckMsg {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -185,4 +223,61 @@ mod tests {
             CheetahString::from_static_str("test_broker")
         );
     }
+
+    #[test]
+    fn ack_msg_unique_id_formats_correctly() {
+        let ack_msg = AckMsg {
+            ack_offset: 123,
+            start_offset: 456,
+            consumer_group: CheetahString::from_static_str("test_group"),
+            topic: CheetahString::from_static_str("test_topic"),
+            queue_id: 1,
+            pop_time: 789,
+            broker_name: CheetahString::from_static_str("test_broker"),
+        };
+        let result = ack_msg.unique_id();
+        let expected = "test_topic@1@123@test_group@789@test_broker@ack";
+        assert_eq!(result, expected);
+    }
+
+    fn test_ack_msg() -> AckMsg {
+        AckMsg {
+            ack_offset: 123,
+            start_offset: 456,
+            consumer_group: CheetahString::from_static_str("test_group"),
+            topic: CheetahString::from_static_str("test_topic"),
+            queue_id: 1,
+            pop_time: 789,
+            broker_name: CheetahString::from_static_str("test_broker"),
+        }
+    }
+
+    #[test]
+    fn ack_msg_body_round_trips_with_version() {
+        let ack_msg = test_ack_msg();
+        let body = ack_msg.encode_body().unwrap();
+        let expected = r#"{"v":1,"ao":123,"so":456,"c":"test_group","t":"test_topic","q":1,"pt":789,"bn":"test_broker"}"#;
+        assert_eq!(String::from_utf8(body.clone()).unwrap(), expected);
+        let decoded = AckMsg::decode_body(&body).unwrap();
+        assert_eq!(decoded.to_string(), ack_msg.to_string());
+    }
+
+    #[test]
+    fn legacy_ack_msg_body_without_version_is_decoded() {
+        let body = r#"{"ao":123,"so":456,"c":"test_group","t":"test_topic","q":1,"pt":789,"bn":"test_broker"}"#;
+        let decoded = AckMsg::decode_body(body.as_bytes()).unwrap();
+        assert_eq!(decoded.to_string(), test_ack_msg().to_string());
+    }
+
+    #[test]
+    fn ack_msg_body_of_later_version_is_rejected() {
+        let body = r#"{"v":2,"ao":123,"so":456,"c":"test_group","t":"test_topic","q":1,"pt":789,"bn":"test_broker"}"#;
+        let Err(error) = AckMsg::decode_body(body.as_bytes()) else {
+            panic!("an ack body of a later version must not be decoded");
+        };
+        assert_eq!(
+            error.to_string(),
+            "ack body version 2 is not supported, the latest supported version is 1"
+        );
+    }
 }
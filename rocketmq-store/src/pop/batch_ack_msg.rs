@@ -17,10 +17,14 @@
 use std::fmt::Display;
 
 use cheetah_string::CheetahString;
+use rocketmq_common::common::pop_ack_constants::PopAckConstants;
+use rocketmq_common::error::Error;
 use serde::Deserialize;
 use serde::Serialize;
 
 use crate::pop::ack_msg::AckMsg;
+use crate::pop::decode_versioned_ack_body;
+use crate::pop::encode_versioned_ack_body;
 use crate::pop::AckMessage;
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -88,6 +92,40 @@ impl AckMessage for BatchAckMsg {
     fn set_broker_name(&mut self, broker_name: CheetahString) {
         self.ack_msg.broker_name = broker_name;
     }
+    fn ack_tag(&self) -> &'static str {
+        PopAckConstants::BATCH_ACK_TAG
+    }
+
+    fn encode_body(&self) -> Result<Vec<u8>, Error> {
+        encode_versioned_ack_body(self)
+    }
+
+    /// Built from everything telling a batch ack apart from another one: its queue, checkpoint
+    /// start offset, pop time, broker and the offsets it acks, the latter as their number and a
+    /// 64 bit hash to keep the id short.
+    fn unique_id(&self) -> String {
+        let ack_msg = &self.ack_msg;
+        format!(
+            "{}{}{}{}{}{}{}{}{}{}{}{}{}-{:016x}{}{}",
+            ack_msg.topic,
+            PopAckConstants::SPLIT,
+            ack_msg.queue_id,
+            PopAckConstants::SPLIT,
+            ack_msg.start_offset,
+            PopAckConstants::SPLIT,
+            ack_msg.consumer_group,
+            PopAckConstants::SPLIT,
+            ack_msg.pop_time,
+            PopAckConstants::SPLIT,
+            ack_msg.broker_name,
+            PopAckConstants::SPLIT,
+            self.ack_offset_list.len(),
+            hash_ack_offsets(&self.ack_offset_list),
+            PopAckConstants::SPLIT,
+            PopAckConstants::BATCH_ACK_TAG
+        )
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -97,6 +135,14 @@ impl AckMessage for BatchAckMsg {
     }
 }
 
+impl BatchAckMsg {
+    /// Decodes the body of a revive topic message carrying a batch ack, with or without a
+    /// version.
+    pub fn decode_body(body: &[u8]) -> Result<Self, Error> {
+        decode_versioned_ack_body(body)
+    }
+}
+
 impl Display for BatchAckMsg {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -107,6 +153,19 @@ impl Display for BatchAckMsg {
     }
 }
 
+/// FNV-1a hash of the offsets acked by a batch ack, stable across restarts so that a retried
+/// batch ack keeps its unique id.
+fn hash_ack_offsets(ack_offsets: &[i64]) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    ack_offsets
+        .iter()
+        .flat_map(|offset| offset.to_le_bytes())
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use cheetah_string::CheetahString;
@@ -176,4 +235,91 @@ mod tests {
         );
         assert_eq!(batch_ack_msg.ack_offset_list, vec![1, 2, 3]);
     }
+
+    #[test]
+    fn batch_ack_msg_unique_id_formats_correctly() {
+        let ack_msg = AckMsg {
+            ack_offset: 123,
+            start_offset: 456,
+            consumer_group: CheetahString::from_static_str("test_group"),
+            topic: CheetahString::from_static_str("test_topic"),
+            queue_id: 1,
+            pop_time: 789,
+            broker_name: CheetahString::from_static_str("test_broker"),
+        };
+        let batch_ack_msg = BatchAckMsg {
+            ack_msg,
+            ack_offset_list: vec![1, 2, 3],
+        };
+        let result = batch_ack_msg.unique_id();
+        let expected = "test_topic@1@456@test_group@789@test_broker@3-da2bfb225e0d1f05@bAck";
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn batch_ack_msg_unique_id_is_distinct_for_distinct_batch_acks() {
+        let mut unique_ids = std::collections::HashSet::new();
+        let mut batch_acks = 0;
+        for queue_id in 0..4 {
+            // the same offsets may be acked against checkpoints of different start offsets
+            for start_offset in [90i64, 95, 100] {
+                for pop_time in [1_000i64, 2_000] {
+                    // every non-empty subset of 8 offsets
+                    for bits in 1u32..256 {
+                        let ack_offset_list = (0..8)
+                            .filter(|bit| bits & (1 << bit) != 0)
+                            .map(|bit| 100 + bit as i64)
+                            .collect();
+                        let batch_ack_msg = BatchAckMsg {
+                            ack_msg: AckMsg {
+                                ack_offset: -1,
+                                start_offset,
+                                consumer_group: CheetahString::from_static_str("test_group"),
+                                topic: CheetahString::from_static_str("test_topic"),
+                                queue_id,
+                                pop_time,
+                                broker_name: CheetahString::from_static_str("test_broker"),
+                            },
+                            ack_offset_list,
+                        };
+                        unique_ids.insert(batch_ack_msg.unique_id());
+                        batch_acks += 1;
+                    }
+                }
+            }
+        }
+        assert_eq!(unique_ids.len(), batch_acks);
+    }
+
+    fn test_batch_ack_msg() -> BatchAckMsg {
+        BatchAckMsg {
+            ack_msg: AckMsg {
+                ack_offset: 123,
+                start_offset: 456,
+                consumer_group: CheetahString::from_static_str("test_group"),
+                topic: CheetahString::from_static_str("test_topic"),
+                queue_id: 1,
+                pop_time: 789,
+                broker_name: CheetahString::from_static_str("test_broker"),
+            },
+            ack_offset_list: vec![1, 2, 3],
+        }
+    }
+
+    #[test]
+    fn batch_ack_msg_body_round_trips_with_version() {
+        let batch_ack_msg = test_batch_ack_msg();
+        let body = batch_ack_msg.encode_body().unwrap();
+        let expected = r#"{"v":1,"ao":123,"so":456,"c":"test_group","t":"test_topic","q":1,"pt":789,"bn":"test_broker","aol":[1,2,3]}"#;
+        assert_eq!(String::from_utf8(body.clone()).unwrap(), expected);
+        let decoded = BatchAckMsg::decode_body(&body).unwrap();
+        assert_eq!(decoded.to_string(), batch_ack_msg.to_string());
+    }
+
+    #[test]
+    fn legacy_batch_ack_msg_body_without_version_is_decoded() {
+        let body = r#"{"ao":123,"so":456,"c":"test_group","t":"test_topic","q":1,"pt":789,"bn":"test_broker","aol":[1,2,3]}"#;
+        let decoded = BatchAckMsg::decode_body(body.as_bytes()).unwrap();
+        assert_eq!(decoded.to_string(), test_batch_ack_msg().to_string());
+    }
 }
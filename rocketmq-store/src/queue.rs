@@ -567,6 +567,20 @@ pub trait ConsumeQueueStoreTrait: Send + Sync {
     /// The minimum offset as a 64-bit integer.
     fn get_min_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64;
 
+    /// Trims the consume queue for a given topic and queue ID before `offset`.
+    ///
+    /// The new minimum offset is persisted and applied again when the offset table is recovered,
+    /// as the consume queue file it falls in keeps the entries before it.
+    ///
+    /// # Arguments
+    /// * `topic` - A string slice that holds the name of the topic.
+    /// * `queue_id` - An integer representing the ID of the queue.
+    /// * `offset` - The offset the entries before which are removed.
+    ///
+    /// # Returns
+    /// The minimum offset in the queue after the trim.
+    fn trim_consume_queue(&self, topic: &CheetahString, queue_id: i32, offset: i64) -> i64;
+
     /// Returns the maximum offset in the queue for a given topic and queue ID.
     ///
     /// This method retrieves the largest offset in the specified consume queue. It can be used to
@@ -763,6 +777,15 @@ pub trait ConsumeQueueTrait: Send + Sync + FileQueueLifeCycle {
     ///   offset against.
     fn correct_min_offset(&self, min_commit_log_offset: i64);
 
+    /// Moves the minimum offset of the consume queue up to `offset`.
+    ///
+    /// The entries before `offset` are no longer readable and the consume queue files holding
+    /// only such entries are deleted. The offset is capped at the maximum offset in the queue.
+    ///
+    /// # Returns
+    /// The minimum offset in the queue after the trim.
+    fn trim_before(&self, offset: i64) -> i64;
+
     /// Applies the dispatched request to the consume queue.
     ///
     /// This method is responsible for processing a dispatch request and applying it to the consume
@@ -18,6 +18,7 @@
 use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use cheetah_string::CheetahString;
@@ -25,6 +26,7 @@ use rocketmq_common::common::attribute::cq_type::CQType;
 use rocketmq_common::common::boundary_type::BoundaryType;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use tracing::info;
+use tracing::warn;
 
 use crate::base::dispatch_request::DispatchRequest;
 use crate::base::swappable::Swappable;
@@ -277,6 +279,15 @@ impl ConsumeQueueTrait for BatchConsumeQueue {
         todo!()
     }
 
+    fn trim_before(&self, offset: i64) -> i64 {
+        // the batch consume queue can not be trimmed yet, its minimum offset is left unchanged
+        warn!(
+            "BatchConsumeQueue[topic={}, queue-id={}] does not support trimming before offset {}",
+            self.topic, self.queue_id, offset
+        );
+        self.min_offset_in_queue.load(Ordering::Acquire)
+    }
+
     fn put_message_position_info_wrapper(&mut self, request: &DispatchRequest) {
         todo!()
     }
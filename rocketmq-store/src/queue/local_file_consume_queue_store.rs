@@ -28,7 +28,9 @@ use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use rocketmq_common::utils::queue_type_utils::QueueTypeUtils;
+use rocketmq_common::FileUtils;
 use rocketmq_rust::ArcMut;
+use tracing::error;
 use tracing::info;
 
 use crate::base::dispatch_request::DispatchRequest;
@@ -43,6 +45,7 @@ use crate::queue::ConsumeQueueTable;
 use crate::queue::ConsumeQueueTrait;
 use crate::queue::CqUnit;
 use crate::store::running_flags::RunningFlags;
+use crate::store_path_config_helper::get_consume_queue_trim_path;
 use crate::store_path_config_helper::get_store_path_batch_consume_queue;
 use crate::store_path_config_helper::get_store_path_consume_queue;
 
@@ -60,6 +63,8 @@ struct Inner {
     pub(crate) broker_config: Arc<BrokerConfig>,
     pub(crate) queue_offset_operator: QueueOffsetOperator,
     pub(crate) consume_queue_table: Arc<ConsumeQueueTable>,
+    /// Minimum offsets the queues are trimmed to, by topic and queue id.
+    pub(crate) trim_offset_table: parking_lot::Mutex<HashMap<CheetahString, HashMap<i32, i64>>>,
 }
 
 impl Inner {
@@ -87,6 +92,7 @@ impl ConsumeQueueStore {
                 broker_config,
                 queue_offset_operator: QueueOffsetOperator::new(),
                 consume_queue_table: Arc::new(parking_lot::Mutex::new(HashMap::new())),
+                trim_offset_table: parking_lot::Mutex::new(HashMap::new()),
             }),
             running_flags,
             store_checkpoint,
@@ -102,6 +108,7 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
     }
 
     fn load(&mut self) -> bool {
+        self.load_trim_offset_table();
         self.load_consume_queues(
             CheetahString::from_string(get_store_path_consume_queue(
                 self.inner.message_store_config.store_path_root_dir.as_str(),
@@ -263,7 +270,16 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
                 } else {
                     bcq_offset_table.insert(key, max_offset_in_queue);
                 }
-                self.correct_min_offset(&***consume_queue, min_phy_offset)
+                self.correct_min_offset(&***consume_queue, min_phy_offset);
+                if let Some(offset) = self
+                    .inner
+                    .trim_offset_table
+                    .lock()
+                    .get(topic)
+                    .and_then(|queues| queues.get(queue_id))
+                {
+                    consume_queue.trim_before(*offset);
+                }
             }
         }
         if self.inner.message_store_config.duplication_enable
@@ -286,6 +302,15 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
 
     fn remove_topic_queue_table(&mut self, topic: &CheetahString, queue_id: i32) {
         self.inner.queue_offset_operator.remove(topic, queue_id);
+        let mut trim_offset_table = self.inner.trim_offset_table.lock();
+        if let Some(queues) = trim_offset_table.get_mut(topic) {
+            if queues.remove(&queue_id).is_some() {
+                if queues.is_empty() {
+                    trim_offset_table.remove(topic);
+                }
+                self.persist_trim_offset_table(&trim_offset_table);
+            }
+        }
     }
 
     fn get_topic_queue_table(&self) -> HashMap<CheetahString, i64> {
@@ -386,6 +411,18 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
         queue.get_min_offset_in_queue()
     }
 
+    fn trim_consume_queue(&self, topic: &CheetahString, queue_id: i32, offset: i64) -> i64 {
+        let queue = self.find_or_create_consume_queue(topic, queue_id);
+        let min_offset = queue.trim_before(offset);
+        let mut trim_offset_table = self.inner.trim_offset_table.lock();
+        trim_offset_table
+            .entry(topic.clone())
+            .or_default()
+            .insert(queue_id, min_offset);
+        self.persist_trim_offset_table(&trim_offset_table);
+        min_offset
+    }
+
     fn get_max_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
         todo!()
     }
@@ -396,6 +433,46 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
 }
 
 impl ConsumeQueueStore {
+    fn load_trim_offset_table(&self) {
+        let path = get_consume_queue_trim_path(
+            self.inner.message_store_config.store_path_root_dir.as_str(),
+        );
+        let Ok(content) = FileUtils::file_to_string(path.as_str()) else {
+            return;
+        };
+        if content.is_empty() {
+            return;
+        }
+        match serde_json::from_str(content.as_str()) {
+            Ok(trim_offset_table) => *self.inner.trim_offset_table.lock() = trim_offset_table,
+            Err(e) => error!(
+                "load consume queue trim offsets from {} failed: {}",
+                path, e
+            ),
+        }
+    }
+
+    fn persist_trim_offset_table(
+        &self,
+        trim_offset_table: &HashMap<CheetahString, HashMap<i32, i64>>,
+    ) {
+        let path = get_consume_queue_trim_path(
+            self.inner.message_store_config.store_path_root_dir.as_str(),
+        );
+        let result = serde_json::to_string(trim_offset_table)
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                FileUtils::string_to_file(content.as_str(), path.as_str())
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            error!(
+                "persist consume queue trim offsets to {} failed: {}",
+                path, e
+            );
+        }
+    }
+
     pub fn correct_min_offset(
         &self,
         consume_queue: &dyn ConsumeQueueTrait,
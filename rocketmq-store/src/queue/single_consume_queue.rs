@@ -504,10 +504,8 @@ impl ConsumeQueueTrait for ConsumeQueue {
     }
 
     fn get(&self, index: i64) -> Option<CqUnit> {
-        match self.iterate_from(index) {
-            None => None,
-            Some(value) => None,
-        }
+        self.iterate_from(index)
+            .and_then(|mut iterator| iterator.next())
     }
 
     fn get_cq_unit_and_store_time(&self, index: i64) -> Option<(CqUnit, i64)> {
@@ -601,7 +599,10 @@ impl ConsumeQueueTrait for ConsumeQueue {
                 .mapped_file
                 .as_ref()
                 .unwrap()
-                .get_bytes(last_record.start_offset as usize, last_record.size as usize)
+                .get_bytes(
+                    max_readable_position as usize - CQ_STORE_UNIT_SIZE as usize,
+                    last_record.size as usize,
+                )
                 .unwrap();
             let commit_log_offset = bytes.get_i64();
             if commit_log_offset < min_commit_log_offset {
@@ -658,10 +659,7 @@ impl ConsumeQueueTrait for ConsumeQueue {
                 return;
             }
             let mapped = result.mapped_file.as_ref().unwrap();
-            let commit_log_offset = mapped
-                .get_bytes(result.start_offset as usize, 8)
-                .unwrap()
-                .get_i64();
+            let commit_log_offset = mapped.get_bytes(start as usize, 8).unwrap().get_i64();
             if intact && commit_log_offset >= min_commit_log_offset {
                 info!(
                     "Abort correction as previous min-offset points to {}, which is greater than \
@@ -677,7 +675,10 @@ impl ConsumeQueueTrait for ConsumeQueue {
                     break;
                 }
                 let mid = (low + high) / 2 / CQ_STORE_UNIT_SIZE * CQ_STORE_UNIT_SIZE;
-                let commit_log_offset = mapped.get_bytes(mid as usize, 8).unwrap().get_i64();
+                let commit_log_offset = mapped
+                    .get_bytes((start + mid as i64) as usize, 8)
+                    .unwrap()
+                    .get_i64();
 
                 match commit_log_offset.cmp(&min_commit_log_offset) {
                     std::cmp::Ordering::Greater => high = mid,
@@ -691,8 +692,9 @@ impl ConsumeQueueTrait for ConsumeQueue {
             }
             let mut i = low;
             while i <= high {
-                let offset_py = mapped.get_bytes(i as usize, 8).unwrap().get_i64();
-                let tags_code = mapped.get_bytes((i + 12) as usize, 8).unwrap().get_i64();
+                let pos = (start + i as i64) as usize;
+                let offset_py = mapped.get_bytes(pos, 8).unwrap().get_i64();
+                let tags_code = mapped.get_bytes(pos + 12, 8).unwrap().get_i64();
                 if offset_py >= min_commit_log_offset {
                     self.min_logic_offset.store(
                         mapped.get_file_from_offset() as i64 + i as i64 + start,
@@ -715,6 +717,22 @@ impl ConsumeQueueTrait for ConsumeQueue {
         }
     }
 
+    fn trim_before(&self, offset: i64) -> i64 {
+        let logic_offset = offset.min(self.get_max_offset_in_queue()) * CQ_STORE_UNIT_SIZE as i64;
+        if logic_offset > self.min_logic_offset.load(Ordering::Acquire) {
+            self.min_logic_offset.store(logic_offset, Ordering::SeqCst);
+            let deleted_files = self.mapped_file_queue.delete_files_before(logic_offset);
+            info!(
+                "ConsumeQueue[topic={}, queue-id={}] is trimmed, min-offset: {}, deleted files: {}",
+                self.topic,
+                self.queue_id,
+                self.get_min_offset_in_queue(),
+                deleted_files
+            );
+        }
+        self.get_min_offset_in_queue()
+    }
+
     fn put_message_position_info_wrapper(&mut self, request: &DispatchRequest) {
         let max_retries = 30i32;
         let can_write = self.running_flags.is_cq_writeable();
@@ -844,17 +862,18 @@ impl Iterator for ConsumeQueueIterator {
                 if self.counter * CQ_STORE_UNIT_SIZE >= value.size {
                     return None;
                 }
-                let mmp = value.mapped_file.as_ref().unwrap().get_mapped_file();
-                let start =
-                    value.start_offset as usize + (self.counter * CQ_STORE_UNIT_SIZE) as usize;
+                let mapped_file = value.mapped_file.as_ref().unwrap();
+                let mmp = mapped_file.get_mapped_file();
+                let offset = value.start_offset + (self.counter * CQ_STORE_UNIT_SIZE) as u64;
                 self.counter += 1;
+                let start = (offset - mapped_file.get_file_from_offset()) as usize;
                 let end = start + CQ_STORE_UNIT_SIZE as usize;
                 let mut bytes = Bytes::copy_from_slice(&mmp[start..end]);
                 let pos = bytes.get_i64();
                 let size = bytes.get_i32();
                 let tags_code = bytes.get_i64();
                 let mut cq_unit = CqUnit {
-                    queue_offset: start as i64 / CQ_STORE_UNIT_SIZE as i64,
+                    queue_offset: offset as i64 / CQ_STORE_UNIT_SIZE as i64,
                     size,
                     pos,
                     tags_code,
@@ -28,6 +28,7 @@ use rocketmq_common::common::statistics::statistics_item_state_getter::Statistic
 use rocketmq_common::common::statistics::statistics_kind_meta::StatisticsKindMeta;
 use rocketmq_common::common::statistics::statistics_manager::StatisticsManager;
 use rocketmq_common::common::stats::moment_stats_item_set::MomentStatsItemSet;
+use rocketmq_common::common::stats::stats_item::StatsItem;
 use rocketmq_common::common::stats::stats_item_set::StatsItemSet;
 use rocketmq_common::common::stats::Stats;
 
@@ -56,7 +57,10 @@ impl BrokerStatsManager {
     pub const ACCOUNT_SEND_REJ: &'static str = "SEND_REJ";
     pub const ACCOUNT_STAT_INVERTAL: u64 = 60 * 1000;
     pub const BROKER_ACK_NUMS: &'static str = "BROKER_ACK_NUMS";
+    pub const BROKER_ACK_UNIQUE_ID_COLLISION_NUMS: &'static str =
+        "BROKER_ACK_UNIQUE_ID_COLLISION_NUMS";
     pub const BROKER_CK_NUMS: &'static str = "BROKER_CK_NUMS";
+    pub const BROKER_REVIVE_CORRUPT_NUMS: &'static str = "BROKER_REVIVE_CORRUPT_NUMS";
     pub const BROKER_GET_NUMS_WITHOUT_SYSTEM_TOPIC: &'static str =
         "BROKER_GET_NUMS_WITHOUT_SYSTEM_TOPIC";
     pub const BROKER_PUT_NUMS_WITHOUT_SYSTEM_TOPIC: &'static str =
@@ -75,6 +79,7 @@ impl BrokerStatsManager {
     pub const FAILURE_MSG_SIZE: &'static str = "FAILURE_MSG_SIZE";
     pub const FAILURE_REQ_NUM: &'static str = "FAILURE_REQ_NUM";
     pub const GROUP_ACK_NUMS: &'static str = "GROUP_ACK_NUMS";
+    pub const GROUP_ACK_OUT_OF_RANGE_NUMS: &'static str = "GROUP_ACK_OUT_OF_RANGE_NUMS";
     pub const GROUP_CK_NUMS: &'static str = "GROUP_CK_NUMS";
     #[deprecated]
     pub const GROUP_GET_FALL_SIZE: &'static str = "GROUP_GET_FALL_SIZE";
@@ -194,6 +199,10 @@ impl BrokerStatsManager {
             Self::GROUP_ACK_NUMS.to_string(),
             StatsItemSet::new(Self::GROUP_ACK_NUMS.to_string()),
         );
+        self.stats_table.write().insert(
+            Self::GROUP_ACK_OUT_OF_RANGE_NUMS.to_string(),
+            StatsItemSet::new(Self::GROUP_ACK_OUT_OF_RANGE_NUMS.to_string()),
+        );
         self.stats_table.write().insert(
             Self::GROUP_CK_NUMS.to_string(),
             StatsItemSet::new(Self::GROUP_CK_NUMS.to_string()),
@@ -226,10 +235,18 @@ impl BrokerStatsManager {
             Self::BROKER_ACK_NUMS.to_string(),
             StatsItemSet::new(Self::BROKER_ACK_NUMS.to_string()),
         );
+        self.stats_table.write().insert(
+            Self::BROKER_ACK_UNIQUE_ID_COLLISION_NUMS.to_string(),
+            StatsItemSet::new(Self::BROKER_ACK_UNIQUE_ID_COLLISION_NUMS.to_string()),
+        );
         self.stats_table.write().insert(
             Self::BROKER_CK_NUMS.to_string(),
             StatsItemSet::new(Self::BROKER_CK_NUMS.to_string()),
         );
+        self.stats_table.write().insert(
+            Self::BROKER_REVIVE_CORRUPT_NUMS.to_string(),
+            StatsItemSet::new(Self::BROKER_REVIVE_CORRUPT_NUMS.to_string()),
+        );
         self.stats_table.write().insert(
             Self::BROKER_GET_NUMS_WITHOUT_SYSTEM_TOPIC.to_string(),
             StatsItemSet::new(Self::BROKER_GET_NUMS_WITHOUT_SYSTEM_TOPIC.to_string()),
@@ -486,7 +503,26 @@ impl BrokerStatsManager {
 
     pub fn inc_group_ck_nums(&self, group: &str, topic: &str, inc_value: i32) {}
 
-    pub fn inc_group_ack_nums(&self, group: &str, topic: &str, inc_value: i32) {}
+    pub fn inc_group_ack_nums(&self, group: &str, topic: &str, inc_value: i32) {
+        let stats_key = build_stats_key(Some(topic), Some(group));
+        if let Some(stats) = self.stats_table.read().get(Self::GROUP_ACK_NUMS) {
+            stats.add_value(&stats_key, inc_value.max(0) as u64, 1);
+        }
+    }
+
+    /// Counts acked offsets outside of the offset range of their queue, the usual symptom of a
+    /// consumer whose offset was not reset along with its queue.
+    pub fn inc_group_ack_out_of_range_nums(&self, group: &str, topic: &str, inc_value: i32) {
+        let stats_key = build_stats_key(Some(topic), Some(group));
+        if let Some(stats) = self
+            .stats_table
+            .read()
+            .get(Self::GROUP_ACK_OUT_OF_RANGE_NUMS)
+        {
+            stats.add_value(&stats_key, inc_value.max(0) as u64, 1);
+        }
+    }
+
     pub fn inc_broker_get_nums(&self, group: &str, inc_value: i32) {}
     pub fn inc_broker_put_nums(&self, group: &str, inc_value: i32) {}
 
@@ -504,7 +540,70 @@ impl BrokerStatsManager {
         }
     }
 
-    pub fn inc_broker_ack_nums(&self, inc_value: i32) {}
+    pub fn inc_broker_ack_nums(&self, inc_value: i32) {
+        if let Some(stats) = self.stats_table.read().get(Self::BROKER_ACK_NUMS) {
+            stats.add_value(&self.cluster_name, inc_value.max(0) as u64, 1);
+        }
+    }
+
+    pub fn get_broker_ack_nums(&self) -> u64 {
+        self.get_stats_value(Self::BROKER_ACK_NUMS, &self.cluster_name)
+    }
+
+    /// Counts an ack whose unique id was already claimed by a different ack.
+    pub fn inc_broker_ack_unique_id_collision_nums(&self) {
+        if let Some(stats) = self
+            .stats_table
+            .read()
+            .get(Self::BROKER_ACK_UNIQUE_ID_COLLISION_NUMS)
+        {
+            stats.add_value(&self.cluster_name, 1, 1);
+        }
+    }
+
+    pub fn get_broker_ack_unique_id_collision_nums(&self) -> u64 {
+        self.get_stats_value(
+            Self::BROKER_ACK_UNIQUE_ID_COLLISION_NUMS,
+            &self.cluster_name,
+        )
+    }
+
+    /// Counts a revive topic message skipped as its body does not match its CRC.
+    pub fn inc_broker_revive_corrupt_nums(&self) {
+        if let Some(stats) = self
+            .stats_table
+            .read()
+            .get(Self::BROKER_REVIVE_CORRUPT_NUMS)
+        {
+            stats.add_value(&self.cluster_name, 1, 1);
+        }
+    }
+
+    pub fn get_broker_revive_corrupt_nums(&self) -> u64 {
+        self.get_stats_value(Self::BROKER_REVIVE_CORRUPT_NUMS, &self.cluster_name)
+    }
+
+    pub fn get_group_ack_nums(&self, group: &str, topic: &str) -> u64 {
+        let stats_key = build_stats_key(Some(topic), Some(group));
+        self.get_stats_value(Self::GROUP_ACK_NUMS, &stats_key)
+    }
+
+    pub fn get_group_ack_out_of_range_nums(&self, group: &str, topic: &str) -> u64 {
+        let stats_key = build_stats_key(Some(topic), Some(group));
+        self.get_stats_value(Self::GROUP_ACK_OUT_OF_RANGE_NUMS, &stats_key)
+    }
+
+    pub fn get_stats_item(&self, stats_name: &str, stats_key: &str) -> Option<Arc<StatsItem>> {
+        self.stats_table
+            .read()
+            .get(stats_name)
+            .and_then(|stats| stats.get_stats_item(stats_key))
+    }
+
+    fn get_stats_value(&self, stats_name: &str, stats_key: &str) -> u64 {
+        self.get_stats_item(stats_name, stats_key)
+            .map_or(0, |item| item.get_value())
+    }
 }
 
 pub fn build_stats_key(topic: Option<&str>, group: Option<&str>) -> String {
@@ -646,4 +745,36 @@ mod tests {
         let parts = split_account_stat_key("part1|part2|part3|part4|part5");
         assert_eq!(parts, vec!["part1", "part2", "part3", "part4", "part5"]);
     }
+
+    #[tokio::test]
+    async fn ack_nums_accumulate_per_broker_and_group() {
+        let manager = BrokerStatsManager::new(Arc::new(BrokerConfig::default()));
+        manager.inc_broker_ack_nums(1);
+        manager.inc_broker_ack_nums(3);
+        manager.inc_group_ack_nums("group1", "topic1", 1);
+        manager.inc_group_ack_nums("group1", "topic1", 3);
+        manager.inc_group_ack_nums("group2", "topic1", 2);
+
+        assert_eq!(manager.get_broker_ack_nums(), 4);
+        assert_eq!(manager.get_group_ack_nums("group1", "topic1"), 4);
+        assert_eq!(manager.get_group_ack_nums("group2", "topic1"), 2);
+        assert_eq!(manager.get_group_ack_nums("group1", "topic2"), 0);
+    }
+
+    #[tokio::test]
+    async fn ack_out_of_range_nums_accumulate_per_group() {
+        let manager = BrokerStatsManager::new(Arc::new(BrokerConfig::default()));
+        manager.inc_group_ack_out_of_range_nums("group1", "topic1", 1);
+        manager.inc_group_ack_out_of_range_nums("group1", "topic1", 2);
+
+        assert_eq!(
+            manager.get_group_ack_out_of_range_nums("group1", "topic1"),
+            3
+        );
+        assert_eq!(
+            manager.get_group_ack_out_of_range_nums("group2", "topic1"),
+            0
+        );
+        assert_eq!(manager.get_group_ack_nums("group1", "topic1"), 0);
+    }
 }
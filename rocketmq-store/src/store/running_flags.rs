@@ -25,6 +25,7 @@ const WRITE_INDEX_FILE_ERROR_BIT: i32 = 1 << 3;
 const DISK_FULL_BIT: i32 = 1 << 4;
 const FENCED_BIT: i32 = 1 << 5;
 const LOGIC_DISK_FULL_BIT: i32 = 1 << 6;
+const RECOVERING_BIT: i32 = 1 << 7;
 
 /// `RunningFlags` is a structure to manage various states using bit flags.
 /// The state is represented by an `AtomicI32` to ensure thread safety.
@@ -196,6 +197,20 @@ impl RunningFlags {
             .fetch_and(!LOGIC_DISK_FULL_BIT, Ordering::SeqCst);
         result
     }
+
+    /// Sets or clears the recovering flag based on the input parameter.
+    pub fn make_recovering(&self, recovering: bool) {
+        if recovering {
+            self.flag_bits.fetch_or(RECOVERING_BIT, Ordering::SeqCst);
+        } else {
+            self.flag_bits.fetch_and(!RECOVERING_BIT, Ordering::SeqCst);
+        }
+    }
+
+    /// Returns true while the store is replaying its logs and its offsets are incomplete.
+    pub fn is_recovering(&self) -> bool {
+        (self.flag_bits.load(Ordering::SeqCst) & RECOVERING_BIT) != 0
+    }
 }
 
 #[cfg(test)]
@@ -358,4 +373,15 @@ mod tests {
         let running_flags = RunningFlags::new();
         assert_eq!(running_flags.get_and_make_logic_disk_ok(), true);
     }
+
+    #[test]
+    fn test_make_recovering() {
+        let running_flags = RunningFlags::new();
+        assert!(!running_flags.is_recovering());
+        running_flags.make_recovering(true);
+        assert!(running_flags.is_recovering());
+        assert!(running_flags.is_writeable());
+        running_flags.make_recovering(false);
+        assert!(!running_flags.is_recovering());
+    }
 }
@@ -74,6 +74,30 @@ pub fn get_delay_offset_store_path(root_dir: &str) -> String {
         .into_owned()
 }
 
+pub fn get_consume_queue_trim_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("config")
+        .join("consumeQueueTrim.json")
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn get_timer_log_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("timerlog")
+        .join("timerlog")
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn get_timer_check_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("config")
+        .join("timercheck.json")
+        .to_string_lossy()
+        .into_owned()
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -0,0 +1,46 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use rocketmq_common::FileUtils;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Progress of the timer message store, persisted along with the timer log it matches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerCheckpoint {
+    /// The timers due before it are delivered.
+    pub last_read_time_ms: i64,
+    /// The messages of the timer topic before it are in the timer log, or delivered.
+    pub last_timer_queue_offset: i64,
+    pub last_timer_log_flush_pos: u64,
+}
+
+impl TimerCheckpoint {
+    pub fn load(path: &str) -> Option<Self> {
+        let content = FileUtils::file_to_string(path).ok()?;
+        if content.is_empty() {
+            return None;
+        }
+        serde_json::from_str(content.as_str()).ok()
+    }
+
+    pub fn persist(&self, path: &str) -> std::io::Result<()> {
+        let content = serde_json::to_string(self)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        FileUtils::string_to_file(content.as_str(), path)
+    }
+}
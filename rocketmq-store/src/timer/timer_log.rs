@@ -0,0 +1,161 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+
+use bytes::Buf;
+use bytes::BufMut;
+
+/// size(4) + magic(4) + delayed time(8) + commit log offset(8) + message size(4) +
+/// queue offset(8)
+pub const UNIT_SIZE: usize = 36;
+
+const UNIT_MAGIC: i32 = 0x54494d52;
+
+/// A timer of the timer log, the message it delays stays in the commit log.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimerLogUnit {
+    pub delayed_time: i64,
+    pub offset_py: i64,
+    pub size_py: i32,
+    /// Offset of the message in the queue of the timer topic.
+    pub queue_offset: i64,
+}
+
+impl TimerLogUnit {
+    fn encode(&self) -> [u8; UNIT_SIZE] {
+        let mut unit = [0u8; UNIT_SIZE];
+        let mut buf = &mut unit[..];
+        buf.put_i32(UNIT_SIZE as i32);
+        buf.put_i32(UNIT_MAGIC);
+        buf.put_i64(self.delayed_time);
+        buf.put_i64(self.offset_py);
+        buf.put_i32(self.size_py);
+        buf.put_i64(self.queue_offset);
+        unit
+    }
+
+    fn decode(mut buf: &[u8]) -> Option<Self> {
+        if buf.get_i32() != UNIT_SIZE as i32 || buf.get_i32() != UNIT_MAGIC {
+            return None;
+        }
+        Some(TimerLogUnit {
+            delayed_time: buf.get_i64(),
+            offset_py: buf.get_i64(),
+            size_py: buf.get_i32(),
+            queue_offset: buf.get_i64(),
+        })
+    }
+}
+
+/// Append only log of the timers put into the timer wheel, read back to rebuild the wheel
+/// when the store restarts.
+pub struct TimerLog {
+    file: File,
+    write_pos: u64,
+}
+
+impl TimerLog {
+    /// Opens the log, dropping what was written after `flush_pos` since the timers of it are
+    /// enqueued again from the timer topic.
+    pub fn open(path: impl AsRef<Path>, flush_pos: u64) -> io::Result<Self> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let len = file.metadata()?.len();
+        let write_pos = flush_pos.min(len) / UNIT_SIZE as u64 * UNIT_SIZE as u64;
+        file.set_len(write_pos)?;
+        Ok(TimerLog { file, write_pos })
+    }
+
+    pub fn append(&mut self, unit: &TimerLogUnit) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(self.write_pos))?;
+        self.file.write_all(&unit.encode())?;
+        self.write_pos += UNIT_SIZE as u64;
+        Ok(())
+    }
+
+    /// Flushes the log to disk and returns the position flushed up to.
+    pub fn flush(&mut self) -> io::Result<u64> {
+        self.file.sync_data()?;
+        Ok(self.write_pos)
+    }
+
+    /// Empties the log, only once none of its timers is pending.
+    pub fn reset(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.write_pos = 0;
+        Ok(())
+    }
+
+    pub fn write_pos(&self) -> u64 {
+        self.write_pos
+    }
+
+    /// Reads every timer of the log, it stops at the first unit that can't be decoded.
+    pub fn units(&mut self) -> io::Result<Vec<TimerLogUnit>> {
+        let mut data = Vec::with_capacity(self.write_pos as usize);
+        self.file.seek(SeekFrom::Start(0))?;
+        (&mut self.file)
+            .take(self.write_pos)
+            .read_to_end(&mut data)?;
+        Ok(data
+            .chunks_exact(UNIT_SIZE)
+            .map_while(TimerLogUnit::decode)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reopen_drops_units_after_flush_pos() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("timerlog");
+        let unit = |i: i64| TimerLogUnit {
+            delayed_time: 1000 * i,
+            offset_py: 100 * i,
+            size_py: 100,
+            queue_offset: i,
+        };
+        let mut timer_log = TimerLog::open(&path, 0).unwrap();
+        timer_log.append(&unit(1)).unwrap();
+        timer_log.append(&unit(2)).unwrap();
+        let flush_pos = timer_log.flush().unwrap();
+        timer_log.append(&unit(3)).unwrap();
+        drop(timer_log);
+
+        let mut timer_log = TimerLog::open(&path, flush_pos).unwrap();
+        assert_eq!(timer_log.units().unwrap(), vec![unit(1), unit(2)]);
+        timer_log.append(&unit(4)).unwrap();
+        assert_eq!(timer_log.units().unwrap(), vec![unit(1), unit(2), unit(4)]);
+    }
+}
@@ -14,15 +14,39 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
 
 use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
 use rocketmq_common::common::system_clock::SystemClock;
+use rocketmq_common::common::TopicFilterType;
+use rocketmq_common::MessageAccessor::MessageAccessor;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_rust::ArcMut;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
 
+use crate::base::message_status_enum::PutMessageStatus;
+use crate::config::message_store_config::MessageStoreConfig;
 use crate::log_file::MessageStore;
 use crate::message_store::default_message_store::DefaultMessageStore;
+use crate::store_path_config_helper::get_timer_check_path;
+use crate::store_path_config_helper::get_timer_log_path;
+use crate::timer::timer_checkpoint::TimerCheckpoint;
+use crate::timer::timer_log::TimerLog;
+use crate::timer::timer_log::TimerLogUnit;
+use crate::timer::timer_wheel::TimerWheel;
 
 pub const TIMER_TOPIC: &str = concat!("rmq_sys_", "wheel_timer");
 pub const TIMER_OUT_MS: &str = MessageConst::PROPERTY_TIMER_OUT_MS;
@@ -45,34 +69,315 @@ pub const MAGIC_DEFAULT: i32 = 1;
 pub const MAGIC_ROLL: i32 = 1 << 1;
 pub const MAGIC_DELETE: i32 = 1 << 2;
 
+/// The longest the timer topic goes unread, which bounds how late a timer enqueued close to
+/// its delivery time can be.
+const TIMER_POLL_INTERVAL_MS: i64 = 100;
+
+/// Delivers the messages of the timer topic to their real topic once their `TIMER_OUT_MS` is
+/// reached.
+///
+/// Messages are enqueued from the timer topic into the timer log and the timer wheel, then read
+/// from the wheel slot by slot. The timer log and a checkpoint of the progress are flushed
+/// every `timer_flush_interval_ms` and replayed into the wheel when the store is loaded again.
+#[derive(Clone)]
 pub struct TimerMessageStore {
-    pub curr_read_time_ms: AtomicI64,
-    pub curr_queue_offset: AtomicI64,
+    pub curr_read_time_ms: Arc<AtomicI64>,
+    pub curr_queue_offset: Arc<AtomicI64>,
     pub default_message_store: Option<ArcMut<DefaultMessageStore>>,
+    message_store_config: Arc<MessageStoreConfig>,
+    timer_state: Arc<Mutex<Option<TimerState>>>,
+    shutdown: Arc<AtomicBool>,
 }
 
-impl Clone for TimerMessageStore {
-    fn clone(&self) -> Self {
-        Self {
-            curr_read_time_ms: AtomicI64::new(
-                self.curr_read_time_ms
-                    .load(std::sync::atomic::Ordering::Relaxed),
-            ),
-            curr_queue_offset: AtomicI64::new(
-                self.curr_queue_offset
-                    .load(std::sync::atomic::Ordering::Relaxed),
-            ),
-            default_message_store: self.default_message_store.clone(),
-        }
-    }
+struct TimerState {
+    wheel: TimerWheel,
+    timer_log: TimerLog,
+    last_flush_ms: u64,
 }
 
 impl TimerMessageStore {
+    /// Rebuilds the timer wheel from the timer log and the checkpoint left by the last run.
     pub fn load(&mut self) -> bool {
+        let root_dir = self.message_store_config.store_path_root_dir.as_str();
+        let mut wheel = TimerWheel::new(
+            self.message_store_config.timer_precision_ms,
+            self.message_store_config.timer_roll_window_slot,
+        );
+        let checkpoint = TimerCheckpoint::load(get_timer_check_path(root_dir).as_str())
+            .unwrap_or_else(|| TimerCheckpoint {
+                last_read_time_ms: wheel.slot_time(get_current_millis() as i64),
+                ..TimerCheckpoint::default()
+            });
+        let mut timer_log = match TimerLog::open(
+            get_timer_log_path(root_dir),
+            checkpoint.last_timer_log_flush_pos,
+        ) {
+            Ok(timer_log) => timer_log,
+            Err(err) => {
+                error!("open timer log failed: {}", err);
+                return false;
+            }
+        };
+        let units = match timer_log.units() {
+            Ok(units) => units,
+            Err(err) => {
+                error!("read timer log failed: {}", err);
+                return false;
+            }
+        };
+        for unit in units {
+            if unit.delayed_time >= checkpoint.last_read_time_ms {
+                wheel.put(unit, checkpoint.last_read_time_ms);
+            }
+        }
+        info!(
+            "load timer message store, pending timers: {}, read time: {}, queue offset: {}",
+            wheel.size(),
+            checkpoint.last_read_time_ms,
+            checkpoint.last_timer_queue_offset
+        );
+        self.curr_read_time_ms
+            .store(checkpoint.last_read_time_ms, Ordering::Release);
+        self.curr_queue_offset
+            .store(checkpoint.last_timer_queue_offset, Ordering::Release);
+        *self.timer_state.lock() = Some(TimerState {
+            wheel,
+            timer_log,
+            last_flush_ms: get_current_millis(),
+        });
         true
     }
 
-    pub fn start(&mut self) {}
+    pub fn start(&mut self) {
+        if self.timer_state.lock().is_none() {
+            warn!("timer message store is not loaded, it is not started");
+            return;
+        }
+        let timer_message_store = self.clone();
+        tokio::spawn(async move {
+            info!("timer message store started");
+            while !timer_message_store.shutdown.load(Ordering::Acquire) {
+                let wait = timer_message_store.run_once().await;
+                tokio::time::sleep(wait).await;
+            }
+        });
+    }
+
+    pub fn shutdown(&self) {
+        if !self.shutdown.swap(true, Ordering::AcqRel) {
+            self.flush(true);
+        }
+    }
+
+    /// Enqueues the new messages of the timer topic and delivers the due ones, returns how long
+    /// to wait before running again.
+    async fn run_once(&self) -> Duration {
+        let mut due = Vec::new();
+        if !self.message_store_config.timer_stop_enqueue {
+            due.extend(self.enqueue());
+        }
+        if !self.message_store_config.timer_stop_dequeue {
+            due.extend(self.dequeue());
+        }
+        for unit in due {
+            if !self.deliver(&unit).await {
+                // put it back to try again on the next run
+                let mut timer_state = self.timer_state.lock();
+                if let Some(timer_state) = timer_state.as_mut() {
+                    let curr_read_time_ms = self.curr_read_time_ms.load(Ordering::Acquire);
+                    timer_state.wheel.put(unit, curr_read_time_ms);
+                }
+            }
+        }
+        self.flush(false);
+        self.next_wait()
+    }
+
+    /// Moves the messages of the timer topic into the timer log and the wheel, returns the ones
+    /// already due.
+    fn enqueue(&self) -> Vec<TimerLogUnit> {
+        let mut due = Vec::new();
+        let Some(message_store) = self.default_message_store.as_ref() else {
+            return due;
+        };
+        let Some(consume_queue) =
+            message_store.find_consume_queue(&CheetahString::from_static_str(TIMER_TOPIC), 0)
+        else {
+            return due;
+        };
+        let mut timer_state = self.timer_state.lock();
+        let Some(timer_state) = timer_state.as_mut() else {
+            return due;
+        };
+        let curr_read_time_ms = self.curr_read_time_ms.load(Ordering::Acquire);
+        let mut queue_offset = self
+            .curr_queue_offset
+            .load(Ordering::Acquire)
+            .max(consume_queue.get_min_offset_in_queue());
+        let max_offset = consume_queue
+            .get_max_offset_in_queue()
+            .min(queue_offset + DEFAULT_CAPACITY as i64);
+        while queue_offset < max_offset {
+            let Some(cq_unit) = consume_queue.get(queue_offset) else {
+                break;
+            };
+            let delayed_time = message_store
+                .look_message_by_offset_with_size(cq_unit.pos, cq_unit.size)
+                .and_then(|msg| {
+                    msg.get_user_property(&CheetahString::from_static_str(TIMER_OUT_MS))
+                })
+                .and_then(|timer_out_ms| timer_out_ms.parse::<i64>().ok());
+            let Some(delayed_time) = delayed_time else {
+                warn!(
+                    "skip timer message without a valid {}, queue offset: {}",
+                    TIMER_OUT_MS, queue_offset
+                );
+                queue_offset += 1;
+                continue;
+            };
+            let unit = TimerLogUnit {
+                delayed_time,
+                offset_py: cq_unit.pos,
+                size_py: cq_unit.size,
+                queue_offset,
+            };
+            if delayed_time < curr_read_time_ms {
+                due.push(unit);
+            } else {
+                if let Err(err) = timer_state.timer_log.append(&unit) {
+                    error!("append timer log failed: {}", err);
+                    break;
+                }
+                timer_state.wheel.put(unit, curr_read_time_ms);
+            }
+            queue_offset += 1;
+        }
+        self.curr_queue_offset
+            .store(queue_offset, Ordering::Release);
+        due
+    }
+
+    /// Reads the wheel up to now, returns the timers due.
+    fn dequeue(&self) -> Vec<TimerLogUnit> {
+        let mut timer_state = self.timer_state.lock();
+        let Some(timer_state) = timer_state.as_mut() else {
+            return Vec::new();
+        };
+        let now = get_current_millis() as i64;
+        let precision_ms = timer_state.wheel.precision_ms();
+        let mut curr_read_time_ms = self.curr_read_time_ms.load(Ordering::Acquire);
+        let mut due = Vec::new();
+        loop {
+            due.extend(timer_state.wheel.take_due(curr_read_time_ms, now));
+            if curr_read_time_ms + precision_ms > now {
+                break;
+            }
+            curr_read_time_ms += precision_ms;
+        }
+        self.curr_read_time_ms
+            .store(curr_read_time_ms, Ordering::Release);
+        due
+    }
+
+    /// Puts the message of a due timer back to its real topic, returns `false` if it should be
+    /// tried again.
+    async fn deliver(&self, unit: &TimerLogUnit) -> bool {
+        let Some(mut message_store) = self.default_message_store.clone() else {
+            return true;
+        };
+        let Some(msg_ext) =
+            message_store.look_message_by_offset_with_size(unit.offset_py, unit.size_py)
+        else {
+            warn!(
+                "timer message is not found in commit log, offset: {}, size: {}",
+                unit.offset_py, unit.size_py
+            );
+            return true;
+        };
+        if msg_ext
+            .get_user_property(&CheetahString::from_static_str(TIMER_DELETE_UNIQUE_KEY))
+            .is_some()
+        {
+            // deleting timers is not supported, the marker itself is not delivered
+            return true;
+        }
+        let result = message_store.put_message(convert_message(&msg_ext)).await;
+        match result.put_message_status() {
+            PutMessageStatus::PutOk
+            | PutMessageStatus::FlushDiskTimeout
+            | PutMessageStatus::FlushSlaveTimeout
+            | PutMessageStatus::SlaveNotAvailable => true,
+            PutMessageStatus::MessageIllegal | PutMessageStatus::PropertiesSizeExceeded => {
+                warn!(
+                    "drop timer message of topic {}, put status: {}",
+                    msg_ext.topic(),
+                    result.put_message_status()
+                );
+                true
+            }
+            status => {
+                error!(
+                    "deliver timer message of topic {} failed, put status: {}",
+                    msg_ext.topic(),
+                    status
+                );
+                self.message_store_config.timer_skip_unknown_error
+            }
+        }
+    }
+
+    /// Flushes the timer log and persists the checkpoint, at most once per
+    /// `timer_flush_interval_ms` unless `force`.
+    fn flush(&self, force: bool) {
+        let mut timer_state = self.timer_state.lock();
+        let Some(timer_state) = timer_state.as_mut() else {
+            return;
+        };
+        let now = get_current_millis();
+        if !force
+            && now - timer_state.last_flush_ms
+                < self.message_store_config.timer_flush_interval_ms as u64
+        {
+            return;
+        }
+        timer_state.last_flush_ms = now;
+        // nothing is pending, the log starts over
+        if timer_state.wheel.size() == 0 && timer_state.timer_log.write_pos() > 0 {
+            if let Err(err) = timer_state.timer_log.reset() {
+                error!("reset timer log failed: {}", err);
+            }
+        }
+        let last_timer_log_flush_pos = match timer_state.timer_log.flush() {
+            Ok(flush_pos) => flush_pos,
+            Err(err) => {
+                error!("flush timer log failed: {}", err);
+                return;
+            }
+        };
+        let checkpoint = TimerCheckpoint {
+            last_read_time_ms: self.curr_read_time_ms.load(Ordering::Acquire),
+            last_timer_queue_offset: self.curr_queue_offset.load(Ordering::Acquire),
+            last_timer_log_flush_pos,
+        };
+        let path = get_timer_check_path(self.message_store_config.store_path_root_dir.as_str());
+        if let Err(err) = checkpoint.persist(path.as_str()) {
+            error!("persist timer checkpoint failed: {}", err);
+        }
+    }
+
+    fn next_wait(&self) -> Duration {
+        let timer_state = self.timer_state.lock();
+        let Some(timer_state) = timer_state.as_ref() else {
+            return Duration::from_millis(TIMER_POLL_INTERVAL_MS as u64);
+        };
+        let curr_read_time_ms = self.curr_read_time_ms.load(Ordering::Acquire);
+        let next_time = timer_state
+            .wheel
+            .next_due(curr_read_time_ms)
+            .unwrap_or(curr_read_time_ms + timer_state.wheel.precision_ms());
+        let wait = (next_time - get_current_millis() as i64).clamp(1, TIMER_POLL_INTERVAL_MS);
+        Duration::from_millis(wait as u64)
+    }
 
     pub fn is_reject(&self, _deliver_ms: u64) -> bool {
         false
@@ -83,16 +388,11 @@ impl TimerMessageStore {
     }
 
     pub fn get_dequeue_behind_millis(&self) -> i64 {
-        (SystemClock::now() as i64)
-            - self
-                .curr_read_time_ms
-                .load(std::sync::atomic::Ordering::Relaxed)
+        (SystemClock::now() as i64) - self.curr_read_time_ms.load(Ordering::Relaxed)
     }
 
     pub fn get_enqueue_behind_messages(&self) -> i64 {
-        let temp_queue_offset = self
-            .curr_queue_offset
-            .load(std::sync::atomic::Ordering::Relaxed);
+        let temp_queue_offset = self.curr_queue_offset.load(Ordering::Relaxed);
         let consume_queue = self
             .default_message_store
             .as_ref()
@@ -106,7 +406,10 @@ impl TimerMessageStore {
     }
 
     pub fn get_all_congest_num(&self) -> i64 {
-        0
+        self.timer_state
+            .lock()
+            .as_ref()
+            .map_or(0, |timer_state| timer_state.wheel.size() as i64)
     }
 
     pub fn get_enqueue_tps(&self) -> f32 {
@@ -118,19 +421,22 @@ impl TimerMessageStore {
     }
 
     pub fn new(default_message_store: Option<ArcMut<DefaultMessageStore>>) -> Self {
+        let message_store_config = default_message_store
+            .as_ref()
+            .map(|message_store| message_store.message_store_config())
+            .unwrap_or_default();
         Self {
-            curr_read_time_ms: AtomicI64::new(0),
-            curr_queue_offset: AtomicI64::new(0),
+            curr_read_time_ms: Arc::new(AtomicI64::new(0)),
+            curr_queue_offset: Arc::new(AtomicI64::new(0)),
             default_message_store,
+            message_store_config,
+            timer_state: Arc::new(Mutex::new(None)),
+            shutdown: Arc::new(AtomicBool::new(false)),
         }
     }
 
     pub fn new_empty() -> Self {
-        Self {
-            curr_read_time_ms: AtomicI64::new(0),
-            curr_queue_offset: AtomicI64::new(0),
-            default_message_store: None,
-        }
+        Self::new(None)
     }
 
     pub fn set_default_message_store(
@@ -139,4 +445,204 @@ impl TimerMessageStore {
     ) {
         self.default_message_store = default_message_store;
     }
+
+    pub fn message_store_config(&self) -> &Arc<MessageStoreConfig> {
+        &self.message_store_config
+    }
+}
+
+/// Restores the real topic and queue of a timer message.
+fn convert_message(msg_ext: &MessageExt) -> MessageExtBrokerInner {
+    let mut msg_inner = MessageExtBrokerInner::default();
+    msg_inner.set_topic(
+        msg_ext
+            .get_user_property(&CheetahString::from_static_str(
+                MessageConst::PROPERTY_REAL_TOPIC,
+            ))
+            .unwrap_or_default(),
+    );
+    msg_inner.message_ext_inner.queue_id = msg_ext
+        .get_user_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_REAL_QUEUE_ID,
+        ))
+        .and_then(|queue_id| queue_id.parse().ok())
+        .unwrap_or_default();
+    if let Some(body) = msg_ext.get_body() {
+        msg_inner.set_body(body.clone());
+    }
+    msg_inner.set_flag(msg_ext.get_flag());
+    msg_inner.message_ext_inner.born_timestamp = msg_ext.born_timestamp;
+    msg_inner.message_ext_inner.born_host = msg_ext.born_host;
+    msg_inner.message_ext_inner.store_host = msg_ext.store_host;
+    msg_inner.message_ext_inner.reconsume_times = msg_ext.reconsume_times;
+    msg_inner.message_ext_inner.sys_flag = msg_ext.sys_flag;
+    msg_inner.set_wait_store_msg_ok(false);
+    let topic_filter_type =
+        if msg_ext.sys_flag & MessageSysFlag::MULTI_TAGS_FLAG == MessageSysFlag::MULTI_TAGS_FLAG {
+            TopicFilterType::MultiTag
+        } else {
+            TopicFilterType::SingleTag
+        };
+    msg_inner.tags_code = msg_ext.get_tags().map_or(0, |tags| {
+        MessageExtBrokerInner::tags_string2tags_code(&topic_filter_type, tags.as_str())
+    });
+    MessageAccessor::set_properties(&mut msg_inner, msg_ext.get_properties().clone());
+    MessageAccessor::clear_property(&mut msg_inner, MessageConst::PROPERTY_REAL_TOPIC);
+    MessageAccessor::clear_property(&mut msg_inner, MessageConst::PROPERTY_REAL_QUEUE_ID);
+    MessageAccessor::put_property(
+        &mut msg_inner,
+        CheetahString::from_static_str(TIMER_DEQUEUE_MS),
+        CheetahString::from_string(get_current_millis().to_string()),
+    );
+    msg_inner.properties_string =
+        message_decoder::message_properties_to_string(msg_inner.get_properties());
+    msg_inner
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use bytes::Bytes;
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+
+    use super::*;
+
+    const REAL_TOPIC: &str = "TimerTopic";
+
+    async fn start_message_store(root_dir: &Path) -> ArcMut<DefaultMessageStore> {
+        let message_store_config = Arc::new(MessageStoreConfig {
+            store_path_root_dir: CheetahString::from_string(root_dir.to_string_lossy().into()),
+            mapped_file_size_commit_log: 4 * 1024 * 1024,
+            timer_wheel_enable: true,
+            timer_precision_ms: 100,
+            timer_roll_window_slot: 64,
+            timer_flush_interval_ms: 100,
+            ..MessageStoreConfig::default()
+        });
+        let mut message_store = ArcMut::new(DefaultMessageStore::new(
+            message_store_config,
+            Arc::new(BrokerConfig::default()),
+            Arc::new(parking_lot::Mutex::new(HashMap::new())),
+            None,
+            false,
+        ));
+        let message_store_clone = message_store.clone();
+        message_store.set_message_store_arc(Some(message_store_clone));
+        assert!(message_store.load().await);
+        message_store.start().unwrap();
+        message_store
+    }
+
+    /// Puts a message the way the broker routes it to the timer topic.
+    async fn put_timer_message(message_store: &mut ArcMut<DefaultMessageStore>, deliver_ms: i64) {
+        let mut msg = MessageExtBrokerInner::default();
+        msg.set_topic(CheetahString::from_static_str(TIMER_TOPIC));
+        msg.set_body(Bytes::from_static(b"timer"));
+        msg.message_ext_inner.born_timestamp = get_current_millis() as i64;
+        msg.message_ext_inner.born_host = "127.0.0.1:10000".parse().unwrap();
+        msg.message_ext_inner.store_host = "127.0.0.1:10911".parse().unwrap();
+        for (key, value) in [
+            (TIMER_OUT_MS, deliver_ms.to_string()),
+            (MessageConst::PROPERTY_REAL_TOPIC, REAL_TOPIC.to_string()),
+            (MessageConst::PROPERTY_REAL_QUEUE_ID, "0".to_string()),
+        ] {
+            MessageAccessor::put_property(
+                &mut msg,
+                CheetahString::from_static_str(key),
+                CheetahString::from_string(value),
+            );
+        }
+        msg.properties_string = message_decoder::message_properties_to_string(msg.get_properties());
+        let result = message_store.put_message(msg).await;
+        assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+    }
+
+    async fn wait_until(mut condition: impl FnMut() -> bool) {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !condition() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("condition is not met in time");
+    }
+
+    /// Store time of the delivered message.
+    async fn wait_delivered(message_store: &ArcMut<DefaultMessageStore>) -> i64 {
+        let real_topic = CheetahString::from_static_str(REAL_TOPIC);
+        wait_until(|| message_store.get_max_offset_in_queue(&real_topic, 0) > 0).await;
+        let delivered = message_store.look_message_by_offset(
+            message_store
+                .find_consume_queue(&real_topic, 0)
+                .unwrap()
+                .get(0)
+                .unwrap()
+                .pos,
+        );
+        let delivered = delivered.unwrap();
+        assert_eq!(delivered.get_body().unwrap().as_ref(), b"timer");
+        assert!(delivered
+            .get_user_property(&CheetahString::from_static_str(
+                MessageConst::PROPERTY_REAL_TOPIC
+            ))
+            .is_none());
+        delivered.store_timestamp
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn timer_message_is_delivered_at_its_time() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let mut message_store = start_message_store(root_dir.path()).await;
+        let mut timer_message_store = TimerMessageStore::new(Some(message_store.clone()));
+        assert!(timer_message_store.load());
+        timer_message_store.start();
+
+        // farther than the 6.4s the wheel spans
+        let deliver_ms = get_current_millis() as i64 + 7_050;
+        put_timer_message(&mut message_store, deliver_ms).await;
+        wait_until(|| timer_message_store.get_all_congest_num() == 1).await;
+
+        let delivered_ms = wait_delivered(&message_store).await;
+        assert!(delivered_ms >= deliver_ms, "delivered too early");
+        assert!(delivered_ms < deliver_ms + 300, "delivered too late");
+        assert_eq!(timer_message_store.get_all_congest_num(), 0);
+
+        timer_message_store.shutdown();
+        message_store.shutdown();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn pending_timers_are_reloaded_after_restart() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let mut message_store = start_message_store(root_dir.path()).await;
+        let mut timer_message_store = TimerMessageStore::new(Some(message_store.clone()));
+        assert!(timer_message_store.load());
+        timer_message_store.start();
+
+        let deliver_ms = get_current_millis() as i64 + 1_500;
+        put_timer_message(&mut message_store, deliver_ms).await;
+        wait_until(|| timer_message_store.get_all_congest_num() == 1).await;
+        timer_message_store.shutdown();
+        message_store.shutdown();
+        drop(timer_message_store);
+        drop(message_store);
+
+        let message_store = start_message_store(root_dir.path()).await;
+        let mut timer_message_store = TimerMessageStore::new(Some(message_store.clone()));
+        assert!(timer_message_store.load());
+        assert_eq!(timer_message_store.get_all_congest_num(), 1);
+        assert_eq!(
+            timer_message_store
+                .curr_queue_offset
+                .load(Ordering::Acquire),
+            1
+        );
+        timer_message_store.start();
+
+        let delivered_ms = wait_delivered(&message_store).await;
+        assert!(delivered_ms >= deliver_ms, "delivered too early");
+        timer_message_store.shutdown();
+    }
 }
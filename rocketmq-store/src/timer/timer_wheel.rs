@@ -0,0 +1,147 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::timer::timer_log::TimerLogUnit;
+
+/// Ring of slots holding the pending timers, each slot spans `precision_ms` and the ring
+/// spans `precision_ms * slot_num`.
+///
+/// Timers farther than the ring spans are parked in its farthest slot and put again once that
+/// slot is read.
+pub struct TimerWheel {
+    precision_ms: i64,
+    slots: Vec<Vec<TimerLogUnit>>,
+    size: usize,
+}
+
+impl TimerWheel {
+    pub fn new(precision_ms: u64, slot_num: usize) -> Self {
+        TimerWheel {
+            precision_ms: precision_ms.max(1) as i64,
+            slots: vec![Vec::new(); slot_num.max(1)],
+            size: 0,
+        }
+    }
+
+    pub fn precision_ms(&self) -> i64 {
+        self.precision_ms
+    }
+
+    /// The start time of the slot `time_ms` falls in.
+    pub fn slot_time(&self, time_ms: i64) -> i64 {
+        time_ms - time_ms.rem_euclid(self.precision_ms)
+    }
+
+    /// Number of timers in the wheel.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    fn slot_index(&self, slot_time: i64) -> usize {
+        (slot_time / self.precision_ms).rem_euclid(self.slots.len() as i64) as usize
+    }
+
+    /// Puts a timer into the wheel read from `curr_read_time_ms` on, timers already due go to
+    /// the slot read next.
+    pub fn put(&mut self, unit: TimerLogUnit, curr_read_time_ms: i64) {
+        let window_ms = self.precision_ms * self.slots.len() as i64;
+        let farthest = curr_read_time_ms + window_ms - self.precision_ms;
+        let slot_time = self.slot_time(unit.delayed_time.clamp(curr_read_time_ms, farthest));
+        let index = self.slot_index(slot_time);
+        self.slots[index].push(unit);
+        self.size += 1;
+    }
+
+    /// Takes the timers of the slot of `slot_time` that are due by `now_ms`, the timers parked
+    /// in the slot for a later round are put again.
+    pub fn take_due(&mut self, slot_time: i64, now_ms: i64) -> Vec<TimerLogUnit> {
+        let index = self.slot_index(slot_time);
+        let next_slot_time = slot_time + self.precision_ms;
+        let units = std::mem::take(&mut self.slots[index]);
+        self.size -= units.len();
+        let mut due = Vec::new();
+        for unit in units {
+            if unit.delayed_time <= now_ms {
+                due.push(unit);
+            } else if unit.delayed_time < next_slot_time {
+                self.slots[index].push(unit);
+                self.size += 1;
+            } else {
+                self.put(unit, next_slot_time);
+            }
+        }
+        due
+    }
+
+    /// The earliest time a timer of the slot of `slot_time` is due.
+    pub fn next_due(&self, slot_time: i64) -> Option<i64> {
+        let next_slot_time = slot_time + self.precision_ms;
+        self.slots[self.slot_index(slot_time)]
+            .iter()
+            .map(|unit| unit.delayed_time)
+            .filter(|delayed_time| *delayed_time < next_slot_time)
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(delayed_time: i64) -> TimerLogUnit {
+        TimerLogUnit {
+            delayed_time,
+            offset_py: delayed_time,
+            size_py: 1,
+            queue_offset: 0,
+        }
+    }
+
+    #[test]
+    fn timers_are_taken_once_due() {
+        let mut wheel = TimerWheel::new(100, 10);
+        wheel.put(unit(1_250), 1_000);
+        wheel.put(unit(1_220), 1_000);
+        wheel.put(unit(1_300), 1_000);
+
+        assert!(wheel.take_due(1_200, 1_210).is_empty());
+        assert_eq!(wheel.next_due(1_200), Some(1_220));
+        assert_eq!(wheel.take_due(1_200, 1_230), vec![unit(1_220)]);
+        assert_eq!(wheel.take_due(1_200, 1_299), vec![unit(1_250)]);
+        assert_eq!(wheel.take_due(1_300, 1_300), vec![unit(1_300)]);
+        assert_eq!(wheel.size(), 0);
+    }
+
+    #[test]
+    fn timers_beyond_the_wheel_roll_until_due() {
+        let mut wheel = TimerWheel::new(100, 10);
+        // the wheel spans 1_000ms so it takes three rounds
+        wheel.put(unit(3_450), 1_000);
+
+        let mut read_time = 1_000;
+        let mut delivered = Vec::new();
+        while read_time <= 3_500 {
+            delivered.extend(
+                wheel
+                    .take_due(read_time, read_time + 99)
+                    .into_iter()
+                    .map(|unit| (read_time, unit.delayed_time)),
+            );
+            read_time += 100;
+        }
+        assert_eq!(delivered, vec![(3_400, 3_450)]);
+    }
+}